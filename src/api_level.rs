@@ -0,0 +1,139 @@
+//! Vulkan API version requirements
+//!
+//! Newer Vulkan versions promote many extensions to core functionality, e.g.
+//! timeline semaphores in Vulkan 1.2. An ApiLevel states which Vulkan version
+//! your application needs at a minimum, and which one it would like to use.
+//! Devices which do not reach the minimum are rejected during device
+//! selection, and the version that can actually be used on the selected
+//! device is recorded so that you can gate your use of newer functionality.
+//!
+//! Using newer functionality than the instance's API version is not allowed
+//! by the Vulkan specification. EasyInstance thus creates its instance with
+//! the newest API version supported by the Vulkan loader, up to the desired
+//! version of the EasyInstanceBuilder's ApiLevel (see the loader module), and
+//! usable versions are clamped to the instance's API version.
+
+use ::{
+    diagnosis::RejectionReason,
+    Error,
+    Result,
+};
+
+use std::cmp;
+
+use vulkano::instance::{
+    PhysicalDevice,
+    Version,
+};
+
+
+/// Vulkan v1.0
+pub const VULKAN_1_0: Version = Version { major: 1, minor: 0, patch: 0 };
+
+/// Vulkan v1.1
+pub const VULKAN_1_1: Version = Version { major: 1, minor: 1, patch: 0 };
+
+/// Vulkan v1.2
+pub const VULKAN_1_2: Version = Version { major: 1, minor: 2, patch: 0 };
+
+/// Vulkan v1.3
+pub const VULKAN_1_3: Version = Version { major: 1, minor: 3, patch: 0 };
+
+
+/// Minimal and desired Vulkan API version of an application
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApiLevel {
+    /// Devices which do not support this version are rejected
+    minimum: Version,
+
+    /// Newer versions will not be used, even if the device supports them
+    desired: Version,
+}
+
+impl ApiLevel {
+    /// Specify the minimal and desired Vulkan API version
+    ///
+    /// Patch versions are ignored. Only Vulkan 1.x is supported.
+    ///
+    pub fn new(minimum: Version, desired: Version) -> Result<Self> {
+        let minimum = minor_version(minimum);
+        let desired = minor_version(desired);
+        if minimum.major != 1 || desired.major != 1 {
+            return Err(Error::InvalidArgument(
+                format!("Only Vulkan 1.x is supported, got minimum {} and \
+                         desired {}", minimum, desired)
+            ));
+        }
+        if minimum > desired {
+            return Err(Error::InvalidArgument(
+                format!("Minimum Vulkan version {} is above desired version \
+                         {}", minimum, desired)
+            ));
+        }
+        Ok(ApiLevel {
+            minimum,
+            desired,
+        })
+    }
+
+    /// Minimal Vulkan API version
+    pub fn minimum(&self) -> Version {
+        self.minimum
+    }
+
+    /// Desired Vulkan API version
+    pub fn desired(&self) -> Version {
+        self.desired
+    }
+
+    /// Vulkan API version that can be used on a device, if it is suitable
+    ///
+    /// This is the highest version which the device, the instance and the
+    /// application all support. None is returned if that is below the
+    /// minimal version.
+    ///
+    pub fn usable_version(&self,
+                          device: PhysicalDevice,
+                          instance_version: Version) -> Option<Version> {
+        let usable = cmp::min(minor_version(device.api_version()),
+                              cmp::min(self.desired,
+                                       minor_version(instance_version)));
+        if usable >= self.minimum { Some(usable) } else { None }
+    }
+
+    /// Tell why a device does not fulfill the requirements, if it doesn't
+    ///
+    /// An instance whose API version is below the minimum is reported as
+    /// such, rather than blamed on the device.
+    ///
+    pub fn rejection(&self,
+                     device: PhysicalDevice,
+                     instance_version: Version) -> Option<RejectionReason> {
+        if minor_version(instance_version) < self.minimum {
+            Some(RejectionReason::InstanceApiVersion(instance_version))
+        } else if minor_version(device.api_version()) < self.minimum {
+            Some(RejectionReason::ApiVersion(device.api_version()))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for ApiLevel {
+    /// Accept Vulkan v1.0 and above, and use the newest version available
+    fn default() -> Self {
+        ApiLevel {
+            minimum: VULKAN_1_0,
+            desired: VULKAN_1_3,
+        }
+    }
+}
+
+
+/// Strip the patch number from a Vulkan version
+fn minor_version(version: Version) -> Version {
+    Version {
+        patch: 0,
+        ..version
+    }
+}
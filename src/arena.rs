@@ -0,0 +1,401 @@
+//! Suballocation of many small buffers from a few large memory blocks
+//!
+//! Every vulkano buffer gets its own device memory allocation, but Vulkan
+//! implementations limit the number of live allocations (see the
+//! max_memory_allocation_count device limit, which is often only 4096).
+//! Prototypes which create thousands of small buffers quickly hit that limit.
+//! EasyBufferArena instead allocates large device-local memory blocks, and
+//! binds many buffers to each of them.
+
+use ::{
+    memory,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    iter,
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    result,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use vulkano::{
+    buffer::{
+        sys::{
+            SparseLevel,
+            UnsafeBuffer,
+        },
+        BufferAccess,
+        BufferInner,
+        BufferUsage,
+        CpuAccessibleBuffer,
+        TypedBufferAccess,
+    },
+    device::{
+        Device,
+        DeviceOwned,
+        Queue,
+    },
+    image::ImageAccess,
+    memory::DeviceMemory,
+    sync::{
+        AccessError,
+        Sharing,
+    },
+};
+
+
+/// Default size of the memory blocks allocated by an EasyBufferArena
+pub const DEFAULT_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+
+/// Allocator of device-local buffers which shares memory allocations
+///
+/// Memory is allocated in blocks of a fixed size (buffers that are larger
+/// than that get a dedicated block), and buffers are placed in the first free
+/// range of a block with a compatible memory type that is large enough.
+///
+/// Each buffer is aligned as required by the Vulkan implementation, and to
+/// the device's buffer_image_granularity limit. The latter is only strictly
+/// needed when buffers and optimally tiled images share a memory block, but
+/// it makes the blocks safe to share with images, and is small on most
+/// devices.
+///
+/// When a buffer is dropped, its memory range becomes available again for
+/// other buffers. Memory blocks are only freed along with the arena and all
+/// of its buffers.
+///
+pub struct EasyBufferArena {
+    /// Device which the buffers are allocated on
+    device: Arc<Device>,
+
+    /// Size of the memory blocks
+    block_size: usize,
+
+    /// Minimal alignment of buffers within a block
+    granularity: usize,
+
+    /// Memory blocks allocated so far
+    blocks: Mutex<Vec<Arc<Block>>>,
+}
+
+impl EasyBufferArena {
+    /// Set up an arena with the default block size
+    pub fn new(device: &Arc<Device>) -> Self {
+        Self::with_block_size(device, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Set up an arena, specifying how large its memory blocks should be
+    pub fn with_block_size(device: &Arc<Device>, block_size: usize) -> Self {
+        let limits = device.physical_device().limits();
+        let granularity = limits.buffer_image_granularity().max(1) as usize;
+        debug!("Creating a buffer arena with {}-byte blocks, aligned to {} \
+                bytes, on a device allowing {} allocations",
+               block_size,
+               granularity,
+               limits.max_memory_allocation_count());
+        EasyBufferArena {
+            device: device.clone(),
+            block_size: align_up(block_size.max(1), granularity),
+            granularity,
+            blocks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Allocate an uninitialized device-local buffer of "len" elements
+    pub fn buffer<T>(&self,
+                     len: usize,
+                     usage: BufferUsage) -> Result<Arc<ArenaBuffer<T>>>
+        where T: Send + Sync + 'static
+    {
+        let _trace = trace::span("EasyBufferArena::buffer",
+                                 || format!("elements: {}, usage: {:?}",
+                                            len, usage));
+        let size = len * mem::size_of::<T>();
+        if size == 0 {
+            return Err(Error::InvalidArgument(
+                "Cannot allocate an empty buffer".to_owned()
+            ));
+        }
+
+        // Create the buffer and find out where it can go
+        let (buffer, requirements) = unsafe {
+            UnsafeBuffer::new(self.device.clone(),
+                              size,
+                              usage,
+                              Sharing::Exclusive::<iter::Empty<u32>>,
+                              SparseLevel::none())?
+        };
+        let alignment = requirements.alignment.max(self.granularity);
+        let (block, range) = self.allocate(requirements.size,
+                                           alignment,
+                                           requirements.memory_type_bits)?;
+
+        // Bind it to its memory range
+        unsafe {
+            buffer.bind_memory(&block.memory, range.start)?;
+        }
+        Ok(Arc::new(ArenaBuffer {
+            buffer,
+            block,
+            range,
+            len,
+            gpu_locks: Mutex::new((0, false)),
+            _elements: PhantomData,
+        }))
+    }
+
+    /// Upload data to a new device-local buffer, and wait for the upload
+    ///
+    /// This works like memory::easy_upload_buffer(), except that the buffer
+    /// comes from the arena and only has the usage flags that you specify
+    /// (plus transfer_destination, which the upload needs).
+    ///
+    pub fn upload<T>(&self,
+                     queue: &Arc<Queue>,
+                     data: &[T],
+                     usage: BufferUsage) -> Result<Arc<ArenaBuffer<T>>>
+        where T: Copy + Send + Sync + 'static
+    {
+        let _trace = trace::span("EasyBufferArena::upload",
+                                 || format!("elements: {}", data.len()));
+        let staging_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::transfer_source(),
+            data.iter().cloned()
+        )?;
+        let buffer = self.buffer::<T>(data.len(), BufferUsage {
+            transfer_destination: true,
+            ..usage
+        })?;
+        memory::run_copy(&self.device, queue, staging_buffer, buffer.clone())?;
+        Ok(buffer)
+    }
+
+    /// Number of memory blocks (and thus device allocations) in use
+    pub fn num_blocks(&self) -> usize {
+        self.blocks.lock().unwrap().len()
+    }
+
+    /// Total size of the memory blocks, in bytes
+    pub fn allocated_size(&self) -> usize {
+        self.blocks.lock().unwrap().iter().map(|block| block.size).sum()
+    }
+
+    /// Amount of block memory which is used by buffers, in bytes
+    pub fn used_size(&self) -> usize {
+        self.blocks.lock().unwrap().iter().map(|block| block.used()).sum()
+    }
+
+    /// Find a free memory range, allocating a new block if needed
+    fn allocate(&self,
+                size: usize,
+                alignment: usize,
+                memory_type_bits: u32) -> Result<(Arc<Block>, Range<usize>)> {
+        // Try the existing blocks first
+        let mut blocks = self.blocks.lock().unwrap();
+        for block in blocks.iter() {
+            if memory_type_bits & (1 << block.memory.memory_type().id()) == 0 {
+                continue;
+            }
+            if let Some(range) = block.allocate(size, alignment) {
+                return Ok((block.clone(), range));
+            }
+        }
+
+        // Allocate a new block, preferring device-local memory
+        let physical_device = self.device.physical_device();
+        let memory_type = physical_device.memory_types()
+            .filter(|ty| memory_type_bits & (1 << ty.id()) != 0)
+            .max_by_key(|ty| ty.is_device_local())
+            .ok_or_else(|| Error::InvalidArgument(
+                "No memory type is suitable for this buffer".to_owned()
+            ))?;
+        let block_size = if size > self.block_size {
+            align_up(size, self.granularity)
+        } else {
+            self.block_size
+        };
+        info!("Allocating a {}-byte arena block from memory type {} \
+               ({} block(s) so far)",
+              block_size, memory_type.id(), blocks.len());
+        let block = Arc::new(Block {
+            memory: DeviceMemory::alloc(self.device.clone(),
+                                        memory_type,
+                                        block_size)?,
+            size: block_size,
+            free_ranges: Mutex::new(vec![0..block_size]),
+        });
+        let range = block.allocate(size, alignment)
+                         .expect("A fresh block should fit the allocation");
+        blocks.push(block.clone());
+        Ok((block, range))
+    }
+}
+
+
+/// Memory block of an EasyBufferArena
+struct Block {
+    /// Device memory allocation
+    memory: DeviceMemory,
+
+    /// Size of the allocation
+    size: usize,
+
+    /// Free ranges within the allocation, sorted by offset and never adjacent
+    free_ranges: Mutex<Vec<Range<usize>>>,
+}
+
+impl Block {
+    /// Find and reserve a free range of a certain size and alignment
+    fn allocate(&self, size: usize, alignment: usize) -> Option<Range<usize>> {
+        let mut free_ranges = self.free_ranges.lock().unwrap();
+        let (index, start) = free_ranges.iter().enumerate().find_map(
+            |(index, free)| {
+                let start = align_up(free.start, alignment);
+                if start + size <= free.end {
+                    Some((index, start))
+                } else {
+                    None
+                }
+            }
+        )?;
+
+        // Split the free range around the allocation
+        let free = free_ranges.remove(index);
+        let end = start + size;
+        if end < free.end { free_ranges.insert(index, end..free.end); }
+        if free.start < start { free_ranges.insert(index, free.start..start); }
+        Some(start..end)
+    }
+
+    /// Give back a range which was reserved by allocate()
+    fn free(&self, range: Range<usize>) {
+        let mut free_ranges = self.free_ranges.lock().unwrap();
+        let index = free_ranges.iter()
+                               .position(|free| free.start > range.start)
+                               .unwrap_or(free_ranges.len());
+        free_ranges.insert(index, range);
+
+        // Merge the range with its neighbours, if they are adjacent
+        if index + 1 < free_ranges.len()
+           && free_ranges[index].end == free_ranges[index + 1].start {
+            free_ranges[index].end = free_ranges.remove(index + 1).end;
+        }
+        if index > 0 && free_ranges[index - 1].end == free_ranges[index].start {
+            free_ranges[index - 1].end = free_ranges.remove(index).end;
+        }
+    }
+
+    /// Amount of memory which is currently reserved
+    fn used(&self) -> usize {
+        let free_ranges = self.free_ranges.lock().unwrap();
+        self.size - free_ranges.iter().map(|free| free.len()).sum::<usize>()
+    }
+}
+
+
+/// A device-local buffer allocated from an EasyBufferArena
+///
+/// It can be used wherever vulkano expects a buffer, and gives its memory
+/// range back to the arena when dropped.
+///
+pub struct ArenaBuffer<T> {
+    /// Raw buffer
+    buffer: UnsafeBuffer,
+
+    /// Memory block which the buffer is bound to
+    block: Arc<Block>,
+
+    /// Memory range which the buffer is bound to
+    range: Range<usize>,
+
+    /// Number of elements in the buffer
+    len: usize,
+
+    /// Number of GPU locks, and whether they are exclusive
+    gpu_locks: Mutex<(usize, bool)>,
+
+    /// The buffer contains elements of type T
+    _elements: PhantomData<Box<[T]>>,
+}
+
+impl<T> Drop for ArenaBuffer<T> {
+    fn drop(&mut self) {
+        self.block.free(self.range.clone());
+    }
+}
+
+unsafe impl<T> BufferAccess for ArenaBuffer<T>
+    where T: Send + Sync + 'static
+{
+    fn inner(&self) -> BufferInner {
+        BufferInner {
+            buffer: &self.buffer,
+            offset: 0,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.len * mem::size_of::<T>()
+    }
+
+    fn conflicts_buffer(&self, other: &dyn BufferAccess) -> bool {
+        self.conflict_key() == other.conflict_key()
+    }
+
+    fn conflicts_image(&self, _other: &dyn ImageAccess) -> bool {
+        false
+    }
+
+    fn conflict_key(&self) -> (u64, usize) {
+        (self.buffer.key(), 0)
+    }
+
+    fn try_gpu_lock(&self,
+                    exclusive_access: bool,
+                    _queue: &Queue) -> result::Result<(), AccessError> {
+        let mut locks = self.gpu_locks.lock().unwrap();
+        let (count, exclusive) = *locks;
+        if count > 0 && (exclusive || exclusive_access) {
+            return Err(AccessError::AlreadyInUse);
+        }
+        *locks = (count + 1, exclusive_access);
+        Ok(())
+    }
+
+    unsafe fn increase_gpu_lock(&self) {
+        self.gpu_locks.lock().unwrap().0 += 1;
+    }
+
+    unsafe fn unlock(&self) {
+        let mut locks = self.gpu_locks.lock().unwrap();
+        locks.0 -= 1;
+        if locks.0 == 0 { locks.1 = false; }
+    }
+}
+
+unsafe impl<T> TypedBufferAccess for ArenaBuffer<T>
+    where T: Send + Sync + 'static
+{
+    type Content = [T];
+}
+
+unsafe impl<T> DeviceOwned for ArenaBuffer<T> {
+    fn device(&self) -> &Arc<Device> {
+        self.buffer.device()
+    }
+}
+
+
+/// Round an offset up to a multiple of some alignment
+fn align_up(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) / alignment * alignment
+}
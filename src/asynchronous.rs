@@ -0,0 +1,231 @@
+//! std::future::Future wrappers for GPU work
+//!
+//! Vulkano's GPU futures can only be waited for in a blocking fashion, which
+//! does not play well with async runtimes like tokio or async-std. This
+//! module turns fence-signaling GPU futures into std Futures, which can be
+//! awaited from any executor.
+//!
+//! Vulkan fences cannot notify anyone when they are signaled, so pending
+//! futures register themselves with a small reactor thread, which wakes them
+//! up periodically so that they check their fence again. This is not as
+//! efficient as a proper OS-level notification mechanism, but it does not
+//! depend on a specific runtime.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{
+        Arc,
+        Condvar,
+        Mutex,
+        Once,
+    },
+    task::{
+        Context,
+        Poll,
+        Waker,
+    },
+    thread,
+    time::Duration,
+};
+
+use vulkano::{
+    buffer::{
+        BufferUsage,
+        CpuAccessibleBuffer,
+        TypedBufferAccess,
+    },
+    command_buffer::{
+        AutoCommandBufferBuilder,
+        CommandBuffer,
+    },
+    device::Queue,
+    sync::{
+        FenceSignalFuture,
+        FlushError,
+        GpuFuture,
+    },
+};
+
+
+/// Delay between two fence checks of a pending future
+const POLL_PERIOD: Duration = Duration::from_millis(1);
+
+/// Shared state of the reactor thread
+struct Reactor {
+    /// Wakers of the futures which are waiting for their fence
+    wakers: Mutex<Vec<Waker>>,
+
+    /// Signaled when a waker is added to the wakers
+    wakers_added: Condvar,
+}
+
+/// Access the reactor, starting its thread on first use
+fn reactor() -> &'static Reactor {
+    static START: Once = Once::new();
+    static mut REACTOR: *const Reactor = 0 as *const Reactor;
+    START.call_once(|| {
+        let reactor: &'static Reactor = Box::leak(Box::new(Reactor {
+            wakers: Mutex::new(Vec::new()),
+            wakers_added: Condvar::new(),
+        }));
+        thread::Builder::new()
+            .name("vulkanoob-fence-reactor".to_owned())
+            .spawn(move || run_reactor(reactor))
+            .expect("Failed to start the fence reactor thread");
+        // Safe because this is only done once, before any read
+        unsafe { REACTOR = reactor; }
+    });
+    // Safe because call_once() has initialized REACTOR, and synchronizes
+    // with its initialization
+    unsafe { &*REACTOR }
+}
+
+/// Ask the reactor to wake up a task after the next polling period
+fn register_waker(waker: &Waker) {
+    let reactor = reactor();
+    reactor.wakers.lock().unwrap().push(waker.clone());
+    reactor.wakers_added.notify_one();
+}
+
+/// Reactor thread, which periodically wakes up the pending futures
+fn run_reactor(reactor: &Reactor) {
+    loop {
+        // Sleep until some futures are pending
+        {
+            let mut wakers = reactor.wakers.lock().unwrap();
+            while wakers.is_empty() {
+                wakers = reactor.wakers_added.wait(wakers).unwrap();
+            }
+        }
+
+        // Give the GPU some time, then have the futures check their fence
+        thread::sleep(POLL_PERIOD);
+        let wakers = mem::replace(&mut *reactor.wakers.lock().unwrap(),
+                                  Vec::new());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+
+/// A std Future which resolves once a GPU future's fence is signaled
+pub struct FenceFuture<F: GpuFuture> {
+    /// Fence-signaling GPU future
+    fence: FenceSignalFuture<F>,
+}
+
+impl<F: GpuFuture> FenceFuture<F> {
+    /// Wrap a fence-signaling GPU future
+    pub fn new(fence: FenceSignalFuture<F>) -> Self {
+        FenceFuture { fence }
+    }
+
+    /// Submit some GPU work, signaling a fence at the end, and wrap it
+    pub fn flush(future: F) -> Result<Self> {
+        Ok(Self::new(future.then_signal_fence_and_flush()?))
+    }
+
+    /// Get back the underlying GPU future
+    pub fn into_inner(self) -> FenceSignalFuture<F> {
+        self.fence
+    }
+}
+
+// FenceFuture never hands out pinned references to its contents
+impl<F: GpuFuture> Unpin for FenceFuture<F> {}
+
+impl<F: GpuFuture> Future for FenceFuture<F> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        match self.fence.wait(Some(Duration::from_secs(0))) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(FlushError::Timeout) => {
+                register_waker(cx.waker());
+                Poll::Pending
+            },
+            Err(e) => Poll::Ready(Err(e.into())),
+        }
+    }
+}
+
+
+/// A std Future which resolves to the contents of a buffer, once downloaded
+pub struct DownloadFuture<T: 'static> {
+    /// Completion of the copy to the staging buffer
+    copy: FenceFuture<Box<dyn GpuFuture + Send + Sync>>,
+
+    /// Staging buffer which the data is copied to
+    staging_buffer: Arc<CpuAccessibleBuffer<[T]>>,
+}
+
+impl<T: 'static> Unpin for DownloadFuture<T> {}
+
+impl<T> Future for DownloadFuture<T>
+    where T: Copy + Send + Sync + 'static
+{
+    type Output = Result<Vec<T>>;
+
+    fn poll(mut self: Pin<&mut Self>,
+            cx: &mut Context) -> Poll<Result<Vec<T>>> {
+        match Pin::new(&mut self.copy).poll(cx) {
+            Poll::Ready(Ok(())) => {
+                Poll::Ready(self.staging_buffer
+                                .read()
+                                .map(|contents| contents.to_vec())
+                                .map_err(Error::from))
+            },
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+
+/// Start downloading the contents of a buffer, and get a std Future to it
+///
+/// This is the asynchronous version of memory::easy_download_buffer(): the
+/// copy is submitted right away, but you can await its completion.
+///
+pub fn download_buffer_async<T, B>(
+    queue: &Arc<Queue>,
+    buffer: &Arc<B>
+) -> Result<DownloadFuture<T>>
+    where T: Copy + Send + Sync + 'static,
+          B: TypedBufferAccess<Content=[T]> + Send + Sync + 'static
+{
+    let _trace = trace::span("asynchronous::download_buffer_async",
+                             || format!("elements: {}", buffer.len()));
+
+    // Allocate a host-visible staging buffer
+    let device = queue.device();
+    let staging_buffer = unsafe {
+        CpuAccessibleBuffer::<[T]>::uninitialized_array(
+            device.clone(),
+            buffer.len(),
+            BufferUsage::transfer_destination()
+        )?
+    };
+
+    // Submit the copy to the staging buffer
+    let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(
+        device.clone(),
+        queue.family()
+    )?.copy_buffer(buffer.clone(), staging_buffer.clone())?
+      .build()?;
+    let copy: Box<dyn GpuFuture + Send + Sync> =
+        Box::new(command_buffer.execute(queue.clone())?);
+    Ok(DownloadFuture {
+        copy: FenceFuture::flush(copy)?,
+        staging_buffer,
+    })
+}
@@ -0,0 +1,709 @@
+//! Pipeline barriers for command buffers recorded with raw Vulkan calls
+//!
+//! Vulkano's AutoCommandBufferBuilder inserts pipeline barriers and image
+//! layout transitions automatically, but some features (see e.g. the
+//! mesh_shader and image modules) need command buffers which are recorded
+//! using the raw Vulkan API. There, forgetting a barrier results in bugs which
+//! only show up on some GPUs, some of the time.
+//!
+//! BarrierTracker is a lightweight resource state tracker for such command
+//! buffers. For each pass, you declare how the pass uses each buffer and
+//! image, and it computes the barriers and layout transitions which must be
+//! recorded before the pass. It is not a render graph: passes are neither
+//! reordered nor culled, and whole resources are tracked as a unit.
+//!
+//! ```ignore
+//! let mut tracker = BarrierTracker::new();
+//! let barriers = tracker.pass(&[
+//!     ResourceAccess::buffer(&particles, ResourceUsage::StorageRead),
+//!     ResourceAccess::image(&target, ResourceUsage::StorageWrite),
+//! ])?;
+//! unsafe { barriers.record(&device, raw_command_buffer); }
+//! // ...record the pass itself...
+//! ```
+//!
+//! Resources which vulkano also accesses have their own state tracking, which
+//! knows nothing about this one, so you should not mix both on a resource.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    collections::HashMap,
+    ptr,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    buffer::BufferAccess,
+    device::Device,
+    format::FormatTy,
+    image::ImageAccess,
+    VulkanObject,
+};
+
+
+/// Way in which a pass uses a resource
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceUsage {
+    /// Source of a copy or blit (buffers and images)
+    TransferSrc,
+
+    /// Destination of a copy, blit or clear (buffers and images)
+    TransferDst,
+
+    /// Vertex buffer (buffers only)
+    VertexBuffer,
+
+    /// Index buffer (buffers only)
+    IndexBuffer,
+
+    /// Source of indirect draw or dispatch commands (buffers only)
+    IndirectBuffer,
+
+    /// Uniform buffer, read by any shader (buffers only)
+    UniformBuffer,
+
+    /// Texture, sampled by any shader (images only)
+    Sampled,
+
+    /// Storage buffer or image, read by any shader
+    StorageRead,
+
+    /// Storage buffer or image, written (and maybe read) by any shader
+    StorageWrite,
+
+    /// Color attachment of a render pass (images only)
+    ColorAttachment,
+
+    /// Depth and/or stencil attachment of a render pass (images only)
+    DepthStencilAttachment,
+
+    /// Swapchain image which is about to be presented (images only)
+    Present,
+
+    /// Read back by the host after the GPU work is done
+    HostRead,
+}
+
+impl ResourceUsage {
+    /// Pipeline stages where the resource is accessed
+    pub fn stages(self) -> vk::PipelineStageFlags {
+        let shaders = vk::PIPELINE_STAGE_VERTEX_SHADER_BIT
+                      | vk::PIPELINE_STAGE_FRAGMENT_SHADER_BIT
+                      | vk::PIPELINE_STAGE_COMPUTE_SHADER_BIT;
+        match self {
+            ResourceUsage::TransferSrc | ResourceUsage::TransferDst =>
+                vk::PIPELINE_STAGE_TRANSFER_BIT,
+            ResourceUsage::VertexBuffer | ResourceUsage::IndexBuffer =>
+                vk::PIPELINE_STAGE_VERTEX_INPUT_BIT,
+            ResourceUsage::IndirectBuffer =>
+                vk::PIPELINE_STAGE_DRAW_INDIRECT_BIT,
+            ResourceUsage::UniformBuffer
+            | ResourceUsage::Sampled
+            | ResourceUsage::StorageRead
+            | ResourceUsage::StorageWrite => shaders,
+            ResourceUsage::ColorAttachment =>
+                vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+            ResourceUsage::DepthStencilAttachment =>
+                vk::PIPELINE_STAGE_EARLY_FRAGMENT_TESTS_BIT
+                | vk::PIPELINE_STAGE_LATE_FRAGMENT_TESTS_BIT,
+            ResourceUsage::Present => vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT,
+            ResourceUsage::HostRead => vk::PIPELINE_STAGE_HOST_BIT,
+        }
+    }
+
+    /// Memory accesses which are performed on the resource
+    pub fn access(self) -> vk::AccessFlags {
+        match self {
+            ResourceUsage::TransferSrc => vk::ACCESS_TRANSFER_READ_BIT,
+            ResourceUsage::TransferDst => vk::ACCESS_TRANSFER_WRITE_BIT,
+            ResourceUsage::VertexBuffer => vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT,
+            ResourceUsage::IndexBuffer => vk::ACCESS_INDEX_READ_BIT,
+            ResourceUsage::IndirectBuffer =>
+                vk::ACCESS_INDIRECT_COMMAND_READ_BIT,
+            ResourceUsage::UniformBuffer => vk::ACCESS_UNIFORM_READ_BIT,
+            ResourceUsage::Sampled | ResourceUsage::StorageRead =>
+                vk::ACCESS_SHADER_READ_BIT,
+            ResourceUsage::StorageWrite =>
+                vk::ACCESS_SHADER_READ_BIT | vk::ACCESS_SHADER_WRITE_BIT,
+            ResourceUsage::ColorAttachment =>
+                vk::ACCESS_COLOR_ATTACHMENT_READ_BIT
+                | vk::ACCESS_COLOR_ATTACHMENT_WRITE_BIT,
+            ResourceUsage::DepthStencilAttachment =>
+                vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_READ_BIT
+                | vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT,
+            ResourceUsage::Present => 0,
+            ResourceUsage::HostRead => vk::ACCESS_HOST_READ_BIT,
+        }
+    }
+
+    /// Truth that the resource is written to
+    pub fn is_write(self) -> bool {
+        match self {
+            ResourceUsage::TransferDst
+            | ResourceUsage::StorageWrite
+            | ResourceUsage::ColorAttachment
+            | ResourceUsage::DepthStencilAttachment => true,
+            _ => false,
+        }
+    }
+
+    /// Layout which an image must be in, or None if images cannot be used
+    /// in this way
+    pub fn image_layout(self) -> Option<vk::ImageLayout> {
+        match self {
+            ResourceUsage::TransferSrc =>
+                Some(vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL),
+            ResourceUsage::TransferDst =>
+                Some(vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL),
+            ResourceUsage::VertexBuffer
+            | ResourceUsage::IndexBuffer
+            | ResourceUsage::IndirectBuffer
+            | ResourceUsage::UniformBuffer => None,
+            ResourceUsage::Sampled =>
+                Some(vk::IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL),
+            ResourceUsage::StorageRead
+            | ResourceUsage::StorageWrite
+            | ResourceUsage::HostRead => Some(vk::IMAGE_LAYOUT_GENERAL),
+            ResourceUsage::ColorAttachment =>
+                Some(vk::IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL),
+            ResourceUsage::DepthStencilAttachment =>
+                Some(vk::IMAGE_LAYOUT_DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+            ResourceUsage::Present => Some(vk::IMAGE_LAYOUT_PRESENT_SRC_KHR),
+        }
+    }
+
+    /// Truth that buffers can be used in this way
+    pub fn applies_to_buffers(self) -> bool {
+        match self {
+            ResourceUsage::Sampled
+            | ResourceUsage::ColorAttachment
+            | ResourceUsage::DepthStencilAttachment
+            | ResourceUsage::Present => false,
+            _ => true,
+        }
+    }
+}
+
+
+/// Resource which is tracked by a BarrierTracker
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Resource {
+    /// A buffer
+    Buffer(vk::Buffer),
+
+    /// An image, with its aspects
+    Image(vk::Image, vk::ImageAspectFlags),
+}
+
+/// Declaration of the way a pass uses a buffer or image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResourceAccess {
+    /// Resource which is accessed
+    resource: Resource,
+
+    /// Way in which it is accessed
+    usage: ResourceUsage,
+}
+
+impl ResourceAccess {
+    /// Declare the use of a buffer
+    ///
+    /// The whole underlying buffer is tracked, even if a slice is passed in.
+    ///
+    pub fn buffer(buffer: &impl BufferAccess, usage: ResourceUsage) -> Self {
+        ResourceAccess {
+            resource: Resource::Buffer(buffer.inner().buffer.internal_object()),
+            usage,
+        }
+    }
+
+    /// Declare the use of an image
+    ///
+    /// All mip levels and array layers of the image are tracked together.
+    ///
+    pub fn image(image: &impl ImageAccess, usage: ResourceUsage) -> Self {
+        let inner = image.inner();
+        let aspects = match inner.image.format().ty() {
+            FormatTy::Depth => vk::IMAGE_ASPECT_DEPTH_BIT,
+            FormatTy::Stencil => vk::IMAGE_ASPECT_STENCIL_BIT,
+            FormatTy::DepthStencil =>
+                vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
+            _ => vk::IMAGE_ASPECT_COLOR_BIT,
+        };
+        ResourceAccess {
+            resource: Resource::Image(inner.image.internal_object(), aspects),
+            usage,
+        }
+    }
+}
+
+
+/// Memory barrier on a whole buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferBarrier {
+    /// Buffer which the barrier applies to
+    pub buffer: vk::Buffer,
+
+    /// Accesses which must be made available
+    pub src_access: vk::AccessFlags,
+
+    /// Accesses which the data must be made visible to
+    pub dst_access: vk::AccessFlags,
+}
+
+/// Memory barrier and layout transition on a whole image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageBarrier {
+    /// Image which the barrier applies to
+    pub image: vk::Image,
+
+    /// Aspects of the image (color, depth, stencil)
+    pub aspects: vk::ImageAspectFlags,
+
+    /// Accesses which must be made available
+    pub src_access: vk::AccessFlags,
+
+    /// Accesses which the data must be made visible to
+    pub dst_access: vk::AccessFlags,
+
+    /// Layout which the image is in
+    pub old_layout: vk::ImageLayout,
+
+    /// Layout which the image must be transitioned to
+    pub new_layout: vk::ImageLayout,
+}
+
+/// Barriers which must be recorded before a pass, as a single
+/// vkCmdPipelineBarrier command
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PassBarriers {
+    /// Pipeline stages which must be done before the pass can start
+    pub src_stages: vk::PipelineStageFlags,
+
+    /// Pipeline stages of the pass which must wait
+    pub dst_stages: vk::PipelineStageFlags,
+
+    /// Buffer memory barriers
+    pub buffer_barriers: Vec<BufferBarrier>,
+
+    /// Image memory barriers and layout transitions
+    pub image_barriers: Vec<ImageBarrier>,
+}
+
+impl PassBarriers {
+    /// Truth that no barrier is needed
+    pub fn is_empty(&self) -> bool {
+        self.dst_stages == 0
+    }
+
+    /// Record the barriers into a raw command buffer
+    ///
+    /// This does nothing if no barrier is needed.
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must belong to the device and be in the recording
+    /// state, outside of a render pass. The resources which the barriers
+    /// refer to must still be alive.
+    ///
+    pub unsafe fn record(&self,
+                         device: &Device,
+                         command_buffer: vk::CommandBuffer) {
+        if self.is_empty() { return; }
+        let buffer_barriers = self.buffer_barriers.iter().map(|barrier| {
+            vk::BufferMemoryBarrier {
+                sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
+                pNext: ptr::null(),
+                srcAccessMask: barrier.src_access,
+                dstAccessMask: barrier.dst_access,
+                srcQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                dstQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                buffer: barrier.buffer,
+                offset: 0,
+                size: vk::WHOLE_SIZE,
+            }
+        }).collect::<Vec<_>>();
+        let image_barriers = self.image_barriers.iter().map(|barrier| {
+            vk::ImageMemoryBarrier {
+                sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+                pNext: ptr::null(),
+                srcAccessMask: barrier.src_access,
+                dstAccessMask: barrier.dst_access,
+                oldLayout: barrier.old_layout,
+                newLayout: barrier.new_layout,
+                srcQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                dstQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                image: barrier.image,
+                subresourceRange: vk::ImageSubresourceRange {
+                    aspectMask: barrier.aspects,
+                    baseMipLevel: 0,
+                    levelCount: vk::REMAINING_MIP_LEVELS,
+                    baseArrayLayer: 0,
+                    layerCount: vk::REMAINING_ARRAY_LAYERS,
+                },
+            }
+        }).collect::<Vec<_>>();
+        device.pointers().CmdPipelineBarrier(command_buffer,
+                                             self.src_stages,
+                                             self.dst_stages,
+                                             0,
+                                             0,
+                                             ptr::null(),
+                                             buffer_barriers.len() as u32,
+                                             buffer_barriers.as_ptr(),
+                                             image_barriers.len() as u32,
+                                             image_barriers.as_ptr());
+    }
+}
+
+
+/// Known state of a tracked resource
+#[derive(Clone, Copy, Debug, Default)]
+struct ResourceState {
+    /// Current image layout (always undefined for buffers)
+    layout: vk::ImageLayout,
+
+    /// Pipeline stages of the last write, if any
+    write_stages: vk::PipelineStageFlags,
+
+    /// Memory accesses of the last write
+    write_access: vk::AccessFlags,
+
+    /// Pipeline stages which read the resource since the last write
+    read_stages: vk::PipelineStageFlags,
+
+    /// Stages and accesses which the last write was made visible to
+    visible_stages: vk::PipelineStageFlags,
+    visible_access: vk::AccessFlags,
+}
+
+
+/// Tracker of buffer and image states across the passes of command buffers
+/// which are recorded using raw Vulkan calls
+#[derive(Clone, Debug, Default)]
+pub struct BarrierTracker {
+    /// Known state of the resources which were used so far
+    states: HashMap<Resource, ResourceState>,
+}
+
+impl BarrierTracker {
+    /// Start tracking resources, which are all assumed to be unused so far
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tell which layout an image is in, if it is not undefined
+    ///
+    /// By default, images are assumed to be in the undefined layout when they
+    /// are first used, which means that their previous contents may be lost.
+    ///
+    pub fn set_image_layout(&mut self,
+                            image: &impl ImageAccess,
+                            layout: vk::ImageLayout) {
+        let resource =
+            ResourceAccess::image(image, ResourceUsage::StorageRead).resource;
+        self.states.entry(resource).or_insert_with(Default::default).layout =
+            layout;
+    }
+
+    /// Declare the resource usage of the next pass, and compute the barriers
+    /// which must be recorded before it
+    ///
+    /// Each resource may only be declared once per pass.
+    ///
+    pub fn pass(&mut self,
+                accesses: &[ResourceAccess]) -> Result<PassBarriers> {
+        let _trace = trace::span("BarrierTracker::pass",
+                                 || format!("accesses: {}", accesses.len()));
+
+        // Check the declarations
+        for (idx, access) in accesses.iter().enumerate() {
+            let valid = match access.resource {
+                Resource::Buffer(_) => access.usage.applies_to_buffers(),
+                Resource::Image(..) => access.usage.image_layout().is_some(),
+            };
+            if !valid {
+                return Err(Error::InvalidArgument(
+                    format!("{:?} cannot be used as {:?}",
+                            access.resource, access.usage)
+                ));
+            }
+            if accesses[..idx].iter()
+                              .any(|other| other.resource == access.resource)
+            {
+                return Err(Error::InvalidArgument(
+                    format!("{:?} was declared twice in the same pass",
+                            access.resource)
+                ));
+            }
+        }
+
+        // Compute the barriers, updating resource states along the way
+        let mut barriers = PassBarriers::default();
+        for access in accesses {
+            let state = self.states.entry(access.resource)
+                                   .or_insert_with(Default::default);
+            if let Some((src_stages, src_access, old_layout)) =
+                Self::transition(state, access)
+            {
+                barriers.src_stages |= src_stages;
+                barriers.dst_stages |= access.usage.stages();
+                match access.resource {
+                    Resource::Buffer(buffer) => {
+                        barriers.buffer_barriers.push(BufferBarrier {
+                            buffer,
+                            src_access,
+                            dst_access: access.usage.access(),
+                        });
+                    },
+                    Resource::Image(image, aspects) => {
+                        barriers.image_barriers.push(ImageBarrier {
+                            image,
+                            aspects,
+                            src_access,
+                            dst_access: access.usage.access(),
+                            old_layout,
+                            new_layout: state.layout,
+                        });
+                    },
+                }
+            }
+        }
+        if barriers.dst_stages != 0 && barriers.src_stages == 0 {
+            barriers.src_stages = vk::PIPELINE_STAGE_TOP_OF_PIPE_BIT;
+        }
+        debug!("Pass needs {} buffer barrier(s) and {} image barrier(s)",
+               barriers.buffer_barriers.len(), barriers.image_barriers.len());
+        Ok(barriers)
+    }
+
+    /// Forget about a resource, e.g. before it is destroyed
+    pub fn forget(&mut self, access: &ResourceAccess) {
+        self.states.remove(&access.resource);
+    }
+
+    /// Update the state of a resource for an access, and tell which source
+    /// stages, source accesses and old layout a barrier needs, if any
+    fn transition(
+        state: &mut ResourceState,
+        access: &ResourceAccess
+    ) -> Option<(vk::PipelineStageFlags, vk::AccessFlags, vk::ImageLayout)> {
+        let usage = access.usage;
+        let old_layout = state.layout;
+        let new_layout = match access.resource {
+            Resource::Buffer(_) => old_layout,
+            Resource::Image(..) => usage.image_layout().expect("Checked"),
+        };
+        let src_stages = state.write_stages | state.read_stages;
+
+        // Writes and layout transitions must wait for all previous accesses
+        if usage.is_write() || new_layout != old_layout {
+            let needs_barrier = src_stages != 0 || new_layout != old_layout;
+            let barrier = (src_stages, state.write_access, old_layout);
+            *state = if usage.is_write() {
+                ResourceState {
+                    layout: new_layout,
+                    write_stages: usage.stages(),
+                    write_access: usage.access(),
+                    ..ResourceState::default()
+                }
+            } else {
+                ResourceState {
+                    layout: new_layout,
+                    read_stages: usage.stages(),
+                    ..ResourceState::default()
+                }
+            };
+            return if needs_barrier { Some(barrier) } else { None };
+        }
+
+        // Reads must wait for the last write, unless it is already visible
+        state.read_stages |= usage.stages();
+        let visible = state.write_stages == 0
+            || (state.visible_stages & usage.stages() == usage.stages()
+                && state.visible_access & usage.access() == usage.access());
+        if visible { return None; }
+        state.visible_stages |= usage.stages();
+        state.visible_access |= usage.access();
+        Some((state.write_stages, state.write_access, old_layout))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Declare the use of a fake buffer handle
+    fn buffer(handle: vk::Buffer, usage: ResourceUsage) -> ResourceAccess {
+        ResourceAccess { resource: Resource::Buffer(handle), usage }
+    }
+
+    /// Declare the use of a fake color image handle
+    fn image(handle: vk::Image, usage: ResourceUsage) -> ResourceAccess {
+        ResourceAccess {
+            resource: Resource::Image(handle, vk::IMAGE_ASPECT_COLOR_BIT),
+            usage,
+        }
+    }
+
+    #[test]
+    fn read_after_write() {
+        let mut tracker = BarrierTracker::new();
+        let written = tracker.pass(&[buffer(1, ResourceUsage::StorageWrite)])
+                             .unwrap();
+        assert!(written.is_empty());
+
+        let read = tracker.pass(&[buffer(1, ResourceUsage::VertexBuffer)])
+                          .unwrap();
+        assert_eq!(read.src_stages, ResourceUsage::StorageWrite.stages());
+        assert_eq!(read.dst_stages, vk::PIPELINE_STAGE_VERTEX_INPUT_BIT);
+        assert_eq!(read.buffer_barriers, vec![BufferBarrier {
+            buffer: 1,
+            src_access: ResourceUsage::StorageWrite.access(),
+            dst_access: vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT,
+        }]);
+        assert!(read.image_barriers.is_empty());
+    }
+
+    #[test]
+    fn write_after_read() {
+        let mut tracker = BarrierTracker::new();
+        let read = tracker.pass(&[buffer(1, ResourceUsage::StorageRead)])
+                          .unwrap();
+        assert!(read.is_empty());
+
+        // Only an execution dependency is needed, as nothing was written
+        let written = tracker.pass(&[buffer(1, ResourceUsage::TransferDst)])
+                             .unwrap();
+        assert_eq!(written.src_stages, ResourceUsage::StorageRead.stages());
+        assert_eq!(written.dst_stages, vk::PIPELINE_STAGE_TRANSFER_BIT);
+        assert_eq!(written.buffer_barriers, vec![BufferBarrier {
+            buffer: 1,
+            src_access: 0,
+            dst_access: vk::ACCESS_TRANSFER_WRITE_BIT,
+        }]);
+    }
+
+    #[test]
+    fn write_after_write() {
+        let mut tracker = BarrierTracker::new();
+        tracker.pass(&[buffer(1, ResourceUsage::TransferDst)]).unwrap();
+        let written = tracker.pass(&[buffer(1, ResourceUsage::StorageWrite)])
+                             .unwrap();
+        assert_eq!(written.src_stages, vk::PIPELINE_STAGE_TRANSFER_BIT);
+        assert_eq!(written.buffer_barriers[0].src_access,
+                   vk::ACCESS_TRANSFER_WRITE_BIT);
+    }
+
+    #[test]
+    fn repeated_reads() {
+        let mut tracker = BarrierTracker::new();
+        tracker.pass(&[buffer(1, ResourceUsage::TransferDst)]).unwrap();
+        let first = tracker.pass(&[buffer(1, ResourceUsage::UniformBuffer)])
+                           .unwrap();
+        assert!(!first.is_empty());
+
+        // The write is already visible to these stages and accesses
+        let second = tracker.pass(&[buffer(1, ResourceUsage::UniformBuffer)])
+                            .unwrap();
+        assert!(second.is_empty());
+        assert!(second.buffer_barriers.is_empty());
+
+        // ...but not to other accesses
+        let other = tracker.pass(&[buffer(1, ResourceUsage::IndexBuffer)])
+                           .unwrap();
+        assert_eq!(other.src_stages, vk::PIPELINE_STAGE_TRANSFER_BIT);
+        assert_eq!(other.buffer_barriers[0].dst_access,
+                   vk::ACCESS_INDEX_READ_BIT);
+    }
+
+    #[test]
+    fn layout_changes() {
+        let mut tracker = BarrierTracker::new();
+
+        // First use transitions from the undefined layout
+        let upload = tracker.pass(&[image(2, ResourceUsage::TransferDst)])
+                            .unwrap();
+        assert_eq!(upload.src_stages, vk::PIPELINE_STAGE_TOP_OF_PIPE_BIT);
+        assert_eq!(upload.image_barriers, vec![ImageBarrier {
+            image: 2,
+            aspects: vk::IMAGE_ASPECT_COLOR_BIT,
+            src_access: 0,
+            dst_access: vk::ACCESS_TRANSFER_WRITE_BIT,
+            old_layout: vk::IMAGE_LAYOUT_UNDEFINED,
+            new_layout: vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+        }]);
+
+        // Reading in another layout needs a transition
+        let sample = tracker.pass(&[image(2, ResourceUsage::Sampled)])
+                            .unwrap();
+        assert_eq!(sample.src_stages, vk::PIPELINE_STAGE_TRANSFER_BIT);
+        assert_eq!(sample.image_barriers, vec![ImageBarrier {
+            image: 2,
+            aspects: vk::IMAGE_ASPECT_COLOR_BIT,
+            src_access: vk::ACCESS_TRANSFER_WRITE_BIT,
+            dst_access: vk::ACCESS_SHADER_READ_BIT,
+            old_layout: vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+            new_layout: vk::IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL,
+        }]);
+        assert!(tracker.pass(&[image(2, ResourceUsage::Sampled)])
+                       .unwrap()
+                       .is_empty());
+
+        // Even a read-to-read layout change must wait for previous reads
+        let storage = tracker.pass(&[image(2, ResourceUsage::StorageRead)])
+                             .unwrap();
+        assert_eq!(storage.src_stages, ResourceUsage::Sampled.stages());
+        assert_eq!(storage.image_barriers[0].src_access, 0);
+        assert_eq!(storage.image_barriers[0].old_layout,
+                   vk::IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL);
+        assert_eq!(storage.image_barriers[0].new_layout,
+                   vk::IMAGE_LAYOUT_GENERAL);
+    }
+
+    #[test]
+    fn known_initial_layout() {
+        let mut tracker = BarrierTracker::new();
+        tracker.states.insert(
+            image(2, ResourceUsage::Sampled).resource,
+            ResourceState {
+                layout: vk::IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL,
+                ..ResourceState::default()
+            }
+        );
+        assert!(tracker.pass(&[image(2, ResourceUsage::Sampled)])
+                       .unwrap()
+                       .is_empty());
+    }
+
+    #[test]
+    fn independent_resources() {
+        let mut tracker = BarrierTracker::new();
+        tracker.pass(&[buffer(1, ResourceUsage::StorageWrite),
+                       image(2, ResourceUsage::StorageWrite)]).unwrap();
+        let barriers = tracker.pass(&[buffer(1, ResourceUsage::StorageRead),
+                                      buffer(3, ResourceUsage::StorageRead)])
+                              .unwrap();
+        assert_eq!(barriers.buffer_barriers.len(), 1);
+        assert_eq!(barriers.buffer_barriers[0].buffer, 1);
+        assert!(barriers.image_barriers.is_empty());
+    }
+
+    #[test]
+    fn invalid_declarations() {
+        let mut tracker = BarrierTracker::new();
+        assert!(tracker.pass(&[buffer(1, ResourceUsage::Sampled)]).is_err());
+        assert!(tracker.pass(&[image(2, ResourceUsage::IndexBuffer)])
+                       .is_err());
+        assert!(tracker.pass(&[buffer(1, ResourceUsage::StorageRead),
+                               buffer(1, ResourceUsage::StorageWrite)])
+                       .is_err());
+    }
+}
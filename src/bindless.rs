@@ -0,0 +1,771 @@
+//! "Bindless" texturing through a large, variable-size array of textures
+//!
+//! Material systems get much simpler when every texture lives in one big
+//! descriptor array, and materials refer to textures by index instead of
+//! requiring descriptor sets of their own. EasyBindless maintains such an
+//! array: textures are registered one by one, and get an index in return.
+//!
+//! This relies on descriptor indexing (VK_EXT_descriptor_indexing, core in
+//! Vulkan 1.2). EasyPhysicalDevice::setup_bindless_device() negotiates the
+//! extension and enables its descriptorBindingPartiallyBound,
+//! descriptorBindingVariableDescriptorCount and runtimeDescriptorArray
+//! features, along with non-uniform indexing of sampled image arrays when the
+//! device supports it. DescriptorIndexingSupport tells what a device supports
+//! in this area. From there:
+//!
+//! - The texture array must be the only binding of its descriptor set. Its
+//!   size in the pipeline layout is an upper bound (e.g.
+//!   `layout(set = 1, binding = 0) uniform sampler2D textures[65536];`), and
+//!   the actual size is chosen when creating EasyBindless. Since vulkano
+//!   creates descriptor set layouts itself, pipelines which use the array
+//!   must be built inside of with_bindless_layout(), which makes the binding
+//!   partially bound and variable-sized.
+//! - Array elements which were never used are left unwritten, whereas those
+//!   of unregistered textures point to a placeholder texture.
+//! - Indices must be dynamically uniform, i.e. the same for all invocations
+//!   of a draw call, unless the device supports non-uniform indexing (see
+//!   DescriptorIndexingSupport) and shaders use `nonuniformEXT()`.
+//! - Descriptor sets cannot be modified while the GPU uses them, so there is
+//!   one descriptor set per frame in flight, which is brought up to date when
+//!   descriptor_set() is called at the beginning of a frame.
+
+use ::{
+    api_level::VULKAN_1_2,
+    loader::{
+        self,
+        BindingFlags,
+        FeatureChain,
+        FeatureStruct,
+    },
+    properties2,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    cmp,
+    collections::BTreeSet,
+    ffi::CString,
+    mem,
+    os::raw::c_void,
+    sync::Arc,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    buffer::BufferAccess,
+    descriptor::{
+        descriptor::{
+            DescriptorDesc,
+            DescriptorDescTy,
+        },
+        descriptor_set::{
+            DescriptorSet,
+            DescriptorSetDesc,
+            DescriptorWrite,
+            DescriptorsCount,
+            UnsafeDescriptorPool,
+            UnsafeDescriptorSet,
+        },
+        pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::{
+        Device,
+        DeviceOwned,
+    },
+    image::ImageViewAccess,
+    instance::{
+        Features,
+        PhysicalDevice,
+        RawDeviceExtensions,
+        Version,
+    },
+    sampler::Sampler,
+};
+
+
+/// Name of the descriptor indexing extension
+pub const DESCRIPTOR_INDEXING_EXTENSION: &str = "VK_EXT_descriptor_indexing";
+
+/// Structure type of VkPhysicalDeviceDescriptorIndexingFeatures
+const STRUCTURE_TYPE_DESCRIPTOR_INDEXING_FEATURES: u32 = 1_000_161_001;
+
+/// Structure type of VkPhysicalDeviceDescriptorIndexingProperties
+const STRUCTURE_TYPE_DESCRIPTOR_INDEXING_PROPERTIES: u32 = 1_000_161_002;
+
+/// Extension which VK_EXT_descriptor_indexing depends on, on Vulkan 1.0
+const MAINTENANCE3_EXTENSION: &str = "VK_KHR_maintenance3";
+
+/// VkDescriptorBindingFlags of the texture array binding
+const DESCRIPTOR_BINDING_PARTIALLY_BOUND: u32 = 0x4;
+const DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT: u32 = 0x8;
+
+
+/// Vulkan 1.0 device features needed by EasyBindless
+///
+/// EasyPhysicalDevice::setup_bindless_device() enables them, along with the
+/// descriptor indexing features.
+///
+pub fn bindless_features() -> Features {
+    Features {
+        shader_sampled_image_array_dynamic_indexing: true,
+        ..Features::none()
+    }
+}
+
+/// Largest texture array which a device supports in a single shader stage
+pub fn max_bindless_textures(device: PhysicalDevice) -> u32 {
+    let limits = device.limits();
+    cmp::min(
+        cmp::min(limits.max_per_stage_descriptor_samplers(),
+                 limits.max_per_stage_descriptor_sampled_images()),
+        cmp::min(limits.max_descriptor_set_samplers(),
+                 limits.max_descriptor_set_sampled_images())
+    )
+}
+
+/// Device filter which only accepts devices that support texture arrays of
+/// a certain size, with the bindless_features() and the descriptor indexing
+/// features which EasyBindless needs
+///
+/// This is meant to be used as (part of) the "other_criteria" parameter of
+/// easy_device_filter().
+///
+pub fn supports_bindless(
+    min_textures: u32
+) -> impl FnMut(PhysicalDevice) -> bool {
+    move |device| {
+        let indexing = match DescriptorIndexingSupport::of_device(device) {
+            Ok(Some(support)) => support.features,
+            _ => return false,
+        };
+        device.supported_features().superset_of(&bindless_features())
+            && indexing.missing_bindless_features().is_empty()
+            && max_bindless_textures(device) >= min_textures
+    }
+}
+
+/// Build pipelines whose layout contains a bindless texture array at some
+/// binding index
+///
+/// Vulkano creates the descriptor set layouts of pipelines as they are built,
+/// and this makes the texture array partially bound and variable-sized in
+/// them. The pipelines must be built by the closure, on the calling thread,
+/// for a device that was set up with
+/// EasyPhysicalDevice::setup_bindless_device(). Other descriptor sets of the
+/// pipelines may not consist of a single array of combined image samplers at
+/// the same binding index.
+///
+pub fn with_bindless_layout<R>(binding: u32,
+                               build: impl FnOnce() -> R) -> R {
+    loader::with_binding_flags(
+        BindingFlags {
+            binding,
+            descriptor_type: vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER,
+            flags: DESCRIPTOR_BINDING_PARTIALLY_BOUND
+                   | DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT,
+        },
+        build
+    )
+}
+
+/// Add the descriptor indexing extension (if needed) to a set of device
+/// extensions, and the features needed by EasyBindless to a feature chain,
+/// or tell what is missing
+///
+/// The API version is that which can be used on the device, which tells
+/// whether descriptor indexing is core or must be enabled as an extension.
+///
+pub(crate) fn add_device_extensions(
+    device: PhysicalDevice,
+    api_version: Version,
+    extensions: &mut RawDeviceExtensions,
+    feature_chain: &mut FeatureChain,
+) -> Result<()> {
+    let _trace = trace::span("bindless::add_device_extensions", || {
+        format!("device: {}, api_version: {}", device.name(), api_version)
+    });
+    let supported = RawDeviceExtensions::supported_by_device_raw(device)?;
+    let has_extension = |name: &str| {
+        supported.iter().any(|ext| ext.as_bytes() == name.as_bytes())
+    };
+    let support = DescriptorIndexingSupport::of_device(device)?;
+    let support = match support {
+        Some(ref support) if api_version >= VULKAN_1_2
+                             || has_extension(DESCRIPTOR_INDEXING_EXTENSION)
+            => support,
+        _ => return Err(Error::MissingExtensions {
+            device: device.name(),
+            extensions: vec![DESCRIPTOR_INDEXING_EXTENSION.to_owned()],
+        }),
+    };
+    let missing_features = support.features.missing_bindless_features();
+    if !missing_features.is_empty() {
+        return Err(Error::MissingFeatures {
+            device: device.name(),
+            features: missing_features,
+        });
+    }
+
+    // On older Vulkan versions, descriptor indexing is an extension, which
+    // depends on VK_KHR_maintenance3 (core since Vulkan 1.1)
+    if api_version < VULKAN_1_2 {
+        for &name in &[DESCRIPTOR_INDEXING_EXTENSION, MAINTENANCE3_EXTENSION] {
+            if has_extension(name) {
+                extensions.insert(CString::new(name)
+                                          .expect("Extension name has no NUL"));
+            }
+        }
+    }
+    let mut features: RawDescriptorIndexingFeatures = unsafe {
+        mem::zeroed()
+    };
+    features.s_type = STRUCTURE_TYPE_DESCRIPTOR_INDEXING_FEATURES;
+    features.shader_sampled_image_array_non_uniform_indexing =
+        support.features.shader_sampled_image_array_non_uniform_indexing
+            as u32;
+    features.descriptor_binding_partially_bound = 1;
+    features.descriptor_binding_variable_descriptor_count = 1;
+    features.runtime_descriptor_array = 1;
+    feature_chain.push(features);
+    Ok(())
+}
+
+
+/// Descriptor indexing features of a device, restricted to those which are
+/// relevant to texture arrays (VkPhysicalDeviceDescriptorIndexingFeatures)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DescriptorIndexingFeatures {
+    /// Sampled image arrays can be indexed with non-uniform indices
+    pub shader_sampled_image_array_non_uniform_indexing: bool,
+
+    /// Sampled image descriptors can be updated after being bound
+    pub descriptor_binding_sampled_image_update_after_bind: bool,
+
+    /// Descriptors which are not used by the GPU can be updated while the
+    /// descriptor set is in use
+    pub descriptor_binding_update_unused_while_pending: bool,
+
+    /// Descriptor arrays do not need to be fully populated
+    pub descriptor_binding_partially_bound: bool,
+
+    /// The size of the last binding of a set can be chosen at allocation time
+    pub descriptor_binding_variable_descriptor_count: bool,
+
+    /// Shaders can use arrays of unspecified size
+    pub runtime_descriptor_array: bool,
+}
+
+impl DescriptorIndexingFeatures {
+    /// Names of the features which EasyBindless needs, but which are missing
+    pub fn missing_bindless_features(&self) -> Vec<String> {
+        let mut missing = Vec::new();
+        if !self.descriptor_binding_partially_bound {
+            missing.push("descriptor_binding_partially_bound".to_owned());
+        }
+        if !self.descriptor_binding_variable_descriptor_count {
+            missing.push("descriptor_binding_variable_descriptor_count"
+                             .to_owned());
+        }
+        if !self.runtime_descriptor_array {
+            missing.push("runtime_descriptor_array".to_owned());
+        }
+        missing
+    }
+}
+
+/// VkPhysicalDeviceDescriptorIndexingFeatures, which vk-sys does not know
+/// about (VkBool32 members are stored as u32)
+#[repr(C)]
+struct RawDescriptorIndexingFeatures {
+    s_type: u32,
+    p_next: *mut c_void,
+    shader_input_attachment_array_dynamic_indexing: u32,
+    shader_uniform_texel_buffer_array_dynamic_indexing: u32,
+    shader_storage_texel_buffer_array_dynamic_indexing: u32,
+    shader_uniform_buffer_array_non_uniform_indexing: u32,
+    shader_sampled_image_array_non_uniform_indexing: u32,
+    shader_storage_buffer_array_non_uniform_indexing: u32,
+    shader_storage_image_array_non_uniform_indexing: u32,
+    shader_input_attachment_array_non_uniform_indexing: u32,
+    shader_uniform_texel_buffer_array_non_uniform_indexing: u32,
+    shader_storage_texel_buffer_array_non_uniform_indexing: u32,
+    descriptor_binding_uniform_buffer_update_after_bind: u32,
+    descriptor_binding_sampled_image_update_after_bind: u32,
+    descriptor_binding_storage_image_update_after_bind: u32,
+    descriptor_binding_storage_buffer_update_after_bind: u32,
+    descriptor_binding_uniform_texel_buffer_update_after_bind: u32,
+    descriptor_binding_storage_texel_buffer_update_after_bind: u32,
+    descriptor_binding_update_unused_while_pending: u32,
+    descriptor_binding_partially_bound: u32,
+    descriptor_binding_variable_descriptor_count: u32,
+    runtime_descriptor_array: u32,
+}
+
+unsafe impl FeatureStruct for RawDescriptorIndexingFeatures {}
+
+/// VkPhysicalDeviceDescriptorIndexingProperties, which vk-sys does not know
+/// about, with related members grouped into arrays
+#[repr(C)]
+struct RawDescriptorIndexingProperties {
+    s_type: u32,
+    p_next: *mut c_void,
+    max_update_after_bind_descriptors_in_all_pools: u32,
+    /// Uniform buffers, sampled images, storage buffers, storage images and
+    /// input attachments
+    non_uniform_indexing_native: [u32; 5],
+    robust_buffer_access_update_after_bind: u32,
+    quad_divergent_implicit_lod: u32,
+    /// Samplers, uniform buffers, storage buffers, sampled images, storage
+    /// images, input attachments and resources
+    max_per_stage_update_after_bind: [u32; 7],
+    /// Samplers, uniform buffers, dynamic uniform buffers, storage buffers,
+    /// dynamic storage buffers, sampled images, storage images and input
+    /// attachments
+    max_set_update_after_bind: [u32; 8],
+}
+
+/// Summary of the descriptor indexing support of a physical device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorIndexingSupport {
+    /// Supported descriptor indexing features
+    pub features: DescriptorIndexingFeatures,
+
+    /// Truth that sampled image arrays are natively indexed with non-uniform
+    /// indices, without performance penalties
+    pub sampled_image_non_uniform_indexing_native: bool,
+
+    /// Maximal number of update-after-bind sampled images per shader stage
+    pub max_per_stage_update_after_bind_sampled_images: u32,
+
+    /// Maximal number of update-after-bind sampled images per pipeline layout
+    pub max_set_update_after_bind_sampled_images: u32,
+
+    /// Truth that VK_EXT_descriptor_indexing must be enabled to use
+    /// descriptor indexing, rather than it being part of core Vulkan 1.2
+    pub needs_extension: bool,
+}
+
+impl DescriptorIndexingSupport {
+    /// Probe the descriptor indexing support of a physical device
+    ///
+    /// This returns None if the device supports neither Vulkan 1.2 nor
+    /// VK_EXT_descriptor_indexing, or if the instance was created without
+    /// VK_KHR_get_physical_device_properties2.
+    ///
+    pub fn of_device(device: PhysicalDevice) -> Result<Option<Self>> {
+        let is_core = device.api_version() >= VULKAN_1_2;
+        let extensions = RawDeviceExtensions::supported_by_device_raw(device)?;
+        let has_extension = extensions.iter().any(|ext| {
+            ext.as_bytes() == DESCRIPTOR_INDEXING_EXTENSION.as_bytes()
+        });
+        if !is_core && !has_extension { return Ok(None); }
+
+        let mut features: RawDescriptorIndexingFeatures = unsafe {
+            mem::zeroed()
+        };
+        features.s_type = STRUCTURE_TYPE_DESCRIPTOR_INDEXING_FEATURES;
+        let mut properties: RawDescriptorIndexingProperties = unsafe {
+            mem::zeroed()
+        };
+        properties.s_type = STRUCTURE_TYPE_DESCRIPTOR_INDEXING_PROPERTIES;
+        let queried = unsafe {
+            properties2::query_features(
+                device,
+                &mut features as *mut RawDescriptorIndexingFeatures
+                              as *mut c_void
+            ) && properties2::query_properties(
+                device,
+                &mut properties as *mut RawDescriptorIndexingProperties
+                                as *mut c_void
+            )
+        };
+        if !queried { return Ok(None); }
+
+        Ok(Some(DescriptorIndexingSupport {
+            features: DescriptorIndexingFeatures {
+                shader_sampled_image_array_non_uniform_indexing:
+                    features.shader_sampled_image_array_non_uniform_indexing
+                        != 0,
+                descriptor_binding_sampled_image_update_after_bind:
+                    features.descriptor_binding_sampled_image_update_after_bind
+                        != 0,
+                descriptor_binding_update_unused_while_pending:
+                    features.descriptor_binding_update_unused_while_pending
+                        != 0,
+                descriptor_binding_partially_bound:
+                    features.descriptor_binding_partially_bound != 0,
+                descriptor_binding_variable_descriptor_count:
+                    features.descriptor_binding_variable_descriptor_count != 0,
+                runtime_descriptor_array:
+                    features.runtime_descriptor_array != 0,
+            },
+            sampled_image_non_uniform_indexing_native:
+                properties.non_uniform_indexing_native[1] != 0,
+            max_per_stage_update_after_bind_sampled_images:
+                properties.max_per_stage_update_after_bind[3],
+            max_set_update_after_bind_sampled_images:
+                properties.max_set_update_after_bind[5],
+            needs_extension: !is_core,
+        }))
+    }
+}
+
+
+/// An image, as stored inside of a bindless texture array
+type DynImage = Arc<dyn ImageViewAccess + Send + Sync>;
+
+/// A texture, i.e. an image with its sampler
+type Texture = (DynImage, Arc<Sampler>);
+
+
+/// Manager of a large array of textures, which shaders index by number
+pub struct EasyBindless {
+    /// Device which the descriptor sets belong to
+    device: Arc<Device>,
+
+    /// Binding of the texture array
+    binding: u32,
+
+    /// Texture which unused array elements point to
+    placeholder: Texture,
+
+    /// Registered textures, by index, if any was registered there
+    textures: Vec<Option<Texture>>,
+
+    /// Truth that the array element at each index was ever written
+    written: Vec<bool>,
+
+    /// Indices of unregistered textures, which can be reused
+    free_indices: BTreeSet<u32>,
+
+    /// Descriptor set of each frame slot, along with the array elements
+    /// which must be rewritten before it is used again
+    sets: Vec<(Arc<BindlessSet>, BTreeSet<u32>)>,
+}
+
+impl EasyBindless {
+    /// Set up a texture array with a certain capacity, for a binding of a
+    /// pipeline layout
+    ///
+    /// The layout must have been created inside of with_bindless_layout().
+    /// The binding must be an array of combined image samplers which can hold
+    /// at least "capacity" textures, and the only binding of its descriptor
+    /// set. One descriptor set is allocated per frame in flight, with every
+    /// array element left unwritten at first.
+    ///
+    pub fn new<L, I>(layout: L,
+                     set: usize,
+                     binding: usize,
+                     capacity: u32,
+                     frames_in_flight: usize,
+                     placeholder_image: Arc<I>,
+                     placeholder_sampler: Arc<Sampler>) -> Result<Self>
+        where L: PipelineLayoutAbstract,
+              I: ImageViewAccess + Send + Sync + 'static
+    {
+        let _trace = trace::span("EasyBindless::new", || {
+            format!("set: {}, binding: {}, capacity: {}, frames_in_flight: {}",
+                    set, binding, capacity, frames_in_flight)
+        });
+        assert!(frames_in_flight > 0, "At least one frame must be in flight");
+        let physical_device = layout.device().physical_device();
+        let indexing = DescriptorIndexingSupport::of_device(physical_device)?
+                           .map(|support| support.features)
+                           .unwrap_or_default();
+        let missing_features = indexing.missing_bindless_features();
+        if !missing_features.is_empty() {
+            return Err(Error::MissingFeatures {
+                device: physical_device.name(),
+                features: missing_features,
+            });
+        }
+
+        // Check the layout of the descriptor set
+        let num_bindings = layout.num_bindings_in_set(set).ok_or_else(|| {
+            Error::InvalidArgument(format!("No descriptor set {} in layout",
+                                           set))
+        })?;
+        let descriptors = (0..num_bindings).map(|idx| layout.descriptor(set,
+                                                                        idx))
+                                           .collect::<Vec<_>>();
+        let max_capacity = match descriptors.get(binding) {
+            Some(Some(DescriptorDesc {
+                ty: DescriptorDescTy::CombinedImageSampler(_),
+                array_count,
+                ..
+            })) => *array_count,
+            _ => return Err(Error::InvalidArgument(
+                format!("Binding {} of descriptor set {} is not an array of \
+                         combined image samplers", binding, set)
+            )),
+        };
+        let other_bindings = descriptors.iter()
+                                        .enumerate()
+                                        .filter(|&(idx, desc)| {
+                                            idx != binding && desc.is_some()
+                                        })
+                                        .count();
+        if other_bindings != 0 {
+            return Err(Error::InvalidArgument(
+                format!("Descriptor set {} has other bindings than the \
+                         texture array", set)
+            ));
+        }
+        if capacity == 0 || capacity > max_capacity {
+            return Err(Error::InvalidArgument(
+                format!("Texture array capacity must be between 1 and {}, \
+                         the size of binding {} of descriptor set {}",
+                        max_capacity, binding, set)
+            ));
+        }
+        let max_textures = max_bindless_textures(physical_device);
+        if capacity > max_textures {
+            return Err(Error::InvalidArgument(
+                format!("Texture array has {} elements, but the device only \
+                         supports {}", capacity, max_textures)
+            ));
+        }
+        info!("Setting up a bindless array of {} textures", capacity);
+
+        // Allocate one variable-size descriptor set per frame in flight, from
+        // a dedicated pool
+        let device = layout.device().clone();
+        let set_layout = layout.descriptor_set_layout(set)
+                               .expect("Set was checked above");
+        let pool_size = DescriptorsCount {
+            combined_image_sampler: capacity * frames_in_flight as u32,
+            ..DescriptorsCount::zero()
+        };
+        let mut pool = UnsafeDescriptorPool::new(device.clone(),
+                                                 &pool_size,
+                                                 frames_in_flight as u32,
+                                                 false)?;
+        let raw_sets = loader::with_variable_descriptor_count(capacity, || {
+            // Safe because the pool was sized for these allocations
+            unsafe {
+                pool.alloc((0..frames_in_flight).map(|_| &**set_layout))
+                    .map(|sets| sets.collect::<Vec<_>>())
+            }
+        })?;
+        let pool = Arc::new(pool);
+        let sets = raw_sets.into_iter().map(|raw_set| {
+            let set = BindlessSet {
+                _pool: pool.clone(),
+                set: raw_set,
+                descriptors: descriptors.clone(),
+                images: Vec::new(),
+                samplers: Vec::new(),
+            };
+            (Arc::new(set), BTreeSet::new())
+        }).collect();
+
+        let placeholder: Texture = (placeholder_image, placeholder_sampler);
+        Ok(EasyBindless {
+            device,
+            binding: binding as u32,
+            placeholder,
+            textures: (0..capacity).map(|_| None).collect(),
+            written: vec![false; capacity as usize],
+            free_indices: (0..capacity).collect(),
+            sets,
+        })
+    }
+
+    /// Number of elements of the texture array
+    pub fn capacity(&self) -> u32 {
+        self.textures.len() as u32
+    }
+
+    /// Number of registered textures
+    pub fn len(&self) -> usize {
+        self.textures.len() - self.free_indices.len()
+    }
+
+    /// Truth that no texture is registered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Register a texture, and get the array index which shaders should use
+    /// to access it
+    ///
+    /// Indices of unregistered textures are reused, lowest ones first. The
+    /// texture becomes visible to shaders from the next call to
+    /// descriptor_set() onwards.
+    ///
+    pub fn register<I>(&mut self,
+                       image: Arc<I>,
+                       sampler: Arc<Sampler>) -> Result<u32>
+        where I: ImageViewAccess + Send + Sync + 'static
+    {
+        let index = match self.free_indices.iter().next() {
+            Some(&index) => index,
+            None => return Err(Error::InvalidArgument(
+                format!("All {} elements of the texture array are used",
+                        self.capacity())
+            )),
+        };
+        self.free_indices.remove(&index);
+        self.replace(index, Some((image, sampler)));
+        Ok(index)
+    }
+
+    /// Unregister a texture, making its index available for reuse
+    ///
+    /// Shaders which still use the index will see the placeholder texture.
+    ///
+    pub fn unregister(&mut self, index: u32) -> Result<()> {
+        match self.textures.get(index as usize) {
+            Some(Some(_)) => {},
+            _ => return Err(Error::InvalidArgument(
+                format!("No texture is registered at index {}", index)
+            )),
+        }
+        self.replace(index, None);
+        self.free_indices.insert(index);
+        Ok(())
+    }
+
+    /// Get the descriptor set of a frame slot, up to date with the textures
+    /// which are registered at this point
+    ///
+    /// The GPU must be done with the previous frame which used the same slot,
+    /// as is the case after FramePacer::begin_frame(), and the descriptor set
+    /// which was returned for that frame must have been dropped, along with
+    /// the command buffers which use it. The result can be passed to
+    /// vulkano's draw and dispatch commands.
+    ///
+    pub fn descriptor_set(&mut self,
+                          frame_index: usize) -> Result<Arc<BindlessSet>> {
+        let _trace = trace::span("EasyBindless::descriptor_set",
+                                 || format!("frame_index: {}", frame_index));
+        let num_sets = self.sets.len();
+        let &mut (ref mut set, ref mut dirty) =
+            self.sets.get_mut(frame_index).ok_or_else(|| {
+                Error::InvalidArgument(
+                    format!("Frame slot {} is out of range ({} frames in \
+                             flight)", frame_index, num_sets)
+                )
+            })?;
+        if !dirty.is_empty() {
+            let set = Arc::get_mut(set).ok_or_else(|| {
+                Error::InvalidArgument(
+                    format!("Descriptor set of frame slot {} is still in use",
+                            frame_index)
+                )
+            })?;
+            debug!("Updating {} element(s) of the texture array of frame \
+                    slot {}", dirty.len(), frame_index);
+
+            // Write the modified array elements
+            let textures = &self.textures;
+            let placeholder = &self.placeholder;
+            let binding = self.binding;
+            let writes = dirty.iter().map(|&index| {
+                let &(ref image, ref sampler) =
+                    textures[index as usize].as_ref().unwrap_or(placeholder);
+                DescriptorWrite::combined_image_sampler(binding,
+                                                        index,
+                                                        sampler,
+                                                        image)
+            }).collect::<Vec<_>>();
+            // Safe because the binding was checked against the layout, and
+            // the GPU is done with the descriptor set
+            unsafe {
+                set.set.write(&self.device, writes.into_iter());
+            }
+            dirty.clear();
+
+            // Keep track of the resources which the set now refers to
+            set.images.clear();
+            set.samplers.clear();
+            let placeholder_used = textures.iter()
+                                           .zip(&self.written)
+                                           .any(|(texture, &written)| {
+                                               written && texture.is_none()
+                                           });
+            let all = textures.iter()
+                              .filter_map(|texture| texture.as_ref())
+                              .chain(Some(placeholder).filter(|_| {
+                                  placeholder_used
+                              }));
+            for &(ref image, ref sampler) in all {
+                if !set.images.iter().any(|(other, _)| {
+                    Arc::ptr_eq(other, image)
+                }) {
+                    set.images.push((image.clone(), binding));
+                }
+                if !set.samplers.iter().any(|other| {
+                    Arc::ptr_eq(other, sampler)
+                }) {
+                    set.samplers.push(sampler.clone());
+                }
+            }
+        }
+        Ok(set.clone())
+    }
+
+    /// Change the texture of an array element in every descriptor set
+    fn replace(&mut self, index: u32, texture: Option<Texture>) {
+        self.textures[index as usize] = texture;
+        self.written[index as usize] = true;
+        for &mut (_, ref mut dirty) in &mut self.sets {
+            dirty.insert(index);
+        }
+    }
+}
+
+
+/// Descriptor set of a frame slot, as returned by EasyBindless
+pub struct BindlessSet {
+    /// Pool which the descriptor set was allocated from, kept alive
+    _pool: Arc<UnsafeDescriptorPool>,
+
+    /// Descriptor set, which is freed along with the pool
+    set: UnsafeDescriptorSet,
+
+    /// Layout of the bindings of the descriptor set
+    descriptors: Vec<Option<DescriptorDesc>>,
+
+    /// Distinct images which the set refers to, with their binding index
+    images: Vec<(DynImage, u32)>,
+
+    /// Distinct samplers which the set refers to, which must be kept alive
+    samplers: Vec<Arc<Sampler>>,
+}
+
+unsafe impl DescriptorSet for BindlessSet {
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        &self.set
+    }
+
+    fn num_buffers(&self) -> usize {
+        0
+    }
+
+    fn buffer(&self, _index: usize) -> Option<(&dyn BufferAccess, u32)> {
+        None
+    }
+
+    fn num_images(&self) -> usize {
+        self.images.len()
+    }
+
+    fn image(&self, index: usize) -> Option<(&dyn ImageViewAccess, u32)> {
+        self.images.get(index)
+                   .map(|(image, binding)| (&**image as _, *binding))
+    }
+}
+
+unsafe impl DescriptorSetDesc for BindlessSet {
+    fn num_bindings(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    fn descriptor(&self, binding: usize) -> Option<DescriptorDesc> {
+        self.descriptors.get(binding).and_then(|desc| desc.clone())
+    }
+}
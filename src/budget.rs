@@ -0,0 +1,242 @@
+//! Tracking of GPU memory usage against the device's memory heaps
+//!
+//! Running out of GPU memory tends to produce unhelpful errors, or worse,
+//! silent performance degradation as the driver starts paging. The
+//! MemoryTracker of an EasyDevice keeps track of the allocations which are
+//! reported to it, and warns you when a memory heap is about to be full.
+//!
+//! Note that the tracker is partial. The version of vulkano that we use does
+//! not tell which memory type its allocations come from, so the only vulkanoob
+//! helper which reports to the tracker is EasyDevice::upload_buffer(), and it
+//! assumes that its buffers come from the first device-local heap. Other
+//! allocations, including those of vulkano itself and of the other vulkanoob
+//! helpers, are invisible to the tracker unless you report them with
+//! MemoryTracker::track(). Tracked usage is thus a lower bound of the actual
+//! usage, and heap sizes are the only fully reliable figures.
+//!
+//! Allocations are tracked through weak references to the allocated
+//! resources, so they stop being counted as soon as the resources are dropped.
+
+use std::{
+    any::Any,
+    fmt,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use vulkano::instance::PhysicalDevice;
+
+
+/// Fraction of a heap's size above which warnings are emitted
+const WARNING_THRESHOLD: f64 = 0.9;
+
+/// Minimal delay between two periodic usage summaries
+const SUMMARY_PERIOD: Duration = Duration::from_secs(10);
+
+
+/// Memory usage of a memory heap
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeapUsage {
+    /// Index of the heap on the physical device
+    pub index: u32,
+
+    /// Size of the heap in bytes
+    pub size: usize,
+
+    /// Truth that the heap is device-local
+    pub device_local: bool,
+
+    /// Bytes which are known to be allocated from this heap
+    pub used: usize,
+
+    /// Number of live allocations which are known to use this heap
+    pub allocations: usize,
+}
+
+impl HeapUsage {
+    /// Fraction of the heap which is in use
+    pub fn usage_ratio(&self) -> f64 {
+        if self.size == 0 { return 0.0; }
+        self.used as f64 / self.size as f64
+    }
+}
+
+impl fmt::Display for HeapUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Heap {}{}: {} / {} bytes used ({:.1}%) by {} allocation(s)",
+               self.index,
+               if self.device_local { " (device-local)" } else { "" },
+               self.used,
+               self.size,
+               100.0 * self.usage_ratio(),
+               self.allocations)
+    }
+}
+
+
+/// Snapshot of the tracked memory usage of a device
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    /// Usage of each memory heap of the device
+    pub heaps: Vec<HeapUsage>,
+}
+
+impl fmt::Display for MemorySnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, heap) in self.heaps.iter().enumerate() {
+            if idx > 0 { writeln!(f)?; }
+            write!(f, "{}", heap)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Allocation record
+struct Allocation {
+    /// Weak reference to the allocated resource, used for liveness checks
+    resource: Weak<dyn Any + Send + Sync>,
+
+    /// Heap which the allocation comes from
+    heap: u32,
+
+    /// Size of the allocation in bytes
+    bytes: usize,
+}
+
+/// Mutable state of a MemoryTracker
+struct TrackerState {
+    /// Known allocations, including dead ones which were not pruned yet
+    allocations: Vec<Allocation>,
+
+    /// Heaps which were above the warning threshold last time we checked
+    warned_heaps: Vec<u32>,
+
+    /// Last time a periodic summary was logged
+    last_summary: Instant,
+}
+
+
+/// Tracker of the memory allocations of a device
+///
+/// Cloning the tracker gives you another handle to the same tracking state.
+///
+#[derive(Clone)]
+pub struct MemoryTracker {
+    /// Memory heaps of the device, as (index, size, device-local) triplets
+    heaps: Arc<Vec<(u32, usize, bool)>>,
+
+    /// Allocation tracking state
+    state: Arc<Mutex<TrackerState>>,
+}
+
+impl MemoryTracker {
+    /// Start tracking the memory allocations of a physical device
+    pub(crate) fn new(device: PhysicalDevice) -> Self {
+        let heaps = device.memory_heaps()
+                          .map(|heap| (heap.id(),
+                                       heap.size(),
+                                       heap.is_device_local()))
+                          .collect();
+        MemoryTracker {
+            heaps: Arc::new(heaps),
+            state: Arc::new(Mutex::new(TrackerState {
+                allocations: Vec::new(),
+                warned_heaps: Vec::new(),
+                last_summary: Instant::now(),
+            })),
+        }
+    }
+
+    /// Index of the first device-local memory heap, if any
+    pub fn device_local_heap(&self) -> Option<u32> {
+        self.heaps.iter()
+                  .find(|&&(_, _, device_local)| device_local)
+                  .map(|&(index, _, _)| index)
+    }
+
+    /// Index of the first memory heap which is not device-local, if any
+    ///
+    /// On discrete GPUs, this is where host-visible memory usually comes
+    /// from. Integrated GPUs may not have such a heap.
+    ///
+    pub fn host_heap(&self) -> Option<u32> {
+        self.heaps.iter()
+                  .find(|&&(_, _, device_local)| !device_local)
+                  .map(|&(index, _, _)| index)
+    }
+
+    /// Record an allocation, which is tracked until the resource is dropped
+    ///
+    /// Only EasyDevice::upload_buffer() does this automatically (see the
+    /// module documentation). Use this method to also account for the memory
+    /// which you allocate yourself, from the heap of its memory type.
+    ///
+    pub fn track<T>(&self, resource: &Arc<T>, heap: u32, bytes: usize)
+        where T: Any + Send + Sync
+    {
+        let resource: Arc<dyn Any + Send + Sync> = resource.clone();
+        let mut state = self.state.lock().unwrap();
+        state.allocations.push(Allocation {
+            resource: Arc::downgrade(&resource),
+            heap,
+            bytes,
+        });
+        debug!("Tracked a {}-byte allocation from memory heap {}",
+               bytes, heap);
+        self.check_usage(&mut state);
+    }
+
+    /// Snapshot of the current tracked memory usage
+    pub fn snapshot(&self) -> MemorySnapshot {
+        let mut state = self.state.lock().unwrap();
+        self.compute_snapshot(&mut state)
+    }
+
+    /// Log the current tracked memory usage
+    pub fn log_summary(&self) {
+        info!("Tracked GPU memory usage:");
+        for line in self.snapshot().to_string().lines() {
+            info!("    {}", line);
+        }
+    }
+
+    /// Compute a memory usage snapshot, forgetting about dead allocations
+    fn compute_snapshot(&self, state: &mut TrackerState) -> MemorySnapshot {
+        state.allocations.retain(|alloc| alloc.resource.upgrade().is_some());
+        let heaps = self.heaps.iter().map(|&(index, size, device_local)| {
+            let heap_allocs = state.allocations.iter()
+                                               .filter(|a| a.heap == index);
+            HeapUsage {
+                index,
+                size,
+                device_local,
+                used: heap_allocs.clone().map(|a| a.bytes).sum(),
+                allocations: heap_allocs.count(),
+            }
+        }).collect();
+        MemorySnapshot { heaps }
+    }
+
+    /// Warn about heaps which are almost full, and periodically log usage
+    fn check_usage(&self, state: &mut TrackerState) {
+        let snapshot = self.compute_snapshot(state);
+        for heap in &snapshot.heaps {
+            let is_full = heap.usage_ratio() >= WARNING_THRESHOLD;
+            let was_full = state.warned_heaps.contains(&heap.index);
+            if is_full && !was_full {
+                warn!("GPU memory heap is almost full! {}", heap);
+                state.warned_heaps.push(heap.index);
+            } else if !is_full && was_full {
+                state.warned_heaps.retain(|&index| index != heap.index);
+            }
+        }
+        if state.last_summary.elapsed() >= SUMMARY_PERIOD {
+            info!("Tracked GPU memory usage:");
+            for heap in &snapshot.heaps {
+                info!("    {}", heap);
+            }
+            state.last_summary = Instant::now();
+        }
+    }
+}
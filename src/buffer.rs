@@ -0,0 +1,309 @@
+//! Typed buffers with usage presets
+//!
+//! Vulkano's buffer types are very flexible, but that flexibility comes at the
+//! price of having to pick a buffer type, a set of usage flags and a memory
+//! type up front. EasyBuffer makes these choices for you based on what the
+//! buffer is going to be used for, and can be used wherever vulkano expects a
+//! buffer (vertex sources, index buffers, descriptor sets, copies...).
+
+use ::{
+    indirect::{
+        self,
+        DispatchIndirectCommand,
+        DrawIndirectCommand,
+    },
+    memory,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    iter,
+    result,
+    sync::Arc,
+};
+
+use vulkano::{
+    buffer::{
+        BufferAccess,
+        BufferInner,
+        BufferUsage,
+        CpuAccessibleBuffer,
+        DeviceLocalBuffer,
+        TypedBufferAccess,
+    },
+    device::{
+        Device,
+        DeviceOwned,
+        Queue,
+    },
+    image::ImageAccess,
+    pipeline::input_assembly::Index,
+    sync::AccessError,
+};
+
+
+/// Storage backing an EasyBuffer
+enum Storage<T: 'static> {
+    /// Device-local memory, accessed through staging buffers
+    DeviceLocal(Arc<DeviceLocalBuffer<[T]>>),
+
+    /// Host-visible memory, accessed directly
+    HostVisible(Arc<CpuAccessibleBuffer<[T]>>),
+}
+
+
+/// A typed buffer whose usage and memory type are picked for you
+///
+/// Vertex, index and storage buffers live in device-local memory, since the
+/// device accesses them a lot. Uniform buffers are small and frequently
+/// updated by the host, so they live in host-visible memory instead.
+///
+/// All buffers can be read back and overwritten, the transfers being carried
+/// out synchronously on the queue which was specified at creation time.
+///
+pub struct EasyBuffer<T: 'static> {
+    /// Underlying buffer
+    storage: Storage<T>,
+
+    /// Queue used for transfers
+    queue: Arc<Queue>,
+}
+
+impl<T> EasyBuffer<T>
+    where T: Copy + Send + Sync + 'static
+{
+    /// Create a vertex buffer, initialized with some data
+    pub fn vertex(queue: &Arc<Queue>, data: &[T]) -> Result<Arc<Self>> {
+        let _trace = trace::span("EasyBuffer::vertex",
+                                 || format!("elements: {}", data.len()));
+        Self::device_local(queue, data, BufferUsage::vertex_buffer())
+    }
+
+    /// Create a uniform buffer, initialized with some data
+    pub fn uniform(queue: &Arc<Queue>, data: &[T]) -> Result<Arc<Self>> {
+        let _trace = trace::span("EasyBuffer::uniform",
+                                 || format!("elements: {}", data.len()));
+        Self::host_visible(queue, data, BufferUsage::uniform_buffer())
+    }
+
+    /// Create a storage buffer, initialized with some data
+    pub fn storage(queue: &Arc<Queue>, data: &[T]) -> Result<Arc<Self>> {
+        let _trace = trace::span("EasyBuffer::storage",
+                                 || format!("elements: {}", data.len()));
+        Self::device_local(queue, data, BufferUsage::storage_buffer())
+    }
+
+    /// Number of elements in the buffer
+    pub fn len(&self) -> usize {
+        match self.storage {
+            Storage::DeviceLocal(ref buffer) => buffer.len(),
+            Storage::HostVisible(ref buffer) => buffer.len(),
+        }
+    }
+
+    /// Truth that the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read back the contents of the buffer
+    ///
+    /// This waits for the device to be done with the buffer.
+    ///
+    pub fn read(&self) -> Result<Vec<T>> {
+        let _trace = trace::span("EasyBuffer::read",
+                                 || format!("elements: {}", self.len()));
+        match self.storage {
+            Storage::DeviceLocal(ref buffer) => {
+                memory::easy_download_buffer(&self.queue, buffer)
+            },
+            Storage::HostVisible(ref buffer) => Ok(buffer.read()?.to_vec()),
+        }
+    }
+
+    /// Overwrite the contents of the buffer
+    ///
+    /// The data must have as many elements as the buffer. For host-visible
+    /// buffers, this fails if the device is currently using the buffer.
+    ///
+    pub fn write(&self, data: &[T]) -> Result<()> {
+        let _trace = trace::span("EasyBuffer::write",
+                                 || format!("elements: {}", data.len()));
+        if data.len() != self.len() {
+            return Err(Error::InvalidArgument(
+                format!("Tried to write {} elements to a buffer of {}",
+                        data.len(), self.len())
+            ));
+        }
+        match self.storage {
+            Storage::DeviceLocal(ref buffer) => {
+                let staging_buffer = CpuAccessibleBuffer::from_iter(
+                    self.queue.device().clone(),
+                    BufferUsage::transfer_source(),
+                    data.iter().cloned()
+                )?;
+                memory::run_copy(self.queue.device(),
+                                 &self.queue,
+                                 staging_buffer,
+                                 buffer.clone())
+            },
+            Storage::HostVisible(ref buffer) => {
+                buffer.write()?.copy_from_slice(data);
+                Ok(())
+            },
+        }
+    }
+
+    /// Create a device-local buffer, initialized with some data
+    fn device_local(queue: &Arc<Queue>,
+                    data: &[T],
+                    usage: BufferUsage) -> Result<Arc<Self>> {
+        let usage = BufferUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..usage
+        };
+        let buffer = DeviceLocalBuffer::array(queue.device().clone(),
+                                              data.len(),
+                                              usage,
+                                              iter::once(queue.family()))?;
+        let easy_buffer = EasyBuffer {
+            storage: Storage::DeviceLocal(buffer),
+            queue: queue.clone(),
+        };
+        easy_buffer.write(data)?;
+        Ok(Arc::new(easy_buffer))
+    }
+
+    /// Create a host-visible buffer, initialized with some data
+    fn host_visible(queue: &Arc<Queue>,
+                    data: &[T],
+                    usage: BufferUsage) -> Result<Arc<Self>> {
+        let usage = BufferUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..usage
+        };
+        let buffer = CpuAccessibleBuffer::from_iter(queue.device().clone(),
+                                                    usage,
+                                                    data.iter().cloned())?;
+        Ok(Arc::new(EasyBuffer {
+            storage: Storage::HostVisible(buffer),
+            queue: queue.clone(),
+        }))
+    }
+
+    /// Access the underlying buffer
+    fn access(&self) -> &dyn BufferAccess {
+        match self.storage {
+            Storage::DeviceLocal(ref buffer) => &**buffer,
+            Storage::HostVisible(ref buffer) => &**buffer,
+        }
+    }
+}
+
+impl<T> EasyBuffer<T>
+    where T: Index + Copy + Send + Sync + 'static
+{
+    /// Create an index buffer, initialized with some indices
+    pub fn index(queue: &Arc<Queue>, indices: &[T]) -> Result<Arc<Self>> {
+        let _trace = trace::span("EasyBuffer::index",
+                                 || format!("elements: {}", indices.len()));
+        Self::device_local(queue, indices, BufferUsage::index_buffer())
+    }
+}
+
+impl EasyBuffer<DrawIndirectCommand> {
+    /// Create a buffer of indirect draw commands
+    ///
+    /// The buffer can also be used as a storage buffer, so that shaders can
+    /// generate draw commands.
+    ///
+    pub fn draw_indirect(
+        queue: &Arc<Queue>,
+        commands: &[DrawIndirectCommand]
+    ) -> Result<Arc<Self>> {
+        let _trace = trace::span("EasyBuffer::draw_indirect",
+                                 || format!("commands: {}", commands.len()));
+        indirect::check_draw_count(queue.device(), commands.len())?;
+        Self::device_local(queue, commands, BufferUsage {
+            indirect_buffer: true,
+            ..BufferUsage::storage_buffer()
+        })
+    }
+}
+
+impl EasyBuffer<DispatchIndirectCommand> {
+    /// Create a buffer of indirect dispatch commands
+    ///
+    /// The buffer can also be used as a storage buffer, so that shaders can
+    /// generate dispatch commands.
+    ///
+    pub fn dispatch_indirect(
+        queue: &Arc<Queue>,
+        commands: &[DispatchIndirectCommand]
+    ) -> Result<Arc<Self>> {
+        let _trace = trace::span("EasyBuffer::dispatch_indirect",
+                                 || format!("commands: {}", commands.len()));
+        indirect::check_dispatch_commands(queue.device().physical_device(),
+                                          commands)?;
+        Self::device_local(queue, commands, BufferUsage {
+            indirect_buffer: true,
+            ..BufferUsage::storage_buffer()
+        })
+    }
+}
+
+unsafe impl<T> BufferAccess for EasyBuffer<T>
+    where T: Copy + Send + Sync + 'static
+{
+    fn inner(&self) -> BufferInner {
+        self.access().inner()
+    }
+
+    fn size(&self) -> usize {
+        self.access().size()
+    }
+
+    fn conflicts_buffer(&self, other: &dyn BufferAccess) -> bool {
+        self.access().conflicts_buffer(other)
+    }
+
+    fn conflicts_image(&self, other: &dyn ImageAccess) -> bool {
+        self.access().conflicts_image(other)
+    }
+
+    fn conflict_key(&self) -> (u64, usize) {
+        self.access().conflict_key()
+    }
+
+    fn try_gpu_lock(&self,
+                    exclusive_access: bool,
+                    queue: &Queue) -> result::Result<(), AccessError> {
+        self.access().try_gpu_lock(exclusive_access, queue)
+    }
+
+    unsafe fn increase_gpu_lock(&self) {
+        self.access().increase_gpu_lock()
+    }
+
+    unsafe fn unlock(&self) {
+        self.access().unlock()
+    }
+}
+
+unsafe impl<T> TypedBufferAccess for EasyBuffer<T>
+    where T: Copy + Send + Sync + 'static
+{
+    type Content = [T];
+}
+
+unsafe impl<T> DeviceOwned for EasyBuffer<T>
+    where T: Copy + Send + Sync + 'static
+{
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
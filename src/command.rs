@@ -0,0 +1,426 @@
+//! Conveniences for recording command buffers
+//!
+//! Command buffers must be allocated from a pool which belongs to the queue
+//! family of the queue that they will be submitted to. Getting this wrong
+//! results in cryptic validation errors, so EasyCommandPool ties command
+//! buffer creation to a specific queue.
+//!
+//! Some commands (queries, mesh draws...) cannot be recorded by vulkano's
+//! command buffer builders yet, and AutoCommandBufferBuilder does not expose
+//! the raw handle of the command buffer that it records. RawCommandBuffer
+//! provides a raw Vulkan command buffer for those commands, which is
+//! submitted on its own.
+//!
+//! This module also provides checked versions of some vulkano commands,
+//! which catch common mistakes before vulkano or the driver do.
+
+use ::{
+    error::check_result,
+    indirect::{
+        self,
+        DispatchIndirectCommand,
+        DrawIndirectCommand,
+    },
+    push_constants,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    mem,
+    ptr,
+    sync::Arc,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    buffer::{
+        BufferAccess,
+        TypedBufferAccess,
+    },
+    command_buffer::{
+        AutoCommandBufferBuilder,
+        CommandBuffer,
+        DynamicState,
+    },
+    descriptor::{
+        descriptor_set::{
+            DescriptorSet,
+            DescriptorSetsCollection,
+        },
+        pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::{
+        DeviceOwned,
+        Queue,
+    },
+    pipeline::{
+        vertex::VertexSource,
+        ComputePipelineAbstract,
+        GraphicsPipelineAbstract,
+    },
+    sync::GpuFuture,
+    VulkanObject,
+};
+
+
+/// Command buffer usage statistics of an EasyCommandPool
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommandPoolStats {
+    /// Number of frames which were completed (see EasyCommandPool::reset())
+    pub frames: u64,
+
+    /// Number of command buffers which were created in the current frame
+    pub frame_command_buffers: usize,
+
+    /// Largest number of command buffers created in a single frame
+    pub max_frame_command_buffers: usize,
+
+    /// Number of command buffers which were created since the pool was set up
+    pub total_command_buffers: usize,
+}
+
+
+/// A source of primary command buffers for a given queue
+///
+/// Under the hood, this uses vulkano's standard command pool for the queue's
+/// family, which recycles the memory of command buffers once they are dropped.
+/// What EasyCommandPool adds is the guarantee that command buffers are
+/// allocated from the right queue family, some per-frame bookkeeping, and
+/// usage statistics, which are logged when the pool is dropped.
+///
+pub struct EasyCommandPool {
+    /// Queue which command buffers will be submitted to
+    queue: Arc<Queue>,
+
+    /// Command buffer usage statistics
+    stats: CommandPoolStats,
+}
+
+impl EasyCommandPool {
+    /// Set up a command pool for a certain queue
+    pub fn new(queue: &Arc<Queue>) -> Self {
+        info!("Setting up a command pool for queue family {}",
+              queue.family().id());
+        EasyCommandPool {
+            queue: queue.clone(),
+            stats: CommandPoolStats::default(),
+        }
+    }
+
+    /// Queue which command buffers from this pool should be submitted to
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Start recording a command buffer which will be submitted once
+    pub fn primary(&mut self) -> Result<AutoCommandBufferBuilder> {
+        let _trace = trace::span("EasyCommandPool::primary", String::new);
+        self.count_command_buffer();
+        Ok(AutoCommandBufferBuilder::primary_one_time_submit(
+            self.queue.device().clone(),
+            self.queue.family()
+        )?)
+    }
+
+    /// Start recording a command buffer which can be submitted many times
+    pub fn primary_reusable(&mut self) -> Result<AutoCommandBufferBuilder> {
+        let _trace = trace::span("EasyCommandPool::primary_reusable",
+                                 String::new);
+        self.count_command_buffer();
+        Ok(AutoCommandBufferBuilder::primary_simultaneous_use(
+            self.queue.device().clone(),
+            self.queue.family()
+        )?)
+    }
+
+    /// Start recording a raw Vulkan command buffer, for commands which
+    /// vulkano cannot record yet
+    pub fn raw(&mut self) -> Result<RawCommandBuffer> {
+        let _trace = trace::span("EasyCommandPool::raw", String::new);
+        self.count_command_buffer();
+        RawCommandBuffer::new(&self.queue)
+    }
+
+    /// Build a recorded command buffer, submit it, and wait for it to finish
+    pub fn submit_and_wait(&self,
+                           builder: AutoCommandBufferBuilder) -> Result<()> {
+        let _trace = trace::span("EasyCommandPool::submit_and_wait",
+                                 String::new);
+        builder.build()?
+               .execute(self.queue.clone())?
+               .then_signal_fence_and_flush()?
+               .wait(None)?;
+        Ok(())
+    }
+
+    /// Mark the end of a frame
+    ///
+    /// Call this once per frame, after submitting the frame's command buffers,
+    /// so that per-frame statistics can be tracked. Unusually high command
+    /// buffer counts are a sign that command buffers should be reused.
+    ///
+    pub fn reset(&mut self) {
+        let stats = &mut self.stats;
+        debug!("Frame {} used {} command buffer(s) from queue family {}",
+               stats.frames,
+               stats.frame_command_buffers,
+               self.queue.family().id());
+        stats.max_frame_command_buffers =
+            stats.max_frame_command_buffers.max(stats.frame_command_buffers);
+        stats.frame_command_buffers = 0;
+        stats.frames += 1;
+    }
+
+    /// Command buffer usage statistics
+    pub fn stats(&self) -> CommandPoolStats {
+        self.stats
+    }
+
+    /// Account for the creation of a command buffer
+    fn count_command_buffer(&mut self) {
+        self.stats.frame_command_buffers += 1;
+        self.stats.total_command_buffers += 1;
+    }
+}
+
+impl Drop for EasyCommandPool {
+    /// Log the command buffer usage statistics
+    fn drop(&mut self) {
+        let stats = &self.stats;
+        info!("Command pool of queue family {} was dropped after creating {} \
+               command buffer(s) over {} frame(s), with at most {} per frame",
+              self.queue.family().id(),
+              stats.total_command_buffers,
+              stats.frames,
+              stats.max_frame_command_buffers.max(stats.frame_command_buffers));
+    }
+}
+
+
+/// Raw Vulkan command buffer, which is submitted once
+///
+/// This is how commands which vulkano cannot record yet (pipeline statistics
+/// and occlusion queries, mesh draws, acceleration structure builds...) get
+/// recorded: the command buffer starts in the recording state, handle() gives
+/// you the raw VkCommandBuffer to pass to the unsafe recording functions of
+/// vulkanoob or to raw Vulkan calls, and submit_and_wait() submits it.
+///
+/// The command buffer has its own transient command pool, which is destroyed
+/// when the RawCommandBuffer is dropped. Work recorded in vulkano command
+/// buffers must be synchronized with it by submission order, so this is best
+/// suited to self-contained work such as setup or measurements.
+///
+pub struct RawCommandBuffer {
+    /// Queue which the command buffer will be submitted to
+    queue: Arc<Queue>,
+
+    /// Transient command pool which the command buffer is allocated from
+    pool: vk::CommandPool,
+
+    /// Raw command buffer, in the recording state until submission
+    command_buffer: vk::CommandBuffer,
+}
+
+impl RawCommandBuffer {
+    /// Allocate a command buffer for a queue, and start recording it
+    pub fn new(queue: &Arc<Queue>) -> Result<Self> {
+        let _trace = trace::span("RawCommandBuffer::new", || {
+            format!("queue family: {}", queue.family().id())
+        });
+        let device = queue.device();
+        let fns = device.pointers();
+        let raw_device = device.internal_object();
+        let pool_info = vk::CommandPoolCreateInfo {
+            sType: vk::STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: vk::COMMAND_POOL_CREATE_TRANSIENT_BIT,
+            queueFamilyIndex: queue.family().id(),
+        };
+        let mut raw = RawCommandBuffer {
+            queue: queue.clone(),
+            pool: 0,
+            command_buffer: 0,
+        };
+        unsafe {
+            check_result(fns.CreateCommandPool(raw_device,
+                                               &pool_info,
+                                               ptr::null(),
+                                               &mut raw.pool),
+                         "vkCreateCommandPool")?;
+            let alloc_info = vk::CommandBufferAllocateInfo {
+                sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO,
+                pNext: ptr::null(),
+                commandPool: raw.pool,
+                level: vk::COMMAND_BUFFER_LEVEL_PRIMARY,
+                commandBufferCount: 1,
+            };
+            check_result(fns.AllocateCommandBuffers(raw_device,
+                                                    &alloc_info,
+                                                    &mut raw.command_buffer),
+                         "vkAllocateCommandBuffers")?;
+            let begin_info = vk::CommandBufferBeginInfo {
+                sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
+                pNext: ptr::null(),
+                flags: vk::COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT_BIT,
+                pInheritanceInfo: ptr::null(),
+            };
+            check_result(fns.BeginCommandBuffer(raw.command_buffer,
+                                                &begin_info),
+                         "vkBeginCommandBuffer")?;
+        }
+        Ok(raw)
+    }
+
+    /// Raw handle of the command buffer, which is in the recording state
+    pub fn handle(&self) -> vk::CommandBuffer {
+        self.command_buffer
+    }
+
+    /// Queue which the command buffer will be submitted to
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// End recording, submit the command buffer, and wait for it to finish
+    ///
+    /// Any resource used by the recorded commands must be kept alive until
+    /// this function returns.
+    ///
+    pub fn submit_and_wait(self) -> Result<()> {
+        let _trace = trace::span("RawCommandBuffer::submit_and_wait",
+                                 String::new);
+        let fns = self.queue.device().pointers();
+        unsafe {
+            check_result(fns.EndCommandBuffer(self.command_buffer),
+                         "vkEndCommandBuffer")?;
+            let submit_info = vk::SubmitInfo {
+                sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+                pNext: ptr::null(),
+                waitSemaphoreCount: 0,
+                pWaitSemaphores: ptr::null(),
+                pWaitDstStageMask: ptr::null(),
+                commandBufferCount: 1,
+                pCommandBuffers: &self.command_buffer,
+                signalSemaphoreCount: 0,
+                pSignalSemaphores: ptr::null(),
+            };
+            let raw_queue = self.queue.internal_object_guard();
+            check_result(fns.QueueSubmit(*raw_queue, 1, &submit_info, 0),
+                         "vkQueueSubmit")?;
+            check_result(fns.QueueWaitIdle(*raw_queue), "vkQueueWaitIdle")
+        }
+    }
+}
+
+impl Drop for RawCommandBuffer {
+    /// Destroy the command pool, which frees the command buffer
+    fn drop(&mut self) {
+        if self.pool == 0 { return; }
+        let device = self.queue.device();
+        unsafe {
+            device.pointers().DestroyCommandPool(device.internal_object(),
+                                                 self.pool,
+                                                 ptr::null());
+        }
+    }
+}
+
+
+/// Record an indirect draw, after checking it
+///
+/// This works like vulkano's draw_indirect(), which draws every command of
+/// the indirect buffer, but first checks the number of commands against the
+/// device's limits (see indirect::check_draw_count()), and the size of the
+/// push constants against the pipeline layout.
+///
+pub fn easy_draw_indirect<V, Gp, S, Pc, Ib>(
+    builder: AutoCommandBufferBuilder,
+    pipeline: Gp,
+    dynamic: DynamicState,
+    vertices: V,
+    commands: Ib,
+    sets: S,
+    constants: Pc
+) -> Result<AutoCommandBufferBuilder>
+    where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync
+              + Clone + 'static,
+          S: DescriptorSetsCollection,
+          Ib: BufferAccess + TypedBufferAccess<Content=[DrawIndirectCommand]>
+              + Send + Sync + 'static
+{
+    let _trace = trace::span("command::easy_draw_indirect",
+                             || format!("commands: {}", commands.len()));
+    indirect::check_draw_count(commands.device(), commands.len())?;
+    push_constants::check_push_constants::<Pc>(&pipeline)?;
+    Ok(builder.draw_indirect(pipeline,
+                             dynamic,
+                             vertices,
+                             commands,
+                             sets,
+                             constants)?)
+}
+
+/// Record an indirect dispatch into a raw command buffer, after checking it
+///
+/// The version of vulkano that we use cannot record indirect dispatches with
+/// AutoCommandBufferBuilder, so this binds the compute pipeline and the
+/// descriptor sets (starting at set 0), then dispatches the command at some
+/// index of the indirect buffer.
+///
+/// # Safety
+///
+/// The command buffer must belong to the pipeline's device. The pipeline,
+/// descriptor sets and indirect buffer must stay alive until the command
+/// buffer is done executing, and any access to them must be synchronized
+/// with it by submission order. The indirect command must respect the
+/// device's limits, see indirect::check_dispatch_commands().
+///
+pub unsafe fn easy_dispatch_indirect<Cp, Ib>(
+    command_buffer: &RawCommandBuffer,
+    pipeline: &Cp,
+    sets: &[&dyn DescriptorSet],
+    commands: &Ib,
+    index: usize
+) -> Result<()>
+    where Cp: ComputePipelineAbstract,
+          Ib: BufferAccess
+              + TypedBufferAccess<Content=[DispatchIndirectCommand]>
+{
+    let _trace = trace::span("command::easy_dispatch_indirect", || {
+        format!("sets: {}, index: {}", sets.len(), index)
+    });
+    if index >= commands.len() {
+        return Err(Error::InvalidArgument(format!(
+            "Dispatch command #{} is out of bounds, the indirect buffer only \
+             holds {} command(s)", index, commands.len()
+        )));
+    }
+    let fns = pipeline.device().pointers();
+    let raw_command_buffer = command_buffer.handle();
+    fns.CmdBindPipeline(raw_command_buffer,
+                        vk::PIPELINE_BIND_POINT_COMPUTE,
+                        pipeline.inner().internal_object());
+    if !sets.is_empty() {
+        let raw_sets = sets.iter()
+                           .map(|set| set.inner().internal_object())
+                           .collect::<Vec<_>>();
+        fns.CmdBindDescriptorSets(raw_command_buffer,
+                                  vk::PIPELINE_BIND_POINT_COMPUTE,
+                                  pipeline.sys().internal_object(),
+                                  0,
+                                  raw_sets.len() as u32,
+                                  raw_sets.as_ptr(),
+                                  0,
+                                  ptr::null());
+    }
+    let inner = commands.inner();
+    let offset = inner.offset
+                 + index * mem::size_of::<DispatchIndirectCommand>();
+    fns.CmdDispatchIndirect(raw_command_buffer,
+                            inner.buffer.internal_object(),
+                            offset as u64);
+    Ok(())
+}
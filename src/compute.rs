@@ -0,0 +1,956 @@
+//! Conveniences for running compute shaders
+//!
+//! The EasyCompute type covers the common prototyping task of running a
+//! compute shader on some data: give it a SPIR-V compute shader and a
+//! description of its descriptor layout, then dispatch it on your buffers.
+//! EasyComputeBatch chains several such dispatches in a single submission.
+//!
+//! Shaders which take push constants are supported too: declare them in the
+//! EasyComputeLayout, then provide their value when dispatching. Shaders with
+//! specialization constants, e.g. for their work group size, can be built
+//! with EasyCompute::with_specialization(), and choose_workgroup_size() helps
+//! picking a work group size which fits the device.
+
+use ::{
+    descriptor::{
+        EasyDescriptorSet,
+        EasyDescriptorSetBuilder,
+    },
+    easy_device_filter,
+    push_constants::{
+        self,
+        EasyPushConstants,
+    },
+    specialization::{
+        self,
+        SpecializationConstants,
+    },
+    subgroup::SubgroupProperties,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    ffi::CString,
+    sync::Arc,
+};
+
+use vulkano::{
+    buffer::BufferAccess,
+    command_buffer::{
+        AutoCommandBufferBuilder,
+        CommandBuffer,
+    },
+    descriptor::{
+        descriptor::{
+            DescriptorBufferDesc,
+            DescriptorDesc,
+            DescriptorDescTy,
+            ShaderStages,
+        },
+        descriptor_set::{
+            DescriptorSetsCollection,
+            PersistentDescriptorSet,
+            PersistentDescriptorSetBuilder,
+        },
+        pipeline_layout::{
+            PipelineLayout,
+            PipelineLayoutDesc,
+            PipelineLayoutDescPcRange,
+        },
+    },
+    device::{
+        Device,
+        DeviceExtensions,
+        Queue,
+    },
+    instance::{
+        Features,
+        PhysicalDevice,
+    },
+    pipeline::{
+        shader::ShaderModule,
+        ComputePipeline,
+    },
+    sync::GpuFuture,
+};
+
+
+/// Helper for building device filters for compute-only applications
+///
+/// This works like easy_device_filter(), but the queue family criterion is
+/// fixed to requiring support for compute operations.
+///
+pub fn easy_compute_filter<'a>(
+    features: &'a Features,
+    extensions: &'a DeviceExtensions,
+    other_criteria: impl FnMut(PhysicalDevice) -> bool + 'a
+) -> impl FnMut(PhysicalDevice) -> bool + 'a {
+    easy_device_filter(features,
+                       extensions,
+                       |family| family.supports_compute(),
+                       other_criteria)
+}
+
+/// Number of invocations per work group that choose_workgroup_size() aims at
+///
+/// This is large enough to keep the GPU busy and hide latency, and small
+/// enough to fit the invocation limits of every Vulkan implementation.
+///
+pub const DEFAULT_TARGET_INVOCATIONS: u32 = 256;
+
+/// Tuning knobs of choose_workgroup_size_with()
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkgroupSizeOptions {
+    /// Number of invocations per work group to aim at, as an upper bound
+    pub target_invocations: u32,
+
+    /// Amount of shared memory used per invocation, in bytes
+    pub shared_memory_per_invocation: u32,
+
+    /// Subgroup size to which the number of invocations should be a multiple
+    ///
+    /// If this is None, the subgroup size of the device is queried, using the
+    /// largest size which compute shaders may use when the device supports
+    /// VK_EXT_subgroup_size_control (see SubgroupProperties::max_compute_size),
+    /// and the number of invocations is not rounded if the size is unknown.
+    /// With VK_EXT_subgroup_size_control, shaders can then require full
+    /// subgroups, which is needed by some subgroup algorithms.
+    ///
+    pub subgroup_size: Option<u32>,
+}
+
+impl Default for WorkgroupSizeOptions {
+    fn default() -> Self {
+        WorkgroupSizeOptions {
+            target_invocations: DEFAULT_TARGET_INVOCATIONS,
+            shared_memory_per_invocation: 0,
+            subgroup_size: None,
+        }
+    }
+}
+
+/// Pick a work group size for a problem of a certain shape, which fits the
+/// compute limits of a device
+///
+/// See choose_workgroup_size_with() for details.
+///
+pub fn choose_workgroup_size(device: PhysicalDevice,
+                             problem_shape: [u32; 3]) -> Result<[u32; 3]> {
+    choose_workgroup_size_with(device,
+                               problem_shape,
+                               &WorkgroupSizeOptions::default())
+}
+
+/// Pick a work group size for a problem of a certain shape, with some tuning
+///
+/// The work group dimensions are powers of two, which are spread across the
+/// problem dimensions in proportion to their extent, and clamped to it. The
+/// number of invocations is a multiple of the subgroup size (see
+/// WorkgroupSizeOptions), unless the whole problem has less elements than a
+/// subgroup, which may mean not clamping some dimensions to the problem.
+/// The device's max_compute_work_group_size, max_compute_work_group_invocations
+/// and max_compute_shared_memory_size limits are respected, and so is the
+/// maximal number of subgroups per work group of VK_EXT_subgroup_size_control.
+/// Pass the result to your shader, e.g. through specialization constants, and
+/// use work_group_count() to compute the matching dispatch size.
+///
+pub fn choose_workgroup_size_with(
+    device: PhysicalDevice,
+    problem_shape: [u32; 3],
+    options: &WorkgroupSizeOptions
+) -> Result<[u32; 3]> {
+    let limits = WorkgroupLimits::of_device(device);
+    let size = pick_workgroup_size(&limits, problem_shape, options)?;
+    debug!("Picked work group size {:?} for problem shape {:?} on device \
+            \"{}\"", size, problem_shape, limits.device_name);
+    Ok(size)
+}
+
+/// Number of work groups needed to cover a problem with some work group size
+///
+/// Every dimension of the work group size must be nonzero.
+///
+pub fn work_group_count(problem_shape: [u32; 3],
+                        workgroup_size: [u32; 3]) -> Result<[u32; 3]> {
+    if workgroup_size.iter().any(|&extent| extent == 0) {
+        return Err(Error::InvalidArgument(
+            format!("Work group size {:?} is empty", workgroup_size)
+        ));
+    }
+    let count = |dim: usize| {
+        let (problem, size) = (problem_shape[dim], workgroup_size[dim]);
+        problem / size + (problem % size != 0) as u32
+    };
+    Ok([count(0), count(1), count(2)])
+}
+
+
+/// Device limits which constrain the choice of a work group size
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct WorkgroupLimits {
+    /// Name of the device, for error messages and logs
+    device_name: String,
+
+    /// Maximal work group size along each dimension
+    max_size: [u32; 3],
+
+    /// Maximal number of invocations per work group
+    max_invocations: u32,
+
+    /// Maximal amount of shared memory per work group, in bytes
+    max_shared_memory: u32,
+
+    /// Subgroup size of compute shaders, if known
+    subgroup_size: Option<u32>,
+
+    /// Maximal number of subgroups per work group, if known
+    max_subgroups: Option<u32>,
+}
+
+impl WorkgroupLimits {
+    /// Query the limits of a device
+    fn of_device(device: PhysicalDevice) -> Self {
+        let limits = device.limits();
+        let subgroup = SubgroupProperties::of_device(device);
+        WorkgroupLimits {
+            device_name: device.name(),
+            max_size: limits.max_compute_work_group_size(),
+            max_invocations: limits.max_compute_work_group_invocations(),
+            max_shared_memory: limits.max_compute_shared_memory_size(),
+            subgroup_size: subgroup.map(|subgroup| subgroup.max_compute_size()),
+            max_subgroups: subgroup.and_then(|subgroup| subgroup.size_control)
+                .map(|control| control.max_compute_workgroup_subgroups),
+        }
+    }
+}
+
+/// Device-independent part of choose_workgroup_size_with()
+fn pick_workgroup_size(limits: &WorkgroupLimits,
+                       problem_shape: [u32; 3],
+                       options: &WorkgroupSizeOptions) -> Result<[u32; 3]> {
+    if problem_shape.iter().any(|&extent| extent == 0) {
+        return Err(Error::InvalidArgument(
+            format!("Problem shape {:?} is empty", problem_shape)
+        ));
+    }
+
+    // Decide how many invocations a work group may have
+    let max_size = limits.max_size;
+    let mut max_invocations =
+        options.target_invocations.min(limits.max_invocations).max(1);
+    if options.shared_memory_per_invocation > 0 {
+        let max_shared = limits.max_shared_memory
+                         / options.shared_memory_per_invocation;
+        if max_shared == 0 {
+            return Err(Error::InvalidArgument(format!(
+                "A single invocation needs {} bytes of shared memory, but \
+                 device \"{}\" only has {}",
+                options.shared_memory_per_invocation,
+                limits.device_name,
+                limits.max_shared_memory
+            )));
+        }
+        max_invocations = max_invocations.min(max_shared);
+    }
+    let subgroup_size = options.subgroup_size
+                               .or(limits.subgroup_size)
+                               .filter(|&size| size > 0);
+    if let Some(subgroup_size) = subgroup_size {
+        let max_subgroups =
+            limits.max_subgroups.filter(|&max_subgroups| max_subgroups > 0);
+        if let Some(max_subgroups) = max_subgroups {
+            max_invocations = max_invocations.min(
+                max_subgroups.saturating_mul(subgroup_size)
+            );
+        }
+        if max_invocations >= subgroup_size {
+            max_invocations -= max_invocations % subgroup_size;
+        }
+    }
+
+    // Repeatedly double the dimension which covers the least of the problem
+    let mut size = [1u32; 3];
+    loop {
+        let invocations = size.iter().product::<u32>();
+        let best_dim = (0..3).filter(|&dim| {
+            size[dim] < problem_shape[dim]
+                && size[dim] * 2 <= max_size[dim]
+                && invocations * 2 <= max_invocations
+        }).max_by(|&dim1, &dim2| {
+            let ratio = |dim: usize| {
+                problem_shape[dim] as f64 / size[dim] as f64
+            };
+            ratio(dim1).partial_cmp(&ratio(dim2))
+                       .expect("Ratios should not be NaN")
+                       .then(dim2.cmp(&dim1))
+        });
+        match best_dim {
+            Some(dim) => size[dim] *= 2,
+            None => break,
+        }
+    }
+
+    // The last doubling of a dimension may overshoot the problem, so clamp
+    // the dimensions to it, unless that breaks up subgroups. Since subgroup
+    // sizes are powers of two, so are the unclamped invocation counts, which
+    // are thus multiples of the subgroup size whenever they are large enough.
+    let is_whole_subgroups = |size: &[u32; 3]| {
+        subgroup_size.map_or(true, |subgroup_size| {
+            size.iter().product::<u32>() % subgroup_size == 0
+        })
+    };
+    let mut clamped = size;
+    for dim in 0..3 {
+        clamped[dim] = size[dim].min(problem_shape[dim]);
+    }
+    let problem_size = problem_shape.iter()
+                                    .fold(1u64, |acc, &x| acc * u64::from(x));
+    let fills_subgroups = subgroup_size.map_or(false, |subgroup_size| {
+        problem_size >= u64::from(subgroup_size)
+    });
+    if fills_subgroups && !is_whole_subgroups(&clamped) {
+        for dim in 0..3 {
+            if is_whole_subgroups(&clamped) { break; }
+            clamped[dim] = size[dim];
+        }
+    }
+    Ok(clamped)
+}
+
+
+/// Description of the descriptor layout of a compute shader
+///
+/// Vulkano normally gets this information from its shader compiler, which
+/// vulkanoob cannot use on user-provided SPIR-V, so you need to state it.
+///
+#[derive(Clone, Debug)]
+pub struct EasyComputeLayout {
+    /// Descriptors of each descriptor set, by binding number
+    sets: Vec<Vec<DescriptorDesc>>,
+
+    /// Push constant range, if the shader uses push constants
+    push_constants: Option<PipelineLayoutDescPcRange>,
+}
+
+impl EasyComputeLayout {
+    /// Describe an arbitrary descriptor layout, as a list of descriptor sets
+    pub fn new(sets: Vec<Vec<DescriptorDesc>>) -> Self {
+        EasyComputeLayout {
+            sets,
+            push_constants: None,
+        }
+    }
+
+    /// Describe the common layout where a shader accesses a number of storage
+    /// buffers through descriptor set 0, at bindings 0, 1, 2...
+    pub fn storage_buffers(count: usize) -> Self {
+        let storage_buffer = DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(false),
+                storage: true,
+            }),
+            array_count: 1,
+            stages: ShaderStages::compute(),
+            readonly: false,
+        };
+        Self::new(vec![vec![storage_buffer; count]])
+    }
+
+    /// Declare that the shader uses a push constant block, stored in a T
+    ///
+    /// You will then need to provide the push constants when dispatching, see
+    /// EasyCompute::run_with_push_constants() for example.
+    ///
+    pub fn with_push_constants<T: Copy>(mut self) -> Self {
+        let push_constants =
+            EasyPushConstants::<T>::new(ShaderStages::compute());
+        self.push_constants = Some(push_constants.range());
+        self
+    }
+}
+
+unsafe impl PipelineLayoutDesc for EasyComputeLayout {
+    fn num_sets(&self) -> usize {
+        self.sets.len()
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        self.sets.get(set).map(|bindings| bindings.len())
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.sets.get(set).and_then(|bindings| bindings.get(binding)).cloned()
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        self.push_constants.iter().count()
+    }
+
+    fn push_constants_range(&self,
+                            num: usize) -> Option<PipelineLayoutDescPcRange> {
+        if num == 0 { self.push_constants } else { None }
+    }
+}
+
+
+/// Compute pipeline type built by EasyCompute
+pub type EasyComputePipeline =
+    ComputePipeline<PipelineLayout<EasyComputeLayout>>;
+
+
+/// A compute pipeline which can be run synchronously with minimal fuss
+pub struct EasyCompute {
+    /// Device that the pipeline runs on
+    device: Arc<Device>,
+
+    /// Queue which compute work is submitted to
+    queue: Arc<Queue>,
+
+    /// Compute pipeline
+    pipeline: Arc<EasyComputePipeline>,
+}
+
+impl EasyCompute {
+    /// Build a compute pipeline from SPIR-V code
+    ///
+    /// The shader's entry point is expected to be called "main", which is
+    /// what GLSL compilers produce.
+    ///
+    /// # Safety
+    ///
+    /// The SPIR-V code must be a valid compute shader, and the layout must
+    /// match what the shader actually expects. Vulkano cannot check this.
+    ///
+    pub unsafe fn new(device: &Arc<Device>,
+                      queue: &Arc<Queue>,
+                      spirv: &[u8],
+                      layout: EasyComputeLayout) -> Result<Self> {
+        Self::with_specialization(device, queue, spirv, layout, &())
+    }
+
+    /// Like new(), but also sets some of the shader's specialization constants
+    ///
+    /// The constants are typically declared with the
+    /// easy_specialization_constants! macro, and are checked against those
+    /// which the shader declares.
+    ///
+    /// # Safety
+    ///
+    /// Same as new().
+    ///
+    pub unsafe fn with_specialization<S: SpecializationConstants>(
+        device: &Arc<Device>,
+        queue: &Arc<Queue>,
+        spirv: &[u8],
+        layout: EasyComputeLayout,
+        constants: &S
+    ) -> Result<Self> {
+        let _trace = trace::span("EasyCompute::with_specialization",
+                                 || format!("spirv: {} bytes, layout: {:?}",
+                                            spirv.len(), layout));
+        if !queue.family().supports_compute() {
+            return Err(Error::InvalidArgument(
+                "The specified queue does not support compute".to_owned()
+            ));
+        }
+        push_constants::check_push_constants_limits(device.physical_device(),
+                                                    &layout)?;
+        specialization::check_specialization_constants::<S>(spirv)?;
+        let module = ShaderModule::new(device.clone(), spirv)?;
+        let entry_point_name = CString::new("main")?;
+        let entry_point =
+            module.compute_entry_point::<S, _>(&entry_point_name, layout);
+        let pipeline = ComputePipeline::new(device.clone(),
+                                            &entry_point,
+                                            constants)?;
+        Ok(EasyCompute {
+            device: device.clone(),
+            queue: queue.clone(),
+            pipeline: Arc::new(pipeline),
+        })
+    }
+
+    /// Access the inner compute pipeline
+    pub fn pipeline(&self) -> &Arc<EasyComputePipeline> {
+        &self.pipeline
+    }
+
+    /// Start building a descriptor set for this pipeline
+    ///
+    /// Add your buffers to the builder, then call build() and pass the result
+    /// to run(). The easy_compute_run! macro does all of this for you in the
+    /// common case of a single descriptor set full of buffers.
+    ///
+    pub fn descriptor_set(
+        &self,
+        set: usize
+    ) -> PersistentDescriptorSetBuilder<Arc<EasyComputePipeline>, ()> {
+        PersistentDescriptorSet::start(self.pipeline.clone(), set)
+    }
+
+    /// Run the compute shader and wait for it to complete
+    ///
+    /// The dimensions are given in work groups, as in Vulkan's dispatch. If
+    /// the shader uses push constants, use run_with_push_constants() instead.
+    ///
+    pub fn run(&self,
+               work_groups: [u32; 3],
+               sets: impl DescriptorSetsCollection) -> Result<()> {
+        self.run_with_push_constants(work_groups, sets, ())
+    }
+
+    /// Like run(), but also sets the shader's push constants
+    ///
+    /// The push constants must have the type which was declared in the
+    /// EasyComputeLayout, or at least a type of the same size.
+    ///
+    pub fn run_with_push_constants<T: Copy>(
+        &self,
+        work_groups: [u32; 3],
+        sets: impl DescriptorSetsCollection,
+        constants: T
+    ) -> Result<()> {
+        let _trace = trace::span("EasyCompute::run_with_push_constants",
+                                 || format!("work_groups: {:?}", work_groups));
+        push_constants::check_push_constants::<T>(&*self.pipeline)?;
+        let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(
+            self.device.clone(),
+            self.queue.family()
+        )?.dispatch(work_groups, self.pipeline.clone(), sets, constants)?
+          .build()?;
+        command_buffer.execute(self.queue.clone())?
+                      .then_signal_fence_and_flush()?
+                      .wait(None)?;
+        Ok(())
+    }
+}
+
+
+/// Run an EasyCompute pipeline on a list of buffers, then wait for it
+///
+/// The buffers are bound to descriptor set 0, at bindings 0, 1, 2... which is
+/// the layout described by EasyComputeLayout::storage_buffers(). This macro
+/// evaluates to a vulkanoob::Result<()>.
+///
+/// ```ignore
+/// easy_compute_run!(compute, [64, 1, 1], input_buffer, output_buffer)?;
+/// ```
+///
+#[macro_export]
+macro_rules! easy_compute_run {
+    ($compute:expr, $work_groups:expr, $($buffer:expr),+ $(,)*) => {
+        (|| -> $crate::Result<()> {
+            let compute = &$compute;
+            let set = compute.descriptor_set(0)
+                             $( .add_buffer($buffer.clone())? )+
+                             .build()?;
+            compute.run($work_groups, ::std::sync::Arc::new(set))
+        })()
+    };
+}
+
+
+/// A chain of compute dispatches, recorded into a single command buffer
+///
+/// Each dispatch declares which buffers it reads and writes. These buffers are
+/// bound to descriptor set 0 of the pipeline, at bindings 0, 1, 2... in the
+/// order where they were declared, as in easy_compute_run!.
+///
+/// Vulkano tracks the buffers of each dispatch and inserts the pipeline
+/// barriers that are needed between dependent dispatches. The batch also
+/// keeps track of which dispatch depends on which, so that you can check that
+/// your dispatch chain does what you think it does.
+///
+/// ```ignore
+/// let mut batch = EasyComputeBatch::new(&device, &queue)?;
+/// batch.dispatch(&blur, [64, 64, 1]).read(&image)?.write(&tmp)?.record()?;
+/// batch.dispatch(&sharpen, [64, 64, 1]).read(&tmp)?.write(&out)?.record()?;
+/// batch.run()?;
+/// ```
+///
+pub struct EasyComputeBatch {
+    /// Queue which the batch is submitted to
+    queue: Arc<Queue>,
+
+    /// Command buffer being recorded, or None if recording a dispatch failed
+    /// and the command buffer was lost
+    builder: Option<AutoCommandBufferBuilder>,
+
+    /// Dispatches recorded so far
+    dispatches: Vec<DispatchRecord>,
+}
+
+/// Records a dispatch, along with its push constants, into a command buffer
+struct Dispatcher {
+    /// Checks that the push constants fit the pipeline's layout, before the
+    /// command buffer is handed over to the recording closure
+    check: fn(&EasyComputePipeline) -> Result<()>,
+
+    /// Records the dispatch
+    record: Box<dyn FnOnce(AutoCommandBufferBuilder,
+                           [u32; 3],
+                           Arc<EasyComputePipeline>,
+                           EasyDescriptorSet)
+                           -> Result<AutoCommandBufferBuilder>>,
+}
+
+/// Build a Dispatcher which sets some push constants
+fn dispatcher<T: Copy + 'static>(constants: T) -> Dispatcher {
+    Dispatcher {
+        check: |pipeline| push_constants::check_push_constants::<T>(pipeline),
+        record: Box::new(move |builder, work_groups, pipeline, set| {
+            Ok(builder.dispatch(work_groups,
+                                pipeline,
+                                Arc::new(set),
+                                constants)?)
+        }),
+    }
+}
+
+/// Buffer accesses of a recorded dispatch
+struct DispatchRecord {
+    /// Buffers which the dispatch reads, by conflict key
+    reads: Vec<(u64, usize)>,
+
+    /// Buffers which the dispatch writes, by conflict key
+    writes: Vec<(u64, usize)>,
+
+    /// Previous dispatches which this dispatch depends on
+    dependencies: Vec<usize>,
+}
+
+impl EasyComputeBatch {
+    /// Start recording a batch of compute dispatches
+    pub fn new(device: &Arc<Device>, queue: &Arc<Queue>) -> Result<Self> {
+        let _trace = trace::span("EasyComputeBatch::new", String::new);
+        if !queue.family().supports_compute() {
+            return Err(Error::InvalidArgument(
+                "The specified queue does not support compute".to_owned()
+            ));
+        }
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            device.clone(),
+            queue.family()
+        )?;
+        Ok(EasyComputeBatch {
+            queue: queue.clone(),
+            builder: Some(builder),
+            dispatches: Vec::new(),
+        })
+    }
+
+    /// Start declaring a dispatch of some compute pipeline
+    ///
+    /// The dimensions are given in work groups, as in Vulkan's dispatch. The
+    /// dispatch is only added to the batch once record() is called.
+    ///
+    pub fn dispatch<'a>(&'a mut self,
+                        compute: &EasyCompute,
+                        work_groups: [u32; 3]) -> BatchDispatch<'a> {
+        let pipeline = compute.pipeline().clone();
+        let set = EasyDescriptorSet::start(pipeline.clone(), 0);
+        BatchDispatch {
+            batch: self,
+            pipeline,
+            work_groups,
+            set,
+            dispatcher: dispatcher(()),
+            next_binding: 0,
+            reads: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Number of dispatches recorded so far
+    pub fn len(&self) -> usize {
+        self.dispatches.len()
+    }
+
+    /// Truth that no dispatch was recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.dispatches.is_empty()
+    }
+
+    /// Previous dispatches which a dispatch depends on, by index
+    ///
+    /// A dispatch depends on an earlier one if it reads a buffer which the
+    /// earlier one writes, or writes a buffer which the earlier one accesses.
+    ///
+    pub fn dependencies(&self, dispatch: usize) -> &[usize] {
+        &self.dispatches[dispatch].dependencies
+    }
+
+    /// Submit the whole batch and wait for it to complete
+    ///
+    /// This fails if recording one of the dispatches failed after the
+    /// command buffer was handed over to vulkano, as the command buffer is
+    /// lost in this case.
+    ///
+    pub fn run(mut self) -> Result<()> {
+        let _trace = trace::span("EasyComputeBatch::run",
+                                 || format!("dispatches: {}",
+                                            self.dispatches.len()));
+        for (index, dispatch) in self.dispatches.iter().enumerate() {
+            debug!("Compute dispatch #{} depends on {:?}",
+                   index, dispatch.dependencies);
+        }
+        let command_buffer = self.take_builder()?.build()?;
+        command_buffer.execute(self.queue.clone())?
+                      .then_signal_fence_and_flush()?
+                      .wait(None)?;
+        Ok(())
+    }
+
+    /// Record a dispatch whose descriptor set has been built
+    fn record(&mut self,
+              pipeline: Arc<EasyComputePipeline>,
+              work_groups: [u32; 3],
+              set: EasyDescriptorSet,
+              dispatcher: Dispatcher,
+              reads: Vec<(u64, usize)>,
+              writes: Vec<(u64, usize)>) -> Result<()> {
+        // Find which previous dispatches this one depends on
+        let mut dependencies = Vec::new();
+        for (index, previous) in self.dispatches.iter().enumerate() {
+            let read_after_write =
+                reads.iter().any(|key| previous.writes.contains(key));
+            let write_after_access = writes.iter().any(|key| {
+                previous.writes.contains(key) || previous.reads.contains(key)
+            });
+            if read_after_write || write_after_access {
+                dependencies.push(index);
+            }
+        }
+
+        // Record the dispatch, vulkano takes care of the barriers
+        (dispatcher.check)(&pipeline)?;
+        let builder = self.take_builder()?;
+        self.builder = Some((dispatcher.record)(builder,
+                                                work_groups,
+                                                pipeline,
+                                                set)?);
+        self.dispatches.push(DispatchRecord {
+            reads,
+            writes,
+            dependencies,
+        });
+        Ok(())
+    }
+
+    /// Take the command buffer builder out of the batch
+    ///
+    /// Vulkano consumes the builder when recording commands, and does not give
+    /// it back if that fails. The batch cannot be used anymore after that.
+    ///
+    fn take_builder(&mut self) -> Result<AutoCommandBufferBuilder> {
+        self.builder.take().ok_or_else(|| Error::Other(format_err!(
+            "A previous dispatch of this compute batch failed to record, so \
+             its command buffer was lost"
+        )))
+    }
+}
+
+
+/// A compute dispatch being declared, see EasyComputeBatch::dispatch()
+pub struct BatchDispatch<'a> {
+    /// Batch which the dispatch will be added to
+    batch: &'a mut EasyComputeBatch,
+
+    /// Pipeline to be dispatched
+    pipeline: Arc<EasyComputePipeline>,
+
+    /// Dimensions of the dispatch, in work groups
+    work_groups: [u32; 3],
+
+    /// Descriptor set being built
+    set: Result<EasyDescriptorSetBuilder<Arc<EasyComputePipeline>>>,
+
+    /// Records the dispatch with its push constants
+    dispatcher: Dispatcher,
+
+    /// Binding of the next declared buffer
+    next_binding: usize,
+
+    /// Buffers which the dispatch reads, by conflict key
+    reads: Vec<(u64, usize)>,
+
+    /// Buffers which the dispatch writes, by conflict key
+    writes: Vec<(u64, usize)>,
+}
+
+impl<'a> BatchDispatch<'a> {
+    /// Declare a buffer which the dispatch only reads
+    pub fn read<B>(mut self, buffer: &Arc<B>) -> Result<Self>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        self.reads.push(buffer.conflict_key());
+        self.bind(buffer)
+    }
+
+    /// Declare a buffer which the dispatch writes (and possibly reads)
+    pub fn write<B>(mut self, buffer: &Arc<B>) -> Result<Self>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        self.writes.push(buffer.conflict_key());
+        self.bind(buffer)
+    }
+
+    /// Set the push constants of the dispatch
+    ///
+    /// They must have the type which was declared in the pipeline's
+    /// EasyComputeLayout, or at least a type of the same size, which is
+    /// checked when the dispatch is recorded.
+    ///
+    pub fn set_push_constants<T: Copy + 'static>(mut self,
+                                                 constants: T) -> Self {
+        self.dispatcher = dispatcher(constants);
+        self
+    }
+
+    /// Add the dispatch to the batch
+    pub fn record(self) -> Result<()> {
+        let set = self.set?.build()?;
+        self.batch.record(self.pipeline,
+                          self.work_groups,
+                          set,
+                          self.dispatcher,
+                          self.reads,
+                          self.writes)
+    }
+
+    /// Bind a buffer to the next binding of the descriptor set
+    fn bind<B>(mut self, buffer: &Arc<B>) -> Result<Self>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        let binding = self.next_binding;
+        self.set = self.set?.buffer(binding, buffer.clone());
+        self.next_binding += 1;
+        Ok(self)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::u32;
+
+    /// Limits of a typical desktop GPU
+    fn limits() -> WorkgroupLimits {
+        WorkgroupLimits {
+            device_name: "Mock GPU".to_owned(),
+            max_size: [1024, 1024, 64],
+            max_invocations: 1024,
+            max_shared_memory: 32768,
+            subgroup_size: Some(32),
+            max_subgroups: None,
+        }
+    }
+
+    /// Pick a work group size with the default options
+    fn pick(limits: &WorkgroupLimits, problem_shape: [u32; 3]) -> [u32; 3] {
+        pick_workgroup_size(limits,
+                            problem_shape,
+                            &WorkgroupSizeOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn spread_across_dimensions() {
+        assert_eq!(pick(&limits(), [1_000_000, 1, 1]), [256, 1, 1]);
+        assert_eq!(pick(&limits(), [1000, 1000, 1]), [16, 16, 1]);
+        assert_eq!(pick(&limits(), [4000, 1000, 1]), [32, 8, 1]);
+        assert_eq!(pick(&limits(), [1000, 1000, 1000]), [8, 8, 4]);
+    }
+
+    #[test]
+    fn device_limits() {
+        let narrow = WorkgroupLimits {
+            max_size: [4, 1024, 1024],
+            subgroup_size: None,
+            ..limits()
+        };
+        assert_eq!(pick(&narrow, [1000, 1, 1]), [4, 1, 1]);
+
+        let small = WorkgroupLimits {
+            max_invocations: 64,
+            ..limits()
+        };
+        assert_eq!(pick(&small, [1_000_000, 1, 1]), [64, 1, 1]);
+
+        let few_subgroups = WorkgroupLimits {
+            max_subgroups: Some(2),
+            ..limits()
+        };
+        assert_eq!(pick(&few_subgroups, [1_000_000, 1, 1]), [64, 1, 1]);
+    }
+
+    #[test]
+    fn shared_memory() {
+        let options = WorkgroupSizeOptions {
+            shared_memory_per_invocation: 1024,
+            ..WorkgroupSizeOptions::default()
+        };
+        assert_eq!(
+            pick_workgroup_size(&limits(), [1000, 1000, 1000], &options)
+                .unwrap(),
+            [4, 4, 2]
+        );
+
+        let options = WorkgroupSizeOptions {
+            shared_memory_per_invocation: 65536,
+            ..WorkgroupSizeOptions::default()
+        };
+        assert!(pick_workgroup_size(&limits(), [1000, 1, 1], &options)
+                    .is_err());
+    }
+
+    #[test]
+    fn clamping() {
+        // Problems smaller than a subgroup are covered exactly
+        assert_eq!(pick(&limits(), [5, 3, 1]), [5, 3, 1]);
+
+        // Larger problems keep whole subgroups...
+        assert_eq!(pick(&limits(), [100, 1, 1]), [128, 1, 1]);
+
+        // ...unless the subgroup size is unknown
+        let unknown = WorkgroupLimits {
+            subgroup_size: None,
+            ..limits()
+        };
+        assert_eq!(pick(&unknown, [100, 1, 1]), [100, 1, 1]);
+    }
+
+    #[test]
+    fn subgroup_size_override() {
+        assert_eq!(pick(&limits(), [48, 1, 1]), [64, 1, 1]);
+        let options = WorkgroupSizeOptions {
+            subgroup_size: Some(64),
+            ..WorkgroupSizeOptions::default()
+        };
+        assert_eq!(pick_workgroup_size(&limits(), [48, 1, 1], &options)
+                       .unwrap(),
+                   [48, 1, 1]);
+    }
+
+    #[test]
+    fn empty_problem() {
+        let options = WorkgroupSizeOptions::default();
+        assert!(pick_workgroup_size(&limits(), [0, 1, 1], &options)
+                    .is_err());
+    }
+
+    #[test]
+    fn work_group_counts() {
+        assert_eq!(work_group_count([100, 1, 1], [32, 1, 1]).unwrap(),
+                   [4, 1, 1]);
+        assert_eq!(work_group_count([64, 64, 0], [16, 16, 1]).unwrap(),
+                   [4, 4, 0]);
+        assert_eq!(work_group_count([u32::MAX, u32::MAX, 1], [1, 2, 1])
+                       .unwrap(),
+                   [u32::MAX, 1 << 31, 1]);
+        assert!(work_group_count([100, 1, 1], [32, 0, 1]).is_err());
+    }
+}
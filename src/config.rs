@@ -0,0 +1,229 @@
+//! Runtime configuration overrides from environment variables
+//!
+//! When testing a prototype on several GPUs or in several configurations, it
+//! is handy to be able to tweak vulkanoob's behavior without recompiling. Once
+//! enabled with set_env_overrides_enabled(), the following environment
+//! variables are taken into account:
+//!
+//! - VULKANOOB_DEVICE_INDEX=<n> only lets the physical device with index n
+//!   be selected (if it passes the device filter).
+//! - VULKANOOB_FORCE_VALIDATION=1 enables the standard validation layer.
+//! - VULKANOOB_LOG_LIMITS=off removes device limits from the device logs.
+//! - VULKANOOB_PREFER=discrete|integrated|memory|newest takes precedence over
+//!   the device preference, which is then only used to break ties.
+//! - VULKANOOB_SOFTWARE=allow|prefer|require overrides the EasyInstance's
+//!   treatment of software Vulkan implementations (see the software module).
+
+use ::{
+    preference,
+    software::SoftwareRendering,
+    Error,
+    Result,
+};
+
+use std::{
+    cmp::Ordering,
+    env,
+    sync::atomic::{AtomicBool, Ordering as AtomicOrdering},
+};
+
+use vulkano::instance::{
+    PhysicalDevice,
+    PhysicalDeviceType,
+};
+
+
+/// Environment variable restricting device selection to one device index
+pub const DEVICE_INDEX_VAR: &str = "VULKANOOB_DEVICE_INDEX";
+
+/// Environment variable forcing the use of the standard validation layer
+pub const FORCE_VALIDATION_VAR: &str = "VULKANOOB_FORCE_VALIDATION";
+
+/// Environment variable controlling whether device limits are logged
+pub const LOG_LIMITS_VAR: &str = "VULKANOOB_LOG_LIMITS";
+
+/// Environment variable overriding the device preference
+pub const PREFER_VAR: &str = "VULKANOOB_PREFER";
+
+/// Environment variable overriding the treatment of software implementations
+pub const SOFTWARE_VAR: &str = "VULKANOOB_SOFTWARE";
+
+/// Global switch telling whether environment overrides are enabled
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+
+/// Enable or disable environment variable overrides
+pub fn set_env_overrides_enabled(enabled: bool) {
+    ENABLED.store(enabled, AtomicOrdering::Relaxed);
+}
+
+/// Truth that environment variable overrides are enabled
+pub fn env_overrides_enabled() -> bool {
+    ENABLED.load(AtomicOrdering::Relaxed)
+}
+
+
+/// Device preference which can be requested via VULKANOOB_PREFER
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DevicePreference {
+    /// Prefer discrete GPUs ("discrete")
+    Discrete,
+
+    /// Prefer integrated GPUs ("integrated")
+    Integrated,
+
+    /// Prefer the devices with the most device-local memory ("memory")
+    MostMemory,
+
+    /// Prefer the devices with the newest Vulkan API version ("newest")
+    Newest,
+}
+
+impl DevicePreference {
+    /// Compare two devices according to this preference
+    pub fn compare(self,
+                   dev1: PhysicalDevice,
+                   dev2: PhysicalDevice) -> Ordering {
+        match self {
+            DevicePreference::Discrete =>
+                preference::prefer_discrete_gpu(dev1, dev2),
+            DevicePreference::Integrated => {
+                let is_integrated = |dev: PhysicalDevice| {
+                    dev.ty() == PhysicalDeviceType::IntegratedGpu
+                };
+                is_integrated(dev1).cmp(&is_integrated(dev2))
+            },
+            DevicePreference::MostMemory =>
+                preference::prefer_most_device_memory(dev1, dev2),
+            DevicePreference::Newest =>
+                preference::prefer_newest_api(dev1, dev2),
+        }
+    }
+}
+
+
+/// Configuration overrides read from the environment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvConfig {
+    /// Only this physical device may be selected
+    pub device_index: Option<usize>,
+
+    /// Force the use of the standard validation layer
+    pub force_validation: bool,
+
+    /// Log the device limits along with other device properties
+    pub log_limits: bool,
+
+    /// Device preference which takes precedence over the user's
+    pub prefer: Option<DevicePreference>,
+
+    /// Treatment of software implementations which takes precedence over the
+    /// EasyInstance's
+    pub software: Option<SoftwareRendering>,
+}
+
+impl Default for EnvConfig {
+    /// Configuration which does not override anything
+    fn default() -> Self {
+        EnvConfig {
+            device_index: None,
+            force_validation: false,
+            log_limits: true,
+            prefer: None,
+            software: None,
+        }
+    }
+}
+
+impl EnvConfig {
+    /// Read the configuration from the environment
+    ///
+    /// This does so even if environment overrides are disabled. Invalid
+    /// values are reported as errors.
+    ///
+    pub fn from_env() -> Result<Self> {
+        let mut config = EnvConfig::default();
+        if let Some(index) = read_var(DEVICE_INDEX_VAR) {
+            config.device_index = Some(index.parse().map_err(|_| {
+                invalid_value(DEVICE_INDEX_VAR, &index)
+            })?);
+        }
+        if let Some(force) = read_var(FORCE_VALIDATION_VAR) {
+            config.force_validation = parse_bool(FORCE_VALIDATION_VAR,
+                                                 &force)?;
+        }
+        if let Some(log) = read_var(LOG_LIMITS_VAR) {
+            config.log_limits = parse_bool(LOG_LIMITS_VAR, &log)?;
+        }
+        if let Some(prefer) = read_var(PREFER_VAR) {
+            config.prefer = Some(match &prefer.to_lowercase()[..] {
+                "discrete" => DevicePreference::Discrete,
+                "integrated" => DevicePreference::Integrated,
+                "memory" => DevicePreference::MostMemory,
+                "newest" => DevicePreference::Newest,
+                _ => return Err(invalid_value(PREFER_VAR, &prefer)),
+            });
+        }
+        if let Some(software) = read_var(SOFTWARE_VAR) {
+            config.software = Some(match &software.to_lowercase()[..] {
+                "allow" => SoftwareRendering::Allow,
+                "prefer" => SoftwareRendering::Prefer,
+                "require" => SoftwareRendering::Require,
+                _ => return Err(invalid_value(SOFTWARE_VAR, &software)),
+            });
+        }
+        Ok(config)
+    }
+
+    /// Configuration which vulkanoob should currently use
+    ///
+    /// This is the configuration from the environment if environment
+    /// overrides are enabled, and the default configuration otherwise.
+    ///
+    pub fn current() -> Result<Self> {
+        if env_overrides_enabled() {
+            let config = Self::from_env()?;
+            if config != EnvConfig::default() {
+                info!("Using configuration overrides from the environment: \
+                       {:?}", config);
+            }
+            Ok(config)
+        } else {
+            Ok(EnvConfig::default())
+        }
+    }
+
+    /// Truth that a device may be selected, according to this configuration
+    pub fn allows_device(&self, device: PhysicalDevice) -> bool {
+        self.device_index.map_or(true, |index| index == device.index())
+    }
+
+    /// Compare two devices according to the preference override, if any
+    pub fn compare(&self,
+                   dev1: PhysicalDevice,
+                   dev2: PhysicalDevice) -> Ordering {
+        self.prefer.map_or(Ordering::Equal, |prefer| {
+            prefer.compare(dev1, dev2)
+        })
+    }
+}
+
+
+/// Read an environment variable, treating empty values as missing
+fn read_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Parse a boolean environment variable
+fn parse_bool(name: &str, value: &str) -> Result<bool> {
+    match &value.to_lowercase()[..] {
+        "1" | "on" | "true" | "yes" => Ok(true),
+        "0" | "off" | "false" | "no" => Ok(false),
+        _ => Err(invalid_value(name, value)),
+    }
+}
+
+/// Report an invalid environment variable value
+fn invalid_value(name: &str, value: &str) -> Error {
+    Error::InvalidArgument(format!("Invalid value {:?} for {}", value, name))
+}
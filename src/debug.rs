@@ -0,0 +1,555 @@
+//! Handling of Vulkan debug messages
+//!
+//! Vulkano only supports the VK_EXT_debug_report extension at the moment, so
+//! this is what EasyInstance uses to receive debug messages. But when the
+//! newer VK_EXT_debug_utils extension is available, EasyInstance enables it
+//! too, and the object names which you set using EasyDevice::set_object_name()
+//! are given to the Vulkan implementation, which makes them visible in
+//! validation messages and in tools such as RenderDoc. Command buffer regions
+//! can also be labeled using begin_label(), end_label() and insert_label().
+//!
+//! Without VK_EXT_debug_utils, vulkanoob falls back to its own object naming
+//! mechanism: object handles which appear in debug messages are annotated
+//! with the names that you gave them, and labels are ignored.
+//!
+//! If the "tracing" feature is enabled, debug messages are also forwarded as
+//! events of the "tracing" ecosystem, with "layer", "message_id" and
+//! "severity" fields, in addition to being logged.
+
+use ::{
+    device::EasyDevice,
+    error::check_result,
+    loader,
+    trace,
+    Error,
+    Result,
+};
+
+use log::Level;
+
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fmt,
+    mem,
+    os::raw::{c_char, c_void},
+    ptr,
+    sync::{Arc, Mutex},
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    instance::debug::{
+        Message,
+        MessageTypes,
+    },
+    VulkanObject,
+};
+
+
+/// Name of the VK_EXT_debug_utils extension
+pub const DEBUG_UTILS_EXTENSION: &str = "VK_EXT_debug_utils";
+
+/// Logging target used for the Vulkan debug messages
+pub const TARGET: &str = "vulkanoob::debug_report";
+
+/// VK_STRUCTURE_TYPE_DEBUG_UTILS_OBJECT_NAME_INFO_EXT
+const STRUCTURE_TYPE_DEBUG_UTILS_OBJECT_NAME_INFO: u32 = 1_000_128_000;
+
+/// VK_STRUCTURE_TYPE_DEBUG_UTILS_LABEL_EXT
+const STRUCTURE_TYPE_DEBUG_UTILS_LABEL: u32 = 1_000_128_002;
+
+/// Maximal number of distinct messages whose repetitions are counted
+///
+/// Messages often embed handles or addresses, so their text does not repeat
+/// exactly and the counters would otherwise grow without bound. Once this
+/// many messages are counted, the counters are reset, which means that a
+/// suppressed message may occasionally be logged again.
+///
+const MAX_COUNTED_MESSAGES: usize = 4096;
+
+
+/// Raw Vulkan object handle, which can be converted to a 64-bit integer
+///
+/// This is implemented for the handle types of vk-sys, which are u64 for
+/// non-dispatchable handles and usize for dispatchable ones.
+///
+pub trait RawHandle: Copy {
+    /// Integer value of the handle
+    fn as_u64(self) -> u64;
+}
+
+impl RawHandle for u64 {
+    fn as_u64(self) -> u64 { self }
+}
+
+impl RawHandle for usize {
+    fn as_u64(self) -> u64 { self as u64 }
+}
+
+
+/// Give a human-readable name to a Vulkan object of some device
+///
+/// This is the implementation of EasyDevice::set_object_name().
+///
+pub(crate) fn set_object_name<O>(device: &EasyDevice,
+                                 object: &O,
+                                 name: String) -> Result<()>
+    where O: VulkanObject,
+          O::Object: RawHandle
+{
+    let _trace = trace::span("debug::set_object_name",
+                             || format!("name: {}", name));
+    let handle = object.internal_object().as_u64();
+    if !device.instance().supports_debug_utils() {
+        device.instance().object_names().set(handle, name);
+        return Ok(());
+    }
+    let object_type = object_type(O::TYPE).ok_or_else(|| {
+        Error::InvalidArgument(format!(
+            "Objects of debug report type {} cannot be named", O::TYPE
+        ))
+    })?;
+    let c_name = CString::new(name)?;
+    let name_info = RawObjectNameInfo {
+        s_type: STRUCTURE_TYPE_DEBUG_UTILS_OBJECT_NAME_INFO,
+        p_next: ptr::null(),
+        object_type,
+        object_handle: handle,
+        p_object_name: c_name.as_ptr(),
+    };
+    unsafe {
+        let set_object_name: extern "system" fn(vk::Device,
+                                                *const RawObjectNameInfo)
+                                                -> vk::Result =
+            mem::transmute(debug_utils_function(device,
+                                                "SetDebugUtilsObjectName")?);
+        check_result(set_object_name(device.device().internal_object(),
+                                     &name_info),
+                     "vkSetDebugUtilsObjectNameEXT")
+    }
+}
+
+/// Open a labeled region of a raw command buffer
+///
+/// The label shows up in debugging tools such as RenderDoc, and in the
+/// validation messages about the commands of the region. This does nothing
+/// if VK_EXT_debug_utils is not enabled.
+///
+/// # Safety
+///
+/// The command buffer must belong to the device and be in the recording
+/// state. The region must be closed with end_label() in the same command
+/// buffer.
+///
+pub unsafe fn begin_label(device: &EasyDevice,
+                          command_buffer: vk::CommandBuffer,
+                          name: &str,
+                          color: [f32; 4]) -> Result<()> {
+    if !device.instance().supports_debug_utils() { return Ok(()); }
+    let c_name = CString::new(name)?;
+    let label = RawLabel::new(&c_name, color);
+    let cmd_begin_label: extern "system" fn(vk::CommandBuffer,
+                                            *const RawLabel) =
+        mem::transmute(debug_utils_function(device,
+                                            "CmdBeginDebugUtilsLabel")?);
+    cmd_begin_label(command_buffer, &label);
+    Ok(())
+}
+
+/// Close the labeled region which was last opened in a raw command buffer
+///
+/// This does nothing if VK_EXT_debug_utils is not enabled.
+///
+/// # Safety
+///
+/// The command buffer must be in the recording state, with a region opened
+/// by begin_label() in it.
+///
+pub unsafe fn end_label(device: &EasyDevice,
+                        command_buffer: vk::CommandBuffer) -> Result<()> {
+    if !device.instance().supports_debug_utils() { return Ok(()); }
+    let cmd_end_label: extern "system" fn(vk::CommandBuffer) =
+        mem::transmute(debug_utils_function(device,
+                                            "CmdEndDebugUtilsLabel")?);
+    cmd_end_label(command_buffer);
+    Ok(())
+}
+
+/// Insert a single label in a raw command buffer
+///
+/// This does nothing if VK_EXT_debug_utils is not enabled.
+///
+/// # Safety
+///
+/// The command buffer must belong to the device and be in the recording
+/// state.
+///
+pub unsafe fn insert_label(device: &EasyDevice,
+                           command_buffer: vk::CommandBuffer,
+                           name: &str,
+                           color: [f32; 4]) -> Result<()> {
+    if !device.instance().supports_debug_utils() { return Ok(()); }
+    let c_name = CString::new(name)?;
+    let label = RawLabel::new(&c_name, color);
+    let cmd_insert_label: extern "system" fn(vk::CommandBuffer,
+                                             *const RawLabel) =
+        mem::transmute(debug_utils_function(device,
+                                            "CmdInsertDebugUtilsLabel")?);
+    cmd_insert_label(command_buffer, &label);
+    Ok(())
+}
+
+/// Load a VK_EXT_debug_utils entry point
+fn debug_utils_function(device: &EasyDevice,
+                        name: &str) -> Result<*const c_void> {
+    loader::device_function(device.device(), &format!("vk{}EXT", name))
+}
+
+/// Translate a debug report object type, as used by vulkano, into the
+/// matching VkObjectType
+///
+/// Core object types have the same value in both enums, but extension object
+/// types do not.
+///
+fn object_type(ty: vk::DebugReportObjectTypeEXT) -> Option<u32> {
+    match ty {
+        // VK_DEBUG_REPORT_OBJECT_TYPE_INSTANCE_EXT to ..._COMMAND_POOL_EXT
+        1..=25 => Some(ty),
+        // VK_DEBUG_REPORT_OBJECT_TYPE_SURFACE_KHR_EXT
+        26 => Some(1_000_000_000),
+        // VK_DEBUG_REPORT_OBJECT_TYPE_SWAPCHAIN_KHR_EXT
+        27 => Some(1_000_001_000),
+        _ => None,
+    }
+}
+
+/// VkDebugUtilsObjectNameInfoEXT
+#[repr(C)]
+struct RawObjectNameInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    object_type: u32,
+    object_handle: u64,
+    p_object_name: *const c_char,
+}
+
+/// VkDebugUtilsLabelEXT
+#[repr(C)]
+struct RawLabel {
+    s_type: u32,
+    p_next: *const c_void,
+    p_label_name: *const c_char,
+    color: [f32; 4],
+}
+
+impl RawLabel {
+    /// Describe a label, which borrows the name
+    fn new(name: &CString, color: [f32; 4]) -> Self {
+        RawLabel {
+            s_type: STRUCTURE_TYPE_DEBUG_UTILS_LABEL,
+            p_next: ptr::null(),
+            p_label_name: name.as_ptr(),
+            color,
+        }
+    }
+}
+
+
+/// Registry of human-readable Vulkan object names, keyed by handle, used when
+/// VK_EXT_debug_utils is not available
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ObjectNames(Arc<Mutex<HashMap<u64, String>>>);
+
+impl ObjectNames {
+    /// Give a name to an object handle
+    pub fn set(&self, handle: u64, name: String) {
+        self.0.lock().unwrap().insert(handle, name);
+    }
+
+    /// Annotate the object handles of a debug message with their names
+    ///
+    /// Every hexadecimal number of the form 0x... which matches a named
+    /// object handle gets followed by the object's name, in brackets.
+    ///
+    pub fn annotate(&self, message: &str) -> String {
+        let names = self.0.lock().unwrap();
+        if names.is_empty() { return message.to_owned(); }
+        let mut result = String::with_capacity(message.len());
+        let mut rest = message;
+        while let Some(pos) = rest.find("0x") {
+            let (before, after) = rest.split_at(pos + 2);
+            result.push_str(before);
+            let hex_len = after.find(|c: char| !c.is_digit(16))
+                               .unwrap_or(after.len());
+            let (hex, after) = after.split_at(hex_len);
+            result.push_str(hex);
+            let name = u64::from_str_radix(hex, 16).ok()
+                                                   .and_then(|h| names.get(&h));
+            if let Some(name) = name {
+                result.push_str(" [");
+                result.push_str(name);
+                result.push(']');
+            }
+            rest = after;
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+
+/// Configuration of debug message filtering
+///
+/// Validation layers can emit the same message over and over again, drowning
+/// useful information. This lets you rate-limit repeated messages, suppress
+/// messages which you are not interested in, and escalate messages which
+/// should never happen to panics (e.g. in test runs).
+///
+/// With VK_EXT_debug_report, message IDs (e.g. "VUID-vkCmdDraw-None-00000")
+/// are only available as part of the message text, so suppression and
+/// escalation both work by looking for substrings in the message.
+///
+/// The default configuration lets every message through.
+///
+#[derive(Clone, Debug, Default)]
+pub struct DebugFilter {
+    /// Maximal number of times that a given message is logged
+    max_repeats: Option<usize>,
+
+    /// Messages containing one of these substrings are not logged
+    suppressed: Vec<String>,
+
+    /// Messages containing one of these substrings are escalated to panics
+    escalated: Vec<String>,
+
+    /// Truth that all error messages should be escalated to panics
+    escalate_errors: bool,
+}
+
+impl DebugFilter {
+    /// Start with a configuration which lets every message through
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only log each distinct message up to a certain number of times
+    pub fn max_repeats(mut self, max_repeats: usize) -> Self {
+        self.max_repeats = Some(max_repeats);
+        self
+    }
+
+    /// Suppress messages containing a substring (e.g. a message ID)
+    pub fn suppress(mut self, pattern: impl Into<String>) -> Self {
+        self.suppressed.push(pattern.into());
+        self
+    }
+
+    /// Escalate messages containing a substring (e.g. a message ID) to panics
+    pub fn escalate(mut self, pattern: impl Into<String>) -> Self {
+        self.escalated.push(pattern.into());
+        self
+    }
+
+    /// Escalate all error messages to panics
+    pub fn escalate_errors(mut self, enabled: bool) -> Self {
+        self.escalate_errors = enabled;
+        self
+    }
+}
+
+
+/// A Vulkan debug message, as received by message sinks
+///
+/// Unlike vulkano's Message, this owns its data, so you can keep it around
+/// (e.g. in order to display it in an overlay later on). Without
+/// VK_EXT_debug_utils, object handles in the description are annotated with
+/// their names, as in the logs.
+///
+#[derive(Clone, Debug)]
+pub struct DebugMessage {
+    /// Type of message (error, warning...)
+    pub ty: MessageTypes,
+
+    /// Prefix of the layer that emitted the message
+    pub layer_prefix: String,
+
+    /// Description of the problem
+    pub description: String,
+}
+
+impl DebugMessage {
+    /// Logging level matching this message's severity
+    pub fn level(&self) -> Level {
+        match self.ty {
+            MessageTypes { error: true, .. } => Level::Error,
+            MessageTypes { performance_warning: true, .. }
+            | MessageTypes { warning: true, .. } => Level::Warn,
+            MessageTypes { information: true, .. } => Level::Info,
+            _ => Level::Debug,
+        }
+    }
+
+    /// Severity of this message, as a short string
+    pub fn severity(&self) -> &'static str {
+        match self.ty {
+            MessageTypes { error: true, .. } => "error",
+            MessageTypes { warning: true, .. } => "warning",
+            MessageTypes { performance_warning: true, .. } => "performance",
+            MessageTypes { information: true, .. } => "information",
+            _ => "debug",
+        }
+    }
+
+    /// Validation message ID (e.g. "VUID-vkCmdDraw-None-00000"), if any
+    ///
+    /// VK_EXT_debug_report does not provide message IDs, so we look for them
+    /// in the message text.
+    ///
+    pub fn message_id(&self) -> Option<&str> {
+        self.description
+            .split(|c: char| c.is_whitespace() || c == '[' || c == ']')
+            .find(|word| word.starts_with("VUID-")
+                         || word.starts_with("UNASSIGNED-"))
+            .map(|word| word.trim_end_matches(|c| c == ':' || c == ','))
+    }
+
+    /// Forward this message as an event of the "tracing" ecosystem
+    #[cfg(feature = "tracing")]
+    pub(crate) fn emit_tracing_event(&self) {
+        macro_rules! emit {
+            ($level:ident) => {
+                ::tracing::event!(
+                    target: TARGET,
+                    ::tracing::Level::$level,
+                    layer = self.layer_prefix.as_str(),
+                    message_id = self.message_id().unwrap_or(""),
+                    severity = self.severity(),
+                    "{}", self.description
+                )
+            };
+        }
+        match self.level() {
+            Level::Error => emit!(ERROR),
+            Level::Warn => emit!(WARN),
+            Level::Info => emit!(INFO),
+            Level::Debug => emit!(DEBUG),
+            Level::Trace => emit!(TRACE),
+        }
+    }
+}
+
+
+/// User-provided handler of Vulkan debug messages
+#[derive(Clone)]
+pub(crate) struct MessageSink(Arc<dyn Fn(&DebugMessage) + Send + Sync>);
+
+impl MessageSink {
+    /// Wrap a message handler
+    pub fn new(sink: impl Fn(&DebugMessage) + Send + Sync + 'static) -> Self {
+        MessageSink(Arc::new(sink))
+    }
+
+    /// Send a message to the handler
+    pub fn send(&self, message: &DebugMessage) {
+        (self.0)(message)
+    }
+}
+
+impl fmt::Debug for MessageSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MessageSink")
+    }
+}
+
+
+/// What should be done with a debug message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    /// Log the message
+    Log,
+
+    /// Log the message, and mention that repetitions will be suppressed
+    LogLastRepeat,
+
+    /// Do not log the message
+    Suppress,
+
+    /// Log the message, then escalate it to a panic
+    Escalate,
+}
+
+
+/// Debug message filter, as configured by a DebugFilter
+///
+/// Vulkano catches panics that occur inside of debug callbacks, so escalated
+/// messages are also recorded, and can be checked for using
+/// EasyInstance::check_escalations(), e.g. at the end of a test.
+///
+#[derive(Debug)]
+pub(crate) struct MessageFilter {
+    /// Filter configuration
+    config: DebugFilter,
+
+    /// Number of times each message was seen (at most MAX_COUNTED_MESSAGES)
+    counts: Mutex<HashMap<String, usize>>,
+
+    /// Messages which were escalated to panics
+    escalated: Mutex<Vec<String>>,
+}
+
+impl MessageFilter {
+    /// Set up a message filter
+    pub fn new(config: DebugFilter) -> Self {
+        MessageFilter {
+            config,
+            counts: Mutex::new(HashMap::new()),
+            escalated: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Decide what should be done with a debug message
+    pub fn process(&self, message: &Message) -> Verdict {
+        let text = message.description;
+
+        // Escalate messages which should never happen
+        let escalate = (self.config.escalate_errors && message.ty.error)
+            || self.config.escalated.iter().any(|p| text.contains(&p[..]));
+        if escalate {
+            self.escalated.lock().unwrap().push(text.to_owned());
+            return Verdict::Escalate;
+        }
+
+        // Suppress messages which the user is not interested in
+        if self.config.suppressed.iter().any(|p| text.contains(&p[..])) {
+            return Verdict::Suppress;
+        }
+
+        // Rate-limit repeated messages
+        if let Some(max_repeats) = self.config.max_repeats {
+            let mut counts = self.counts.lock().unwrap();
+            let key = format!("{}: {}", message.layer_prefix, text);
+            if counts.len() >= MAX_COUNTED_MESSAGES
+               && !counts.contains_key(&key)
+            {
+                debug!("Resetting the repeat counters of {} debug messages",
+                       counts.len());
+                counts.clear();
+            }
+            let count = counts.entry(key).or_insert(0);
+            *count += 1;
+            if *count > max_repeats {
+                return Verdict::Suppress;
+            } else if *count == max_repeats {
+                return Verdict::LogLastRepeat;
+            }
+        }
+        Verdict::Log
+    }
+
+    /// Messages which were escalated to panics so far
+    pub fn escalated_messages(&self) -> Vec<String> {
+        self.escalated.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,411 @@
+//! Conveniences for building descriptor sets
+//!
+//! Vulkano's PersistentDescriptorSet builder expects resources to be added in
+//! binding order, encodes them in its type, and reports mistakes through
+//! rather obscure errors. EasyDescriptorSet instead lets you bind resources
+//! by binding index, in any order, and checks each of them against the
+//! pipeline layout as soon as it is bound.
+//!
+//! Vulkano's draw and dispatch commands cannot provide the dynamic offsets of
+//! dynamic buffers, so descriptor sets which contain some must be bound to
+//! raw command buffers using bind_dynamic_descriptor_set().
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::sync::Arc;
+
+use vk_sys as vk;
+
+use vulkano::{
+    buffer::BufferAccess,
+    descriptor::{
+        descriptor::{
+            DescriptorBufferDesc,
+            DescriptorDesc,
+            DescriptorDescTy,
+            DescriptorImageDesc,
+        },
+        descriptor_set::{
+            DescriptorPool,
+            DescriptorPoolAlloc,
+            DescriptorSet,
+            DescriptorSetDesc,
+            DescriptorWrite,
+            StdDescriptorPoolAlloc,
+            UnsafeDescriptorSet,
+        },
+        pipeline_layout::PipelineLayoutAbstract,
+    },
+    device::DeviceOwned,
+    image::ImageViewAccess,
+    sampler::Sampler,
+    VulkanObject,
+};
+
+
+/// A buffer, as stored inside of a descriptor set
+type DynBuffer = Arc<dyn BufferAccess + Send + Sync>;
+
+/// An image, as stored inside of a descriptor set
+type DynImage = Arc<dyn ImageViewAccess + Send + Sync>;
+
+/// A resource which was bound to a descriptor set
+enum Resource {
+    /// Uniform or storage buffer
+    Buffer(DynBuffer),
+
+    /// Sampled or storage image
+    Image(DynImage),
+
+    /// Image with an associated sampler
+    SampledImage(DynImage, Arc<Sampler>),
+
+    /// Standalone sampler
+    Sampler(Arc<Sampler>),
+}
+
+
+/// Builder for an EasyDescriptorSet
+///
+/// Resources are bound by binding index, and checked against the pipeline
+/// layout immediately. Array bindings are not supported.
+///
+pub struct EasyDescriptorSetBuilder<L> {
+    /// Pipeline layout which the descriptor set is meant for
+    layout: L,
+
+    /// Index of the descriptor set in the pipeline layout
+    set: usize,
+
+    /// Resources bound so far, indexed by binding number
+    resources: Vec<Option<Resource>>,
+}
+
+impl<L: PipelineLayoutAbstract> EasyDescriptorSetBuilder<L> {
+    /// Bind a uniform or storage buffer
+    pub fn buffer<B>(self, binding: usize, buffer: Arc<B>) -> Result<Self>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        match self.descriptor(binding)?.ty {
+            DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(true), ..
+            }) => Err(self.mismatch(binding, "non-dynamic buffers")),
+            DescriptorDescTy::Buffer(_) => {
+                self.bind(binding, Resource::Buffer(buffer))
+            },
+            _ => Err(self.mismatch(binding, "a buffer")),
+        }
+    }
+
+    /// Bind a dynamic uniform or storage buffer
+    ///
+    /// The buffer should cover a single uniform or storage block, at the
+    /// start of the memory which dynamic offsets refer to. See the
+    /// uniform_ring module for a way to get such buffers.
+    ///
+    pub fn dynamic_buffer<B>(self,
+                             binding: usize,
+                             buffer: Arc<B>) -> Result<Self>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        match self.descriptor(binding)?.ty {
+            DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(true), ..
+            }) => self.bind(binding, Resource::Buffer(buffer)),
+            DescriptorDescTy::Buffer(_) => {
+                Err(self.mismatch(binding, "dynamic buffers"))
+            },
+            _ => Err(self.mismatch(binding, "a buffer")),
+        }
+    }
+
+    /// Bind a sampled or storage image
+    pub fn image<I>(self, binding: usize, image: Arc<I>) -> Result<Self>
+        where I: ImageViewAccess + Send + Sync + 'static
+    {
+        match self.descriptor(binding)?.ty {
+            DescriptorDescTy::Image(_) => {
+                self.bind(binding, Resource::Image(image))
+            },
+            _ => Err(self.mismatch(binding, "an image")),
+        }
+    }
+
+    /// Bind an image along with a sampler
+    pub fn sampled_image<I>(self,
+                            binding: usize,
+                            image: Arc<I>,
+                            sampler: Arc<Sampler>) -> Result<Self>
+        where I: ImageViewAccess + Send + Sync + 'static
+    {
+        match self.descriptor(binding)?.ty {
+            DescriptorDescTy::CombinedImageSampler(_) => {
+                self.bind(binding, Resource::SampledImage(image, sampler))
+            },
+            _ => Err(self.mismatch(binding, "a combined image sampler")),
+        }
+    }
+
+    /// Bind a standalone sampler
+    pub fn sampler(self,
+                   binding: usize,
+                   sampler: Arc<Sampler>) -> Result<Self> {
+        match self.descriptor(binding)?.ty {
+            DescriptorDescTy::Sampler => {
+                self.bind(binding, Resource::Sampler(sampler))
+            },
+            _ => Err(self.mismatch(binding, "a sampler")),
+        }
+    }
+
+    /// Build the descriptor set, checking that every binding was filled
+    pub fn build(self) -> Result<EasyDescriptorSet> {
+        let _trace = trace::span("EasyDescriptorSetBuilder::build",
+                                 || format!("set: {}", self.set));
+
+        // Check that every binding of the layout has a resource
+        let mut descriptors = Vec::with_capacity(self.resources.len());
+        for binding in 0..self.resources.len() {
+            let descriptor = self.layout.descriptor(self.set, binding);
+            if descriptor.is_some() && self.resources[binding].is_none() {
+                return Err(Error::InvalidArgument(
+                    format!("Binding {} of descriptor set {} was not filled",
+                            binding, self.set)
+                ));
+            }
+            descriptors.push(descriptor);
+        }
+
+        // Sort the resources for later access, and prepare descriptor writes
+        let mut buffers = Vec::new();
+        let mut images = Vec::new();
+        let mut samplers = Vec::new();
+        let mut writes = Vec::new();
+        let bindings = self.resources.iter().zip(&descriptors).enumerate();
+        for (binding, (resource, descriptor)) in bindings {
+            let (resource, descriptor) = match (resource, descriptor) {
+                (Some(resource), Some(descriptor)) => (resource, descriptor),
+                _ => continue,
+            };
+            let binding = binding as u32;
+            match (resource, &descriptor.ty) {
+                (Resource::Buffer(buffer),
+                 DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                     storage, dynamic
+                 })) => {
+                    // Dynamic buffer writes are unsafe because vulkano
+                    // cannot check the dynamic offsets, and this is left to
+                    // the caller of bind_dynamic_descriptor_set().
+                    writes.push(match (*storage, *dynamic == Some(true)) {
+                        (true, false) =>
+                            DescriptorWrite::storage_buffer(binding, 0, buffer),
+                        (false, false) =>
+                            DescriptorWrite::uniform_buffer(binding, 0, buffer),
+                        (true, true) => unsafe {
+                            DescriptorWrite::dynamic_storage_buffer(binding, 0,
+                                                                    buffer)
+                        },
+                        (false, true) => unsafe {
+                            DescriptorWrite::dynamic_uniform_buffer(binding, 0,
+                                                                    buffer)
+                        },
+                    });
+                    buffers.push((buffer.clone(), binding));
+                },
+                (Resource::Image(image),
+                 DescriptorDescTy::Image(DescriptorImageDesc {
+                     sampled, ..
+                 })) => {
+                    writes.push(if *sampled {
+                        DescriptorWrite::sampled_image(binding, 0, image)
+                    } else {
+                        DescriptorWrite::storage_image(binding, 0, image)
+                    });
+                    images.push((image.clone(), binding));
+                },
+                (Resource::SampledImage(image, sampler), _) => {
+                    writes.push(DescriptorWrite::combined_image_sampler(
+                        binding, 0, sampler, image
+                    ));
+                    images.push((image.clone(), binding));
+                    samplers.push(sampler.clone());
+                },
+                (Resource::Sampler(sampler), _) => {
+                    writes.push(DescriptorWrite::sampler(binding, 0, sampler));
+                    samplers.push(sampler.clone());
+                },
+                _ => unreachable!("Resources are checked when bound"),
+            }
+        }
+
+        // Allocate the descriptor set and write the descriptors into it
+        let device = self.layout.device();
+        let set_layout = self.layout.descriptor_set_layout(self.set)
+                                    .ok_or_else(|| {
+            Error::InvalidArgument(format!("No descriptor set {} in layout",
+                                           self.set))
+        })?;
+        let mut alloc = device.standard_descriptor_pool().alloc(set_layout)?;
+        // Safe because every descriptor was checked against the layout
+        unsafe { alloc.inner_mut().write(device, writes.into_iter()); }
+
+        Ok(EasyDescriptorSet {
+            alloc,
+            descriptors,
+            buffers,
+            images,
+            _samplers: samplers,
+        })
+    }
+
+    /// Layout of one of the bindings of the descriptor set
+    fn descriptor(&self, binding: usize) -> Result<DescriptorDesc> {
+        let descriptor = self.layout.descriptor(self.set, binding)
+                                    .ok_or_else(|| {
+            Error::InvalidArgument(
+                format!("Descriptor set {} has no binding {}",
+                        self.set, binding)
+            )
+        })?;
+        if descriptor.array_count != 1 {
+            return Err(Error::InvalidArgument(
+                format!("Binding {} of descriptor set {} is an array, which \
+                         is not supported", binding, self.set)
+            ));
+        }
+        Ok(descriptor)
+    }
+
+    /// Record a resource, which was checked against the layout
+    fn bind(mut self, binding: usize, resource: Resource) -> Result<Self> {
+        if self.resources[binding].is_some() {
+            warn!("Overwriting binding {} of descriptor set {}",
+                  binding, self.set);
+        }
+        self.resources[binding] = Some(resource);
+        Ok(self)
+    }
+
+    /// Report that a resource does not match the layout
+    fn mismatch(&self, binding: usize, resource: &str) -> Error {
+        Error::InvalidArgument(
+            format!("Binding {} of descriptor set {} does not accept {}",
+                    binding, self.set, resource)
+        )
+    }
+}
+
+
+/// A descriptor set built with EasyDescriptorSetBuilder
+///
+/// This can be passed to vulkano's draw and dispatch commands, preferably
+/// wrapped in an Arc.
+///
+pub struct EasyDescriptorSet {
+    /// Allocation of the descriptor set
+    alloc: StdDescriptorPoolAlloc,
+
+    /// Layout of the bindings of the descriptor set
+    descriptors: Vec<Option<DescriptorDesc>>,
+
+    /// Buffers bound to the descriptor set, along with their binding index
+    buffers: Vec<(DynBuffer, u32)>,
+
+    /// Images bound to the descriptor set, along with their binding index
+    images: Vec<(DynImage, u32)>,
+
+    /// Samplers bound to the descriptor set, which must be kept alive
+    _samplers: Vec<Arc<Sampler>>,
+}
+
+impl EasyDescriptorSet {
+    /// Start building a descriptor set for a pipeline layout (or pipeline)
+    pub fn start<L>(layout: L,
+                    set: usize) -> Result<EasyDescriptorSetBuilder<L>>
+        where L: PipelineLayoutAbstract
+    {
+        let num_bindings = layout.num_bindings_in_set(set).ok_or_else(|| {
+            Error::InvalidArgument(format!("No descriptor set {} in layout",
+                                           set))
+        })?;
+        Ok(EasyDescriptorSetBuilder {
+            layout,
+            set,
+            resources: (0..num_bindings).map(|_| None).collect(),
+        })
+    }
+}
+
+unsafe impl DescriptorSet for EasyDescriptorSet {
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        self.alloc.inner()
+    }
+
+    fn num_buffers(&self) -> usize {
+        self.buffers.len()
+    }
+
+    fn buffer(&self, index: usize) -> Option<(&dyn BufferAccess, u32)> {
+        self.buffers.get(index)
+                    .map(|(buffer, binding)| (&**buffer as _, *binding))
+    }
+
+    fn num_images(&self) -> usize {
+        self.images.len()
+    }
+
+    fn image(&self, index: usize) -> Option<(&dyn ImageViewAccess, u32)> {
+        self.images.get(index)
+                   .map(|(image, binding)| (&**image as _, *binding))
+    }
+}
+
+unsafe impl DescriptorSetDesc for EasyDescriptorSet {
+    fn num_bindings(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    fn descriptor(&self, binding: usize) -> Option<DescriptorDesc> {
+        self.descriptors.get(binding).and_then(|desc| desc.clone())
+    }
+}
+
+
+/// Bind a descriptor set which contains dynamic buffers to a raw command
+/// buffer, along with the dynamic offset of each of its dynamic buffers
+///
+/// Dynamic offsets are listed in binding order, and must be multiples of the
+/// device's min_uniform_buffer_offset_alignment (for uniform buffers) or
+/// min_storage_buffer_offset_alignment (for storage buffers).
+///
+/// # Safety
+///
+/// The command buffer must belong to the layout's device and be in the
+/// recording state. The offsets must keep the buffer ranges in bounds, and
+/// the descriptor set must stay alive until the command buffer is done
+/// executing.
+///
+pub unsafe fn bind_dynamic_descriptor_set(
+    command_buffer: vk::CommandBuffer,
+    bind_point: vk::PipelineBindPoint,
+    layout: &impl PipelineLayoutAbstract,
+    set_index: u32,
+    set: &impl DescriptorSet,
+    dynamic_offsets: &[u32],
+) {
+    let raw_set = set.inner().internal_object();
+    layout.device().pointers().CmdBindDescriptorSets(
+        command_buffer,
+        bind_point,
+        layout.sys().internal_object(),
+        set_index,
+        1,
+        &raw_set,
+        dynamic_offsets.len() as u32,
+        dynamic_offsets.as_ptr()
+    );
+}
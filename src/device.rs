@@ -1,13 +1,67 @@
 //! Conveniences for creating and manipulating Vulkan devices
 
-use ::Result;
+use ::{
+    api_level::{
+        self,
+        ApiLevel,
+    },
+    bindless,
+    budget::MemoryTracker,
+    debug::{
+        self,
+        RawHandle,
+    },
+    dynamic_rendering::{
+        self,
+        RenderPath,
+    },
+    instance::EasyInstance,
+    interop::{
+        self,
+        ExternalMemoryHandleType,
+    },
+    loader::{
+        self,
+        FeatureChain,
+    },
+    memory,
+    mesh_shader,
+    portability,
+    preference,
+    recovery::{
+        DeviceLostHooks,
+        DeviceLostReport,
+        DeviceSelection,
+    },
+    report,
+    subgroup::SubgroupProperties,
+    surface,
+    trace,
+    video::{
+        VideoCodec,
+        VideoSupport,
+    },
+    Error,
+    Result,
+};
+
+#[cfg(feature = "raytracing")]
+use ::raytracing::{
+    self,
+    RayTracingMode,
+};
 
 use std::{
     cmp::Ordering,
+    collections::HashMap,
+    mem,
     sync::Arc,
+    time::Instant,
 };
 
 use vulkano::{
+    buffer::DeviceLocalBuffer,
+    command_buffer::CommandBuffer,
     device::{
         Device,
         Queue,
@@ -17,27 +71,205 @@ use vulkano::{
         Features,
         PhysicalDevice,
         QueueFamily,
+        RawDeviceExtensions,
+        Version,
     },
+    swapchain::Surface,
+    sync::GpuFuture,
+    VulkanObject,
 };
 
 
+/// Priority of the queue of single-queue devices, unless specified otherwise
+const DEFAULT_QUEUE_PRIORITY: f32 = 1.0;
+
+
 /// A convenience wrapper for quickly setting up Vulkan devices
-pub struct EasyPhysicalDevice<'instance> {
-    /// Wrapped PhysicalDevice
-    device: PhysicalDevice<'instance>,
+///
+/// This does not borrow from the EasyInstance, but holds on to it, so that
+/// you can store it in your own structs alongside the instance or move it to
+/// other threads. The vulkano PhysicalDevice is looked up on demand.
+///
+#[derive(Clone)]
+pub struct EasyPhysicalDevice {
+    /// Instance which the device belongs to
+    instance: Arc<EasyInstance>,
+
+    /// Index of the wrapped PhysicalDevice within the instance
+    index: usize,
+
+    /// Vulkan API version that can be used on this device
+    api_version: Version,
 }
 
-impl<'instance> EasyPhysicalDevice<'instance> {
-    /// Build an EasyPhysicalDevice by wrapping a vulkano PhysicalDevice
-    pub(crate) fn new(device: PhysicalDevice<'instance>) -> Self {
+impl EasyPhysicalDevice {
+    /// Build an EasyPhysicalDevice, with API version requirements
+    ///
+    /// If the device does not fulfill the requirements, which should have
+    /// been checked during device selection, we fall back to the minimal
+    /// Vulkan version.
+    ///
+    pub(crate) fn with_api_level(instance: Arc<EasyInstance>,
+                                 device: PhysicalDevice,
+                                 api_level: &ApiLevel) -> Self {
         EasyPhysicalDevice {
-            device,
+            index: device.index(),
+            api_version: api_level.usable_version(device,
+                                                  instance.api_version())
+                                  .unwrap_or(api_level::VULKAN_1_0),
+            instance,
         }
     }
 
+    /// Access the EasyInstance which the device belongs to
+    pub fn instance(&self) -> &Arc<EasyInstance> {
+        &self.instance
+    }
+
     /// Access the inner Vulkan PhysicalDevice
-    pub fn physical_device(&self) -> &PhysicalDevice {
-        &self.device
+    pub fn physical_device(&self) -> PhysicalDevice {
+        PhysicalDevice::from_index(self.instance.instance(), self.index)
+            .expect("Physical devices do not go away while the instance lives")
+    }
+
+    /// Index of the physical device within the instance
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Vulkan API version that can be used on this device
+    ///
+    /// See the api_level module for more information.
+    ///
+    pub fn api_version(&self) -> Version {
+        self.api_version
+    }
+
+    /// Subgroup size and supported subgroup operations, if known
+    ///
+    /// See the subgroup module for the conditions under which they are known.
+    ///
+    pub fn subgroup_properties(&self) -> Option<SubgroupProperties> {
+        SubgroupProperties::of_device(self.physical_device())
+    }
+
+    /// Check that the device supports some features and extensions
+    ///
+    /// This is done automatically by the device setup methods, and lets them
+    /// report exactly what is missing instead of failing at device creation.
+    ///
+    pub fn check_support(&self,
+                         features: &Features,
+                         extensions: &DeviceExtensions) -> Result<()> {
+        let physical_device = self.physical_device();
+        let missing_extensions = extensions.difference(
+            &DeviceExtensions::supported_by_device(physical_device)
+        );
+        if missing_extensions != DeviceExtensions::none() {
+            return Err(Error::MissingExtensions {
+                device: physical_device.name(),
+                extensions: report::extension_names(&missing_extensions),
+            });
+        }
+        let missing_features =
+            features.difference(physical_device.supported_features());
+        if missing_features != Features::none() {
+            return Err(Error::MissingFeatures {
+                device: physical_device.name(),
+                features: report::feature_names(&missing_features),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that some queue priorities are valid and supported
+    ///
+    /// Priorities must be between 0.0 and 1.0. Using more distinct priorities
+    /// than the device's discrete_queue_priorities limit is allowed, but
+    /// causes a warning, as the Vulkan implementation will quantize them and
+    /// some of them will thus end up being the same.
+    ///
+    pub fn check_queue_priorities(
+        &self,
+        requirements: &[QueueRequirement]
+    ) -> Result<()> {
+        let physical_device = self.physical_device();
+        let mut distinct_priorities = Vec::<f32>::new();
+        for requirement in requirements {
+            for &priority in &requirement.priorities {
+                if !(0.0..=1.0).contains(&priority) {
+                    return Err(Error::InvalidArgument(format!(
+                        "Priority {} of the {} queues is not between 0.0 and \
+                         1.0", priority, requirement.role
+                    )));
+                }
+                if !distinct_priorities.contains(&priority) {
+                    distinct_priorities.push(priority);
+                }
+            }
+        }
+        let discrete_priorities =
+            physical_device.limits().discrete_queue_priorities() as usize;
+        if distinct_priorities.len() > discrete_priorities {
+            warn!("{} distinct queue priorities were requested ({:?}), but \
+                   device \"{}\" only supports {} discrete priority levels, \
+                   so some of them will be merged",
+                  distinct_priorities.len(),
+                  distinct_priorities,
+                  physical_device.name(),
+                  discrete_priorities);
+        }
+        Ok(())
+    }
+
+    /// Negotiate features and extensions with the device
+    ///
+    /// Required features and extensions must be supported by the device, or
+    /// an error is returned. Optional ones are enabled if the device supports
+    /// them, and silently left out otherwise.
+    ///
+    /// The result tells what should actually be enabled, and can be passed
+    /// to the device setup methods. Keep it around so that you can check which
+    /// optional capabilities are available at runtime.
+    ///
+    pub fn negotiate(
+        &self,
+        required_features: &Features,
+        optional_features: &Features,
+        required_extensions: &DeviceExtensions,
+        optional_extensions: &DeviceExtensions,
+    ) -> Result<EnabledCapabilities> {
+        // Required capabilities must be there
+        self.check_support(required_features, required_extensions)?;
+        let physical_device = self.physical_device();
+
+        // Optional capabilities are enabled if available
+        let supported_features = physical_device.supported_features();
+        let supported_extensions =
+            DeviceExtensions::supported_by_device(physical_device);
+        let enabled_optional_features =
+            optional_features.intersection(supported_features);
+        let enabled_optional_extensions =
+            optional_extensions.intersection(&supported_extensions);
+        info!("Enabled optional features: {:?}",
+              report::feature_names(&enabled_optional_features));
+        info!("Missing optional features: {:?}",
+              report::feature_names(
+                  &optional_features.difference(supported_features)
+              ));
+        info!("Enabled optional extensions: {:?}",
+              report::extension_names(&enabled_optional_extensions));
+        info!("Missing optional extensions: {:?}",
+              report::extension_names(
+                  &optional_extensions.difference(&supported_extensions)
+              ));
+
+        Ok(EnabledCapabilities {
+            features: features_union(required_features,
+                                     &enabled_optional_features),
+            extensions:
+                required_extensions.union(&enabled_optional_extensions),
+        })
     }
 
     /// Setup a logical device in a single-queue configuration
@@ -51,25 +283,348 @@ impl<'instance> EasyPhysicalDevice<'instance> {
     /// your physical device, you may want to integrate your queue
     /// filter/preference into your device filter/preference.
     ///
+    /// If no queue family passes the filter, None is returned.
+    ///
     pub fn setup_single_queue_device(
         &self,
         features: &Features,
         extensions: &DeviceExtensions,
         filter: impl FnMut(&QueueFamily) -> bool,
         preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
-    ) -> Result<Option<(Arc<Device>, Arc<Queue>)>> {
+    ) -> Result<Option<EasyDevice>> {
+        self.setup_single_queue_device_with_priority(features,
+                                                     extensions,
+                                                     DEFAULT_QUEUE_PRIORITY,
+                                                     filter,
+                                                     preference)
+    }
+
+    /// Like setup_single_queue_device(), but lets you specify the priority
+    /// of the queue, which must be between 0.0 and 1.0
+    ///
+    /// Queue priorities are relative to the other queues of the same logical
+    /// device. See setup_multi_queue_device() for finer control over them.
+    ///
+    pub fn setup_single_queue_device_with_priority(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        priority: f32,
+        filter: impl FnMut(&QueueFamily) -> bool,
+        preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+        let _trace = trace::span(
+            "EasyPhysicalDevice::setup_single_queue_device_with_priority",
+            || format!("device: {}, features: {:?}, extensions: {:?}, \
+                        priority: {}",
+                       physical_device.name(), features, extensions, priority)
+        );
+
+        // Check that the device supports what we need
+        if !(0.0..=1.0).contains(&priority) {
+            return Err(Error::InvalidArgument(format!(
+                "Queue priority {} is not between 0.0 and 1.0", priority
+            )));
+        }
+        self.check_support(features, extensions)?;
+
+        // Build the device
+        self.build_single_queue_device(
+            features,
+            portability::device_extensions(physical_device, extensions),
+            priority,
+            filter,
+            preference
+        )
+    }
+
+    /// Setup a single-queue logical device which can do ray tracing
+    ///
+    /// This works like setup_single_queue_device(), but also enables the
+    /// extensions and extension features which the chosen flavor of ray
+    /// tracing needs. If the device does not support them, or if Vulkan 1.1
+    /// cannot be used on this device (see the api_level module), an error is
+    /// returned.
+    ///
+    /// See the raytracing module for what can and cannot be done with the
+    /// resulting device at the moment.
+    ///
+    #[cfg(feature = "raytracing")]
+    pub fn setup_ray_tracing_device(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        mode: RayTracingMode,
+        filter: impl FnMut(&QueueFamily) -> bool,
+        preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+        let _trace = trace::span(
+            "EasyPhysicalDevice::setup_ray_tracing_device",
+            || format!("device: {}, features: {:?}, extensions: {:?}, \
+                        mode: {:?}",
+                       physical_device.name(), features, extensions, mode)
+        );
+
+        // Check that the device supports what we need
+        if self.api_version < api_level::VULKAN_1_1 {
+            return Err(Error::InvalidArgument(format!(
+                "Ray tracing requires Vulkan {}, but only Vulkan {} can be \
+                 used on device \"{}\" (instance version: {})",
+                api_level::VULKAN_1_1, self.api_version, physical_device.name(),
+                self.instance.api_version()
+            )));
+        }
+        self.check_support(features, extensions)?;
+        let mut raw_extensions =
+            portability::device_extensions(physical_device, extensions);
+        let mut feature_chain = FeatureChain::default();
+        raytracing::add_device_extensions(physical_device,
+                                          mode,
+                                          &mut raw_extensions,
+                                          &mut feature_chain)?;
+
+        // Build the device
+        self.build_single_queue_device_with_features(features,
+                                                     raw_extensions,
+                                                     feature_chain,
+                                                     DEFAULT_QUEUE_PRIORITY,
+                                                     filter,
+                                                     preference)
+    }
+
+    /// Setup a single-queue logical device which can use bindless texture
+    /// arrays
+    ///
+    /// This works like setup_single_queue_device(), but also enables the
+    /// bindless::bindless_features(), and negotiates descriptor indexing:
+    /// VK_EXT_descriptor_indexing is enabled if the device cannot use Vulkan
+    /// 1.2, along with the descriptor indexing features which
+    /// bindless::EasyBindless needs. If the device does not support them, an
+    /// error is returned.
+    ///
+    pub fn setup_bindless_device(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        filter: impl FnMut(&QueueFamily) -> bool,
+        preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+        let _trace = trace::span(
+            "EasyPhysicalDevice::setup_bindless_device",
+            || format!("device: {}, features: {:?}, extensions: {:?}",
+                       physical_device.name(), features, extensions)
+        );
+
+        // Check that the device supports what we need
+        let features = features.union(&bindless::bindless_features());
+        self.check_support(&features, extensions)?;
+        let mut raw_extensions =
+            portability::device_extensions(physical_device, extensions);
+        let mut feature_chain = FeatureChain::default();
+        bindless::add_device_extensions(physical_device,
+                                        self.api_version,
+                                        &mut raw_extensions,
+                                        &mut feature_chain)?;
+
+        // Build the device
+        self.build_single_queue_device_with_features(&features,
+                                                     raw_extensions,
+                                                     feature_chain,
+                                                     DEFAULT_QUEUE_PRIORITY,
+                                                     filter,
+                                                     preference)
+    }
+
+    /// Setup a single-queue logical device which can run mesh shading
+    /// pipelines
+    ///
+    /// This works like setup_single_queue_device(), but also enables
+    /// VK_EXT_mesh_shader, its dependencies, and its meshShader feature, as
+    /// well as its taskShader feature if task_shaders is true. If the device
+    /// does not support them, an error is returned. Mesh shading requires
+    /// Vulkan 1.1. See mesh_shader::EasyMeshPipeline for how to use it.
+    ///
+    pub fn setup_mesh_shader_device(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        task_shaders: bool,
+        filter: impl FnMut(&QueueFamily) -> bool,
+        preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+        let _trace = trace::span(
+            "EasyPhysicalDevice::setup_mesh_shader_device",
+            || format!("device: {}, features: {:?}, extensions: {:?}, \
+                        task_shaders: {}",
+                       physical_device.name(), features, extensions,
+                       task_shaders)
+        );
+
+        // Check that the device supports what we need
+        if self.api_version < api_level::VULKAN_1_1 {
+            return Err(Error::InvalidArgument(format!(
+                "Mesh shading requires Vulkan {}, but only Vulkan {} can be \
+                 used on device \"{}\" (instance version: {})",
+                api_level::VULKAN_1_1, self.api_version, physical_device.name(),
+                self.instance.api_version()
+            )));
+        }
+        self.check_support(features, extensions)?;
+        let mut raw_extensions =
+            portability::device_extensions(physical_device, extensions);
+        let mut feature_chain = FeatureChain::default();
+        mesh_shader::add_device_extensions(physical_device,
+                                           task_shaders,
+                                           &mut raw_extensions,
+                                           &mut feature_chain)?;
+
+        // Build the device
+        self.build_single_queue_device_with_features(features,
+                                                     raw_extensions,
+                                                     feature_chain,
+                                                     DEFAULT_QUEUE_PRIORITY,
+                                                     filter,
+                                                     preference)
+    }
+
+    /// Setup a single-queue logical device which can share memory with other
+    /// APIs
+    ///
+    /// This works like setup_single_queue_device(), but also enables the
+    /// extensions needed to share memory through some kind of OS handle. If
+    /// the device does not support them, an error is returned. See the
+    /// interop module for how to share memory once the device is set up.
+    ///
+    pub fn setup_interop_device(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        handle_type: ExternalMemoryHandleType,
+        filter: impl FnMut(&QueueFamily) -> bool,
+        preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+        let _trace = trace::span(
+            "EasyPhysicalDevice::setup_interop_device",
+            || format!("device: {}, features: {:?}, extensions: {:?}, \
+                        handle_type: {:?}",
+                       physical_device.name(), features, extensions,
+                       handle_type)
+        );
+
+        // Check that the device supports what we need
+        self.check_support(features, extensions)?;
+        let mut raw_extensions =
+            portability::device_extensions(physical_device, extensions);
+        interop::add_device_extensions(physical_device,
+                                       handle_type,
+                                       &mut raw_extensions)?;
+
+        // Build the device
+        self.build_single_queue_device(features,
+                                       raw_extensions,
+                                       DEFAULT_QUEUE_PRIORITY,
+                                       filter,
+                                       preference)
+    }
+
+    /// Setup a single-queue logical device which can use dynamic rendering
+    ///
+    /// This works like setup_single_queue_device(), but also enables the
+    /// dynamicRendering feature, along with the extensions which dynamic
+    /// rendering needs when Vulkan 1.3 cannot be used. If the device does not
+    /// support them, an error is returned.
+    ///
+    pub fn setup_dynamic_rendering_device(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        filter: impl FnMut(&QueueFamily) -> bool,
+        preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+        let _trace = trace::span(
+            "EasyPhysicalDevice::setup_dynamic_rendering_device",
+            || format!("device: {}, features: {:?}, extensions: {:?}",
+                       physical_device.name(), features, extensions)
+        );
+
+        // Check that the device supports what we need
+        self.check_support(features, extensions)?;
+        let mut raw_extensions =
+            portability::device_extensions(physical_device, extensions);
+        let mut feature_chain = FeatureChain::default();
+        dynamic_rendering::add_device_extensions(
+            physical_device,
+            self.api_version,
+            &mut raw_extensions,
+            &mut feature_chain
+        )?;
+
+        // Build the device, and remember that it can use dynamic rendering
+        let device = self.build_single_queue_device_with_features(
+            features,
+            raw_extensions,
+            feature_chain,
+            DEFAULT_QUEUE_PRIORITY,
+            filter,
+            preference
+        )?;
+        Ok(device.map(|mut device| {
+            device.dynamic_rendering = Some(self.api_version);
+            device
+        }))
+    }
+
+    /// Build a single-queue device on the preferred queue family, once the
+    /// device extensions have been figured out
+    fn build_single_queue_device(
+        &self,
+        features: &Features,
+        raw_extensions: RawDeviceExtensions,
+        priority: f32,
+        filter: impl FnMut(&QueueFamily) -> bool,
+        preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Option<EasyDevice>> {
+        self.build_single_queue_device_with_features(features,
+                                                     raw_extensions,
+                                                     FeatureChain::default(),
+                                                     priority,
+                                                     filter,
+                                                     preference)
+    }
+
+    /// Like build_single_queue_device(), but also enables the extension
+    /// features of a feature chain
+    fn build_single_queue_device_with_features(
+        &self,
+        features: &Features,
+        raw_extensions: RawDeviceExtensions,
+        mut feature_chain: FeatureChain,
+        priority: f32,
+        filter: impl FnMut(&QueueFamily) -> bool,
+        preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+
         // Select the appropriate queue family (if any)
-        if let Some(queue_family) = self.device.queue_families()
-                                               .filter(filter)
-                                               .max_by(preference)
+        if let Some(queue_family) = physical_device.queue_families()
+                                                   .filter(filter)
+                                                   .max_by(preference)
         {
             // Build a single-queue device
-            let (device, mut queues_iter) = Device::new(
-                self.device,
-                features,
-                extensions,
-                [(queue_family, 1.0)].iter().cloned()
-            )?;
+            let raw_extension_names = raw_extension_names(&raw_extensions);
+            let (device, mut queues_iter) =
+                loader::with_device_features(&mut feature_chain, || {
+                    Device::new(physical_device,
+                                features,
+                                raw_extensions,
+                                [(queue_family, priority)].iter().cloned())
+                })?;
 
             // Extract the only queue from the iterator (should always succeed,
             // if not it is a bug in vulkano or the Vulkan implementation)
@@ -77,10 +632,751 @@ impl<'instance> EasyPhysicalDevice<'instance> {
             assert!(queues_iter.next().is_none());
 
             // And now we can return the device and the queue
-            Ok(Some((device, queue)))
+            Ok(Some(EasyDevice::new(self.instance.clone(),
+                                    device,
+                                    vec![queue],
+                                    None,
+                                    QueueSet::default(),
+                                    self.api_version,
+                                    raw_extension_names)))
         } else {
             // No suitable queue family was found :-/
             Ok(None)
         }
     }
-}
\ No newline at end of file
+
+    /// Setup a logical device for rendering to a surface
+    ///
+    /// This picks a graphics queue family and a presentation queue family
+    /// using surface::presentation_queue_families(), enables the
+    /// VK_KHR_swapchain extension on top of the ones that you specify, and
+    /// creates one queue of each kind.
+    ///
+    /// If a single queue family can both render and present, the graphics and
+    /// presentation queues of the resulting EasyDevice will be the same. If
+    /// the device cannot render to this surface, None is returned.
+    ///
+    pub fn setup_presentation_device<W>(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        surface: &Arc<Surface<W>>,
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+        let _trace = trace::span(
+            "EasyPhysicalDevice::setup_presentation_device",
+            || format!("device: {}, features: {:?}, extensions: {:?}",
+                       physical_device.name(), features, extensions)
+        );
+
+        // Check that the device supports what we need
+        self.check_support(features, extensions)?;
+
+        // Select the appropriate queue families (if any)
+        let (graphics_family, present_family) =
+            match surface::presentation_queue_families(physical_device,
+                                                       surface) {
+                Some(families) => families,
+                None => return Ok(None),
+            };
+
+        // Build the device, with one queue per queue family
+        let extensions =
+            extensions.union(&surface::presentation_extensions());
+        let is_unified = graphics_family.id() == present_family.id();
+        let mut queue_families = vec![(graphics_family,
+                                       DEFAULT_QUEUE_PRIORITY)];
+        if !is_unified {
+            queue_families.push((present_family, DEFAULT_QUEUE_PRIORITY));
+        }
+        let raw_extensions =
+            portability::device_extensions(physical_device, &extensions);
+        let raw_extension_names = raw_extension_names(&raw_extensions);
+        let (device, queues_iter) = Device::new(
+            physical_device,
+            features,
+            raw_extensions,
+            queue_families
+        )?;
+
+        // Find out which queue is the presentation queue
+        let queues = queues_iter.collect::<Vec<_>>();
+        let present_queue =
+            queues.iter()
+                  .find(|queue| queue.family().id() == present_family.id())
+                  .cloned();
+        Ok(Some(EasyDevice::new(self.instance.clone(),
+                                device,
+                                queues,
+                                present_queue,
+                                QueueSet::default(),
+                                self.api_version,
+                                raw_extension_names)))
+    }
+
+    /// Setup a logical device with multiple queues, grouped by role
+    ///
+    /// You describe which queues you need as a list of queue requirements,
+    /// each of which names a role (e.g. "graphics" or "transfer"), tells how
+    /// many queues should be created for this role and with which priorities,
+    /// and which queue families are suitable for this role.
+    ///
+    /// Queue priorities must be between 0.0 and 1.0. Devices only support a
+    /// limited number of distinct priority levels (as given by the
+    /// discrete_queue_priorities limit), and using more distinct priorities
+    /// than that causes a warning, since some of them will be merged by the
+    /// Vulkan implementation.
+    ///
+    /// Requirements are processed in order. For each of them, we pick the
+    /// most specialized queue family which fits the requirement and has
+    /// enough queues left, so that e.g. transfer queues end up on a dedicated
+    /// transfer queue family if the device has one.
+    ///
+    /// The queues are available through EasyDevice::queue_set(), grouped by
+    /// role, and through the other EasyDevice queue accessors.
+    ///
+    /// If the requirements cannot be fulfilled, None is returned.
+    ///
+    pub fn setup_multi_queue_device(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        requirements: &[QueueRequirement],
+    ) -> Result<Option<EasyDevice>> {
+        let physical_device = self.physical_device();
+        let _trace = trace::span(
+            "EasyPhysicalDevice::setup_multi_queue_device",
+            || format!("device: {}, features: {:?}, extensions: {:?}, \
+                        requirements: {:?}",
+                       physical_device.name(), features, extensions,
+                       requirements.iter()
+                                   .map(|r| (&r.role, &r.priorities))
+                                   .collect::<Vec<_>>())
+        );
+
+        // Check that the device supports what we need
+        self.check_support(features, extensions)?;
+        self.check_queue_priorities(requirements)?;
+
+        // Assign queues to each requirement, keeping track of how many queues
+        // have been used in each queue family so far
+        let mut used_queues = HashMap::<u32, usize>::new();
+        let mut assignments = Vec::with_capacity(requirements.len());
+        for requirement in requirements {
+            let family = physical_device.queue_families()
+                .filter(|family| (requirement.filter)(family))
+                .filter(|family| {
+                    let used = used_queues.get(&family.id()).unwrap_or(&0);
+                    family.queues_count() - used >= requirement.count()
+                })
+                .min_by_key(preference::queue_family_capabilities);
+            let family = match family {
+                Some(family) => family,
+                None => {
+                    info!("No queue family can fulfill the {} queue \
+                           requirement", requirement.role);
+                    return Ok(None);
+                }
+            };
+            let used = used_queues.entry(family.id()).or_insert(0);
+            assignments.push((family, *used, requirement));
+            *used += requirement.count();
+        }
+
+        // Build the device
+        let queue_requests = assignments.iter().flat_map(|&(family, _, req)| {
+            req.priorities.iter().map(move |&priority| (family, priority))
+        });
+        let raw_extensions =
+            portability::device_extensions(physical_device, extensions);
+        let raw_extension_names = raw_extension_names(&raw_extensions);
+        let (device, queues_iter) = Device::new(
+            physical_device,
+            features,
+            raw_extensions,
+            queue_requests
+        )?;
+
+        // Dispatch the queues across roles. We cannot rely on the order in
+        // which vulkano returns queues, so we use their family and index.
+        let all_queues = queues_iter.collect::<Vec<_>>();
+        let mut queues = all_queues.iter().map(|queue| {
+            ((queue.family().id(), queue.id_within_family() as usize),
+             queue.clone())
+        }).collect::<HashMap<_, _>>();
+        let mut queue_set = QueueSet { queues: HashMap::new() };
+        for (family, first_index, requirement) in assignments {
+            let role_queues = queue_set.queues
+                                       .entry(requirement.role.clone())
+                                       .or_insert_with(Vec::new);
+            for index in first_index..first_index + requirement.count() {
+                let queue = queues.remove(&(family.id(), index)).unwrap();
+                role_queues.push(queue);
+            }
+        }
+        assert!(queues.is_empty());
+
+        // And now we can return the device and the queues
+        Ok(Some(EasyDevice::new(self.instance.clone(),
+                                device,
+                                all_queues,
+                                None,
+                                queue_set,
+                                self.api_version,
+                                raw_extension_names)))
+    }
+}
+
+
+/// A logical device, along with its queues and enabled capabilities
+///
+/// Queues are organized by capability, with accessors that pick the most
+/// appropriate queue for a given kind of work.
+///
+pub struct EasyDevice {
+    /// Instance which the device was created from
+    instance: Arc<EasyInstance>,
+
+    /// Wrapped logical Device
+    device: Arc<Device>,
+
+    /// All queues of the device
+    queues: Vec<Arc<Queue>>,
+
+    /// Queue used for presentation, if any
+    present_queue: Option<Arc<Queue>>,
+
+    /// Queues grouped by role, if set up using setup_multi_queue_device()
+    queue_set: QueueSet,
+
+    /// Features and extensions which were enabled
+    capabilities: EnabledCapabilities,
+
+    /// Vulkan API version that can be used on this device
+    api_version: Version,
+
+    /// Names of all enabled extensions, including those which vulkano does
+    /// not know about
+    raw_extensions: Vec<String>,
+
+    /// Device loss tracking and recovery
+    lost_hooks: DeviceLostHooks,
+
+    /// Tracker of memory allocations
+    memory_tracker: MemoryTracker,
+
+    /// API version with which dynamic rendering was set up, if the
+    /// dynamicRendering feature was enabled
+    dynamic_rendering: Option<Version>,
+}
+
+impl EasyDevice {
+    /// Build an EasyDevice from a freshly created device and its queues
+    ///
+    /// The enabled features and extensions are queried from the device, so
+    /// that they include anything which was implicitly enabled along the way.
+    ///
+    pub(crate) fn new(instance: Arc<EasyInstance>,
+                      device: Arc<Device>,
+                      queues: Vec<Arc<Queue>>,
+                      present_queue: Option<Arc<Queue>>,
+                      queue_set: QueueSet,
+                      api_version: Version,
+                      raw_extensions: Vec<String>) -> Self {
+        let capabilities = EnabledCapabilities {
+            features: device.enabled_features().clone(),
+            extensions: device.loaded_extensions().clone(),
+        };
+        log_device_summary(&device, &queues, &capabilities);
+        let selection = DeviceSelection::new(instance.clone(),
+                                             device.physical_device(),
+                                             capabilities.clone());
+        let memory_tracker = MemoryTracker::new(device.physical_device());
+        EasyDevice {
+            instance,
+            device,
+            queues,
+            present_queue,
+            queue_set,
+            capabilities,
+            api_version,
+            raw_extensions,
+            lost_hooks: DeviceLostHooks::new(selection),
+            memory_tracker,
+            dynamic_rendering: None,
+        }
+    }
+
+    /// Access the EasyInstance which the device was created from
+    pub fn instance(&self) -> &Arc<EasyInstance> {
+        &self.instance
+    }
+
+    /// Access the inner logical Device
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Access all queues of the device
+    pub fn queues(&self) -> &[Arc<Queue>] {
+        &self.queues[..]
+    }
+
+    /// Access the queues grouped by role
+    ///
+    /// This is only populated for devices built using
+    /// setup_multi_queue_device(), and empty otherwise.
+    ///
+    pub fn queue_set(&self) -> &QueueSet {
+        &self.queue_set
+    }
+
+    /// Features and extensions which were enabled on this device
+    pub fn capabilities(&self) -> &EnabledCapabilities {
+        &self.capabilities
+    }
+
+    /// Features which were enabled on this device
+    pub fn enabled_features(&self) -> &Features {
+        &self.capabilities.features
+    }
+
+    /// Extensions which were enabled on this device
+    pub fn enabled_extensions(&self) -> &DeviceExtensions {
+        &self.capabilities.extensions
+    }
+
+    /// Truth that an extension was enabled on this device, by name
+    ///
+    /// Unlike enabled_extensions(), this also covers the extensions which
+    /// vulkano does not know about, e.g. those enabled for ray tracing or
+    /// for external memory interop.
+    ///
+    pub fn is_extension_enabled(&self, name: &str) -> bool {
+        self.raw_extensions.iter().any(|ext| ext == name)
+    }
+
+    /// Vulkan API version that can be used on this device
+    ///
+    /// Use this to decide whether functionality from newer Vulkan versions
+    /// (e.g. timeline semaphores) can be used. See the api_level module.
+    ///
+    pub fn api_version(&self) -> Version {
+        self.api_version
+    }
+
+    /// Truth that dynamic rendering can be used on this device
+    ///
+    /// This requires Vulkan 1.3 or the VK_KHR_dynamic_rendering extension,
+    /// and the dynamicRendering feature, which are enabled by
+    /// EasyPhysicalDevice::setup_dynamic_rendering_device().
+    ///
+    pub fn supports_dynamic_rendering(&self) -> bool {
+        self.dynamic_rendering.is_some()
+    }
+
+    /// API version with which dynamic rendering was set up, which tells
+    /// whether its core or extension entry points should be used
+    pub(crate) fn dynamic_rendering_version(&self) -> Option<Version> {
+        self.dynamic_rendering
+    }
+
+    /// Render path which should be used on this device
+    ///
+    /// Dynamic rendering is preferred when supported, as it does not need
+    /// render pass and framebuffer objects.
+    ///
+    pub fn preferred_render_path(&self) -> RenderPath {
+        if self.supports_dynamic_rendering() {
+            RenderPath::DynamicRendering
+        } else {
+            RenderPath::RenderPass
+        }
+    }
+
+    /// Subgroup size and supported subgroup operations, if known
+    pub fn subgroup_properties(&self) -> Option<SubgroupProperties> {
+        SubgroupProperties::of_device(self.device.physical_device())
+    }
+
+    /// First queue with graphics support, if any
+    pub fn graphics_queue(&self) -> Option<&Arc<Queue>> {
+        self.find_queue(|family| family.supports_graphics())
+    }
+
+    /// Queue with compute support, if any
+    ///
+    /// Queues without graphics support are preferred, as they are more likely
+    /// to run asynchronously with respect to rendering work.
+    ///
+    pub fn compute_queue(&self) -> Option<&Arc<Queue>> {
+        self.find_queue(|family| family.supports_compute()
+                                 && !family.supports_graphics())
+            .or_else(|| self.find_queue(|family| family.supports_compute()))
+    }
+
+    /// Queue from a dedicated transfer queue family, if any
+    pub fn transfer_queue(&self) -> Option<&Arc<Queue>> {
+        self.find_queue(|family| family.supports_transfers()
+                                 && !family.supports_graphics()
+                                 && !family.supports_compute())
+    }
+
+    /// Queue which can decode some video codec, if any
+    ///
+    /// Vulkan Video extensions must have been enabled on the device for the
+    /// queue to be usable for decoding. See the video module.
+    ///
+    pub fn video_decode_queue(&self, codec: VideoCodec) -> Option<&Arc<Queue>> {
+        let support =
+            VideoSupport::of_device(self.device.physical_device()).ok()?;
+        self.find_queue(|family| {
+            support.queue_family(family.id())
+                   .map_or(false, |family| family.can_decode(codec))
+        })
+    }
+
+    /// Queue which can be used for transfers, preferring dedicated ones
+    ///
+    /// Graphics and compute queues implicitly support transfers, so this will
+    /// fall back to them when there is no dedicated transfer queue. None is
+    /// returned if no queue of the device supports transfers.
+    ///
+    pub fn transfer_queue_or_fallback(&self) -> Option<&Arc<Queue>> {
+        self.transfer_queue()
+            .or_else(|| self.find_queue(|family| family.supports_transfers()))
+            .or_else(|| self.compute_queue())
+            .or_else(|| self.graphics_queue())
+    }
+
+    /// Queue used for presentation, if the device was set up for it
+    pub fn present_queue(&self) -> Option<&Arc<Queue>> {
+        self.present_queue.as_ref()
+    }
+
+    /// Wait for the device to be idle
+    ///
+    /// This is a blunt synchronization tool, which is handy when tearing
+    /// things down or when debugging synchronization issues.
+    ///
+    pub fn wait_idle(&self) -> Result<()> {
+        let _trace = trace::span("EasyDevice::wait_idle", String::new);
+        info!("Waiting for device \"{}\" to be idle...",
+              self.device.physical_device().name());
+        let start = Instant::now();
+        unsafe { self.device.wait()?; }
+        info!("Device became idle after {:?}", start.elapsed());
+        Ok(())
+    }
+
+    /// Give a human-readable name to a Vulkan object of this device
+    ///
+    /// If VK_EXT_debug_utils is enabled, the name is given to the Vulkan
+    /// implementation, and shows up in validation messages and debugging
+    /// tools. Otherwise, whenever the object's handle appears in a Vulkan
+    /// debug message, vulkanoob follows it with this name. Either way,
+    /// naming an object again replaces its previous name.
+    ///
+    pub fn set_object_name<O>(&self,
+                              object: &O,
+                              name: impl Into<String>) -> Result<()>
+        where O: VulkanObject,
+              O::Object: RawHandle
+    {
+        debug::set_object_name(self, object, name.into())
+    }
+
+    /// Register a callback to be invoked when the device is lost
+    ///
+    /// The callback receives a report which, in addition to diagnostic
+    /// information, tells how the device was set up. Use it to build a
+    /// replacement device, and make it available to the rest of your
+    /// application, as this EasyDevice cannot be used anymore.
+    ///
+    /// Device loss is detected by check_device_lost() and submit_and_wait().
+    ///
+    pub fn on_device_lost(&self,
+                          callback: impl FnMut(&DeviceLostReport)
+                                        + Send + 'static) {
+        self.lost_hooks.set_callback(Box::new(callback));
+    }
+
+    /// Record what is being submitted to the device, for diagnostic purposes
+    ///
+    /// If the device is lost, the last recorded submission will be logged.
+    ///
+    pub fn note_submission(&self, description: impl Into<String>) {
+        self.lost_hooks.note_submission(description.into());
+    }
+
+    /// Check the result of a device operation for device loss
+    ///
+    /// If the device was lost, a diagnostic is logged and the device loss
+    /// callback is invoked. The result is passed through in any case.
+    ///
+    pub fn check_device_lost<T>(&self, result: Result<T>) -> Result<T> {
+        if let Err(ref e) = result {
+            if e.is_device_lost() { self.lost_hooks.device_lost(); }
+        }
+        result
+    }
+
+    /// Submit a command buffer to a queue of this device and wait for it
+    ///
+    /// The submission is recorded for diagnostic purposes, and device loss is
+    /// checked for as in check_device_lost().
+    ///
+    pub fn submit_and_wait<C>(&self,
+                              queue: &Arc<Queue>,
+                              command_buffer: C,
+                              description: &str) -> Result<()>
+        where C: CommandBuffer + Send + Sync + 'static
+    {
+        let _trace = trace::span("EasyDevice::submit_and_wait",
+                                 || format!("description: {}", description));
+        self.note_submission(description);
+        let result = (|| -> Result<()> {
+            command_buffer.execute(queue.clone())?
+                          .then_signal_fence_and_flush()?
+                          .wait(None)?;
+            Ok(())
+        })();
+        self.check_device_lost(result)
+    }
+
+    /// Access the tracker of this device's memory allocations
+    pub fn memory_tracker(&self) -> &MemoryTracker {
+        &self.memory_tracker
+    }
+
+    /// Tracked version of memory::easy_upload_buffer()
+    ///
+    /// The resulting buffer is accounted for by the memory tracker. As vulkano
+    /// does not tell which memory type the buffer was allocated from, it is
+    /// assumed to come from the first device-local heap.
+    ///
+    pub fn upload_buffer<T>(
+        &self,
+        queue: &Arc<Queue>,
+        data: &[T]
+    ) -> Result<Arc<DeviceLocalBuffer<[T]>>>
+        where T: Copy + Send + Sync + 'static
+    {
+        let buffer = memory::easy_upload_buffer(&self.device, queue, data)?;
+        if let Some(heap) = self.memory_tracker.device_local_heap() {
+            self.memory_tracker.track(&buffer,
+                                      heap,
+                                      data.len() * mem::size_of::<T>());
+        }
+        Ok(buffer)
+    }
+
+    /// Find the first queue whose family matches a predicate
+    fn find_queue(
+        &self,
+        mut predicate: impl FnMut(&QueueFamily) -> bool
+    ) -> Option<&Arc<Queue>> {
+        self.queues.iter().find(|queue| predicate(&queue.family()))
+    }
+}
+
+
+/// Log which capabilities and queues a freshly created device has
+fn log_device_summary(device: &Device,
+                      queues: &[Arc<Queue>],
+                      capabilities: &EnabledCapabilities) {
+    info!("Created device \"{}\"", device.physical_device().name());
+    info!("Enabled features: {}",
+          report::feature_names(&capabilities.features).join(", "));
+    info!("Enabled extensions: {}",
+          report::extension_names(&capabilities.extensions).join(", "));
+    for queue in queues {
+        info!("Queue #{} of family {}",
+              queue.id_within_family(),
+              queue.family().id());
+    }
+}
+
+
+/// Features and extensions which are enabled on a device
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnabledCapabilities {
+    /// Enabled device features
+    pub features: Features,
+
+    /// Enabled device extensions
+    pub extensions: DeviceExtensions,
+}
+
+
+/// A group of physical devices, for multi-GPU workflows
+///
+/// Vulkan device groups, where multiple GPUs act as a single logical device,
+/// are not supported by vulkano yet. This is a simpler abstraction where each
+/// physical device gets its own logical device.
+///
+pub struct EasyDeviceGroup {
+    /// Physical devices, sorted by decreasing preference
+    devices: Vec<EasyPhysicalDevice>,
+}
+
+impl EasyDeviceGroup {
+    /// Build an EasyDeviceGroup from a list of physical devices
+    pub(crate) fn new(devices: Vec<EasyPhysicalDevice>) -> Self {
+        EasyDeviceGroup {
+            devices,
+        }
+    }
+
+    /// Access the physical devices, sorted by decreasing preference
+    pub fn physical_devices(&self) -> &[EasyPhysicalDevice] {
+        &self.devices[..]
+    }
+
+    /// Number of physical devices in the group
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// Truth that the group contains no physical device
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Setup one logical device per physical device, in a single-queue
+    /// configuration (see EasyPhysicalDevice::setup_single_queue_device())
+    ///
+    /// The queue filter and preference are applied to every device. If no
+    /// suitable queue family is found on one of the devices, an error is
+    /// returned, as that device should have been filtered out beforehand.
+    ///
+    pub fn setup_single_queue_devices(
+        &self,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        mut filter: impl FnMut(&QueueFamily) -> bool,
+        mut preference: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+    ) -> Result<Vec<EasyDevice>> {
+        let mut result = Vec::with_capacity(self.devices.len());
+        for device in &self.devices {
+            let setup = device.setup_single_queue_device(features,
+                                                         extensions,
+                                                         &mut filter,
+                                                         &mut preference)?;
+            match setup {
+                Some(easy_device) => result.push(easy_device),
+                None => return Err(Error::NoSuitableQueueFamily {
+                    device: device.physical_device().name(),
+                }),
+            }
+        }
+        Ok(result)
+    }
+}
+
+
+/// Union of two sets of features
+///
+/// Vulkano's Features struct only provides intersection and difference, so
+/// we go through the complement with respect to the set of all features.
+///
+fn features_union(features1: &Features, features2: &Features) -> Features {
+    let all = Features::all();
+    all.difference(&all.difference(features1)
+                       .intersection(&all.difference(features2)))
+}
+
+
+/// Names of a set of raw device extensions
+fn raw_extension_names(extensions: &RawDeviceExtensions) -> Vec<String> {
+    extensions.iter()
+              .map(|ext| ext.to_string_lossy().into_owned())
+              .collect()
+}
+
+
+/// Description of a group of queues that should be created for a given role
+pub struct QueueRequirement<'a> {
+    /// Name of the role that these queues will play
+    role: String,
+
+    /// Priority of each queue, between 0.0 and 1.0
+    priorities: Vec<f32>,
+
+    /// Queue family filter
+    filter: Box<dyn Fn(&QueueFamily) -> bool + 'a>,
+}
+
+impl<'a> QueueRequirement<'a> {
+    /// Require some queues, using a custom queue family filter
+    pub fn new(role: impl Into<String>,
+               count: usize,
+               priority: f32,
+               filter: impl Fn(&QueueFamily) -> bool + 'a) -> Self {
+        Self::with_priorities(role, vec![priority; count], filter)
+    }
+
+    /// Require some queues with individual priorities, using a custom queue
+    /// family filter (one queue is created per priority)
+    pub fn with_priorities(role: impl Into<String>,
+                           priorities: Vec<f32>,
+                           filter: impl Fn(&QueueFamily) -> bool + 'a) -> Self {
+        QueueRequirement {
+            role: role.into(),
+            priorities,
+            filter: Box::new(filter),
+        }
+    }
+
+    /// Number of queues to be created
+    pub fn count(&self) -> usize {
+        self.priorities.len()
+    }
+
+    /// Require some queues with graphics support, under the "graphics" role
+    pub fn graphics(count: usize, priority: f32) -> Self {
+        Self::new("graphics", count, priority, |f| f.supports_graphics())
+    }
+
+    /// Require some queues with compute support, under the "compute" role
+    pub fn compute(count: usize, priority: f32) -> Self {
+        Self::new("compute", count, priority, |f| f.supports_compute())
+    }
+
+    /// Require some queues with transfer support, under the "transfer" role
+    ///
+    /// Graphics and compute queues implicitly support transfers, as per the
+    /// Vulkan specification, so we accept them as well.
+    ///
+    pub fn transfer(count: usize, priority: f32) -> Self {
+        Self::new("transfer", count, priority, |f| {
+            f.supports_transfers() || f.supports_graphics()
+                                   || f.supports_compute()
+        })
+    }
+}
+
+
+/// Queues created by setup_multi_queue_device(), grouped by role
+#[derive(Clone, Default)]
+pub struct QueueSet {
+    /// Queues associated with each role
+    queues: HashMap<String, Vec<Arc<Queue>>>,
+}
+
+impl QueueSet {
+    /// Queues associated with a role (empty slice if there are none)
+    pub fn get(&self, role: &str) -> &[Arc<Queue>] {
+        self.queues.get(role).map(|queues| &queues[..]).unwrap_or(&[])
+    }
+
+    /// First queue associated with a role, if any
+    pub fn first(&self, role: &str) -> Option<&Arc<Queue>> {
+        self.get(role).first()
+    }
+
+    /// Iterate over roles and their associated queues
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &[Arc<Queue>])> {
+        self.queues.iter().map(|(role, queues)| (&role[..], &queues[..]))
+    }
+}
@@ -0,0 +1,176 @@
+//! Explanations of why physical devices were rejected
+//!
+//! When no physical device fits your needs, vulkanoob can tell you why each
+//! device was rejected, which is a lot more helpful than a bare None.
+
+use ::{
+    portability,
+    report,
+};
+
+use std::fmt;
+
+use vulkano::{
+    device::DeviceExtensions,
+    instance::{
+        Features,
+        PhysicalDevice,
+        QueueFamily,
+        Version,
+    },
+};
+
+
+/// Reason why a physical device was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The device's Vulkan API version is not supported by vulkanoob
+    ApiVersion(Version),
+
+    /// The Vulkan instance's API version is below the minimal version
+    /// required by the application, whatever the device supports
+    InstanceApiVersion(Version),
+
+    /// The device does not support some requested features
+    MissingFeatures(Vec<String>),
+
+    /// The device does not support some requested extensions
+    MissingExtensions(Vec<String>),
+
+    /// Some device limits are below the requested minimum
+    InsufficientLimits(Vec<String>),
+
+    /// No queue family of the device passed the queue filter
+    NoSuitableQueueFamily,
+
+    /// The device is a portability device, and those were forbidden
+    Portability,
+
+    /// The device was rejected by the user-defined criteria
+    UserFilter,
+
+    /// The device was excluded by an environment variable override
+    EnvOverride,
+
+    /// The device is a hardware implementation, and a software one was
+    /// required
+    NotSoftware,
+}
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RejectionReason::ApiVersion(ref version) =>
+                write!(f, "unsupported Vulkan API version {}", version),
+            RejectionReason::InstanceApiVersion(ref version) =>
+                write!(f, "Vulkan instance only supports API version {}",
+                       version),
+            RejectionReason::MissingFeatures(ref features) =>
+                write!(f, "missing feature(s) {}", features.join(", ")),
+            RejectionReason::MissingExtensions(ref extensions) =>
+                write!(f, "missing extension(s) {}", extensions.join(", ")),
+            RejectionReason::InsufficientLimits(ref violations) =>
+                write!(f, "insufficient limit(s): {}", violations.join(", ")),
+            RejectionReason::NoSuitableQueueFamily =>
+                write!(f, "no suitable queue family"),
+            RejectionReason::Portability =>
+                write!(f, "portability devices are not allowed"),
+            RejectionReason::UserFilter =>
+                write!(f, "rejected by user criteria"),
+            RejectionReason::EnvOverride =>
+                write!(f, "excluded by environment configuration"),
+            RejectionReason::NotSoftware =>
+                write!(f, "not a software implementation"),
+        }
+    }
+}
+
+
+/// Explanation of why a physical device was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceDiagnosis {
+    /// Index of the device in the instance's device list
+    pub index: usize,
+
+    /// Name of the device
+    pub name: String,
+
+    /// Reasons why the device was rejected
+    pub reasons: Vec<RejectionReason>,
+}
+
+impl fmt::Display for DeviceDiagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "device #{} ({}) rejected because of ",
+               self.index, self.name)?;
+        for (idx, reason) in self.reasons.iter().enumerate() {
+            if idx > 0 { write!(f, ", ")?; }
+            write!(f, "{}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Diagnosing counterpart of easy_device_filter()
+///
+/// Instead of telling whether a device is acceptable, the resulting closure
+/// returns the list of reasons why a device should be rejected, which is
+/// empty if the device is acceptable.
+///
+/// All basic requirements are checked, so that every problem is reported at
+/// once. The user-defined criteria are only checked if all basic
+/// requirements are met, as they may rely on them.
+///
+pub fn easy_device_diagnosis<'a>(
+    features: &'a Features,
+    extensions: &'a DeviceExtensions,
+    mut queue_filter: impl FnMut(&QueueFamily) -> bool + 'a,
+    mut other_criteria: impl FnMut(PhysicalDevice) -> bool + 'a
+) -> impl FnMut(PhysicalDevice) -> Vec<RejectionReason> + 'a {
+    move |dev: PhysicalDevice| -> Vec<RejectionReason> {
+        let mut reasons = Vec::new();
+
+        // This library was written against Vulkan v1.0.76. We tolerate older
+        // patch releases and new minor versions but not new major versions.
+        let min_ver = Version { major: 1, minor: 0, patch: 0 };
+        let max_ver = Version { major: 2, minor: 0, patch: 0 };
+        if (dev.api_version() < min_ver) || (dev.api_version() >= max_ver) {
+            reasons.push(RejectionReason::ApiVersion(dev.api_version()));
+        }
+
+        // Some features may be requested by the user, we need to look at them
+        let missing_features = features.difference(dev.supported_features());
+        if missing_features != Features::none() {
+            reasons.push(RejectionReason::MissingFeatures(
+                report::feature_names(&missing_features)
+            ));
+        }
+
+        // Same goes for device extensions
+        let missing_exts =
+            extensions.difference(&DeviceExtensions::supported_by_device(dev));
+        if missing_exts != DeviceExtensions::none() {
+            reasons.push(RejectionReason::MissingExtensions(
+                report::extension_names(&missing_exts)
+            ));
+        }
+
+        // At least one device queue family should fit our needs
+        if dev.queue_families().find(&mut queue_filter).is_none() {
+            reasons.push(RejectionReason::NoSuitableQueueFamily);
+        }
+
+        // Portability devices may have been forbidden by the user
+        if !portability::is_allowed() && portability::is_portability_device(dev)
+        {
+            reasons.push(RejectionReason::Portability);
+        }
+
+        // Test extra user filtering criteria
+        if reasons.is_empty() && !other_criteria(dev) {
+            reasons.push(RejectionReason::UserFilter);
+        }
+        reasons
+    }
+}
@@ -0,0 +1,112 @@
+//! Direct-to-display rendering (VK_KHR_display)
+//!
+//! Without a window system (e.g. on a kiosk or an embedded board booted to a
+//! console), Vulkan can still present images by driving a display directly.
+//! EasyInstance enables VK_KHR_display whenever it is available, and this
+//! module lists the displays attached to a physical device along with their
+//! modes, and creates surfaces which cover a whole display.
+//!
+//! The resulting surface is used like any other surface, e.g. with
+//! EasyPhysicalDevice::setup_presentation_device() and the swapchain module.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    ffi::CString,
+    sync::Arc,
+};
+
+use vulkano::{
+    instance::{
+        PhysicalDevice,
+        RawInstanceExtensions,
+    },
+    swapchain::{
+        display::{
+            Display,
+            DisplayMode,
+            DisplayPlane,
+        },
+        Surface,
+    },
+};
+
+
+/// Instance extension which is needed to drive displays directly
+pub const DISPLAY_EXTENSION: &str = "VK_KHR_display";
+
+
+/// Truth that displays can be enumerated on a physical device
+pub fn is_available(device: PhysicalDevice) -> bool {
+    device.instance().loaded_extensions().khr_display
+}
+
+/// List the displays which are attached to a physical device
+///
+/// This list is empty if VK_KHR_display is not available, and is typically
+/// also empty when a window system currently owns the displays.
+///
+pub fn displays(device: PhysicalDevice) -> Result<Vec<Display>> {
+    if !is_available(device) { return Ok(Vec::new()); }
+    Ok(Display::enumerate_raw(device)?.collect())
+}
+
+/// List the modes of a display
+pub fn display_modes(display: &Display) -> Result<Vec<DisplayMode>> {
+    Ok(display.display_modes_raw()?.collect())
+}
+
+/// Pick the mode with the largest visible region of a display, preferring
+/// higher refresh rates among modes of equal size
+pub fn preferred_mode(display: &Display) -> Result<Option<DisplayMode>> {
+    Ok(display_modes(display)?.into_iter().max_by_key(|mode| {
+        let [width, height] = mode.visible_region();
+        (u64::from(width) * u64::from(height), mode.refresh_rate())
+    }))
+}
+
+/// Create a surface which covers a whole display, using some display mode
+///
+/// The first display plane which can be used with this display is picked.
+///
+pub fn create_display_surface(display: &Display,
+                              mode: &DisplayMode) -> Result<Arc<Surface<()>>> {
+    let _trace = trace::span("display::create_display_surface", || {
+        format!("display: {}, mode: {:?} @ {} mHz",
+                display.name(), mode.visible_region(), mode.refresh_rate())
+    });
+    let device = display.physical_device();
+    let plane = DisplayPlane::enumerate_raw(device)?
+                             .find(|plane| plane.supports(display))
+                             .ok_or_else(|| Error::InvalidArgument(format!(
+                                 "No display plane of device \"{}\" can be \
+                                  used with display \"{}\"",
+                                 device.name(), display.name()
+                             )))?;
+    info!("Rendering directly to display \"{}\" in mode {:?} @ {} mHz, \
+           using plane #{}",
+          display.name(), mode.visible_region(), mode.refresh_rate(),
+          plane.index());
+    Ok(Surface::from_display_mode(mode, &plane)?)
+}
+
+
+/// Add the display extension to a set of instance extensions, if the Vulkan
+/// implementation supports it
+pub(crate) fn add_instance_extensions(
+    extensions: &mut RawInstanceExtensions,
+    supported: &RawInstanceExtensions,
+) {
+    if supported.iter().any(|ext| {
+        ext.as_bytes() == DISPLAY_EXTENSION.as_bytes()
+    }) {
+        info!("Enabling {} for direct-to-display rendering",
+              DISPLAY_EXTENSION);
+        extensions.insert(CString::new(DISPLAY_EXTENSION)
+                                  .expect("Extension name has no NUL"));
+    }
+}
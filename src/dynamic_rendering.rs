@@ -0,0 +1,525 @@
+//! Dynamic rendering (VK_KHR_dynamic_rendering, core in Vulkan 1.3)
+//!
+//! Dynamic rendering does away with render pass and framebuffer objects:
+//! rendering starts with a vkCmdBeginRendering command which lists the
+//! attachments directly, and graphics pipelines only need to know the
+//! attachment formats. This makes for a lot less boilerplate than the render
+//! pass path of the render and graphics_pipeline modules, so it should be
+//! preferred when available.
+//!
+//! This module tells which devices support dynamic rendering, enables the
+//! extensions and the dynamicRendering feature that it needs (see
+//! EasyPhysicalDevice::setup_dynamic_rendering_device()), and records
+//! begin/end rendering commands into raw command buffers. Graphics pipelines
+//! are created by chaining a PipelineRenderingCreateInfo to their
+//! VkGraphicsPipelineCreateInfo, with no render pass, which
+//! EasyGraphicsPipelineBuilder::build_for_dynamic_rendering() takes care of.
+//!
+//! Whether the core or the extension entry points are used depends on the
+//! API version which can be used on the device, which accounts for the
+//! instance's API version (see the api_level module).
+
+use ::{
+    api_level::VULKAN_1_3,
+    device::EasyDevice,
+    loader::{
+        self,
+        FeatureChain,
+        FeatureStruct,
+    },
+    properties2,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    cmp,
+    ffi::CString,
+    mem,
+    os::raw::c_void,
+    ptr,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    format::{
+        ClearValue,
+        Format,
+        FormatTy,
+    },
+    image::ImageViewAccess,
+    instance::{
+        PhysicalDevice,
+        RawDeviceExtensions,
+        Version,
+    },
+    VulkanObject,
+};
+
+
+/// Name of the dynamic rendering extension
+pub const DYNAMIC_RENDERING_EXTENSION: &str = "VK_KHR_dynamic_rendering";
+
+/// Extensions which dynamic rendering depends on before Vulkan 1.2, along
+/// with the version that made them part of core Vulkan
+const DYNAMIC_RENDERING_DEPENDENCIES: &[(&str, u32)] = &[
+    ("VK_KHR_depth_stencil_resolve", 2),
+    ("VK_KHR_create_renderpass2", 2),
+    ("VK_KHR_multiview", 1),
+    ("VK_KHR_maintenance2", 1),
+];
+
+/// Structure type of VkRenderingInfo
+const STRUCTURE_TYPE_RENDERING_INFO: u32 = 1_000_044_000;
+
+/// Structure type of VkRenderingAttachmentInfo
+const STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO: u32 = 1_000_044_001;
+
+/// Structure type of VkPipelineRenderingCreateInfo
+const STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO: u32 = 1_000_044_002;
+
+/// Structure type of VkPhysicalDeviceDynamicRenderingFeatures
+const STRUCTURE_TYPE_DYNAMIC_RENDERING_FEATURES: u32 = 1_000_044_003;
+
+
+/// Way in which a prototype should render
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Render passes and framebuffers, as set up by the render module
+    RenderPass,
+
+    /// Dynamic rendering, as set up by this module
+    DynamicRendering,
+}
+
+
+/// Summary of the dynamic rendering support of a physical device
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DynamicRenderingSupport {
+    /// Truth that dynamic rendering is part of the device's core API
+    pub is_core: bool,
+
+    /// Truth that the dynamicRendering feature is supported
+    pub feature: bool,
+
+    /// Extensions which must be enabled to use dynamic rendering
+    pub required_extensions: Vec<&'static str>,
+
+    /// Required extensions which are not supported
+    pub missing_extensions: Vec<String>,
+}
+
+impl DynamicRenderingSupport {
+    /// Probe the dynamic rendering support of a physical device, when used
+    /// from an instance of a certain API version
+    ///
+    /// Dynamic rendering is only part of the core API if both the device and
+    /// the instance support Vulkan 1.3. The dynamicRendering feature can only
+    /// be queried if the instance was created with
+    /// VK_KHR_get_physical_device_properties2. Otherwise, it is reported as
+    /// unsupported.
+    ///
+    pub fn of_device(device: PhysicalDevice,
+                     instance_version: Version) -> Result<Self> {
+        let _trace = trace::span("DynamicRenderingSupport::of_device", || {
+            format!("device: {}, instance_version: {}",
+                    device.name(), instance_version)
+        });
+        let version = cmp::min(device.api_version(), instance_version);
+        let minor = if version.major > 1 { u32::max_value() }
+                    else { u32::from(version.minor) };
+        let is_core = minor >= 3;
+
+        // Find out which extensions are needed, and which of them are missing
+        let mut required_extensions = Vec::new();
+        if !is_core {
+            required_extensions.push(DYNAMIC_RENDERING_EXTENSION);
+            required_extensions.extend(
+                DYNAMIC_RENDERING_DEPENDENCIES
+                    .iter()
+                    .filter(|&&(_, core_minor)| minor < core_minor)
+                    .map(|&(name, _)| name)
+            );
+        }
+        let extensions = RawDeviceExtensions::supported_by_device_raw(device)?;
+        let missing_extensions = required_extensions
+            .iter()
+            .filter(|&&name| {
+                !extensions.iter().any(|ext| ext.as_bytes() == name.as_bytes())
+            })
+            .map(|&name| name.to_owned())
+            .collect::<Vec<_>>();
+
+        // Query the dynamicRendering feature
+        let mut features = RawDynamicRenderingFeatures {
+            s_type: STRUCTURE_TYPE_DYNAMIC_RENDERING_FEATURES,
+            p_next: ptr::null_mut(),
+            dynamic_rendering: 0,
+        };
+        let feature = missing_extensions.is_empty() && unsafe {
+            properties2::query_features(
+                device,
+                &mut features as *mut RawDynamicRenderingFeatures
+                              as *mut c_void
+            )
+        } && features.dynamic_rendering != 0;
+
+        Ok(DynamicRenderingSupport {
+            is_core,
+            feature,
+            required_extensions,
+            missing_extensions,
+        })
+    }
+
+    /// Truth that the device supports dynamic rendering
+    pub fn is_supported(&self) -> bool {
+        self.missing_extensions.is_empty() && self.feature
+    }
+}
+
+/// VkPhysicalDeviceDynamicRenderingFeatures, which vk-sys does not know about
+#[repr(C)]
+struct RawDynamicRenderingFeatures {
+    s_type: u32,
+    p_next: *mut c_void,
+    dynamic_rendering: u32,
+}
+
+unsafe impl FeatureStruct for RawDynamicRenderingFeatures {}
+
+
+/// Device filter which only accepts devices that support dynamic rendering,
+/// when used from an instance of a certain API version
+///
+/// This is meant to be used as (part of) the "other_criteria" parameter of
+/// easy_device_filter(), with the version of the instance that the devices
+/// come from (EasyInstance::api_version()). Devices whose support cannot be
+/// queried are rejected.
+///
+pub fn supports_dynamic_rendering(
+    instance_version: Version
+) -> impl FnMut(PhysicalDevice) -> bool {
+    move |device| {
+        DynamicRenderingSupport::of_device(device, instance_version)
+            .map(|support| support.is_supported())
+            .unwrap_or(false)
+    }
+}
+
+/// Add the extensions needed by dynamic rendering to a set of device
+/// extensions, and the dynamicRendering feature to a feature chain, or tell
+/// what is missing
+///
+/// The API version is the one that will be used on the device (see
+/// EasyPhysicalDevice::api_version()), and must also be used to load the
+/// dynamic rendering entry points later on.
+///
+pub(crate) fn add_device_extensions(
+    device: PhysicalDevice,
+    api_version: Version,
+    extensions: &mut RawDeviceExtensions,
+    feature_chain: &mut FeatureChain,
+) -> Result<()> {
+    let _trace = trace::span("dynamic_rendering::add_device_extensions", || {
+        format!("device: {}, api_version: {}", device.name(), api_version)
+    });
+    let support = DynamicRenderingSupport::of_device(device, api_version)?;
+    if !support.missing_extensions.is_empty() {
+        return Err(Error::MissingExtensions {
+            device: device.name(),
+            extensions: support.missing_extensions,
+        });
+    }
+    if !support.feature {
+        return Err(Error::MissingFeatures {
+            device: device.name(),
+            features: vec!["dynamic_rendering".to_owned()],
+        });
+    }
+    for name in support.required_extensions {
+        extensions.insert(CString::new(name)
+                                  .expect("Extension name has no NUL"));
+    }
+    feature_chain.push(RawDynamicRenderingFeatures {
+        s_type: STRUCTURE_TYPE_DYNAMIC_RENDERING_FEATURES,
+        p_next: ptr::null_mut(),
+        dynamic_rendering: 1,
+    });
+    Ok(())
+}
+
+
+/// Attachment formats of a graphics pipeline which uses dynamic rendering
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipelineRenderingFormats {
+    /// Formats of the color attachments
+    pub color: Vec<Format>,
+
+    /// Format of the depth attachment, if any
+    pub depth: Option<Format>,
+
+    /// Format of the stencil attachment, if any
+    pub stencil: Option<Format>,
+
+    /// Views to render to with multiview rendering, or 0 to disable it
+    pub view_mask: u32,
+}
+
+impl PipelineRenderingFormats {
+    /// Formats matching easy_single_pass(): one color attachment, and an
+    /// optional depth(-stencil) attachment
+    pub fn single_pass(color_format: Format,
+                       depth_format: Option<Format>) -> Self {
+        let has_stencil = depth_format.map_or(false, |format| {
+            format.ty() == FormatTy::DepthStencil
+        });
+        PipelineRenderingFormats {
+            color: vec![color_format],
+            depth: depth_format,
+            stencil: if has_stencil { depth_format } else { None },
+            view_mask: 0,
+        }
+    }
+
+    /// Build the VkPipelineRenderingCreateInfo which must be chained to the
+    /// VkGraphicsPipelineCreateInfo of the pipeline
+    ///
+    /// The color formats are stored on the heap, so the result can be moved
+    /// around without invalidating it.
+    ///
+    pub fn create_info(&self) -> PipelineRenderingCreateInfo {
+        let color_formats = self.color.iter()
+                                      .map(|&format| format as vk::Format)
+                                      .collect::<Vec<_>>();
+        PipelineRenderingCreateInfo {
+            s_type: STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO,
+            p_next: ptr::null(),
+            view_mask: self.view_mask,
+            color_attachment_count: color_formats.len() as u32,
+            p_color_attachment_formats: color_formats.as_ptr(),
+            depth_attachment_format:
+                self.depth.map_or(vk::FORMAT_UNDEFINED, |f| f as vk::Format),
+            stencil_attachment_format:
+                self.stencil.map_or(vk::FORMAT_UNDEFINED, |f| f as vk::Format),
+            color_formats,
+        }
+    }
+}
+
+/// VkPipelineRenderingCreateInfo, to be chained to the
+/// VkGraphicsPipelineCreateInfo of pipelines which use dynamic rendering
+///
+/// The color formats are owned by the struct, after the Vulkan members.
+///
+#[derive(Debug)]
+#[repr(C)]
+pub struct PipelineRenderingCreateInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    view_mask: u32,
+    color_attachment_count: u32,
+    p_color_attachment_formats: *const vk::Format,
+    depth_attachment_format: vk::Format,
+    stencil_attachment_format: vk::Format,
+    color_formats: Vec<vk::Format>,
+}
+
+
+/// Attachment of a dynamic rendering scope
+pub struct RenderingAttachment<'a> {
+    /// Image view to render to, in the attachment layout which matches its
+    /// format (color or depth-stencil attachment optimal)
+    pub image: &'a dyn ImageViewAccess,
+
+    /// Value to clear the attachment with, or None to keep its contents
+    pub clear: Option<ClearValue>,
+
+    /// Truth that the rendering results should be stored
+    pub store: bool,
+}
+
+/// Description of a dynamic rendering scope
+pub struct RenderingInfo<'a> {
+    /// Dimensions of the area to render to, starting from the origin
+    pub extent: [u32; 2],
+
+    /// Color attachments
+    pub color: Vec<RenderingAttachment<'a>>,
+
+    /// Depth(-stencil) attachment, if any
+    pub depth: Option<RenderingAttachment<'a>>,
+}
+
+/// VkRenderingAttachmentInfo, which vk-sys does not know about
+#[repr(C)]
+struct RawRenderingAttachmentInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    image_view: vk::ImageView,
+    image_layout: vk::ImageLayout,
+    resolve_mode: u32,
+    resolve_image_view: vk::ImageView,
+    resolve_image_layout: vk::ImageLayout,
+    load_op: vk::AttachmentLoadOp,
+    store_op: vk::AttachmentStoreOp,
+    /// VkClearValue, as raw bits
+    clear_value: [u32; 4],
+}
+
+/// VkRenderingInfo, which vk-sys does not know about
+#[repr(C)]
+struct RawRenderingInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    flags: u32,
+    render_area: vk::Rect2D,
+    layer_count: u32,
+    view_mask: u32,
+    color_attachment_count: u32,
+    p_color_attachments: *const RawRenderingAttachmentInfo,
+    p_depth_attachment: *const RawRenderingAttachmentInfo,
+    p_stencil_attachment: *const RawRenderingAttachmentInfo,
+}
+
+impl<'a> RenderingAttachment<'a> {
+    /// Convert to a VkRenderingAttachmentInfo, for a given layout
+    fn to_raw(&self, layout: vk::ImageLayout) -> RawRenderingAttachmentInfo {
+        let clear_value = match self.clear {
+            Some(ClearValue::Float(color)) => [color[0].to_bits(),
+                                               color[1].to_bits(),
+                                               color[2].to_bits(),
+                                               color[3].to_bits()],
+            Some(ClearValue::Int(color)) => [color[0] as u32,
+                                             color[1] as u32,
+                                             color[2] as u32,
+                                             color[3] as u32],
+            Some(ClearValue::Uint(color)) => color,
+            Some(ClearValue::Depth(depth)) => [depth.to_bits(), 0, 0, 0],
+            Some(ClearValue::Stencil(stencil)) => [0, stencil, 0, 0],
+            Some(ClearValue::DepthStencil((depth, stencil))) =>
+                [depth.to_bits(), stencil, 0, 0],
+            Some(ClearValue::None) | None => [0; 4],
+        };
+        RawRenderingAttachmentInfo {
+            s_type: STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO,
+            p_next: ptr::null(),
+            image_view: self.image.inner().internal_object(),
+            image_layout: layout,
+            resolve_mode: 0,
+            resolve_image_view: 0,
+            resolve_image_layout: vk::IMAGE_LAYOUT_UNDEFINED,
+            load_op: if self.clear.is_some() {
+                vk::ATTACHMENT_LOAD_OP_CLEAR
+            } else {
+                vk::ATTACHMENT_LOAD_OP_LOAD
+            },
+            store_op: if self.store {
+                vk::ATTACHMENT_STORE_OP_STORE
+            } else {
+                vk::ATTACHMENT_STORE_OP_DONT_CARE
+            },
+            clear_value,
+        }
+    }
+}
+
+
+/// Begin a dynamic rendering scope in a raw command buffer
+///
+/// Color attachments must be in the color attachment optimal layout, and the
+/// depth attachment in the depth-stencil attachment optimal layout. If the
+/// depth attachment has a stencil aspect, it is used as the stencil
+/// attachment as well.
+///
+/// # Safety
+///
+/// The command buffer must belong to the device and be in the recording
+/// state, outside of any rendering scope. The attachments must stay alive
+/// until the command buffer is done executing. An error is returned if
+/// dynamic rendering was not enabled on the device.
+///
+pub unsafe fn begin_rendering(device: &EasyDevice,
+                              command_buffer: vk::CommandBuffer,
+                              info: &RenderingInfo) -> Result<()> {
+    let _trace = trace::span("dynamic_rendering::begin_rendering", || {
+        format!("extent: {:?}, color attachments: {}, depth: {}",
+                info.extent, info.color.len(), info.depth.is_some())
+    });
+    let colors = info.color.iter().map(|attachment| {
+        attachment.to_raw(vk::IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL)
+    }).collect::<Vec<_>>();
+    let depth = info.depth.as_ref().map(|attachment| {
+        attachment.to_raw(vk::IMAGE_LAYOUT_DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+    });
+    let has_stencil = info.depth.as_ref().map_or(false, |attachment| {
+        attachment.image.parent().format().ty() == FormatTy::DepthStencil
+    });
+    let depth_ptr = depth.as_ref()
+                         .map_or(ptr::null(), |depth| depth as *const _);
+    let rendering_info = RawRenderingInfo {
+        s_type: STRUCTURE_TYPE_RENDERING_INFO,
+        p_next: ptr::null(),
+        flags: 0,
+        render_area: vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: vk::Extent2D {
+                width: info.extent[0],
+                height: info.extent[1],
+            },
+        },
+        layer_count: 1,
+        view_mask: 0,
+        color_attachment_count: colors.len() as u32,
+        p_color_attachments: colors.as_ptr(),
+        p_depth_attachment: depth_ptr,
+        p_stencil_attachment: if has_stencil { depth_ptr } else { ptr::null() },
+    };
+    let cmd_begin_rendering: extern "system" fn(vk::CommandBuffer,
+                                                *const RawRenderingInfo) =
+        mem::transmute(rendering_function(device,
+                                          rendering_version(device)?,
+                                          "CmdBeginRendering")?);
+    cmd_begin_rendering(command_buffer, &rendering_info);
+    Ok(())
+}
+
+/// End a dynamic rendering scope in a raw command buffer
+///
+/// # Safety
+///
+/// The command buffer must be in a rendering scope started by
+/// begin_rendering().
+///
+pub unsafe fn end_rendering(device: &EasyDevice,
+                            command_buffer: vk::CommandBuffer) -> Result<()> {
+    let _trace = trace::span("dynamic_rendering::end_rendering", String::new);
+    let cmd_end_rendering: extern "system" fn(vk::CommandBuffer) =
+        mem::transmute(rendering_function(device,
+                                          rendering_version(device)?,
+                                          "CmdEndRendering")?);
+    cmd_end_rendering(command_buffer);
+    Ok(())
+}
+
+/// API version with which dynamic rendering was set up on a device, or an
+/// error if it was not enabled
+fn rendering_version(device: &EasyDevice) -> Result<Version> {
+    device.dynamic_rendering_version().ok_or_else(|| Error::InvalidArgument(
+        "Dynamic rendering was not enabled on this device, see \
+         EasyPhysicalDevice::setup_dynamic_rendering_device()".to_owned()
+    ))
+}
+
+/// Load a dynamic rendering entry point, using its core name if the device
+/// was set up for dynamic rendering with Vulkan 1.3, and its extension name
+/// otherwise
+fn rendering_function(device: &EasyDevice,
+                      api_version: Version,
+                      name: &str) -> Result<*const c_void> {
+    let suffix = if api_version >= VULKAN_1_3 { "" } else { "KHR" };
+    loader::device_function(device.device(),
+                            &format!("vk{}{}", name, suffix))
+}
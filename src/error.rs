@@ -0,0 +1,328 @@
+//! Error handling
+//!
+//! Errors which vulkanoob can diagnose itself get a dedicated Error variant,
+//! with enough context to tell what went wrong. Errors coming from vulkano
+//! are wrapped in the Vulkan variant, from which the original error can be
+//! recovered using failure::Error::downcast_ref().
+
+use ::diagnosis::DeviceDiagnosis;
+
+use failure;
+
+use std::{
+    ffi::NulError,
+    fmt,
+    io,
+    result,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    buffer::{
+        cpu_access::{
+            ReadLockError,
+            WriteLockError,
+        },
+        sys::BufferCreationError,
+    },
+    command_buffer::{
+        BlitImageError,
+        BuildError,
+        CommandBufferExecError,
+        CopyBufferError,
+        CopyBufferImageError,
+        DispatchError,
+        DrawIndexedError,
+        DrawIndirectError,
+    },
+    descriptor::descriptor_set::{
+        DescriptorPoolAllocError,
+        PersistentDescriptorSetBuildError,
+        PersistentDescriptorSetError,
+    },
+    device::DeviceCreationError,
+    framebuffer::{
+        FramebufferCreationError,
+        RenderPassCreationError,
+    },
+    image::ImageCreationError,
+    instance::{
+        debug::DebugCallbackCreationError,
+        loader::LoadingError,
+        InstanceCreationError,
+        LayersListError,
+        SupportedExtensionsError,
+    },
+    memory::DeviceMemoryAllocError,
+    pipeline::{
+        ComputePipelineCreationError,
+        GraphicsPipelineCreationError,
+    },
+    query::QueryPoolCreationError,
+    sampler::SamplerCreationError,
+    swapchain::{
+        AcquireError,
+        CapabilitiesError,
+        SurfaceCreationError,
+        SwapchainCreationError,
+    },
+    sync::{
+        AccessError,
+        FlushError,
+    },
+    OomError,
+};
+
+
+/// Result type used throughout vulkanoob
+pub type Result<T> = result::Result<T, Error>;
+
+
+/// Things that can go wrong when using vulkanoob
+#[derive(Debug, Fail)]
+pub enum Error {
+    /// No physical device matched the requirements
+    #[fail(display = "No physical device matches the requirements ({})",
+           _0)]
+    NoSuitableDevice(Diagnoses),
+
+    /// The Vulkan implementation does not support some instance extensions
+    #[fail(display = "Instance extension(s) {:?} are not supported", _0)]
+    MissingInstanceExtensions(Vec<String>),
+
+    /// A device does not support some requested extensions
+    #[fail(display = "Device \"{}\" does not support extension(s) {:?}",
+           device, extensions)]
+    MissingExtensions {
+        /// Name of the device
+        device: String,
+
+        /// Names of the missing extensions
+        extensions: Vec<String>,
+    },
+
+    /// A device does not support some requested features
+    #[fail(display = "Device \"{}\" does not support feature(s) {:?}",
+           device, features)]
+    MissingFeatures {
+        /// Name of the device
+        device: String,
+
+        /// Names of the missing features, in vulkano's naming convention
+        features: Vec<String>,
+    },
+
+    /// Some limits of a device are lower than what the application needs
+    #[fail(display = "Device \"{}\" has insufficient limits: {:?}",
+           device, violations)]
+    InsufficientLimits {
+        /// Name of the device
+        device: String,
+
+        /// Description of the violated limits
+        violations: Vec<String>,
+    },
+
+    /// A device has no queue family that fits the requirements
+    #[fail(display = "Device \"{}\" has no suitable queue family", device)]
+    NoSuitableQueueFamily {
+        /// Name of the device
+        device: String,
+    },
+
+    /// A surface cannot be used in the way that vulkanoob needs
+    #[fail(display = "Unsupported surface: {}", _0)]
+    UnsupportedSurface(String),
+
+    /// GLSL shader compilation failed, with the listed diagnostics
+    #[fail(display = "GLSL compilation failed:\n{}", _0)]
+    ShaderCompilation(String),
+
+    /// A parameter passed to vulkanoob is invalid
+    #[fail(display = "Invalid argument: {}", _0)]
+    InvalidArgument(String),
+
+    /// An error was reported by vulkano or the Vulkan implementation
+    #[fail(display = "{}", _0)]
+    Vulkan(failure::Error),
+
+    /// Any other error (I/O, serialization, user code...)
+    #[fail(display = "{}", _0)]
+    Other(failure::Error),
+}
+
+/// Explanations of why each physical device was rejected
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagnoses(pub Vec<DeviceDiagnosis>);
+
+impl fmt::Display for Diagnoses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "no device found");
+        }
+        for (idx, diagnosis) in self.0.iter().enumerate() {
+            if idx > 0 { write!(f, "; ")?; }
+            write!(f, "{}", diagnosis)?;
+        }
+        Ok(())
+    }
+}
+
+
+impl Error {
+    /// Truth that this error signals the loss of a device
+    ///
+    /// A lost device cannot be used anymore, and must be recreated. See
+    /// EasyDevice::on_device_lost() for a way to automate this.
+    ///
+    pub fn is_device_lost(&self) -> bool {
+        let error = match *self {
+            Error::Vulkan(ref error) => error,
+            _ => return false,
+        };
+        if let Some(FlushError::DeviceLost) = error.downcast_ref() {
+            return true;
+        }
+        if let Some(AcquireError::DeviceLost) = error.downcast_ref() {
+            return true;
+        }
+        if let Some(DeviceCreationError::DeviceLost) = error.downcast_ref() {
+            return true;
+        }
+        false
+    }
+
+    /// Truth that this error signals that no usable Vulkan implementation is
+    /// installed, i.e. that the Vulkan loader or a compatible driver is missing
+    pub fn is_vulkan_unavailable(&self) -> bool {
+        let error = match *self {
+            Error::Vulkan(ref error) => error,
+            _ => return false,
+        };
+        if error.downcast_ref::<LoadingError>().is_some() {
+            return true;
+        }
+        match error.downcast_ref() {
+            Some(InstanceCreationError::LoadingError(_))
+            | Some(InstanceCreationError::IncompatibleDriver) => return true,
+            _ => {},
+        }
+        if let Some(SupportedExtensionsError::LoadingError(_)) =
+            error.downcast_ref()
+        {
+            return true;
+        }
+        if let Some(LayersListError::LoadingError(_)) = error.downcast_ref() {
+            return true;
+        }
+        false
+    }
+}
+
+
+impl From<failure::Error> for Error {
+    fn from(error: failure::Error) -> Self {
+        Error::Other(error)
+    }
+}
+
+
+/// Implement From conversions from vulkano errors to our Error type
+macro_rules! vulkan_errors {
+    ($($error:ty),* $(,)*) => {
+        $(
+            impl From<$error> for Error {
+                fn from(error: $error) -> Self {
+                    Error::Vulkan(error.into())
+                }
+            }
+        )*
+    };
+}
+
+vulkan_errors!(
+    AccessError,
+    AcquireError,
+    BlitImageError,
+    BufferCreationError,
+    BuildError,
+    CapabilitiesError,
+    CommandBufferExecError,
+    ComputePipelineCreationError,
+    CopyBufferError,
+    CopyBufferImageError,
+    DebugCallbackCreationError,
+    DescriptorPoolAllocError,
+    DeviceCreationError,
+    DeviceMemoryAllocError,
+    DispatchError,
+    DrawIndexedError,
+    DrawIndirectError,
+    FlushError,
+    FramebufferCreationError,
+    GraphicsPipelineCreationError,
+    ImageCreationError,
+    InstanceCreationError,
+    LayersListError,
+    LoadingError,
+    OomError,
+    PersistentDescriptorSetBuildError,
+    PersistentDescriptorSetError,
+    QueryPoolCreationError,
+    ReadLockError,
+    RenderPassCreationError,
+    SamplerCreationError,
+    SupportedExtensionsError,
+    SurfaceCreationError,
+    SwapchainCreationError,
+    WriteLockError,
+);
+
+/// Turn the result code of a raw Vulkan call into a Result
+pub(crate) fn check_result(result: vk::Result, function: &str) -> Result<()> {
+    if result == vk::SUCCESS {
+        Ok(())
+    } else {
+        Err(Error::Vulkan(format_err!("{} failed with error code {}",
+                                      function, result)))
+    }
+}
+
+
+/// Implement From conversions from other errors to our Error type
+macro_rules! other_errors {
+    ($($error:ty),* $(,)*) => {
+        $(
+            impl From<$error> for Error {
+                fn from(error: $error) -> Self {
+                    Error::Other(error.into())
+                }
+            }
+        )*
+    };
+}
+
+other_errors!(
+    fmt::Error,
+    io::Error,
+    NulError,
+);
+
+#[cfg(feature = "hot-reload")]
+other_errors!(::notify::Error);
+
+#[cfg(any(feature = "image-loading", feature = "png-export"))]
+other_errors!(::image_crate::ImageError);
+
+#[cfg(feature = "serde-export")]
+other_errors!(::serde_json::Error);
+
+#[cfg(feature = "toml-export")]
+other_errors!(::toml::ser::Error);
+
+#[cfg(feature = "toml-export")]
+other_errors!(::toml::de::Error);
+
+#[cfg(feature = "window")]
+other_errors!(::vulkano_win::CreationError);
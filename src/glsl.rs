@@ -0,0 +1,138 @@
+//! Runtime GLSL to SPIR-V compilation, using shaderc
+//!
+//! This lets quick prototypes embed GLSL sources and compile them at startup,
+//! rather than setting up a shader compilation pipeline in their build script.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use shaderc::{
+    self,
+    CompileOptions,
+    Compiler,
+    ShaderKind,
+};
+
+
+/// Pipeline stage which a GLSL shader is meant for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    /// Vertex shader
+    Vertex,
+
+    /// Tessellation control shader
+    TessellationControl,
+
+    /// Tessellation evaluation shader
+    TessellationEvaluation,
+
+    /// Geometry shader
+    Geometry,
+
+    /// Fragment shader
+    Fragment,
+
+    /// Compute shader
+    Compute,
+}
+
+impl ShaderStage {
+    /// Translate this stage into shaderc's terminology
+    fn shader_kind(self) -> ShaderKind {
+        match self {
+            ShaderStage::Vertex => ShaderKind::Vertex,
+            ShaderStage::TessellationControl => ShaderKind::TessControl,
+            ShaderStage::TessellationEvaluation => ShaderKind::TessEvaluation,
+            ShaderStage::Geometry => ShaderKind::Geometry,
+            ShaderStage::Fragment => ShaderKind::Fragment,
+            ShaderStage::Compute => ShaderKind::Compute,
+        }
+    }
+}
+
+
+/// Compile GLSL source code into SPIR-V words
+///
+/// The shader's entry point must be called "main". Compiler warnings are
+/// logged, and compilation errors are reported as Error::ShaderCompilation,
+/// with one "line L, column C: message" entry per diagnostic.
+///
+pub fn easy_compile_glsl(source: &str, stage: ShaderStage) -> Result<Vec<u32>> {
+    let _trace = trace::span("easy_compile_glsl",
+                             || format!("source: {} bytes, stage: {:?}",
+                                        source.len(), stage));
+    const FILE_NAME: &str = "shader.glsl";
+
+    let mut compiler = Compiler::new().ok_or_else(|| {
+        format_err!("Failed to initialize the shaderc compiler")
+    })?;
+    let mut options = CompileOptions::new().ok_or_else(|| {
+        format_err!("Failed to initialize the shaderc compiler options")
+    })?;
+    options.set_generate_debug_info();
+    let result = compiler.compile_into_spirv(source,
+                                             stage.shader_kind(),
+                                             FILE_NAME,
+                                             "main",
+                                             Some(&options));
+
+    match result {
+        Ok(artifact) => {
+            if artifact.get_num_warnings() > 0 {
+                warn!("GLSL compiler warnings:\n{}",
+                      format_diagnostics(FILE_NAME,
+                                         &artifact.get_warning_messages()));
+            }
+            Ok(artifact.as_binary().to_owned())
+        },
+        Err(shaderc::Error::CompilationError(_, messages)) => {
+            let diagnostics = format_diagnostics(FILE_NAME, &messages);
+            error!("GLSL compilation failed:\n{}", diagnostics);
+            Err(Error::ShaderCompilation(diagnostics))
+        },
+        Err(e) => Err(format_err!("shaderc error: {:?}", e).into()),
+    }
+}
+
+
+/// Reformat shaderc diagnostics as "line L, column C: message" entries
+fn format_diagnostics(file_name: &str, messages: &str) -> String {
+    messages.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| format!("  {}", format_diagnostic(file_name, line)))
+            .collect::<Vec<_>>()
+            .join("\n")
+}
+
+/// Reformat a single shaderc diagnostic
+///
+/// shaderc emits diagnostics of the form "file:line: severity: message" or
+/// "file:line:column: severity: message". Lines which do not follow this
+/// pattern are passed through as is.
+///
+fn format_diagnostic(file_name: &str, line: &str) -> String {
+    let prefix = format!("{}:", file_name);
+    if !line.starts_with(&prefix) {
+        return line.to_owned();
+    }
+    let mut parts = line[prefix.len()..].splitn(3, ':');
+    let line_num = match parts.next().map(|s| s.trim().parse::<u32>()) {
+        Some(Ok(line_num)) => line_num,
+        _ => return line.to_owned(),
+    };
+    let second = parts.next().unwrap_or("");
+    let rest = parts.next();
+    match (second.trim().parse::<u32>(), rest) {
+        (Ok(column), Some(message)) => {
+            format!("line {}, column {}: {}", line_num, column, message.trim())
+        },
+        (_, Some(rest)) => {
+            format!("line {}: {}:{}", line_num, second.trim(), rest)
+        },
+        (_, None) => format!("line {}: {}", line_num, second.trim()),
+    }
+}
@@ -0,0 +1,317 @@
+//! Graphics pipelines with sane defaults
+//!
+//! Vulkano's GraphicsPipelineBuilder exposes every knob of the Vulkan graphics
+//! pipeline, which is a lot of boilerplate to go through before the first
+//! triangle shows up. EasyGraphicsPipelineBuilder only needs a vertex type, a
+//! vertex shader, a fragment shader and a render pass, and makes the usual
+//! prototyping choices for the rest:
+//!
+//! - Vertices are assembled as a triangle list
+//! - Back-face culling is disabled, so that winding order mistakes do not
+//!   make geometry disappear
+//! - Depth testing is disabled
+//! - Blending is disabled
+//! - The viewport and scissor are dynamic, so that the pipeline does not need
+//!   to be rebuilt when the window is resized. Use easy_dynamic_state() or
+//!   EasyRenderTargets::dynamic_state_with_scissor() when drawing.
+//!
+//! Blending, depth testing, culling and topology can be toggled as needed.
+//! Pipelines can target either a render pass, or dynamic rendering on devices
+//! which support it (see EasyDevice::preferred_render_path()).
+
+use ::{
+    device::EasyDevice,
+    dynamic_rendering::PipelineRenderingFormats,
+    loader,
+    render::{
+        self,
+        EasyRenderPass,
+    },
+    trace,
+    vertex::EasyVertex,
+    Error,
+    Result,
+};
+
+use std::{
+    os::raw::c_void,
+    sync::Arc,
+};
+
+use vulkano::{
+    command_buffer::DynamicState,
+    device::Device,
+    framebuffer::{
+        RenderPassSubpassInterface,
+        Subpass,
+    },
+    pipeline::{
+        blend::{
+            AttachmentBlend,
+            BlendFactor,
+        },
+        input_assembly::PrimitiveTopology,
+        shader::{
+            GraphicsEntryPointAbstract,
+            ShaderInterfaceDefMatch,
+        },
+        vertex::{
+            SingleBufferDefinition,
+            VertexDefinition,
+        },
+        viewport::{
+            Scissor,
+            Viewport,
+        },
+        GraphicsPipeline,
+        GraphicsPipelineAbstract,
+    },
+};
+
+
+/// Graphics pipeline type built by EasyGraphicsPipelineBuilder
+pub type EasyGraphicsPipeline =
+    Arc<dyn GraphicsPipelineAbstract + Send + Sync>;
+
+
+/// How the fragment shader output is combined with the framebuffer contents
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blending {
+    /// The output overwrites the framebuffer contents
+    Opaque,
+
+    /// The output is blended according to its alpha channel (transparency)
+    AlphaBlending,
+
+    /// The output, weighted by its alpha channel, is added to the framebuffer
+    /// contents (particles, glow...)
+    Additive,
+}
+
+impl Blending {
+    /// Vulkano description of the blending
+    fn attachment_blend(&self) -> AttachmentBlend {
+        match *self {
+            Blending::Opaque => AttachmentBlend::pass_through(),
+            Blending::AlphaBlending => AttachmentBlend::alpha_blending(),
+            Blending::Additive => AttachmentBlend {
+                color_destination: BlendFactor::One,
+                alpha_source: BlendFactor::One,
+                alpha_destination: BlendFactor::One,
+                .. AttachmentBlend::alpha_blending()
+            },
+        }
+    }
+}
+
+
+/// Builder of graphics pipelines with sane defaults, see the module docs
+#[derive(Clone, Debug)]
+pub struct EasyGraphicsPipelineBuilder {
+    /// Primitive topology
+    topology: PrimitiveTopology,
+
+    /// Truth that back faces should be culled
+    cull_back_faces: bool,
+
+    /// Truth that depth testing (and writing) should be enabled
+    depth_test: bool,
+
+    /// How the fragment shader output should be blended
+    blending: Blending,
+
+    /// Index of the render pass' subpass which the pipeline is used in
+    subpass: u32,
+}
+
+impl EasyGraphicsPipelineBuilder {
+    /// Start building a graphics pipeline with the default settings
+    pub fn new() -> Self {
+        EasyGraphicsPipelineBuilder {
+            topology: PrimitiveTopology::TriangleList,
+            cull_back_faces: false,
+            depth_test: false,
+            blending: Blending::Opaque,
+            subpass: 0,
+        }
+    }
+
+    /// Assemble vertices into other primitives than a triangle list
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Cull back faces, whose vertices are in clockwise order on screen
+    pub fn cull_back_faces(mut self, enabled: bool) -> Self {
+        self.cull_back_faces = enabled;
+        self
+    }
+
+    /// Enable depth testing, which requires a subpass with a depth attachment
+    ///
+    /// Fragments are kept if they are closer than what was drawn before
+    /// (i.e. if their depth is lower), and their depth is then written.
+    ///
+    pub fn depth_test(mut self, enabled: bool) -> Self {
+        self.depth_test = enabled;
+        self
+    }
+
+    /// Specify how the fragment shader output should be blended
+    pub fn blending(mut self, blending: Blending) -> Self {
+        self.blending = blending;
+        self
+    }
+
+    /// Use the pipeline in another subpass than the first one
+    pub fn subpass(mut self, subpass: u32) -> Self {
+        self.subpass = subpass;
+        self
+    }
+
+    /// Build the pipeline
+    ///
+    /// The vertex layout is checked against the device limits, see
+    /// EasyVertex. Shader entry points are typically generated by
+    /// vulkano-shaders, and must not take specialization constants.
+    ///
+    pub fn build<V, Vs, Fs>(
+        &self,
+        device: &Arc<Device>,
+        render_pass: &EasyRenderPass,
+        vertex_shader: Vs,
+        fragment_shader: Fs
+    ) -> Result<EasyGraphicsPipeline>
+        where V: EasyVertex,
+              Vs: GraphicsEntryPointAbstract<SpecializationConstants=()>,
+              Fs: GraphicsEntryPointAbstract<SpecializationConstants=()>,
+              Vs::PipelineLayout: Clone + Send + Sync + 'static,
+              Fs::PipelineLayout: Clone + Send + Sync + 'static,
+              Fs::InputDefinition:
+                  ShaderInterfaceDefMatch<Vs::OutputDefinition>,
+              SingleBufferDefinition<V>: VertexDefinition<Vs::InputDefinition>,
+              EasyRenderPass: RenderPassSubpassInterface<Fs::OutputDefinition>
+    {
+        let _trace = trace::span("EasyGraphicsPipelineBuilder::build",
+                                 || format!("{:?}", self));
+
+        // Check the configuration against the render pass
+        let subpass = Subpass::from(render_pass.clone(), self.subpass)
+                              .ok_or_else(|| Error::InvalidArgument(format!(
+                                  "Render pass has no subpass #{}",
+                                  self.subpass
+                              )))?;
+        if self.depth_test && !subpass.has_depth() {
+            return Err(Error::InvalidArgument(
+                "Depth testing requires a subpass with a depth attachment"
+                    .to_owned()
+            ));
+        }
+        let vertex_input = V::definition(device.physical_device())?;
+
+        // Configure the pipeline
+        let builder = GraphicsPipeline::start()
+            .vertex_input(vertex_input)
+            .vertex_shader(vertex_shader, ())
+            .primitive_topology(self.topology)
+            .viewports_scissors_dynamic(1)
+            .fragment_shader(fragment_shader, ())
+            .blend_collective(self.blending.attachment_blend());
+        let builder = if self.cull_back_faces {
+            builder.cull_mode_back()
+        } else {
+            builder.cull_mode_disabled()
+        };
+        let builder = if self.depth_test {
+            builder.depth_stencil_simple_depth()
+        } else {
+            builder.depth_stencil_disabled()
+        };
+        Ok(Arc::new(builder.render_pass(subpass).build(device.clone())?))
+    }
+
+    /// Build the pipeline for dynamic rendering, with some attachment formats
+    ///
+    /// This works like build(), but the pipeline is used inside of
+    /// dynamic_rendering::begin_rendering() scopes instead of a render pass.
+    /// Like easy_single_pass(), only one color attachment and an optional
+    /// depth(-stencil) attachment are supported. The device must have been
+    /// set up using EasyPhysicalDevice::setup_dynamic_rendering_device().
+    ///
+    pub fn build_for_dynamic_rendering<V, Vs, Fs>(
+        &self,
+        device: &EasyDevice,
+        formats: &PipelineRenderingFormats,
+        vertex_shader: Vs,
+        fragment_shader: Fs
+    ) -> Result<EasyGraphicsPipeline>
+        where V: EasyVertex,
+              Vs: GraphicsEntryPointAbstract<SpecializationConstants=()>,
+              Fs: GraphicsEntryPointAbstract<SpecializationConstants=()>,
+              Vs::PipelineLayout: Clone + Send + Sync + 'static,
+              Fs::PipelineLayout: Clone + Send + Sync + 'static,
+              Fs::InputDefinition:
+                  ShaderInterfaceDefMatch<Vs::OutputDefinition>,
+              SingleBufferDefinition<V>: VertexDefinition<Vs::InputDefinition>,
+              EasyRenderPass: RenderPassSubpassInterface<Fs::OutputDefinition>
+    {
+        let _trace = trace::span(
+            "EasyGraphicsPipelineBuilder::build_for_dynamic_rendering",
+            || format!("{:?}, formats: {:?}", self, formats)
+        );
+        if !device.supports_dynamic_rendering() {
+            return Err(Error::InvalidArgument(
+                "Dynamic rendering was not enabled on this device".to_owned()
+            ));
+        }
+        if formats.color.len() != 1 || self.subpass != 0 {
+            return Err(Error::InvalidArgument(
+                "Dynamic rendering pipelines must have exactly one color \
+                 attachment and no subpass index".to_owned()
+            ));
+        }
+
+        // Vulkano checks pipelines against a render pass, so build one with
+        // the same attachments, which the loader then swaps for the formats
+        let render_pass = render::easy_single_pass(device.device(),
+                                                   formats.color[0],
+                                                   formats.depth,
+                                                   1)?;
+        let mut rendering_info = formats.create_info();
+        // Safe because the rendering info outlives pipeline creation
+        unsafe {
+            loader::with_pipeline_rendering(
+                &mut rendering_info as *mut _ as *mut c_void,
+                || self.build::<V, _, _>(device.device(),
+                                         render_pass.render_pass(),
+                                         vertex_shader,
+                                         fragment_shader)
+            )
+        }
+    }
+}
+
+impl Default for EasyGraphicsPipelineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Dynamic state with a viewport and scissor covering a framebuffer of some
+/// dimensions, as pipelines from EasyGraphicsPipelineBuilder need
+pub fn easy_dynamic_state(dimensions: [u32; 2]) -> DynamicState {
+    DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+            depth_range: 0.0..1.0,
+        }]),
+        scissors: Some(vec![Scissor {
+            origin: [0, 0],
+            dimensions,
+        }]),
+        .. DynamicState::none()
+    }
+}
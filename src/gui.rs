@@ -0,0 +1,581 @@
+//! Debug overlay based on the Dear ImGui immediate-mode GUI
+//!
+//! This module is only available when the "gui" feature is enabled. It
+//! provides EasyGui, an ImGui renderer which draws inside of your render pass
+//! and comes with a built-in debug overlay featuring the device summary, the
+//! tracked memory usage and frame timings. You can add your own widgets too.
+//!
+//! The overlay is display-only: feeding user input to ImGui is left to you,
+//! through the ImGui context that EasyGui exposes.
+
+use ::{
+    budget::MemoryTracker,
+    device::EasyDevice,
+    glsl::{
+        easy_compile_glsl,
+        ShaderStage,
+    },
+    profiling::FrameTimings,
+    push_constants::EasyPushConstants,
+    render::EasyRenderPass,
+    report::DeviceReport,
+    trace,
+    Error,
+    Result,
+};
+
+use imgui::{
+    FrameSize,
+    ImGui,
+    ImGuiCond,
+    Ui,
+};
+
+use std::{
+    borrow::Cow,
+    ffi::CString,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+    vec,
+};
+
+use vulkano::{
+    buffer::{
+        BufferUsage,
+        CpuAccessibleBuffer,
+    },
+    command_buffer::{
+        AutoCommandBufferBuilder,
+        DynamicState,
+    },
+    descriptor::{
+        descriptor::{
+            DescriptorDesc,
+            DescriptorDescTy,
+            DescriptorImageDesc,
+            DescriptorImageDescArray,
+            DescriptorImageDescDimensions::TwoDimensional,
+            ShaderStages,
+        },
+        descriptor_set::PersistentDescriptorSet,
+        pipeline_layout::{
+            PipelineLayoutDesc,
+            PipelineLayoutDescPcRange,
+        },
+        DescriptorSet,
+    },
+    device::Queue,
+    format::{
+        Format,
+        R8G8B8A8Unorm,
+    },
+    framebuffer::Subpass,
+    image::{
+        Dimensions,
+        ImmutableImage,
+    },
+    pipeline::{
+        shader::{
+            GraphicsShaderType,
+            ShaderInterfaceDef,
+            ShaderInterfaceDefEntry,
+            ShaderModule,
+        },
+        viewport::{
+            Scissor,
+            Viewport,
+        },
+        GraphicsPipeline,
+        GraphicsPipelineAbstract,
+    },
+    sampler::Sampler,
+    sync::GpuFuture,
+};
+
+
+/// Vertex shader of the ImGui renderer
+const VERTEX_SHADER: &str = "
+    #version 450
+
+    layout(push_constant) uniform Transform {
+        vec2 scale;
+        vec2 translate;
+    } transform;
+
+    layout(location = 0) in vec2 pos;
+    layout(location = 1) in vec2 uv;
+    layout(location = 2) in vec4 col;
+
+    layout(location = 0) out vec2 frag_uv;
+    layout(location = 1) out vec4 frag_col;
+
+    void main() {
+        frag_uv = uv;
+        frag_col = col;
+        gl_Position = vec4(pos * transform.scale + transform.translate,
+                           0.0,
+                           1.0);
+    }
+";
+
+/// Fragment shader of the ImGui renderer
+const FRAGMENT_SHADER: &str = "
+    #version 450
+
+    layout(set = 0, binding = 0) uniform sampler2D font;
+
+    layout(location = 0) in vec2 frag_uv;
+    layout(location = 1) in vec4 frag_col;
+
+    layout(location = 0) out vec4 color;
+
+    void main() {
+        color = frag_col * texture(font, frag_uv);
+    }
+";
+
+
+/// Vertex of the ImGui renderer
+#[derive(Clone, Copy, Debug, Default)]
+struct GuiVertex {
+    /// Position in pixels
+    pos: [f32; 2],
+
+    /// Font texture coordinates
+    uv: [f32; 2],
+
+    /// RGBA color
+    col: [f32; 4],
+}
+impl_vertex!(GuiVertex, pos, uv, col);
+
+/// Push constants of the ImGui renderer, mapping pixels to Vulkan NDC
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct Transform {
+    /// Scale factor
+    scale: [f32; 2],
+
+    /// Translation, applied after scaling
+    translate: [f32; 2],
+}
+
+
+/// Input or output interface of an ImGui renderer shader
+struct GuiInterface(&'static [(u32, Format, &'static str)]);
+
+unsafe impl ShaderInterfaceDef for GuiInterface {
+    type Iter = vec::IntoIter<ShaderInterfaceDefEntry>;
+
+    fn elements(&self) -> Self::Iter {
+        self.0.iter()
+              .map(|&(location, format, name)| ShaderInterfaceDefEntry {
+                  location: location..location+1,
+                  format,
+                  name: Some(Cow::Borrowed(name)),
+              })
+              .collect::<Vec<_>>()
+              .into_iter()
+    }
+}
+
+/// Vertex shader input
+const VERTEX_INPUT: GuiInterface = GuiInterface(&[
+    (0, Format::R32G32Sfloat, "pos"),
+    (1, Format::R32G32Sfloat, "uv"),
+    (2, Format::R32G32B32A32Sfloat, "col"),
+]);
+
+/// Vertex shader output, and fragment shader input
+const VERTEX_OUTPUT: GuiInterface = GuiInterface(&[
+    (0, Format::R32G32Sfloat, "frag_uv"),
+    (1, Format::R32G32B32A32Sfloat, "frag_col"),
+]);
+
+/// Fragment shader output
+const FRAGMENT_OUTPUT: GuiInterface = GuiInterface(&[
+    (0, Format::R32G32B32A32Sfloat, "color"),
+]);
+
+
+/// Pipeline layout of an ImGui renderer shader
+///
+/// The vertex shader only uses push constants, and the fragment shader only
+/// samples the font texture.
+///
+#[derive(Clone, Copy, Debug)]
+enum GuiLayout {
+    /// Layout of the vertex shader
+    Vertex,
+
+    /// Layout of the fragment shader
+    Fragment,
+}
+
+unsafe impl PipelineLayoutDesc for GuiLayout {
+    fn num_sets(&self) -> usize {
+        match *self {
+            GuiLayout::Vertex => 0,
+            GuiLayout::Fragment => 1,
+        }
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        match (*self, set) {
+            (GuiLayout::Fragment, 0) => Some(1),
+            _ => None,
+        }
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        match (*self, set, binding) {
+            (GuiLayout::Fragment, 0, 0) => Some(DescriptorDesc {
+                ty: DescriptorDescTy::CombinedImageSampler(
+                    DescriptorImageDesc {
+                        sampled: true,
+                        dimensions: TwoDimensional,
+                        format: None,
+                        multisampled: false,
+                        array_layers: DescriptorImageDescArray::NonArrayed,
+                    }
+                ),
+                array_count: 1,
+                stages: ShaderStages {
+                    fragment: true,
+                    .. ShaderStages::none()
+                },
+                readonly: true,
+            }),
+            _ => None,
+        }
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        match *self {
+            GuiLayout::Vertex => 1,
+            GuiLayout::Fragment => 0,
+        }
+    }
+
+    fn push_constants_range(&self,
+                            num: usize) -> Option<PipelineLayoutDescPcRange> {
+        match (*self, num) {
+            (GuiLayout::Vertex, 0) => Some(
+                EasyPushConstants::<Transform>::new(ShaderStages {
+                    vertex: true,
+                    .. ShaderStages::none()
+                }).range()
+            ),
+            _ => None,
+        }
+    }
+}
+
+
+/// An ImGui renderer with a built-in debug overlay
+///
+/// Call draw() once per frame while recording your render pass' commands, it
+/// will draw the debug overlay along with any widget of yours on top of what
+/// was drawn so far.
+///
+pub struct EasyGui {
+    /// ImGui context
+    imgui: ImGui,
+
+    /// Queue which the GUI is rendered on
+    queue: Arc<Queue>,
+
+    /// Graphics pipeline used to render the GUI
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+
+    /// Descriptor set featuring the font texture
+    font_set: Arc<dyn DescriptorSet + Send + Sync>,
+
+    /// Device summary, as displayed in the overlay
+    device_summary: String,
+
+    /// Memory tracker of the device
+    memory_tracker: MemoryTracker,
+
+    /// Latest GPU frame timings, if any were provided
+    frame_timings: Option<FrameTimings>,
+
+    /// Time at which the previous frame was drawn
+    last_frame: Instant,
+
+    /// Time between the two previous frames
+    frame_time: Duration,
+
+    /// Truth that the debug overlay should be displayed
+    overlay_visible: bool,
+}
+
+impl EasyGui {
+    /// Set up GUI rendering in the first subpass of a render pass
+    ///
+    /// The subpass must not be multisampled. The font texture is uploaded
+    /// using the specified queue, which is also used for drawing the GUI.
+    ///
+    pub fn new(device: &EasyDevice,
+               queue: &Arc<Queue>,
+               render_pass: &EasyRenderPass) -> Result<Self> {
+        let _trace = trace::span("EasyGui::new", String::new);
+        let vk_device = device.device();
+        let subpass = Subpass::from(render_pass.clone(), 0).ok_or_else(|| {
+            Error::InvalidArgument("Render pass has no subpass".to_owned())
+        })?;
+        if subpass.num_samples() != Some(1) {
+            return Err(Error::InvalidArgument(
+                "EasyGui does not support multisampled subpasses".to_owned()
+            ));
+        }
+
+        // Compile the shaders and build the pipeline
+        let vertex_module = load_shader(device, VERTEX_SHADER,
+                                        ShaderStage::Vertex)?;
+        let fragment_module = load_shader(device, FRAGMENT_SHADER,
+                                          ShaderStage::Fragment)?;
+        let main = CString::new("main")?;
+        let pipeline = unsafe {
+            let vertex_shader =
+                vertex_module.graphics_entry_point::<(), _, _, _>(
+                    &main,
+                    VERTEX_INPUT,
+                    VERTEX_OUTPUT,
+                    GuiLayout::Vertex,
+                    GraphicsShaderType::Vertex
+                );
+            let fragment_shader =
+                fragment_module.graphics_entry_point::<(), _, _, _>(
+                    &main,
+                    VERTEX_OUTPUT,
+                    FRAGMENT_OUTPUT,
+                    GuiLayout::Fragment,
+                    GraphicsShaderType::Fragment
+                );
+            Arc::new(GraphicsPipeline::start()
+                         .vertex_input_single_buffer::<GuiVertex>()
+                         .vertex_shader(vertex_shader, ())
+                         .triangle_list()
+                         .viewports_scissors_dynamic(1)
+                         .fragment_shader(fragment_shader, ())
+                         .blend_alpha_blending()
+                         .render_pass(subpass)
+                         .build(vk_device.clone())?)
+        };
+
+        // Upload the font texture
+        let mut imgui = ImGui::init();
+        imgui.set_ini_filename(None);
+        let (font_image, upload) = imgui.prepare_texture(|handle| {
+            let pixels = handle.pixels
+                               .chunks(4)
+                               .map(|px| [px[0], px[1], px[2], px[3]])
+                               .collect::<Vec<_>>();
+            ImmutableImage::from_iter(pixels.into_iter(),
+                                      Dimensions::Dim2d {
+                                          width: handle.width,
+                                          height: handle.height,
+                                      },
+                                      R8G8B8A8Unorm,
+                                      queue.clone())
+        })?;
+        upload.then_signal_fence_and_flush()?.wait(None)?;
+        let font_set = Arc::new(
+            PersistentDescriptorSet::start(pipeline.clone(), 0)
+                .add_sampled_image(font_image,
+                                   Sampler::simple_repeat_linear(
+                                       vk_device.clone()
+                                   ))?
+                .build()?
+        );
+
+        // Describe the device for the overlay
+        let report = DeviceReport::new(vk_device.physical_device())?;
+        Ok(EasyGui {
+            imgui,
+            queue: queue.clone(),
+            pipeline,
+            font_set,
+            device_summary: report.summary().to_string(),
+            memory_tracker: device.memory_tracker().clone(),
+            frame_timings: None,
+            last_frame: Instant::now(),
+            frame_time: Duration::from_secs(0),
+            overlay_visible: true,
+        })
+    }
+
+    /// Access the ImGui context, e.g. to feed it user input
+    pub fn imgui(&mut self) -> &mut ImGui {
+        &mut self.imgui
+    }
+
+    /// Show or hide the debug overlay
+    pub fn set_overlay_visible(&mut self, visible: bool) {
+        self.overlay_visible = visible;
+    }
+
+    /// Display some GPU frame timings in the overlay, e.g. from EasyTimer
+    pub fn show_frame_timings(&mut self, timings: FrameTimings) {
+        self.frame_timings = Some(timings);
+    }
+
+    /// Draw the GUI inside of the current render pass
+    ///
+    /// The builder must be inside of the subpass that the GUI was set up for,
+    /// and the dimensions are those of the framebuffer, in pixels. Your own
+    /// widgets can be added using the ui callback.
+    ///
+    pub fn draw(
+        &mut self,
+        builder: AutoCommandBufferBuilder,
+        dimensions: [u32; 2],
+        ui: impl FnOnce(&Ui)
+    ) -> Result<AutoCommandBufferBuilder> {
+        let _trace = trace::span("EasyGui::draw",
+                                 || format!("dimensions: {:?}", dimensions));
+
+        // Measure the CPU frame time
+        let now = Instant::now();
+        self.frame_time = now - self.last_frame;
+        self.last_frame = now;
+
+        // Build the user interface
+        let frame_size = FrameSize::new(dimensions[0] as f64,
+                                        dimensions[1] as f64,
+                                        1.0);
+        let delta_time = self.frame_time.as_secs() as f32
+                         + self.frame_time.subsec_nanos() as f32 * 1e-9;
+        let overlay = if self.overlay_visible {
+            Some(self.overlay_text())
+        } else {
+            None
+        };
+        let frame = self.imgui.frame(frame_size, delta_time.max(1e-6));
+        if let Some(overlay) = overlay {
+            frame.window(im_str!("vulkanoob"))
+                 .position((10.0, 10.0), ImGuiCond::FirstUseEver)
+                 .always_auto_resize(true)
+                 .build(|| frame.text(&overlay));
+        }
+        ui(&frame);
+
+        // Render it
+        let device = self.queue.device().clone();
+        let pipeline = self.pipeline.clone();
+        let font_set = self.font_set.clone();
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+            depth_range: 0.0..1.0,
+        };
+        let transform = Transform {
+            scale: [2.0 / dimensions[0] as f32, 2.0 / dimensions[1] as f32],
+            translate: [-1.0, -1.0],
+        };
+        let mut builder = Some(builder);
+        frame.render(|_ui, draw_data| -> Result<()> {
+            for draw_list in &draw_data {
+                let vertices = draw_list.vtx_buffer.iter().map(|v| {
+                    let col = v.col;
+                    GuiVertex {
+                        pos: [v.pos.x, v.pos.y],
+                        uv: [v.uv.x, v.uv.y],
+                        col: [(col & 0xff) as f32 / 255.0,
+                              ((col >> 8) & 0xff) as f32 / 255.0,
+                              ((col >> 16) & 0xff) as f32 / 255.0,
+                              ((col >> 24) & 0xff) as f32 / 255.0],
+                    }
+                });
+                let vertex_buffer = CpuAccessibleBuffer::from_iter(
+                    device.clone(),
+                    BufferUsage::vertex_buffer(),
+                    vertices
+                )?;
+
+                // Issue one draw call per ImGui command, with its clip rect
+                let mut first_index = 0;
+                for command in draw_list.cmd_buffer {
+                    let count = command.elem_count as usize;
+                    let indices = &draw_list.idx_buffer[first_index..
+                                                        first_index+count];
+                    first_index += count;
+                    let clip = command.clip_rect;
+                    let scissor = Scissor {
+                        origin: [clip.x.max(0.0) as i32,
+                                 clip.y.max(0.0) as i32],
+                        dimensions: [(clip.z - clip.x).max(0.0) as u32,
+                                     (clip.w - clip.y).max(0.0) as u32],
+                    };
+                    if count == 0 || scissor.dimensions.contains(&0) {
+                        continue;
+                    }
+                    let index_buffer = CpuAccessibleBuffer::from_iter(
+                        device.clone(),
+                        BufferUsage::index_buffer(),
+                        indices.iter().cloned()
+                    )?;
+                    let dynamic_state = DynamicState {
+                        viewports: Some(vec![viewport.clone()]),
+                        scissors: Some(vec![scissor]),
+                        .. DynamicState::none()
+                    };
+                    builder = Some(builder.take().unwrap().draw_indexed(
+                        pipeline.clone(),
+                        dynamic_state,
+                        vertex_buffer.clone(),
+                        index_buffer,
+                        font_set.clone(),
+                        transform
+                    )?);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(builder.unwrap())
+    }
+
+    /// Text of the debug overlay
+    fn overlay_text(&self) -> String {
+        let mut text = self.device_summary.trim_end().to_owned();
+
+        // Tracked memory usage
+        text.push_str("\n\nTracked memory usage:");
+        for heap in self.memory_tracker.snapshot().heaps {
+            const MIB: f64 = 1024.0 * 1024.0;
+            text.push_str(&format!(
+                "\n  Heap {}{}: {:.1} / {:.1} MiB",
+                heap.index,
+                if heap.device_local { " (device-local)" } else { "" },
+                heap.used as f64 / MIB,
+                heap.size as f64 / MIB
+            ));
+        }
+
+        // Frame timings
+        let frame_ms = self.frame_time.as_secs() as f64 * 1000.0
+                       + self.frame_time.subsec_nanos() as f64 * 1e-6;
+        text.push_str(&format!("\n\nCPU frame time: {:.2} ms", frame_ms));
+        if let Some(ref timings) = self.frame_timings {
+            text.push('\n');
+            text.push_str(&timings.to_string());
+        }
+        text
+    }
+}
+
+
+/// Compile an embedded GLSL shader and load it on the device
+fn load_shader(device: &EasyDevice,
+               source: &str,
+               stage: ShaderStage) -> Result<Arc<ShaderModule>> {
+    let words = easy_compile_glsl(source, stage)?;
+    let bytes = words.iter()
+                     .flat_map(|word| word.to_ne_bytes().to_vec())
+                     .collect::<Vec<u8>>();
+    Ok(unsafe { ShaderModule::new(device.device().clone(), &bytes)? })
+}
@@ -0,0 +1,111 @@
+//! Tracking of physical devices which appear or disappear at runtime
+//!
+//! External GPUs can be attached or removed while an application is running,
+//! but a Vulkan instance only enumerates physical devices once. So
+//! EasyInstance::rescan_devices() enumerates them again through a temporary
+//! instance, and describes what changed since the previous enumeration using
+//! the types of this module. Devices are told apart by UUID, since their
+//! enumeration index may change when another device is added or removed.
+//!
+//! Devices which appeared this way cannot be used from the existing
+//! EasyInstance: build a new one to use them.
+
+use std::fmt;
+
+use vulkano::instance::PhysicalDevice;
+
+
+/// Identity of a physical device, as seen during an enumeration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceIdentity {
+    /// Index of the device in the enumeration
+    pub index: usize,
+
+    /// Name of the device
+    pub name: String,
+
+    /// UUID of the device, which is stable across enumerations
+    pub uuid: [u8; 16],
+}
+
+impl DeviceIdentity {
+    /// Identify a physical device
+    pub fn new(device: PhysicalDevice) -> Self {
+        DeviceIdentity {
+            index: device.index(),
+            name: device.name(),
+            uuid: *device.uuid(),
+        }
+    }
+}
+
+impl fmt::Display for DeviceIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} \"{}\"", self.index, self.name)
+    }
+}
+
+
+/// Differences between two enumerations of the physical devices
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceChanges {
+    /// Devices which appeared since the previous enumeration
+    pub added: Vec<DeviceIdentity>,
+
+    /// Devices which disappeared since the previous enumeration
+    pub removed: Vec<DeviceIdentity>,
+
+    /// Devices of the new enumeration which pass the selection criteria
+    pub selectable: Vec<DeviceIdentity>,
+}
+
+impl DeviceChanges {
+    /// Compare two enumerations, given the devices which pass the selection
+    /// criteria in the new one
+    pub fn new(previous: &[DeviceIdentity],
+               current: &[DeviceIdentity],
+               selectable: Vec<DeviceIdentity>) -> Self {
+        let contains = |list: &[DeviceIdentity], device: &DeviceIdentity| {
+            list.iter().any(|other| other.uuid == device.uuid)
+        };
+        DeviceChanges {
+            added: current.iter()
+                          .filter(|device| !contains(previous, device))
+                          .cloned()
+                          .collect(),
+            removed: previous.iter()
+                             .filter(|device| !contains(current, device))
+                             .cloned()
+                             .collect(),
+            selectable,
+        }
+    }
+
+    /// Truth that some devices appeared or disappeared
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for DeviceChanges {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.has_changes() {
+            return write!(f, "No physical device was added or removed");
+        }
+        let list = |f: &mut fmt::Formatter,
+                    devices: &[DeviceIdentity]| -> fmt::Result {
+            for (idx, device) in devices.iter().enumerate() {
+                if idx > 0 { write!(f, ", ")?; }
+                write!(f, "{}", device)?;
+            }
+            Ok(())
+        };
+        write!(f, "Added devices: [")?;
+        list(f, &self.added)?;
+        write!(f, "], removed devices: [")?;
+        list(f, &self.removed)?;
+        write!(f, "], selectable devices: [")?;
+        list(f, &self.selectable)?;
+        write!(f, "]")
+    }
+}
@@ -0,0 +1,445 @@
+//! Conveniences for loading textures and capturing images
+//!
+//! Loading images from PNG or JPEG files requires the "image-loading" feature,
+//! and saving captured images as PNG requires the "png-export" feature.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+#[cfg(any(feature = "image-loading", feature = "png-export"))]
+use image_crate;
+
+#[cfg(any(feature = "image-loading", feature = "png-export"))]
+use std::path::Path;
+
+use std::{
+    iter,
+    mem,
+    sync::Arc,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    buffer::{
+        BufferUsage,
+        CpuAccessibleBuffer,
+    },
+    command_buffer::{
+        AutoCommandBufferBuilder,
+        CommandBuffer,
+    },
+    device::{
+        Device,
+        Queue,
+    },
+    format::{
+        Format,
+        FormatTy,
+        R8G8B8A8Srgb,
+    },
+    image::{
+        Dimensions,
+        ImageAccess,
+        ImageLayout,
+        ImageUsage,
+        ImmutableImage,
+        MipmapsCount,
+    },
+    sampler::{
+        Filter,
+        Sampler,
+    },
+    sync::GpuFuture,
+    VulkanObject,
+};
+
+
+/// A sampled texture, with mipmaps and a default sampler
+pub struct EasyImage {
+    /// Texture image
+    image: Arc<ImmutableImage<R8G8B8A8Srgb>>,
+
+    /// Sampler for the texture
+    sampler: Arc<Sampler>,
+}
+
+impl EasyImage {
+    /// Create a texture from raw 8-bit sRGB RGBA data, in row-major order
+    ///
+    /// A full mipmap chain is generated on the host using a box filter, then
+    /// all mipmap levels are uploaded and we wait for the upload to complete.
+    /// The default sampler uses linear filtering and repeats the texture.
+    ///
+    pub fn from_rgba8(device: &Arc<Device>,
+                      queue: &Arc<Queue>,
+                      width: u32,
+                      height: u32,
+                      data: &[u8]) -> Result<Self> {
+        let _trace = trace::span("EasyImage::from_rgba8",
+                                 || format!("dimensions: {}x{}",
+                                            width, height));
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidArgument(
+                "Images cannot be empty".to_owned()
+            ));
+        }
+        if data.len() != 4 * (width as usize) * (height as usize) {
+            return Err(Error::InvalidArgument(
+                format!("Expected {}x{} RGBA pixels, got {} bytes",
+                        width, height, data.len())
+            ));
+        }
+
+        // Allocate the image
+        let (image, init) = ImmutableImage::uninitialized(
+            device.clone(),
+            Dimensions::Dim2d { width, height },
+            R8G8B8A8Srgb,
+            MipmapsCount::Log2,
+            ImageUsage {
+                transfer_destination: true,
+                sampled: true,
+                .. ImageUsage::none()
+            },
+            ImageLayout::ShaderReadOnlyOptimal,
+            iter::once(queue.family())
+        )?;
+        let init = Arc::new(init);
+
+        // Upload each mipmap level, generating it from the previous one
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            device.clone(),
+            queue.family()
+        )?;
+        let mut level_data = data.chunks(4)
+                                 .map(|px| [px[0], px[1], px[2], px[3]])
+                                 .collect::<Vec<_>>();
+        let mut level_dims = [width, height];
+        for level in 0..image.mipmap_levels() {
+            if level > 0 {
+                let (data, dims) = downsample(&level_data, level_dims);
+                level_data = data;
+                level_dims = dims;
+            }
+            let staging_buffer = CpuAccessibleBuffer::from_iter(
+                device.clone(),
+                BufferUsage::transfer_source(),
+                level_data.iter().cloned()
+            )?;
+            builder = builder.copy_buffer_to_image_dimensions(
+                staging_buffer,
+                init.clone(),
+                [0, 0, 0],
+                [level_dims[0], level_dims[1], 1],
+                0,
+                1,
+                level
+            )?;
+        }
+
+        // Wait for the upload to complete
+        builder.build()?
+               .execute(queue.clone())?
+               .then_signal_fence_and_flush()?
+               .wait(None)?;
+
+        // Return the image with a default sampler
+        Ok(EasyImage {
+            image,
+            sampler: Sampler::simple_repeat_linear(device.clone()),
+        })
+    }
+
+    /// Create a texture from the contents of an image file (PNG, JPEG...)
+    #[cfg(feature = "image-loading")]
+    pub fn from_file(device: &Arc<Device>,
+                     queue: &Arc<Queue>,
+                     path: impl AsRef<Path>) -> Result<Self> {
+        let rgba = image_crate::open(path)?.to_rgba();
+        let (width, height) = rgba.dimensions();
+        Self::from_rgba8(device, queue, width, height, &rgba.into_raw())
+    }
+
+    /// Create a texture from an in-memory image file (PNG, JPEG...)
+    #[cfg(feature = "image-loading")]
+    pub fn from_memory(device: &Arc<Device>,
+                       queue: &Arc<Queue>,
+                       bytes: &[u8]) -> Result<Self> {
+        let rgba = image_crate::load_from_memory(bytes)?.to_rgba();
+        let (width, height) = rgba.dimensions();
+        Self::from_rgba8(device, queue, width, height, &rgba.into_raw())
+    }
+
+    /// Access the texture image
+    pub fn image(&self) -> &Arc<ImmutableImage<R8G8B8A8Srgb>> {
+        &self.image
+    }
+
+    /// Access the default sampler
+    pub fn sampler(&self) -> &Arc<Sampler> {
+        &self.sampler
+    }
+}
+
+
+/// Halve the dimensions of an RGBA image using a 2x2 box filter
+///
+/// Odd dimensions are handled by clamping to the image's edge. For the sake
+/// of simplicity, we average sRGB values directly, which is not quite right
+/// but good enough for prototyping.
+///
+fn downsample(data: &[[u8; 4]], dims: [u32; 2]) -> (Vec<[u8; 4]>, [u32; 2]) {
+    let (width, height) = (dims[0] as usize, dims[1] as usize);
+    let new_width = (width / 2).max(1);
+    let new_height = (height / 2).max(1);
+    let pixel = |x: usize, y: usize| data[y.min(height-1) * width
+                                          + x.min(width-1)];
+    let mut result = Vec::with_capacity(new_width * new_height);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let quad = [pixel(2*x, 2*y), pixel(2*x+1, 2*y),
+                        pixel(2*x, 2*y+1), pixel(2*x+1, 2*y+1)];
+            let mut average = [0; 4];
+            for channel in 0..4 {
+                let sum = quad.iter().map(|px| px[channel] as u32).sum::<u32>();
+                average[channel] = ((sum + 2) / 4) as u8;
+            }
+            result.push(average);
+        }
+    }
+    (result, [new_width as u32, new_height as u32])
+}
+
+
+/// An image which was captured from the GPU, converted to 8-bit RGBA
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedImage {
+    /// Width of the image in pixels
+    pub width: u32,
+
+    /// Height of the image in pixels
+    pub height: u32,
+
+    /// Pixels in row-major order, 4 bytes (R, G, B, A) per pixel
+    pub pixels: Vec<u8>,
+}
+
+impl CapturedImage {
+    /// Save the image as a PNG file
+    #[cfg(feature = "png-export")]
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let _trace = trace::span("CapturedImage::save_png",
+                                 || format!("path: {:?}", path));
+        image_crate::save_buffer(path,
+                                 &self.pixels,
+                                 self.width,
+                                 self.height,
+                                 image_crate::ColorType::RGBA(8))?;
+        info!("Saved {}x{} image to {:?}", self.width, self.height, path);
+        Ok(())
+    }
+}
+
+
+/// Copy the first layer and mip level of an image to the host, as RGBA8
+///
+/// This waits for the copy to complete, and for any GPU work using the image
+/// that was submitted before. The image must have been created with the
+/// transfer_source usage, which vulkanoob's swapchains have if the surface
+/// allows it. Vulkano takes care of the image layout transitions.
+///
+/// Supported formats are 8-bit RGBA and BGRA (UNORM or sRGB, the sRGB
+/// encoding is preserved) and 32-bit float RGBA (which is clamped to [0, 1]).
+///
+pub fn capture_image_to_rgba<I>(queue: &Arc<Queue>,
+                                image: Arc<I>) -> Result<CapturedImage>
+    where I: ImageAccess + Send + Sync + 'static
+{
+    let format = image.format();
+    let [width, height] = image.dimensions().width_height();
+    let _trace = trace::span("image::capture_image_to_rgba", || {
+        format!("format: {:?}, dimensions: {}x{}", format, width, height)
+    });
+    let pixel_size = match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb
+        | Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => 4,
+        Format::R32G32B32A32Sfloat => 16,
+        _ => return Err(Error::InvalidArgument(
+            format!("Cannot capture images of format {:?}", format)
+        )),
+    };
+
+    // Copy the image into a host-visible buffer and wait for the copy
+    let device = queue.device();
+    let num_bytes = pixel_size * (width as usize) * (height as usize);
+    let buffer = unsafe {
+        CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+            device.clone(),
+            num_bytes,
+            BufferUsage::transfer_destination()
+        )?
+    };
+    AutoCommandBufferBuilder::primary_one_time_submit(
+        device.clone(),
+        queue.family()
+    )?.copy_image_to_buffer_dimensions(
+        image,
+        buffer.clone(),
+        [0, 0, 0],
+        [width, height, 1],
+        0,
+        1,
+        0
+    )?.build()?
+      .execute(queue.clone())?
+      .then_signal_fence_and_flush()?
+      .wait(None)?;
+
+    // Convert the pixels to RGBA8
+    let contents = buffer.read()?;
+    let pixels = match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => contents.to_vec(),
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => {
+            contents.chunks(4)
+                    .flat_map(|px| vec![px[2], px[1], px[0], px[3]])
+                    .collect()
+        },
+        Format::R32G32B32A32Sfloat => {
+            contents.chunks(4)
+                    .map(|bytes| {
+                        let mut float_bytes = [0; 4];
+                        float_bytes.copy_from_slice(bytes);
+                        let value = f32::from_bits(
+                            u32::from_ne_bytes(float_bytes)
+                        );
+                        (value.max(0.0).min(1.0) * 255.0).round() as u8
+                    })
+                    .collect()
+        },
+        _ => unreachable!("Format was checked above"),
+    };
+    Ok(CapturedImage { width, height, pixels })
+}
+
+
+/// Generate the mipmaps of an image from its first mip level
+///
+/// Each mip level is blitted from the previous one, for every array layer of
+/// the image, using vulkano's blit_image() command so that vulkano takes care
+/// of synchronization and layout transitions. The image must be a color image
+/// which was created with the transfer_source and transfer_destination
+/// usages. We wait for the blits to complete.
+///
+/// The image is written to, so ImmutableImages must be accessed through the
+/// ImmutableImageInitialization which ImmutableImage::uninitialized() returns
+/// along with them, before they are used for anything else.
+///
+/// Linear filtering is used if the image's format supports it, otherwise we
+/// fall back to nearest-neighbor filtering (with a warning, since that gives
+/// rather ugly mipmaps). Formats which cannot be blitted at all are rejected.
+///
+pub fn generate_mipmaps<I>(queue: &Arc<Queue>, image: &Arc<I>) -> Result<()>
+    where I: ImageAccess + Send + Sync + 'static
+{
+    let format = image.format();
+    let dimensions = image.dimensions();
+    let inner = image.inner();
+    let _trace = trace::span("image::generate_mipmaps", || {
+        format!("format: {:?}, dimensions: {:?}, mip levels: {}",
+                format, dimensions, inner.num_mipmap_levels)
+    });
+
+    // Check that the image is suitable
+    match format.ty() {
+        FormatTy::Float | FormatTy::Uint | FormatTy::Sint => {},
+        _ => return Err(Error::InvalidArgument(
+            format!("Cannot generate mipmaps for format {:?}", format)
+        )),
+    }
+    if !inner.image.usage_transfer_source()
+       || !inner.image.usage_transfer_destination()
+    {
+        return Err(Error::InvalidArgument(
+            "Mipmap generation requires the transfer_source and \
+             transfer_destination image usages".to_owned()
+        ));
+    }
+    if inner.num_mipmap_levels < 2 {
+        debug!("Image has a single mip level, nothing to generate");
+        return Ok(());
+    }
+
+    // Pick a blit filter that the format supports
+    let filter = match blit_filter(queue.device(), format) {
+        Some(filter) => filter,
+        None => return Err(Error::InvalidArgument(
+            format!("Format {:?} does not support blits", format)
+        )),
+    };
+
+    // Blit each mip level from the previous one
+    let extent = |level: u32| [
+        (dimensions.width() >> level).max(1) as i32,
+        (dimensions.height() >> level).max(1) as i32,
+        (dimensions.depth() >> level).max(1) as i32,
+    ];
+    let num_layers = inner.num_layers as u32;
+    let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
+        queue.device().clone(),
+        queue.family()
+    )?;
+    for level in 1..inner.num_mipmap_levels as u32 {
+        builder = builder.blit_image(image.clone(),
+                                     [0, 0, 0],
+                                     extent(level - 1),
+                                     0,
+                                     level - 1,
+                                     image.clone(),
+                                     [0, 0, 0],
+                                     extent(level),
+                                     0,
+                                     level,
+                                     num_layers,
+                                     filter)?;
+    }
+
+    // Submit the blits and wait for them to complete
+    builder.build()?
+           .execute(queue.clone())?
+           .then_signal_fence_and_flush()?
+           .wait(None)?;
+    Ok(())
+}
+
+/// Best blit filter supported by a format, if blits are supported at all
+fn blit_filter(device: &Arc<Device>, format: Format) -> Option<Filter> {
+    let physical_device = device.physical_device();
+    let fns = physical_device.instance().pointers();
+    let mut properties = unsafe { mem::zeroed::<vk::FormatProperties>() };
+    unsafe {
+        fns.GetPhysicalDeviceFormatProperties(
+            physical_device.internal_object(),
+            format as vk::Format,
+            &mut properties
+        );
+    }
+    let features = properties.optimalTilingFeatures;
+    let blit_bits = vk::FORMAT_FEATURE_BLIT_SRC_BIT
+                    | vk::FORMAT_FEATURE_BLIT_DST_BIT;
+    if features & blit_bits != blit_bits {
+        None
+    } else if features & vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT
+              != 0
+    {
+        Some(Filter::Linear)
+    } else {
+        warn!("Format {:?} does not support linear blits, mipmaps will be \
+               generated using nearest-neighbor filtering", format);
+        Some(Filter::Nearest)
+    }
+}
@@ -0,0 +1,111 @@
+//! Indirect draw and dispatch commands
+//!
+//! Indirect commands read their parameters from a buffer, which GPU-driven
+//! rendering experiments fill from shaders. Vulkan expects these buffers to
+//! contain tightly packed VkDrawIndirectCommand or VkDispatchIndirectCommand
+//! structs, which vulkano exposes as DrawIndirectCommand and
+//! DispatchIndirectCommand. This module provides constructors for these
+//! structs and checks them against the device's limits. The corresponding
+//! buffers are built with EasyBuffer::draw_indirect() and
+//! EasyBuffer::dispatch_indirect(), and indirect commands are recorded with
+//! command::easy_draw_indirect() and command::easy_dispatch_indirect().
+//!
+//! Note that the version of vulkano that we use cannot record indirect
+//! dispatches with AutoCommandBufferBuilder yet, so easy_dispatch_indirect()
+//! records them into a RawCommandBuffer.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    ops::Range,
+    sync::Arc,
+};
+
+use vulkano::{
+    device::Device,
+    instance::PhysicalDevice,
+};
+
+pub use vulkano::command_buffer::{
+    DispatchIndirectCommand,
+    DrawIndirectCommand,
+};
+
+
+/// Build an indirect draw command
+pub fn draw_command(vertices: Range<u32>,
+                    instances: Range<u32>) -> DrawIndirectCommand {
+    DrawIndirectCommand {
+        vertex_count: vertices.end.saturating_sub(vertices.start),
+        instance_count: instances.end.saturating_sub(instances.start),
+        first_vertex: vertices.start,
+        first_instance: instances.start,
+    }
+}
+
+/// Build an indirect dispatch command, with dimensions given in work groups
+pub fn dispatch_command(work_groups: [u32; 3]) -> DispatchIndirectCommand {
+    DispatchIndirectCommand {
+        x: work_groups[0],
+        y: work_groups[1],
+        z: work_groups[2],
+    }
+}
+
+
+/// Check that a device can execute a number of indirect draws at once
+///
+/// Drawing more than one command requires the multi_draw_indirect feature,
+/// and the number of commands is bounded by the max_draw_indirect_count
+/// limit.
+///
+pub fn check_draw_count(device: &Arc<Device>, count: usize) -> Result<()> {
+    let _trace = trace::span("indirect::check_draw_count",
+                             || format!("count: {}", count));
+    if count > 1 && !device.enabled_features().multi_draw_indirect {
+        return Err(Error::InvalidArgument(format!(
+            "Drawing {} indirect commands at once requires the \
+             multi_draw_indirect feature, which is not enabled", count
+        )));
+    }
+    let physical_device = device.physical_device();
+    let max_count = physical_device.limits().max_draw_indirect_count() as usize;
+    if count > max_count {
+        return Err(Error::InvalidArgument(format!(
+            "Tried to draw {} indirect commands at once, but device \"{}\" \
+             only supports {}", count, physical_device.name(), max_count
+        )));
+    }
+    Ok(())
+}
+
+/// Check some indirect dispatch commands against the device's limits
+///
+/// Only the initial buffer contents can be checked this way, commands which
+/// are later written by shaders must respect the limits on their own.
+///
+pub fn check_dispatch_commands(
+    device: PhysicalDevice,
+    commands: &[DispatchIndirectCommand]
+) -> Result<()> {
+    let _trace = trace::span("indirect::check_dispatch_commands", || {
+        format!("device: {}, commands: {}", device.name(), commands.len())
+    });
+    let max_work_groups = device.limits().max_compute_work_group_count();
+    for (index, command) in commands.iter().enumerate() {
+        let work_groups = [command.x, command.y, command.z];
+        if work_groups.iter().zip(max_work_groups.iter())
+                      .any(|(count, max)| count > max) {
+            return Err(Error::InvalidArgument(format!(
+                "Dispatch command #{} uses {:?} work groups, but device \
+                 \"{}\" only supports {:?}",
+                index, work_groups, device.name(), max_work_groups
+            )));
+        }
+    }
+    Ok(())
+}
@@ -1,20 +1,77 @@
 //! Conveniences for creating and manipulating Vulkan instances
 
 use ::{
-    device::EasyPhysicalDevice,
+    api_level::ApiLevel,
+    config::EnvConfig,
+    debug::{
+        self,
+        DebugFilter,
+        DebugMessage,
+        MessageFilter,
+        MessageSink,
+        ObjectNames,
+        Verdict,
+        DEBUG_UTILS_EXTENSION,
+    },
+    device::{
+        EasyDevice,
+        EasyDeviceGroup,
+        EasyPhysicalDevice,
+    },
+    diagnosis::{
+        easy_device_diagnosis,
+        DeviceDiagnosis,
+        RejectionReason,
+    },
+    display,
+    error::Diagnoses,
+    loader,
+    hotplug::{
+        DeviceChanges,
+        DeviceIdentity,
+    },
+    interop,
+    portability,
+    properties2,
+    recovery::DeviceSelection,
+    report::{
+        DeviceReport,
+        LazyDeviceReport,
+    },
+    selection_cache::{
+        self,
+        CachedSelection,
+        SelectionCache,
+    },
+    software::SoftwareRendering,
+    trace,
+    Error,
     Result,
 };
 
-use log::{self, Level};
+#[cfg(feature = "renderdoc")]
+use ::renderdoc::EasyRenderDoc;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+#[cfg(feature = "window")]
+use ::window::EasyWindow;
+
+use log::Level;
 
 use std::{
+    borrow::Cow,
     cmp::Ordering,
     ffi::CString,
-    fmt::Write,
-    sync::Arc,
+    hash::Hash,
+    mem,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex, TryLockError},
 };
 
 use vulkano::{
+    device::DeviceExtensions,
     instance::{
         self,
         debug::{
@@ -22,32 +79,151 @@ use vulkano::{
             MessageTypes,
         },
         ApplicationInfo,
-        DeviceExtensions,
+        Features,
         Instance,
         InstanceExtensions,
         PhysicalDevice,
         RawInstanceExtensions,
+        Version,
     },
 };
 
 
+/// Logging target used for Vulkan implementation and physical device
+/// enumeration logs
+pub const ENUMERATION_TARGET: &str = "vulkanoob::enumeration";
+
+/// Log something at the INFO level, with the enumeration target
+macro_rules! enumeration_info {
+    ($($args:tt)*) => { info!(target: ENUMERATION_TARGET, $($args)*) };
+}
+
+/// Log something at the DEBUG level, with the enumeration target
+macro_rules! enumeration_debug {
+    ($($args:tt)*) => { debug!(target: ENUMERATION_TARGET, $($args)*) };
+}
+
+
+/// How much detail is logged about physical devices during device selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceLogDetail {
+    /// Do not describe the physical devices
+    Off,
+
+    /// Only log each device's name, type, memory heaps and queue families
+    Summary,
+
+    /// Log everything we know about each device at the INFO level, except for
+    /// the device limits which are logged at the DEBUG level
+    Full,
+}
+
+impl Default for DeviceLogDetail {
+    fn default() -> Self {
+        DeviceLogDetail::Full
+    }
+}
+
+
 /// A convenience abstraction for quickly setting up a Vulkan instance
 ///
 /// You will likely want to keep the EasyInstance object alive througout your
 /// application's lifetime, even when you're not using it, as it connects
 /// Vulkan's debug printout mechanism to your logging system.
 ///
-/// We will help you with that by binding the lifetime of the other objects to
-/// that of this EasyInstance object.
+/// We will help you with that by handing out EasyInstances as an Arc, which
+/// the physical devices and devices built from it hold on to. This way, the
+/// instance stays alive as long as something uses it, and devices can be
+/// moved to other threads (e.g. background loaders) without worrying about
+/// the instance going away. EasyInstance is Send and Sync, and so are the
+/// device wrappers.
 ///
 pub struct EasyInstance {
     /// Vulkan instance
     instance: Arc<Instance>,
 
+    /// Vulkan API version targeted by the instance
+    api_version: Version,
+
+    /// Instance extensions which were enabled, including those which vulkano
+    /// does not know about
+    raw_extensions: RawInstanceExtensions,
+
     /// Vulkan debug callback
-    _debug_callback: DebugCallback,
+    _debug_callback: SharedDebugCallback,
+
+    /// Truth that VK_EXT_debug_utils was enabled
+    debug_utils: bool,
+
+    /// Names given to Vulkan objects, for debug message annotation when
+    /// VK_EXT_debug_utils is not available
+    object_names: ObjectNames,
+
+    /// Debug message filter
+    message_filter: Arc<MessageFilter>,
+
+    /// Level of detail of physical device descriptions
+    device_log_detail: Mutex<DeviceLogDetail>,
+
+    /// Vulkan API version requirements
+    api_level: Mutex<ApiLevel>,
+
+    /// Treatment of software implementations during device selection
+    software_rendering: Mutex<SoftwareRendering>,
+
+    /// Physical devices seen during the last enumeration
+    known_devices: Mutex<Vec<DeviceIdentity>>,
+
+    /// Callbacks to be invoked when physical devices are added or removed
+    device_change_callbacks: Mutex<Vec<DeviceChangeCallback>>,
+
+    /// Callbacks to be invoked with the report of each device considered
+    /// during device selection
+    device_report_callbacks: Mutex<Vec<DeviceReportCallback>>,
+
+    /// Attached RenderDoc instance, if any
+    #[cfg(feature = "renderdoc")]
+    renderdoc: Option<EasyRenderDoc>,
 }
 
+/// Callback which is notified of some event
+///
+/// Each callback has its own lock, so that callbacks can be invoked without
+/// holding the lock of the list which they belong to, see invoke_callbacks().
+///
+type Callback<T> = Arc<Mutex<dyn FnMut(&T) + Send>>;
+
+/// Callback which is notified of physical device changes
+type DeviceChangeCallback = Callback<DeviceChanges>;
+
+/// Callback which receives the reports of physical devices
+type DeviceReportCallback = Callback<DeviceReport>;
+
+/// Vulkan debug callback, which can be shared across threads
+///
+/// Vulkano's DebugCallback stores the user callback as a boxed closure without
+/// Send or Sync bounds, so it is neither Send nor Sync. But it requires said
+/// closure to be Send, the Vulkan implementation may call it from any thread
+/// anyway, and our closure only captures thread-safe state. The callback is
+/// never accessed after creation, except to be destroyed.
+///
+struct SharedDebugCallback(DebugCallback);
+
+unsafe impl Send for SharedDebugCallback {}
+unsafe impl Sync for SharedDebugCallback {}
+
+/// Compile-time check that the instance and device wrappers can be moved and
+/// shared across threads
+#[allow(dead_code)]
+fn assert_thread_safety() {
+    fn is_send_sync<T: Send + Sync>() {}
+    is_send_sync::<EasyInstance>();
+    is_send_sync::<EasyPhysicalDevice>();
+    is_send_sync::<EasyDevice>();
+    is_send_sync::<DeviceSelection>();
+}
+
+
 impl EasyInstance {
     /// Sets up a Vulkan instance with debug logging
     ///
@@ -55,13 +231,19 @@ impl EasyInstance {
     /// Instance::new() and DebugCallback::new() methods, please refer to the
     /// Instance::new() documentation to know what its parameters do.
     ///
+    /// The instance targets the newest Vulkan API version supported by the
+    /// Vulkan loader. Use EasyInstanceBuilder::api_level() if you need to
+    /// cap it, see also the loader module.
+    ///
     /// The Vulkan implementation capabilities will be described at the INFO
     /// logging level, which will help you quickly debug instance creation
     /// errors without needing to resort to use of external and finicky programs
     /// like vulkaninfo.
     ///
     /// In addition to the extensions that you specify, we will also enable the
-    /// VK_EXT_debug_report extension as it is needed for debug logging.
+    /// VK_EXT_debug_report extension as it is needed for debug logging. Use
+    /// negotiate_instance_extensions() to check your extensions beforehand,
+    /// rather than discovering missing ones when the instance is created.
     ///
     /// By default, debug messages are enabled based on the logger configuration
     /// at the time where this method is called. If this is not what you want
@@ -72,22 +254,62 @@ impl EasyInstance {
         app_infos: Option<&ApplicationInfo>,
         extensions: impl Into<RawInstanceExtensions>,
         layers: impl IntoIterator<Item=&'a str>,
-    ) -> Result<Self> {
-        let max_log_level = log::max_level();
+    ) -> Result<Arc<Self>> {
         Self::with_debug_config(
             app_infos,
             extensions,
             layers,
-            MessageTypes {
-                error: (max_log_level >= log::LevelFilter::Error),
-                warning: (max_log_level >= log::LevelFilter::Warn),
-                performance_warning: (max_log_level >= log::LevelFilter::Warn),
-                information: (max_log_level >= log::LevelFilter::Info),
-                debug: (max_log_level >= log::LevelFilter::Debug),
-            }
+            default_message_types(),
         )
     }
 
+    /// Like new(), but also enables the standard validation layer
+    ///
+    /// We look for the modern Khronos validation layer first, then for the
+    /// legacy LunarG one. If none of them is installed, a warning is logged
+    /// and the instance is created without validation.
+    ///
+    pub fn with_validation<'a>(
+        app_infos: Option<&ApplicationInfo>,
+        extensions: impl Into<RawInstanceExtensions>,
+        layers: impl IntoIterator<Item=&'a str>,
+    ) -> Result<Arc<Self>> {
+        let mut layers = layers.into_iter().collect::<Vec<_>>();
+        match find_validation_layer()? {
+            Some(layer) => {
+                info!("Enabling validation layer {}", layer);
+                if !layers.contains(&layer) { layers.push(layer); }
+            },
+            None => warn!("No validation layer is installed, please install \
+                           the Vulkan SDK to enable validation."),
+        }
+        Self::new(app_infos, extensions, layers)
+    }
+
+    /// Sets up a Vulkan instance for compute-only (GPGPU) applications
+    ///
+    /// No surface extension is enabled, so you do not need to think about
+    /// instance extensions at all. Like new(), this does not enable any
+    /// layer. If you want validation, call with_validation() with no extra
+    /// extension instead.
+    ///
+    /// You will likely want to use this in combination with the
+    /// easy_compute_filter() device filter.
+    ///
+    pub fn headless(app_infos: Option<&ApplicationInfo>) -> Result<Arc<Self>> {
+        let _trace = trace::span("EasyInstance::headless", String::new);
+        Self::new(app_infos, &InstanceExtensions::none(), None)
+    }
+
+    /// Start building an EasyInstance incrementally
+    ///
+    /// This is an alternative to the new() constructor which lets you opt
+    /// into individual pieces of instance configuration as needed.
+    ///
+    pub fn builder() -> EasyInstanceBuilder {
+        EasyInstanceBuilder::new()
+    }
+
     /// Like new(), but lets you specify manually which types of Vulkan debug
     /// reports you want to listen to.
     pub fn with_debug_config<'a>(
@@ -95,58 +317,220 @@ impl EasyInstance {
         extensions: impl Into<RawInstanceExtensions>,
         layers: impl IntoIterator<Item=&'a str>,
         messages: MessageTypes,
+    ) -> Result<Arc<Self>> {
+        Self::with_debug_filter(app_infos,
+                                extensions,
+                                layers,
+                                messages,
+                                DebugFilter::default())
+    }
+
+    /// Like with_debug_config(), but additionally lets you filter the Vulkan
+    /// debug reports (see DebugFilter for more information).
+    pub fn with_debug_filter<'a>(
+        app_infos: Option<&ApplicationInfo>,
+        extensions: impl Into<RawInstanceExtensions>,
+        layers: impl IntoIterator<Item=&'a str>,
+        messages: MessageTypes,
+        filter: DebugFilter,
+    ) -> Result<Arc<Self>> {
+        Self::with_debug_output(app_infos,
+                                extensions,
+                                layers,
+                                messages,
+                                filter,
+                                None,
+                                true,
+                                ApiLevel::default().desired()).map(Arc::new)
+    }
+
+    /// Like new(), but also sends Vulkan debug messages to a handler of
+    /// your choosing (e.g. to display validation messages in a GUI)
+    ///
+    /// Messages are still logged as usual. All messages except for debug ones
+    /// are enabled, irrespective of the logger configuration, so that your
+    /// handler receives them. Use EasyInstanceBuilder::message_sink() if you
+    /// need more control.
+    ///
+    pub fn with_message_sink<'a>(
+        app_infos: Option<&ApplicationInfo>,
+        extensions: impl Into<RawInstanceExtensions>,
+        layers: impl IntoIterator<Item=&'a str>,
+        sink: impl Fn(&DebugMessage) + Send + Sync + 'static,
+    ) -> Result<Arc<Self>> {
+        let messages = MessageTypes {
+            error: true,
+            warning: true,
+            performance_warning: true,
+            information: true,
+            debug: false,
+        };
+        Self::with_debug_output(app_infos,
+                                extensions,
+                                layers,
+                                messages,
+                                DebugFilter::default(),
+                                Some(MessageSink::new(sink)),
+                                true,
+                                ApiLevel::default().desired()).map(Arc::new)
+    }
+
+    /// Implementation of the constructors, with full control over where
+    /// Vulkan debug messages go and over the instance's API version
+    fn with_debug_output<'a>(
+        app_infos: Option<&ApplicationInfo>,
+        extensions: impl Into<RawInstanceExtensions>,
+        layers: impl IntoIterator<Item=&'a str>,
+        messages: MessageTypes,
+        filter: DebugFilter,
+        sink: Option<MessageSink>,
+        log_messages: bool,
+        max_api_version: Version,
     ) -> Result<Self> {
+        let _trace = trace::span("EasyInstance::with_debug_output", || {
+            format!("messages: {:?}, filter: {:?}, sink: {}, log: {}, \
+                     max_api_version: {}",
+                    messages, filter, sink.is_some(), log_messages,
+                    max_api_version)
+        });
+
+        // Apply the environment configuration overrides
+        let mut layers = layers.into_iter().collect::<Vec<_>>();
+        if EnvConfig::current()?.force_validation {
+            match find_validation_layer()? {
+                Some(layer) => {
+                    info!("Validation was forced, enabling {}", layer);
+                    if !layers.contains(&layer) { layers.push(layer); }
+                },
+                None => warn!("Validation was forced, but no validation layer \
+                               is installed"),
+            }
+        }
+
         // Display Vulkan implementation information
-        if log_enabled!(Level::Info) {
+        if log_enabled!(target: ENUMERATION_TARGET, Level::Info) {
             // Display available instance extensions
             let supported_exts = InstanceExtensions::supported_by_core()?;
-            info!("Supported instance extensions: {:?}", supported_exts);
+            enumeration_info!("Supported instance extensions: {:?}",
+                              supported_exts);
 
             // Display available instance layers
-            info!("Available instance layers:");
+            enumeration_info!("Available instance layers:");
             for layer in instance::layers_list()? {
-                info!("    - {} ({}) [Version {}, targeting Vulkan v{}]",
-                      layer.name(),
-                      layer.description(),
-                      layer.implementation_version(),
-                      layer.vulkan_version());
+                enumeration_info!(
+                    "    - {} ({}) [Version {}, targeting Vulkan v{}]",
+                    layer.name(),
+                    layer.description(),
+                    layer.implementation_version(),
+                    layer.vulkan_version()
+                );
             }
         }
 
+        // Enable VK_EXT_debug_utils for object names and labels, if available
+        let supported_raw_exts =
+            RawInstanceExtensions::supported_by_core_raw()?;
+        let debug_utils = supported_raw_exts.iter().any(|ext| {
+            ext.as_bytes() == DEBUG_UTILS_EXTENSION.as_bytes()
+        });
         let mut raw_extensions = extensions.into();
         raw_extensions.insert(CString::new("VK_EXT_debug_report")?);
+        if debug_utils {
+            info!("Enabling {} for object names and labels",
+                  DEBUG_UTILS_EXTENSION);
+            raw_extensions.insert(CString::new(DEBUG_UTILS_EXTENSION)?);
+        } else {
+            info!("{} is not available, object names will only be shown in \
+                   vulkanoob's logs", DEBUG_UTILS_EXTENSION);
+        }
+        portability::add_instance_extensions(&mut raw_extensions,
+                                             &supported_raw_exts);
+        properties2::add_instance_extensions(&mut raw_extensions,
+                                             &supported_raw_exts);
+        interop::add_instance_extensions(&mut raw_extensions,
+                                         &supported_raw_exts);
+        display::add_instance_extensions(&mut raw_extensions,
+                                         &supported_raw_exts);
+
+        // Attach to RenderDoc, which must be done before instance creation
+        #[cfg(feature = "renderdoc")]
+        let renderdoc = EasyRenderDoc::detect();
 
         // Create our Vulkan instance
-        let instance = Instance::new(app_infos, raw_extensions, layers)?;
+        let (instance, api_version) =
+            loader::create_instance(app_infos,
+                                    raw_extensions.clone(),
+                                    layers,
+                                    max_api_version)?;
 
         // Set up a debug callback
-        let _debug_callback = DebugCallback::new(
+        let object_names = ObjectNames::default();
+        let callback_names = object_names.clone();
+        let message_filter = Arc::new(MessageFilter::new(filter));
+        let callback_filter = message_filter.clone();
+        let sink = AssertUnwindSafe(sink);
+        let debug_callback = DebugCallback::new(
             &instance,
             messages,
-            |msg| {
-                let log_level = match msg.ty {
-                    MessageTypes { error: true, .. } => Level::Error,
-                    MessageTypes { performance_warning: true, .. }
-                    | MessageTypes { warning: true, .. } => Level::Warn,
-                    MessageTypes { information: true, .. } => Level::Info,
-                    MessageTypes { debug: true, .. } => Level::Debug,
-                    _ => unimplemented!()
+            move |msg| {
+                let verdict = callback_filter.process(msg);
+                if verdict == Verdict::Suppress { return; }
+                let message = DebugMessage {
+                    ty: msg.ty,
+                    layer_prefix: msg.layer_prefix.to_owned(),
+                    description: callback_names.annotate(msg.description),
                 };
-                log!(log_level,
-                     "VULKAN{}{}{}{}{} @ {} \t=> {}",
+                if let Some(ref sink) = sink.0 { sink.send(&message); }
+                #[cfg(feature = "tracing")]
+                message.emit_tracing_event();
+                if !log_messages { return; }
+                let log_level = message.level();
+                log!(target: debug::TARGET,
+                     log_level,
+                     "VULKAN{}{}{}{}{} @ {}{} \t=> {}",
                      if msg.ty.error { " ERRO" } else { "" },
                      if msg.ty.warning { " WARN" } else { "" },
                      if msg.ty.performance_warning { " PERF" } else { "" },
                      if msg.ty.information { " INFO" } else { "" },
                      if msg.ty.debug { " DEBG" } else { "" },
-                     msg.layer_prefix, msg.description);
+                     message.layer_prefix,
+                     message.message_id()
+                            .map(|id| format!(" [{}]", id))
+                            .unwrap_or_default(),
+                     message.description);
+                if verdict == Verdict::LogLastRepeat {
+                    log!(target: debug::TARGET,
+                         log_level,
+                         "(Further repetitions of the above Vulkan message \
+                          will be suppressed)");
+                }
+                if verdict == Verdict::Escalate {
+                    panic!("Escalated Vulkan debug message: {}",
+                           message.description);
+                }
             }
         )?;
 
         // Return the freshly built wrapper
+        let known_devices = PhysicalDevice::enumerate(&instance)
+                                           .map(DeviceIdentity::new)
+                                           .collect();
         Ok(EasyInstance {
             instance,
-            _debug_callback,
+            api_version,
+            raw_extensions,
+            _debug_callback: SharedDebugCallback(debug_callback),
+            debug_utils,
+            object_names,
+            message_filter,
+            device_log_detail: Mutex::new(DeviceLogDetail::default()),
+            api_level: Mutex::new(ApiLevel::default()),
+            software_rendering: Mutex::new(SoftwareRendering::default()),
+            known_devices: Mutex::new(known_devices),
+            device_change_callbacks: Mutex::new(Vec::new()),
+            device_report_callbacks: Mutex::new(Vec::new()),
+            #[cfg(feature = "renderdoc")]
+            renderdoc,
         })
     }
 
@@ -155,6 +539,205 @@ impl EasyInstance {
         &self.instance
     }
 
+    /// Vulkan API version targeted by the instance
+    ///
+    /// No device functionality beyond this version may be used, whatever
+    /// the device supports.
+    ///
+    pub fn api_version(&self) -> Version {
+        self.api_version
+    }
+
+    /// Access the attached RenderDoc instance, if any
+    #[cfg(feature = "renderdoc")]
+    pub fn renderdoc(&self) -> Option<&EasyRenderDoc> {
+        self.renderdoc.as_ref()
+    }
+
+    /// Ask RenderDoc to capture the next frame that is presented
+    ///
+    /// Returns false (and logs a warning) if RenderDoc is not attached. See
+    /// EasyRenderDoc for more capture control, including for compute work.
+    ///
+    #[cfg(feature = "renderdoc")]
+    pub fn trigger_capture(&self) -> bool {
+        match self.renderdoc {
+            Some(ref renderdoc) => {
+                renderdoc.trigger_capture();
+                true
+            },
+            None => {
+                warn!("Cannot trigger a capture, RenderDoc is not attached");
+                false
+            },
+        }
+    }
+
+    /// Adjust how much is logged about physical devices during selection
+    ///
+    /// By default, everything is logged (see DeviceLogDetail::Full).
+    ///
+    pub fn set_device_log_detail(&self, detail: DeviceLogDetail) {
+        *self.device_log_detail.lock().unwrap() = detail;
+    }
+
+    /// How much is logged about physical devices during selection
+    pub fn device_log_detail(&self) -> DeviceLogDetail {
+        *self.device_log_detail.lock().unwrap()
+    }
+
+    /// Specify which Vulkan API version your application needs
+    ///
+    /// Devices which do not reach the minimal version are rejected during
+    /// device selection. By default, every Vulkan 1.x device is accepted. The
+    /// usable version is also capped by the instance's API version, which is
+    /// chosen when the instance is created. See the api_level module for more
+    /// information.
+    ///
+    pub fn set_api_level(&self, api_level: ApiLevel) {
+        *self.api_level.lock().unwrap() = api_level;
+    }
+
+    /// Vulkan API version requirements
+    pub fn api_level(&self) -> ApiLevel {
+        *self.api_level.lock().unwrap()
+    }
+
+    /// Specify whether software implementations of Vulkan (lavapipe,
+    /// SwiftShader...) should be preferred or required
+    ///
+    /// By default, they are treated like any other device. Requiring them
+    /// makes device selection deterministic on machines with and without a
+    /// GPU, which is useful for automated tests. This setting can be
+    /// overridden via the environment, see the config module.
+    ///
+    pub fn set_software_rendering(&self, mode: SoftwareRendering) {
+        *self.software_rendering.lock().unwrap() = mode;
+    }
+
+    /// Treatment of software implementations during device selection
+    pub fn software_rendering(&self) -> SoftwareRendering {
+        *self.software_rendering.lock().unwrap()
+    }
+
+    /// Truth that VK_EXT_debug_utils was enabled on the instance
+    ///
+    /// If so, object names and command buffer labels are passed on to the
+    /// Vulkan implementation, see the debug module.
+    ///
+    pub fn supports_debug_utils(&self) -> bool {
+        self.debug_utils
+    }
+
+    /// Check that no debug message was escalated to a panic so far
+    ///
+    /// Vulkano catches the panics that occur inside of debug callbacks, so
+    /// escalated messages (see DebugFilter) do not stop the program on their
+    /// own. Call this at the end of a test run, or at any other convenient
+    /// point, to turn them into an error.
+    ///
+    pub fn check_escalations(&self) -> Result<()> {
+        let escalated = self.message_filter.escalated_messages();
+        if escalated.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Other(format_err!(
+                "{} Vulkan debug message(s) were escalated to panics: {:?}",
+                escalated.len(), escalated
+            )))
+        }
+    }
+
+    /// Names given to Vulkan objects, when VK_EXT_debug_utils is not there
+    pub(crate) fn object_names(&self) -> &ObjectNames {
+        &self.object_names
+    }
+
+    /// Enumerate the physical devices again, and tell what changed
+    ///
+    /// Vulkan instances only enumerate physical devices once, so this goes
+    /// through a temporary instance with the same extensions (but no layers).
+    /// The result is compared with the previous enumeration, which is the one
+    /// of the previous rescan or of instance creation, and the selection
+    /// criteria (the filter, the environment configuration and the API level)
+    /// are evaluated on every device that is currently present.
+    ///
+    /// If devices were added or removed, the callbacks registered with
+    /// on_device_change() are invoked. Call this periodically in long-running
+    /// tools which should react to external GPUs being plugged in or out.
+    ///
+    /// Devices which appeared cannot be used from this EasyInstance, see the
+    /// hotplug module for more information.
+    ///
+    pub fn rescan_devices(
+        &self,
+        mut filter: impl FnMut(PhysicalDevice) -> bool
+    ) -> Result<DeviceChanges> {
+        let _trace = trace::span("EasyInstance::rescan_devices", String::new);
+        let config = EnvConfig::current()?;
+        let software = self.effective_software_rendering(&config);
+        let (instance, _) = loader::create_instance(
+            None,
+            self.raw_extensions.clone(),
+            None,
+            self.api_version
+        )?;
+
+        // Enumerate the devices and check the selection criteria
+        let mut current = Vec::new();
+        let mut selectable = Vec::new();
+        for device in PhysicalDevice::enumerate(&instance) {
+            let identity = DeviceIdentity::new(device);
+            if self.is_selectable(device, &config, software) && filter(device) {
+                selectable.push(identity.clone());
+            }
+            current.push(identity);
+        }
+
+        // Compare with the previous enumeration
+        let changes = {
+            let mut known_devices = self.known_devices.lock().unwrap();
+            let changes = DeviceChanges::new(&known_devices,
+                                             &current,
+                                             selectable);
+            *known_devices = current;
+            changes
+        };
+        if changes.has_changes() {
+            enumeration_info!("{}", changes);
+            invoke_callbacks(&self.device_change_callbacks, &changes);
+        } else {
+            enumeration_debug!("{}", changes);
+        }
+        Ok(changes)
+    }
+
+    /// Register a callback to be invoked when rescan_devices() notices that
+    /// physical devices were added or removed
+    pub fn on_device_change(
+        &self,
+        callback: impl FnMut(&DeviceChanges) + Send + 'static
+    ) {
+        self.device_change_callbacks.lock()
+                                    .unwrap()
+                                    .push(Arc::new(Mutex::new(callback)));
+    }
+
+    /// Register a callback to be invoked with the report of every physical
+    /// device which is considered during device selection
+    ///
+    /// Device reports are only built when they are logged or when such a
+    /// callback is registered, since building them takes some time.
+    ///
+    pub fn on_device_report(
+        &self,
+        callback: impl FnMut(&DeviceReport) + Send + 'static
+    ) {
+        self.device_report_callbacks.lock()
+                                    .unwrap()
+                                    .push(Arc::new(Mutex::new(callback)));
+    }
+
     /// Select a (single) physical device
     ///
     /// As a convenience wrapper, EasyInstance currently focuses on the most
@@ -178,371 +761,945 @@ impl EasyInstance {
     /// enabling you to promptly resolve device selection problems.
     ///
     pub fn select_physical_device(
-        &self,
+        self: &Arc<Self>,
         mut filter: impl FnMut(PhysicalDevice) -> bool,
         mut preference: impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering
     ) -> Result<Option<EasyPhysicalDevice>> {
+        let _trace = trace::span("EasyInstance::select_physical_device",
+                                 String::new);
+        let config = EnvConfig::current()?;
+        let software = self.effective_software_rendering(&config);
+
         // Enumerate the physical devices
-        info!("---- BEGINNING OF PHYSICAL DEVICE LIST ----");
+        enumeration_info!("---- BEGINNING OF PHYSICAL DEVICE LIST ----");
         let mut favorite_device = None;
         for device in PhysicalDevice::enumerate(&self.instance) {
-            // Low-level device and driver information
-            info!("");
-            info!("Device #{}: {}", device.index(), device.name());
-            info!("Type: {:?}", device.ty());
-            info!("Driver version: {}", device.driver_version());
-            info!("PCI vendor/device id: 0x{:x}/0x{:x}",
-                  device.pci_vendor_id(),
-                  device.pci_device_id());
-            if log_enabled!(Level::Info) {
-                let uuid = device.uuid();
-                let mut uuid_str = String::with_capacity(2 * uuid.len());
-                for byte in uuid {
-                    write!(&mut uuid_str, "{:02x}", byte)?;
-                }
-                info!("UUID: 0x{}", uuid_str);
+            // Describe the device's capabilities
+            self.describe_physical_device(device, &config)?;
+
+            // Does it fit our selection criteria?
+            let is_selected = self.is_selectable(device, &config, software)
+                              && filter(device);
+            enumeration_info!("Selected: {}", is_selected);
+
+            // If so, do we consider it better than devices seen before (if any)?
+            if is_selected {
+                consider_device(&mut favorite_device,
+                                device,
+                                &config,
+                                software,
+                                &mut preference);
             }
+        }
+        enumeration_info!("");
+        enumeration_info!("---- END OF PHYSICAL DEVICE LIST ----");
 
-            // Supported Vulkan API version and extensions
-            info!("Vulkan API version: {}", device.api_version());
-            info!("Supported device extensions: {:?}",
-                  DeviceExtensions::supported_by_device(device));
-
-            // Supported Vulkan features
-            let supported_features = device.supported_features();
-            info!("{:#?}", supported_features);
-            ensure!(supported_features.robust_buffer_access,
-                    "Robust buffer access support is mandated by the spec");
-
-            // Queue families
-            if log_enabled!(Level::Info) {
-                info!("Queue familie(s):");
-                let mut family_str = String::new();
-                for family in device.queue_families() {
-                    family_str.clear();
-                    write!(&mut family_str,
-                           "    {}: {} queue(s) for ",
-                           family.id(),
-                           family.queues_count())?;
-                    if family.supports_graphics() {
-                        write!(&mut family_str, "graphics, ")?;
-                    }
-                    if family.supports_compute() {
-                        write!(&mut family_str, "compute, " )?;
-                    }
-                    if family.supports_transfers() {
-                        write!(&mut family_str, "transfers, ")?;
-                    }
-                    if family.supports_sparse_binding() {
-                        write!(&mut family_str, "sparse resource bindings, ")?;
-                    }
-                    info!("{}", family_str);
-                }
+        // Return our physical device of choice (hopefully there is one)
+        Ok(favorite_device.map(|device| self.easy_physical_device(device)))
+    }
+
+    /// Select a (single) physical device, evaluating devices concurrently
+    ///
+    /// This works like select_physical_device(), but the devices are probed
+    /// and the filter is evaluated on every device in parallel, which helps
+    /// on machines with many devices or when the filter is expensive. For
+    /// this to be possible, the filter must be callable from several threads.
+    ///
+    /// Logs are buffered and emitted in enumeration order once every device
+    /// has been evaluated, so they look the same as in sequential mode.
+    ///
+    #[cfg(feature = "parallel")]
+    pub fn select_physical_device_parallel(
+        self: &Arc<Self>,
+        filter: impl Fn(PhysicalDevice) -> bool + Sync,
+        mut preference: impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering
+    ) -> Result<Option<EasyPhysicalDevice>> {
+        let _trace = trace::span(
+            "EasyInstance::select_physical_device_parallel",
+            String::new
+        );
+        let config = EnvConfig::current()?;
+        let software = self.effective_software_rendering(&config);
+        let needs_reports = self.needs_device_reports();
+
+        // Describe the devices and check our selection criteria concurrently
+        let devices = PhysicalDevice::enumerate(&self.instance)
+                                     .collect::<Vec<_>>();
+        let evaluations = devices.par_iter().map(|&device| {
+            let mut report = LazyDeviceReport::new(device);
+            if needs_reports { report.get()?; }
+            let is_selected = self.is_selectable(device, &config, software)
+                              && filter(device);
+            Ok((report, is_selected))
+        }).collect::<Result<Vec<_>>>()?;
+
+        // Log the outcome in enumeration order and pick the favorite device
+        enumeration_info!("---- BEGINNING OF PHYSICAL DEVICE LIST ----");
+        let mut favorite_device = None;
+        let evaluated_devices = devices.into_iter().zip(evaluations);
+        for (device, (mut report, is_selected)) in evaluated_devices {
+            self.publish_device_report(&mut report, &config)?;
+            enumeration_info!("Selected: {}", is_selected);
+            if is_selected {
+                consider_device(&mut favorite_device,
+                                device,
+                                &config,
+                                software,
+                                &mut preference);
             }
+        }
+        enumeration_info!("");
+        enumeration_info!("---- END OF PHYSICAL DEVICE LIST ----");
 
-            // Memory types
-            if log_enabled!(Level::Info) {
-                info!("Memory type(s):");
-                let mut type_str = String::new();
-                for memory_type in device.memory_types() {
-                    type_str.clear();
-                    write!(&mut type_str,
-                           "    {}: from heap #{}, ",
-                           memory_type.id(),
-                           memory_type.heap().id())?;
-                    if memory_type.is_device_local() {
-                        write!(&mut type_str, "on device, ")?;
-                    } else {
-                        write!(&mut type_str, "on host, ")?;
-                    }
-                    if memory_type.is_host_visible() {
-                        write!(&mut type_str, "host-visible, ")?;
-                    } else {
-                        write!(&mut type_str, "only accessible by device, ")?;
-                    }
-                    if memory_type.is_host_coherent() {
-                        write!(&mut type_str, "host-coherent, ")?;
-                    }
-                    if memory_type.is_host_cached() {
-                        write!(&mut type_str, "host-cached, ")?;
-                    }
-                    if memory_type.is_lazily_allocated() {
-                        write!(&mut type_str, "lazily allocated, ")?;
-                    }
-                    info!("{}", type_str);
-                }
+        // Return our physical device of choice (hopefully there is one)
+        Ok(favorite_device.map(|device| self.easy_physical_device(device)))
+    }
+
+    /// Select a (single) physical device, reusing the previous selection if
+    /// possible
+    ///
+    /// This works like select_physical_device(), but remembers the selected
+    /// device in a SelectionCache. If the device which was selected last time
+    /// is still present, and was selected with the same requirements, it is
+    /// only checked against the filter, which skips the slow enumeration and
+    /// logging pass.
+    ///
+    /// Since the filter and preference are closures, which cannot be
+    /// compared, you need to describe them via a hashable "requirements" key.
+    /// Change it whenever your filter or preference changes. The environment
+    /// configuration, API level and software rendering mode are taken into
+    /// account automatically.
+    ///
+    pub fn select_physical_device_cached(
+        self: &Arc<Self>,
+        cache: &SelectionCache,
+        requirements: &impl Hash,
+        mut filter: impl FnMut(PhysicalDevice) -> bool,
+        preference: impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering
+    ) -> Result<Option<EasyPhysicalDevice>> {
+        let _trace = trace::span("EasyInstance::select_physical_device_cached",
+                                 || format!("cache: {}",
+                                            cache.path().display()));
+        let config = EnvConfig::current()?;
+        let software = self.effective_software_rendering(&config);
+        let requirements_hash = selection_cache::requirements_hash(&(
+            selection_cache::requirements_hash(requirements),
+            format!("{:?}", config),
+            format!("{:?}", software),
+            format!("{:?}", self.api_level()),
+        ));
+
+        // Try to reuse the cached selection
+        if let Some(cached) = cache.load() {
+            let device = PhysicalDevice::enumerate(&self.instance)
+                                        .find(|dev| *dev.uuid() == cached.uuid);
+            match device {
+                Some(device) if cached.requirements_hash == requirements_hash
+                                && self.is_selectable(device,
+                                                      &config,
+                                                      software)
+                                && filter(device) => {
+                    enumeration_info!("Reusing the cached selection of device \
+                                       #{} ({})",
+                                      device.index(), device.name());
+                    return Ok(Some(self.easy_physical_device(device)));
+                },
+                _ => enumeration_info!("Cached device selection is outdated, \
+                                        selecting a device again"),
             }
+        }
 
-            // Memory heaps
-            if log_enabled!(Level::Info) {
-                info!("Memory heap(s):");
-                let mut heap_str = String::new();
-                for heap in device.memory_heaps() {
-                    heap_str.clear();
-                    write!(&mut heap_str,
-                           "    {}: {} bytes, ",
-                           heap.id(),
-                           heap.size())?;
-                    if heap.is_device_local() {
-                        write!(&mut heap_str, "on device, ")?;
-                    } else {
-                        write!(&mut heap_str, "on host, ")?;
-                    }
-                    info!("{}", heap_str);
-                }
+        // Otherwise, select a device and remember it
+        let selected = self.select_physical_device(&mut filter, preference)?;
+        if let Some(ref device) = selected {
+            let selection = CachedSelection {
+                uuid: *device.physical_device().uuid(),
+                requirements_hash,
+            };
+            if let Err(e) = cache.store(&selection) {
+                warn!("Failed to update device selection cache {}: {}",
+                      cache.path().display(), e);
             }
+        }
+        Ok(selected)
+    }
 
-            // Device limits
-            info!("Device limits:");
-            let limits = device.limits();
-            info!("    - Max image dimension:");
-            info!("        * 1D: {}",
-                  limits.max_image_dimension_1d());
-            info!("        * 2D: {}",
-                  limits.max_image_dimension_2d());
-            info!("        * 3D: {}",
-                  limits.max_image_dimension_3d());
-            info!("        * Cube: {}",
-                  limits.max_image_dimension_cube());
-            info!("    - Max image array layers: {}",
-                  limits.max_image_array_layers());
-            info!("    - Max texel buffer elements: {}",
-                  limits.max_texel_buffer_elements());
-            info!("    - Max uniform buffer range: {}",
-                  limits.max_uniform_buffer_range());
-            info!("    - Max storage buffer range: {}",
-                  limits.max_storage_buffer_range());
-            info!("    - Max push constants size: {} bytes",
-                  limits.max_push_constants_size());
-            info!("    - Max memory allocation count: {}",
-                  limits.max_memory_allocation_count());
-            info!("    - Max sampler allocation count: {}",
-                  limits.max_sampler_allocation_count());
-            info!("    - Buffer image granularity: {} bytes",
-                  limits.buffer_image_granularity());
-            info!("    - Sparse address space size: {} bytes",
-                  limits.sparse_address_space_size());
-            info!("    - Max bound descriptor sets: {}",
-                  limits.max_bound_descriptor_sets());
-            info!("    - Max per-stage descriptors:");
-            info!("        * Samplers: {}",
-                  limits.max_per_stage_descriptor_samplers());
-            info!("        * Uniform buffers: {}",
-                  limits.max_per_stage_descriptor_uniform_buffers());
-            info!("        * Storage buffers: {}",
-                  limits.max_per_stage_descriptor_storage_buffers());
-            info!("        * Sampled images: {}",
-                  limits.max_per_stage_descriptor_sampled_images());
-            info!("        * Storage images: {}",
-                  limits.max_per_stage_descriptor_storage_images());
-            info!("        * Input attachments: {}",
-                  limits.max_per_stage_descriptor_input_attachments());
-            info!("    - Max per-stage resources: {}",
-                  limits.max_per_stage_resources());
-            info!("    - Max descriptor set:");
-            info!("        * Samplers: {}",
-                  limits.max_descriptor_set_samplers());
-            info!("        * Uniform buffers: {}",
-                  limits.max_descriptor_set_uniform_buffers());
-            info!("        * Dynamic uniform buffers: {}",
-                  limits.max_descriptor_set_uniform_buffers_dynamic());
-            info!("        * Storage buffers: {}",
-                  limits.max_descriptor_set_storage_buffers());
-            info!("        * Dynamic storage buffers: {}",
-                  limits.max_descriptor_set_storage_buffers_dynamic());
-            info!("        * Sampled images: {}",
-                  limits.max_descriptor_set_sampled_images());
-            info!("        * Storage images: {}",
-                  limits.max_descriptor_set_storage_images());
-            info!("        * Input attachments: {}",
-                  limits.max_descriptor_set_input_attachments());
-            info!("    - Vertex input limits:");
-            info!("        * Max attributes: {}",
-                  limits.max_vertex_input_attributes());
-            info!("        * Max bindings: {}",
-                  limits.max_vertex_input_bindings());
-            info!("        * Max attribute offset: {}",
-                  limits.max_vertex_input_attribute_offset());
-            info!("        * Max binding stride: {}",
-                  limits.max_vertex_input_binding_stride());
-            info!("    - Max vertex output components: {}",
-                  limits.max_vertex_output_components());
-            info!("    - Max tesselation generation level: {}",
-                  limits.max_tessellation_generation_level());
-            info!("    - Max tesselation patch size: {} vertices",
-                  limits.max_tessellation_patch_size());
-            info!("    - Tesselation control shader limits:");
-            info!("        * Inputs per vertex: {}",
-                  limits.max_tessellation_control_per_vertex_input_components());
-            info!("        * Outputs per vertex: {}",
-                  limits.max_tessellation_control_per_vertex_output_components());
-            info!("        * Outputs per patch: {}",
-                  limits.max_tessellation_control_per_patch_output_components());
-            info!("        * Total outputs: {}",
-                  limits.max_tessellation_control_total_output_components());
-            info!("    - Tesselation evaluation shader limits:");
-            info!("        * Inputs: {}",
-                  limits.max_tessellation_evaluation_input_components());
-            info!("        * Outputs: {}",
-                  limits.max_tessellation_evaluation_output_components());
-            info!("    - Geometry shader limits:");
-            info!("        * Invocations: {}",
-                  limits.max_geometry_shader_invocations());
-            info!("        * Inputs per vertex: {}",
-                  limits.max_geometry_input_components());
-            info!("        * Outputs per vertex: {}",
-                  limits.max_geometry_output_components());
-            info!("        * Emitted vertices: {}",
-                  limits.max_geometry_output_vertices());
-            info!("        * Total outputs: {}",
-                  limits.max_geometry_total_output_components());
-            info!("    - Fragment shader limits:");
-            info!("        * Inputs: {}",
-                  limits.max_fragment_input_components());
-            info!("        * Output attachmnents: {}",
-                  limits.max_fragment_output_attachments());
-            info!("        * Dual-source output attachments: {}",
-                  limits.max_fragment_dual_src_attachments());
-            info!("        * Combined output resources: {}",
-                  limits.max_fragment_combined_output_resources());
-            info!("    - Compute shader limits:");
-            info!("        * Shared memory: {} bytes",
-                  limits.max_compute_shared_memory_size());
-            info!("        * Work group count: {:?}",
-                  limits.max_compute_work_group_count());
-            info!("        * Work group invocations: {}",
-                  limits.max_compute_work_group_invocations());
-            info!("        * Work group size: {:?}",
-                  limits.max_compute_work_group_size());
-            info!("    - Sub-pixel precision: {} bits",
-                  limits.sub_pixel_precision_bits());
-            info!("    - Sub-texel precision: {} bits",
-                  limits.sub_texel_precision_bits());
-            info!("    - Mipmap precision: {} bits",
-                  limits.mipmap_precision_bits());
-            info!("    - Max draw index: {}",
-                  limits.max_draw_indexed_index_value());
-            info!("    - Max draws per indirect call: {}",
-                  limits.max_draw_indirect_count());
-            info!("    - Max sampler LOD bias: {}",
-                  limits.max_sampler_lod_bias());
-            info!("    - Max anisotropy: {}",
-                  limits.max_sampler_anisotropy());
-            info!("    - Max viewports: {}",
-                  limits.max_viewports());
-            info!("    - Max viewport dimensions: {:?}",
-                  limits.max_viewport_dimensions());
-            info!("    - Viewport bounds range: {:?}",
-                  limits.viewport_bounds_range());
-            info!("    - Viewport subpixel precision: {} bits",
-                  limits.viewport_sub_pixel_bits());
-            info!("    - Minimal alignments:");
-            info!("        * Host allocations: {} bytes",
-                  limits.min_memory_map_alignment());
-            info!("        * Texel buffer offset: {} bytes",
-                  limits.min_texel_buffer_offset_alignment());
-            info!("        * Uniform buffer offset: {} bytes",
-                  limits.min_uniform_buffer_offset_alignment());
-            info!("        * Storage buffer offset: {} bytes",
-                  limits.min_storage_buffer_offset_alignment());
-            info!("    - Offset ranges:");
-            info!("        * Texel fetch: [{}, {}]",
-                  limits.min_texel_offset(),
-                  limits.max_texel_offset());
-            info!("        * Texel gather: [{}, {}]",
-                  limits.min_texel_gather_offset(),
-                  limits.max_texel_gather_offset());
-            info!("        * Interpolation: [{}, {}]",
-                  limits.min_interpolation_offset(),
-                  limits.max_interpolation_offset());
-            info!("    - Sub-pixel interpolation rounding: {} bits",
-                  limits.sub_pixel_interpolation_offset_bits());
-            info!("    - Framebuffer limits:");
-            info!("        * Max size: [{}, {}]",
-                  limits.max_framebuffer_width(),
-                  limits.max_framebuffer_height());
-            info!("        * Max layers: {}",
-                  limits.max_framebuffer_layers());
-            info!("        * Supported color sample counts: 0b{:b}",
-                  limits.framebuffer_color_sample_counts());
-            info!("        * Supported depth sample counts: 0b{:b}",
-                  limits.framebuffer_depth_sample_counts());
-            info!("        * Supported stencil sample counts: 0b{:b}",
-                  limits.framebuffer_stencil_sample_counts());
-            info!("        * Supported detached sample counts: 0b{:b}",
-                  limits.framebuffer_no_attachments_sample_counts());
-            info!("    - Max subpass color attachments: {}",
-                  limits.max_color_attachments());
-            info!("    - Supported sample counts for sampled images:");
-            info!("        * Non-integer color: 0b{:b}",
-                  limits.sampled_image_color_sample_counts());
-            info!("        * Integer color: 0b{:b}",
-                  limits.sampled_image_integer_sample_counts());
-            info!("        * Depth: 0b{:b}",
-                  limits.sampled_image_depth_sample_counts());
-            info!("        * Stencil: 0b{:b}",
-                  limits.sampled_image_stencil_sample_counts());
-            info!("    - Supported storage image sample counts: 0b{:b}",
-                  limits.storage_image_sample_counts());
-            info!("    - Max SampleMask words: {}",
-                  limits.max_sample_mask_words());
-            info!("    - Timestamp support on compute and graphics queues: {}",
-                  limits.timestamp_compute_and_graphics() != 0);
-            info!("    - Timestamp period: {} ns",
-                  limits.timestamp_period());
-            info!("    - Max clip distances: {}",
-                  limits.max_clip_distances());
-            info!("    - Max cull distances: {}",
-                  limits.max_cull_distances());
-            info!("    - Max clip and cull distances: {}",
-                  limits.max_combined_clip_and_cull_distances());
-            info!("    - Discrete queue priorities: {}",
-                  limits.discrete_queue_priorities());
-            info!("    - Point size range: {:?}",
-                  limits.point_size_range());
-            info!("    - Line width range: {:?}",
-                  limits.line_width_range());
-            info!("    - Point size granularity: {}",
-                  limits.point_size_granularity());
-            info!("    - Line width granularity: {}",
-                  limits.line_width_granularity());
-            info!("    - Strict line rasterization: {}",
-                  limits.strict_lines() != 0);
-            info!("    - Standard sample locations: {}",
-                  limits.standard_sample_locations() != 0);
-            info!("    - Optimal buffer copy offset alignment: {} bytes",
-                  limits.optimal_buffer_copy_offset_alignment());
-            info!("    - Optimal buffer copy row pitch alignment: {} bytes",
-                  limits.optimal_buffer_copy_row_pitch_alignment());
-            info!("    - Non-coherent atom size: {} bytes",
-                  limits.non_coherent_atom_size());
+    /// Select a (single) physical device, explaining any rejection
+    ///
+    /// This works like select_physical_device(), but uses a diagnosing device
+    /// filter such as the one produced by easy_device_diagnosis(), which tells
+    /// why a device is rejected instead of merely rejecting it.
+    ///
+    /// If no device is suitable, this returns an Error::NoSuitableDevice which
+    /// contains the reasons why each device was rejected.
+    ///
+    pub fn select_physical_device_diagnosed(
+        self: &Arc<Self>,
+        mut diagnose: impl FnMut(PhysicalDevice) -> Vec<RejectionReason>,
+        mut preference: impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering
+    ) -> Result<EasyPhysicalDevice> {
+        let _trace = trace::span(
+            "EasyInstance::select_physical_device_diagnosed",
+            String::new
+        );
+        let config = EnvConfig::current()?;
+        let software = self.effective_software_rendering(&config);
+
+        // Enumerate the physical devices
+        enumeration_info!("---- BEGINNING OF PHYSICAL DEVICE LIST ----");
+        let mut favorite_device = None;
+        let mut diagnoses = Vec::new();
+        for device in PhysicalDevice::enumerate(&self.instance) {
+            // Describe the device's capabilities
+            self.describe_physical_device(device, &config)?;
 
             // Does it fit our selection criteria?
-            let is_selected = filter(device);
-            info!("Selected: {}", is_selected);
+            let mut reasons = diagnose(device);
+            reasons.extend(self.selection_rejections(device,
+                                                     &config,
+                                                     software));
+            enumeration_info!("Selected: {}", reasons.is_empty());
+            if !reasons.is_empty() {
+                let diagnosis = DeviceDiagnosis {
+                    index: device.index(),
+                    name: device.name(),
+                    reasons,
+                };
+                enumeration_info!("Diagnosis: {}", diagnosis);
+                diagnoses.push(diagnosis);
+                continue;
+            }
 
-            // If so, do we consider it better than devices seen before (if any)?
-            if is_selected {
-                let is_better = if let Some(best_so_far) = favorite_device {
-                    preference(device, best_so_far) == Ordering::Greater
-                } else {
-                    true
+            // If so, is it better than devices seen before (if any)?
+            consider_device(&mut favorite_device,
+                            device,
+                            &config,
+                            software,
+                            &mut preference);
+        }
+        enumeration_info!("");
+        enumeration_info!("---- END OF PHYSICAL DEVICE LIST ----");
+
+        // Return our physical device of choice, or explain why there is none
+        favorite_device.map(|device| self.easy_physical_device(device))
+                       .ok_or_else(|| {
+                           Error::NoSuitableDevice(Diagnoses(diagnoses))
+                       })
+    }
+
+    /// Select all physical devices that match a filter, for multi-GPU work
+    ///
+    /// This works like select_physical_device(), except that every device
+    /// that passes the filter is kept. Devices are returned in an
+    /// EasyDeviceGroup, sorted by decreasing preference.
+    ///
+    pub fn select_physical_devices(
+        self: &Arc<Self>,
+        mut filter: impl FnMut(PhysicalDevice) -> bool,
+        mut preference: impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering
+    ) -> Result<EasyDeviceGroup> {
+        let _trace = trace::span("EasyInstance::select_physical_devices",
+                                 String::new);
+        let config = EnvConfig::current()?;
+        let software = self.effective_software_rendering(&config);
+
+        // Enumerate the physical devices
+        enumeration_info!("---- BEGINNING OF PHYSICAL DEVICE LIST ----");
+        let mut selected_devices = Vec::new();
+        for device in PhysicalDevice::enumerate(&self.instance) {
+            // Describe the device's capabilities
+            self.describe_physical_device(device, &config)?;
+
+            // Does it fit our selection criteria?
+            let is_selected = self.is_selectable(device, &config, software)
+                              && filter(device);
+            enumeration_info!("Selected: {}", is_selected);
+            if is_selected { selected_devices.push(device); }
+        }
+        enumeration_info!("");
+        enumeration_info!("---- END OF PHYSICAL DEVICE LIST ----");
+
+        // Sort the selected devices by decreasing preference
+        selected_devices.sort_by(|&dev1, &dev2| {
+            software.compare(dev2, dev1)
+                    .then_with(|| config.compare(dev2, dev1))
+                    .then_with(|| preference(dev2, dev1))
+        });
+        enumeration_info!("Selected devices, by order of preference: {:?}",
+                          selected_devices.iter().map(|dev| dev.name())
+                                                 .collect::<Vec<_>>());
+
+        // Return our physical devices of choice (hopefully there are some)
+        Ok(EasyDeviceGroup::new(
+            selected_devices.into_iter()
+                            .map(|device| self.easy_physical_device(device))
+                            .collect()
+        ))
+    }
+
+    /// Select a (single) physical device by scoring each candidate
+    ///
+    /// This is an alternative to select_physical_device() where the filter
+    /// and preference are merged into a single scoring function. A score of
+    /// None means that the device is rejected, otherwise the device with the
+    /// highest score wins. On ties, the first device wins.
+    ///
+    /// This is often easier to write than a pairwise preference, and makes it
+    /// easy to combine criteria by giving each of them a weight.
+    ///
+    pub fn select_physical_device_by_score(
+        self: &Arc<Self>,
+        mut scorer: impl FnMut(PhysicalDevice) -> Option<u64>
+    ) -> Result<Option<EasyPhysicalDevice>> {
+        let _trace = trace::span(
+            "EasyInstance::select_physical_device_by_score",
+            String::new
+        );
+        let config = EnvConfig::current()?;
+        let software = self.effective_software_rendering(&config);
+
+        // Enumerate the physical devices
+        enumeration_info!("---- BEGINNING OF PHYSICAL DEVICE LIST ----");
+        let mut favorite_device: Option<(PhysicalDevice, u64)> = None;
+        for device in PhysicalDevice::enumerate(&self.instance) {
+            // Describe the device's capabilities
+            self.describe_physical_device(device, &config)?;
+
+            // Does it fit our selection criteria, and if so how well?
+            let allowed = self.is_selectable(device, &config, software);
+            let score = if allowed {
+                scorer(device)
+            } else {
+                None
+            };
+            match score {
+                Some(score) => enumeration_info!("Score: {}", score),
+                None => enumeration_info!("Score: rejected"),
+            }
+
+            // Do we consider it better than devices seen before (if any)?
+            if let Some(score) = score {
+                let is_better = match favorite_device {
+                    Some((best_so_far, best_score)) => {
+                        software.compare(device, best_so_far)
+                            .then_with(|| config.compare(device, best_so_far))
+                            .then(score.cmp(&best_score))
+                            == Ordering::Greater
+                    },
+                    None => true,
                 };
-                if is_better { favorite_device = Some(device); }
-                info!("Preferred: {}", is_better);
+                if is_better { favorite_device = Some((device, score)); }
+                enumeration_info!("Preferred: {}", is_better);
             }
         }
-        info!("");
-        info!("---- END OF PHYSICAL DEVICE LIST ----");
+        enumeration_info!("");
+        enumeration_info!("---- END OF PHYSICAL DEVICE LIST ----");
 
         // Return our physical device of choice (hopefully there is one)
-        Ok(favorite_device.map(EasyPhysicalDevice::new))
+        Ok(favorite_device.map(|(device, _)| self.easy_physical_device(device)))
+    }
+
+    /// Select the physical device with a certain index, bypassing the usual
+    /// filter and preference
+    ///
+    /// This is an escape hatch for when the device selection heuristics pick
+    /// the wrong device, e.g. on multi-GPU laptops. Devices are still logged,
+    /// and the selected device is still checked for basic compatibility with
+    /// vulkanoob, but your own requirements are not checked.
+    ///
+    pub fn select_physical_device_by_index(
+        self: &Arc<Self>,
+        index: usize
+    ) -> Result<EasyPhysicalDevice> {
+        let _trace = trace::span(
+            "EasyInstance::select_physical_device_by_index",
+            || format!("index: {}", index)
+        );
+        self.select_physical_device_override(
+            &format!("index {}", index),
+            |device| device.index() == index
+        )
+    }
+
+    /// Select the first physical device whose name contains a substring
+    /// (ignoring case), bypassing the usual filter and preference
+    ///
+    /// See select_physical_device_by_index() for more details.
+    ///
+    pub fn select_physical_device_by_name(
+        self: &Arc<Self>,
+        name_substring: &str
+    ) -> Result<EasyPhysicalDevice> {
+        let _trace = trace::span(
+            "EasyInstance::select_physical_device_by_name",
+            || format!("name_substring: {:?}", name_substring)
+        );
+        let pattern = name_substring.to_lowercase();
+        self.select_physical_device_override(
+            &format!("name containing {:?}", name_substring),
+            |device| device.name().to_lowercase().contains(&pattern)
+        )
+    }
+
+    /// Select the physical device with a certain UUID, bypassing the usual
+    /// filter and preference
+    ///
+    /// Device UUIDs are stable across runs and device enumeration order
+    /// changes, which makes them a good fit for configuration files.
+    ///
+    /// See select_physical_device_by_index() for more details.
+    ///
+    pub fn select_physical_device_by_uuid(
+        self: &Arc<Self>,
+        uuid: &[u8; 16]
+    ) -> Result<EasyPhysicalDevice> {
+        let _trace = trace::span(
+            "EasyInstance::select_physical_device_by_uuid",
+            || format!("uuid: {:?}", uuid)
+        );
+        self.select_physical_device_override(
+            &format!("UUID {:?}", uuid),
+            |device| device.uuid() == uuid
+        )
+    }
+
+    /// Implementation of the select_physical_device_by_xyz() overrides
+    fn select_physical_device_override(
+        self: &Arc<Self>,
+        description: &str,
+        mut matches: impl FnMut(PhysicalDevice) -> bool
+    ) -> Result<EasyPhysicalDevice> {
+        let config = EnvConfig::current()?;
+        let features = Features::none();
+        let extensions = DeviceExtensions::none();
+        let mut diagnose = easy_device_diagnosis(&features,
+                                                 &extensions,
+                                                 |_| true,
+                                                 |_| true);
+
+        // Enumerate the physical devices
+        enumeration_info!("---- BEGINNING OF PHYSICAL DEVICE LIST ----");
+        enumeration_info!("(Selecting the device with {})", description);
+        let mut selected_device = None;
+        for device in PhysicalDevice::enumerate(&self.instance) {
+            // Describe the device's capabilities
+            self.describe_physical_device(device, &config)?;
+
+            // Is this the device that we are looking for?
+            let is_selected = selected_device.is_none() && matches(device);
+            enumeration_info!("Selected: {}", is_selected);
+            if is_selected { selected_device = Some(device); }
+        }
+        enumeration_info!("");
+        enumeration_info!("---- END OF PHYSICAL DEVICE LIST ----");
+
+        // Check that the device was found and can be used by vulkanoob
+        let device = selected_device.ok_or_else(|| {
+            Error::InvalidArgument(format!("No physical device with {}",
+                                           description))
+        })?;
+        let mut reasons = diagnose(device);
+        if let Some(reason) = self.api_level_rejection(device) {
+            reasons.push(reason);
+        }
+        if !reasons.is_empty() {
+            return Err(Error::NoSuitableDevice(Diagnoses(vec![
+                DeviceDiagnosis {
+                    index: device.index(),
+                    name: device.name(),
+                    reasons,
+                }
+            ])));
+        }
+        Ok(self.easy_physical_device(device))
+    }
+
+    /// Describe a physical device's capabilities in the logs, at the INFO
+    /// level, and hand its report to the device report callbacks
+    fn describe_physical_device(&self,
+                                device: PhysicalDevice,
+                                config: &EnvConfig) -> Result<()> {
+        let mut report = LazyDeviceReport::new(device);
+        self.publish_device_report(&mut report, config)
+    }
+
+    /// Log a device report and hand it to the device report callbacks,
+    /// building it only if someone is going to look at it
+    fn publish_device_report(&self,
+                             report: &mut LazyDeviceReport,
+                             config: &EnvConfig) -> Result<()> {
+        let detail = self.device_log_detail();
+        if is_logging_devices(detail) {
+            log_device_report(report.get()?, config, detail);
+        }
+        let has_callbacks =
+            !self.device_report_callbacks.lock().unwrap().is_empty();
+        if has_callbacks {
+            invoke_callbacks(&self.device_report_callbacks, report.get()?);
+        }
+        Ok(())
+    }
+
+    /// Truth that describing physical devices requires building their report
+    #[cfg(feature = "parallel")]
+    fn needs_device_reports(&self) -> bool {
+        is_logging_devices(self.device_log_detail())
+            || !self.device_report_callbacks.lock().unwrap().is_empty()
+    }
+
+    /// Treatment of software implementations, taking the environment
+    /// configuration into account
+    fn effective_software_rendering(
+        &self,
+        config: &EnvConfig
+    ) -> SoftwareRendering {
+        config.software.unwrap_or_else(|| self.software_rendering())
+    }
+
+    /// Tell why a device is rejected by the selection criteria which every
+    /// device selector applies on top of the user's own requirements, namely
+    /// the environment configuration, the software rendering mode and the
+    /// API level requirements
+    fn selection_rejections(
+        &self,
+        device: PhysicalDevice,
+        config: &EnvConfig,
+        software: SoftwareRendering
+    ) -> Vec<RejectionReason> {
+        let mut reasons = Vec::new();
+        if !config.allows_device(device) {
+            reasons.push(RejectionReason::EnvOverride);
+        }
+        if !software.allows_device(device) {
+            reasons.push(RejectionReason::NotSoftware);
+        }
+        if let Some(reason) = self.api_level_rejection(device) {
+            reasons.push(reason);
+        }
+        reasons
+    }
+
+    /// Truth that a device passes the selection criteria of
+    /// selection_rejections()
+    fn is_selectable(&self,
+                     device: PhysicalDevice,
+                     config: &EnvConfig,
+                     software: SoftwareRendering) -> bool {
+        self.selection_rejections(device, config, software).is_empty()
+    }
+
+    /// Tell why a device does not fulfill the API level requirements, if it
+    /// doesn't
+    fn api_level_rejection(
+        &self,
+        device: PhysicalDevice
+    ) -> Option<RejectionReason> {
+        self.api_level().rejection(device, self.api_version)
+    }
+
+    /// Wrap a selected device, recording which API version it can use
+    fn easy_physical_device(
+        self: &Arc<Self>,
+        device: PhysicalDevice
+    ) -> EasyPhysicalDevice {
+        EasyPhysicalDevice::with_api_level(self.clone(),
+                                           device,
+                                           &self.api_level())
     }
 }
 
 impl Drop for EasyInstance {
-    /// Warn the user that dropping causes the logger to be dropped
+    /// Warn the user that dropping causes the logger to be dropped, and log
+    /// any debug message that was escalated to a panic
     fn drop(&mut self) {
-        info!("EasyInstance was dropped, Vulkan logging will now shut down.")
+        info!("EasyInstance was dropped, Vulkan logging will now shut down.");
+        let escalated = self.message_filter.escalated_messages();
+        if !escalated.is_empty() {
+            error!("{} Vulkan debug message(s) were escalated to panics: {:?}",
+                   escalated.len(), escalated);
+        }
     }
-}
\ No newline at end of file
+}
+
+
+/// Invoke a list of callbacks, without holding the lock of the list
+///
+/// This lets the callbacks use the EasyInstance, e.g. to register other
+/// callbacks. A callback which is already running, because it indirectly
+/// triggered its own invocation, is skipped.
+///
+fn invoke_callbacks<T>(callbacks: &Mutex<Vec<Callback<T>>>, arg: &T) {
+    let callbacks = callbacks.lock().unwrap().clone();
+    for callback in callbacks {
+        match callback.try_lock() {
+            Ok(mut callback) => (*callback)(arg),
+            Err(TryLockError::Poisoned(e)) => (*e.into_inner())(arg),
+            Err(TryLockError::WouldBlock) => {
+                debug!("Skipping a callback which is already running")
+            },
+        }
+    }
+}
+
+/// Consider a device which passed the selection criteria, making it the
+/// favorite device if it is preferred over the current favorite (if any)
+fn consider_device(
+    favorite_device: &mut Option<PhysicalDevice>,
+    device: PhysicalDevice,
+    config: &EnvConfig,
+    software: SoftwareRendering,
+    preference: &mut impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering
+) {
+    let is_better = if let Some(best_so_far) = *favorite_device {
+        software.compare(device, best_so_far)
+            .then_with(|| config.compare(device, best_so_far))
+            .then_with(|| preference(device, best_so_far))
+            == Ordering::Greater
+    } else {
+        true
+    };
+    if is_better { *favorite_device = Some(device); }
+    enumeration_info!("Preferred: {}", is_better);
+}
+
+/// Truth that physical device descriptions are going to be logged
+fn is_logging_devices(detail: DeviceLogDetail) -> bool {
+    detail != DeviceLogDetail::Off
+        && log_enabled!(target: ENUMERATION_TARGET, Level::Info)
+}
+
+/// Log a physical device's capabilities, at the INFO level
+fn log_device_report(report: &DeviceReport,
+                     config: &EnvConfig,
+                     detail: DeviceLogDetail) {
+    enumeration_info!("");
+    if detail == DeviceLogDetail::Summary {
+        for line in report.summary().to_string().lines() {
+            enumeration_info!("{}", line);
+        }
+        return;
+    }
+
+    // The device limits are a flood of hundreds of lines, which is only
+    // logged at the DEBUG level
+    let mut report = report.clone();
+    let limits = mem::replace(&mut report.limits, Vec::new());
+    for line in report.to_string().lines() {
+        enumeration_info!("{}", line);
+    }
+    if config.log_limits
+       && log_enabled!(target: ENUMERATION_TARGET, Level::Debug) {
+        enumeration_debug!("Device limits:");
+        for (name, value) in limits {
+            enumeration_debug!("    - {}: {}", name, value);
+        }
+    }
+}
+
+
+/// Names of the standard Vulkan validation layers, by order of preference
+///
+/// The Khronos layer is the modern one, the LunarG names are what older Vulkan
+/// SDKs provide.
+///
+pub const VALIDATION_LAYERS: &[&str] = &[
+    "VK_LAYER_KHRONOS_validation",
+    "VK_LAYER_LUNARG_standard_validation",
+];
+
+
+/// Find which standard validation layer is installed, if any
+pub fn find_validation_layer() -> Result<Option<&'static str>> {
+    let available_layers = instance::layers_list()?
+                                    .map(|layer| layer.name().to_owned())
+                                    .collect::<Vec<_>>();
+    Ok(VALIDATION_LAYERS.iter()
+                        .cloned()
+                        .find(|name| available_layers.iter()
+                                                     .any(|l| l == name)))
+}
+
+
+/// Negotiate instance extensions with the Vulkan implementation
+///
+/// Required extensions must be supported, or an error listing the missing
+/// ones is returned. Optional ones are enabled if supported, and left out
+/// otherwise. The result can be passed (by reference) to EasyInstance::new()
+/// or to EasyInstanceBuilder::enable_extensions().
+///
+pub fn negotiate_instance_extensions(
+    required: &InstanceExtensions,
+    optional: &InstanceExtensions,
+) -> Result<InstanceExtensions> {
+    let _trace = trace::span("instance::negotiate_instance_extensions", || {
+        format!("required: {:?}, optional: {:?}", required, optional)
+    });
+    let supported = InstanceExtensions::supported_by_core()?;
+
+    // Required extensions must be there
+    let missing_required = required.difference(&supported);
+    if missing_required != InstanceExtensions::none() {
+        return Err(Error::MissingInstanceExtensions(
+            instance_extension_names(&missing_required)
+        ));
+    }
+
+    // Optional extensions are enabled if available
+    let enabled_optional = optional.intersection(&supported);
+    info!("Enabled optional instance extensions: {:?}",
+          instance_extension_names(&enabled_optional));
+    info!("Missing optional instance extensions: {:?}",
+          instance_extension_names(&optional.difference(&supported)));
+    Ok(required.union(&enabled_optional))
+}
+
+/// Names of a set of instance extensions
+fn instance_extension_names(extensions: &InstanceExtensions) -> Vec<String> {
+    RawInstanceExtensions::from(extensions)
+        .iter()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .collect()
+}
+
+
+/// Debug report configuration matching the logger configuration
+fn default_message_types() -> MessageTypes {
+    let enabled = |level: Level| log_enabled!(target: debug::TARGET, level);
+    MessageTypes {
+        error: enabled(Level::Error),
+        warning: enabled(Level::Warn),
+        performance_warning: enabled(Level::Warn),
+        information: enabled(Level::Info),
+        debug: enabled(Level::Debug),
+    }
+}
+
+
+/// Incremental builder for EasyInstance
+///
+/// Start from EasyInstance::builder(), chain the configuration methods that
+/// you need, then call build() to get your EasyInstance. Anything which you do
+/// not specify gets the same default as in EasyInstance::new().
+///
+#[derive(Clone, Debug)]
+pub struct EasyInstanceBuilder {
+    /// Application and engine information
+    app_infos: ApplicationInfo<'static>,
+
+    /// Instance extensions to be enabled
+    extensions: RawInstanceExtensions,
+
+    /// Instance layers to be enabled
+    layers: Vec<String>,
+
+    /// Debug report configuration (default: based on the logger's)
+    messages: Option<MessageTypes>,
+
+    /// Truth that the standard validation layer should be enabled
+    validation: bool,
+
+    /// Debug message filter configuration
+    filter: DebugFilter,
+
+    /// Level of detail of physical device descriptions
+    device_log_detail: DeviceLogDetail,
+
+    /// Vulkan API version requirements
+    api_level: ApiLevel,
+
+    /// Treatment of software implementations during device selection
+    software_rendering: SoftwareRendering,
+
+    /// User-provided handler of Vulkan debug messages
+    sink: Option<MessageSink>,
+
+    /// Truth that Vulkan debug messages should be logged
+    log_messages: bool,
+}
+
+impl EasyInstanceBuilder {
+    /// Start with an empty configuration
+    pub fn new() -> Self {
+        EasyInstanceBuilder {
+            app_infos: ApplicationInfo {
+                application_name: None,
+                application_version: None,
+                engine_name: None,
+                engine_version: None,
+            },
+            extensions: RawInstanceExtensions::none(),
+            layers: Vec::new(),
+            messages: None,
+            validation: false,
+            filter: DebugFilter::default(),
+            device_log_detail: DeviceLogDetail::default(),
+            api_level: ApiLevel::default(),
+            software_rendering: SoftwareRendering::default(),
+            sink: None,
+            log_messages: true,
+        }
+    }
+
+    /// Set the application name
+    pub fn app_name(mut self, name: impl Into<String>) -> Self {
+        self.app_infos.application_name = Some(Cow::Owned(name.into()));
+        self
+    }
+
+    /// Set the application version
+    pub fn app_version(mut self, version: Version) -> Self {
+        self.app_infos.application_version = Some(version);
+        self
+    }
+
+    /// Set the engine name
+    pub fn engine_name(mut self, name: impl Into<String>) -> Self {
+        self.app_infos.engine_name = Some(Cow::Owned(name.into()));
+        self
+    }
+
+    /// Set the engine version
+    pub fn engine_version(mut self, version: Version) -> Self {
+        self.app_infos.engine_version = Some(version);
+        self
+    }
+
+    /// Enable a set of instance extensions
+    pub fn enable_extensions(
+        mut self,
+        extensions: impl Into<RawInstanceExtensions>
+    ) -> Self {
+        self.extensions = self.extensions.union(&extensions.into());
+        self
+    }
+
+    /// Negotiate and enable a set of instance extensions
+    ///
+    /// See negotiate_instance_extensions() for more information.
+    ///
+    pub fn negotiate_extensions(
+        self,
+        required: &InstanceExtensions,
+        optional: &InstanceExtensions,
+    ) -> Result<Self> {
+        let extensions = negotiate_instance_extensions(required, optional)?;
+        Ok(self.enable_extensions(&extensions))
+    }
+
+    /// Enable an instance extension by name
+    pub fn enable_extension(mut self, name: &str) -> Result<Self> {
+        self.extensions.insert(CString::new(name)?);
+        Ok(self)
+    }
+
+    /// Enable the instance extensions needed by EasyWindow
+    #[cfg(feature = "window")]
+    pub fn window_extensions(self) -> Self {
+        self.enable_extensions(EasyWindow::required_extensions())
+    }
+
+    /// Enable an instance layer by name
+    pub fn enable_layer(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        if !self.layers.contains(&name) {
+            self.layers.push(name);
+        }
+        self
+    }
+
+    /// Enable or disable the standard validation layer
+    ///
+    /// The validation layer is looked up when build() is called, see
+    /// EasyInstance::with_validation() for details.
+    ///
+    pub fn validation(mut self, enabled: bool) -> Self {
+        self.validation = enabled;
+        self
+    }
+
+    /// Specify which types of Vulkan debug reports should be logged
+    pub fn debug_messages(mut self, messages: MessageTypes) -> Self {
+        self.messages = Some(messages);
+        self
+    }
+
+    /// Specify how Vulkan debug reports should be filtered
+    pub fn debug_filter(mut self, filter: DebugFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Send Vulkan debug messages to a handler of your choosing
+    ///
+    /// The handler receives the messages which were enabled using
+    /// debug_messages() and made it through the debug_filter(). Remember
+    /// that by default, the enabled messages depend on the logger config.
+    ///
+    pub fn message_sink(
+        mut self,
+        sink: impl Fn(&DebugMessage) + Send + Sync + 'static
+    ) -> Self {
+        self.sink = Some(MessageSink::new(sink));
+        self
+    }
+
+    /// Enable or disable the logging of Vulkan debug messages (default: on)
+    ///
+    /// This is mostly useful in combination with a message_sink().
+    ///
+    pub fn log_messages(mut self, enabled: bool) -> Self {
+        self.log_messages = enabled;
+        self
+    }
+
+    /// Specify which Vulkan API version your application needs
+    pub fn api_level(mut self, api_level: ApiLevel) -> Self {
+        self.api_level = api_level;
+        self
+    }
+
+    /// Prefer or require software implementations of Vulkan, e.g. to get
+    /// deterministic device selection in automated tests
+    pub fn software_rendering(mut self, mode: SoftwareRendering) -> Self {
+        self.software_rendering = mode;
+        self
+    }
+
+    /// Specify how much is logged about physical devices during selection
+    pub fn device_log_detail(mut self, detail: DeviceLogDetail) -> Self {
+        self.device_log_detail = detail;
+        self
+    }
+
+    /// Build the EasyInstance
+    pub fn build(mut self) -> Result<Arc<EasyInstance>> {
+        if self.validation {
+            if let Some(layer) = find_validation_layer()? {
+                self = self.enable_layer(layer);
+            } else {
+                warn!("Validation was requested, but no validation layer is \
+                       installed. Please install the Vulkan SDK.");
+            }
+        }
+        let instance = EasyInstance::with_debug_output(
+            Some(&self.app_infos),
+            self.extensions,
+            self.layers.iter().map(|layer| &layer[..]),
+            self.messages.unwrap_or_else(default_message_types),
+            self.filter,
+            self.sink,
+            self.log_messages,
+            self.api_level.desired(),
+        )?;
+        instance.set_device_log_detail(self.device_log_detail);
+        instance.set_api_level(self.api_level);
+        instance.set_software_rendering(self.software_rendering);
+        Ok(Arc::new(instance))
+    }
+}
+
+impl Default for EasyInstanceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
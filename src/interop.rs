@@ -0,0 +1,1025 @@
+//! Sharing memory with other APIs through external memory handles
+//!
+//! VK_KHR_external_memory lets device memory be exported as an OS handle (a
+//! file descriptor on Unix, a HANDLE on Windows), which OpenGL
+//! (GL_EXT_memory_object), CUDA (cudaImportExternalMemory) or another Vulkan
+//! device can then import, and the other way around. This is the standard way
+//! to share textures with an existing OpenGL or CUDA context.
+//!
+//! Vulkano does not support external memory yet, so this module does the
+//! parts that it cannot do: enabling the extensions (see
+//! EasyPhysicalDevice::setup_interop_device(), and EasyInstance which enables
+//! VK_KHR_external_memory_capabilities when available), allocating exportable
+//! memory, importing memory handles, and making sure that handles are closed
+//! when they are not needed anymore.
+//!
+//! EasyExternalBuffer and EasyExternalImage create buffers and 2D images
+//! which are bound to such memory, for the common case where the whole
+//! allocation is shared. Other resources can be created with the Vulkan API
+//! directly, chaining an ExternalMemoryBufferCreateInfo or
+//! ExternalMemoryImageCreateInfo to their create info, and bound to an
+//! EasyExternalMemory with vkBind(Buffer|Image)Memory.
+
+use ::{
+    device::EasyDevice,
+    error::check_result,
+    loader,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    ffi::CString,
+    fs::File,
+    mem,
+    os::raw::c_void,
+    ptr,
+    sync::Arc,
+};
+
+#[cfg(unix)]
+use std::os::{
+    raw::c_int,
+    unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+};
+
+#[cfg(windows)]
+use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
+
+use vk_sys as vk;
+
+use vulkano::{
+    device::Device,
+    format::Format,
+    instance::{
+        PhysicalDevice,
+        RawDeviceExtensions,
+        RawInstanceExtensions,
+    },
+    VulkanObject,
+};
+
+
+/// Name of the instance extension which device external memory extensions
+/// depend on, before Vulkan 1.1
+pub const EXTERNAL_MEMORY_CAPABILITIES_EXTENSION: &str =
+    "VK_KHR_external_memory_capabilities";
+
+/// Name of the base external memory extension
+pub const EXTERNAL_MEMORY_EXTENSION: &str = "VK_KHR_external_memory";
+
+/// Name of the file descriptor external memory extension
+pub const EXTERNAL_MEMORY_FD_EXTENSION: &str = "VK_KHR_external_memory_fd";
+
+/// Name of the Win32 handle external memory extension
+pub const EXTERNAL_MEMORY_WIN32_EXTENSION: &str =
+    "VK_KHR_external_memory_win32";
+
+/// Structure types which vk-sys does not know about
+const STRUCTURE_TYPE_EXTERNAL_MEMORY_BUFFER_CREATE_INFO: u32 = 1_000_072_000;
+const STRUCTURE_TYPE_EXTERNAL_MEMORY_IMAGE_CREATE_INFO: u32 = 1_000_072_001;
+const STRUCTURE_TYPE_EXPORT_MEMORY_ALLOCATE_INFO: u32 = 1_000_072_002;
+#[cfg(windows)]
+const STRUCTURE_TYPE_IMPORT_MEMORY_WIN32_HANDLE_INFO: u32 = 1_000_073_000;
+#[cfg(windows)]
+const STRUCTURE_TYPE_MEMORY_GET_WIN32_HANDLE_INFO: u32 = 1_000_073_003;
+#[cfg(unix)]
+const STRUCTURE_TYPE_IMPORT_MEMORY_FD_INFO: u32 = 1_000_074_000;
+#[cfg(unix)]
+const STRUCTURE_TYPE_MEMORY_GET_FD_INFO: u32 = 1_000_074_002;
+
+
+/// Kind of OS handle which memory is shared through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalMemoryHandleType {
+    /// POSIX file descriptor, which can only be used by compatible drivers
+    /// (VK_EXTERNAL_MEMORY_HANDLE_TYPE_OPAQUE_FD_BIT)
+    OpaqueFd,
+
+    /// Windows NT handle, which can only be used by compatible drivers
+    /// (VK_EXTERNAL_MEMORY_HANDLE_TYPE_OPAQUE_WIN32_BIT)
+    OpaqueWin32,
+}
+
+impl ExternalMemoryHandleType {
+    /// Handle type which is native to the current platform, if any
+    pub fn native() -> Option<Self> {
+        if cfg!(unix) {
+            Some(ExternalMemoryHandleType::OpaqueFd)
+        } else if cfg!(windows) {
+            Some(ExternalMemoryHandleType::OpaqueWin32)
+        } else {
+            None
+        }
+    }
+
+    /// Device extensions which must be enabled to use this handle type
+    pub fn extensions(self) -> [&'static str; 2] {
+        [EXTERNAL_MEMORY_EXTENSION,
+         match self {
+             ExternalMemoryHandleType::OpaqueFd => EXTERNAL_MEMORY_FD_EXTENSION,
+             ExternalMemoryHandleType::OpaqueWin32 =>
+                 EXTERNAL_MEMORY_WIN32_EXTENSION,
+         }]
+    }
+
+    /// Vulkan's VkExternalMemoryHandleTypeFlagBits for this handle type
+    pub fn bits(self) -> u32 {
+        match self {
+            ExternalMemoryHandleType::OpaqueFd => 0x1,
+            ExternalMemoryHandleType::OpaqueWin32 => 0x2,
+        }
+    }
+
+    /// Check that this handle type can be used on the current platform
+    fn check_platform(self) -> Result<()> {
+        if ExternalMemoryHandleType::native() == Some(self) {
+            Ok(())
+        } else {
+            Err(Error::InvalidArgument(format!(
+                "{:?} handles are not supported on this platform", self
+            )))
+        }
+    }
+}
+
+
+/// Extensions needed by some handle type which a device does not support
+pub fn missing_extensions(
+    device: PhysicalDevice,
+    handle_type: ExternalMemoryHandleType
+) -> Result<Vec<String>> {
+    let extensions = RawDeviceExtensions::supported_by_device_raw(device)?;
+    Ok(handle_type.extensions()
+                  .iter()
+                  .filter(|name| {
+                      !extensions.iter()
+                                 .any(|ext| ext.as_bytes() == name.as_bytes())
+                  })
+                  .map(|name| name.to_string())
+                  .collect())
+}
+
+/// Device filter which only accepts devices that can share memory using some
+/// handle type
+///
+/// This is meant to be used as (part of) the "other_criteria" parameter of
+/// easy_device_filter(). Devices whose extensions cannot be queried are
+/// rejected.
+///
+pub fn supports_external_memory(
+    handle_type: ExternalMemoryHandleType
+) -> impl FnMut(PhysicalDevice) -> bool {
+    move |device| {
+        handle_type.check_platform().is_ok()
+            && missing_extensions(device, handle_type)
+                   .map(|missing| missing.is_empty())
+                   .unwrap_or(false)
+    }
+}
+
+/// Add VK_KHR_external_memory_capabilities to a set of instance extensions,
+/// if it is supported, so that devices can enable external memory extensions
+pub(crate) fn add_instance_extensions(
+    extensions: &mut RawInstanceExtensions,
+    supported: &RawInstanceExtensions,
+) {
+    if supported.iter().any(|ext| {
+        ext.as_bytes() == EXTERNAL_MEMORY_CAPABILITIES_EXTENSION.as_bytes()
+    }) {
+        info!("Enabling {} for external memory sharing",
+              EXTERNAL_MEMORY_CAPABILITIES_EXTENSION);
+        extensions.insert(CString::new(EXTERNAL_MEMORY_CAPABILITIES_EXTENSION)
+                                  .expect("Extension name has no NUL"));
+    }
+}
+
+/// Add the extensions needed by some handle type to a set of device
+/// extensions, or tell which ones are missing
+pub(crate) fn add_device_extensions(
+    device: PhysicalDevice,
+    handle_type: ExternalMemoryHandleType,
+    extensions: &mut RawDeviceExtensions,
+) -> Result<()> {
+    handle_type.check_platform()?;
+    let missing = missing_extensions(device, handle_type)?;
+    if !missing.is_empty() {
+        return Err(Error::MissingExtensions {
+            device: device.name(),
+            extensions: missing,
+        });
+    }
+    for name in handle_type.extensions().iter() {
+        extensions.insert(CString::new(*name)
+                                  .expect("Extension name has no NUL"));
+    }
+    Ok(())
+}
+
+
+/// OS handle to some external memory, which is closed when dropped
+///
+/// On Unix, this is a file descriptor. On Windows, this is a HANDLE.
+///
+#[derive(Debug)]
+pub struct ExternalMemoryHandle {
+    /// File which owns the OS handle and will close it
+    file: File,
+
+    /// Type of handle
+    handle_type: ExternalMemoryHandleType,
+}
+
+impl ExternalMemoryHandle {
+    /// Take ownership of a file descriptor, e.g. one exported by OpenGL
+    ///
+    /// This is unsafe because the file descriptor must be open and must not
+    /// be owned by anything else, since it will be closed on drop.
+    ///
+    #[cfg(unix)]
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        ExternalMemoryHandle {
+            file: File::from_raw_fd(fd),
+            handle_type: ExternalMemoryHandleType::OpaqueFd,
+        }
+    }
+
+    /// Take ownership of a Windows HANDLE, e.g. one exported by CUDA
+    ///
+    /// This is unsafe because the handle must be valid and must not be owned
+    /// by anything else, since it will be closed on drop.
+    ///
+    #[cfg(windows)]
+    pub unsafe fn from_raw_handle(handle: RawHandle) -> Self {
+        ExternalMemoryHandle {
+            file: File::from_raw_handle(handle),
+            handle_type: ExternalMemoryHandleType::OpaqueWin32,
+        }
+    }
+
+    /// Type of handle
+    pub fn handle_type(&self) -> ExternalMemoryHandleType {
+        self.handle_type
+    }
+
+    /// Borrow the file descriptor, e.g. to duplicate it
+    #[cfg(unix)]
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+
+    /// Give up ownership of the file descriptor, e.g. to hand it over to an
+    /// OpenGL or CUDA import function which takes ownership of it
+    #[cfg(unix)]
+    pub fn into_raw_fd(self) -> RawFd {
+        self.file.into_raw_fd()
+    }
+
+    /// Borrow the Windows HANDLE, e.g. to pass it to an import function
+    /// (Win32 handle imports never take ownership of the handle)
+    #[cfg(windows)]
+    pub fn as_raw_handle(&self) -> RawHandle {
+        self.file.as_raw_handle()
+    }
+}
+
+
+/// Device memory which can be shared with other APIs
+///
+/// The memory is freed on drop. Other APIs which imported it keep their own
+/// reference to it, so it is fine to drop this while they still use it.
+///
+pub struct EasyExternalMemory {
+    /// Device which the memory belongs to
+    device: Arc<Device>,
+
+    /// Raw memory handle
+    memory: vk::DeviceMemory,
+
+    /// Size of the allocation, in bytes
+    size: u64,
+
+    /// Memory type of the allocation
+    memory_type: u32,
+
+    /// Handle type which the memory can be shared through
+    handle_type: ExternalMemoryHandleType,
+}
+
+impl EasyExternalMemory {
+    /// Allocate device-local memory which can be exported
+    ///
+    /// The memory type is picked among the device-local types which are
+    /// allowed by memory_type_bits, usually taken from the memory requirements
+    /// of the buffer or image that the memory will be bound to.
+    ///
+    pub fn allocate(device: &EasyDevice,
+                    size: u64,
+                    memory_type_bits: u32,
+                    handle_type: ExternalMemoryHandleType) -> Result<Self> {
+        let _trace = trace::span("EasyExternalMemory::allocate", || {
+            format!("size: {}, memory_type_bits: 0x{:x}, handle_type: {:?}",
+                    size, memory_type_bits, handle_type)
+        });
+        check_enabled(device, handle_type)?;
+        let memory_type = device_local_type(device.device(),
+                                            memory_type_bits)?;
+        let export_info = ExportMemoryAllocateInfo {
+            s_type: STRUCTURE_TYPE_EXPORT_MEMORY_ALLOCATE_INFO,
+            p_next: ptr::null(),
+            handle_types: handle_type.bits(),
+        };
+        let memory = unsafe {
+            allocate(device.device(),
+                     size,
+                     memory_type,
+                     &export_info as *const _ as *const c_void)?
+        };
+        Ok(EasyExternalMemory {
+            device: device.device().clone(),
+            memory,
+            size,
+            memory_type,
+            handle_type,
+        })
+    }
+
+    /// Import memory which was exported by another API
+    ///
+    /// The size and memory type must match those of the exported memory, as
+    /// reported by the exporting API. On Unix, Vulkan takes ownership of the
+    /// file descriptor if the import succeeds.
+    ///
+    pub fn import(device: &EasyDevice,
+                  handle: ExternalMemoryHandle,
+                  size: u64,
+                  memory_type: u32) -> Result<Self> {
+        let handle_type = handle.handle_type();
+        let _trace = trace::span("EasyExternalMemory::import", || {
+            format!("size: {}, memory_type: {}, handle_type: {:?}",
+                    size, memory_type, handle_type)
+        });
+        check_enabled(device, handle_type)?;
+        let memory = unsafe { import(device.device(), handle, size,
+                                     memory_type)? };
+        Ok(EasyExternalMemory {
+            device: device.device().clone(),
+            memory,
+            size,
+            memory_type,
+            handle_type,
+        })
+    }
+
+    /// Export a new OS handle to this memory
+    ///
+    /// Every call produces a new handle, which the caller owns.
+    ///
+    pub fn export(&self) -> Result<ExternalMemoryHandle> {
+        let _trace = trace::span("EasyExternalMemory::export", String::new);
+        unsafe { export(&self.device, self.memory, self.handle_type) }
+    }
+
+    /// Raw Vulkan memory handle, for binding buffers and images
+    pub fn internal_object(&self) -> vk::DeviceMemory {
+        self.memory
+    }
+
+    /// Size of the allocation, in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Memory type of the allocation
+    pub fn memory_type(&self) -> u32 {
+        self.memory_type
+    }
+
+    /// Handle type which the memory can be shared through
+    pub fn handle_type(&self) -> ExternalMemoryHandleType {
+        self.handle_type
+    }
+}
+
+impl Drop for EasyExternalMemory {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.pointers().FreeMemory(self.device.internal_object(),
+                                              self.memory,
+                                              ptr::null());
+        }
+    }
+}
+
+
+/// Buffer which is bound to memory that can be shared with other APIs
+///
+/// The buffer is bound to the whole allocation, at offset 0.
+///
+pub struct EasyExternalBuffer {
+    /// Raw buffer handle
+    buffer: vk::Buffer,
+
+    /// Size of the buffer, in bytes
+    size: u64,
+
+    /// Memory which the buffer is bound to, freed after the buffer is
+    /// destroyed
+    memory: EasyExternalMemory,
+}
+
+impl EasyExternalBuffer {
+    /// Create a buffer, bound to freshly allocated memory which can be
+    /// exported
+    ///
+    /// The usage is a raw VkBufferUsageFlags bitmask.
+    ///
+    pub fn new(device: &EasyDevice,
+               size: u64,
+               usage: vk::BufferUsageFlags,
+               handle_type: ExternalMemoryHandleType) -> Result<Self> {
+        let _trace = trace::span("EasyExternalBuffer::new", || {
+            format!("size: {}, usage: 0x{:x}, handle_type: {:?}",
+                    size, usage, handle_type)
+        });
+        check_enabled(device, handle_type)?;
+        let buffer = unsafe {
+            create_buffer(device.device(), size, usage, handle_type)?
+        };
+        let memory = unsafe {
+            bind_buffer_memory(device.device(), buffer, |requirements| {
+                EasyExternalMemory::allocate(device,
+                                             requirements.size,
+                                             requirements.memoryTypeBits,
+                                             handle_type)
+            })?
+        };
+        Ok(EasyExternalBuffer { buffer, size, memory })
+    }
+
+    /// Create a buffer, bound to memory which was exported by another API
+    ///
+    /// The buffer must be described in the same way as the exported one, and
+    /// allocation_size must be the size of the exported memory allocation,
+    /// as reported by the exporting API. On Unix, Vulkan takes ownership of
+    /// the file descriptor if the import succeeds.
+    ///
+    pub fn import(device: &EasyDevice,
+                  handle: ExternalMemoryHandle,
+                  allocation_size: u64,
+                  size: u64,
+                  usage: vk::BufferUsageFlags) -> Result<Self> {
+        let handle_type = handle.handle_type();
+        let _trace = trace::span("EasyExternalBuffer::import", || {
+            format!("allocation_size: {}, size: {}, usage: 0x{:x}, \
+                     handle_type: {:?}",
+                    allocation_size, size, usage, handle_type)
+        });
+        check_enabled(device, handle_type)?;
+        let buffer = unsafe {
+            create_buffer(device.device(), size, usage, handle_type)?
+        };
+        let memory = unsafe {
+            bind_buffer_memory(device.device(), buffer, |requirements| {
+                import_for(device, handle, allocation_size, requirements)
+            })?
+        };
+        Ok(EasyExternalBuffer { buffer, size, memory })
+    }
+
+    /// Export a new OS handle to the buffer's memory
+    ///
+    /// Every call produces a new handle, which the caller owns.
+    ///
+    pub fn export(&self) -> Result<ExternalMemoryHandle> {
+        self.memory.export()
+    }
+
+    /// Raw Vulkan buffer handle
+    pub fn internal_object(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// Size of the buffer, in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Memory which the buffer is bound to
+    pub fn memory(&self) -> &EasyExternalMemory {
+        &self.memory
+    }
+}
+
+impl Drop for EasyExternalBuffer {
+    fn drop(&mut self) {
+        let device = &self.memory.device;
+        unsafe {
+            device.pointers().DestroyBuffer(device.internal_object(),
+                                            self.buffer,
+                                            ptr::null());
+        }
+    }
+}
+
+
+/// 2D image which is bound to memory that can be shared with other APIs
+///
+/// The image has a single mip level and array layer, optimal tiling, and is
+/// bound to the whole allocation, at offset 0. It starts in the undefined
+/// layout.
+///
+pub struct EasyExternalImage {
+    /// Raw image handle
+    image: vk::Image,
+
+    /// Dimensions of the image
+    dimensions: [u32; 2],
+
+    /// Format of the image
+    format: Format,
+
+    /// Memory which the image is bound to, freed after the image is
+    /// destroyed
+    memory: EasyExternalMemory,
+}
+
+impl EasyExternalImage {
+    /// Create an image, bound to freshly allocated memory which can be
+    /// exported
+    ///
+    /// The usage is a raw VkImageUsageFlags bitmask.
+    ///
+    pub fn new(device: &EasyDevice,
+               dimensions: [u32; 2],
+               format: Format,
+               usage: vk::ImageUsageFlags,
+               handle_type: ExternalMemoryHandleType) -> Result<Self> {
+        let _trace = trace::span("EasyExternalImage::new", || {
+            format!("dimensions: {:?}, format: {:?}, usage: 0x{:x}, \
+                     handle_type: {:?}",
+                    dimensions, format, usage, handle_type)
+        });
+        check_enabled(device, handle_type)?;
+        let image = unsafe {
+            create_image(device.device(), dimensions, format, usage,
+                         handle_type)?
+        };
+        let memory = unsafe {
+            bind_image_memory(device.device(), image, |requirements| {
+                EasyExternalMemory::allocate(device,
+                                             requirements.size,
+                                             requirements.memoryTypeBits,
+                                             handle_type)
+            })?
+        };
+        Ok(EasyExternalImage { image, dimensions, format, memory })
+    }
+
+    /// Create an image, bound to memory which was exported by another API
+    ///
+    /// The image must be described in the same way as the exported one, and
+    /// allocation_size must be the size of the exported memory allocation,
+    /// as reported by the exporting API. On Unix, Vulkan takes ownership of
+    /// the file descriptor if the import succeeds.
+    ///
+    pub fn import(device: &EasyDevice,
+                  handle: ExternalMemoryHandle,
+                  allocation_size: u64,
+                  dimensions: [u32; 2],
+                  format: Format,
+                  usage: vk::ImageUsageFlags) -> Result<Self> {
+        let handle_type = handle.handle_type();
+        let _trace = trace::span("EasyExternalImage::import", || {
+            format!("allocation_size: {}, dimensions: {:?}, format: {:?}, \
+                     usage: 0x{:x}, handle_type: {:?}",
+                    allocation_size, dimensions, format, usage, handle_type)
+        });
+        check_enabled(device, handle_type)?;
+        let image = unsafe {
+            create_image(device.device(), dimensions, format, usage,
+                         handle_type)?
+        };
+        let memory = unsafe {
+            bind_image_memory(device.device(), image, |requirements| {
+                import_for(device, handle, allocation_size, requirements)
+            })?
+        };
+        Ok(EasyExternalImage { image, dimensions, format, memory })
+    }
+
+    /// Export a new OS handle to the image's memory
+    ///
+    /// Every call produces a new handle, which the caller owns.
+    ///
+    pub fn export(&self) -> Result<ExternalMemoryHandle> {
+        self.memory.export()
+    }
+
+    /// Raw Vulkan image handle
+    pub fn internal_object(&self) -> vk::Image {
+        self.image
+    }
+
+    /// Dimensions of the image
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// Format of the image
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Memory which the image is bound to
+    pub fn memory(&self) -> &EasyExternalMemory {
+        &self.memory
+    }
+}
+
+impl Drop for EasyExternalImage {
+    fn drop(&mut self) {
+        let device = &self.memory.device;
+        unsafe {
+            device.pointers().DestroyImage(device.internal_object(),
+                                           self.image,
+                                           ptr::null());
+        }
+    }
+}
+
+
+/// VkExternalMemoryBufferCreateInfo, to be chained to the VkBufferCreateInfo
+/// of buffers which will be bound to external memory
+#[derive(Debug)]
+#[repr(C)]
+pub struct ExternalMemoryBufferCreateInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    handle_types: u32,
+}
+
+impl ExternalMemoryBufferCreateInfo {
+    /// Describe a buffer which will be bound to some kind of external memory
+    pub fn new(handle_type: ExternalMemoryHandleType) -> Self {
+        ExternalMemoryBufferCreateInfo {
+            s_type: STRUCTURE_TYPE_EXTERNAL_MEMORY_BUFFER_CREATE_INFO,
+            p_next: ptr::null(),
+            handle_types: handle_type.bits(),
+        }
+    }
+}
+
+/// VkExternalMemoryImageCreateInfo, to be chained to the VkImageCreateInfo
+/// of images which will be bound to external memory
+#[derive(Debug)]
+#[repr(C)]
+pub struct ExternalMemoryImageCreateInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    handle_types: u32,
+}
+
+impl ExternalMemoryImageCreateInfo {
+    /// Describe an image which will be bound to some kind of external memory
+    pub fn new(handle_type: ExternalMemoryHandleType) -> Self {
+        ExternalMemoryImageCreateInfo {
+            s_type: STRUCTURE_TYPE_EXTERNAL_MEMORY_IMAGE_CREATE_INFO,
+            p_next: ptr::null(),
+            handle_types: handle_type.bits(),
+        }
+    }
+}
+
+
+/// VkExportMemoryAllocateInfo
+#[repr(C)]
+struct ExportMemoryAllocateInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    handle_types: u32,
+}
+
+/// VkImportMemoryFdInfoKHR
+#[cfg(unix)]
+#[repr(C)]
+struct ImportMemoryFdInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    handle_type: u32,
+    fd: c_int,
+}
+
+/// VkMemoryGetFdInfoKHR
+#[cfg(unix)]
+#[repr(C)]
+struct MemoryGetFdInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    memory: vk::DeviceMemory,
+    handle_type: u32,
+}
+
+/// vkGetMemoryFdKHR
+#[cfg(unix)]
+type GetMemoryFd = unsafe extern "system" fn(vk::Device,
+                                             *const MemoryGetFdInfo,
+                                             *mut c_int) -> vk::Result;
+
+/// VkImportMemoryWin32HandleInfoKHR
+#[cfg(windows)]
+#[repr(C)]
+struct ImportMemoryWin32HandleInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    handle_type: u32,
+    handle: *mut c_void,
+    name: *const u16,
+}
+
+/// VkMemoryGetWin32HandleInfoKHR
+#[cfg(windows)]
+#[repr(C)]
+struct MemoryGetWin32HandleInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    memory: vk::DeviceMemory,
+    handle_type: u32,
+}
+
+/// vkGetMemoryWin32HandleKHR
+#[cfg(windows)]
+type GetMemoryWin32Handle =
+    unsafe extern "system" fn(vk::Device,
+                              *const MemoryGetWin32HandleInfo,
+                              *mut *mut c_void) -> vk::Result;
+
+
+/// Check that a device was set up for some handle type, which also
+/// guarantees that the corresponding entry points can be loaded
+fn check_enabled(device: &EasyDevice,
+                 handle_type: ExternalMemoryHandleType) -> Result<()> {
+    handle_type.check_platform()?;
+    let missing = handle_type.extensions()
+                             .iter()
+                             .filter(|name| !device.is_extension_enabled(name))
+                             .map(|name| name.to_string())
+                             .collect::<Vec<_>>();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument(format!(
+            "Extension(s) {:?} were not enabled on the device, use \
+             EasyPhysicalDevice::setup_interop_device()", missing
+        )))
+    }
+}
+
+/// Pick the first device-local memory type among some allowed types
+fn device_local_type(device: &Arc<Device>,
+                     memory_type_bits: u32) -> Result<u32> {
+    device.physical_device()
+          .memory_types()
+          .find(|ty| memory_type_bits & (1 << ty.id()) != 0
+                     && ty.is_device_local())
+          .map(|ty| ty.id())
+          .ok_or_else(|| Error::InvalidArgument(format!(
+              "No device-local memory type in bits 0x{:x}", memory_type_bits
+          )))
+}
+
+/// Create a buffer which can be bound to some kind of external memory
+unsafe fn create_buffer(device: &Arc<Device>,
+                        size: u64,
+                        usage: vk::BufferUsageFlags,
+                        handle_type: ExternalMemoryHandleType)
+    -> Result<vk::Buffer>
+{
+    let external_info = ExternalMemoryBufferCreateInfo::new(handle_type);
+    let buffer_info = vk::BufferCreateInfo {
+        sType: vk::STRUCTURE_TYPE_BUFFER_CREATE_INFO,
+        pNext: &external_info as *const _ as *const c_void,
+        flags: 0,
+        size,
+        usage,
+        sharingMode: vk::SHARING_MODE_EXCLUSIVE,
+        queueFamilyIndexCount: 0,
+        pQueueFamilyIndices: ptr::null(),
+    };
+    let mut buffer = 0;
+    check_result(device.pointers().CreateBuffer(device.internal_object(),
+                                                &buffer_info,
+                                                ptr::null(),
+                                                &mut buffer),
+                 "vkCreateBuffer")?;
+    Ok(buffer)
+}
+
+/// Create a 2D image which can be bound to some kind of external memory
+unsafe fn create_image(device: &Arc<Device>,
+                       dimensions: [u32; 2],
+                       format: Format,
+                       usage: vk::ImageUsageFlags,
+                       handle_type: ExternalMemoryHandleType)
+    -> Result<vk::Image>
+{
+    let external_info = ExternalMemoryImageCreateInfo::new(handle_type);
+    let image_info = vk::ImageCreateInfo {
+        sType: vk::STRUCTURE_TYPE_IMAGE_CREATE_INFO,
+        pNext: &external_info as *const _ as *const c_void,
+        flags: 0,
+        imageType: vk::IMAGE_TYPE_2D,
+        format: format as vk::Format,
+        extent: vk::Extent3D {
+            width: dimensions[0],
+            height: dimensions[1],
+            depth: 1,
+        },
+        mipLevels: 1,
+        arrayLayers: 1,
+        samples: vk::SAMPLE_COUNT_1_BIT,
+        tiling: vk::IMAGE_TILING_OPTIMAL,
+        usage,
+        sharingMode: vk::SHARING_MODE_EXCLUSIVE,
+        queueFamilyIndexCount: 0,
+        pQueueFamilyIndices: ptr::null(),
+        initialLayout: vk::IMAGE_LAYOUT_UNDEFINED,
+    };
+    let mut image = 0;
+    check_result(device.pointers().CreateImage(device.internal_object(),
+                                               &image_info,
+                                               ptr::null(),
+                                               &mut image),
+                 "vkCreateImage")?;
+    Ok(image)
+}
+
+/// Get some memory for a freshly created buffer and bind it, or destroy the
+/// buffer if that fails
+unsafe fn bind_buffer_memory(
+    device: &Arc<Device>,
+    buffer: vk::Buffer,
+    get_memory: impl FnOnce(&vk::MemoryRequirements)
+                            -> Result<EasyExternalMemory>
+) -> Result<EasyExternalMemory> {
+    let fns = device.pointers();
+    let raw_device = device.internal_object();
+    let result = (|| -> Result<EasyExternalMemory> {
+        let mut requirements = mem::zeroed::<vk::MemoryRequirements>();
+        fns.GetBufferMemoryRequirements(raw_device, buffer, &mut requirements);
+        let memory = get_memory(&requirements)?;
+        check_result(fns.BindBufferMemory(raw_device, buffer, memory.memory, 0),
+                     "vkBindBufferMemory")?;
+        Ok(memory)
+    })();
+    if result.is_err() {
+        fns.DestroyBuffer(raw_device, buffer, ptr::null());
+    }
+    result
+}
+
+/// Get some memory for a freshly created image and bind it, or destroy the
+/// image if that fails
+unsafe fn bind_image_memory(
+    device: &Arc<Device>,
+    image: vk::Image,
+    get_memory: impl FnOnce(&vk::MemoryRequirements)
+                            -> Result<EasyExternalMemory>
+) -> Result<EasyExternalMemory> {
+    let fns = device.pointers();
+    let raw_device = device.internal_object();
+    let result = (|| -> Result<EasyExternalMemory> {
+        let mut requirements = mem::zeroed::<vk::MemoryRequirements>();
+        fns.GetImageMemoryRequirements(raw_device, image, &mut requirements);
+        let memory = get_memory(&requirements)?;
+        check_result(fns.BindImageMemory(raw_device, image, memory.memory, 0),
+                     "vkBindImageMemory")?;
+        Ok(memory)
+    })();
+    if result.is_err() {
+        fns.DestroyImage(raw_device, image, ptr::null());
+    }
+    result
+}
+
+/// Import memory for a buffer or image with some memory requirements
+fn import_for(device: &EasyDevice,
+              handle: ExternalMemoryHandle,
+              allocation_size: u64,
+              requirements: &vk::MemoryRequirements)
+    -> Result<EasyExternalMemory>
+{
+    if allocation_size < requirements.size {
+        return Err(Error::InvalidArgument(format!(
+            "Imported allocation ({} bytes) is smaller than the {} bytes \
+             which the resource needs", allocation_size, requirements.size
+        )));
+    }
+    let memory_type = device_local_type(device.device(),
+                                        requirements.memoryTypeBits)?;
+    EasyExternalMemory::import(device, handle, allocation_size, memory_type)
+}
+
+/// Allocate device memory, with some extension structs chained
+unsafe fn allocate(device: &Arc<Device>,
+                   size: u64,
+                   memory_type: u32,
+                   p_next: *const c_void) -> Result<vk::DeviceMemory> {
+    let alloc_info = vk::MemoryAllocateInfo {
+        sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
+        pNext: p_next,
+        allocationSize: size,
+        memoryTypeIndex: memory_type,
+    };
+    let mut memory = 0;
+    check_result(device.pointers().AllocateMemory(device.internal_object(),
+                                                  &alloc_info,
+                                                  ptr::null(),
+                                                  &mut memory),
+                 "vkAllocateMemory")?;
+    Ok(memory)
+}
+
+/// Import memory from a file descriptor
+#[cfg(unix)]
+unsafe fn import(device: &Arc<Device>,
+                 handle: ExternalMemoryHandle,
+                 size: u64,
+                 memory_type: u32) -> Result<vk::DeviceMemory> {
+    let import_info = ImportMemoryFdInfo {
+        s_type: STRUCTURE_TYPE_IMPORT_MEMORY_FD_INFO,
+        p_next: ptr::null(),
+        handle_type: handle.handle_type().bits(),
+        fd: handle.as_raw_fd(),
+    };
+    let memory = allocate(device, size, memory_type,
+                          &import_info as *const _ as *const c_void)?;
+
+    // The Vulkan implementation now owns the file descriptor
+    let _ = handle.into_raw_fd();
+    Ok(memory)
+}
+
+/// Import memory from a Windows HANDLE
+#[cfg(windows)]
+unsafe fn import(device: &Arc<Device>,
+                 handle: ExternalMemoryHandle,
+                 size: u64,
+                 memory_type: u32) -> Result<vk::DeviceMemory> {
+    // Win32 imports do not take ownership, so the handle is closed on drop
+    let import_info = ImportMemoryWin32HandleInfo {
+        s_type: STRUCTURE_TYPE_IMPORT_MEMORY_WIN32_HANDLE_INFO,
+        p_next: ptr::null(),
+        handle_type: handle.handle_type().bits(),
+        handle: handle.as_raw_handle() as *mut c_void,
+        name: ptr::null(),
+    };
+    allocate(device, size, memory_type,
+             &import_info as *const _ as *const c_void)
+}
+
+/// Export memory as a file descriptor
+#[cfg(unix)]
+unsafe fn export(device: &Arc<Device>,
+                 memory: vk::DeviceMemory,
+                 handle_type: ExternalMemoryHandleType)
+    -> Result<ExternalMemoryHandle>
+{
+    let get_memory_fd: GetMemoryFd =
+        mem::transmute(loader::device_function(device, "vkGetMemoryFdKHR")?);
+    let get_info = MemoryGetFdInfo {
+        s_type: STRUCTURE_TYPE_MEMORY_GET_FD_INFO,
+        p_next: ptr::null(),
+        memory,
+        handle_type: handle_type.bits(),
+    };
+    let mut fd = -1;
+    check_result(get_memory_fd(device.internal_object(), &get_info, &mut fd),
+                 "vkGetMemoryFdKHR")?;
+    Ok(ExternalMemoryHandle::from_raw_fd(fd))
+}
+
+/// Export memory as a Windows HANDLE
+#[cfg(windows)]
+unsafe fn export(device: &Arc<Device>,
+                 memory: vk::DeviceMemory,
+                 handle_type: ExternalMemoryHandleType)
+    -> Result<ExternalMemoryHandle>
+{
+    let get_memory_handle: GetMemoryWin32Handle =
+        mem::transmute(loader::device_function(device,
+                                               "vkGetMemoryWin32HandleKHR")?);
+    let get_info = MemoryGetWin32HandleInfo {
+        s_type: STRUCTURE_TYPE_MEMORY_GET_WIN32_HANDLE_INFO,
+        p_next: ptr::null(),
+        memory,
+        handle_type: handle_type.bits(),
+    };
+    let mut handle = ptr::null_mut();
+    check_result(get_memory_handle(device.internal_object(),
+                                   &get_info,
+                                   &mut handle),
+                 "vkGetMemoryWin32HandleKHR")?;
+    Ok(ExternalMemoryHandle::from_raw_handle(handle as RawHandle))
+}
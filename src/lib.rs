@@ -2,16 +2,107 @@
 //!
 //! This library provides shortcuts to ease usage of the vulkano library in
 //! quick application prototypes. It should not be used in production code.
+//!
+//! vulkanoob logs a lot, and uses dedicated logging targets so that you can
+//! filter its various log streams independently:
+//!
+//! - "vulkanoob::debug_report" for Vulkan debug messages (debug::TARGET)
+//! - "vulkanoob::enumeration" for the description of the Vulkan implementation
+//!   and physical devices (instance::ENUMERATION_TARGET)
+//! - "vulkanoob::trace" for trace records (trace::TARGET)
+//!
+//! Everything else uses the default, module-based logging targets.
 
 #[macro_use] extern crate failure;
 #[macro_use] extern crate log;
 
-extern crate vulkano;
+extern crate vk_sys;
+#[macro_use] extern crate vulkano;
+
+#[cfg(any(feature = "image-loading", feature = "png-export"))]
+extern crate image as image_crate;
+#[cfg(feature = "gui")] #[macro_use] extern crate imgui;
+#[cfg(feature = "hot-reload")] extern crate notify;
+#[cfg(feature = "parallel")] extern crate rayon;
+#[cfg(feature = "renderdoc")] extern crate renderdoc as renderdoc_crate;
+#[cfg(feature = "reflection")] extern crate rspirv;
+#[cfg(feature = "serde-export")] extern crate serde;
+#[cfg(feature = "serde-export")] #[macro_use] extern crate serde_derive;
+#[cfg(feature = "serde-export")] extern crate serde_json;
+#[cfg(feature = "glsl")] extern crate shaderc;
+#[cfg(feature = "reflection")] extern crate spirv_headers;
+#[cfg(feature = "toml-export")] extern crate toml;
+#[cfg(feature = "tracing")] extern crate tracing;
+#[cfg(feature = "window")] extern crate vulkano_win;
+#[cfg(feature = "window")] extern crate winit;
 
 pub mod instance;
+pub mod api_level;
+pub mod arena;
+pub mod asynchronous;
+pub mod barrier;
+pub mod bindless;
+pub mod budget;
+pub mod buffer;
+pub mod command;
+pub mod compute;
+pub mod config;
+pub mod debug;
+pub mod descriptor;
 pub mod device;
-
-use std::result;
+pub mod diagnosis;
+pub mod display;
+pub mod dynamic_rendering;
+pub mod error;
+#[cfg(feature = "glsl")] pub mod glsl;
+pub mod graphics_pipeline;
+#[cfg(feature = "gui")] pub mod gui;
+pub mod hotplug;
+pub mod image;
+pub mod indirect;
+pub mod interop;
+pub mod limits;
+pub mod loader;
+pub mod memory;
+pub mod mesh_shader;
+pub mod pipeline;
+pub mod portability;
+pub mod preference;
+pub mod profile;
+#[cfg(feature = "profile-db")] pub mod profile_db;
+pub mod profiling;
+pub mod properties2;
+pub mod push_constants;
+#[cfg(feature = "raytracing")] pub mod raytracing;
+pub mod recovery;
+#[cfg(feature = "reflection")] pub mod reflection;
+pub mod render;
+#[cfg(feature = "render-graph")] pub mod render_graph;
+pub mod render_loop;
+pub mod render_targets;
+#[cfg(feature = "renderdoc")] pub mod renderdoc;
+pub mod report;
+pub mod sampler;
+pub mod selection_cache;
+pub mod self_test;
+pub mod shader;
+pub mod software;
+pub mod sparse;
+pub mod specialization;
+pub mod staging;
+pub mod stats;
+pub mod subgroup;
+pub mod submission;
+pub mod surface;
+pub mod swapchain;
+pub mod sync;
+pub mod testing;
+pub mod trace;
+pub mod transfer;
+pub mod uniform_ring;
+pub mod vertex;
+pub mod video;
+#[cfg(feature = "window")] pub mod window;
 
 use vulkano::{
     device::DeviceExtensions,
@@ -19,13 +110,16 @@ use vulkano::{
         Features,
         PhysicalDevice,
         QueueFamily,
-        Version,
     }
 };
 
 
-/// We use failure's type-erased error handling
-pub type Result<T> = result::Result<T, failure::Error>;
+pub use diagnosis::easy_device_diagnosis;
+#[cfg(feature = "glsl")] pub use glsl::easy_compile_glsl;
+pub use error::{
+    Error,
+    Result,
+};
 
 
 /// Helper for building vulkanoob device filters
@@ -33,39 +127,19 @@ pub type Result<T> = result::Result<T, failure::Error>;
 /// Features all the basic device selection criteria which you will almost
 /// always want to specify when using vulkanoob.
 ///
+/// If you want to know why devices are rejected, see easy_device_diagnosis().
+///
 pub fn easy_device_filter<'a>(
     features: &'a Features,
     extensions: &'a DeviceExtensions,
-    mut queue_filter: impl FnMut(&QueueFamily) -> bool + 'a,
-    mut other_criteria: impl FnMut(PhysicalDevice) -> bool + 'a
+    queue_filter: impl FnMut(&QueueFamily) -> bool + 'a,
+    other_criteria: impl FnMut(PhysicalDevice) -> bool + 'a
 ) -> impl FnMut(PhysicalDevice) -> bool + 'a {
+    let mut diagnose = easy_device_diagnosis(features,
+                                             extensions,
+                                             queue_filter,
+                                             other_criteria);
     move |dev: PhysicalDevice| -> bool {
-        // This library was written against Vulkan v1.0.76. We tolerate older
-        // patch releases and new minor versions but not new major versions.
-        let min_ver = Version { major: 1, minor: 0, patch: 0 };
-        let max_ver = Version { major: 2, minor: 0, patch: 0 };
-        if (dev.api_version() < min_ver) || (dev.api_version() >= max_ver) {
-            return false;
-        }
-
-        // Some features may be requested by the user, we need to look at them
-        if !dev.supported_features().superset_of(features) {
-            return false;
-        }
-
-        // Same goes for device extensions
-        let unsupported_exts =
-            extensions.difference(&DeviceExtensions::supported_by_device(dev));
-        if unsupported_exts != DeviceExtensions::none() {
-            return false;
-        }
-
-        // At least one device queue family should fit our needs
-        if dev.queue_families().find(&mut queue_filter).is_none() {
-            return false;
-        }
-
-        // Test extra user filtering criteria
-        other_criteria(dev)
+        diagnose(dev).is_empty()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,246 @@
+//! Minimal device limit requirements
+//!
+//! Device filters are good at checking features and extensions, but checking
+//! device limits requires writing closures by hand, and those rarely tell
+//! which limit was the problem. LimitRequirements lists the minimal values
+//! that an application needs for the limits where larger is better, and tells
+//! which ones a device violates.
+//!
+//! ```ignore
+//! let requirements = LimitRequirements {
+//!     max_push_constants_size: 128,
+//!     max_compute_shared_memory_size: 32768,
+//!     .. LimitRequirements::default()
+//! };
+//! requirements.assert_satisfied_by(physical_device)?;
+//! ```
+
+use ::{
+    profile::CapabilityProfile,
+    Error,
+    Result,
+};
+
+use vulkano::instance::PhysicalDevice;
+
+
+/// Generate LimitRequirements and its checking code from a list of limits
+macro_rules! limit_requirements {
+    ($($(#[$doc:meta])* $name:ident: $ty:ty,)*) => {
+        /// Minimal values of the device limits that an application needs
+        ///
+        /// The default value of every limit (zero) means that the application
+        /// has no requirement on that limit. Array-valued limits are checked
+        /// componentwise.
+        ///
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct LimitRequirements {
+            $(
+                $(#[$doc])*
+                pub $name: $ty,
+            )*
+        }
+
+        impl LimitRequirements {
+            /// List the limits of a device which violate the requirements
+            ///
+            /// Each violation is described in a human-readable way, and the
+            /// list is empty if the device satisfies the requirements.
+            ///
+            pub fn violations(&self, device: PhysicalDevice) -> Vec<String> {
+                let limits = device.limits();
+                let mut violations = Vec::new();
+                $(
+                    let actual = limits.$name();
+                    if !LimitValue::satisfies(&actual, &self.$name) {
+                        violations.push(format!(
+                            "{} is {:?}, but {:?} is required",
+                            stringify!($name), actual, self.$name
+                        ));
+                    }
+                )*
+                violations
+            }
+
+            /// List the limits of a capability profile which violate the
+            /// requirements
+            ///
+            /// Limits which the profile does not specify are assumed to be
+            /// fine, since profiles are often incomplete.
+            ///
+            pub fn profile_violations(
+                &self,
+                profile: &CapabilityProfile
+            ) -> Vec<String> {
+                let mut violations = Vec::new();
+                $(
+                    let actual = profile.limit(stringify!($name))
+                                        .and_then(LimitValue::parse);
+                    if let Some(actual) = actual {
+                        if !LimitValue::satisfies(&actual, &self.$name) {
+                            violations.push(format!(
+                                "{} is {:?}, but {:?} is required",
+                                stringify!($name), actual, self.$name
+                            ));
+                        }
+                    }
+                )*
+                violations
+            }
+        }
+    };
+}
+
+limit_requirements! {
+    /// Maximal 2D image width and height
+    max_image_dimension_2d: u32,
+
+    /// Maximal number of image array layers
+    max_image_array_layers: u32,
+
+    /// Maximal uniform buffer binding size, in bytes
+    max_uniform_buffer_range: u32,
+
+    /// Maximal storage buffer binding size, in bytes
+    max_storage_buffer_range: u32,
+
+    /// Maximal push constant block size, in bytes
+    max_push_constants_size: u32,
+
+    /// Maximal number of live device memory allocations
+    max_memory_allocation_count: u32,
+
+    /// Maximal number of simultaneously bound descriptor sets
+    max_bound_descriptor_sets: u32,
+
+    /// Maximal number of samplers accessible by a shader stage
+    max_per_stage_descriptor_samplers: u32,
+
+    /// Maximal number of uniform buffers accessible by a shader stage
+    max_per_stage_descriptor_uniform_buffers: u32,
+
+    /// Maximal number of storage buffers accessible by a shader stage
+    max_per_stage_descriptor_storage_buffers: u32,
+
+    /// Maximal number of sampled images accessible by a shader stage
+    max_per_stage_descriptor_sampled_images: u32,
+
+    /// Maximal number of storage images accessible by a shader stage
+    max_per_stage_descriptor_storage_images: u32,
+
+    /// Maximal number of vertex input attributes
+    max_vertex_input_attributes: u32,
+
+    /// Maximal number of vertex input bindings
+    max_vertex_input_bindings: u32,
+
+    /// Maximal number of color attachments in a subpass
+    max_color_attachments: u32,
+
+    /// Maximal compute shader shared memory size, in bytes
+    max_compute_shared_memory_size: u32,
+
+    /// Maximal number of work groups in a dispatch, along each dimension
+    max_compute_work_group_count: [u32; 3],
+
+    /// Maximal number of invocations in a compute work group
+    max_compute_work_group_invocations: u32,
+
+    /// Maximal compute work group size, along each dimension
+    max_compute_work_group_size: [u32; 3],
+
+    /// Maximal number of draws in an indirect draw command
+    max_draw_indirect_count: u32,
+
+    /// Maximal sampler anisotropy
+    max_sampler_anisotropy: f32,
+
+    /// Maximal number of viewports
+    max_viewports: u32,
+
+    /// Maximal framebuffer width
+    max_framebuffer_width: u32,
+
+    /// Maximal framebuffer height
+    max_framebuffer_height: u32,
+}
+
+impl LimitRequirements {
+    /// Truth that a device satisfies the requirements
+    ///
+    /// Use this in device filters. If you want to know why a device is
+    /// rejected, see violations() and assert_satisfied_by().
+    ///
+    pub fn is_satisfied_by(&self, device: PhysicalDevice) -> bool {
+        self.violations(device).is_empty()
+    }
+
+    /// Check that a device satisfies the requirements, or return an error
+    /// which lists every violated limit
+    pub fn assert_satisfied_by(&self, device: PhysicalDevice) -> Result<()> {
+        let violations = self.violations(device);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InsufficientLimits {
+                device: device.name(),
+                violations,
+            })
+        }
+    }
+}
+
+/// Check that a device satisfies some limit requirements
+///
+/// This is a shorthand for LimitRequirements::assert_satisfied_by().
+///
+pub fn assert_limits(device: PhysicalDevice,
+                     requirements: &LimitRequirements) -> Result<()> {
+    requirements.assert_satisfied_by(device)
+}
+
+
+/// A device limit value, which can be compared to a requirement
+trait LimitValue: Sized {
+    /// Truth that this value is at least as large as the requirement
+    fn satisfies(&self, requirement: &Self) -> bool;
+
+    /// Parse this value from its Debug output, as found in reports
+    fn parse(value: &str) -> Option<Self>;
+}
+
+impl LimitValue for u32 {
+    fn satisfies(&self, requirement: &Self) -> bool {
+        self >= requirement
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        value.trim().parse().ok()
+    }
+}
+
+impl LimitValue for f32 {
+    fn satisfies(&self, requirement: &Self) -> bool {
+        self >= requirement
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        value.trim().parse().ok()
+    }
+}
+
+impl LimitValue for [u32; 3] {
+    fn satisfies(&self, requirement: &Self) -> bool {
+        self.iter().zip(requirement.iter()).all(|(actual, req)| actual >= req)
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().trim_start_matches('[').trim_end_matches(']');
+        let mut parts = value.split(',').map(<u32 as LimitValue>::parse);
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Some(x)), Some(Some(y)), Some(Some(z)), None) =>
+                Some([x, y, z]),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,684 @@
+//! Vulkan loader shim, working around the limitations of vulkano's instance
+//! creation
+//!
+//! vulkano always requests Vulkan v1.0 when creating an instance, and using
+//! newer functionality than the instance's API version is not allowed by the
+//! Vulkan specification. We thus load Vulkan through a thin wrapper around
+//! vulkano's default loader, which intercepts vkCreateInstance in order to
+//! request the newest API version that both the Vulkan loader and the
+//! application support.
+//!
+//! vulkano also only knows about the device features of Vulkan 1.0, whereas
+//! newer functionality (ray tracing, mesh shading, descriptor indexing...)
+//! must be enabled by chaining extension feature structs to the device's
+//! create info. The wrapper thus also intercepts vkCreateDevice, so that the
+//! device setup methods of EasyPhysicalDevice can pass a FeatureChain along.
+//!
+//! Finally, descriptor indexing needs descriptor binding flags to be set when
+//! vulkano creates descriptor set layouts, and a variable descriptor count to
+//! be specified when it allocates descriptor sets, whereas dynamic rendering
+//! needs graphics pipelines to be created without a render pass. The wrapper
+//! hands out its own vkGetDeviceProcAddr for that purpose, which intercepts
+//! vkCreateDescriptorSetLayout, vkAllocateDescriptorSets and
+//! vkCreateGraphicsPipelines.
+
+use ::{
+    api_level::VULKAN_1_0,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    cell::Cell,
+    cmp,
+    ffi::{CStr, CString},
+    mem,
+    os::raw::{c_char, c_void},
+    ptr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::LocalKey,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    device::Device,
+    instance::{
+        loader::{
+            self,
+            FunctionPointers,
+            Loader,
+        },
+        ApplicationInfo,
+        Instance,
+        RawInstanceExtensions,
+        Version,
+    },
+    VulkanObject,
+};
+
+
+/// Signature of vkCreateInstance
+type CreateInstanceFn = extern "system" fn(*const vk::InstanceCreateInfo,
+                                           *const vk::AllocationCallbacks,
+                                           *mut vk::Instance) -> vk::Result;
+
+/// Signature of vkCreateDevice
+type CreateDeviceFn = extern "system" fn(vk::PhysicalDevice,
+                                         *const vk::DeviceCreateInfo,
+                                         *const vk::AllocationCallbacks,
+                                         *mut vk::Device) -> vk::Result;
+
+/// Signature of vkEnumerateInstanceVersion
+type EnumerateInstanceVersionFn = extern "system" fn(*mut u32) -> vk::Result;
+
+/// Signature of vkGetDeviceProcAddr, with a nullable result
+type GetDeviceProcAddrFn = extern "system" fn(vk::Device,
+                                              *const c_char) -> *const c_void;
+
+/// Signature of vkCreateDescriptorSetLayout
+type CreateDescriptorSetLayoutFn =
+    extern "system" fn(vk::Device,
+                       *const vk::DescriptorSetLayoutCreateInfo,
+                       *const vk::AllocationCallbacks,
+                       *mut vk::DescriptorSetLayout) -> vk::Result;
+
+/// Signature of vkAllocateDescriptorSets
+type AllocateDescriptorSetsFn =
+    extern "system" fn(vk::Device,
+                       *const vk::DescriptorSetAllocateInfo,
+                       *mut vk::DescriptorSet) -> vk::Result;
+
+/// Signature of vkCreateGraphicsPipelines
+type CreateGraphicsPipelinesFn =
+    extern "system" fn(vk::Device,
+                       vk::PipelineCache,
+                       u32,
+                       *const vk::GraphicsPipelineCreateInfo,
+                       *const vk::AllocationCallbacks,
+                       *mut vk::Pipeline) -> vk::Result;
+
+/// Structure type of VkDescriptorSetLayoutBindingFlagsCreateInfo
+const STRUCTURE_TYPE_BINDING_FLAGS_CREATE_INFO: u32 = 1_000_161_000;
+
+/// Structure type of VkDescriptorSetVariableDescriptorCountAllocateInfo
+const STRUCTURE_TYPE_VARIABLE_COUNT_ALLOCATE_INFO: u32 = 1_000_161_003;
+
+/// vkCreateInstance implementation of the underlying Vulkan loader
+static REAL_CREATE_INSTANCE: AtomicUsize = AtomicUsize::new(0);
+
+/// vkCreateDevice implementation of the underlying Vulkan loader
+///
+/// The Vulkan loader hands out the same vkCreateDevice trampoline for every
+/// instance, which dispatches on the physical device, so a single global
+/// suffices.
+///
+static REAL_CREATE_DEVICE: AtomicUsize = AtomicUsize::new(0);
+
+/// vkGetDeviceProcAddr implementation of the underlying Vulkan loader
+///
+/// Like vkCreateDevice, this is a trampoline which dispatches on the device.
+/// Device-level functions, on the other hand, are device-specific, so the
+/// intercepted ones look up their real implementation on every call.
+///
+static REAL_GET_DEVICE_PROC_ADDR: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// API version to be requested by instances created on this thread
+    static REQUESTED_VERSION: Cell<Option<u32>> = Cell::new(None);
+
+    /// Extension feature structs to be chained into the create info of
+    /// devices created on this thread
+    static DEVICE_FEATURES: Cell<*mut c_void> = Cell::new(ptr::null_mut());
+
+    /// Binding flags to be applied to descriptor set layouts created on this
+    /// thread
+    static BINDING_FLAGS: Cell<Option<BindingFlags>> = Cell::new(None);
+
+    /// Variable descriptor count of descriptor sets allocated on this thread
+    static VARIABLE_DESCRIPTOR_COUNT: Cell<Option<u32>> = Cell::new(None);
+
+    /// VkPipelineRenderingCreateInfo to be chained into the create info of
+    /// graphics pipelines created on this thread, instead of a render pass
+    static PIPELINE_RENDERING: Cell<*mut c_void> = Cell::new(ptr::null_mut());
+}
+
+/// Restores the previous value of one of the above thread-locals when
+/// dropped, even if the guarded code panicked or returned early
+struct ResetGuard<T: Copy + 'static> {
+    /// Thread-local to be restored
+    key: &'static LocalKey<Cell<T>>,
+
+    /// Value to be restored
+    previous: T,
+}
+
+impl<T: Copy + 'static> Drop for ResetGuard<T> {
+    fn drop(&mut self) {
+        let previous = self.previous;
+        self.key.with(|cell| cell.set(previous));
+    }
+}
+
+/// Set one of the above thread-locals for the duration of a closure
+fn with_thread_local<T: Copy + 'static, R>(key: &'static LocalKey<Cell<T>>,
+                                           value: T,
+                                           f: impl FnOnce() -> R) -> R {
+    let previous = key.with(|cell| cell.replace(value));
+    let _guard = ResetGuard { key, previous };
+    f()
+}
+
+
+/// Vulkan API version supported by the Vulkan loader
+///
+/// Vulkan v1.0 loaders do not provide vkEnumerateInstanceVersion, and only
+/// support Vulkan v1.0 instances.
+///
+pub fn loader_version() -> Result<Version> {
+    let loader = loader::auto_loader()?;
+    let name = b"vkEnumerateInstanceVersion\0".as_ptr() as *const c_char;
+    let function = loader.get_instance_proc_addr(0, name);
+    if function.is_null() { return Ok(VULKAN_1_0); }
+    let mut version = 0;
+    let result = unsafe {
+        let function: EnumerateInstanceVersionFn = mem::transmute(function);
+        function(&mut version)
+    };
+    if result != vk::SUCCESS { return Ok(VULKAN_1_0); }
+    Ok(decode_version(version))
+}
+
+/// Create a Vulkan instance which targets the newest API version supported
+/// by the Vulkan loader, up to some maximum
+///
+/// Returns the instance, along with the API version that it targets.
+///
+pub(crate) fn create_instance<'a>(
+    app_infos: Option<&ApplicationInfo>,
+    extensions: RawInstanceExtensions,
+    layers: impl IntoIterator<Item=&'a str>,
+    max_version: Version,
+) -> Result<(Arc<Instance>, Version)> {
+    let _trace = trace::span("loader::create_instance", || {
+        format!("max_version: {}", max_version)
+    });
+    let version = cmp::min(loader_version()?, max_version);
+    info!("Creating a Vulkan v{}.{} instance", version.major, version.minor);
+    let loader = FunctionPointers::new(
+        Box::new(ShimLoader(loader::auto_loader()?))
+            as Box<dyn Loader + Send + Sync>
+    );
+    let instance = with_thread_local(
+        &REQUESTED_VERSION,
+        Some(encode_version(version)),
+        || Instance::with_loader(loader, app_infos, extensions, layers)
+    )?;
+    Ok((instance, version))
+}
+
+/// Create a device, enabling the extension features of a FeatureChain
+///
+/// The device must be created by the closure, from an instance that was
+/// created using create_instance(), on the calling thread.
+///
+pub(crate) fn with_device_features<R>(chain: &mut FeatureChain,
+                                      create: impl FnOnce() -> R) -> R {
+    let _trace = trace::span("loader::with_device_features", || {
+        format!("{} feature struct(s)", chain.structs.len())
+    });
+    with_thread_local(&DEVICE_FEATURES, chain.link(), create)
+}
+
+/// Create descriptor set layouts (e.g. by building pipelines), applying some
+/// descriptor binding flags to those which match
+///
+/// The device must have been created from an instance that was created
+/// using create_instance(), and the layouts must be created on the calling
+/// thread.
+///
+/// Vulkano does not tell which layout it is creating, so layouts are matched
+/// using a heuristic: the flags are applied to every layout with a single
+/// binding whose binding number and descriptor type match, created while the
+/// closure runs. Layouts with several bindings are never affected, and
+/// unrelated single-binding layouts which happen to match would be, so the
+/// closure should create as few layouts as possible.
+///
+pub(crate) fn with_binding_flags<R>(flags: BindingFlags,
+                                    create: impl FnOnce() -> R) -> R {
+    let _trace = trace::span("loader::with_binding_flags",
+                             || format!("{:?}", flags));
+    with_thread_local(&BINDING_FLAGS, Some(flags), create)
+}
+
+/// Allocate descriptor sets whose last binding has a variable descriptor
+/// count, with some number of descriptors in that binding
+///
+/// The same requirements as for with_binding_flags() apply.
+///
+pub(crate) fn with_variable_descriptor_count<R>(
+    count: u32,
+    allocate: impl FnOnce() -> R
+) -> R {
+    let _trace = trace::span("loader::with_variable_descriptor_count",
+                             || format!("count: {}", count));
+    with_thread_local(&VARIABLE_DESCRIPTOR_COUNT, Some(count), allocate)
+}
+
+/// Create graphics pipelines (e.g. using vulkano's GraphicsPipelineBuilder)
+/// for dynamic rendering, by replacing their render pass with a
+/// VkPipelineRenderingCreateInfo
+///
+/// Only pipelines which are created one at a time are affected. The same
+/// requirements as for with_binding_flags() apply.
+///
+/// # Safety
+///
+/// rendering_info must point to a valid VkPipelineRenderingCreateInfo, whose
+/// pNext member will be overwritten, and which must stay alive until the
+/// closure returns.
+///
+pub(crate) unsafe fn with_pipeline_rendering<R>(
+    rendering_info: *mut c_void,
+    create: impl FnOnce() -> R
+) -> R {
+    let _trace = trace::span("loader::with_pipeline_rendering", String::new);
+    with_thread_local(&PIPELINE_RENDERING, rendering_info, create)
+}
+
+/// Load a device-level entry point which vk-sys does not know about
+///
+/// The extension or Vulkan version which provides it must be enabled on the
+/// device, otherwise an error is returned.
+///
+pub(crate) fn device_function(device: &Arc<Device>,
+                              name: &str) -> Result<*const c_void> {
+    let c_name = |name: &str| {
+        CString::new(name).expect("Entry point names have no NUL")
+    };
+    let get_device_proc_addr = loader::auto_loader()?.get_instance_proc_addr(
+        device.instance().internal_object(),
+        c_name("vkGetDeviceProcAddr").as_ptr()
+    );
+    if get_device_proc_addr.is_null() {
+        return Err(Error::Vulkan(format_err!(
+            "vkGetDeviceProcAddr is not available"
+        )));
+    }
+    let function = unsafe {
+        let get_device_proc_addr: GetDeviceProcAddrFn =
+            mem::transmute(get_device_proc_addr);
+        get_device_proc_addr(device.internal_object(), c_name(name).as_ptr())
+    };
+    if function.is_null() {
+        Err(Error::Vulkan(format_err!(
+            "{} is not available, was the extension which provides it \
+             enabled on the device?", name
+        )))
+    } else {
+        Ok(function)
+    }
+}
+
+
+/// Chain of extension feature structs, to be enabled at device creation time
+#[derive(Default)]
+pub(crate) struct FeatureChain {
+    /// Feature structs, in chaining order
+    structs: Vec<Box<dyn FeatureStruct>>,
+}
+
+impl FeatureChain {
+    /// Add an extension feature struct to the chain
+    pub fn push(&mut self, features: impl FeatureStruct + 'static) {
+        self.structs.push(Box::new(features));
+    }
+
+    /// Truth that no feature struct was added
+    pub fn is_empty(&self) -> bool {
+        self.structs.is_empty()
+    }
+
+    /// Link the feature structs together, returning the head of the chain
+    fn link(&mut self) -> *mut c_void {
+        let mut next = ptr::null_mut();
+        for features in self.structs.iter_mut().rev() {
+            let header = &mut **features as *mut dyn FeatureStruct
+                                         as *mut ChainHeader;
+            unsafe { (*header).p_next = next; }
+            next = header as *mut c_void;
+        }
+        next
+    }
+}
+
+/// Extension feature struct, such as VkPhysicalDeviceMeshShaderFeaturesEXT
+///
+/// Implementors must be #[repr(C)] structs which start with the sType and
+/// pNext members, as described by ChainHeader.
+///
+pub(crate) unsafe trait FeatureStruct {}
+
+/// Members which every Vulkan extension struct starts with
+#[repr(C)]
+struct ChainHeader {
+    s_type: u32,
+    p_next: *mut c_void,
+}
+
+
+/// Descriptor binding flags (VkDescriptorBindingFlags) of a descriptor set
+/// layout binding
+///
+/// They are only applied to descriptor set layouts whose only binding has
+/// the specified index and descriptor type.
+///
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BindingFlags {
+    /// Binding index
+    pub binding: u32,
+
+    /// Descriptor type of the binding
+    pub descriptor_type: vk::DescriptorType,
+
+    /// Flags to be applied to the binding
+    pub flags: u32,
+}
+
+/// VkDescriptorSetLayoutBindingFlagsCreateInfo, which vk-sys does not know
+#[repr(C)]
+struct BindingFlagsCreateInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    binding_count: u32,
+    p_binding_flags: *const u32,
+}
+
+/// VkDescriptorSetVariableDescriptorCountAllocateInfo, which vk-sys does not
+/// know about
+#[repr(C)]
+struct VariableCountAllocateInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    descriptor_set_count: u32,
+    p_descriptor_counts: *const u32,
+}
+
+
+/// Vulkan loader which forwards to vulkano's default loader, except for the
+/// entry points which vulkanoob needs to intercept
+struct ShimLoader(&'static FunctionPointers<Box<dyn Loader + Send + Sync>>);
+
+unsafe impl Loader for ShimLoader {
+    fn get_instance_proc_addr(&self,
+                              instance: vk::Instance,
+                              name: *const c_char) -> *const c_void {
+        let function = self.0.get_instance_proc_addr(instance, name);
+        if function.is_null() { return function; }
+        let name = unsafe { CStr::from_ptr(name) };
+        match name.to_bytes() {
+            b"vkCreateInstance" => {
+                REAL_CREATE_INSTANCE.store(function as usize,
+                                           Ordering::Release);
+                create_instance_shim as CreateInstanceFn as *const c_void
+            },
+            b"vkCreateDevice" => {
+                REAL_CREATE_DEVICE.store(function as usize, Ordering::Release);
+                create_device_shim as CreateDeviceFn as *const c_void
+            },
+            b"vkGetDeviceProcAddr" => {
+                REAL_GET_DEVICE_PROC_ADDR.store(function as usize,
+                                                Ordering::Release);
+                get_device_proc_addr_shim as GetDeviceProcAddrFn
+                                          as *const c_void
+            },
+            _ => function,
+        }
+    }
+}
+
+/// vkCreateInstance wrapper, which requests the API version selected by
+/// create_instance() instead of the one specified by vulkano
+extern "system" fn create_instance_shim(
+    create_info: *const vk::InstanceCreateInfo,
+    allocator: *const vk::AllocationCallbacks,
+    instance: *mut vk::Instance
+) -> vk::Result {
+    // Safe because REAL_CREATE_INSTANCE is set before this function is handed
+    // out, and the structs are only copied, never modified in place.
+    unsafe {
+        let real_create_instance: CreateInstanceFn =
+            mem::transmute(REAL_CREATE_INSTANCE.load(Ordering::Acquire));
+        let api_version = match REQUESTED_VERSION.with(Cell::get) {
+            Some(version) => version,
+            None => return real_create_instance(create_info,
+                                                allocator,
+                                                instance),
+        };
+        let mut app_info = if (*create_info).pApplicationInfo.is_null() {
+            vk::ApplicationInfo {
+                sType: vk::STRUCTURE_TYPE_APPLICATION_INFO,
+                pNext: ptr::null(),
+                pApplicationName: ptr::null(),
+                applicationVersion: 0,
+                pEngineName: ptr::null(),
+                engineVersion: 0,
+                apiVersion: 0,
+            }
+        } else {
+            ptr::read((*create_info).pApplicationInfo)
+        };
+        app_info.apiVersion = api_version;
+        let mut patched_info = ptr::read(create_info);
+        patched_info.pApplicationInfo = &app_info;
+        real_create_instance(&patched_info, allocator, instance)
+    }
+}
+
+/// vkCreateDevice wrapper, which chains the extension feature structs passed
+/// to with_device_features() in front of the create info's own pNext chain
+extern "system" fn create_device_shim(
+    physical_device: vk::PhysicalDevice,
+    create_info: *const vk::DeviceCreateInfo,
+    allocator: *const vk::AllocationCallbacks,
+    device: *mut vk::Device
+) -> vk::Result {
+    // Safe because REAL_CREATE_DEVICE is set before this function is handed
+    // out, and the feature chain outlives the with_device_features() call.
+    unsafe {
+        let real_create_device: CreateDeviceFn =
+            mem::transmute(REAL_CREATE_DEVICE.load(Ordering::Acquire));
+        let chain = DEVICE_FEATURES.with(Cell::get);
+        if chain.is_null() {
+            return real_create_device(physical_device,
+                                      create_info,
+                                      allocator,
+                                      device);
+        }
+        let mut last = chain as *mut ChainHeader;
+        while !(*last).p_next.is_null() {
+            last = (*last).p_next as *mut ChainHeader;
+        }
+        (*last).p_next = (*create_info).pNext as *mut c_void;
+        let mut patched_info = ptr::read(create_info);
+        patched_info.pNext = chain as *const c_void;
+        real_create_device(physical_device, &patched_info, allocator, device)
+    }
+}
+
+/// vkGetDeviceProcAddr wrapper, which hands out the descriptor set layout
+/// creation and allocation wrappers
+extern "system" fn get_device_proc_addr_shim(
+    device: vk::Device,
+    name: *const c_char
+) -> *const c_void {
+    // Safe because REAL_GET_DEVICE_PROC_ADDR is set before this function is
+    // handed out, and name is a valid C string as per the Vulkan spec.
+    unsafe {
+        let function = real_device_function(device, name);
+        if function.is_null() { return function; }
+        match CStr::from_ptr(name).to_bytes() {
+            b"vkCreateDescriptorSetLayout" => {
+                create_descriptor_set_layout_shim
+                    as CreateDescriptorSetLayoutFn as *const c_void
+            },
+            b"vkAllocateDescriptorSets" => {
+                allocate_descriptor_sets_shim
+                    as AllocateDescriptorSetsFn as *const c_void
+            },
+            b"vkCreateGraphicsPipelines" => {
+                create_graphics_pipelines_shim
+                    as CreateGraphicsPipelinesFn as *const c_void
+            },
+            _ => function,
+        }
+    }
+}
+
+/// vkCreateDescriptorSetLayout wrapper, which chains the binding flags
+/// passed to with_binding_flags() to the create info of matching layouts
+///
+/// See with_binding_flags() for the limits of the matching heuristic.
+///
+extern "system" fn create_descriptor_set_layout_shim(
+    device: vk::Device,
+    create_info: *const vk::DescriptorSetLayoutCreateInfo,
+    allocator: *const vk::AllocationCallbacks,
+    layout: *mut vk::DescriptorSetLayout
+) -> vk::Result {
+    // Safe because this function is only handed out if the real one exists,
+    // and the structs are only copied, never modified in place.
+    unsafe {
+        let name = c_str(b"vkCreateDescriptorSetLayout\0");
+        let real_create_layout: CreateDescriptorSetLayoutFn =
+            mem::transmute(real_device_function(device, name));
+        let info = &*create_info;
+        let flags = match BINDING_FLAGS.with(Cell::get) {
+            Some(flags) if info.bindingCount == 1
+                           && (*info.pBindings).binding == flags.binding
+                           && (*info.pBindings).descriptorType
+                                  == flags.descriptor_type => flags.flags,
+            _ => return real_create_layout(device,
+                                           create_info,
+                                           allocator,
+                                           layout),
+        };
+        let flags_info = BindingFlagsCreateInfo {
+            s_type: STRUCTURE_TYPE_BINDING_FLAGS_CREATE_INFO,
+            p_next: info.pNext,
+            binding_count: 1,
+            p_binding_flags: &flags,
+        };
+        let mut patched_info = ptr::read(create_info);
+        patched_info.pNext = &flags_info as *const _ as *const c_void;
+        real_create_layout(device, &patched_info, allocator, layout)
+    }
+}
+
+/// vkAllocateDescriptorSets wrapper, which chains the variable descriptor
+/// count passed to with_variable_descriptor_count() to the allocate info
+extern "system" fn allocate_descriptor_sets_shim(
+    device: vk::Device,
+    allocate_info: *const vk::DescriptorSetAllocateInfo,
+    sets: *mut vk::DescriptorSet
+) -> vk::Result {
+    // Safe for the same reasons as create_descriptor_set_layout_shim()
+    unsafe {
+        let name = c_str(b"vkAllocateDescriptorSets\0");
+        let real_allocate: AllocateDescriptorSetsFn =
+            mem::transmute(real_device_function(device, name));
+        let count = match VARIABLE_DESCRIPTOR_COUNT.with(Cell::get) {
+            Some(count) => count,
+            None => return real_allocate(device, allocate_info, sets),
+        };
+        let info = &*allocate_info;
+        let counts = vec![count; info.descriptorSetCount as usize];
+        let count_info = VariableCountAllocateInfo {
+            s_type: STRUCTURE_TYPE_VARIABLE_COUNT_ALLOCATE_INFO,
+            p_next: info.pNext,
+            descriptor_set_count: info.descriptorSetCount,
+            p_descriptor_counts: counts.as_ptr(),
+        };
+        let mut patched_info = ptr::read(allocate_info);
+        patched_info.pNext = &count_info as *const _ as *const c_void;
+        real_allocate(device, &patched_info, sets)
+    }
+}
+
+/// vkCreateGraphicsPipelines wrapper, which swaps the render pass for the
+/// VkPipelineRenderingCreateInfo passed to with_pipeline_rendering()
+extern "system" fn create_graphics_pipelines_shim(
+    device: vk::Device,
+    cache: vk::PipelineCache,
+    create_info_count: u32,
+    create_infos: *const vk::GraphicsPipelineCreateInfo,
+    allocator: *const vk::AllocationCallbacks,
+    pipelines: *mut vk::Pipeline
+) -> vk::Result {
+    // Safe for the same reasons as create_descriptor_set_layout_shim(), and
+    // because the rendering info outlives the with_pipeline_rendering() call
+    unsafe {
+        let name = c_str(b"vkCreateGraphicsPipelines\0");
+        let real_create_pipelines: CreateGraphicsPipelinesFn =
+            mem::transmute(real_device_function(device, name));
+        let rendering_info = PIPELINE_RENDERING.with(Cell::get);
+        if rendering_info.is_null() || create_info_count != 1 {
+            return real_create_pipelines(device,
+                                         cache,
+                                         create_info_count,
+                                         create_infos,
+                                         allocator,
+                                         pipelines);
+        }
+        (*(rendering_info as *mut ChainHeader)).p_next =
+            (*create_infos).pNext as *mut c_void;
+        let mut patched_info = ptr::read(create_infos);
+        patched_info.pNext = rendering_info as *const c_void;
+        patched_info.renderPass = 0;
+        patched_info.subpass = 0;
+        real_create_pipelines(device,
+                              cache,
+                              1,
+                              &patched_info,
+                              allocator,
+                              pipelines)
+    }
+}
+
+/// Look up a device-level function using the real vkGetDeviceProcAddr
+///
+/// REAL_GET_DEVICE_PROC_ADDR must have been set beforehand.
+///
+unsafe fn real_device_function(device: vk::Device,
+                               name: *const c_char) -> *const c_void {
+    let real_get_device_proc_addr: GetDeviceProcAddrFn =
+        mem::transmute(REAL_GET_DEVICE_PROC_ADDR.load(Ordering::Acquire));
+    real_get_device_proc_addr(device, name)
+}
+
+/// Turn a NUL-terminated byte string into a C string pointer
+fn c_str(bytes: &'static [u8]) -> *const c_char {
+    debug_assert_eq!(bytes.last(), Some(&0));
+    bytes.as_ptr() as *const c_char
+}
+
+
+/// Encode a Vulkan version as in the Vulkan API
+fn encode_version(version: Version) -> u32 {
+    (u32::from(version.major) << 22)
+        | (u32::from(version.minor) << 12)
+        | u32::from(version.patch)
+}
+
+/// Decode a Vulkan version from the Vulkan API
+fn decode_version(version: u32) -> Version {
+    Version {
+        major: (version >> 22) as u16,
+        minor: ((version >> 12) & 0x3ff) as u16,
+        patch: (version & 0xfff) as u16,
+    }
+}
@@ -0,0 +1,335 @@
+//! Conveniences for moving data between the host and the device
+//!
+//! These helpers are synchronous: they submit the transfer, then wait for it
+//! to complete. This is wasteful, but very convenient when prototyping.
+//!
+//! Some devices (integrated GPUs, discrete GPUs with resizable BAR) have
+//! memory which is both device-local and host-visible, where buffers can be
+//! written directly without a staging copy. easy_upload_device_local() makes
+//! use of it when available.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    fmt,
+    iter,
+    marker::PhantomData,
+    mem,
+    result,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use vulkano::{
+    buffer::{
+        sys::{
+            SparseLevel,
+            UnsafeBuffer,
+        },
+        BufferAccess,
+        BufferInner,
+        BufferUsage,
+        CpuAccessibleBuffer,
+        DeviceLocalBuffer,
+        TypedBufferAccess,
+    },
+    command_buffer::{
+        AutoCommandBufferBuilder,
+        CommandBuffer,
+    },
+    device::{
+        Device,
+        DeviceOwned,
+        Queue,
+    },
+    image::ImageAccess,
+    memory::{
+        DeviceMemory,
+        MappedDeviceMemory,
+    },
+    sync::{
+        AccessError,
+        GpuFuture,
+        Sharing,
+    },
+};
+
+
+/// Buffer type produced by easy_upload_device_local()
+pub type EasyDeviceBuffer<T> =
+    Arc<dyn TypedBufferAccess<Content=[T]> + Send + Sync>;
+
+
+/// How data made its way into a device-local buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadPath {
+    /// Written directly into device-local, host-visible memory
+    Direct,
+
+    /// Written into a staging buffer, then copied to device-local memory
+    Staged,
+}
+
+impl fmt::Display for UploadPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UploadPath::Direct => write!(f, "direct write"),
+            UploadPath::Staged => write!(f, "staging copy"),
+        }
+    }
+}
+
+
+/// Upload data to a new device-local buffer, and wait for the upload to end
+///
+/// The buffer is created with all usage flags enabled, so that it can be used
+/// for any purpose (vertex data, storage buffer, transfer source...). It is
+/// only accessible from the queue family of the specified queue.
+///
+pub fn easy_upload_buffer<T>(
+    device: &Arc<Device>,
+    queue: &Arc<Queue>,
+    data: &[T]
+) -> Result<Arc<DeviceLocalBuffer<[T]>>>
+    where T: Copy + Send + Sync + 'static
+{
+    let _trace = trace::span("memory::easy_upload_buffer",
+                             || format!("elements: {}", data.len()));
+
+    // Put the data in a host-visible staging buffer
+    let staging_buffer = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage::transfer_source(),
+        data.iter().cloned()
+    )?;
+
+    // Allocate the device-local buffer
+    let buffer = DeviceLocalBuffer::array(device.clone(),
+                                          data.len(),
+                                          BufferUsage::all(),
+                                          iter::once(queue.family()))?;
+
+    // Copy the data from the staging buffer and wait for the copy to end
+    run_copy(device, queue, staging_buffer, buffer.clone())?;
+    Ok(buffer)
+}
+
+/// Upload data to a new device-local buffer, avoiding staging if possible
+///
+/// If the device has a memory type which is both device-local and
+/// host-visible, the data is written there directly. Otherwise, we fall back
+/// to easy_upload_buffer()'s staging copy, which requires a queue that can
+/// perform transfers. The path which was taken is logged and returned.
+///
+/// The buffer is only meant to be accessed by the device after creation, and
+/// is only accessible from the queue family of the specified queue.
+///
+pub fn easy_upload_device_local<T>(
+    device: &Arc<Device>,
+    queue: &Arc<Queue>,
+    data: &[T],
+    usage: BufferUsage
+) -> Result<(EasyDeviceBuffer<T>, UploadPath)>
+    where T: Copy + Send + Sync + 'static
+{
+    let _trace = trace::span("memory::easy_upload_device_local",
+                             || format!("elements: {}, usage: {:?}",
+                                        data.len(), usage));
+    if let Some(buffer) = MappedBuffer::new(device, data, usage)? {
+        info!("Uploaded {} elements to device-local memory by {}",
+              data.len(), UploadPath::Direct);
+        let buffer: EasyDeviceBuffer<T> = Arc::new(buffer);
+        return Ok((buffer, UploadPath::Direct));
+    }
+    let buffer: EasyDeviceBuffer<T> = easy_upload_buffer(device, queue, data)?;
+    info!("Uploaded {} elements to device-local memory by {}",
+          data.len(), UploadPath::Staged);
+    Ok((buffer, UploadPath::Staged))
+}
+
+/// Download the contents of a buffer, and wait for the download to end
+///
+/// The buffer must be usable as a transfer source, and the queue must belong
+/// to a queue family which can access it.
+///
+pub fn easy_download_buffer<T, B>(
+    queue: &Arc<Queue>,
+    buffer: &Arc<B>
+) -> Result<Vec<T>>
+    where T: Copy + Send + Sync + 'static,
+          B: TypedBufferAccess<Content=[T]> + Send + Sync + 'static
+{
+    let _trace = trace::span("memory::easy_download_buffer",
+                             || format!("elements: {}", buffer.len()));
+
+    // Allocate a host-visible staging buffer
+    let device = queue.device();
+    let staging_buffer = unsafe {
+        CpuAccessibleBuffer::<[T]>::uninitialized_array(
+            device.clone(),
+            buffer.len(),
+            BufferUsage::transfer_destination()
+        )?
+    };
+
+    // Copy the data to the staging buffer and wait for the copy to end
+    run_copy(device, queue, buffer.clone(), staging_buffer.clone())?;
+
+    // Read back the data
+    let contents = staging_buffer.read()?;
+    Ok(contents.to_vec())
+}
+
+/// Copy a buffer into another buffer, and wait for the copy to end
+pub(crate) fn run_copy<S, D, T>(device: &Arc<Device>,
+                                queue: &Arc<Queue>,
+                                source: S,
+                                destination: D) -> Result<()>
+    where S: TypedBufferAccess<Content=T> + Send + Sync + 'static,
+          D: TypedBufferAccess<Content=T> + Send + Sync + 'static,
+          T: ?Sized
+{
+    let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(
+        device.clone(),
+        queue.family()
+    )?.copy_buffer(source, destination)?
+      .build()?;
+    command_buffer.execute(queue.clone())?
+                  .then_signal_fence_and_flush()?
+                  .wait(None)?;
+    Ok(())
+}
+
+
+/// A buffer in device-local, host-visible memory
+struct MappedBuffer<T> {
+    /// Raw buffer
+    buffer: UnsafeBuffer,
+
+    /// Memory backing the buffer, kept alive as long as the buffer
+    _memory: MappedDeviceMemory,
+
+    /// Number of elements in the buffer
+    len: usize,
+
+    /// Number of GPU locks, and whether they are exclusive
+    gpu_locks: Mutex<(usize, bool)>,
+
+    /// The buffer contains elements of type T
+    _elements: PhantomData<Box<[T]>>,
+}
+
+impl<T: Copy + Send + Sync + 'static> MappedBuffer<T> {
+    /// Create a buffer in device-local, host-visible memory and fill it
+    ///
+    /// Returns None if the device has no suitable memory type.
+    ///
+    fn new(device: &Arc<Device>,
+           data: &[T],
+           usage: BufferUsage) -> Result<Option<Self>> {
+        let size = data.len() * mem::size_of::<T>();
+        if size == 0 {
+            return Err(Error::InvalidArgument(
+                "Cannot upload an empty buffer".to_owned()
+            ));
+        }
+        let (buffer, requirements) = unsafe {
+            UnsafeBuffer::new(device.clone(),
+                              size,
+                              usage,
+                              Sharing::Exclusive::<iter::Empty<u32>>,
+                              SparseLevel::none())?
+        };
+        let memory_type = device.physical_device().memory_types().find(|ty| {
+            requirements.memory_type_bits & (1 << ty.id()) != 0
+                && ty.is_device_local()
+                && ty.is_host_visible()
+        });
+        let memory_type = match memory_type {
+            Some(ty) => ty,
+            None => return Ok(None),
+        };
+        let memory = DeviceMemory::alloc_and_map(device.clone(),
+                                                 memory_type,
+                                                 requirements.size)?;
+        unsafe {
+            buffer.bind_memory(memory.as_ref(), 0)?;
+            let mut contents = memory.read_write::<[T]>(0..size);
+            contents.copy_from_slice(data);
+        }
+        Ok(Some(MappedBuffer {
+            buffer,
+            _memory: memory,
+            len: data.len(),
+            gpu_locks: Mutex::new((0, false)),
+            _elements: PhantomData,
+        }))
+    }
+}
+
+unsafe impl<T> BufferAccess for MappedBuffer<T>
+    where T: Send + Sync + 'static
+{
+    fn inner(&self) -> BufferInner {
+        BufferInner {
+            buffer: &self.buffer,
+            offset: 0,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.len * mem::size_of::<T>()
+    }
+
+    fn conflicts_buffer(&self, other: &dyn BufferAccess) -> bool {
+        self.conflict_key() == other.conflict_key()
+    }
+
+    fn conflicts_image(&self, _other: &dyn ImageAccess) -> bool {
+        false
+    }
+
+    fn conflict_key(&self) -> (u64, usize) {
+        (self.buffer.key(), 0)
+    }
+
+    fn try_gpu_lock(&self,
+                    exclusive_access: bool,
+                    _queue: &Queue) -> result::Result<(), AccessError> {
+        let mut locks = self.gpu_locks.lock().unwrap();
+        let (count, exclusive) = *locks;
+        if count > 0 && (exclusive || exclusive_access) {
+            return Err(AccessError::AlreadyInUse);
+        }
+        *locks = (count + 1, exclusive_access);
+        Ok(())
+    }
+
+    unsafe fn increase_gpu_lock(&self) {
+        self.gpu_locks.lock().unwrap().0 += 1;
+    }
+
+    unsafe fn unlock(&self) {
+        let mut locks = self.gpu_locks.lock().unwrap();
+        locks.0 -= 1;
+        if locks.0 == 0 { locks.1 = false; }
+    }
+}
+
+unsafe impl<T> TypedBufferAccess for MappedBuffer<T>
+    where T: Send + Sync + 'static
+{
+    type Content = [T];
+}
+
+unsafe impl<T> DeviceOwned for MappedBuffer<T> {
+    fn device(&self) -> &Arc<Device> {
+        self.buffer.device()
+    }
+}
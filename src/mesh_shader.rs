@@ -0,0 +1,1019 @@
+//! Mesh shader support (VK_EXT_mesh_shader)
+//!
+//! Mesh shading replaces the vertex input and vertex processing stages of the
+//! graphics pipeline with compute-like task and mesh shaders. Whether a device
+//! can use it depends not only on the VK_EXT_mesh_shader extension, but also
+//! on extension features and on a set of mesh/task limits, none of which
+//! vulkano knows about. This module queries them (see the properties2
+//! module), provides device filters which check them, and checks that a set
+//! of task, mesh and fragment shaders fits the device's limits.
+//!
+//! EasyPhysicalDevice::setup_mesh_shader_device() enables the extension and
+//! its task/mesh shader features. Vulkano cannot build mesh shading
+//! pipelines yet, so EasyMeshPipeline builds one from MeshPipelineShaders
+//! using raw Vulkan calls, with the same defaults as
+//! EasyGraphicsPipelineBuilder. Mesh draws must then be recorded into a raw
+//! command buffer, such as a command::RawCommandBuffer.
+
+use ::{
+    api_level::VULKAN_1_1,
+    device::EasyDevice,
+    error::check_result,
+    loader::{
+        self,
+        FeatureChain,
+        FeatureStruct,
+    },
+    properties2,
+    render::EasyRenderPass,
+    shader,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    ffi::CString,
+    mem,
+    os::raw::c_void,
+    ptr,
+    sync::Arc,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    descriptor::PipelineLayoutAbstract,
+    device::Device,
+    framebuffer::Subpass,
+    instance::{
+        PhysicalDevice,
+        RawDeviceExtensions,
+    },
+    VulkanObject,
+};
+
+
+/// Name of the mesh shader extension
+pub const MESH_SHADER_EXTENSION: &str = "VK_EXT_mesh_shader";
+
+/// Extensions which VK_EXT_mesh_shader depends on, on Vulkan 1.1 devices
+pub const MESH_SHADER_DEPENDENCIES: &[&str] = &[
+    "VK_KHR_spirv_1_4",
+    "VK_KHR_shader_float_controls",
+];
+
+/// Shader stage flags of task and mesh shaders, which vk-sys does not know
+const SHADER_STAGE_TASK: u32 = 0x40;
+const SHADER_STAGE_MESH: u32 = 0x80;
+
+/// Structure type of VkPhysicalDeviceMeshShaderFeaturesEXT
+const STRUCTURE_TYPE_MESH_SHADER_FEATURES: u32 = 1_000_328_000;
+
+/// Structure type of VkPhysicalDeviceMeshShaderPropertiesEXT
+const STRUCTURE_TYPE_MESH_SHADER_PROPERTIES: u32 = 1_000_328_001;
+
+/// SPIR-V opcodes and enumerants which are relevant to mesh shading
+mod spirv {
+    pub const OP_ENTRY_POINT: u32 = 15;
+    pub const OP_EXECUTION_MODE: u32 = 16;
+    pub const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+    pub const EXECUTION_MODEL_TASK: u32 = 5364;
+    pub const EXECUTION_MODEL_MESH: u32 = 5365;
+    pub const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+    pub const EXECUTION_MODE_OUTPUT_VERTICES: u32 = 26;
+    pub const EXECUTION_MODE_OUTPUT_PRIMITIVES: u32 = 5270;
+}
+
+
+/// Mesh shading features of a device (VkPhysicalDeviceMeshShaderFeaturesEXT)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MeshShaderFeatures {
+    /// Task shaders are supported
+    pub task_shader: bool,
+
+    /// Mesh shaders are supported
+    pub mesh_shader: bool,
+
+    /// Mesh shading can be combined with multiview rendering
+    pub multiview_mesh_shader: bool,
+
+    /// Mesh shaders can set the primitive fragment shading rate
+    pub primitive_fragment_shading_rate_mesh_shader: bool,
+
+    /// Mesh shading pipeline statistics queries are supported
+    pub mesh_shader_queries: bool,
+}
+
+impl MeshShaderFeatures {
+    /// Names of the supported features, in vulkano's naming convention
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if self.task_shader { names.push("task_shader"); }
+        if self.mesh_shader { names.push("mesh_shader"); }
+        if self.multiview_mesh_shader { names.push("multiview_mesh_shader"); }
+        if self.primitive_fragment_shading_rate_mesh_shader {
+            names.push("primitive_fragment_shading_rate_mesh_shader");
+        }
+        if self.mesh_shader_queries { names.push("mesh_shader_queries"); }
+        names.into_iter().map(str::to_owned).collect()
+    }
+}
+
+
+/// Generate MeshShaderLimits, along with its raw Vulkan counterpart and the
+/// conversion between them, from the list of limits in spec order
+macro_rules! mesh_shader_limits {
+    ($($(#[$doc:meta])* $name:ident: $ty:ty,)*) => {
+        /// Mesh and task shader limits of a device
+        /// (VkPhysicalDeviceMeshShaderPropertiesEXT)
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+        pub struct MeshShaderLimits {
+            $(
+                $(#[$doc])*
+                pub $name: $ty,
+            )*
+        }
+
+        impl MeshShaderLimits {
+            /// List the limits as (name, value) pairs, like DeviceReport does
+            pub fn to_pairs(&self) -> Vec<(String, String)> {
+                vec![
+                    $( (stringify!($name).to_owned(),
+                        format!("{:?}", self.$name)) ),*
+                ]
+            }
+        }
+
+        /// VkPhysicalDeviceMeshShaderPropertiesEXT, which vk-sys does not
+        /// know about (VkBool32 members are stored as u32)
+        #[repr(C)]
+        struct RawMeshShaderProperties {
+            s_type: u32,
+            p_next: *mut c_void,
+            $( $name: <$ty as RawLimit>::Raw, )*
+        }
+
+        impl RawMeshShaderProperties {
+            /// Convert to MeshShaderLimits
+            fn limits(&self) -> MeshShaderLimits {
+                MeshShaderLimits {
+                    $( $name: <$ty as RawLimit>::from_raw(self.$name), )*
+                }
+            }
+        }
+    };
+}
+
+mesh_shader_limits! {
+    /// Maximal total number of task work groups in a draw
+    max_task_work_group_total_count: u32,
+
+    /// Maximal number of task work groups in a draw, along each dimension
+    max_task_work_group_count: [u32; 3],
+
+    /// Maximal number of invocations in a task work group
+    max_task_work_group_invocations: u32,
+
+    /// Maximal task work group size, along each dimension
+    max_task_work_group_size: [u32; 3],
+
+    /// Maximal task payload size, in bytes
+    max_task_payload_size: u32,
+
+    /// Maximal task shader shared memory size, in bytes
+    max_task_shared_memory_size: u32,
+
+    /// Maximal task payload and shared memory size, in bytes
+    max_task_payload_and_shared_memory_size: u32,
+
+    /// Maximal total number of mesh work groups launched by a task shader
+    max_mesh_work_group_total_count: u32,
+
+    /// Maximal number of mesh work groups, along each dimension
+    max_mesh_work_group_count: [u32; 3],
+
+    /// Maximal number of invocations in a mesh work group
+    max_mesh_work_group_invocations: u32,
+
+    /// Maximal mesh work group size, along each dimension
+    max_mesh_work_group_size: [u32; 3],
+
+    /// Maximal mesh shader shared memory size, in bytes
+    max_mesh_shared_memory_size: u32,
+
+    /// Maximal task payload and mesh shared memory size, in bytes
+    max_mesh_payload_and_shared_memory_size: u32,
+
+    /// Maximal mesh shader output size, in bytes
+    max_mesh_output_memory_size: u32,
+
+    /// Maximal task payload and mesh output size, in bytes
+    max_mesh_payload_and_output_memory_size: u32,
+
+    /// Maximal number of mesh shader output components
+    max_mesh_output_components: u32,
+
+    /// Maximal number of vertices emitted by a mesh work group
+    max_mesh_output_vertices: u32,
+
+    /// Maximal number of primitives emitted by a mesh work group
+    max_mesh_output_primitives: u32,
+
+    /// Maximal number of layers which mesh shaders can output to
+    max_mesh_output_layers: u32,
+
+    /// Maximal number of views which mesh shaders can output to
+    max_mesh_multiview_view_count: u32,
+
+    /// Granularity of per-vertex mesh output allocations
+    mesh_output_per_vertex_granularity: u32,
+
+    /// Granularity of per-primitive mesh output allocations
+    mesh_output_per_primitive_granularity: u32,
+
+    /// Preferred maximal number of invocations in a task work group
+    max_preferred_task_work_group_invocations: u32,
+
+    /// Preferred maximal number of invocations in a mesh work group
+    max_preferred_mesh_work_group_invocations: u32,
+
+    /// Vertex outputs should preferably be written by the invocation whose
+    /// index matches the vertex index
+    prefers_local_invocation_vertex_output: bool,
+
+    /// Primitive outputs should preferably be written by the invocation
+    /// whose index matches the primitive index
+    prefers_local_invocation_primitive_output: bool,
+
+    /// Vertex outputs should preferably be compacted
+    prefers_compact_vertex_output: bool,
+
+    /// Primitive outputs should preferably be compacted
+    prefers_compact_primitive_output: bool,
+}
+
+
+/// Summary of the mesh shading support of a physical device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeshShaderSupport {
+    /// Supported mesh shading features
+    pub features: MeshShaderFeatures,
+
+    /// Mesh and task shader limits
+    pub limits: MeshShaderLimits,
+}
+
+impl MeshShaderSupport {
+    /// Probe the mesh shading support of a physical device
+    ///
+    /// This returns None if the device does not support VK_EXT_mesh_shader,
+    /// or if the instance was created without
+    /// VK_KHR_get_physical_device_properties2.
+    ///
+    pub fn of_device(device: PhysicalDevice) -> Result<Option<Self>> {
+        let _trace = trace::span("MeshShaderSupport::of_device",
+                                 || format!("device: {}", device.name()));
+        let extensions = RawDeviceExtensions::supported_by_device_raw(device)?;
+        if !extensions.iter().any(|ext| {
+            ext.as_bytes() == MESH_SHADER_EXTENSION.as_bytes()
+        }) {
+            return Ok(None);
+        }
+
+        let mut features = RawMeshShaderFeatures {
+            s_type: STRUCTURE_TYPE_MESH_SHADER_FEATURES,
+            p_next: ptr::null_mut(),
+            task_shader: 0,
+            mesh_shader: 0,
+            multiview_mesh_shader: 0,
+            primitive_fragment_shading_rate_mesh_shader: 0,
+            mesh_shader_queries: 0,
+        };
+        let mut properties: RawMeshShaderProperties = unsafe {
+            mem::zeroed()
+        };
+        properties.s_type = STRUCTURE_TYPE_MESH_SHADER_PROPERTIES;
+        let queried = unsafe {
+            properties2::query_features(
+                device,
+                &mut features as *mut RawMeshShaderFeatures as *mut c_void
+            ) && properties2::query_properties(
+                device,
+                &mut properties as *mut RawMeshShaderProperties as *mut c_void
+            )
+        };
+        if !queried { return Ok(None); }
+
+        Ok(Some(MeshShaderSupport {
+            features: MeshShaderFeatures {
+                task_shader: features.task_shader != 0,
+                mesh_shader: features.mesh_shader != 0,
+                multiview_mesh_shader: features.multiview_mesh_shader != 0,
+                primitive_fragment_shading_rate_mesh_shader:
+                    features.primitive_fragment_shading_rate_mesh_shader != 0,
+                mesh_shader_queries: features.mesh_shader_queries != 0,
+            },
+            limits: properties.limits(),
+        }))
+    }
+}
+
+
+/// Device filter which only accepts devices that support mesh shaders, and
+/// task shaders if requested
+///
+/// This is meant to be used as (part of) the "other_criteria" parameter of
+/// easy_device_filter(). Devices whose mesh shading support cannot be queried
+/// are rejected.
+///
+pub fn supports_mesh_shaders(
+    task_shaders: bool
+) -> impl FnMut(PhysicalDevice) -> bool {
+    move |device| {
+        match MeshShaderSupport::of_device(device) {
+            Ok(Some(support)) => {
+                support.features.mesh_shader
+                    && (!task_shaders || support.features.task_shader)
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Device filter which only accepts devices that can run some mesh shading
+/// pipeline, as checked by MeshPipelineShaders::ensure_supported()
+///
+/// This is meant to be used as (part of) the "other_criteria" parameter of
+/// easy_device_filter().
+///
+pub fn can_run_mesh_pipeline<'a>(
+    shaders: &'a MeshPipelineShaders
+) -> impl FnMut(PhysicalDevice) -> bool + 'a {
+    move |device| shaders.ensure_supported(device).is_ok()
+}
+
+
+/// Add the mesh shader extension and its dependencies to a set of device
+/// extensions, and the mesh shading features to a feature chain, or tell
+/// what is missing
+pub(crate) fn add_device_extensions(
+    device: PhysicalDevice,
+    task_shaders: bool,
+    extensions: &mut RawDeviceExtensions,
+    feature_chain: &mut FeatureChain,
+) -> Result<()> {
+    let _trace = trace::span("mesh_shader::add_device_extensions", || {
+        format!("device: {}, task_shaders: {}", device.name(), task_shaders)
+    });
+    let support = match MeshShaderSupport::of_device(device)? {
+        Some(support) => support,
+        None => return Err(Error::MissingExtensions {
+            device: device.name(),
+            extensions: vec![MESH_SHADER_EXTENSION.to_owned()],
+        }),
+    };
+    let mut missing_features = Vec::new();
+    if !support.features.mesh_shader {
+        missing_features.push("mesh_shader".to_owned());
+    }
+    if task_shaders && !support.features.task_shader {
+        missing_features.push("task_shader".to_owned());
+    }
+    if !missing_features.is_empty() {
+        return Err(Error::MissingFeatures {
+            device: device.name(),
+            features: missing_features,
+        });
+    }
+
+    // Dependencies were promoted to core in Vulkan 1.2, so only enable them
+    // when they are advertised
+    let supported = RawDeviceExtensions::supported_by_device_raw(device)?;
+    for &name in [MESH_SHADER_EXTENSION].iter()
+                                        .chain(MESH_SHADER_DEPENDENCIES)
+    {
+        if supported.iter().any(|ext| ext.as_bytes() == name.as_bytes()) {
+            extensions.insert(CString::new(name)
+                                      .expect("Extension name has no NUL"));
+        }
+    }
+    feature_chain.push(RawMeshShaderFeatures {
+        s_type: STRUCTURE_TYPE_MESH_SHADER_FEATURES,
+        p_next: ptr::null_mut(),
+        task_shader: task_shaders as u32,
+        mesh_shader: 1,
+        multiview_mesh_shader: 0,
+        primitive_fragment_shading_rate_mesh_shader: 0,
+        mesh_shader_queries: 0,
+    });
+    Ok(())
+}
+
+
+/// Shaders of a mesh shading pipeline: optional task shader, mesh shader and
+/// fragment shader
+///
+/// The SPIR-V modules are checked for the right execution models upon
+/// construction, and their work group sizes and output sizes can then be
+/// checked against the mesh and task shader limits of a device.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MeshPipelineShaders {
+    /// Task shader SPIR-V, if any
+    pub task: Option<Vec<u8>>,
+
+    /// Mesh shader SPIR-V
+    pub mesh: Vec<u8>,
+
+    /// Fragment shader SPIR-V
+    pub fragment: Vec<u8>,
+
+    /// Entry point name and execution modes of the task shader
+    task_modes: Option<(CString, ExecutionModes)>,
+
+    /// Entry point name and execution modes of the mesh shader
+    mesh_modes: (CString, ExecutionModes),
+
+    /// Entry point name of the fragment shader
+    fragment_entry_point: CString,
+}
+
+impl MeshPipelineShaders {
+    /// Check the execution models of a set of mesh pipeline shaders
+    pub fn new(task: Option<Vec<u8>>,
+               mesh: Vec<u8>,
+               fragment: Vec<u8>) -> Result<Self> {
+        let task_modes = match task {
+            Some(ref task) => Some(
+                ExecutionModes::of_entry_point(task,
+                                               spirv::EXECUTION_MODEL_TASK,
+                                               "task")?
+            ),
+            None => None,
+        };
+        let mesh_modes = ExecutionModes::of_entry_point(
+            &mesh,
+            spirv::EXECUTION_MODEL_MESH,
+            "mesh"
+        )?;
+        let (fragment_entry_point, _) = ExecutionModes::of_entry_point(
+            &fragment,
+            spirv::EXECUTION_MODEL_FRAGMENT,
+            "fragment"
+        )?;
+        Ok(MeshPipelineShaders {
+            task,
+            mesh,
+            fragment,
+            task_modes,
+            mesh_modes,
+            fragment_entry_point,
+        })
+    }
+
+    /// Check that a device supports the features and limits that these
+    /// shaders need
+    pub fn ensure_supported(&self, device: PhysicalDevice) -> Result<()> {
+        let support = match MeshShaderSupport::of_device(device)? {
+            Some(support) => support,
+            None => return Err(Error::MissingExtensions {
+                device: device.name(),
+                extensions: vec![MESH_SHADER_EXTENSION.to_owned()],
+            }),
+        };
+        let mut missing_features = Vec::new();
+        if !support.features.mesh_shader {
+            missing_features.push("mesh_shader".to_owned());
+        }
+        if self.task.is_some() && !support.features.task_shader {
+            missing_features.push("task_shader".to_owned());
+        }
+        if !missing_features.is_empty() {
+            return Err(Error::MissingFeatures {
+                device: device.name(),
+                features: missing_features,
+            });
+        }
+
+        let limits = &support.limits;
+        let mut violations = Vec::new();
+        if let Some((_, ref task_modes)) = self.task_modes {
+            task_modes.check_local_size(
+                "task",
+                limits.max_task_work_group_size,
+                limits.max_task_work_group_invocations,
+                &mut violations
+            );
+        }
+        let mesh_modes = &self.mesh_modes.1;
+        mesh_modes.check_local_size(
+            "mesh",
+            limits.max_mesh_work_group_size,
+            limits.max_mesh_work_group_invocations,
+            &mut violations
+        );
+        if mesh_modes.output_vertices > limits.max_mesh_output_vertices {
+            violations.push(format!(
+                "mesh shader outputs {} vertices, but max_mesh_output_vertices \
+                 is {}",
+                mesh_modes.output_vertices, limits.max_mesh_output_vertices
+            ));
+        }
+        if mesh_modes.output_primitives > limits.max_mesh_output_primitives {
+            violations.push(format!(
+                "mesh shader outputs {} primitives, but \
+                 max_mesh_output_primitives is {}",
+                mesh_modes.output_primitives,
+                limits.max_mesh_output_primitives
+            ));
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InsufficientLimits {
+                device: device.name(),
+                violations,
+            })
+        }
+    }
+}
+
+
+/// Mesh shading graphics pipeline, built from MeshPipelineShaders
+///
+/// The pipeline uses the same defaults as EasyGraphicsPipelineBuilder: no
+/// face culling, no depth test, no blending, and a dynamic viewport and
+/// scissor, which bind() sets to the full render area. Since vulkano cannot
+/// record mesh shading draws, bind() and draw_mesh_tasks() work on raw
+/// command buffers, such as that of a command::RawCommandBuffer.
+///
+pub struct EasyMeshPipeline {
+    /// Device which the pipeline belongs to
+    device: Arc<Device>,
+
+    /// Raw pipeline handle
+    pipeline: vk::Pipeline,
+
+    /// Pipeline layout, which descriptor sets must be bound with
+    layout: Arc<dyn PipelineLayoutAbstract + Send + Sync>,
+
+    /// Render pass which the pipeline is used with
+    render_pass: EasyRenderPass,
+
+    /// Mesh and task shader limits of the device
+    limits: MeshShaderLimits,
+
+    /// Truth that the pipeline starts with a task shader
+    has_task_shader: bool,
+
+    /// vkCmdDrawMeshTasksEXT, which vk-sys does not know about
+    draw_mesh_tasks: extern "system" fn(vk::CommandBuffer, u32, u32, u32),
+}
+
+impl EasyMeshPipeline {
+    /// Build a mesh shading pipeline for some subpass of a render pass
+    ///
+    /// The device must have been set up using
+    /// EasyPhysicalDevice::setup_mesh_shader_device(), with task shaders
+    /// enabled if the pipeline has one.
+    ///
+    pub fn new(device: &EasyDevice,
+               shaders: &MeshPipelineShaders,
+               layout: Arc<dyn PipelineLayoutAbstract + Send + Sync>,
+               render_pass: &EasyRenderPass,
+               subpass: u32) -> Result<Self> {
+        let _trace = trace::span("EasyMeshPipeline::new", || {
+            format!("task shader: {}, subpass: {}",
+                    shaders.task.is_some(), subpass)
+        });
+        if device.api_version() < VULKAN_1_1 {
+            return Err(Error::InvalidArgument(format!(
+                "Mesh shading requires Vulkan {}, but only Vulkan {} can be \
+                 used on this device", VULKAN_1_1, device.api_version()
+            )));
+        }
+        let device = device.device();
+        let physical_device = device.physical_device();
+        shaders.ensure_supported(physical_device)?;
+        let limits = MeshShaderSupport::of_device(physical_device)?
+                         .expect("Checked by ensure_supported()")
+                         .limits;
+        let draw_mesh_tasks = unsafe {
+            mem::transmute(
+                loader::device_function(device, "vkCmdDrawMeshTasksEXT")?
+            )
+        };
+        let subpass_desc = Subpass::from(render_pass.clone(), subpass)
+                                   .ok_or_else(|| Error::InvalidArgument(
+                                       format!("Render pass has no subpass \
+                                                {}", subpass)
+                                   ))?;
+
+        // List the shader stages
+        let mut stages = Vec::with_capacity(3);
+        if let Some(ref task) = shaders.task {
+            let entry_point = &shaders.task_modes.as_ref()
+                                                 .expect("Checked by new()")
+                                                 .0;
+            stages.push((task, SHADER_STAGE_TASK, entry_point));
+        }
+        stages.push((&shaders.mesh, SHADER_STAGE_MESH, &shaders.mesh_modes.0));
+        stages.push((&shaders.fragment,
+                     vk::SHADER_STAGE_FRAGMENT_BIT,
+                     &shaders.fragment_entry_point));
+
+        // Create the shader modules, which are only needed until the pipeline
+        // is built, and the pipeline itself
+        let raw_device = device.internal_object();
+        let vk_fns = device.pointers();
+        let mut modules = Vec::with_capacity(stages.len());
+        let result = (|| unsafe {
+            let mut stage_infos = Vec::with_capacity(stages.len());
+            for &(spirv, stage, entry_point) in &stages {
+                let words = shader::spirv_words(spirv)?;
+                let module_info = vk::ShaderModuleCreateInfo {
+                    sType: vk::STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO,
+                    pNext: ptr::null(),
+                    flags: 0,
+                    codeSize: words.len() * mem::size_of::<u32>(),
+                    pCode: words.as_ptr(),
+                };
+                let mut module = 0;
+                check_result(vk_fns.CreateShaderModule(raw_device,
+                                                       &module_info,
+                                                       ptr::null(),
+                                                       &mut module),
+                             "vkCreateShaderModule")?;
+                modules.push(module);
+                stage_infos.push(vk::PipelineShaderStageCreateInfo {
+                    sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
+                    pNext: ptr::null(),
+                    flags: 0,
+                    stage,
+                    module,
+                    pName: entry_point.as_ptr(),
+                    pSpecializationInfo: ptr::null(),
+                });
+            }
+            Self::create_pipeline(device,
+                                  &stage_infos,
+                                  &*layout,
+                                  &subpass_desc)
+        })();
+        for module in modules {
+            unsafe { vk_fns.DestroyShaderModule(raw_device,
+                                                module,
+                                                ptr::null()); }
+        }
+        Ok(EasyMeshPipeline {
+            device: device.clone(),
+            pipeline: result?,
+            layout,
+            render_pass: render_pass.clone(),
+            limits,
+            has_task_shader: shaders.task.is_some(),
+            draw_mesh_tasks,
+        })
+    }
+
+    /// Pipeline layout, which descriptor sets must be bound with
+    pub fn layout(&self) -> &Arc<dyn PipelineLayoutAbstract + Send + Sync> {
+        &self.layout
+    }
+
+    /// Render pass which the pipeline is used with
+    pub fn render_pass(&self) -> &EasyRenderPass {
+        &self.render_pass
+    }
+
+    /// Raw pipeline handle
+    pub fn handle(&self) -> vk::Pipeline {
+        self.pipeline
+    }
+
+    /// Bind the pipeline to a raw command buffer, setting the viewport and
+    /// scissor to cover a render area of some dimensions
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must belong to the pipeline's device, be in the
+    /// recording state, and be inside of a compatible render pass instance.
+    ///
+    pub unsafe fn bind(&self,
+                       command_buffer: vk::CommandBuffer,
+                       dimensions: [u32; 2]) {
+        let _trace = trace::span("EasyMeshPipeline::bind",
+                                 || format!("dimensions: {:?}", dimensions));
+        let vk_fns = self.device.pointers();
+        vk_fns.CmdBindPipeline(command_buffer,
+                               vk::PIPELINE_BIND_POINT_GRAPHICS,
+                               self.pipeline);
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: dimensions[0] as f32,
+            height: dimensions[1] as f32,
+            minDepth: 0.0,
+            maxDepth: 1.0,
+        };
+        vk_fns.CmdSetViewport(command_buffer, 0, 1, &viewport);
+        let scissor = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: vk::Extent2D {
+                width: dimensions[0],
+                height: dimensions[1],
+            },
+        };
+        vk_fns.CmdSetScissor(command_buffer, 0, 1, &scissor);
+    }
+
+    /// Record a mesh shading draw into a raw command buffer, after checking
+    /// the work group count against the device's limits
+    ///
+    /// The work groups are those of the task shader if the pipeline has one,
+    /// and those of the mesh shader otherwise.
+    ///
+    /// # Safety
+    ///
+    /// The pipeline must have been bound to the command buffer using bind(),
+    /// along with any descriptor set that the shaders use.
+    ///
+    pub unsafe fn draw_mesh_tasks(&self,
+                                  command_buffer: vk::CommandBuffer,
+                                  group_count: [u32; 3]) -> Result<()> {
+        let _trace = trace::span("EasyMeshPipeline::draw_mesh_tasks",
+                                 || format!("group_count: {:?}", group_count));
+        let (max_count, max_total_count) = if self.has_task_shader {
+            (self.limits.max_task_work_group_count,
+             self.limits.max_task_work_group_total_count)
+        } else {
+            (self.limits.max_mesh_work_group_count,
+             self.limits.max_mesh_work_group_total_count)
+        };
+        let total_count =
+            group_count.iter().map(|&c| u64::from(c)).product::<u64>();
+        if group_count.iter().zip(max_count.iter()).any(|(c, max)| c > max)
+           || total_count > u64::from(max_total_count)
+        {
+            return Err(Error::InvalidArgument(format!(
+                "Mesh shading draw of {:?} work groups exceeds the device's \
+                 limits ({:?} work groups, {} in total)",
+                group_count, max_count, max_total_count
+            )));
+        }
+        (self.draw_mesh_tasks)(command_buffer,
+                               group_count[0],
+                               group_count[1],
+                               group_count[2]);
+        Ok(())
+    }
+
+    /// Create the raw pipeline, once the shader stages are known
+    unsafe fn create_pipeline(
+        device: &Arc<Device>,
+        stages: &[vk::PipelineShaderStageCreateInfo],
+        layout: &(dyn PipelineLayoutAbstract + Send + Sync),
+        subpass: &Subpass<EasyRenderPass>,
+    ) -> Result<vk::Pipeline> {
+        let viewport_state = vk::PipelineViewportStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_VIEWPORT_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            viewportCount: 1,
+            pViewports: ptr::null(),
+            scissorCount: 1,
+            pScissors: ptr::null(),
+        };
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            depthClampEnable: 0,
+            rasterizerDiscardEnable: 0,
+            polygonMode: vk::POLYGON_MODE_FILL,
+            cullMode: vk::CULL_MODE_NONE,
+            frontFace: vk::FRONT_FACE_COUNTER_CLOCKWISE,
+            depthBiasEnable: 0,
+            depthBiasConstantFactor: 0.0,
+            depthBiasClamp: 0.0,
+            depthBiasSlopeFactor: 0.0,
+            lineWidth: 1.0,
+        };
+        let multisample_state = vk::PipelineMultisampleStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            rasterizationSamples: subpass.num_samples().unwrap_or(1),
+            sampleShadingEnable: 0,
+            minSampleShading: 1.0,
+            pSampleMask: ptr::null(),
+            alphaToCoverageEnable: 0,
+            alphaToOneEnable: 0,
+        };
+        let mut depth_stencil_state: vk::PipelineDepthStencilStateCreateInfo =
+            mem::zeroed();
+        depth_stencil_state.sType =
+            vk::STRUCTURE_TYPE_PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO;
+        depth_stencil_state.maxDepthBounds = 1.0;
+        let mut blend_attachment: vk::PipelineColorBlendAttachmentState =
+            mem::zeroed();
+        blend_attachment.colorWriteMask = vk::COLOR_COMPONENT_R_BIT
+                                          | vk::COLOR_COMPONENT_G_BIT
+                                          | vk::COLOR_COMPONENT_B_BIT
+                                          | vk::COLOR_COMPONENT_A_BIT;
+        let blend_attachments =
+            vec![blend_attachment; subpass.num_color_attachments() as usize];
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            logicOpEnable: 0,
+            logicOp: vk::LOGIC_OP_CLEAR,
+            attachmentCount: blend_attachments.len() as u32,
+            pAttachments: blend_attachments.as_ptr(),
+            blendConstants: [0.0; 4],
+        };
+        let dynamic_states = [vk::DYNAMIC_STATE_VIEWPORT,
+                              vk::DYNAMIC_STATE_SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_DYNAMIC_STATE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            dynamicStateCount: dynamic_states.len() as u32,
+            pDynamicStates: dynamic_states.as_ptr(),
+        };
+
+        // Mesh shading pipelines have no vertex input and input assembly
+        let create_info = vk::GraphicsPipelineCreateInfo {
+            sType: vk::STRUCTURE_TYPE_GRAPHICS_PIPELINE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            stageCount: stages.len() as u32,
+            pStages: stages.as_ptr(),
+            pVertexInputState: ptr::null(),
+            pInputAssemblyState: ptr::null(),
+            pTessellationState: ptr::null(),
+            pViewportState: &viewport_state,
+            pRasterizationState: &rasterization_state,
+            pMultisampleState: &multisample_state,
+            pDepthStencilState: &depth_stencil_state,
+            pColorBlendState: &color_blend_state,
+            pDynamicState: &dynamic_state,
+            layout: layout.sys().internal_object(),
+            renderPass: subpass.render_pass().inner().internal_object(),
+            subpass: subpass.index(),
+            basePipelineHandle: 0,
+            basePipelineIndex: -1,
+        };
+        let mut pipeline = 0;
+        check_result(
+            device.pointers().CreateGraphicsPipelines(device.internal_object(),
+                                                      0,
+                                                      1,
+                                                      &create_info,
+                                                      ptr::null(),
+                                                      &mut pipeline),
+            "vkCreateGraphicsPipelines"
+        )?;
+        Ok(pipeline)
+    }
+}
+
+impl Drop for EasyMeshPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.pointers().DestroyPipeline(
+                self.device.internal_object(),
+                self.pipeline,
+                ptr::null()
+            );
+        }
+    }
+}
+
+
+/// Execution modes of a task or mesh shader entry point
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct ExecutionModes {
+    /// Work group size
+    local_size: [u32; 3],
+
+    /// Maximal number of vertices emitted by a work group (mesh shaders only)
+    output_vertices: u32,
+
+    /// Maximal number of primitives emitted by a work group (mesh shaders
+    /// only)
+    output_primitives: u32,
+}
+
+impl ExecutionModes {
+    /// Find the entry point of some execution model in a SPIR-V module, and
+    /// extract its name and execution modes
+    fn of_entry_point(spirv: &[u8],
+                      execution_model: u32,
+                      stage: &str) -> Result<(CString, Self)> {
+        let instructions = shader::spirv_instructions(spirv)?;
+        let (entry_point, name) = instructions.iter().filter_map(
+            |&(op, ref args)| {
+                if op == spirv::OP_ENTRY_POINT && args.len() >= 3
+                   && args[0] == execution_model
+                {
+                    Some((args[1], shader::spirv_string(&args[2..])))
+                } else {
+                    None
+                }
+            }
+        ).next().ok_or_else(|| Error::InvalidArgument(format!(
+            "The {} shader has no {} entry point", stage, stage
+        )))?;
+
+        let mut modes = ExecutionModes::default();
+        for &(op, ref args) in &instructions {
+            if op != spirv::OP_EXECUTION_MODE || args.len() < 3
+               || args[0] != entry_point
+            {
+                continue;
+            }
+            match args[1] {
+                spirv::EXECUTION_MODE_LOCAL_SIZE if args.len() >= 5 => {
+                    modes.local_size.copy_from_slice(&args[2..5]);
+                },
+                spirv::EXECUTION_MODE_OUTPUT_VERTICES => {
+                    modes.output_vertices = args[2];
+                },
+                spirv::EXECUTION_MODE_OUTPUT_PRIMITIVES => {
+                    modes.output_primitives = args[2];
+                },
+                _ => {},
+            }
+        }
+        let name = CString::new(name).expect("SPIR-V strings have no NUL");
+        Ok((name, modes))
+    }
+
+    /// Check the work group size against the limits of a shader stage
+    ///
+    /// Work group sizes which are set via specialization constants
+    /// (LocalSizeId) are not seen here, and therefore not checked.
+    ///
+    fn check_local_size(&self,
+                        stage: &str,
+                        max_size: [u32; 3],
+                        max_invocations: u32,
+                        violations: &mut Vec<String>) {
+        let size = self.local_size;
+        if size.iter().zip(max_size.iter()).any(|(s, max)| s > max) {
+            violations.push(format!(
+                "{} shader work group size is {:?}, but the maximum is {:?}",
+                stage, size, max_size
+            ));
+        }
+        let invocations = size.iter().map(|&s| u64::from(s)).product::<u64>();
+        if invocations > u64::from(max_invocations) {
+            violations.push(format!(
+                "{} shader work groups have {} invocations, but the maximum is \
+                 {}", stage, invocations, max_invocations
+            ));
+        }
+    }
+}
+
+
+/// VkPhysicalDeviceMeshShaderFeaturesEXT, which vk-sys does not know about
+#[repr(C)]
+struct RawMeshShaderFeatures {
+    s_type: u32,
+    p_next: *mut c_void,
+    task_shader: u32,
+    mesh_shader: u32,
+    multiview_mesh_shader: u32,
+    primitive_fragment_shading_rate_mesh_shader: u32,
+    mesh_shader_queries: u32,
+}
+
+unsafe impl FeatureStruct for RawMeshShaderFeatures {}
+
+/// Type of a MeshShaderLimits member, which has a raw Vulkan counterpart
+trait RawLimit {
+    /// Raw Vulkan type
+    type Raw;
+
+    /// Convert from the raw Vulkan type
+    fn from_raw(raw: Self::Raw) -> Self;
+}
+
+impl RawLimit for u32 {
+    type Raw = u32;
+    fn from_raw(raw: u32) -> Self { raw }
+}
+
+impl RawLimit for bool {
+    type Raw = u32;
+    fn from_raw(raw: u32) -> Self { raw != 0 }
+}
+
+impl RawLimit for [u32; 3] {
+    type Raw = [u32; 3];
+    fn from_raw(raw: [u32; 3]) -> Self { raw }
+}
@@ -0,0 +1,491 @@
+//! Conveniences for building Vulkan pipelines and caching them on disk
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    fmt::Debug,
+    fs,
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use vulkano::{
+    device::Device,
+    pipeline::cache::PipelineCache,
+};
+
+
+/// Declaration of a pipeline which should be built ahead of time
+///
+/// This wraps a named pipeline builder closure, so that vulkanoob can time its
+/// execution, run it on a background thread if asked to, and hand it the
+/// pipeline cache which the pipeline should be built with (if any).
+///
+pub struct PipelineDescription<P> {
+    /// Name of the pipeline, used for reporting purposes
+    name: String,
+
+    /// Closure which builds the pipeline from an optional pipeline cache
+    build: BatchBuilder<P>,
+}
+
+impl<P> PipelineDescription<P> {
+    /// Declare a pipeline, given a name and the code needed to build it from
+    /// an optional pipeline cache
+    pub fn new(
+        name: impl Into<String>,
+        build: impl FnOnce(Option<Arc<PipelineCache>>) -> Result<P>
+                    + Send + 'static
+    ) -> Self {
+        PipelineDescription {
+            name: name.into(),
+            build: Box::new(build),
+        }
+    }
+
+    /// Name of the pipeline
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Build the pipeline, measuring how long it takes
+    fn build(
+        self,
+        cache: Option<Arc<PipelineCache>>
+    ) -> Result<PrewarmedPipeline<P>> {
+        let start = Instant::now();
+        let pipeline = (self.build)(cache)?;
+        let compile_time = start.elapsed();
+        info!("Pipeline \"{}\" was built in {:?}", self.name, compile_time);
+        Ok(PrewarmedPipeline {
+            name: self.name,
+            compile_time,
+            pipeline,
+        })
+    }
+}
+
+
+/// A pipeline that was built by prewarm() or by a PipelineBatch, along with
+/// its build time
+pub struct PrewarmedPipeline<P> {
+    /// Name of the pipeline
+    pub name: String,
+
+    /// Time that it took to build the pipeline
+    pub compile_time: Duration,
+
+    /// The pipeline itself
+    pub pipeline: P,
+}
+
+
+/// Build a set of pipelines up front
+///
+/// Pipeline compilation can be slow, and if it happens on first use, it will
+/// cause a hitch in your application. This function lets you build all of
+/// your pipelines ahead of time, while reporting how long each of them took to
+/// build so that you can tell which shaders are slow to compile.
+///
+/// If a persistent pipeline cache is provided, it is handed to every pipeline
+/// builder, and saved to disk once all pipelines are built, so that the
+/// warm-up work also benefits the next runs of the application.
+///
+/// If "parallel" is set, the pipelines are built by the bounded worker pool
+/// of a PipelineBatch, with its default number of workers.
+///
+/// Pipelines are returned in the order in which they were declared.
+///
+pub fn prewarm<P: Send + 'static>(
+    descriptions: impl IntoIterator<Item=PipelineDescription<P>>,
+    cache: Option<&EasyPipelineCache>,
+    parallel: bool,
+) -> Result<Vec<PrewarmedPipeline<P>>> {
+    let descriptions = descriptions.into_iter().collect::<Vec<_>>();
+    let _trace = trace::span("pipeline::prewarm", || {
+        format!("pipelines: {:?}, cached: {}, parallel: {}",
+                descriptions.iter().map(|d| d.name()).collect::<Vec<_>>(),
+                cache.is_some(),
+                parallel)
+    });
+
+    if !parallel {
+        let shared_cache = cache.map(|cache| cache.cache().clone());
+        let pipelines =
+            descriptions.into_iter()
+                        .map(|desc| desc.build(shared_cache.clone()))
+                        .collect::<Result<Vec<_>>>()?;
+        if let Some(cache) = cache {
+            if let Err(e) = cache.save() {
+                warn!("Failed to save pipeline cache to {:?}: {}",
+                      cache.path(), e);
+            }
+        }
+        return Ok(pipelines);
+    }
+
+    let mut names = Vec::with_capacity(descriptions.len());
+    let mut batch = PipelineBatch::new();
+    for (index, desc) in descriptions.into_iter().enumerate() {
+        let PipelineDescription { name, build } = desc;
+        names.push(name);
+        batch.add(index, build)?;
+    }
+    let mut pipelines = batch.compile(cache, |_progress| {})?;
+    Ok(names.into_iter().enumerate().map(|(index, name)| {
+        let prewarmed = pipelines.remove(&index)
+                                 .expect("Batch should build every pipeline");
+        info!("Pipeline \"{}\" was built in {:?}",
+              name, prewarmed.compile_time);
+        PrewarmedPipeline { name, ..prewarmed }
+    }).collect())
+}
+
+
+/// Builder closure of a PipelineBatch pipeline, which is handed the batch's
+/// pipeline cache (if any)
+type BatchBuilder<P> =
+    Box<dyn FnOnce(Option<Arc<PipelineCache>>) -> Result<P> + Send>;
+
+/// Number of worker threads which a PipelineBatch uses by default
+const DEFAULT_WORKERS: usize = 4;
+
+/// A batch of pipelines to be compiled in parallel
+///
+/// This is the engine behind prewarm()'s parallel mode. A PipelineBatch
+/// spreads the compilation work across a bounded pool of worker threads, hands
+/// its pipeline cache to every builder, and reports progress as pipelines are
+/// completed. Pipelines are identified by a user-supplied id, which is also
+/// used to key the results.
+///
+/// Pipeline derivatives (VK_PIPELINE_CREATE_DERIVATIVE_BIT) are not used, as
+/// vulkano does not expose pipeline creation flags. Sharing a pipeline cache
+/// across the batch gets most drivers the same benefits.
+///
+pub struct PipelineBatch<K, P> {
+    /// Pipelines to be built, along with their ids
+    jobs: Vec<(K, BatchBuilder<P>)>,
+
+    /// Maximal number of worker threads
+    workers: usize,
+}
+
+impl<K, P> PipelineBatch<K, P>
+    where K: Debug + Eq + Hash + Send + 'static,
+          P: Send + 'static
+{
+    /// Start an empty batch of pipelines
+    pub fn new() -> Self {
+        PipelineBatch {
+            jobs: Vec::new(),
+            workers: DEFAULT_WORKERS,
+        }
+    }
+
+    /// Set the maximal number of worker threads (at least 1)
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Add a pipeline to the batch, given an id and the code needed to build
+    /// it from an optional pipeline cache
+    pub fn add(
+        &mut self,
+        id: K,
+        build: impl FnOnce(Option<Arc<PipelineCache>>) -> Result<P>
+                    + Send + 'static
+    ) -> Result<()> {
+        if self.jobs.iter().any(|(other_id, _)| *other_id == id) {
+            return Err(Error::InvalidArgument(
+                format!("Pipeline {:?} was added to the batch twice", id)
+            ));
+        }
+        self.jobs.push((id, Box::new(build)));
+        Ok(())
+    }
+
+    /// Number of pipelines in the batch
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Truth that the batch contains no pipeline
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Build all pipelines of the batch
+    ///
+    /// The progress callback is invoked on the calling thread every time a
+    /// pipeline is done building, successfully or not. If a pipeline fails to
+    /// build, the pipelines which were not started yet are skipped and the
+    /// first error is returned. If a pipeline cache is provided, it is saved
+    /// to disk once all pipelines are built.
+    ///
+    pub fn compile(
+        self,
+        cache: Option<&EasyPipelineCache>,
+        mut progress: impl FnMut(CompileProgress<K>)
+    ) -> Result<HashMap<K, PrewarmedPipeline<P>>> {
+        let total = self.jobs.len();
+        let _trace = trace::span("PipelineBatch::compile", || {
+            format!("pipelines: {}, workers: {}, cached: {}",
+                    total, self.workers, cache.is_some())
+        });
+
+        // Spawn the workers, which pull pipelines from a shared queue until
+        // it is empty or an error occurs
+        let queue = Arc::new(Mutex::new(self.jobs
+                                            .into_iter()
+                                            .collect::<VecDeque<_>>()));
+        let abort = Arc::new(AtomicBool::new(false));
+        let shared_cache = cache.map(|cache| cache.cache().clone());
+        let (sender, receiver) = mpsc::channel();
+        let workers = (0..self.workers.min(total)).map(|_| {
+            let queue = queue.clone();
+            let abort = abort.clone();
+            let cache = shared_cache.clone();
+            let sender = sender.clone();
+            thread::spawn(move || {
+                while !abort.load(Ordering::Relaxed) {
+                    let job = queue.lock()
+                                   .expect("Pipeline queue was poisoned")
+                                   .pop_front();
+                    let (id, build) = match job {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let start = Instant::now();
+                    let result = build(cache.clone());
+                    if sender.send((id, start.elapsed(), result)).is_err() {
+                        break;
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+        drop(sender);
+
+        // Collect the pipelines as they are built
+        let mut pipelines = HashMap::with_capacity(total);
+        let mut first_error = None;
+        let mut completed = 0;
+        for (id, compile_time, result) in receiver {
+            completed += 1;
+            progress(CompileProgress {
+                id: &id,
+                compile_time,
+                completed,
+                total,
+                succeeded: result.is_ok(),
+            });
+            match result {
+                Ok(pipeline) => {
+                    info!("Pipeline {:?} was built in {:?} ({}/{})",
+                          id, compile_time, completed, total);
+                    pipelines.insert(id, PrewarmedPipeline {
+                        name: format!("{:?}", id),
+                        compile_time,
+                        pipeline,
+                    });
+                },
+                Err(e) => {
+                    error!("Failed to build pipeline {:?}: {}", id, e);
+                    abort.store(true, Ordering::Relaxed);
+                    first_error = first_error.or(Some(e));
+                }
+            }
+        }
+
+        // Wait for the workers, checking for panicked pipeline builders
+        let panicked = workers.into_iter()
+                              .map(|worker| worker.join().is_err())
+                              .fold(false, |acc, panicked| acc || panicked);
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+        if panicked {
+            return Err(format_err!("A pipeline builder panicked").into());
+        }
+
+        // Persist the pipeline cache for the next run
+        if let Some(cache) = cache {
+            if let Err(e) = cache.save() {
+                warn!("Failed to save pipeline cache to {:?}: {}",
+                      cache.path(), e);
+            }
+        }
+        Ok(pipelines)
+    }
+}
+
+impl<K, P> Default for PipelineBatch<K, P>
+    where K: Debug + Eq + Hash + Send + 'static,
+          P: Send + 'static
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Progress report of a PipelineBatch compilation
+#[derive(Debug)]
+pub struct CompileProgress<'a, K: 'a> {
+    /// Id of the pipeline which was just completed
+    pub id: &'a K,
+
+    /// Time that it took to build this pipeline
+    pub compile_time: Duration,
+
+    /// Number of pipelines which were completed so far, including this one
+    pub completed: usize,
+
+    /// Number of pipelines in the batch
+    pub total: usize,
+
+    /// Truth that the pipeline was built successfully
+    pub succeeded: bool,
+}
+
+
+/// A pipeline cache which is persisted on disk across application runs
+///
+/// The cache is loaded from disk on creation and saved back to disk when it is
+/// dropped, so that repeated runs of a prototype do not pay the full pipeline
+/// compilation cost every time.
+///
+/// Cache files are named after the UUID and driver version of the physical
+/// device, so that the data of one device or driver is never fed to another.
+///
+pub struct EasyPipelineCache {
+    /// The vulkano pipeline cache
+    cache: Arc<PipelineCache>,
+
+    /// File where the cache data is stored
+    path: PathBuf,
+}
+
+impl EasyPipelineCache {
+    /// Load the pipeline cache of a device from the default cache directory
+    ///
+    /// See default_cache_directory() for more information about where this
+    /// directory is located.
+    ///
+    pub fn new(device: &Arc<Device>) -> Result<Self> {
+        Self::with_directory(device, default_cache_directory())
+    }
+
+    /// Load the pipeline cache of a device from a user-specified directory
+    ///
+    /// If there is no cache data for this device yet, or if it cannot be
+    /// loaded, an empty cache is created instead.
+    ///
+    pub fn with_directory(device: &Arc<Device>,
+                          directory: impl AsRef<Path>) -> Result<Self> {
+        let directory = directory.as_ref();
+        let _trace = trace::span("EasyPipelineCache::with_directory",
+                                 || format!("directory: {:?}", directory));
+
+        // Name the cache file after the device UUID and driver version
+        let physical_device = device.physical_device();
+        let uuid = physical_device.uuid()
+                                  .iter()
+                                  .map(|byte| format!("{:02x}", byte))
+                                  .collect::<String>();
+        let path = directory.join(
+            format!("pipeline-cache-{}-{}.bin",
+                    uuid,
+                    physical_device.driver_version())
+        );
+
+        // Load the cache data if there is any, otherwise start afresh
+        let cache = match fs::read(&path) {
+            Ok(data) => {
+                info!("Loading pipeline cache from {:?}", path);
+                // Safe because the cache file is keyed by device UUID and
+                // driver version, and the driver validates the cache header.
+                match unsafe { PipelineCache::with_data(device.clone(),
+                                                        &data) } {
+                    Ok(cache) => cache,
+                    Err(e) => {
+                        warn!("Failed to load pipeline cache ({}), starting \
+                               with an empty one", e);
+                        PipelineCache::empty(device.clone())?
+                    }
+                }
+            },
+            Err(_) => {
+                info!("No pipeline cache at {:?}, starting with an empty one",
+                      path);
+                PipelineCache::empty(device.clone())?
+            }
+        };
+        Ok(EasyPipelineCache { cache, path })
+    }
+
+    /// Access the vulkano pipeline cache, for use in pipeline creation
+    pub fn cache(&self) -> &Arc<PipelineCache> {
+        &self.cache
+    }
+
+    /// File where the cache data is stored
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save the cache contents to disk right away
+    ///
+    /// This is done automatically when the EasyPipelineCache is dropped, but
+    /// you may want to do it earlier in order to handle errors.
+    ///
+    pub fn save(&self) -> Result<()> {
+        let _trace = trace::span("EasyPipelineCache::save",
+                                 || format!("path: {:?}", self.path));
+        let data = self.cache.get_data()?;
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(&self.path, data)?;
+        info!("Saved pipeline cache to {:?}", self.path);
+        Ok(())
+    }
+}
+
+impl Drop for EasyPipelineCache {
+    /// Save the cache contents to disk, logging any error
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            warn!("Failed to save pipeline cache to {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Platform-default directory for vulkanoob's pipeline caches
+///
+/// This is $XDG_CACHE_HOME/vulkanoob or ~/.cache/vulkanoob on Unix,
+/// %LOCALAPPDATA%\vulkanoob on Windows, and a "vulkanoob" subdirectory of the
+/// system's temporary directory if none of these can be determined.
+///
+pub fn default_cache_directory() -> PathBuf {
+    let base = env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| {
+            PathBuf::from(home).join(".cache")
+        }))
+        .unwrap_or_else(env::temp_dir);
+    base.join("vulkanoob")
+}
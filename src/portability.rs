@@ -0,0 +1,101 @@
+//! Support for Vulkan portability implementations such as MoltenVK
+//!
+//! Implementations which are layered on top of another graphics API (e.g.
+//! MoltenVK on macOS and iOS) do not support the full Vulkan API. They
+//! advertise this via the VK_KHR_portability_subset device extension, which
+//! must then be enabled on device creation, and newer Vulkan loaders only list
+//! them if the VK_KHR_portability_enumeration instance extension is enabled.
+//!
+//! vulkanoob handles these extensions automatically. You can also decide
+//! whether portability devices should be considered during device selection.
+//!
+//! Note that vulkano does not let us set the instance creation flag which
+//! fully enables portability enumeration, so with recent loaders, portability
+//! devices may only show up if the loader is configured to expose them.
+
+use std::{
+    ffi::CString,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use vulkano::{
+    device::{
+        DeviceExtensions,
+        RawDeviceExtensions,
+    },
+    instance::{
+        PhysicalDevice,
+        RawInstanceExtensions,
+    },
+};
+
+
+/// Instance extension which enables the enumeration of portability devices
+pub const PORTABILITY_ENUMERATION_EXTENSION: &str =
+    "VK_KHR_portability_enumeration";
+
+/// Device extension which portability devices advertise, and which must then
+/// be enabled on device creation
+pub const PORTABILITY_SUBSET_EXTENSION: &str = "VK_KHR_portability_subset";
+
+/// Global switch telling whether portability devices may be selected
+static ALLOWED: AtomicBool = AtomicBool::new(true);
+
+
+/// Allow or forbid the selection of portability devices
+///
+/// Portability devices are allowed by default, so that vulkanoob works out of
+/// the box on MoltenVK. Forbidding them makes easy_device_filter() reject
+/// them, which can be useful if you rely on the full Vulkan feature set.
+///
+pub fn set_allowed(allowed: bool) {
+    ALLOWED.store(allowed, Ordering::Relaxed);
+}
+
+/// Truth that portability devices may be selected
+pub fn is_allowed() -> bool {
+    ALLOWED.load(Ordering::Relaxed)
+}
+
+/// Truth that a physical device is a portability device
+pub fn is_portability_device(device: PhysicalDevice) -> bool {
+    RawDeviceExtensions::supported_by_device(device)
+                        .iter()
+                        .any(|ext| {
+                            ext.as_bytes()
+                                == PORTABILITY_SUBSET_EXTENSION.as_bytes()
+                        })
+}
+
+
+/// Add the portability enumeration extension to a set of instance extensions,
+/// if the Vulkan implementation supports it
+pub(crate) fn add_instance_extensions(
+    extensions: &mut RawInstanceExtensions,
+    supported: &RawInstanceExtensions,
+) {
+    if supported.iter().any(|ext| {
+        ext.as_bytes() == PORTABILITY_ENUMERATION_EXTENSION.as_bytes()
+    }) {
+        info!("Enabling {} to enumerate portability devices",
+              PORTABILITY_ENUMERATION_EXTENSION);
+        extensions.insert(CString::new(PORTABILITY_ENUMERATION_EXTENSION)
+                                  .expect("Extension name has no NUL"));
+    }
+}
+
+/// Device extensions to be enabled on a physical device, including the
+/// portability subset extension if the device needs it
+pub(crate) fn device_extensions(
+    device: PhysicalDevice,
+    extensions: &DeviceExtensions,
+) -> RawDeviceExtensions {
+    let mut raw_extensions = RawDeviceExtensions::from(extensions);
+    if is_portability_device(device) {
+        info!("Device \"{}\" is a portability device, enabling {}",
+              device.name(), PORTABILITY_SUBSET_EXTENSION);
+        raw_extensions.insert(CString::new(PORTABILITY_SUBSET_EXTENSION)
+                                      .expect("Extension name has no NUL"));
+    }
+    raw_extensions
+}
@@ -0,0 +1,122 @@
+//! Ready-made physical device and queue family preferences
+//!
+//! Device preferences can be passed as the "preference" parameter of
+//! EasyInstance::select_physical_device(), either directly or combined with
+//! compose_preferences().
+//!
+//! Queue family preferences can be passed as the "preference" parameter of
+//! EasyPhysicalDevice::setup_single_queue_device(), either directly or
+//! combined with compose_queue_preferences().
+
+use std::cmp::Ordering;
+
+use vulkano::instance::{
+    PhysicalDevice,
+    PhysicalDeviceType,
+    QueueFamily,
+};
+
+
+/// Prefer discrete GPUs, then integrated GPUs, virtual GPUs, and finally CPUs
+pub fn prefer_discrete_gpu(dev1: PhysicalDevice,
+                           dev2: PhysicalDevice) -> Ordering {
+    let rank = |dev: PhysicalDevice| match dev.ty() {
+        PhysicalDeviceType::DiscreteGpu => 4,
+        PhysicalDeviceType::IntegratedGpu => 3,
+        PhysicalDeviceType::VirtualGpu => 2,
+        PhysicalDeviceType::Cpu => 1,
+        PhysicalDeviceType::Other => 0,
+    };
+    rank(dev1).cmp(&rank(dev2))
+}
+
+/// Prefer devices with the largest amount of device-local memory
+pub fn prefer_most_device_memory(dev1: PhysicalDevice,
+                                 dev2: PhysicalDevice) -> Ordering {
+    device_local_memory(dev1).cmp(&device_local_memory(dev2))
+}
+
+/// Prefer devices which support the newest Vulkan API version
+pub fn prefer_newest_api(dev1: PhysicalDevice,
+                         dev2: PhysicalDevice) -> Ordering {
+    dev1.api_version().cmp(&dev2.api_version())
+}
+
+/// Combine two device preferences, using the second one to break ties
+///
+/// Calls can be nested to combine more than two preferences.
+///
+pub fn compose_preferences(
+    mut first: impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering,
+    mut second: impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering
+) -> impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering {
+    move |dev1: PhysicalDevice, dev2: PhysicalDevice| -> Ordering {
+        first(dev1, dev2).then_with(|| second(dev1, dev2))
+    }
+}
+
+
+/// Total amount of device-local memory of a device, in bytes
+pub fn device_local_memory(dev: PhysicalDevice) -> usize {
+    dev.memory_heaps()
+       .filter(|heap| heap.is_device_local())
+       .map(|heap| heap.size())
+       .sum()
+}
+
+
+/// Prefer queue families dedicated to transfers (no graphics or compute)
+pub fn prefer_dedicated_transfer(family1: &QueueFamily,
+                                 family2: &QueueFamily) -> Ordering {
+    let is_dedicated = |family: &QueueFamily| {
+        family.supports_transfers() && !family.supports_graphics()
+                                    && !family.supports_compute()
+    };
+    is_dedicated(family1).cmp(&is_dedicated(family2))
+}
+
+/// Prefer queue families dedicated to compute (no graphics)
+pub fn prefer_dedicated_compute(family1: &QueueFamily,
+                                family2: &QueueFamily) -> Ordering {
+    let is_dedicated = |family: &QueueFamily| {
+        family.supports_compute() && !family.supports_graphics()
+    };
+    is_dedicated(family1).cmp(&is_dedicated(family2))
+}
+
+/// Prefer the most specialized queue families, which have the fewest
+/// capabilities among graphics, compute, transfers and sparse binding
+pub fn prefer_fewest_capabilities(family1: &QueueFamily,
+                                  family2: &QueueFamily) -> Ordering {
+    queue_family_capabilities(family2).cmp(&queue_family_capabilities(family1))
+}
+
+/// Prefer queue families with the largest number of queues
+pub fn prefer_most_queues(family1: &QueueFamily,
+                          family2: &QueueFamily) -> Ordering {
+    family1.queues_count().cmp(&family2.queues_count())
+}
+
+/// Combine two queue family preferences, using the second to break ties
+///
+/// Calls can be nested to combine more than two preferences.
+///
+pub fn compose_queue_preferences(
+    mut first: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering,
+    mut second: impl FnMut(&QueueFamily, &QueueFamily) -> Ordering
+) -> impl FnMut(&QueueFamily, &QueueFamily) -> Ordering {
+    move |family1: &QueueFamily, family2: &QueueFamily| -> Ordering {
+        first(family1, family2).then_with(|| second(family1, family2))
+    }
+}
+
+
+/// Number of capabilities of a queue family, used to find specialized ones
+pub fn queue_family_capabilities(family: &QueueFamily) -> usize {
+    [
+        family.supports_graphics(),
+        family.supports_compute(),
+        family.supports_transfers(),
+        family.supports_sparse_binding(),
+    ].iter().filter(|&&supported| supported).count()
+}
@@ -0,0 +1,357 @@
+//! Synthetic device capabilities, for testing device selection logic
+//!
+//! Device filters and preferences take vulkano PhysicalDevices, which can
+//! only be obtained from an actual Vulkan implementation. That makes it hard
+//! to check that your selection logic does the right thing on GPUs which you
+//! do not own. Instead, you can write it against CapabilityProfiles:
+//!
+//! - In unit tests, build profiles by hand or load them from JSON (with the
+//!   "serde-export" feature), then check which one select_profile() picks.
+//! - In production, turn your profile-based logic into a regular device
+//!   filter and preference using profile_filter() and profile_preference().
+//!
+//! Every field of a profile has a default value, so a JSON profile only needs
+//! to specify what your selection logic actually looks at. DeviceReports,
+//! e.g. saved on another machine, can also be converted into profiles.
+//!
+//! ProfileRequirements and check_profiles() tell which profiles some basic
+//! requirements would exclude, and why. See also the profile_db module.
+
+use ::{
+    diagnosis::{
+        DeviceDiagnosis,
+        RejectionReason,
+    },
+    limits::LimitRequirements,
+    report::{
+        self,
+        DeviceReport,
+        MemoryHeapReport,
+        QueueFamilyReport,
+    },
+    Result,
+};
+
+#[cfg(feature = "serde-export")]
+use serde_json;
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+};
+
+use vulkano::instance::{
+    DeviceExtensions,
+    Features,
+    PhysicalDevice,
+    PhysicalDeviceType,
+    Version,
+};
+
+
+/// Capabilities of a real or imaginary physical device
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-export", serde(default))]
+pub struct CapabilityProfile {
+    /// Name of the device
+    pub name: String,
+
+    /// Type of device, as in DeviceReport ("DiscreteGpu", "Cpu"...)
+    pub device_type: String,
+
+    /// Supported Vulkan API version (e.g. "1.2.0")
+    pub api_version: String,
+
+    /// PCI vendor identifier
+    pub pci_vendor_id: u32,
+
+    /// Supported device extensions
+    pub extensions: Vec<String>,
+
+    /// Supported device features, in vulkano's naming convention
+    pub features: Vec<String>,
+
+    /// Device limits, as (name, value) pairs using vulkano's snake_case names
+    /// (e.g. "max_image_dimension_2d")
+    pub limits: Vec<(String, String)>,
+
+    /// Queue families
+    pub queue_families: Vec<QueueFamilyReport>,
+
+    /// Memory heaps
+    pub memory_heaps: Vec<MemoryHeapReport>,
+}
+
+impl CapabilityProfile {
+    /// Probe the capabilities of a physical device
+    ///
+    /// This builds a full DeviceReport, so it is comparatively expensive.
+    ///
+    pub fn of_device(device: PhysicalDevice) -> Result<Self> {
+        DeviceReport::new(device).map(Self::from)
+    }
+
+    /// Load a profile from JSON
+    #[cfg(feature = "serde-export")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Type of device, if it is a known one
+    pub fn device_type(&self) -> Option<PhysicalDeviceType> {
+        match &self.device_type[..] {
+            "DiscreteGpu" => Some(PhysicalDeviceType::DiscreteGpu),
+            "IntegratedGpu" => Some(PhysicalDeviceType::IntegratedGpu),
+            "VirtualGpu" => Some(PhysicalDeviceType::VirtualGpu),
+            "Cpu" => Some(PhysicalDeviceType::Cpu),
+            "Other" => Some(PhysicalDeviceType::Other),
+            _ => None,
+        }
+    }
+
+    /// Supported Vulkan API version, if it is valid
+    pub fn api_version(&self) -> Option<Version> {
+        let mut parts = self.api_version.split('.').map(|part| part.parse());
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), patch, None) => Some(Version {
+                major,
+                minor,
+                patch: match patch {
+                    Some(Ok(patch)) => patch,
+                    Some(Err(_)) => return None,
+                    None => 0,
+                },
+            }),
+            _ => None,
+        }
+    }
+
+    /// Truth that a device extension is supported
+    pub fn supports_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|ext| ext == name)
+    }
+
+    /// Truth that a device feature is supported
+    pub fn supports_feature(&self, name: &str) -> bool {
+        self.features.iter().any(|feature| feature == name)
+    }
+
+    /// Look up a device limit by its snake_case name
+    pub fn limit(&self, name: &str) -> Option<&str> {
+        self.limits.iter()
+                   .find(|&&(ref limit, _)| limit == name)
+                   .map(|&(_, ref value)| &value[..])
+    }
+
+    /// Look up a scalar integer device limit by its snake_case name
+    pub fn limit_u64(&self, name: &str) -> Option<u64> {
+        self.limit(name).and_then(|value| value.parse().ok())
+    }
+
+    /// Total amount of device-local memory, in bytes
+    pub fn device_local_memory(&self) -> usize {
+        self.memory_heaps.iter()
+                         .filter(|heap| heap.device_local)
+                         .map(|heap| heap.size)
+                         .sum()
+    }
+
+    /// Truth that some queue family supports graphics
+    pub fn supports_graphics(&self) -> bool {
+        self.queue_families.iter().any(|family| family.graphics)
+    }
+
+    /// Truth that some queue family supports compute
+    pub fn supports_compute(&self) -> bool {
+        self.queue_families.iter().any(|family| family.compute)
+    }
+}
+
+impl From<DeviceReport> for CapabilityProfile {
+    fn from(report: DeviceReport) -> Self {
+        CapabilityProfile {
+            name: report.name,
+            device_type: report.device_type,
+            api_version: report.api_version,
+            pci_vendor_id: report.pci_vendor_id,
+            extensions: report.extensions,
+            features: report.features,
+            limits: report.limits,
+            queue_families: report.queue_families,
+            memory_heaps: report.memory_heaps,
+        }
+    }
+}
+
+
+/// Basic requirements which capability profiles can be checked against
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileRequirements {
+    /// Minimal Vulkan API version
+    pub api_version: Version,
+
+    /// Required device features
+    pub features: Features,
+
+    /// Required device extensions
+    pub extensions: DeviceExtensions,
+
+    /// Minimal device limits
+    pub limits: LimitRequirements,
+
+    /// Truth that a queue family with graphics support is needed
+    pub graphics: bool,
+
+    /// Truth that a queue family with compute support is needed
+    pub compute: bool,
+}
+
+impl Default for ProfileRequirements {
+    /// Requirements which any Vulkan 1.x device satisfies
+    fn default() -> Self {
+        ProfileRequirements {
+            api_version: Version { major: 1, minor: 0, patch: 0 },
+            features: Features::none(),
+            extensions: DeviceExtensions::none(),
+            limits: LimitRequirements::default(),
+            graphics: false,
+            compute: false,
+        }
+    }
+}
+
+impl ProfileRequirements {
+    /// Tell why a profile does not meet the requirements, if it doesn't
+    ///
+    /// Profiles with an invalid API version are assumed to support any
+    /// version, and limits which a profile does not specify are assumed to
+    /// be large enough.
+    ///
+    pub fn diagnose(&self,
+                    profile: &CapabilityProfile) -> Vec<RejectionReason> {
+        let mut reasons = Vec::new();
+        if let Some(version) = profile.api_version() {
+            if version < self.api_version {
+                reasons.push(RejectionReason::ApiVersion(version));
+            }
+        }
+
+        let missing_features =
+            report::feature_names(&self.features)
+                .into_iter()
+                .filter(|feature| !profile.supports_feature(feature))
+                .collect::<Vec<_>>();
+        if !missing_features.is_empty() {
+            reasons.push(RejectionReason::MissingFeatures(missing_features));
+        }
+
+        let missing_extensions =
+            report::extension_names(&self.extensions)
+                .into_iter()
+                .filter(|extension| !profile.supports_extension(extension))
+                .collect::<Vec<_>>();
+        if !missing_extensions.is_empty() {
+            reasons.push(RejectionReason::MissingExtensions(
+                missing_extensions
+            ));
+        }
+
+        let violations = self.limits.profile_violations(profile);
+        if !violations.is_empty() {
+            reasons.push(RejectionReason::InsufficientLimits(violations));
+        }
+
+        let has_queue_family = profile.queue_families.iter().any(|family| {
+            (family.graphics || !self.graphics)
+                && (family.compute || !self.compute)
+        });
+        if (self.graphics || self.compute) && !has_queue_family {
+            reasons.push(RejectionReason::NoSuitableQueueFamily);
+        }
+        reasons
+    }
+}
+
+/// Tell which profiles some requirements would exclude, and why
+///
+/// The index of each diagnosis is the position of the profile in the input.
+///
+pub fn check_profiles(
+    requirements: &ProfileRequirements,
+    profiles: &[CapabilityProfile]
+) -> Vec<DeviceDiagnosis> {
+    profiles.iter().enumerate().filter_map(|(index, profile)| {
+        let reasons = requirements.diagnose(profile);
+        if reasons.is_empty() {
+            None
+        } else {
+            Some(DeviceDiagnosis {
+                index,
+                name: profile.name.clone(),
+                reasons,
+            })
+        }
+    }).collect()
+}
+
+
+/// Select a profile using a profile-based filter and preference, following
+/// the same rules as EasyInstance::select_physical_device()
+///
+/// The index of the selected profile is returned. When several profiles are
+/// equally preferred, the first one wins.
+///
+pub fn select_profile(
+    profiles: &[CapabilityProfile],
+    mut filter: impl FnMut(&CapabilityProfile) -> bool,
+    mut preference: impl FnMut(&CapabilityProfile,
+                               &CapabilityProfile) -> Ordering
+) -> Option<usize> {
+    let mut favorite: Option<usize> = None;
+    for (index, profile) in profiles.iter().enumerate() {
+        if !filter(profile) { continue; }
+        let is_better = favorite.map_or(true, |best_so_far| {
+            preference(profile, &profiles[best_so_far]) == Ordering::Greater
+        });
+        if is_better { favorite = Some(index); }
+    }
+    favorite
+}
+
+/// Turn a profile-based filter into a device filter
+///
+/// Devices whose capabilities cannot be probed are rejected.
+///
+pub fn profile_filter(
+    mut filter: impl FnMut(&CapabilityProfile) -> bool
+) -> impl FnMut(PhysicalDevice) -> bool {
+    move |device| {
+        CapabilityProfile::of_device(device).map(|profile| filter(&profile))
+                                            .unwrap_or(false)
+    }
+}
+
+/// Turn a profile-based preference into a device preference
+///
+/// Devices whose capabilities cannot be probed are considered equivalent.
+/// The profile of each device is only probed once, the first time that the
+/// device is compared, and reused in later comparisons.
+///
+pub fn profile_preference(
+    mut preference: impl FnMut(&CapabilityProfile,
+                               &CapabilityProfile) -> Ordering
+) -> impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering {
+    let mut profiles = HashMap::new();
+    move |dev1, dev2| {
+        for device in &[dev1, dev2] {
+            profiles.entry(device.index()).or_insert_with(|| {
+                CapabilityProfile::of_device(*device).ok()
+            });
+        }
+        match (&profiles[&dev1.index()], &profiles[&dev2.index()]) {
+            (Some(profile1), Some(profile2)) => preference(profile1, profile2),
+            _ => Ordering::Equal,
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Embedded capability profiles of common GPUs
+//!
+//! Before sharing a prototype, it is good to know which popular devices its
+//! requirements would exclude. This module embeds a small database of
+//! capability profiles, which check_against_profiles() checks requirements
+//! against. It also loads device exports from vulkan.gpuinfo.org, which can
+//! be checked using profile::check_profiles() for better coverage.
+//!
+//! The embedded profiles are representative snapshots of typical driver
+//! versions, not an authoritative reference. Limits which are not listed in a
+//! profile are assumed to be large enough, see the profile module.
+
+use ::{
+    diagnosis::DeviceDiagnosis,
+    profile::{
+        self,
+        CapabilityProfile,
+        ProfileRequirements,
+    },
+    report::{
+        MemoryHeapReport,
+        QueueFamilyReport,
+    },
+    Error,
+    Result,
+};
+
+use serde_json::{
+    self,
+    Value,
+};
+
+
+/// JSON sources of the embedded profiles
+const EMBEDDED_PROFILES: &[&str] = &[
+    include_str!("../profiles/nvidia_geforce_rtx_3060.json"),
+    include_str!("../profiles/amd_radeon_rx_580_radv.json"),
+    include_str!("../profiles/intel_uhd_graphics_620_anv.json"),
+    include_str!("../profiles/apple_m1_moltenvk.json"),
+    include_str!("../profiles/mesa_llvmpipe.json"),
+];
+
+
+/// Capability profiles of common GPUs
+pub fn common_profiles() -> Vec<CapabilityProfile> {
+    EMBEDDED_PROFILES.iter()
+                     .map(|json| CapabilityProfile::from_json(json)
+                                                   .expect("Invalid profile"))
+                     .collect()
+}
+
+/// Tell which common GPUs some requirements would exclude, and why
+///
+/// Diagnoses are indexed by position in common_profiles().
+///
+pub fn check_against_profiles(
+    requirements: &ProfileRequirements
+) -> Vec<DeviceDiagnosis> {
+    profile::check_profiles(requirements, &common_profiles())
+}
+
+
+/// Load a device export from vulkan.gpuinfo.org (JSON format)
+///
+/// Vulkan's camelCase names are converted to vulkano's snake_case naming
+/// convention, on a best-effort basis. Array and boolean values are accepted
+/// in the various encodings that different database versions use.
+///
+pub fn load_gpuinfo_export(json: &str) -> Result<CapabilityProfile> {
+    let export: Value = serde_json::from_str(json)?;
+    let properties = &export["properties"];
+    let name = properties["deviceName"].as_str().ok_or_else(|| {
+        Error::InvalidArgument("gpuinfo export has no device name".to_owned())
+    })?;
+
+    let device_type = match properties["deviceType"] {
+        Value::Number(ref ty) => match ty.as_u64() {
+            Some(1) => "IntegratedGpu",
+            Some(2) => "DiscreteGpu",
+            Some(3) => "VirtualGpu",
+            Some(4) => "Cpu",
+            _ => "Other",
+        },
+        Value::String(ref ty) => {
+            let ty = ty.to_uppercase();
+            [("INTEGRATED", "IntegratedGpu"),
+             ("DISCRETE", "DiscreteGpu"),
+             ("VIRTUAL", "VirtualGpu"),
+             ("CPU", "Cpu")].iter()
+                            .find(|&&(pattern, _)| ty.contains(pattern))
+                            .map_or("Other", |&(_, name)| name)
+        },
+        _ => "Other",
+    };
+
+    let api_version = match properties["apiVersion"] {
+        Value::Number(ref version) => {
+            let version = version.as_u64().unwrap_or(0);
+            format!("{}.{}.{}",
+                    version >> 22, (version >> 12) & 0x3ff, version & 0xfff)
+        },
+        Value::String(ref version) => version.clone(),
+        _ => String::new(),
+    };
+
+    let mut extensions = export["extensions"].as_array()
+        .map(|extensions| extensions.iter().filter_map(|extension| {
+            extension["extensionName"].as_str()
+                                      .or_else(|| extension.as_str())
+                                      .map(str::to_owned)
+        }).collect::<Vec<_>>())
+        .unwrap_or_default();
+    extensions.sort();
+
+    let mut features = export["features"].as_object()
+        .map(|features| features.iter().filter(|&(_, value)| {
+            value.as_bool().unwrap_or_else(|| value.as_u64() == Some(1))
+        }).map(|(name, _)| snake_case(name)).collect::<Vec<_>>())
+        .unwrap_or_default();
+    features.sort();
+
+    let limits = properties["limits"].as_object()
+        .map(|limits| limits.iter().filter_map(|(name, value)| {
+            limit_value(value).map(|value| (snake_case(name), value))
+        }).collect())
+        .unwrap_or_default();
+
+    let queues = export["queues"].as_array().or_else(|| {
+        export["queueFamilies"].as_array()
+    });
+    let queue_families = queues.map(|queues| {
+        queues.iter().enumerate().map(|(id, queue)| {
+            let flags = queue["queueFlags"].as_u64().unwrap_or(0);
+            QueueFamilyReport {
+                id: id as u32,
+                queues_count: queue["queueCount"].as_u64().unwrap_or(0)
+                                                 as usize,
+                graphics: flags & 0x1 != 0,
+                compute: flags & 0x2 != 0,
+                transfers: flags & 0x4 != 0,
+                sparse_binding: flags & 0x8 != 0,
+            }
+        }).collect()
+    }).unwrap_or_default();
+
+    let memory_heaps = export["memory"]["memoryHeaps"].as_array()
+        .map(|heaps| heaps.iter().enumerate().map(|(id, heap)| {
+            MemoryHeapReport {
+                id: id as u32,
+                size: heap["size"].as_u64().unwrap_or(0) as usize,
+                device_local: heap["flags"].as_u64().unwrap_or(0) & 0x1 != 0,
+            }
+        }).collect())
+        .unwrap_or_default();
+
+    Ok(CapabilityProfile {
+        name: name.to_owned(),
+        device_type: device_type.to_owned(),
+        api_version,
+        pci_vendor_id: properties["vendorID"].as_u64().unwrap_or(0) as u32,
+        extensions,
+        features,
+        limits,
+        queue_families,
+        memory_heaps,
+    })
+}
+
+
+/// Convert a Vulkan camelCase name to vulkano's snake_case convention
+///
+/// Acronyms stay together ("textureCompressionETC2" becomes
+/// "texture_compression_etc2"), numbers stick to the previous word
+/// ("sparseResidency2Samples" becomes "sparse_residency2_samples"), except
+/// for image dimensions ("maxImageDimension2D" becomes
+/// "max_image_dimension_2d").
+///
+fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(name.len() + 8);
+    for (idx, &c) in chars.iter().enumerate() {
+        let prev = if idx > 0 { Some(chars[idx - 1]) } else { None };
+        let next = chars.get(idx + 1).cloned();
+        let new_word = match prev {
+            Some(prev) if c.is_ascii_uppercase() => {
+                let next_lowercase =
+                    next.map_or(false, |n| n.is_ascii_lowercase());
+                prev.is_ascii_lowercase() || next_lowercase
+            },
+            Some(prev) if c.is_ascii_digit() => {
+                prev.is_ascii_alphabetic() && next == Some('D')
+            },
+            _ => false,
+        };
+        if new_word { result.push('_'); }
+        result.push(c.to_ascii_lowercase());
+    }
+    result
+}
+
+/// Format a limit value like the Debug output found in reports
+fn limit_value(value: &Value) -> Option<String> {
+    match *value {
+        Value::Number(ref number) => Some(
+            if let Some(integer) = number.as_u64() {
+                integer.to_string()
+            } else {
+                format!("{:?}", number.as_f64()? as f32)
+            }
+        ),
+        Value::Bool(boolean) => Some(boolean.to_string()),
+        Value::Array(ref values) => {
+            let values = values.iter()
+                               .map(limit_value)
+                               .collect::<Option<Vec<_>>>()?;
+            Some(format!("[{}]", values.join(", ")))
+        },
+        _ => None,
+    }
+}
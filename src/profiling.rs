@@ -0,0 +1,814 @@
+//! GPU profiling using timestamp and pipeline statistics queries
+//!
+//! EasyTimer measures how long the GPU spends executing the work that you
+//! submit to a queue, using Vulkan timestamp queries. Scopes can be nested,
+//! and their GPU durations are reported once per frame.
+//!
+//! EasyPipelineStats counts shader invocations, which tells whether some
+//! rendering work is vertex-bound or fragment-bound.
+//!
+//! EasyOcclusionQueries counts the samples which pass the depth and stencil
+//! tests during some draws, which tells whether the drawn geometry is visible
+//! and is the basis of occlusion culling experiments.
+//!
+//! Vulkano does not support timestamp queries in its command buffer builders
+//! yet, so EasyTimer submits tiny pre-recorded command buffers which write the
+//! timestamps, using the raw Vulkan API. As a consequence, scopes are
+//! delimited by queue submissions rather than individual commands: everything
+//! submitted to the queue between the beginning and the end of a scope is
+//! measured.
+
+use ::{
+    command::RawCommandBuffer,
+    error::check_result,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    fmt,
+    ptr,
+    sync::Arc,
+    time::Duration,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    device::{
+        Device,
+        Queue,
+    },
+    query::{
+        QueryPipelineStatisticFlags,
+        QueryType,
+        UnsafeQueryPool,
+    },
+    SynchronizedVulkanObject,
+    VulkanObject,
+};
+
+
+/// Timing of a profiling scope
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScopeTiming {
+    /// Name of the scope
+    pub name: String,
+
+    /// Nesting depth of the scope (0 for top-level scopes)
+    pub depth: usize,
+
+    /// Time that the GPU spent in this scope
+    pub duration: Duration,
+}
+
+/// Timings of all profiling scopes of a frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameTimings {
+    /// Index of the frame
+    pub frame: u64,
+
+    /// Scope timings, in the order in which the scopes were started
+    pub scopes: Vec<ScopeTiming>,
+}
+
+impl fmt::Display for FrameTimings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GPU timings of frame {}:", self.frame)?;
+        for scope in &self.scopes {
+            write!(f, "\n    {:indent$}{}: {:?}",
+                   "", scope.name, scope.duration, indent = 2 * scope.depth)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// A profiling scope which is being recorded
+struct Scope {
+    /// Name of the scope
+    name: String,
+
+    /// Nesting depth of the scope
+    depth: usize,
+
+    /// Query where the starting timestamp was written
+    begin_query: u32,
+
+    /// Query where the ending timestamp was written, if the scope has ended
+    end_query: Option<u32>,
+}
+
+
+/// GPU timer based on timestamp queries
+pub struct EasyTimer {
+    /// Device which the timer belongs to
+    device: Arc<Device>,
+
+    /// Queue whose work is being timed
+    queue: Arc<Queue>,
+
+    /// Timestamp query pool
+    query_pool: UnsafeQueryPool,
+
+    /// Command pool which the timestamp command buffers come from
+    command_pool: vk::CommandPool,
+
+    /// One command buffer per query, which writes a timestamp in that query
+    command_buffers: Vec<vk::CommandBuffer>,
+
+    /// Number of nanoseconds per timestamp tick
+    timestamp_period: f64,
+
+    /// Scopes of the current frame
+    scopes: Vec<Scope>,
+
+    /// Indices of the scopes which have been started but not ended yet
+    open_scopes: Vec<usize>,
+
+    /// Next query to be written
+    next_query: u32,
+
+    /// Index of the current frame
+    frame: u64,
+}
+
+impl EasyTimer {
+    /// Set up a timer for a queue, with room for a certain number of
+    /// timestamps (i.e. twice the number of scopes) per frame
+    pub fn new(device: &Arc<Device>,
+               queue: &Arc<Queue>,
+               max_timestamps: u32) -> Result<Self> {
+        let _trace = trace::span("EasyTimer::new", || {
+            format!("max_timestamps: {}", max_timestamps)
+        });
+        let limits = device.physical_device().limits();
+        if limits.timestamp_compute_and_graphics() == 0 {
+            warn!("This device does not guarantee timestamp support on \
+                   graphics and compute queues, timings may be invalid");
+        }
+        let query_pool = UnsafeQueryPool::new(device.clone(),
+                                              QueryType::Timestamp,
+                                              max_timestamps)?;
+
+        // Create the command pool and command buffers, recording them right
+        // away as they are the same every frame.
+        let fns = device.pointers();
+        let raw_device = device.internal_object();
+        let mut command_pool = 0;
+        let mut command_buffers = vec![0; max_timestamps as usize];
+        unsafe {
+            let pool_info = vk::CommandPoolCreateInfo {
+                sType: vk::STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO,
+                pNext: ptr::null(),
+                flags: 0,
+                queueFamilyIndex: queue.family().id(),
+            };
+            check_result(fns.CreateCommandPool(raw_device,
+                                               &pool_info,
+                                               ptr::null(),
+                                               &mut command_pool),
+                         "vkCreateCommandPool")?;
+            let mut timer = EasyTimer {
+                device: device.clone(),
+                queue: queue.clone(),
+                query_pool,
+                command_pool,
+                command_buffers: Vec::new(),
+                timestamp_period: limits.timestamp_period() as f64,
+                scopes: Vec::new(),
+                open_scopes: Vec::new(),
+                next_query: 0,
+                frame: 0,
+            };
+            if max_timestamps == 0 { return Ok(timer); }
+
+            let alloc_info = vk::CommandBufferAllocateInfo {
+                sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO,
+                pNext: ptr::null(),
+                commandPool: command_pool,
+                level: vk::COMMAND_BUFFER_LEVEL_PRIMARY,
+                commandBufferCount: max_timestamps,
+            };
+            let result =
+                fns.AllocateCommandBuffers(raw_device,
+                                           &alloc_info,
+                                           command_buffers.as_mut_ptr());
+            check_result(result, "vkAllocateCommandBuffers")?;
+            timer.command_buffers = command_buffers;
+
+            let raw_query_pool = timer.query_pool.internal_object();
+            let begin_info = vk::CommandBufferBeginInfo {
+                sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
+                pNext: ptr::null(),
+                flags: vk::COMMAND_BUFFER_USAGE_SIMULTANEOUS_USE_BIT,
+                pInheritanceInfo: ptr::null(),
+            };
+            for (query, &cb) in timer.command_buffers.iter().enumerate() {
+                let query = query as u32;
+                check_result(fns.BeginCommandBuffer(cb, &begin_info),
+                             "vkBeginCommandBuffer")?;
+                fns.CmdResetQueryPool(cb, raw_query_pool, query, 1);
+                fns.CmdWriteTimestamp(cb,
+                                      vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT,
+                                      raw_query_pool,
+                                      query);
+                check_result(fns.EndCommandBuffer(cb), "vkEndCommandBuffer")?;
+            }
+            Ok(timer)
+        }
+    }
+
+    /// Start a profiling scope
+    ///
+    /// Scopes may be nested, and must be ended in the reverse order of the
+    /// order in which they were started.
+    ///
+    pub fn begin(&mut self, name: impl Into<String>) -> Result<()> {
+        let begin_query = self.write_timestamp()?;
+        self.open_scopes.push(self.scopes.len());
+        self.scopes.push(Scope {
+            name: name.into(),
+            depth: self.open_scopes.len() - 1,
+            begin_query,
+            end_query: None,
+        });
+        Ok(())
+    }
+
+    /// End the last profiling scope which was started
+    pub fn end(&mut self) -> Result<()> {
+        let scope_idx = self.open_scopes.pop().ok_or_else(|| {
+            Error::InvalidArgument("No profiling scope to end".to_owned())
+        })?;
+        let end_query = self.write_timestamp()?;
+        self.scopes[scope_idx].end_query = Some(end_query);
+        Ok(())
+    }
+
+    /// Run some code (which submits GPU work) inside of a profiling scope
+    pub fn scope<R>(&mut self,
+                    name: impl Into<String>,
+                    f: impl FnOnce() -> Result<R>) -> Result<R> {
+        self.begin(name)?;
+        let result = f();
+        self.end()?;
+        result
+    }
+
+    /// End the current frame, wait for its timings and return them
+    ///
+    /// The timings are also logged at the DEBUG log level.
+    ///
+    pub fn end_frame(&mut self) -> Result<FrameTimings> {
+        let _trace = trace::span("EasyTimer::end_frame",
+                                 || format!("frame: {}", self.frame));
+        if !self.open_scopes.is_empty() {
+            return Err(Error::InvalidArgument(
+                format!("{} profiling scope(s) were not ended",
+                        self.open_scopes.len())
+            ));
+        }
+
+        // Wait for the timestamps and fetch them
+        let mut timestamps = vec![0u64; self.next_query as usize];
+        if self.next_query > 0 {
+            let fns = self.device.pointers();
+            unsafe {
+                let result = fns.GetQueryPoolResults(
+                    self.device.internal_object(),
+                    self.query_pool.internal_object(),
+                    0,
+                    self.next_query,
+                    8 * timestamps.len(),
+                    timestamps.as_mut_ptr() as *mut _,
+                    8,
+                    vk::QUERY_RESULT_64_BIT | vk::QUERY_RESULT_WAIT_BIT
+                );
+                check_result(result, "vkGetQueryPoolResults")?;
+            }
+        }
+
+        // Convert them into scope durations
+        let timestamp_period = self.timestamp_period;
+        let scopes = self.scopes.drain(..).map(|scope| {
+            let end_query = scope.end_query.expect("Checked above");
+            let ticks = timestamps[end_query as usize]
+                            .saturating_sub(timestamps[scope.begin_query
+                                                       as usize]);
+            let nanos = (ticks as f64 * timestamp_period) as u64;
+            ScopeTiming {
+                name: scope.name,
+                depth: scope.depth,
+                duration: Duration::new(nanos / 1_000_000_000,
+                                        (nanos % 1_000_000_000) as u32),
+            }
+        }).collect();
+        let timings = FrameTimings {
+            frame: self.frame,
+            scopes,
+        };
+        debug!("{}", timings);
+
+        // Get ready for the next frame
+        self.next_query = 0;
+        self.frame += 1;
+        Ok(timings)
+    }
+
+    /// Submit a timestamp write to the queue
+    fn write_timestamp(&mut self) -> Result<u32> {
+        let query = self.next_query;
+        if query as usize >= self.command_buffers.len() {
+            return Err(Error::InvalidArgument(
+                format!("EasyTimer ran out of timestamps ({} per frame)",
+                        self.command_buffers.len())
+            ));
+        }
+        let submit_info = vk::SubmitInfo {
+            sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+            pNext: ptr::null(),
+            waitSemaphoreCount: 0,
+            pWaitSemaphores: ptr::null(),
+            pWaitDstStageMask: ptr::null(),
+            commandBufferCount: 1,
+            pCommandBuffers: &self.command_buffers[query as usize],
+            signalSemaphoreCount: 0,
+            pSignalSemaphores: ptr::null(),
+        };
+        unsafe {
+            let queue = self.queue.internal_object_guard();
+            check_result(self.device.pointers().QueueSubmit(*queue,
+                                                            1,
+                                                            &submit_info,
+                                                            0),
+                         "vkQueueSubmit")?;
+        }
+        self.next_query += 1;
+        Ok(query)
+    }
+}
+
+impl Drop for EasyTimer {
+    /// Wait for pending timestamp writes, then destroy the command pool
+    fn drop(&mut self) {
+        if let Err(e) = self.queue.wait() {
+            error!("Failed to wait for the EasyTimer queue: {}", e);
+        }
+        unsafe {
+            self.device.pointers().DestroyCommandPool(
+                self.device.internal_object(),
+                self.command_pool,
+                ptr::null()
+            );
+        }
+    }
+}
+
+
+/// Decoded results of a pipeline statistics query
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    /// Number of vertex shader invocations
+    pub vertex_invocations: u64,
+
+    /// Number of fragment shader invocations
+    pub fragment_invocations: u64,
+
+    /// Number of compute shader invocations
+    pub compute_invocations: u64,
+}
+
+impl fmt::Display for PipelineStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} vertex, {} fragment and {} compute invocation(s)",
+               self.vertex_invocations,
+               self.fragment_invocations,
+               self.compute_invocations)
+    }
+}
+
+
+/// Pipeline statistics query pool with a begin/end scope API
+///
+/// Unlike timestamps, pipeline statistics queries must begin and end within a
+/// single command buffer, along with the work that they measure. But
+/// vulkano's command buffer builders cannot record queries yet, and
+/// AutoCommandBufferBuilder does not expose the raw handle of its command
+/// buffer, so the scopes cannot be used with it. They must be recorded into
+/// a raw Vulkan command buffer instead, whose handle you can get from a
+/// command::RawCommandBuffer (see EasyCommandPool::raw()) or from your own
+/// raw Vulkan code. This is why the scope API is unsafe. Once the command
+/// buffers have executed, read_results() decodes the statistics.
+///
+/// The device must have the pipeline_statistics_query feature enabled.
+///
+pub struct EasyPipelineStats {
+    /// Device which the query pool belongs to
+    device: Arc<Device>,
+
+    /// Pipeline statistics query pool
+    query_pool: UnsafeQueryPool,
+
+    /// Names of the scopes which were started, indexed by query
+    names: Vec<String>,
+
+    /// Truth that a scope is currently open
+    in_scope: bool,
+}
+
+impl EasyPipelineStats {
+    /// Set up a pipeline statistics query pool with room for some scopes
+    pub fn new(device: &Arc<Device>, max_scopes: u32) -> Result<Self> {
+        let _trace = trace::span("EasyPipelineStats::new",
+                                 || format!("max_scopes: {}", max_scopes));
+        if !device.enabled_features().pipeline_statistics_query {
+            return Err(Error::InvalidArgument(
+                "The pipeline_statistics_query feature must be enabled"
+                    .to_owned()
+            ));
+        }
+        let flags = QueryPipelineStatisticFlags {
+            vertex_shader_invocations: true,
+            fragment_shader_invocations: true,
+            compute_shader_invocations: true,
+            .. QueryPipelineStatisticFlags::none()
+        };
+        let query_pool = UnsafeQueryPool::new(
+            device.clone(),
+            QueryType::PipelineStatistics(flags),
+            max_scopes
+        )?;
+        Ok(EasyPipelineStats {
+            device: device.clone(),
+            query_pool,
+            names: Vec::new(),
+            in_scope: false,
+        })
+    }
+
+    /// Record the beginning of a scope into a raw command buffer
+    ///
+    /// The command buffer is typically RawCommandBuffer::handle(), see the
+    /// type-level documentation.
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must be in the recording state, outside of a render
+    /// pass, and end() must be recorded in the same command buffer.
+    ///
+    pub unsafe fn begin(&mut self,
+                        command_buffer: vk::CommandBuffer,
+                        name: impl Into<String>) -> Result<()> {
+        if self.in_scope {
+            return Err(Error::InvalidArgument(
+                "Pipeline statistics scopes cannot be nested".to_owned()
+            ));
+        }
+        let query = self.names.len() as u32;
+        if query >= self.query_pool.num_queries() {
+            return Err(Error::InvalidArgument(
+                format!("EasyPipelineStats ran out of queries ({})",
+                        self.query_pool.num_queries())
+            ));
+        }
+        let fns = self.device.pointers();
+        let raw_query_pool = self.query_pool.internal_object();
+        fns.CmdResetQueryPool(command_buffer, raw_query_pool, query, 1);
+        fns.CmdBeginQuery(command_buffer, raw_query_pool, query, 0);
+        self.names.push(name.into());
+        self.in_scope = true;
+        Ok(())
+    }
+
+    /// Record the end of the current scope into a raw command buffer
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must be the one where the scope was started.
+    ///
+    pub unsafe fn end(&mut self,
+                      command_buffer: vk::CommandBuffer) -> Result<()> {
+        if !self.in_scope {
+            return Err(Error::InvalidArgument(
+                "No pipeline statistics scope to end".to_owned()
+            ));
+        }
+        let query = self.names.len() as u32 - 1;
+        self.device.pointers().CmdEndQuery(command_buffer,
+                                           self.query_pool.internal_object(),
+                                           query);
+        self.in_scope = false;
+        Ok(())
+    }
+
+    /// Wait for the statistics of all recorded scopes, and decode them
+    ///
+    /// The results are also logged at the DEBUG log level. Afterwards, the
+    /// queries are recycled for use by new scopes.
+    ///
+    /// The command buffers containing the scopes must have been submitted.
+    ///
+    pub fn read_results(&mut self) -> Result<Vec<(String, PipelineStats)>> {
+        let _trace = trace::span("EasyPipelineStats::read_results",
+                                 || format!("scopes: {}", self.names.len()));
+        if self.in_scope {
+            return Err(Error::InvalidArgument(
+                "A pipeline statistics scope was not ended".to_owned()
+            ));
+        }
+
+        // Results come in the order of the statistic flag bits, which is
+        // vertex, fragment and compute invocations.
+        let mut raw_results = vec![0u64; 3 * self.names.len()];
+        if !self.names.is_empty() {
+            unsafe {
+                let result = self.device.pointers().GetQueryPoolResults(
+                    self.device.internal_object(),
+                    self.query_pool.internal_object(),
+                    0,
+                    self.names.len() as u32,
+                    8 * raw_results.len(),
+                    raw_results.as_mut_ptr() as *mut _,
+                    8 * 3,
+                    vk::QUERY_RESULT_64_BIT | vk::QUERY_RESULT_WAIT_BIT
+                );
+                check_result(result, "vkGetQueryPoolResults")?;
+            }
+        }
+
+        let results = self.names.drain(..)
+                                .zip(raw_results.chunks(3))
+                                .map(|(name, raw)| {
+                                    (name, PipelineStats {
+                                        vertex_invocations: raw[0],
+                                        fragment_invocations: raw[1],
+                                        compute_invocations: raw[2],
+                                    })
+                                })
+                                .collect::<Vec<_>>();
+        for (name, stats) in &results {
+            debug!("Pipeline statistics of {}: {}", name, stats);
+        }
+        Ok(results)
+    }
+}
+
+
+/// Occlusion query pool with a begin/end scope API
+///
+/// As with EasyPipelineStats, vulkano cannot record queries yet, and
+/// AutoCommandBufferBuilder does not expose its raw command buffer handle.
+/// So scopes are recorded into raw Vulkan command buffers, e.g. the handle()
+/// of a command::RawCommandBuffer, using an unsafe API. Each frame (or
+/// whenever you want to reuse the queries), reset the queries, either with
+/// reset_and_wait() or by recording reset() outside of a render pass, then
+/// record begin() and end() around the draws of interest, inside of a render
+/// pass.
+///
+/// Results can be polled without blocking using poll_results(), which tells
+/// which queries are available yet, or waited for using read_results().
+///
+/// Imprecise queries only guarantee that the sample count is nonzero if some
+/// samples passed the tests, which is enough for visibility checks and faster
+/// on some hardware. Precise queries require the occlusion_query_precise
+/// device feature.
+///
+pub struct EasyOcclusionQueries {
+    /// Device which the query pool belongs to
+    device: Arc<Device>,
+
+    /// Occlusion query pool
+    query_pool: UnsafeQueryPool,
+
+    /// Truth that exact sample counts are requested
+    precise: bool,
+
+    /// Names of the scopes which were started, indexed by query
+    names: Vec<String>,
+
+    /// Truth that a scope is currently open
+    in_scope: bool,
+
+    /// Truth that the queries were reset since their results were last read
+    reset: bool,
+}
+
+impl EasyOcclusionQueries {
+    /// Set up an occlusion query pool with room for some scopes
+    pub fn new(device: &Arc<Device>,
+               max_scopes: u32,
+               precise: bool) -> Result<Self> {
+        let _trace = trace::span("EasyOcclusionQueries::new",
+                                 || format!("max_scopes: {}, precise: {}",
+                                            max_scopes, precise));
+        if precise && !device.enabled_features().occlusion_query_precise {
+            return Err(Error::InvalidArgument(
+                "Precise occlusion queries require the occlusion_query_precise \
+                 feature to be enabled".to_owned()
+            ));
+        }
+        let query_pool = UnsafeQueryPool::new(device.clone(),
+                                              QueryType::Occlusion,
+                                              max_scopes)?;
+        Ok(EasyOcclusionQueries {
+            device: device.clone(),
+            query_pool,
+            precise,
+            names: Vec::new(),
+            in_scope: false,
+            reset: false,
+        })
+    }
+
+    /// Record a reset of all queries into a raw command buffer, discarding
+    /// the previously started scopes
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must be in the recording state, outside of a render
+    /// pass, and be executed before the command buffers containing the scopes.
+    ///
+    pub unsafe fn reset(&mut self, command_buffer: vk::CommandBuffer) {
+        self.device.pointers().CmdResetQueryPool(
+            command_buffer,
+            self.query_pool.internal_object(),
+            0,
+            self.query_pool.num_queries()
+        );
+        self.names.clear();
+        self.in_scope = false;
+        self.reset = true;
+    }
+
+    /// Reset all queries from a command buffer of its own, submitted to some
+    /// queue, and wait for the reset to complete
+    ///
+    /// Unlike reset(), this does not need a raw command buffer, as resets do
+    /// not have to be recorded in the command buffer containing the scopes.
+    ///
+    pub fn reset_and_wait(&mut self, queue: &Arc<Queue>) -> Result<()> {
+        let _trace = trace::span("EasyOcclusionQueries::reset_and_wait",
+                                 String::new);
+        let command_buffer = RawCommandBuffer::new(queue)?;
+        // Safe because a fresh RawCommandBuffer is recording, outside of any
+        // render pass, and is executed before this function returns
+        unsafe { self.reset(command_buffer.handle()); }
+        command_buffer.submit_and_wait()
+    }
+
+    /// Record the beginning of a scope into a raw command buffer
+    ///
+    /// The command buffer is typically RawCommandBuffer::handle(), see the
+    /// type-level documentation.
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must be in the recording state, inside of a render
+    /// pass, and end() must be recorded in the same subpass.
+    ///
+    pub unsafe fn begin(&mut self,
+                        command_buffer: vk::CommandBuffer,
+                        name: impl Into<String>) -> Result<()> {
+        if !self.reset {
+            return Err(Error::InvalidArgument(
+                "Occlusion queries must be reset before being used".to_owned()
+            ));
+        }
+        if self.in_scope {
+            return Err(Error::InvalidArgument(
+                "Occlusion query scopes cannot be nested".to_owned()
+            ));
+        }
+        let query = self.names.len() as u32;
+        if query >= self.query_pool.num_queries() {
+            return Err(Error::InvalidArgument(
+                format!("EasyOcclusionQueries ran out of queries ({})",
+                        self.query_pool.num_queries())
+            ));
+        }
+        let flags = if self.precise {
+            vk::QUERY_CONTROL_PRECISE_BIT
+        } else {
+            0
+        };
+        self.device.pointers().CmdBeginQuery(command_buffer,
+                                             self.query_pool.internal_object(),
+                                             query,
+                                             flags);
+        self.names.push(name.into());
+        self.in_scope = true;
+        Ok(())
+    }
+
+    /// Record the end of the current scope into a raw command buffer
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must be the one where the scope was started.
+    ///
+    pub unsafe fn end(&mut self,
+                      command_buffer: vk::CommandBuffer) -> Result<()> {
+        if !self.in_scope {
+            return Err(Error::InvalidArgument(
+                "No occlusion query scope to end".to_owned()
+            ));
+        }
+        let query = self.names.len() as u32 - 1;
+        self.device.pointers().CmdEndQuery(command_buffer,
+                                           self.query_pool.internal_object(),
+                                           query);
+        self.in_scope = false;
+        Ok(())
+    }
+
+    /// Check which scopes have results available, without waiting
+    ///
+    /// Each scope comes with its sample count, or None if its results are not
+    /// available yet. The queries are not recycled, call read_results() or
+    /// reset() for that.
+    ///
+    pub fn poll_results(&self) -> Result<Vec<(String, Option<u64>)>> {
+        let _trace = trace::span("EasyOcclusionQueries::poll_results",
+                                 || format!("scopes: {}", self.names.len()));
+        if self.in_scope {
+            return Err(Error::InvalidArgument(
+                "An occlusion query scope was not ended".to_owned()
+            ));
+        }
+
+        // Each query yields a sample count followed by an availability flag,
+        // and results which are not available yet are not written at all.
+        let mut raw_results = vec![0u64; 2 * self.names.len()];
+        if !self.names.is_empty() {
+            let result = unsafe {
+                self.device.pointers().GetQueryPoolResults(
+                    self.device.internal_object(),
+                    self.query_pool.internal_object(),
+                    0,
+                    self.names.len() as u32,
+                    8 * raw_results.len(),
+                    raw_results.as_mut_ptr() as *mut _,
+                    8 * 2,
+                    vk::QUERY_RESULT_64_BIT
+                        | vk::QUERY_RESULT_WITH_AVAILABILITY_BIT
+                )
+            };
+            if result != vk::NOT_READY {
+                check_result(result, "vkGetQueryPoolResults")?;
+            }
+        }
+
+        Ok(self.names.iter()
+                     .zip(raw_results.chunks(2))
+                     .map(|(name, raw)| {
+                         let available = raw[1] != 0;
+                         (name.clone(), Some(raw[0]).filter(|_| available))
+                     })
+                     .collect())
+    }
+
+    /// Wait for the sample counts of all recorded scopes
+    ///
+    /// The results are also logged at the DEBUG log level. Afterwards, the
+    /// queries must be reset before they are used again.
+    ///
+    /// The command buffers containing the scopes must have been submitted.
+    ///
+    pub fn read_results(&mut self) -> Result<Vec<(String, u64)>> {
+        let _trace = trace::span("EasyOcclusionQueries::read_results",
+                                 || format!("scopes: {}", self.names.len()));
+        if self.in_scope {
+            return Err(Error::InvalidArgument(
+                "An occlusion query scope was not ended".to_owned()
+            ));
+        }
+
+        let mut raw_results = vec![0u64; self.names.len()];
+        if !self.names.is_empty() {
+            unsafe {
+                let result = self.device.pointers().GetQueryPoolResults(
+                    self.device.internal_object(),
+                    self.query_pool.internal_object(),
+                    0,
+                    self.names.len() as u32,
+                    8 * raw_results.len(),
+                    raw_results.as_mut_ptr() as *mut _,
+                    8,
+                    vk::QUERY_RESULT_64_BIT | vk::QUERY_RESULT_WAIT_BIT
+                );
+                check_result(result, "vkGetQueryPoolResults")?;
+            }
+        }
+
+        let results = self.names.drain(..)
+                                .zip(raw_results)
+                                .collect::<Vec<_>>();
+        for (name, samples) in &results {
+            debug!("Occlusion query {} saw {} sample(s) pass", name, samples);
+        }
+        self.reset = false;
+        Ok(results)
+    }
+}
@@ -0,0 +1,113 @@
+//! Extended physical device queries
+//!
+//! Many device properties and features introduced after Vulkan 1.0 can only be
+//! queried by chaining extension structs to the output of
+//! vkGetPhysicalDeviceProperties2KHR and vkGetPhysicalDeviceFeatures2KHR,
+//! which vulkano does not let us do. So EasyInstance enables
+//! VK_KHR_get_physical_device_properties2 whenever it is available, and this
+//! module carries out the queries on behalf of the modules which need them.
+
+use std::{
+    ffi::CString,
+    mem,
+    os::raw::c_void,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    instance::{
+        PhysicalDevice,
+        RawInstanceExtensions,
+    },
+    VulkanObject,
+};
+
+
+/// Instance extension which is needed for extended queries
+pub const PROPERTIES2_EXTENSION: &str =
+    "VK_KHR_get_physical_device_properties2";
+
+/// Truth that extended queries can be carried out on a physical device
+pub fn is_available(device: PhysicalDevice) -> bool {
+    device.instance().loaded_extensions().khr_get_physical_device_properties2
+}
+
+
+/// Add the extension needed for extended queries to a set of instance
+/// extensions, if the Vulkan implementation supports it
+pub(crate) fn add_instance_extensions(
+    extensions: &mut RawInstanceExtensions,
+    supported: &RawInstanceExtensions,
+) {
+    if supported.iter().any(|ext| {
+        ext.as_bytes() == PROPERTIES2_EXTENSION.as_bytes()
+    }) {
+        info!("Enabling {} for extended device queries",
+              PROPERTIES2_EXTENSION);
+        extensions.insert(CString::new(PROPERTIES2_EXTENSION)
+                                  .expect("Extension name has no NUL"));
+    }
+}
+
+/// Fill a chain of extension property structs, if extended queries are
+/// available, and tell whether that was the case
+///
+/// The chain must consist of valid Vulkan output structs, with their sType
+/// set and their pNext pointers forming a null-terminated list.
+///
+pub(crate) unsafe fn query_properties(device: PhysicalDevice,
+                                      chain: *mut c_void) -> bool {
+    if !is_available(device) { return false; }
+    let mut properties2 = vk::PhysicalDeviceProperties2KHR {
+        sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2_KHR,
+        pNext: chain,
+        properties: mem::zeroed(),
+    };
+    device.instance().pointers().GetPhysicalDeviceProperties2KHR(
+        device.internal_object(),
+        &mut properties2
+    );
+    true
+}
+
+/// Fill a chain of extension feature structs, if extended queries are
+/// available, and tell whether that was the case
+///
+/// The same requirements as for query_properties() apply.
+///
+pub(crate) unsafe fn query_features(device: PhysicalDevice,
+                                    chain: *mut c_void) -> bool {
+    if !is_available(device) { return false; }
+    let mut features2 = vk::PhysicalDeviceFeatures2KHR {
+        sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2_KHR,
+        pNext: chain,
+        features: mem::zeroed(),
+    };
+    device.instance().pointers().GetPhysicalDeviceFeatures2KHR(
+        device.internal_object(),
+        &mut features2
+    );
+    true
+}
+
+/// Fill the properties of every queue family of a device, along with their
+/// chains of extension property structs, if extended queries are available,
+/// and tell whether that was the case
+///
+/// There must be one entry per queue family, each with its sType set and a
+/// valid pNext chain as in query_properties().
+///
+pub(crate) unsafe fn query_queue_family_properties(
+    device: PhysicalDevice,
+    properties: &mut [vk::QueueFamilyProperties2KHR]
+) -> bool {
+    if !is_available(device) { return false; }
+    let mut count = properties.len() as u32;
+    device.instance().pointers().GetPhysicalDeviceQueueFamilyProperties2KHR(
+        device.internal_object(),
+        &mut count,
+        properties.as_mut_ptr()
+    );
+    count as usize == properties.len()
+}
@@ -0,0 +1,166 @@
+//! Typed push constants, checked against the device limits
+//!
+//! Push constants are the cheapest way to get a few bytes of data to a
+//! shader, but getting their size wrong leads to validation errors at best
+//! and garbage data at worst. EasyPushConstants describes a push constant
+//! block from the Rust type that holds it, checks what can be checked at
+//! compile time (the size must be a nonzero multiple of 4 bytes), and the
+//! other helpers of this module check the rest against the device's
+//! max_push_constants_size limit when pipelines are built and used.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    marker::PhantomData,
+    mem,
+};
+
+use vulkano::{
+    descriptor::{
+        descriptor::ShaderStages,
+        pipeline_layout::{
+            PipelineLayoutDesc,
+            PipelineLayoutDescPcRange,
+        },
+    },
+    instance::PhysicalDevice,
+};
+
+
+/// Push constant block size which every Vulkan implementation supports
+pub const GUARANTEED_PUSH_CONSTANTS_SIZE: usize = 128;
+
+
+/// Description of a push constant block, stored in a Rust type T
+///
+/// T should be #[repr(C)] and follow the std430 layout rules, like the push
+/// constant block declared in your shaders.
+///
+#[derive(Debug)]
+pub struct EasyPushConstants<T> {
+    /// Shader stages which access the push constants
+    stages: ShaderStages,
+
+    /// Push constants are stored in a T
+    _data: PhantomData<fn() -> T>,
+}
+
+impl<T: Copy> EasyPushConstants<T> {
+    /// Size of the push constant block, checked at compile time
+    const SIZE: usize = {
+        let size = mem::size_of::<T>();
+        assert!(size > 0 && size % 4 == 0,
+                "Push constant blocks must have a nonzero size which is a \
+                 multiple of 4 bytes");
+        size
+    };
+
+    /// Describe a push constant block which some shader stages access
+    pub fn new(stages: ShaderStages) -> Self {
+        if Self::SIZE > GUARANTEED_PUSH_CONSTANTS_SIZE {
+            warn!("{}-byte push constant blocks are not supported by every \
+                   Vulkan implementation, only {} bytes are guaranteed",
+                  Self::SIZE, GUARANTEED_PUSH_CONSTANTS_SIZE);
+        }
+        EasyPushConstants {
+            stages,
+            _data: PhantomData,
+        }
+    }
+
+    /// Size of the push constant block, in bytes
+    pub fn size(&self) -> usize {
+        Self::SIZE
+    }
+
+    /// Shader stages which access the push constants
+    pub fn stages(&self) -> ShaderStages {
+        self.stages
+    }
+
+    /// Push constant range, for use in a pipeline layout description
+    pub fn range(&self) -> PipelineLayoutDescPcRange {
+        PipelineLayoutDescPcRange {
+            offset: 0,
+            size: Self::SIZE,
+            stages: self.stages,
+        }
+    }
+}
+
+impl<T> Clone for EasyPushConstants<T> {
+    fn clone(&self) -> Self {
+        EasyPushConstants {
+            stages: self.stages,
+            _data: PhantomData,
+        }
+    }
+}
+
+
+/// Number of push constant bytes that a pipeline layout uses
+pub fn push_constants_size(layout: &impl PipelineLayoutDesc) -> usize {
+    (0..layout.num_push_constants_ranges())
+        .filter_map(|num| layout.push_constants_range(num))
+        .map(|range| range.offset + range.size)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Check the push constant ranges of a pipeline layout against the limits
+/// of a device, before a pipeline is built from it
+pub fn check_push_constants_limits(
+    device: PhysicalDevice,
+    layout: &impl PipelineLayoutDesc
+) -> Result<()> {
+    let _trace = trace::span("push_constants::check_push_constants_limits",
+                             || format!("device: {}", device.name()));
+    let max_size = device.limits().max_push_constants_size() as usize;
+    for num in 0..layout.num_push_constants_ranges() {
+        let range = match layout.push_constants_range(num) {
+            Some(range) => range,
+            None => continue,
+        };
+        if range.offset % 4 != 0 || range.size % 4 != 0 || range.size == 0 {
+            return Err(Error::InvalidArgument(format!(
+                "Push constant range #{} ({} bytes at offset {}) must have a \
+                 nonzero size and an offset which are multiples of 4 bytes",
+                num, range.size, range.offset
+            )));
+        }
+        if range.offset + range.size > max_size {
+            return Err(Error::InvalidArgument(format!(
+                "Push constant range #{} ends at byte {}, but device \"{}\" \
+                 only supports {} bytes of push constants",
+                num, range.offset + range.size, device.name(), max_size
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Check that some push constant data fits a pipeline layout, before it is
+/// used to record a draw or dispatch
+///
+/// Vulkano reads as many bytes from the data as the layout's push constant
+/// ranges cover, so data that is smaller than that would lead it to read out
+/// of bounds. Larger data is also rejected, as it is most likely a mistake.
+///
+pub fn check_push_constants<T>(layout: &impl PipelineLayoutDesc) -> Result<()> {
+    let _trace = trace::span("push_constants::check_push_constants", || {
+        format!("size: {}", mem::size_of::<T>())
+    });
+    let expected = push_constants_size(layout);
+    let actual = mem::size_of::<T>();
+    if actual != expected {
+        return Err(Error::InvalidArgument(format!(
+            "The pipeline layout expects {} bytes of push constants, but {} \
+             bytes were provided", expected, actual
+        )));
+    }
+    Ok(())
+}
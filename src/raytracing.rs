@@ -0,0 +1,1249 @@
+//! Hardware ray tracing
+//!
+//! Vulkano does not know about the ray tracing extensions yet, so this module
+//! takes care of it: telling which devices support ray tracing, enabling the
+//! right set of device extensions and extension features (see
+//! EasyPhysicalDevice::setup_ray_tracing_device()), and building bottom-level
+//! and top-level acceleration structures (see AccelerationStructure).
+//!
+//! Two flavors of ray tracing are supported by Vulkan. Ray tracing pipelines
+//! (VK_KHR_ray_tracing_pipeline) add dedicated shader stages for ray
+//! generation, hits and misses, whereas ray queries (VK_KHR_ray_query) let
+//! existing shader stages trace rays. Both rely on acceleration structures
+//! (VK_KHR_acceleration_structure): bottom-level ones (BLAS) which contain
+//! triangles, and top-level ones (TLAS) which contain instances of BLASes.
+//!
+//! The ray tracing extensions require Vulkan 1.1, so both the device and the
+//! instance must support it (see the api_level module). Acceleration
+//! structure builds use entry points which neither vulkano nor vk-sys expose
+//! at the moment, so they are recorded into a command::RawCommandBuffer and
+//! submitted synchronously. Tracing rays through the resulting acceleration
+//! structures still requires raw Vulkan pipelines or ray queries.
+
+use ::{
+    api_level::VULKAN_1_1,
+    command::RawCommandBuffer,
+    device::EasyDevice,
+    error::check_result,
+    loader::{
+        self,
+        FeatureChain,
+        FeatureStruct,
+    },
+    properties2,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    ffi::CString,
+    mem,
+    os::raw::c_void,
+    ptr,
+    sync::Arc,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    device::{
+        Device,
+        Queue,
+    },
+    instance::{
+        PhysicalDevice,
+        RawDeviceExtensions,
+        Version,
+    },
+    VulkanObject,
+};
+
+
+/// Name of the acceleration structure extension
+pub const ACCELERATION_STRUCTURE_EXTENSION: &str =
+    "VK_KHR_acceleration_structure";
+
+/// Name of the ray tracing pipeline extension
+pub const RAY_TRACING_PIPELINE_EXTENSION: &str =
+    "VK_KHR_ray_tracing_pipeline";
+
+/// Name of the ray query extension
+pub const RAY_QUERY_EXTENSION: &str = "VK_KHR_ray_query";
+
+/// Extensions which VK_KHR_acceleration_structure depends on, on Vulkan 1.0
+/// and 1.1 devices (some of them were promoted to core in Vulkan 1.2)
+pub const ACCELERATION_STRUCTURE_DEPENDENCIES: &[&str] = &[
+    "VK_KHR_deferred_host_operations",
+    "VK_KHR_buffer_device_address",
+    "VK_EXT_descriptor_indexing",
+];
+
+/// Extensions which ray tracing pipelines and ray queries depend on, on
+/// Vulkan 1.0 and 1.1 devices
+pub const RAY_TRACING_SHADER_DEPENDENCIES: &[&str] = &[
+    "VK_KHR_spirv_1_4",
+    "VK_KHR_shader_float_controls",
+];
+
+/// Structure types which vk-sys does not know about
+const STRUCTURE_TYPE_BUFFER_DEVICE_ADDRESS_FEATURES: u32 = 1_000_257_000;
+const STRUCTURE_TYPE_BUFFER_DEVICE_ADDRESS_INFO: u32 = 1_000_244_001;
+const STRUCTURE_TYPE_MEMORY_ALLOCATE_FLAGS_INFO: u32 = 1_000_060_000;
+const STRUCTURE_TYPE_ACCELERATION_STRUCTURE_BUILD_GEOMETRY_INFO: u32 =
+    1_000_150_000;
+const STRUCTURE_TYPE_ACCELERATION_STRUCTURE_DEVICE_ADDRESS_INFO: u32 =
+    1_000_150_002;
+const STRUCTURE_TYPE_GEOMETRY_INSTANCES_DATA: u32 = 1_000_150_004;
+const STRUCTURE_TYPE_GEOMETRY_TRIANGLES_DATA: u32 = 1_000_150_005;
+const STRUCTURE_TYPE_ACCELERATION_STRUCTURE_GEOMETRY: u32 = 1_000_150_006;
+const STRUCTURE_TYPE_ACCELERATION_STRUCTURE_FEATURES: u32 = 1_000_150_013;
+const STRUCTURE_TYPE_ACCELERATION_STRUCTURE_PROPERTIES: u32 = 1_000_150_014;
+const STRUCTURE_TYPE_ACCELERATION_STRUCTURE_CREATE_INFO: u32 = 1_000_150_017;
+const STRUCTURE_TYPE_ACCELERATION_STRUCTURE_BUILD_SIZES_INFO: u32 =
+    1_000_150_020;
+const STRUCTURE_TYPE_RAY_TRACING_PIPELINE_FEATURES: u32 = 1_000_347_000;
+const STRUCTURE_TYPE_RAY_QUERY_FEATURES: u32 = 1_000_348_013;
+
+/// Flags and enumerants which vk-sys does not know about
+const BUFFER_USAGE_SHADER_DEVICE_ADDRESS: u32 = 0x0002_0000;
+const BUFFER_USAGE_BUILD_INPUT_READ_ONLY: u32 = 0x0008_0000;
+const BUFFER_USAGE_ACCELERATION_STRUCTURE_STORAGE: u32 = 0x0010_0000;
+const MEMORY_ALLOCATE_DEVICE_ADDRESS: u32 = 0x2;
+const ACCELERATION_STRUCTURE_TYPE_TOP_LEVEL: u32 = 0;
+const ACCELERATION_STRUCTURE_TYPE_BOTTOM_LEVEL: u32 = 1;
+const GEOMETRY_TYPE_TRIANGLES: u32 = 0;
+const GEOMETRY_TYPE_INSTANCES: u32 = 2;
+const GEOMETRY_OPAQUE: u32 = 0x1;
+const BUILD_PREFER_FAST_TRACE: u32 = 0x4;
+const BUILD_MODE_BUILD: u32 = 0;
+const BUILD_TYPE_DEVICE: u32 = 1;
+const FORMAT_R32G32B32_SFLOAT: u32 = 106;
+const INDEX_TYPE_UINT32: u32 = 1;
+
+
+/// Flavor of ray tracing that an application uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RayTracingMode {
+    /// Ray tracing pipelines, with dedicated shader stages
+    Pipeline,
+
+    /// Ray queries from existing shader stages
+    Query,
+}
+
+impl RayTracingMode {
+    /// Device extensions which must be enabled for this flavor
+    pub fn extensions(self) -> Vec<&'static str> {
+        let mut extensions = vec![ACCELERATION_STRUCTURE_EXTENSION];
+        extensions.push(match self {
+            RayTracingMode::Pipeline => RAY_TRACING_PIPELINE_EXTENSION,
+            RayTracingMode::Query => RAY_QUERY_EXTENSION,
+        });
+        extensions.extend_from_slice(ACCELERATION_STRUCTURE_DEPENDENCIES);
+        extensions.extend_from_slice(RAY_TRACING_SHADER_DEPENDENCIES);
+        extensions
+    }
+
+    /// Extension features which must be enabled for this flavor
+    pub fn features(self) -> Vec<&'static str> {
+        vec![
+            "acceleration_structure",
+            match self {
+                RayTracingMode::Pipeline => "ray_tracing_pipeline",
+                RayTracingMode::Query => "ray_query",
+            },
+            "buffer_device_address",
+        ]
+    }
+}
+
+
+/// Summary of the ray tracing support of a physical device
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RayTracingSupport {
+    /// Whether VK_KHR_acceleration_structure is supported
+    pub acceleration_structure: bool,
+
+    /// Whether VK_KHR_ray_tracing_pipeline is supported
+    pub pipeline: bool,
+
+    /// Whether VK_KHR_ray_query is supported
+    pub query: bool,
+
+    /// Extensions required by ray tracing which are not supported
+    pub missing_extensions: Vec<String>,
+
+    /// Extension features required by ray tracing which are not supported,
+    /// or None if they could not be queried
+    pub missing_features: Option<Vec<String>>,
+
+    /// Vulkan API version of the device
+    pub api_version: Version,
+}
+
+impl RayTracingSupport {
+    /// Probe the ray tracing support of a physical device
+    pub fn of_device(device: PhysicalDevice) -> Result<Self> {
+        let _trace = trace::span("RayTracingSupport::of_device",
+                                 || format!("device: {}", device.name()));
+        let extensions = RawDeviceExtensions::supported_by_device_raw(device)?;
+        let supports = |name: &str| {
+            extensions.iter().any(|ext| ext.as_bytes() == name.as_bytes())
+        };
+        let mut missing_extensions = Vec::new();
+        for &name in [ACCELERATION_STRUCTURE_EXTENSION,
+                      RAY_TRACING_PIPELINE_EXTENSION,
+                      RAY_QUERY_EXTENSION].iter()
+                          .chain(ACCELERATION_STRUCTURE_DEPENDENCIES)
+                          .chain(RAY_TRACING_SHADER_DEPENDENCIES)
+        {
+            if !supports(name) { missing_extensions.push(name.to_owned()); }
+        }
+        let missing_features = if missing_extensions.is_empty() {
+            RayTracingFeatures::of_device(device)
+                               .map(|features| features.missing())
+        } else {
+            None
+        };
+        Ok(RayTracingSupport {
+            acceleration_structure: supports(ACCELERATION_STRUCTURE_EXTENSION),
+            pipeline: supports(RAY_TRACING_PIPELINE_EXTENSION),
+            query: supports(RAY_QUERY_EXTENSION),
+            missing_extensions,
+            missing_features,
+            api_version: device.api_version(),
+        })
+    }
+
+    /// Extensions which are needed by some flavor of ray tracing, but are not
+    /// supported by the device
+    pub fn missing_extensions(&self, mode: RayTracingMode) -> Vec<String> {
+        mode.extensions()
+            .into_iter()
+            .filter(|name| self.missing_extensions.iter().any(|m| m == name))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// Extension features which are needed by some flavor of ray tracing, but
+    /// are not supported by the device
+    ///
+    /// Features which could not be queried are all reported as missing.
+    ///
+    pub fn missing_features(&self, mode: RayTracingMode) -> Vec<String> {
+        let needed = mode.features();
+        match self.missing_features {
+            Some(ref missing) => {
+                needed.into_iter()
+                      .filter(|name| missing.iter().any(|m| m == name))
+                      .map(str::to_owned)
+                      .collect()
+            },
+            None => needed.into_iter().map(str::to_owned).collect(),
+        }
+    }
+
+    /// Truth that the device supports some flavor of ray tracing
+    ///
+    /// The instance must also support Vulkan 1.1, which is checked by
+    /// EasyPhysicalDevice::setup_ray_tracing_device().
+    ///
+    pub fn supports(&self, mode: RayTracingMode) -> bool {
+        self.api_version >= VULKAN_1_1
+            && self.missing_extensions(mode).is_empty()
+            && self.missing_features(mode).is_empty()
+    }
+}
+
+
+/// Ray tracing extension features of a device
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct RayTracingFeatures {
+    acceleration_structure: bool,
+    ray_tracing_pipeline: bool,
+    ray_query: bool,
+    buffer_device_address: bool,
+}
+
+impl RayTracingFeatures {
+    /// Query the ray tracing features of a device, if extended queries are
+    /// available
+    fn of_device(device: PhysicalDevice) -> Option<Self> {
+        let mut features = RawRayTracingFeatures::new(0);
+        let chain = features.link();
+        if !unsafe { properties2::query_features(device, chain) } {
+            return None;
+        }
+        Some(RayTracingFeatures {
+            acceleration_structure:
+                features.acceleration_structure.acceleration_structure != 0,
+            ray_tracing_pipeline:
+                features.ray_tracing_pipeline.ray_tracing_pipeline != 0,
+            ray_query: features.ray_query.ray_query != 0,
+            buffer_device_address:
+                features.buffer_device_address.buffer_device_address != 0,
+        })
+    }
+
+    /// Names of the unsupported features
+    fn missing(&self) -> Vec<String> {
+        let mut missing = Vec::new();
+        if !self.acceleration_structure {
+            missing.push("acceleration_structure");
+        }
+        if !self.ray_tracing_pipeline { missing.push("ray_tracing_pipeline"); }
+        if !self.ray_query { missing.push("ray_query"); }
+        if !self.buffer_device_address {
+            missing.push("buffer_device_address");
+        }
+        missing.into_iter().map(str::to_owned).collect()
+    }
+}
+
+/// Raw ray tracing feature structs, which vk-sys does not know about
+struct RawRayTracingFeatures {
+    acceleration_structure: AccelerationStructureFeatures,
+    ray_tracing_pipeline: RayTracingPipelineFeatures,
+    ray_query: RayQueryFeatures,
+    buffer_device_address: BufferDeviceAddressFeatures,
+}
+
+impl RawRayTracingFeatures {
+    /// Set up the feature structs, with every feature set to some value
+    fn new(value: u32) -> Self {
+        RawRayTracingFeatures {
+            acceleration_structure: AccelerationStructureFeatures {
+                s_type: STRUCTURE_TYPE_ACCELERATION_STRUCTURE_FEATURES,
+                p_next: ptr::null_mut(),
+                acceleration_structure: value,
+                acceleration_structure_capture_replay: 0,
+                acceleration_structure_indirect_build: 0,
+                acceleration_structure_host_commands: 0,
+                descriptor_binding_acceleration_structure_update_after_bind: 0,
+            },
+            ray_tracing_pipeline: RayTracingPipelineFeatures {
+                s_type: STRUCTURE_TYPE_RAY_TRACING_PIPELINE_FEATURES,
+                p_next: ptr::null_mut(),
+                ray_tracing_pipeline: value,
+                ray_tracing_pipeline_shader_group_handle_capture_replay: 0,
+                ray_tracing_pipeline_shader_group_handle_capture_replay_mixed:
+                    0,
+                ray_tracing_pipeline_trace_rays_indirect: 0,
+                ray_traversal_primitive_culling: 0,
+            },
+            ray_query: RayQueryFeatures {
+                s_type: STRUCTURE_TYPE_RAY_QUERY_FEATURES,
+                p_next: ptr::null_mut(),
+                ray_query: value,
+            },
+            buffer_device_address: BufferDeviceAddressFeatures {
+                s_type: STRUCTURE_TYPE_BUFFER_DEVICE_ADDRESS_FEATURES,
+                p_next: ptr::null_mut(),
+                buffer_device_address: value,
+                buffer_device_address_capture_replay: 0,
+                buffer_device_address_multi_device: 0,
+            },
+        }
+    }
+
+    /// Chain the feature structs together for a query
+    fn link(&mut self) -> *mut c_void {
+        self.acceleration_structure.p_next =
+            &mut self.ray_tracing_pipeline as *mut _ as *mut c_void;
+        self.ray_tracing_pipeline.p_next =
+            &mut self.ray_query as *mut _ as *mut c_void;
+        self.ray_query.p_next =
+            &mut self.buffer_device_address as *mut _ as *mut c_void;
+        &mut self.acceleration_structure as *mut _ as *mut c_void
+    }
+}
+
+/// VkPhysicalDeviceAccelerationStructureFeaturesKHR
+#[repr(C)]
+struct AccelerationStructureFeatures {
+    s_type: u32,
+    p_next: *mut c_void,
+    acceleration_structure: u32,
+    acceleration_structure_capture_replay: u32,
+    acceleration_structure_indirect_build: u32,
+    acceleration_structure_host_commands: u32,
+    descriptor_binding_acceleration_structure_update_after_bind: u32,
+}
+
+unsafe impl FeatureStruct for AccelerationStructureFeatures {}
+
+/// VkPhysicalDeviceRayTracingPipelineFeaturesKHR
+#[repr(C)]
+struct RayTracingPipelineFeatures {
+    s_type: u32,
+    p_next: *mut c_void,
+    ray_tracing_pipeline: u32,
+    ray_tracing_pipeline_shader_group_handle_capture_replay: u32,
+    ray_tracing_pipeline_shader_group_handle_capture_replay_mixed: u32,
+    ray_tracing_pipeline_trace_rays_indirect: u32,
+    ray_traversal_primitive_culling: u32,
+}
+
+unsafe impl FeatureStruct for RayTracingPipelineFeatures {}
+
+/// VkPhysicalDeviceRayQueryFeaturesKHR
+#[repr(C)]
+struct RayQueryFeatures {
+    s_type: u32,
+    p_next: *mut c_void,
+    ray_query: u32,
+}
+
+unsafe impl FeatureStruct for RayQueryFeatures {}
+
+/// VkPhysicalDeviceBufferDeviceAddressFeaturesKHR
+#[repr(C)]
+struct BufferDeviceAddressFeatures {
+    s_type: u32,
+    p_next: *mut c_void,
+    buffer_device_address: u32,
+    buffer_device_address_capture_replay: u32,
+    buffer_device_address_multi_device: u32,
+}
+
+unsafe impl FeatureStruct for BufferDeviceAddressFeatures {}
+
+
+/// Device filter which only accepts devices that support some flavor of ray
+/// tracing
+///
+/// This is meant to be used as (part of) the "other_criteria" parameter of
+/// easy_device_filter(). Devices whose extensions cannot be queried are
+/// rejected.
+///
+pub fn supports_ray_tracing(
+    mode: RayTracingMode
+) -> impl FnMut(PhysicalDevice) -> bool {
+    move |device| {
+        RayTracingSupport::of_device(device)
+            .map(|support| support.supports(mode))
+            .unwrap_or(false)
+    }
+}
+
+
+/// Add the extensions needed by some flavor of ray tracing to a set of device
+/// extensions, and the extension features to a feature chain, or tell which
+/// ones are missing
+pub(crate) fn add_device_extensions(
+    device: PhysicalDevice,
+    mode: RayTracingMode,
+    extensions: &mut RawDeviceExtensions,
+    feature_chain: &mut FeatureChain,
+) -> Result<()> {
+    let _trace = trace::span("raytracing::add_device_extensions", || {
+        format!("device: {}, mode: {:?}", device.name(), mode)
+    });
+    let support = RayTracingSupport::of_device(device)?;
+    let missing = support.missing_extensions(mode);
+    if !missing.is_empty() {
+        return Err(Error::MissingExtensions {
+            device: device.name(),
+            extensions: missing,
+        });
+    }
+    let missing = support.missing_features(mode);
+    if !missing.is_empty() {
+        return Err(Error::MissingFeatures {
+            device: device.name(),
+            features: missing,
+        });
+    }
+    for name in mode.extensions() {
+        extensions.insert(CString::new(name)
+                                  .expect("Extension name has no NUL"));
+    }
+    let features = RawRayTracingFeatures::new(1);
+    feature_chain.push(features.acceleration_structure);
+    match mode {
+        RayTracingMode::Pipeline =>
+            feature_chain.push(features.ray_tracing_pipeline),
+        RayTracingMode::Query => feature_chain.push(features.ray_query),
+    }
+    feature_chain.push(features.buffer_device_address);
+    Ok(())
+}
+
+
+/// Triangle geometry, as consumed by bottom-level acceleration structure
+/// builds (VkAccelerationStructureGeometryTrianglesDataKHR)
+///
+/// Vertices are tightly packed 3D positions (VK_FORMAT_R32G32B32_SFLOAT),
+/// and triangles are described by triplets of 32-bit indices
+/// (VK_INDEX_TYPE_UINT32).
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TriangleGeometry {
+    /// Vertex positions
+    pub vertices: Vec<[f32; 3]>,
+
+    /// Vertex indices, three per triangle
+    pub indices: Vec<u32>,
+}
+
+impl TriangleGeometry {
+    /// Validate some triangle data
+    pub fn new(vertices: Vec<[f32; 3]>, indices: Vec<u32>) -> Result<Self> {
+        if indices.len() % 3 != 0 {
+            return Err(Error::InvalidArgument(format!(
+                "Triangle geometry has {} indices, which is not a multiple \
+                 of 3", indices.len()
+            )));
+        }
+        if let Some(&index) = indices.iter()
+                                     .find(|&&i| i as usize >= vertices.len())
+        {
+            return Err(Error::InvalidArgument(format!(
+                "Triangle geometry has index {}, but only {} vertices",
+                index, vertices.len()
+            )));
+        }
+        Ok(TriangleGeometry { vertices, indices })
+    }
+
+    /// Build non-indexed triangle geometry, where each consecutive triplet of
+    /// vertices is a triangle
+    pub fn from_triangles(vertices: Vec<[f32; 3]>) -> Result<Self> {
+        let indices = (0..vertices.len() as u32).collect();
+        Self::new(vertices, indices)
+    }
+
+    /// Number of triangles (primitiveCount of the build range)
+    pub fn triangle_count(&self) -> u32 {
+        (self.indices.len() / 3) as u32
+    }
+
+    /// Highest vertex index (maxVertex of the triangle data)
+    pub fn max_vertex(&self) -> u32 {
+        self.indices.iter().cloned().max().unwrap_or(0)
+    }
+
+    /// Distance between consecutive vertices in bytes (vertexStride)
+    pub fn vertex_stride(&self) -> u64 {
+        ::std::mem::size_of::<[f32; 3]>() as u64
+    }
+}
+
+
+/// Instance of a bottom-level acceleration structure within a top-level one
+/// (VkAccelerationStructureInstanceKHR)
+///
+/// This has the memory layout that top-level acceleration structure builds
+/// expect, so a slice of these can be uploaded as-is to the instance buffer.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct AccelerationStructureInstance {
+    /// Row-major 3x4 transform from object space to world space
+    pub transform: [[f32; 4]; 3],
+
+    /// Custom index (low 24 bits) and visibility mask (high 8 bits)
+    pub custom_index_and_mask: u32,
+
+    /// Hit group offset in the shader binding table (low 24 bits) and
+    /// VkGeometryInstanceFlagsKHR (high 8 bits)
+    pub sbt_offset_and_flags: u32,
+
+    /// Device address of the bottom-level acceleration structure
+    pub acceleration_structure_reference: u64,
+}
+
+impl AccelerationStructureInstance {
+    /// Identity transform
+    pub const IDENTITY: [[f32; 4]; 3] = [[1.0, 0.0, 0.0, 0.0],
+                                         [0.0, 1.0, 0.0, 0.0],
+                                         [0.0, 0.0, 1.0, 0.0]];
+
+    /// Describe an instance, which is visible to all rays and uses the first
+    /// hit group of the shader binding table
+    pub fn new(blas_address: u64,
+               transform: [[f32; 4]; 3],
+               custom_index: u32) -> Result<Self> {
+        if custom_index >= 1 << 24 {
+            return Err(Error::InvalidArgument(format!(
+                "Instance custom index {} does not fit in 24 bits",
+                custom_index
+            )));
+        }
+        Ok(AccelerationStructureInstance {
+            transform,
+            custom_index_and_mask: custom_index | 0xff << 24,
+            sbt_offset_and_flags: 0,
+            acceleration_structure_reference: blas_address,
+        })
+    }
+
+    /// Set the visibility mask, which rays are tested against
+    pub fn with_mask(mut self, mask: u8) -> Self {
+        self.custom_index_and_mask =
+            (self.custom_index_and_mask & 0x00ff_ffff) | u32::from(mask) << 24;
+        self
+    }
+
+    /// Set the hit group offset in the shader binding table, and the
+    /// instance flags (VkGeometryInstanceFlagsKHR)
+    pub fn with_sbt_offset_and_flags(mut self,
+                                     sbt_offset: u32,
+                                     flags: u8) -> Result<Self> {
+        if sbt_offset >= 1 << 24 {
+            return Err(Error::InvalidArgument(format!(
+                "Shader binding table offset {} does not fit in 24 bits",
+                sbt_offset
+            )));
+        }
+        self.sbt_offset_and_flags = sbt_offset | u32::from(flags) << 24;
+        Ok(self)
+    }
+}
+
+
+/// Level of an acceleration structure
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccelerationStructureLevel {
+    /// Bottom-level acceleration structure, containing triangles
+    Bottom,
+
+    /// Top-level acceleration structure, containing instances of bottom-level
+    /// acceleration structures
+    Top,
+}
+
+
+/// Acceleration structure, along with the device memory which backs it
+///
+/// Builds are recorded in a one-shot command buffer, submitted to a queue
+/// with compute support, and waited for. The device must have been set up
+/// using EasyPhysicalDevice::setup_ray_tracing_device().
+///
+pub struct AccelerationStructure {
+    /// Entry points of VK_KHR_acceleration_structure
+    fns: AccelerationStructureFns,
+
+    /// Raw acceleration structure handle
+    handle: u64,
+
+    /// Level of the acceleration structure
+    level: AccelerationStructureLevel,
+
+    /// Device address of the acceleration structure
+    address: u64,
+
+    /// Buffer which the acceleration structure is stored in
+    _storage: RawBuffer,
+}
+
+impl AccelerationStructure {
+    /// Build a bottom-level acceleration structure, with one opaque geometry
+    /// per set of triangles
+    pub fn build_bottom_level(device: &EasyDevice,
+                              queue: &Arc<Queue>,
+                              geometries: &[TriangleGeometry]) -> Result<Self> {
+        let _trace = trace::span(
+            "AccelerationStructure::build_bottom_level",
+            || format!("{} geometries", geometries.len())
+        );
+        if geometries.is_empty() {
+            return Err(Error::InvalidArgument(
+                "Bottom-level acceleration structures need some geometry"
+                    .to_owned()
+            ));
+        }
+        let fns = AccelerationStructureFns::load(device)?;
+        let device = device.device();
+
+        // Upload the triangles to the device
+        let mut inputs = Vec::with_capacity(geometries.len());
+        for geometry in geometries {
+            let vertices = RawBuffer::upload(device, &fns, &geometry.vertices)?;
+            let indices = RawBuffer::upload(device, &fns, &geometry.indices)?;
+            inputs.push((vertices, indices));
+        }
+
+        // Describe the geometries
+        let descriptions = geometries.iter().zip(&inputs).map(
+            |(geometry, &(ref vertices, ref indices))| GeometryInfo {
+                s_type: STRUCTURE_TYPE_ACCELERATION_STRUCTURE_GEOMETRY,
+                p_next: ptr::null(),
+                geometry_type: GEOMETRY_TYPE_TRIANGLES,
+                geometry: GeometryData {
+                    triangles: TrianglesData {
+                        s_type: STRUCTURE_TYPE_GEOMETRY_TRIANGLES_DATA,
+                        p_next: ptr::null(),
+                        vertex_format: FORMAT_R32G32B32_SFLOAT,
+                        vertex_data: vertices.address,
+                        vertex_stride: geometry.vertex_stride(),
+                        max_vertex: geometry.max_vertex(),
+                        index_type: INDEX_TYPE_UINT32,
+                        index_data: indices.address,
+                        transform_data: 0,
+                    },
+                },
+                flags: GEOMETRY_OPAQUE,
+            }
+        ).collect::<Vec<_>>();
+        let primitive_counts = geometries.iter()
+                                         .map(TriangleGeometry::triangle_count)
+                                         .collect::<Vec<_>>();
+        Self::build(device,
+                    fns,
+                    queue,
+                    AccelerationStructureLevel::Bottom,
+                    &descriptions,
+                    &primitive_counts)
+    }
+
+    /// Build a top-level acceleration structure from instances of
+    /// bottom-level acceleration structures
+    ///
+    /// The bottom-level acceleration structures which the instances refer to
+    /// must be kept alive for as long as the top-level one is used.
+    ///
+    pub fn build_top_level(
+        device: &EasyDevice,
+        queue: &Arc<Queue>,
+        instances: &[AccelerationStructureInstance]
+    ) -> Result<Self> {
+        let _trace = trace::span(
+            "AccelerationStructure::build_top_level",
+            || format!("{} instances", instances.len())
+        );
+        if instances.is_empty() {
+            return Err(Error::InvalidArgument(
+                "Top-level acceleration structures need some instances"
+                    .to_owned()
+            ));
+        }
+        let fns = AccelerationStructureFns::load(device)?;
+        let device = device.device();
+
+        // Upload the instances to the device, and describe them
+        let instance_buffer = RawBuffer::upload(device, &fns, instances)?;
+        let description = GeometryInfo {
+            s_type: STRUCTURE_TYPE_ACCELERATION_STRUCTURE_GEOMETRY,
+            p_next: ptr::null(),
+            geometry_type: GEOMETRY_TYPE_INSTANCES,
+            geometry: GeometryData {
+                instances: InstancesData {
+                    s_type: STRUCTURE_TYPE_GEOMETRY_INSTANCES_DATA,
+                    p_next: ptr::null(),
+                    array_of_pointers: 0,
+                    data: instance_buffer.address,
+                },
+            },
+            flags: GEOMETRY_OPAQUE,
+        };
+        Self::build(device,
+                    fns,
+                    queue,
+                    AccelerationStructureLevel::Top,
+                    &[description],
+                    &[instances.len() as u32])
+    }
+
+    /// Level of the acceleration structure
+    pub fn level(&self) -> AccelerationStructureLevel {
+        self.level
+    }
+
+    /// Raw VkAccelerationStructureKHR handle, e.g. for descriptor writes
+    pub fn handle(&self) -> u64 {
+        self.handle
+    }
+
+    /// Device address of the acceleration structure, which is what
+    /// AccelerationStructureInstance::new() expects for bottom-level ones
+    pub fn device_address(&self) -> u64 {
+        self.address
+    }
+
+    /// Build an acceleration structure from a set of geometries, each
+    /// containing a certain number of primitives
+    fn build(device: &Arc<Device>,
+             fns: AccelerationStructureFns,
+             queue: &Arc<Queue>,
+             level: AccelerationStructureLevel,
+             geometries: &[GeometryInfo],
+             primitive_counts: &[u32]) -> Result<Self> {
+        if !queue.family().supports_compute() {
+            return Err(Error::InvalidArgument(
+                "Acceleration structures must be built on a queue with \
+                 compute support".to_owned()
+            ));
+        }
+        let raw_device = device.internal_object();
+        let ty = match level {
+            AccelerationStructureLevel::Bottom =>
+                ACCELERATION_STRUCTURE_TYPE_BOTTOM_LEVEL,
+            AccelerationStructureLevel::Top =>
+                ACCELERATION_STRUCTURE_TYPE_TOP_LEVEL,
+        };
+        let mut build_info = BuildGeometryInfo {
+            s_type: STRUCTURE_TYPE_ACCELERATION_STRUCTURE_BUILD_GEOMETRY_INFO,
+            p_next: ptr::null(),
+            ty,
+            flags: BUILD_PREFER_FAST_TRACE,
+            mode: BUILD_MODE_BUILD,
+            src_acceleration_structure: 0,
+            dst_acceleration_structure: 0,
+            geometry_count: geometries.len() as u32,
+            geometries: geometries.as_ptr(),
+            geometry_pointers: ptr::null(),
+            scratch_data: 0,
+        };
+
+        // Ask how much memory the build needs
+        let mut sizes = BuildSizesInfo {
+            s_type: STRUCTURE_TYPE_ACCELERATION_STRUCTURE_BUILD_SIZES_INFO,
+            p_next: ptr::null(),
+            acceleration_structure_size: 0,
+            update_scratch_size: 0,
+            build_scratch_size: 0,
+        };
+        (fns.get_build_sizes)(raw_device,
+                              BUILD_TYPE_DEVICE,
+                              &build_info,
+                              primitive_counts.as_ptr(),
+                              &mut sizes);
+        debug!("Building a {:?}-level acceleration structure of {} bytes, \
+                with {} bytes of scratch memory",
+               level, sizes.acceleration_structure_size,
+               sizes.build_scratch_size);
+
+        // Create the acceleration structure
+        let storage = RawBuffer::new(
+            device,
+            &fns,
+            sizes.acceleration_structure_size,
+            BUFFER_USAGE_ACCELERATION_STRUCTURE_STORAGE,
+            false
+        )?;
+        let create_info = CreateInfo {
+            s_type: STRUCTURE_TYPE_ACCELERATION_STRUCTURE_CREATE_INFO,
+            p_next: ptr::null(),
+            create_flags: 0,
+            buffer: storage.buffer,
+            offset: 0,
+            size: sizes.acceleration_structure_size,
+            ty,
+            device_address: 0,
+        };
+        let mut handle = 0;
+        check_result((fns.create)(raw_device,
+                                  &create_info,
+                                  ptr::null(),
+                                  &mut handle),
+                     "vkCreateAccelerationStructureKHR")?;
+        let address_info = AddressInfo {
+            s_type: STRUCTURE_TYPE_ACCELERATION_STRUCTURE_DEVICE_ADDRESS_INFO,
+            p_next: ptr::null(),
+            acceleration_structure: handle,
+        };
+        let address = (fns.get_address)(raw_device, &address_info);
+        let acceleration_structure = AccelerationStructure {
+            fns,
+            handle,
+            level,
+            address,
+            _storage: storage,
+        };
+
+        // Allocate scratch memory, with the alignment that the device needs
+        let alignment = u64::from(scratch_alignment(device.physical_device()));
+        let scratch = RawBuffer::new(
+            device,
+            &acceleration_structure.fns,
+            sizes.build_scratch_size + alignment,
+            vk::BUFFER_USAGE_STORAGE_BUFFER_BIT,
+            false
+        )?;
+        build_info.dst_acceleration_structure = handle;
+        build_info.scratch_data =
+            (scratch.address + alignment - 1) / alignment * alignment;
+
+        // Record and carry out the build
+        let ranges = primitive_counts.iter().map(|&count| BuildRangeInfo {
+            primitive_count: count,
+            primitive_offset: 0,
+            first_vertex: 0,
+            transform_offset: 0,
+        }).collect::<Vec<_>>();
+        let range_pointer = ranges.as_ptr();
+        let command_buffer = RawCommandBuffer::new(queue)?;
+        (acceleration_structure.fns.cmd_build)(command_buffer.handle(),
+                                               1,
+                                               &build_info,
+                                               &range_pointer);
+        command_buffer.submit_and_wait()?;
+        Ok(acceleration_structure)
+    }
+}
+
+impl Drop for AccelerationStructure {
+    fn drop(&mut self) {
+        (self.fns.destroy)(self.fns.device.internal_object(),
+                           self.handle,
+                           ptr::null());
+    }
+}
+
+
+/// Entry points of VK_KHR_acceleration_structure and
+/// VK_KHR_buffer_device_address, which vk-sys does not know about
+struct AccelerationStructureFns {
+    device: Arc<Device>,
+    get_build_sizes: extern "system" fn(vk::Device,
+                                        u32,
+                                        *const BuildGeometryInfo,
+                                        *const u32,
+                                        *mut BuildSizesInfo),
+    create: extern "system" fn(vk::Device,
+                               *const CreateInfo,
+                               *const vk::AllocationCallbacks,
+                               *mut u64) -> vk::Result,
+    destroy: extern "system" fn(vk::Device,
+                                u64,
+                                *const vk::AllocationCallbacks),
+    cmd_build: extern "system" fn(vk::CommandBuffer,
+                                  u32,
+                                  *const BuildGeometryInfo,
+                                  *const *const BuildRangeInfo),
+    get_address: extern "system" fn(vk::Device, *const AddressInfo) -> u64,
+    get_buffer_address: extern "system" fn(vk::Device,
+                                           *const BufferAddressInfo) -> u64,
+}
+
+impl AccelerationStructureFns {
+    /// Load the entry points, checking that ray tracing was set up
+    fn load(device: &EasyDevice) -> Result<Self> {
+        if !device.is_extension_enabled(ACCELERATION_STRUCTURE_EXTENSION) {
+            return Err(Error::InvalidArgument(format!(
+                "{} was not enabled on the device, use \
+                 EasyPhysicalDevice::setup_ray_tracing_device()",
+                ACCELERATION_STRUCTURE_EXTENSION
+            )));
+        }
+        let device = device.device();
+        let load = |name| loader::device_function(device, name);
+        // Safe because the signatures match the Vulkan specification
+        unsafe {
+            Ok(AccelerationStructureFns {
+                device: device.clone(),
+                get_build_sizes: mem::transmute(
+                    load("vkGetAccelerationStructureBuildSizesKHR")?
+                ),
+                create: mem::transmute(
+                    load("vkCreateAccelerationStructureKHR")?
+                ),
+                destroy: mem::transmute(
+                    load("vkDestroyAccelerationStructureKHR")?
+                ),
+                cmd_build: mem::transmute(
+                    load("vkCmdBuildAccelerationStructuresKHR")?
+                ),
+                get_address: mem::transmute(
+                    load("vkGetAccelerationStructureDeviceAddressKHR")?
+                ),
+                get_buffer_address: mem::transmute(
+                    load("vkGetBufferDeviceAddressKHR")?
+                ),
+            })
+        }
+    }
+}
+
+
+/// Raw buffer with a device address, which vulkano cannot create yet
+struct RawBuffer {
+    device: Arc<Device>,
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    address: u64,
+}
+
+impl RawBuffer {
+    /// Create a buffer with dedicated memory, which is host-visible if
+    /// requested and device-local otherwise
+    fn new(device: &Arc<Device>,
+           fns: &AccelerationStructureFns,
+           size: u64,
+           usage: u32,
+           host_visible: bool) -> Result<Self> {
+        let raw_device = device.internal_object();
+        let vk_fns = device.pointers();
+        let buffer_info = vk::BufferCreateInfo {
+            sType: vk::STRUCTURE_TYPE_BUFFER_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            size: size.max(1),
+            usage: usage | BUFFER_USAGE_SHADER_DEVICE_ADDRESS,
+            sharingMode: vk::SHARING_MODE_EXCLUSIVE,
+            queueFamilyIndexCount: 0,
+            pQueueFamilyIndices: ptr::null(),
+        };
+        let mut raw_buffer = RawBuffer {
+            device: device.clone(),
+            buffer: 0,
+            memory: 0,
+            address: 0,
+        };
+        unsafe {
+            check_result(vk_fns.CreateBuffer(raw_device,
+                                             &buffer_info,
+                                             ptr::null(),
+                                             &mut raw_buffer.buffer),
+                         "vkCreateBuffer")?;
+            let mut requirements = mem::zeroed::<vk::MemoryRequirements>();
+            vk_fns.GetBufferMemoryRequirements(raw_device,
+                                               raw_buffer.buffer,
+                                               &mut requirements);
+            let memory_type = device.physical_device()
+                .memory_types()
+                .find(|ty| {
+                    requirements.memoryTypeBits & (1 << ty.id()) != 0
+                        && if host_visible {
+                            ty.is_host_visible() && ty.is_host_coherent()
+                        } else {
+                            ty.is_device_local()
+                        }
+                })
+                .ok_or_else(|| Error::Vulkan(format_err!(
+                    "No suitable memory type for a ray tracing buffer"
+                )))?;
+            let flags_info = MemoryAllocateFlagsInfo {
+                s_type: STRUCTURE_TYPE_MEMORY_ALLOCATE_FLAGS_INFO,
+                p_next: ptr::null(),
+                flags: MEMORY_ALLOCATE_DEVICE_ADDRESS,
+                device_mask: 0,
+            };
+            let alloc_info = vk::MemoryAllocateInfo {
+                sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
+                pNext: &flags_info as *const _ as *const c_void,
+                allocationSize: requirements.size,
+                memoryTypeIndex: memory_type.id(),
+            };
+            check_result(vk_fns.AllocateMemory(raw_device,
+                                               &alloc_info,
+                                               ptr::null(),
+                                               &mut raw_buffer.memory),
+                         "vkAllocateMemory")?;
+            check_result(vk_fns.BindBufferMemory(raw_device,
+                                                 raw_buffer.buffer,
+                                                 raw_buffer.memory,
+                                                 0),
+                         "vkBindBufferMemory")?;
+        }
+        let address_info = BufferAddressInfo {
+            s_type: STRUCTURE_TYPE_BUFFER_DEVICE_ADDRESS_INFO,
+            p_next: ptr::null(),
+            buffer: raw_buffer.buffer,
+        };
+        raw_buffer.address = (fns.get_buffer_address)(raw_device,
+                                                      &address_info);
+        Ok(raw_buffer)
+    }
+
+    /// Create a host-visible build input buffer, containing some data
+    fn upload<T: Copy>(device: &Arc<Device>,
+                       fns: &AccelerationStructureFns,
+                       data: &[T]) -> Result<Self> {
+        let size = (data.len() * mem::size_of::<T>()) as u64;
+        let buffer = Self::new(device,
+                               fns,
+                               size,
+                               BUFFER_USAGE_BUILD_INPUT_READ_ONLY,
+                               true)?;
+        let vk_fns = device.pointers();
+        unsafe {
+            let mut mapping = ptr::null_mut();
+            check_result(vk_fns.MapMemory(device.internal_object(),
+                                          buffer.memory,
+                                          0,
+                                          vk::WHOLE_SIZE,
+                                          0,
+                                          &mut mapping),
+                         "vkMapMemory")?;
+            ptr::copy_nonoverlapping(data.as_ptr(),
+                                     mapping as *mut T,
+                                     data.len());
+            vk_fns.UnmapMemory(device.internal_object(), buffer.memory);
+        }
+        Ok(buffer)
+    }
+}
+
+impl Drop for RawBuffer {
+    fn drop(&mut self) {
+        let raw_device = self.device.internal_object();
+        let vk_fns = self.device.pointers();
+        unsafe {
+            if self.buffer != 0 {
+                vk_fns.DestroyBuffer(raw_device, self.buffer, ptr::null());
+            }
+            if self.memory != 0 {
+                vk_fns.FreeMemory(raw_device, self.memory, ptr::null());
+            }
+        }
+    }
+}
+
+
+/// Scratch buffer alignment of a device
+/// (minAccelerationStructureScratchOffsetAlignment)
+fn scratch_alignment(device: PhysicalDevice) -> u32 {
+    let mut properties = AccelerationStructureProperties {
+        s_type: STRUCTURE_TYPE_ACCELERATION_STRUCTURE_PROPERTIES,
+        p_next: ptr::null_mut(),
+        max_geometry_count: 0,
+        max_instance_count: 0,
+        max_primitive_count: 0,
+        max_per_stage_descriptor_acceleration_structures: 0,
+        max_per_stage_descriptor_update_after_bind_acceleration_structures: 0,
+        max_descriptor_set_acceleration_structures: 0,
+        max_descriptor_set_update_after_bind_acceleration_structures: 0,
+        min_acceleration_structure_scratch_offset_alignment: 0,
+    };
+    unsafe {
+        properties2::query_properties(
+            device,
+            &mut properties as *mut _ as *mut c_void
+        );
+    }
+    properties.min_acceleration_structure_scratch_offset_alignment.max(1)
+}
+
+/// VkAccelerationStructureGeometryTrianglesDataKHR
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct TrianglesData {
+    s_type: u32,
+    p_next: *const c_void,
+    vertex_format: u32,
+    vertex_data: u64,
+    vertex_stride: u64,
+    max_vertex: u32,
+    index_type: u32,
+    index_data: u64,
+    transform_data: u64,
+}
+
+/// VkAccelerationStructureGeometryInstancesDataKHR
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct InstancesData {
+    s_type: u32,
+    p_next: *const c_void,
+    array_of_pointers: u32,
+    data: u64,
+}
+
+/// VkAccelerationStructureGeometryDataKHR
+#[derive(Clone, Copy)]
+#[repr(C)]
+union GeometryData {
+    triangles: TrianglesData,
+    instances: InstancesData,
+}
+
+/// VkAccelerationStructureGeometryKHR
+#[repr(C)]
+struct GeometryInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    geometry_type: u32,
+    geometry: GeometryData,
+    flags: u32,
+}
+
+/// VkAccelerationStructureBuildGeometryInfoKHR
+#[repr(C)]
+struct BuildGeometryInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    ty: u32,
+    flags: u32,
+    mode: u32,
+    src_acceleration_structure: u64,
+    dst_acceleration_structure: u64,
+    geometry_count: u32,
+    geometries: *const GeometryInfo,
+    geometry_pointers: *const *const GeometryInfo,
+    scratch_data: u64,
+}
+
+/// VkAccelerationStructureBuildRangeInfoKHR
+#[repr(C)]
+struct BuildRangeInfo {
+    primitive_count: u32,
+    primitive_offset: u32,
+    first_vertex: u32,
+    transform_offset: u32,
+}
+
+/// VkAccelerationStructureBuildSizesInfoKHR
+#[repr(C)]
+struct BuildSizesInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    acceleration_structure_size: u64,
+    update_scratch_size: u64,
+    build_scratch_size: u64,
+}
+
+/// VkAccelerationStructureCreateInfoKHR
+#[repr(C)]
+struct CreateInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    create_flags: u32,
+    buffer: vk::Buffer,
+    offset: u64,
+    size: u64,
+    ty: u32,
+    device_address: u64,
+}
+
+/// VkAccelerationStructureDeviceAddressInfoKHR
+#[repr(C)]
+struct AddressInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    acceleration_structure: u64,
+}
+
+/// VkBufferDeviceAddressInfoKHR
+#[repr(C)]
+struct BufferAddressInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    buffer: vk::Buffer,
+}
+
+/// VkMemoryAllocateFlagsInfo
+#[repr(C)]
+struct MemoryAllocateFlagsInfo {
+    s_type: u32,
+    p_next: *const c_void,
+    flags: u32,
+    device_mask: u32,
+}
+
+/// VkPhysicalDeviceAccelerationStructurePropertiesKHR
+#[repr(C)]
+struct AccelerationStructureProperties {
+    s_type: u32,
+    p_next: *mut c_void,
+    max_geometry_count: u64,
+    max_instance_count: u64,
+    max_primitive_count: u64,
+    max_per_stage_descriptor_acceleration_structures: u32,
+    max_per_stage_descriptor_update_after_bind_acceleration_structures: u32,
+    max_descriptor_set_acceleration_structures: u32,
+    max_descriptor_set_update_after_bind_acceleration_structures: u32,
+    min_acceleration_structure_scratch_offset_alignment: u32,
+}
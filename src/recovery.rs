@@ -0,0 +1,216 @@
+//! Detection of, and recovery from, device loss
+//!
+//! Flaky drivers and GPU hangs can make Vulkan devices "lost", at which point
+//! every subsequent submission fails and the device must be recreated. This
+//! module lets EasyDevice notice when this happens, log everything that could
+//! help figuring out why, and hand over control to user-defined recovery code.
+
+use ::{
+    api_level::ApiLevel,
+    device::{
+        EasyPhysicalDevice,
+        EnabledCapabilities,
+    },
+    hotplug::DeviceIdentity,
+    instance::EasyInstance,
+    report,
+};
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex, TryLockError},
+};
+
+use vulkano::instance::{
+    Instance,
+    PhysicalDevice,
+};
+
+
+/// What is needed to set up a replacement for a lost device
+///
+/// The physical device is recorded by UUID, rather than by its index in the
+/// enumeration, so that it can also be found again in another instance (e.g.
+/// one created after rescanning the devices).
+///
+#[derive(Clone)]
+pub struct DeviceSelection {
+    /// Instance which the device was created from
+    instance: Arc<EasyInstance>,
+
+    /// Identity of the physical device
+    identity: DeviceIdentity,
+
+    /// Vulkan API version requirements which the device was selected with
+    api_level: ApiLevel,
+
+    /// Features and extensions which were enabled on the device
+    capabilities: EnabledCapabilities,
+}
+
+impl DeviceSelection {
+    /// Record how a device was set up
+    pub(crate) fn new(instance: Arc<EasyInstance>,
+                      physical_device: PhysicalDevice,
+                      capabilities: EnabledCapabilities) -> Self {
+        let api_level = instance.api_level();
+        DeviceSelection {
+            instance,
+            identity: DeviceIdentity::new(physical_device),
+            api_level,
+            capabilities,
+        }
+    }
+
+    /// Identity of the physical device which the lost device was created from
+    pub fn identity(&self) -> &DeviceIdentity {
+        &self.identity
+    }
+
+    /// Physical device which the lost device was created from, if it is
+    /// still enumerated by the instance
+    pub fn physical_device(&self) -> Option<PhysicalDevice> {
+        self.find(self.instance.instance())
+    }
+
+    /// Same as physical_device(), but ready for setting up a new device
+    ///
+    /// Use it along with capabilities() to set up a replacement device in
+    /// the same way as the lost one.
+    ///
+    pub fn easy_physical_device(&self) -> Option<EasyPhysicalDevice> {
+        self.easy_physical_device_in(&self.instance)
+    }
+
+    /// Look up the physical device in another instance, and get it ready for
+    /// setting up a new device with the same API level requirements
+    pub fn easy_physical_device_in(
+        &self,
+        instance: &Arc<EasyInstance>
+    ) -> Option<EasyPhysicalDevice> {
+        self.find(instance.instance()).map(|device| {
+            EasyPhysicalDevice::with_api_level(instance.clone(),
+                                               device,
+                                               &self.api_level)
+        })
+    }
+
+    /// Vulkan API version requirements which the device was selected with
+    pub fn api_level(&self) -> ApiLevel {
+        self.api_level
+    }
+
+    /// Features and extensions which were enabled on the lost device
+    pub fn capabilities(&self) -> &EnabledCapabilities {
+        &self.capabilities
+    }
+
+    /// Find the physical device among those of an instance
+    fn find<'a>(&self,
+                instance: &'a Arc<Instance>) -> Option<PhysicalDevice<'a>> {
+        PhysicalDevice::enumerate(instance)
+            .find(|device| *device.uuid() == self.identity.uuid)
+    }
+}
+
+
+/// Diagnostic information about a lost device
+#[derive(Clone)]
+pub struct DeviceLostReport {
+    /// Name of the device
+    pub device_name: String,
+
+    /// Description of the last work that was submitted to the device, if any
+    pub last_submission: Option<String>,
+
+    /// How the device was set up, for the purpose of recreating it
+    pub selection: DeviceSelection,
+}
+
+impl fmt::Display for DeviceLostReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Device \"{}\" was lost", self.device_name)?;
+        writeln!(f, "Last submitted work: {}",
+                 self.last_submission.as_ref()
+                                     .map(|s| &s[..])
+                                     .unwrap_or("<unknown>"))?;
+        let capabilities = self.selection.capabilities();
+        writeln!(f, "Enabled features: {:?}",
+                 report::feature_names(&capabilities.features))?;
+        write!(f, "Enabled extensions: {:?}",
+               report::extension_names(&capabilities.extensions))
+    }
+}
+
+
+/// Callback which is invoked when a device is lost
+pub type DeviceLostCallback = Box<dyn FnMut(&DeviceLostReport) + Send>;
+
+
+/// Device loss tracking state of an EasyDevice
+#[derive(Clone)]
+pub(crate) struct DeviceLostHooks {
+    /// How the device was set up
+    selection: DeviceSelection,
+
+    /// Description of the last work that was submitted to the device
+    last_submission: Arc<Mutex<Option<String>>>,
+
+    /// User-defined recovery callback
+    ///
+    /// The callback has its own lock, so that it can be invoked without
+    /// holding the lock of the hooks, which it may want to use (e.g. to
+    /// register another callback).
+    ///
+    callback: Arc<Mutex<Option<Arc<Mutex<DeviceLostCallback>>>>>,
+}
+
+impl DeviceLostHooks {
+    /// Start tracking device loss
+    pub fn new(selection: DeviceSelection) -> Self {
+        DeviceLostHooks {
+            selection,
+            last_submission: Arc::new(Mutex::new(None)),
+            callback: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Register the recovery callback, replacing any previous one
+    pub fn set_callback(&self, callback: DeviceLostCallback) {
+        *self.callback.lock().unwrap() = Some(Arc::new(Mutex::new(callback)));
+    }
+
+    /// Record a description of the work that is being submitted
+    pub fn note_submission(&self, description: String) {
+        *self.last_submission.lock().unwrap() = Some(description);
+    }
+
+    /// Report device loss and invoke the recovery callback
+    pub fn device_lost(&self) {
+        let report = DeviceLostReport {
+            device_name: self.selection.identity().name.clone(),
+            last_submission: self.last_submission.lock().unwrap().clone(),
+            selection: self.selection.clone(),
+        };
+        for line in report.to_string().lines() {
+            error!("{}", line);
+        }
+
+        // Invoke the callback without holding the lock of the hooks
+        let callback = self.callback.lock().unwrap().clone();
+        let callback = match callback {
+            Some(callback) => callback,
+            None => {
+                warn!("No device loss recovery callback was registered");
+                return;
+            },
+        };
+        match callback.try_lock() {
+            Ok(mut callback) => (*callback)(&report),
+            Err(TryLockError::Poisoned(e)) => (*e.into_inner())(&report),
+            Err(TryLockError::WouldBlock) => {
+                warn!("Device loss recovery is already in progress")
+            },
+        }
+    }
+}
@@ -0,0 +1,860 @@
+//! SPIR-V reflection, to derive pipeline layouts from shaders
+//!
+//! Vulkano derives pipeline layouts from the shaders that it compiles itself,
+//! but prototypes which load their SPIR-V at run time must describe the
+//! layout by hand (see EasyComputeLayout), and getting it out of sync with
+//! the shaders is the most common source of validation errors.
+//!
+//! This module reads the descriptor bindings, push constant block and vertex
+//! inputs that a SPIR-V module declares. The reflections of all the shaders
+//! of a pipeline can then be merged into a ReflectedLayout, which implements
+//! vulkano's PipelineLayoutDesc and can thus be used to build pipelines and
+//! descriptor sets (see EasyDescriptorSet::start()).
+//!
+//! SPIR-V modules are parsed using rspirv, and only the parts of the module
+//! that matter to pipeline layouts are then looked at. Vertex inputs are
+//! taken from the interface of the module's vertex shader entry points.
+
+use ::{
+    shader,
+    vertex::EasyVertex,
+    Error,
+    Result,
+};
+
+use rspirv::mr::{
+    self,
+    Instruction,
+    Operand,
+};
+
+use spirv_headers as spirv;
+
+use std::collections::{
+    BTreeMap,
+    HashMap,
+    HashSet,
+};
+
+use vulkano::{
+    descriptor::{
+        descriptor::{
+            DescriptorBufferDesc,
+            DescriptorDesc,
+            DescriptorDescTy,
+            DescriptorImageDesc,
+            DescriptorImageDescArray,
+            DescriptorImageDescDimensions,
+            ShaderStages,
+        },
+        pipeline_layout::{
+            PipelineLayoutDesc,
+            PipelineLayoutDescPcRange,
+        },
+    },
+    pipeline::vertex::VertexMemberTy,
+};
+
+
+/// Kind of scalar that a vertex input is made of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarKind {
+    /// Floating-point numbers
+    Float,
+
+    /// Signed integers
+    Int,
+
+    /// Unsigned integers
+    Uint,
+}
+
+/// A vertex input which is declared by a vertex shader
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VertexInput {
+    /// Input location
+    pub location: u32,
+
+    /// Name of the input variable, if the SPIR-V has debug names
+    pub name: Option<String>,
+
+    /// Kind of scalar that the input is made of
+    pub kind: ScalarKind,
+
+    /// Width of the scalars, in bits
+    pub width: u32,
+
+    /// Number of components (1 for scalars)
+    pub components: u32,
+}
+
+
+/// Pipeline layout information which was extracted from a SPIR-V module
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    /// Shader stages of the module's entry points
+    stages: ShaderStages,
+
+    /// Descriptors, by (set, binding)
+    descriptors: BTreeMap<(u32, u32), DescriptorDesc>,
+
+    /// Descriptors which are runtime-sized arrays, by (set, binding)
+    runtime_arrays: Vec<(u32, u32)>,
+
+    /// Size of the push constant block, if any
+    push_constants_size: Option<u32>,
+
+    /// Vertex inputs, by location
+    vertex_inputs: Vec<VertexInput>,
+}
+
+impl ShaderReflection {
+    /// Reflect a SPIR-V module
+    ///
+    /// Descriptors and push constants are considered to be accessed by all
+    /// the module's entry points, as telling which entry point uses which
+    /// variable would require walking the code. Vertex inputs are the input
+    /// variables in the interface of the module's vertex shader entry points,
+    /// so the inputs of other stages are never reported as vertex inputs.
+    ///
+    pub fn new(spirv: &[u8]) -> Result<Self> {
+        let module = Module::parse(spirv)?;
+        let stages = module.stages();
+        let vertex_interface = module.interface(spirv::ExecutionModel::Vertex);
+        let mut reflection = ShaderReflection {
+            stages,
+            descriptors: BTreeMap::new(),
+            runtime_arrays: Vec::new(),
+            push_constants_size: None,
+            vertex_inputs: Vec::new(),
+        };
+
+        for &(id, pointer, storage_class) in &module.variables {
+            let pointee = match module.types.get(&pointer) {
+                Some(&Type::Pointer { pointee }) => pointee,
+                _ => return Err(Error::InvalidArgument(format!(
+                    "Variable %{} does not have a pointer type", id
+                ))),
+            };
+            match storage_class {
+                spirv::StorageClass::UniformConstant
+                | spirv::StorageClass::Uniform
+                | spirv::StorageClass::StorageBuffer => {
+                    let set = module.decoration(
+                        id, spirv::Decoration::DescriptorSet
+                    );
+                    let binding =
+                        module.decoration(id, spirv::Decoration::Binding);
+                    let (set, binding) = match (set, binding) {
+                        (Some(set), Some(binding)) => (set[0], binding[0]),
+                        _ => continue,
+                    };
+                    let (desc, runtime_array) =
+                        module.descriptor(id, pointee, storage_class, stages)?;
+                    reflection.descriptors.insert((set, binding), desc);
+                    if runtime_array {
+                        reflection.runtime_arrays.push((set, binding));
+                    }
+                },
+                spirv::StorageClass::PushConstant => {
+                    reflection.push_constants_size =
+                        Some(module.size_of(pointee)?);
+                },
+                spirv::StorageClass::Input
+                    if vertex_interface.contains(&id) =>
+                {
+                    if module.has_decoration(id, spirv::Decoration::BuiltIn) {
+                        continue;
+                    }
+                    let location =
+                        module.decoration(id, spirv::Decoration::Location)
+                              .ok_or_else(|| Error::InvalidArgument(format!(
+                                  "Vertex input %{} has no location", id
+                              )))?[0];
+                    module.vertex_inputs(id, pointee, location,
+                                         &mut reflection.vertex_inputs)?;
+                },
+                _ => {},
+            }
+        }
+        reflection.vertex_inputs.sort_by_key(|input| input.location);
+        Ok(reflection)
+    }
+
+    /// Shader stages of the module's entry points
+    pub fn stages(&self) -> ShaderStages {
+        self.stages
+    }
+
+    /// Descriptors which the module declares, by (set, binding)
+    ///
+    /// Runtime-sized arrays are reported with an array_count of 1, see
+    /// ReflectedLayout::set_array_count().
+    ///
+    pub fn descriptors(&self) -> &BTreeMap<(u32, u32), DescriptorDesc> {
+        &self.descriptors
+    }
+
+    /// Descriptors which are runtime-sized arrays, by (set, binding)
+    pub fn runtime_arrays(&self) -> &[(u32, u32)] {
+        &self.runtime_arrays[..]
+    }
+
+    /// Size of the push constant block, if the module declares one
+    pub fn push_constants_size(&self) -> Option<u32> {
+        self.push_constants_size
+    }
+
+    /// Push constant range of the module, if it declares a push constant
+    /// block
+    pub fn push_constants_range(&self) -> Option<PipelineLayoutDescPcRange> {
+        self.push_constants_size.map(|size| PipelineLayoutDescPcRange {
+            offset: 0,
+            size: size as usize,
+            stages: self.stages,
+        })
+    }
+
+    /// Vertex inputs of the module, by location
+    pub fn vertex_inputs(&self) -> &[VertexInput] {
+        &self.vertex_inputs[..]
+    }
+
+    /// Check that a vertex type provides the vertex inputs of the module
+    ///
+    /// Inputs are matched with vertex attributes by name, so this needs the
+    /// SPIR-V to have debug names, and inputs without a name are not checked.
+    /// An attribute may have less components than the input, as Vulkan fills
+    /// in the missing ones, but it must hold the same kind of numbers unless
+    /// it is meant to be normalized (8-bit and 16-bit attributes).
+    ///
+    pub fn check_vertex_type<V: EasyVertex>(&self) -> Result<()> {
+        let attributes = V::attributes();
+        for input in &self.vertex_inputs {
+            let name = match input.name {
+                Some(ref name) => name,
+                None => continue,
+            };
+            let info = match attributes.iter()
+                                       .find(|&&(attr, _)| attr == name) {
+                Some(&(_, ref info)) => info,
+                None => return Err(Error::InvalidArgument(format!(
+                    "The vertex type has no \"{}\" attribute, which the \
+                     shader expects at location {}", name, input.location
+                ))),
+            };
+            let kind = match info.ty {
+                VertexMemberTy::I32 => Some(ScalarKind::Int),
+                VertexMemberTy::U32 => Some(ScalarKind::Uint),
+                VertexMemberTy::F32 | VertexMemberTy::F64 =>
+                    Some(ScalarKind::Float),
+                _ => None,
+            };
+            if kind.map_or(false, |kind| kind != input.kind) {
+                return Err(Error::InvalidArgument(format!(
+                    "Vertex attribute \"{}\" holds {:?} numbers, but the \
+                     shader expects {:?} numbers", name, info.ty, input.kind
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Pipeline layout which was derived from the reflection of some shaders
+#[derive(Clone, Debug)]
+pub struct ReflectedLayout {
+    /// Descriptors of each descriptor set, by binding number
+    sets: Vec<Vec<Option<DescriptorDesc>>>,
+
+    /// Push constant range, if some shader uses push constants
+    push_constants: Option<PipelineLayoutDescPcRange>,
+}
+
+impl ReflectedLayout {
+    /// Merge the reflections of the shaders of a pipeline into its layout
+    ///
+    /// Descriptors which several shaders declare must be compatible, and are
+    /// made visible to all of these shaders. Push constant blocks are merged
+    /// into a single range which is visible to every shader that has one.
+    ///
+    pub fn new(shaders: &[ShaderReflection]) -> Result<Self> {
+        let mut sets = Vec::<Vec<Option<DescriptorDesc>>>::new();
+        let mut push_constants = None::<PipelineLayoutDescPcRange>;
+        for shader in shaders {
+            for (&(set, binding), desc) in &shader.descriptors {
+                let (set, binding) = (set as usize, binding as usize);
+                if sets.len() <= set {
+                    sets.resize(set + 1, Vec::new());
+                }
+                if sets[set].len() <= binding {
+                    sets[set].resize(binding + 1, None);
+                }
+                let slot = &mut sets[set][binding];
+                let merged = match *slot {
+                    Some(ref other) => other.union(desc).ok_or_else(|| {
+                        Error::InvalidArgument(format!(
+                            "Shaders disagree on the type of descriptor \
+                             (set {}, binding {})", set, binding
+                        ))
+                    })?,
+                    None => desc.clone(),
+                };
+                *slot = Some(merged);
+            }
+
+            if let Some(range) = shader.push_constants_range() {
+                push_constants = Some(match push_constants {
+                    Some(other) => PipelineLayoutDescPcRange {
+                        offset: 0,
+                        size: other.size.max(range.size),
+                        stages: union_stages(other.stages, range.stages),
+                    },
+                    None => range,
+                });
+            }
+        }
+        Ok(ReflectedLayout { sets, push_constants })
+    }
+
+    /// Set the number of descriptors of an array binding
+    ///
+    /// This is needed for runtime-sized arrays, whose size the shader does
+    /// not tell, and which are assumed to contain a single descriptor.
+    ///
+    pub fn set_array_count(&mut self,
+                           set: usize,
+                           binding: usize,
+                           count: u32) -> Result<()> {
+        let desc = self.sets.get_mut(set)
+                            .and_then(|bindings| bindings.get_mut(binding))
+                            .and_then(|desc| desc.as_mut())
+                            .ok_or_else(|| Error::InvalidArgument(format!(
+            "No descriptor at (set {}, binding {}) in layout", set, binding
+        )))?;
+        desc.array_count = count;
+        Ok(())
+    }
+}
+
+unsafe impl PipelineLayoutDesc for ReflectedLayout {
+    fn num_sets(&self) -> usize {
+        self.sets.len()
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        self.sets.get(set).map(|bindings| bindings.len())
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.sets.get(set)
+                 .and_then(|bindings| bindings.get(binding))
+                 .and_then(|desc| desc.clone())
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        self.push_constants.iter().count()
+    }
+
+    fn push_constants_range(&self,
+                            num: usize) -> Option<PipelineLayoutDescPcRange> {
+        if num == 0 { self.push_constants } else { None }
+    }
+}
+
+
+/// Union of two sets of shader stages
+fn union_stages(a: ShaderStages, b: ShaderStages) -> ShaderStages {
+    ShaderStages {
+        vertex: a.vertex || b.vertex,
+        tessellation_control: a.tessellation_control || b.tessellation_control,
+        tessellation_evaluation:
+            a.tessellation_evaluation || b.tessellation_evaluation,
+        geometry: a.geometry || b.geometry,
+        fragment: a.fragment || b.fragment,
+        compute: a.compute || b.compute,
+    }
+}
+
+
+/// SPIR-V type, reduced to what reflection needs
+#[derive(Clone, Debug)]
+enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image {
+        dim: spirv::Dim,
+        arrayed: bool,
+        multisampled: bool,
+        sampled: u32,
+    },
+    Sampler,
+    SampledImage { image: u32 },
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+}
+
+/// Decorations and their integer operands
+type Decorations = Vec<(spirv::Decoration, Vec<u32>)>;
+
+/// The parts of a SPIR-V module which reflection needs
+#[derive(Debug, Default)]
+struct Module {
+    /// Execution model and interface variables of each entry point
+    entry_points: Vec<(spirv::ExecutionModel, Vec<u32>)>,
+
+    /// Debug names, by result id
+    names: HashMap<u32, String>,
+
+    /// Decorations, by target id
+    decorations: HashMap<u32, Decorations>,
+
+    /// Decorations of struct members, by (struct id, member index)
+    member_decorations: HashMap<(u32, u32), Decorations>,
+
+    /// Types, by result id
+    types: HashMap<u32, Type>,
+
+    /// Value of 32-bit integer constants, by result id
+    constants: HashMap<u32, u32>,
+
+    /// Global variables, as (result id, pointer type, storage class)
+    variables: Vec<(u32, u32, spirv::StorageClass)>,
+}
+
+impl Module {
+    /// Parse a SPIR-V module, and extract the parts which reflection needs
+    fn parse(spirv: &[u8]) -> Result<Self> {
+        let words = shader::spirv_words(spirv)?;
+        let parsed = mr::load_words(&words).map_err(|e| {
+            Error::InvalidArgument(format!("Failed to parse SPIR-V: {:?}", e))
+        })?;
+        let mut module = Module::default();
+
+        for inst in &parsed.entry_points {
+            if let Some(&Operand::ExecutionModel(model)) =
+                inst.operands.get(0)
+            {
+                let interface = inst.operands.iter()
+                                             .skip(3)
+                                             .filter_map(word)
+                                             .collect();
+                module.entry_points.push((model, interface));
+            }
+        }
+
+        for inst in &parsed.debugs {
+            if let (spirv::Op::Name,
+                    Some(target),
+                    Some(&Operand::LiteralString(ref name)))
+                = (inst.class.opcode, operand(inst, 0), inst.operands.get(1))
+            {
+                module.names.insert(target, name.clone());
+            }
+        }
+
+        for inst in &parsed.annotations {
+            let decoration = |index| match inst.operands.get(index) {
+                Some(&Operand::Decoration(decoration)) => {
+                    let args = inst.operands[index+1..].iter()
+                                                       .filter_map(word)
+                                                       .collect();
+                    Some((decoration, args))
+                },
+                _ => None,
+            };
+            match (inst.class.opcode, operand(inst, 0)) {
+                (spirv::Op::Decorate, Some(target)) => {
+                    if let Some(decoration) = decoration(1) {
+                        module.decorations.entry(target)
+                              .or_insert_with(Vec::new)
+                              .push(decoration);
+                    }
+                },
+                (spirv::Op::MemberDecorate, Some(target)) => {
+                    if let (Some(member), Some(decoration)) =
+                        (operand(inst, 1), decoration(2))
+                    {
+                        module.member_decorations.entry((target, member))
+                              .or_insert_with(Vec::new)
+                              .push(decoration);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        for inst in &parsed.types_global_values {
+            module.parse_type_or_global(inst)?;
+        }
+        Ok(module)
+    }
+
+    /// Extract a type, constant or global variable declaration
+    fn parse_type_or_global(&mut self, inst: &Instruction) -> Result<()> {
+        let id = match inst.result_id {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        let arg = |index| operand(inst, index).ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "Missing or unexpected operand #{} of {:?} %{}",
+                index, inst.class.opcode, id
+            ))
+        });
+        let ty = match inst.class.opcode {
+            spirv::Op::TypeBool => Type::Bool,
+            spirv::Op::TypeInt => Type::Int {
+                width: arg(0)?,
+                signed: arg(1)? != 0,
+            },
+            spirv::Op::TypeFloat => Type::Float { width: arg(0)? },
+            spirv::Op::TypeVector => Type::Vector {
+                component: arg(0)?,
+                count: arg(1)?,
+            },
+            spirv::Op::TypeMatrix => Type::Matrix {
+                column: arg(0)?,
+                count: arg(1)?,
+            },
+            spirv::Op::TypeImage => {
+                let dim = match inst.operands.get(1) {
+                    Some(&Operand::Dim(dim)) => dim,
+                    _ => return Err(Error::InvalidArgument(format!(
+                        "Image type %{} has no dimensionality", id
+                    ))),
+                };
+                Type::Image {
+                    dim,
+                    arrayed: arg(3)? != 0,
+                    multisampled: arg(4)? != 0,
+                    sampled: arg(5)?,
+                }
+            },
+            spirv::Op::TypeSampler => Type::Sampler,
+            spirv::Op::TypeSampledImage => Type::SampledImage {
+                image: arg(0)?,
+            },
+            spirv::Op::TypeArray => Type::Array {
+                element: arg(0)?,
+                length: arg(1)?,
+            },
+            spirv::Op::TypeRuntimeArray => Type::RuntimeArray {
+                element: arg(0)?,
+            },
+            spirv::Op::TypeStruct => Type::Struct {
+                members: inst.operands.iter().filter_map(word).collect(),
+            },
+            spirv::Op::TypePointer => Type::Pointer { pointee: arg(1)? },
+            spirv::Op::Constant => {
+                if let Some(&Operand::LiteralInt32(value)) =
+                    inst.operands.get(0)
+                {
+                    self.constants.insert(id, value);
+                }
+                return Ok(());
+            },
+            spirv::Op::Variable => {
+                if let (Some(pointer), Some(&Operand::StorageClass(class))) =
+                    (inst.result_type, inst.operands.get(0))
+                {
+                    self.variables.push((id, pointer, class));
+                }
+                return Ok(());
+            },
+            _ => return Ok(()),
+        };
+        self.types.insert(id, ty);
+        Ok(())
+    }
+
+    /// Shader stages of the entry points
+    fn stages(&self) -> ShaderStages {
+        let has = |model| {
+            self.entry_points.iter().any(|&(other, _)| other == model)
+        };
+        ShaderStages {
+            vertex: has(spirv::ExecutionModel::Vertex),
+            tessellation_control:
+                has(spirv::ExecutionModel::TessellationControl),
+            tessellation_evaluation:
+                has(spirv::ExecutionModel::TessellationEvaluation),
+            geometry: has(spirv::ExecutionModel::Geometry),
+            fragment: has(spirv::ExecutionModel::Fragment),
+            compute: has(spirv::ExecutionModel::GLCompute),
+        }
+    }
+
+    /// Interface variables of the entry points of some execution model
+    fn interface(&self, model: spirv::ExecutionModel) -> HashSet<u32> {
+        self.entry_points.iter()
+                         .filter(|&&(other, _)| other == model)
+                         .flat_map(|&(_, ref interface)| interface.clone())
+                         .collect()
+    }
+
+    /// Operands of a decoration of some id, if it is decorated this way
+    fn decoration(&self,
+                  id: u32,
+                  decoration: spirv::Decoration) -> Option<&[u32]> {
+        find_decoration(self.decorations.get(&id)?, decoration)
+    }
+
+    /// Truth that some id has a decoration
+    fn has_decoration(&self, id: u32, decoration: spirv::Decoration) -> bool {
+        self.decorations.get(&id).map_or(false, |decorations| {
+            decorations.iter().any(|&(other, _)| other == decoration)
+        })
+    }
+
+    /// Operands of a decoration of some struct member, if any
+    fn member_decoration(&self,
+                         id: u32,
+                         member: u32,
+                         decoration: spirv::Decoration) -> Option<&[u32]> {
+        find_decoration(self.member_decorations.get(&(id, member))?,
+                        decoration)
+    }
+
+    /// Truth that some struct member has a decoration
+    fn member_has_decoration(&self,
+                             id: u32,
+                             member: u32,
+                             decoration: spirv::Decoration) -> bool {
+        self.member_decorations.get(&(id, member)).map_or(false, |decos| {
+            decos.iter().any(|&(other, _)| other == decoration)
+        })
+    }
+
+    /// Look up a type
+    fn ty(&self, id: u32) -> Result<&Type> {
+        self.types.get(&id).ok_or_else(|| Error::InvalidArgument(format!(
+            "Unknown or unsupported SPIR-V type %{}", id
+        )))
+    }
+
+    /// Describe the descriptor of a variable, and tell whether it is a
+    /// runtime-sized array
+    fn descriptor(&self,
+                  variable: u32,
+                  pointee: u32,
+                  storage_class: spirv::StorageClass,
+                  stages: ShaderStages) -> Result<(DescriptorDesc, bool)> {
+        // Arrays of descriptors are described by their element type
+        let (ty_id, array_count, runtime_array) = match *self.ty(pointee)? {
+            Type::Array { element, length } => {
+                let length = *self.constants.get(&length).ok_or_else(|| {
+                    Error::InvalidArgument(format!(
+                        "Descriptor array %{} does not have a constant length",
+                        variable
+                    ))
+                })?;
+                (element, length, false)
+            },
+            Type::RuntimeArray { element } => (element, 1, true),
+            _ => (pointee, 1, false),
+        };
+        let non_writable =
+            self.has_decoration(variable, spirv::Decoration::NonWritable);
+
+        let (ty, readonly) = match (storage_class, self.ty(ty_id)?) {
+            (spirv::StorageClass::UniformConstant, &Type::Sampler) => {
+                (DescriptorDescTy::Sampler, true)
+            },
+            (spirv::StorageClass::UniformConstant,
+             &Type::SampledImage { image }) => {
+                let image = self.image_desc(image)?;
+                (DescriptorDescTy::CombinedImageSampler(image), true)
+            },
+            (spirv::StorageClass::UniformConstant,
+             &Type::Image { dim, multisampled, sampled, .. }) => {
+                match dim {
+                    spirv::Dim::DimSubpassData => {
+                        (DescriptorDescTy::InputAttachment {
+                            multisampled,
+                            array_layers: DescriptorImageDescArray::NonArrayed,
+                        }, true)
+                    },
+                    spirv::Dim::DimBuffer => {
+                        (DescriptorDescTy::TexelBuffer {
+                            storage: sampled == 2,
+                            format: None,
+                        }, sampled != 2 || non_writable)
+                    },
+                    _ => {
+                        (DescriptorDescTy::Image(self.image_desc(ty_id)?),
+                         sampled != 2 || non_writable)
+                    },
+                }
+            },
+            (spirv::StorageClass::Uniform, &Type::Struct { ref members })
+            | (spirv::StorageClass::StorageBuffer,
+               &Type::Struct { ref members }) => {
+                let storage =
+                    storage_class == spirv::StorageClass::StorageBuffer
+                    || self.has_decoration(ty_id,
+                                           spirv::Decoration::BufferBlock);
+                let members_non_writable = (0..members.len() as u32).all(|m| {
+                    self.member_has_decoration(ty_id, m,
+                                               spirv::Decoration::NonWritable)
+                });
+                (DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                    dynamic: Some(false),
+                    storage,
+                }), !storage || non_writable || members_non_writable)
+            },
+            (_, ty) => return Err(Error::InvalidArgument(format!(
+                "Unsupported type {:?} for descriptor %{}", ty, variable
+            ))),
+        };
+
+        Ok((DescriptorDesc { ty, array_count, stages, readonly },
+            runtime_array))
+    }
+
+    /// Describe a sampled or storage image type
+    fn image_desc(&self, id: u32) -> Result<DescriptorImageDesc> {
+        let (dim, arrayed, multisampled, sampled) = match *self.ty(id)? {
+            Type::Image { dim, arrayed, multisampled, sampled } =>
+                (dim, arrayed, multisampled, sampled),
+            ref other => return Err(Error::InvalidArgument(format!(
+                "Expected an image type, found {:?}", other
+            ))),
+        };
+        let dimensions = match dim {
+            spirv::Dim::Dim1D => DescriptorImageDescDimensions::OneDimensional,
+            spirv::Dim::Dim2D => DescriptorImageDescDimensions::TwoDimensional,
+            spirv::Dim::Dim3D =>
+                DescriptorImageDescDimensions::ThreeDimensional,
+            spirv::Dim::DimCube => DescriptorImageDescDimensions::Cube,
+            _ => return Err(Error::InvalidArgument(format!(
+                "Unsupported image dimensionality {:?} for type %{}", dim, id
+            ))),
+        };
+        Ok(DescriptorImageDesc {
+            sampled: sampled != 2,
+            dimensions,
+            format: None,
+            multisampled,
+            array_layers: if arrayed {
+                DescriptorImageDescArray::Arrayed { max_layers: None }
+            } else {
+                DescriptorImageDescArray::NonArrayed
+            },
+        })
+    }
+
+    /// Size of a type in bytes, following its explicit layout decorations
+    fn size_of(&self, id: u32) -> Result<u32> {
+        Ok(match *self.ty(id)? {
+            Type::Bool => 4,
+            Type::Int { width, .. } | Type::Float { width } => width / 8,
+            Type::Vector { component, count } =>
+                count * self.size_of(component)?,
+            Type::Matrix { column, count } => count * self.size_of(column)?,
+            Type::Array { element, length } => {
+                let length = self.constants.get(&length).cloned()
+                                 .unwrap_or(0);
+                let stride = match self.decoration(
+                    id, spirv::Decoration::ArrayStride
+                ) {
+                    Some(stride) => stride[0],
+                    None => self.size_of(element)?,
+                };
+                length * stride
+            },
+            Type::RuntimeArray { .. } => 0,
+            Type::Struct { ref members } => {
+                let mut size = 0;
+                let mut offset = 0;
+                for (index, &member) in members.iter().enumerate() {
+                    let index = index as u32;
+                    if let Some(member_offset) = self.member_decoration(
+                        id, index, spirv::Decoration::Offset
+                    ) {
+                        offset = member_offset[0];
+                    }
+                    let member_size = match (self.ty(member)?,
+                                             self.member_decoration(
+                        id, index, spirv::Decoration::MatrixStride
+                    )) {
+                        (&Type::Matrix { count, .. }, Some(stride)) =>
+                            count * stride[0],
+                        _ => self.size_of(member)?,
+                    };
+                    offset += member_size;
+                    size = size.max(offset);
+                }
+                size
+            },
+            ref other => return Err(Error::InvalidArgument(format!(
+                "Cannot compute the size of type {:?}", other
+            ))),
+        })
+    }
+
+    /// Describe the vertex input(s) of a variable, matrices being spread
+    /// across consecutive locations
+    fn vertex_inputs(&self,
+                     variable: u32,
+                     ty: u32,
+                     location: u32,
+                     inputs: &mut Vec<VertexInput>) -> Result<()> {
+        let name = self.names.get(&variable).cloned();
+        let (columns, column) = match *self.ty(ty)? {
+            Type::Matrix { column, count } => (count, column),
+            _ => (1, ty),
+        };
+        let (component, components) = match *self.ty(column)? {
+            Type::Vector { component, count } => (component, count),
+            _ => (column, 1),
+        };
+        let (kind, width) = match *self.ty(component)? {
+            Type::Float { width } => (ScalarKind::Float, width),
+            Type::Int { width, signed: true } => (ScalarKind::Int, width),
+            Type::Int { width, signed: false } => (ScalarKind::Uint, width),
+            ref other => return Err(Error::InvalidArgument(format!(
+                "Unsupported vertex input type {:?} at location {}",
+                other, location
+            ))),
+        };
+        inputs.extend((0..columns).map(|column| VertexInput {
+            location: location + column,
+            name: name.clone(),
+            kind,
+            width,
+            components,
+        }));
+        Ok(())
+    }
+}
+
+/// Find the operands of a decoration in a list of decorations
+fn find_decoration(decorations: &Decorations,
+                   decoration: spirv::Decoration) -> Option<&[u32]> {
+    decorations.iter()
+               .find(|&&(other, ref args)| {
+                   other == decoration && !args.is_empty()
+               })
+               .map(|&(_, ref args)| &args[..])
+}
+
+/// Value of an instruction operand which is an id or a 32-bit integer
+fn word(operand: &Operand) -> Option<u32> {
+    match *operand {
+        Operand::IdRef(word) | Operand::LiteralInt32(word) => Some(word),
+        _ => None,
+    }
+}
+
+/// Value of the operand of an instruction at some index, if it is an id or a
+/// 32-bit integer
+fn operand(inst: &Instruction, index: usize) -> Option<u32> {
+    inst.operands.get(index).and_then(word)
+}
@@ -0,0 +1,252 @@
+//! Conveniences for setting up render passes and framebuffers
+//!
+//! Most prototypes render to the swapchain in a single subpass, optionally
+//! with a depth buffer and multisampling. easy_single_pass() builds the
+//! matching render pass, and EasySinglePass then builds the framebuffers,
+//! allocating the depth and multisampled color images along the way.
+
+use ::{
+    render_loop::EasyFramebuffer,
+    trace,
+    Error,
+    Result,
+};
+
+use std::sync::Arc;
+
+use vulkano::{
+    device::Device,
+    format::{
+        ClearValue,
+        Format,
+    },
+    framebuffer::{
+        Framebuffer,
+        RenderPassAbstract,
+    },
+    image::{
+        AttachmentImage,
+        ImageViewAccess,
+    },
+};
+
+
+/// Render pass type produced by easy_single_pass()
+pub type EasyRenderPass = Arc<dyn RenderPassAbstract + Send + Sync>;
+
+
+/// Build the typical one-subpass render pass
+///
+/// The render pass has a color attachment of the specified format, which is
+/// cleared at the start of the pass and stored at the end, and which will
+/// typically be a swapchain image. If a depth format is specified, a depth
+/// attachment is added, which is cleared and then discarded. If more than one
+/// sample is requested, rendering happens in multisampled color (and depth)
+/// attachments, and the color attachment is resolved into the final image.
+///
+pub fn easy_single_pass(device: &Arc<Device>,
+                        color_format: Format,
+                        depth_format: Option<Format>,
+                        samples: u32) -> Result<EasySinglePass> {
+    let _trace = trace::span("render::easy_single_pass", || {
+        format!("color_format: {:?}, depth_format: {:?}, samples: {}",
+                color_format, depth_format, samples)
+    });
+    if samples == 0 || !samples.is_power_of_two() {
+        return Err(Error::InvalidArgument(
+            format!("Invalid sample count {}", samples)
+        ));
+    }
+
+    let render_pass: EasyRenderPass = match (depth_format, samples) {
+        (None, 1) => Arc::new(single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )?),
+        (Some(depth_format), 1) => Arc::new(single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: depth_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {depth}
+            }
+        )?),
+        (None, _) => Arc::new(single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                multisampled_color: {
+                    load: Clear,
+                    store: DontCare,
+                    format: color_format,
+                    samples: samples,
+                },
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [multisampled_color],
+                depth_stencil: {},
+                resolve: [color],
+            }
+        )?),
+        (Some(depth_format), _) => Arc::new(single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                multisampled_color: {
+                    load: Clear,
+                    store: DontCare,
+                    format: color_format,
+                    samples: samples,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: depth_format,
+                    samples: samples,
+                },
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: color_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [multisampled_color],
+                depth_stencil: {depth},
+                resolve: [color],
+            }
+        )?),
+    };
+
+    Ok(EasySinglePass {
+        device: device.clone(),
+        render_pass,
+        color_format,
+        depth_format,
+        samples,
+    })
+}
+
+
+/// A render pass built by easy_single_pass(), along with its configuration
+pub struct EasySinglePass {
+    /// Device which the render pass belongs to
+    device: Arc<Device>,
+
+    /// The render pass itself
+    render_pass: EasyRenderPass,
+
+    /// Format of the color attachment
+    color_format: Format,
+
+    /// Format of the depth attachment, if any
+    depth_format: Option<Format>,
+
+    /// Number of samples per pixel
+    samples: u32,
+}
+
+impl EasySinglePass {
+    /// Access the render pass
+    pub fn render_pass(&self) -> &EasyRenderPass {
+        &self.render_pass
+    }
+
+    /// Clear values to be used when beginning the render pass
+    ///
+    /// These are given in attachment order, which is what vulkano expects.
+    ///
+    pub fn clear_values(&self, color: [f32; 4]) -> Vec<ClearValue> {
+        let mut clear_values = vec![color.into()];
+        if self.depth_format.is_some() {
+            clear_values.push(1.0f32.into());
+        }
+        if self.samples > 1 {
+            clear_values.push(ClearValue::None);
+        }
+        clear_values
+    }
+
+    /// Build one framebuffer per target image (typically swapchain images)
+    ///
+    /// The images must have the color format of the render pass and the same
+    /// dimensions. The depth and multisampled color images that the render
+    /// pass needs, if any, are allocated here and shared by all framebuffers.
+    /// Call this again whenever the swapchain is recreated.
+    ///
+    pub fn framebuffers<I>(&self,
+                           images: &[Arc<I>]) -> Result<Vec<EasyFramebuffer>>
+        where I: ImageViewAccess + Send + Sync + 'static
+    {
+        let _trace = trace::span("EasySinglePass::framebuffers",
+                                 || format!("images: {}", images.len()));
+        let dimensions = match images.first() {
+            Some(image) => image.dimensions().width_height(),
+            None => return Ok(Vec::new()),
+        };
+
+        // Allocate the shared attachments
+        let depth = match self.depth_format {
+            Some(format) => Some(AttachmentImage::transient_multisampled(
+                self.device.clone(), dimensions, self.samples, format
+            )?),
+            None => None,
+        };
+        let multisampled_color = if self.samples > 1 {
+            Some(AttachmentImage::transient_multisampled(
+                self.device.clone(), dimensions, self.samples, self.color_format
+            )?)
+        } else {
+            None
+        };
+
+        // Build the framebuffers, adding attachments in declaration order
+        images.iter().map(|image| -> Result<EasyFramebuffer> {
+            let start = Framebuffer::start(self.render_pass.clone());
+            Ok(match (&multisampled_color, &depth) {
+                (None, None) => Arc::new(start.add(image.clone())?.build()?),
+                (None, Some(depth)) => Arc::new(start.add(image.clone())?
+                                                     .add(depth.clone())?
+                                                     .build()?),
+                (Some(color), None) => Arc::new(start.add(color.clone())?
+                                                     .add(image.clone())?
+                                                     .build()?),
+                (Some(color), Some(depth)) => {
+                    Arc::new(start.add(color.clone())?
+                                  .add(depth.clone())?
+                                  .add(image.clone())?
+                                  .build()?)
+                },
+            })
+        }).collect()
+    }
+}
@@ -0,0 +1,845 @@
+//! Render graphs, for prototypes with many passes (requires the
+//! "render-graph" feature)
+//!
+//! Once a prototype grows beyond a couple of passes, keeping track of pass
+//! ordering, intermediate images and synchronization by hand gets tedious. A
+//! RenderGraph lets passes declare which resources they use and how, along
+//! with any extra ordering constraint, and compiles this into:
+//!
+//! - An execution order which respects the dependencies between passes.
+//!   Passes which access the same resource, at least one of them writing to
+//!   it, run in declaration order.
+//! - Transient attachments, i.e. intermediate images which only live within
+//!   the graph. Transient attachments whose lifetimes do not overlap share
+//!   the same underlying image, so that memory usage stays low.
+//! - Pipeline barriers and layout transitions, computed by the barrier module.
+//!
+//! Like BarrierTracker, render graphs are meant for command buffers which are
+//! recorded using raw Vulkan calls: each pass records its commands via a
+//! callback, which receives the raw command buffer.
+//!
+//! ```ignore
+//! let mut graph = RenderGraph::new();
+//! let hdr = graph.transient_attachment("hdr", AttachmentDesc {
+//!     format: Format::R16G16B16A16Sfloat,
+//!     dimensions: [1920, 1080],
+//! });
+//! let output =
+//!     graph.import_image("output", image, vk::IMAGE_LAYOUT_UNDEFINED);
+//! graph.add_pass("scene")
+//!      .access(hdr, ResourceUsage::StorageWrite)
+//!      .record(|ctx| { /* ...dispatch... */ Ok(()) })
+//!      .finish();
+//! graph.add_pass("tonemap")
+//!      .access(hdr, ResourceUsage::StorageRead)
+//!      .access(output, ResourceUsage::StorageWrite)
+//!      .record(|ctx| { /* ...dispatch... */ Ok(()) })
+//!      .finish();
+//! let mut compiled = graph.compile(&device)?;
+//! unsafe { compiled.execute(&device, raw_command_buffer)?; }
+//! ```
+
+use ::{
+    barrier::{
+        BarrierTracker,
+        PassBarriers,
+        ResourceAccess,
+        ResourceUsage,
+    },
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    collections::BTreeSet,
+    sync::Arc,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    buffer::BufferAccess,
+    device::Device,
+    format::Format,
+    image::{
+        AttachmentImage,
+        ImageAccess,
+        ImageUsage,
+    },
+};
+
+
+/// Image which is used by a render graph
+pub type GraphImage = Arc<dyn ImageAccess + Send + Sync>;
+
+/// Buffer which is used by a render graph
+pub type GraphBuffer = Arc<dyn BufferAccess + Send + Sync>;
+
+/// Callback which records the commands of a pass
+pub type PassCallback = Box<dyn FnMut(&PassContext) -> Result<()> + Send>;
+
+
+/// Identifier of a resource of a render graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphResource(usize);
+
+/// Identifier of a pass of a render graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphPass(usize);
+
+/// Description of a transient attachment
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AttachmentDesc {
+    /// Format of the attachment
+    pub format: Format,
+
+    /// Width and height of the attachment
+    pub dimensions: [u32; 2],
+}
+
+
+/// Kind of resource which a render graph uses
+enum ResourceKind {
+    /// Image which only lives within the graph
+    Transient(AttachmentDesc),
+
+    /// Image which comes from outside the graph, with its initial layout
+    Image(GraphImage, vk::ImageLayout),
+
+    /// Buffer which comes from outside the graph
+    Buffer(GraphBuffer),
+}
+
+/// Resource declaration
+struct ResourceDecl {
+    /// Name of the resource, for error messages and logs
+    name: String,
+
+    /// Kind of resource
+    kind: ResourceKind,
+}
+
+/// Pass declaration
+struct PassDecl {
+    /// Name of the pass, for error messages and logs
+    name: String,
+
+    /// Resources which the pass uses, and how
+    accesses: Vec<(GraphResource, ResourceUsage)>,
+
+    /// Passes which must run before this one
+    after: Vec<GraphPass>,
+
+    /// Command recording callback, if any
+    record: Option<PassCallback>,
+}
+
+
+/// Mapping of transient attachments to physical images
+struct TransientPlan {
+    /// First and last position in the execution order where each transient
+    /// attachment is used (None for other resources and unused attachments)
+    lifetimes: Vec<Option<(usize, usize)>>,
+
+    /// Description and usage of each physical image
+    physical: Vec<(AttachmentDesc, ImageUsage)>,
+
+    /// Physical image which each transient attachment is backed by
+    assignment: Vec<Option<usize>>,
+}
+
+
+/// Set of passes and resources, which must be compiled before use
+#[derive(Default)]
+pub struct RenderGraph {
+    /// Declared resources
+    resources: Vec<ResourceDecl>,
+
+    /// Declared passes
+    passes: Vec<PassDecl>,
+}
+
+impl RenderGraph {
+    /// Start building an empty render graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare an image which only lives within the graph
+    ///
+    /// Its contents are undefined before the first pass which uses it, and
+    /// lost after the last pass which uses it.
+    ///
+    pub fn transient_attachment(&mut self,
+                                name: &str,
+                                desc: AttachmentDesc) -> GraphResource {
+        self.add_resource(name, ResourceKind::Transient(desc))
+    }
+
+    /// Declare an image which comes from outside the graph
+    ///
+    /// The image must be in the specified layout whenever the graph starts
+    /// executing. Use vk::IMAGE_LAYOUT_UNDEFINED if its contents do not
+    /// matter.
+    ///
+    pub fn import_image(&mut self,
+                        name: &str,
+                        image: GraphImage,
+                        initial_layout: vk::ImageLayout) -> GraphResource {
+        self.add_resource(name, ResourceKind::Image(image, initial_layout))
+    }
+
+    /// Declare a buffer which comes from outside the graph
+    pub fn import_buffer(&mut self,
+                         name: &str,
+                         buffer: GraphBuffer) -> GraphResource {
+        self.add_resource(name, ResourceKind::Buffer(buffer))
+    }
+
+    /// Start declaring a pass
+    pub fn add_pass(&mut self, name: &str) -> PassBuilder {
+        self.passes.push(PassDecl {
+            name: name.to_owned(),
+            accesses: Vec::new(),
+            after: Vec::new(),
+            record: None,
+        });
+        PassBuilder {
+            pass: GraphPass(self.passes.len() - 1),
+            graph: self,
+        }
+    }
+
+    /// Compile the graph, allocating its transient attachments
+    pub fn compile(self, device: &Arc<Device>) -> Result<CompiledGraph> {
+        let _trace = trace::span("RenderGraph::compile", || {
+            format!("passes: {}, resources: {}",
+                    self.passes.len(), self.resources.len())
+        });
+        self.check_declarations()?;
+        let order = self.execution_order()?;
+
+        // Allocate the transient attachments, aliasing them when possible
+        let TransientPlan { lifetimes, physical, assignment } =
+            self.plan_transients(&order);
+        let images = physical.iter().map(|&(desc, usage)| {
+            let image: GraphImage = AttachmentImage::with_usage(
+                device.clone(), desc.dimensions, desc.format, usage
+            )?;
+            Ok(image)
+        }).collect::<Result<Vec<_>>>()?;
+
+        // Resolve every resource to an actual image or buffer
+        let resources = self.resources.iter().enumerate().map(|(idx, decl)| {
+            match decl.kind {
+                ResourceKind::Transient(_) => assignment[idx].map(|slot| {
+                    ResolvedResource::Image(images[slot].clone())
+                }).unwrap_or(ResolvedResource::Unused),
+                ResourceKind::Image(ref image, _) =>
+                    ResolvedResource::Image(image.clone()),
+                ResourceKind::Buffer(ref buffer) =>
+                    ResolvedResource::Buffer(buffer.clone()),
+            }
+        }).collect::<Vec<_>>();
+
+        // Compute the barriers of each pass
+        let mut tracker = BarrierTracker::new();
+        for decl in &self.resources {
+            if let ResourceKind::Image(ref image, layout) = decl.kind {
+                tracker.set_image_layout(image, layout);
+            }
+        }
+        let mut barriers = Vec::with_capacity(order.len());
+        for (position, &pass) in order.iter().enumerate() {
+            let decl = &self.passes[pass];
+            let mut accesses = Vec::with_capacity(decl.accesses.len());
+            for &(resource, usage) in &decl.accesses {
+                let access = match resources[resource.0] {
+                    ResolvedResource::Image(ref image) => {
+                        // A newly used transient attachment discards the
+                        // contents of the image that it shares
+                        let first_use =
+                            lifetimes[resource.0].map(|(first, _)| first);
+                        if first_use == Some(position) {
+                            if !usage.is_write() {
+                                warn!("Transient attachment {} is read by \
+                                       pass {} before being written",
+                                      self.resources[resource.0].name,
+                                      decl.name);
+                            }
+                            tracker.set_image_layout(
+                                image,
+                                vk::IMAGE_LAYOUT_UNDEFINED
+                            );
+                        }
+                        ResourceAccess::image(image, usage)
+                    },
+                    ResolvedResource::Buffer(ref buffer) =>
+                        ResourceAccess::buffer(buffer, usage),
+                    ResolvedResource::Unused =>
+                        unreachable!("Accessed resources are resolved"),
+                };
+                accesses.push(access);
+            }
+            barriers.push(tracker.pass(&accesses).map_err(|e| {
+                Error::InvalidArgument(format!("In pass {}: {}", decl.name, e))
+            })?);
+        }
+
+        // Put the passes in execution order
+        let mut passes = self.passes.into_iter().map(Some).collect::<Vec<_>>();
+        let passes = order.iter().zip(barriers).map(|(&pass, barriers)| {
+            let decl = passes[pass].take().expect("Passes run only once");
+            CompiledPass {
+                name: decl.name,
+                barriers,
+                record: decl.record,
+            }
+        }).collect::<Vec<_>>();
+        info!("Compiled a render graph with {} pass(es) and {} transient \
+               attachment(s), backed by {} image(s)",
+              passes.len(),
+              lifetimes.iter().filter(|lifetime| lifetime.is_some()).count(),
+              images.len());
+        Ok(CompiledGraph {
+            passes,
+            resources,
+            transient_images: images.len(),
+        })
+    }
+
+    /// Find out when and how each transient attachment is used, and assign
+    /// them to physical images, reusing images whose previous user is done
+    fn plan_transients(&self, order: &[usize]) -> TransientPlan {
+        // Find out when and how each transient attachment is used
+        let num_resources = self.resources.len();
+        let mut lifetimes = vec![None; num_resources];
+        let mut usages = vec![ImageUsage::none(); num_resources];
+        for (position, &pass) in order.iter().enumerate() {
+            for &(resource, usage) in &self.passes[pass].accesses {
+                if let ResourceKind::Transient(_) =
+                    self.resources[resource.0].kind
+                {
+                    let lifetime = lifetimes[resource.0]
+                                       .get_or_insert((position, position));
+                    lifetime.1 = position;
+                    usages[resource.0] =
+                        merge_usage(usages[resource.0], usage);
+                }
+            }
+        }
+
+        // Assign transient attachments to physical images, starting with the
+        // earliest ones
+        let mut by_first_use = (0..num_resources)
+            .filter_map(|idx| lifetimes[idx].map(|(first, _)| (first, idx)))
+            .collect::<Vec<_>>();
+        by_first_use.sort();
+        let mut physical: Vec<(AttachmentDesc, ImageUsage, usize)> =
+            Vec::new();
+        let mut assignment = vec![None; num_resources];
+        for (first, idx) in by_first_use {
+            let desc = match self.resources[idx].kind {
+                ResourceKind::Transient(desc) => desc,
+                _ => unreachable!("Only transient resources have lifetimes"),
+            };
+            let (_, last) = lifetimes[idx].expect("Checked above");
+            let reusable = physical.iter().position(|&(other, _, end)| {
+                other == desc && end < first
+            });
+            let slot = match reusable {
+                Some(slot) => slot,
+                None => {
+                    physical.push((desc, ImageUsage::none(), 0));
+                    physical.len() - 1
+                },
+            };
+            let entry = &mut physical[slot];
+            entry.1 = union_usage(entry.1, usages[idx]);
+            entry.2 = last;
+            assignment[idx] = Some(slot);
+        }
+        TransientPlan {
+            lifetimes,
+            physical: physical.into_iter()
+                              .map(|(desc, usage, _)| (desc, usage))
+                              .collect(),
+            assignment,
+        }
+    }
+
+    /// Record a new resource declaration
+    fn add_resource(&mut self,
+                    name: &str,
+                    kind: ResourceKind) -> GraphResource {
+        self.resources.push(ResourceDecl {
+            name: name.to_owned(),
+            kind,
+        });
+        GraphResource(self.resources.len() - 1)
+    }
+
+    /// Check that passes only refer to resources and passes of this graph,
+    /// in ways that make sense
+    fn check_declarations(&self) -> Result<()> {
+        for pass in &self.passes {
+            for &(resource, usage) in &pass.accesses {
+                let decl = self.resources.get(resource.0).ok_or_else(|| {
+                    Error::InvalidArgument(
+                        format!("Pass {} uses unknown resource {:?}",
+                                pass.name, resource)
+                    )
+                })?;
+                let valid = match decl.kind {
+                    ResourceKind::Transient(_) => match usage {
+                        ResourceUsage::Present | ResourceUsage::HostRead =>
+                            false,
+                        _ => usage.image_layout().is_some(),
+                    },
+                    ResourceKind::Image(..) => usage.image_layout().is_some(),
+                    ResourceKind::Buffer(_) => usage.applies_to_buffers(),
+                };
+                if !valid {
+                    return Err(Error::InvalidArgument(
+                        format!("Pass {} cannot use resource {} as {:?}",
+                                pass.name, decl.name, usage)
+                    ));
+                }
+            }
+            if let Some(other) = pass.after.iter()
+                                           .find(|other| {
+                                               other.0 >= self.passes.len()
+                                           })
+            {
+                return Err(Error::InvalidArgument(
+                    format!("Pass {} depends on unknown pass {:?}",
+                            pass.name, other)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Order the passes so that every pass runs after the ones it depends on
+    ///
+    /// Among the passes which are ready to run, the one that was declared
+    /// first runs first.
+    ///
+    fn execution_order(&self) -> Result<Vec<usize>> {
+        // Collect the dependencies of each pass
+        let num_passes = self.passes.len();
+        let mut dependencies = vec![BTreeSet::new(); num_passes];
+        for (idx, pass) in self.passes.iter().enumerate() {
+            dependencies[idx].extend(pass.after.iter().map(|other| other.0));
+            for &(resource, usage) in &pass.accesses {
+                for (other_idx, other) in self.passes[..idx].iter()
+                                                            .enumerate()
+                {
+                    let conflicts = other.accesses.iter().any(|&(r, u)| {
+                        r == resource && (u.is_write() || usage.is_write())
+                    });
+                    if conflicts { dependencies[idx].insert(other_idx); }
+                }
+            }
+        }
+
+        // Schedule passes whose dependencies have run, until none is left
+        let mut order = Vec::with_capacity(num_passes);
+        let mut scheduled = vec![false; num_passes];
+        while order.len() < num_passes {
+            let ready = (0..num_passes).find(|&idx| {
+                !scheduled[idx]
+                    && dependencies[idx].iter().all(|&dep| scheduled[dep])
+            });
+            match ready {
+                Some(idx) => {
+                    scheduled[idx] = true;
+                    order.push(idx);
+                },
+                None => {
+                    let stuck = (0..num_passes)
+                        .filter(|&idx| !scheduled[idx])
+                        .map(|idx| &self.passes[idx].name[..])
+                        .collect::<Vec<_>>();
+                    return Err(Error::InvalidArgument(
+                        format!("Render graph has a dependency cycle \
+                                 between passes {:?}", stuck)
+                    ));
+                },
+            }
+        }
+        Ok(order)
+    }
+}
+
+
+/// Declaration of a pass in progress, as returned by RenderGraph::add_pass()
+pub struct PassBuilder<'a> {
+    /// Graph which the pass belongs to
+    graph: &'a mut RenderGraph,
+
+    /// Pass which is being declared
+    pass: GraphPass,
+}
+
+impl<'a> PassBuilder<'a> {
+    /// Declare that the pass uses a resource in a certain way
+    ///
+    /// Each resource may only be declared once per pass.
+    ///
+    pub fn access(self,
+                  resource: GraphResource,
+                  usage: ResourceUsage) -> Self {
+        self.graph.passes[self.pass.0].accesses.push((resource, usage));
+        self
+    }
+
+    /// Declare that the pass must run after another one, even though they
+    /// do not access the same resources
+    pub fn after(self, other: GraphPass) -> Self {
+        self.graph.passes[self.pass.0].after.push(other);
+        self
+    }
+
+    /// Set the callback which records the commands of the pass
+    ///
+    /// Passes without a callback only record barriers, which is useful e.g.
+    /// to transition a swapchain image to the presentation layout.
+    ///
+    pub fn record(
+        self,
+        callback: impl FnMut(&PassContext) -> Result<()> + Send + 'static
+    ) -> Self {
+        self.graph.passes[self.pass.0].record = Some(Box::new(callback));
+        self
+    }
+
+    /// Finish declaring the pass
+    pub fn finish(self) -> GraphPass {
+        self.pass
+    }
+}
+
+
+/// Resource of a compiled graph
+enum ResolvedResource {
+    /// Image (transient or imported)
+    Image(GraphImage),
+
+    /// Imported buffer
+    Buffer(GraphBuffer),
+
+    /// Transient attachment which no pass uses
+    Unused,
+}
+
+/// Pass of a compiled graph
+struct CompiledPass {
+    /// Name of the pass
+    name: String,
+
+    /// Barriers which must be recorded before the pass
+    barriers: PassBarriers,
+
+    /// Command recording callback, if any
+    record: Option<PassCallback>,
+}
+
+/// What a pass callback gets to work with
+pub struct PassContext<'a> {
+    /// Raw command buffer which commands should be recorded into
+    pub command_buffer: vk::CommandBuffer,
+
+    /// Resources of the graph
+    resources: &'a [ResolvedResource],
+}
+
+impl<'a> PassContext<'a> {
+    /// Image which backs a resource, if it is an image used by some pass
+    pub fn image(&self, resource: GraphResource) -> Option<&GraphImage> {
+        match self.resources.get(resource.0) {
+            Some(&ResolvedResource::Image(ref image)) => Some(image),
+            _ => None,
+        }
+    }
+
+    /// Buffer which backs a resource, if it is a buffer
+    pub fn buffer(&self, resource: GraphResource) -> Option<&GraphBuffer> {
+        match self.resources.get(resource.0) {
+            Some(&ResolvedResource::Buffer(ref buffer)) => Some(buffer),
+            _ => None,
+        }
+    }
+}
+
+
+/// Render graph which is ready for execution
+pub struct CompiledGraph {
+    /// Passes, in execution order
+    passes: Vec<CompiledPass>,
+
+    /// Resources, indexed by GraphResource
+    resources: Vec<ResolvedResource>,
+
+    /// Number of images which were allocated for transient attachments
+    transient_images: usize,
+}
+
+impl CompiledGraph {
+    /// Names of the passes, in execution order
+    pub fn execution_order(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| &pass.name[..]).collect()
+    }
+
+    /// Number of images which were allocated for transient attachments
+    pub fn transient_images(&self) -> usize {
+        self.transient_images
+    }
+
+    /// Record the whole graph into a raw command buffer
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must belong to the device that the graph was
+    /// compiled for, and be in the recording state, outside of a render pass.
+    /// It must not start executing before the GPU is done with the previous
+    /// execution of the graph (if any), and imported images must then be in
+    /// their declared initial layout. Pass callbacks must record valid
+    /// commands, leaving resources in the layout which they declared.
+    ///
+    pub unsafe fn execute(&mut self,
+                          device: &Device,
+                          command_buffer: vk::CommandBuffer) -> Result<()> {
+        let _trace = trace::span("CompiledGraph::execute",
+                                 || format!("passes: {}", self.passes.len()));
+        let resources = &self.resources[..];
+        for pass in &mut self.passes {
+            pass.barriers.record(device, command_buffer);
+            if let Some(ref mut record) = pass.record {
+                record(&PassContext {
+                    command_buffer,
+                    resources,
+                }).map_err(|e| {
+                    Error::Other(format_err!("Pass {} failed: {}",
+                                             pass.name, e))
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Add the image usage flags which a resource usage requires
+fn merge_usage(usage: ImageUsage, resource_usage: ResourceUsage) -> ImageUsage {
+    let mut usage = usage;
+    match resource_usage {
+        ResourceUsage::TransferSrc => usage.transfer_source = true,
+        ResourceUsage::TransferDst => usage.transfer_destination = true,
+        ResourceUsage::Sampled => usage.sampled = true,
+        ResourceUsage::StorageRead | ResourceUsage::StorageWrite =>
+            usage.storage = true,
+        ResourceUsage::ColorAttachment => usage.color_attachment = true,
+        ResourceUsage::DepthStencilAttachment =>
+            usage.depth_stencil_attachment = true,
+        _ => {},
+    }
+    usage
+}
+
+/// Union of two sets of image usage flags
+fn union_usage(a: ImageUsage, b: ImageUsage) -> ImageUsage {
+    ImageUsage {
+        transfer_source: a.transfer_source || b.transfer_source,
+        transfer_destination: a.transfer_destination || b.transfer_destination,
+        sampled: a.sampled || b.sampled,
+        storage: a.storage || b.storage,
+        color_attachment: a.color_attachment || b.color_attachment,
+        depth_stencil_attachment:
+            a.depth_stencil_attachment || b.depth_stencil_attachment,
+        transient_attachment:
+            a.transient_attachment || b.transient_attachment,
+        input_attachment: a.input_attachment || b.input_attachment,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Attachment description used by most tests
+    const HDR: AttachmentDesc = AttachmentDesc {
+        format: Format::R16G16B16A16Sfloat,
+        dimensions: [64, 64],
+    };
+
+    /// Names of the passes of a graph, in execution order
+    fn order_names(graph: &RenderGraph) -> Vec<&str> {
+        graph.execution_order()
+             .unwrap()
+             .into_iter()
+             .map(|idx| &graph.passes[idx].name[..])
+             .collect()
+    }
+
+    #[test]
+    fn declaration_order() {
+        let mut graph = RenderGraph::new();
+        let a = graph.transient_attachment("a", HDR);
+        let b = graph.transient_attachment("b", HDR);
+        graph.add_pass("first").access(a, ResourceUsage::StorageWrite)
+                               .finish();
+        graph.add_pass("second").access(b, ResourceUsage::StorageWrite)
+                                .finish();
+        graph.add_pass("third").access(a, ResourceUsage::StorageRead)
+                               .access(b, ResourceUsage::StorageRead)
+                               .finish();
+        assert_eq!(order_names(&graph), vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn explicit_dependency() {
+        let mut graph = RenderGraph::new();
+        let a = graph.transient_attachment("a", HDR);
+        let b = graph.transient_attachment("b", HDR);
+        let upload = GraphPass(1);
+        graph.add_pass("process").access(a, ResourceUsage::StorageWrite)
+                                 .after(upload)
+                                 .finish();
+        graph.add_pass("upload").access(b, ResourceUsage::TransferDst)
+                                .finish();
+        assert_eq!(order_names(&graph), vec!["upload", "process"]);
+    }
+
+    #[test]
+    fn transitive_dependencies() {
+        // "late" waits for "read", which waits for "early" as both access "b"
+        let mut graph = RenderGraph::new();
+        let a = graph.transient_attachment("a", HDR);
+        let b = graph.transient_attachment("b", HDR);
+        let read = GraphPass(2);
+        graph.add_pass("late").access(a, ResourceUsage::StorageWrite)
+                              .after(read)
+                              .finish();
+        graph.add_pass("early").access(b, ResourceUsage::StorageWrite)
+                               .finish();
+        graph.add_pass("read").access(b, ResourceUsage::StorageRead)
+                              .finish();
+        assert_eq!(order_names(&graph), vec!["early", "read", "late"]);
+    }
+
+    #[test]
+    fn concurrent_reads() {
+        // Reads do not order passes, so declaration order is kept
+        let mut graph = RenderGraph::new();
+        let a = graph.transient_attachment("a", HDR);
+        let b = graph.transient_attachment("b", HDR);
+        let second = GraphPass(2);
+        graph.add_pass("write").access(a, ResourceUsage::StorageWrite)
+                               .finish();
+        graph.add_pass("first").access(a, ResourceUsage::StorageRead)
+                               .after(second)
+                               .finish();
+        graph.add_pass("second").access(a, ResourceUsage::StorageRead)
+                                .access(b, ResourceUsage::StorageWrite)
+                                .finish();
+        graph.add_pass("last").access(a, ResourceUsage::StorageRead)
+                              .finish();
+        assert_eq!(order_names(&graph),
+                   vec!["write", "second", "first", "last"]);
+    }
+
+    #[test]
+    fn dependency_cycle() {
+        let mut graph = RenderGraph::new();
+        let a = graph.transient_attachment("a", HDR);
+        graph.add_pass("write").access(a, ResourceUsage::StorageWrite)
+                               .after(GraphPass(1))
+                               .finish();
+        graph.add_pass("read").access(a, ResourceUsage::StorageRead)
+                              .finish();
+        assert!(graph.execution_order().is_err());
+    }
+
+    #[test]
+    fn invalid_declarations() {
+        let mut graph = RenderGraph::new();
+        let a = graph.transient_attachment("a", HDR);
+        graph.add_pass("present").access(a, ResourceUsage::Present)
+                                 .finish();
+        assert!(graph.check_declarations().is_err());
+
+        let mut graph = RenderGraph::new();
+        graph.add_pass("unknown").after(GraphPass(1)).finish();
+        assert!(graph.check_declarations().is_err());
+
+        let mut graph = RenderGraph::new();
+        graph.add_pass("unknown")
+             .access(GraphResource(0), ResourceUsage::StorageRead)
+             .finish();
+        assert!(graph.check_declarations().is_err());
+    }
+
+    #[test]
+    fn transient_aliasing() {
+        // "a" is dead by the time "c" is first used, but "b" is not
+        let mut graph = RenderGraph::new();
+        let a = graph.transient_attachment("a", HDR);
+        let b = graph.transient_attachment("b", HDR);
+        let c = graph.transient_attachment("c", HDR);
+        let unused = graph.transient_attachment("unused", HDR);
+        graph.add_pass("0").access(a, ResourceUsage::ColorAttachment)
+                           .finish();
+        graph.add_pass("1").access(a, ResourceUsage::Sampled)
+                           .access(b, ResourceUsage::StorageWrite)
+                           .finish();
+        graph.add_pass("2").access(c, ResourceUsage::StorageWrite)
+                           .finish();
+        graph.add_pass("3").access(b, ResourceUsage::StorageRead)
+                           .access(c, ResourceUsage::StorageRead)
+                           .finish();
+        let order = graph.execution_order().unwrap();
+        let plan = graph.plan_transients(&order);
+
+        assert_eq!(plan.lifetimes[a.0], Some((0, 1)));
+        assert_eq!(plan.lifetimes[b.0], Some((1, 3)));
+        assert_eq!(plan.lifetimes[c.0], Some((2, 3)));
+        assert_eq!(plan.lifetimes[unused.0], None);
+        assert_eq!(plan.physical.len(), 2);
+        assert_eq!(plan.assignment[a.0], Some(0));
+        assert_eq!(plan.assignment[b.0], Some(1));
+        assert_eq!(plan.assignment[c.0], Some(0));
+        assert_eq!(plan.assignment[unused.0], None);
+
+        // Shared images support the usages of all their users
+        let (desc, usage) = plan.physical[0];
+        assert_eq!(desc, HDR);
+        assert!(usage.color_attachment && usage.sampled && usage.storage);
+        assert!(!usage.transfer_source && !usage.transfer_destination);
+        let (_, usage) = plan.physical[1];
+        assert!(usage.storage && !usage.color_attachment && !usage.sampled);
+    }
+
+    #[test]
+    fn no_aliasing_across_descriptions() {
+        let mut graph = RenderGraph::new();
+        let a = graph.transient_attachment("a", HDR);
+        let b = graph.transient_attachment("b", AttachmentDesc {
+            dimensions: [32, 32],
+            ..HDR
+        });
+        let c = graph.transient_attachment("c", AttachmentDesc {
+            format: Format::R8G8B8A8Unorm,
+            ..HDR
+        });
+        graph.add_pass("0").access(a, ResourceUsage::StorageWrite).finish();
+        graph.add_pass("1").access(b, ResourceUsage::StorageWrite).finish();
+        graph.add_pass("2").access(c, ResourceUsage::StorageWrite).finish();
+        let order = graph.execution_order().unwrap();
+        let plan = graph.plan_transients(&order);
+        assert_eq!(plan.physical.len(), 3);
+        assert_eq!(plan.assignment, vec![Some(0), Some(1), Some(2)]);
+    }
+}
@@ -0,0 +1,183 @@
+//! Frame orchestration for simple renderers
+
+use ::{
+    stats::FrameStats,
+    swapchain::EasySwapchain,
+    trace,
+    Result,
+};
+
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::AutoCommandBufferBuilder,
+    device::{
+        Device,
+        Queue,
+    },
+    framebuffer::{
+        Framebuffer,
+        FramebufferAbstract,
+        RenderPassAbstract,
+    },
+    sync::{
+        self,
+        GpuFuture,
+    },
+};
+
+
+/// Framebuffer type handed over to the render loop's drawing callback
+pub type EasyFramebuffer = Arc<dyn FramebufferAbstract + Send + Sync>;
+
+
+/// A render loop which takes care of per-frame synchronization
+///
+/// Every frame, the render loop acquires a swapchain image, hands over the
+/// matching framebuffer and a fresh command buffer builder to your drawing
+/// code, then submits the resulting commands and presents the image.
+///
+/// Frames are chained using vulkano's futures, so that the CPU can prepare the
+/// next frame while the GPU renders the previous ones. The number of frames in
+/// flight is bounded by the number of swapchain images.
+///
+/// When the window is resized, call resize(), and the swapchain and
+/// framebuffers will be rebuilt on the next frame.
+///
+/// Frame timing statistics are collected along the way, see stats().
+///
+pub struct EasyRenderLoop<W> {
+    /// Device used for rendering
+    device: Arc<Device>,
+
+    /// Queue which rendering commands are submitted to
+    queue: Arc<Queue>,
+
+    /// Swapchain that we render to
+    swapchain: EasySwapchain<W>,
+
+    /// Render pass which the framebuffers are built for
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+
+    /// One framebuffer per swapchain image
+    framebuffers: Vec<EasyFramebuffer>,
+
+    /// Future representing the end of the previous frame
+    previous_frame_end: Box<dyn GpuFuture>,
+
+    /// Frame timing statistics
+    stats: FrameStats,
+}
+
+impl<W: Send + Sync + 'static> EasyRenderLoop<W> {
+    /// Set up a render loop
+    ///
+    /// The render pass must have a single attachment, which is compatible
+    /// with the swapchain images. The queue is used for rendering, and must
+    /// be able to access the swapchain images.
+    ///
+    pub fn new(
+        device: &Arc<Device>,
+        queue: &Arc<Queue>,
+        swapchain: EasySwapchain<W>,
+        render_pass: Arc<dyn RenderPassAbstract + Send + Sync>
+    ) -> Result<Self> {
+        let framebuffers = build_framebuffers(&swapchain, &render_pass)?;
+        Ok(EasyRenderLoop {
+            device: device.clone(),
+            queue: queue.clone(),
+            swapchain,
+            render_pass,
+            framebuffers,
+            previous_frame_end: Box::new(sync::now(device.clone())),
+            stats: FrameStats::new(),
+        })
+    }
+
+    /// Access the swapchain
+    pub fn swapchain(&self) -> &EasySwapchain<W> {
+        &self.swapchain
+    }
+
+    /// Access the frame timing statistics
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Access the frame timing statistics mutably, e.g. to feed them with
+    /// GPU timings or to enable periodic logging
+    pub fn stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.stats
+    }
+
+    /// Notify the render loop that the window was resized
+    pub fn resize(&mut self, dimensions: [u32; 2]) {
+        self.swapchain.resize(dimensions);
+    }
+
+    /// Render and present a frame
+    ///
+    /// The drawing callback receives the framebuffer to render to and a
+    /// command buffer builder, and must return the builder after recording
+    /// its commands in it.
+    ///
+    /// Returns false if the frame had to be skipped, which happens when the
+    /// swapchain cannot be used at the moment (e.g. minimized window).
+    ///
+    pub fn draw_frame(
+        &mut self,
+        draw: impl FnOnce(&EasyFramebuffer, AutoCommandBufferBuilder)
+                  -> Result<AutoCommandBufferBuilder>
+    ) -> Result<bool> {
+        let _trace = trace::span("EasyRenderLoop::draw_frame", String::new);
+
+        // Release the resources of frames which the GPU is done with
+        self.previous_frame_end.cleanup_finished();
+
+        // Acquire a swapchain image, rebuilding framebuffers as needed
+        let (image_num, acquire_future, recreated) =
+            match self.swapchain.acquire()? {
+                Some(acquired) => acquired,
+                None => return Ok(false),
+            };
+        self.stats.begin_frame();
+        if recreated {
+            self.framebuffers = build_framebuffers(&self.swapchain,
+                                                   &self.render_pass)?;
+        }
+
+        // Let the user record the drawing commands
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            self.device.clone(),
+            self.queue.family()
+        )?;
+        let command_buffer = draw(&self.framebuffers[image_num], builder)?
+                                 .build()?;
+
+        // Submit the commands and present the image
+        let previous_frame_end =
+            ::std::mem::replace(&mut self.previous_frame_end,
+                                Box::new(sync::now(self.device.clone())));
+        let future = previous_frame_end.join(acquire_future)
+                                       .then_execute(self.queue.clone(),
+                                                     command_buffer)?;
+        if let Some(future) = self.swapchain.present(future, image_num)? {
+            self.previous_frame_end = Box::new(future);
+        }
+        self.stats.end_frame();
+        Ok(true)
+    }
+}
+
+
+/// Build one framebuffer per swapchain image
+fn build_framebuffers<W: Send + Sync + 'static>(
+    swapchain: &EasySwapchain<W>,
+    render_pass: &Arc<dyn RenderPassAbstract + Send + Sync>
+) -> Result<Vec<EasyFramebuffer>> {
+    swapchain.images().iter().map(|image| -> Result<EasyFramebuffer> {
+        Ok(Arc::new(Framebuffer::start(render_pass.clone())
+                                .add(image.clone())?
+                                .build()?))
+    }).collect()
+}
@@ -0,0 +1,172 @@
+//! Size-dependent render targets which follow window resizes
+//!
+//! Handling window resizes is surprisingly fiddly: the swapchain must be
+//! recreated, then everything whose size depends on it (framebuffers, depth
+//! buffer, viewport) must be rebuilt, and all of this must happen at a time
+//! where the swapchain is not in use. EasyRenderTargets takes care of it.
+
+use ::{
+    graphics_pipeline,
+    render::EasySinglePass,
+    render_loop::EasyFramebuffer,
+    swapchain::EasySwapchain,
+    trace,
+    Result,
+};
+
+use vulkano::{
+    command_buffer::DynamicState,
+    pipeline::viewport::Viewport,
+    swapchain::{
+        PresentFuture,
+        SwapchainAcquireFuture,
+    },
+    sync::{
+        FenceSignalFuture,
+        GpuFuture,
+    },
+};
+
+
+/// A swapchain, along with the render targets that depend on its size
+///
+/// The framebuffers, including the depth and multisampled color attachments
+/// of the render pass if any, are built by the EasySinglePass. A viewport
+/// covering the whole swapchain image is provided as a vulkano DynamicState.
+///
+/// Call on_resize() when the window is resized, or at any other time. Nothing
+/// is rebuilt right away: the swapchain and the render targets are recreated
+/// on the next call to acquire(), which also handles swapchains that the
+/// presentation engine reports as out of date.
+///
+pub struct EasyRenderTargets<W> {
+    /// Swapchain that we render to
+    swapchain: EasySwapchain<W>,
+
+    /// Render pass which the framebuffers are built for
+    single_pass: EasySinglePass,
+
+    /// One framebuffer per swapchain image
+    framebuffers: Vec<EasyFramebuffer>,
+
+    /// Dynamic state with a viewport covering the swapchain images
+    dynamic_state: DynamicState,
+}
+
+impl<W: Send + Sync + 'static> EasyRenderTargets<W> {
+    /// Build the render targets of a swapchain
+    ///
+    /// The render pass' color format must match that of the swapchain.
+    ///
+    pub fn new(swapchain: EasySwapchain<W>,
+               single_pass: EasySinglePass) -> Result<Self> {
+        let framebuffers = single_pass.framebuffers(swapchain.images())?;
+        let dynamic_state = full_viewport(swapchain.dimensions());
+        Ok(EasyRenderTargets {
+            swapchain,
+            single_pass,
+            framebuffers,
+            dynamic_state,
+        })
+    }
+
+    /// Notify the render targets that the window was resized
+    ///
+    /// Everything will be recreated on the next call to acquire().
+    ///
+    pub fn on_resize(&mut self, new_extent: [u32; 2]) {
+        self.swapchain.resize(new_extent);
+    }
+
+    /// Acquire the next swapchain image, rebuilding the targets if needed
+    ///
+    /// On success, this returns the index of the acquired image and a future
+    /// representing the moment where it becomes available. Use framebuffer()
+    /// and dynamic_state() to render to it, as they may have changed.
+    ///
+    /// If the swapchain cannot be used at the moment (e.g. because the window
+    /// is minimized), None is returned and you should skip the frame.
+    ///
+    pub fn acquire(
+        &mut self
+    ) -> Result<Option<(usize, SwapchainAcquireFuture<W>)>> {
+        let _trace = trace::span("EasyRenderTargets::acquire", String::new);
+        let (image_num, future, recreated) = match self.swapchain.acquire()? {
+            Some(acquired) => acquired,
+            None => return Ok(None),
+        };
+        if recreated {
+            let dimensions = self.swapchain.dimensions();
+            debug!("Rebuilding render targets with dimensions {:?}",
+                   dimensions);
+            self.framebuffers =
+                self.single_pass.framebuffers(self.swapchain.images())?;
+            self.dynamic_state = full_viewport(dimensions);
+        }
+        Ok(Some((image_num, future)))
+    }
+
+    /// Present a swapchain image once a future has completed
+    ///
+    /// See EasySwapchain::present() for details.
+    ///
+    pub fn present<F: GpuFuture>(
+        &mut self,
+        future: F,
+        image_num: usize
+    ) -> Result<Option<FenceSignalFuture<PresentFuture<F, W>>>> {
+        self.swapchain.present(future, image_num)
+    }
+
+    /// Access the swapchain
+    pub fn swapchain(&self) -> &EasySwapchain<W> {
+        &self.swapchain
+    }
+
+    /// Access the render pass
+    pub fn single_pass(&self) -> &EasySinglePass {
+        &self.single_pass
+    }
+
+    /// Framebuffer targeting a swapchain image
+    pub fn framebuffer(&self, image_num: usize) -> &EasyFramebuffer {
+        &self.framebuffers[image_num]
+    }
+
+    /// Current dimensions of the render targets
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.swapchain.dimensions()
+    }
+
+    /// Viewport covering the whole render targets
+    pub fn viewport(&self) -> &Viewport {
+        &self.dynamic_state
+             .viewports
+             .as_ref()
+             .expect("Render targets should always have a viewport")[0]
+    }
+
+    /// Dynamic state to be used when drawing, which sets the viewport
+    pub fn dynamic_state(&self) -> &DynamicState {
+        &self.dynamic_state
+    }
+
+    /// Dynamic state which sets both the viewport and the scissor, as needed
+    /// by pipelines from EasyGraphicsPipelineBuilder
+    pub fn dynamic_state_with_scissor(&self) -> DynamicState {
+        graphics_pipeline::easy_dynamic_state(self.dimensions())
+    }
+}
+
+
+/// Dynamic state with a viewport covering an image of some dimensions
+fn full_viewport(dimensions: [u32; 2]) -> DynamicState {
+    DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+            depth_range: 0.0..1.0,
+        }]),
+        .. DynamicState::none()
+    }
+}
@@ -0,0 +1,99 @@
+//! RenderDoc frame capture integration
+//!
+//! This module is only available when the "renderdoc" feature is enabled.
+//! When the application is launched from RenderDoc (or when RenderDoc's
+//! library can otherwise be loaded), EasyInstance detects it at creation time
+//! and lets you trigger captures programmatically.
+//!
+//! This is especially handy for compute prototypes, which never present
+//! frames and thus cannot be captured using RenderDoc's usual hotkeys: wrap
+//! the work that you want to inspect into EasyRenderDoc::capture().
+
+use renderdoc_crate::{
+    RenderDoc,
+    RenderDocV100,
+    V110,
+};
+
+use std::{
+    ptr,
+    sync::Mutex,
+};
+
+
+/// RenderDoc's in-application API
+struct Api(RenderDoc<V110>);
+
+// RenderDoc's in-application API may be called from any thread, we only
+// need to make sure that calls are not concurrent, which the Mutex does.
+unsafe impl Send for Api {}
+
+
+/// Handle to an attached RenderDoc instance
+pub struct EasyRenderDoc {
+    /// In-application API, serialized by a Mutex
+    api: Mutex<Api>,
+}
+
+impl EasyRenderDoc {
+    /// Try to attach to RenderDoc, returns None if it is not available
+    ///
+    /// This must be done before the Vulkan instance is created, which is why
+    /// EasyInstance takes care of it.
+    ///
+    pub fn detect() -> Option<Self> {
+        match RenderDoc::<V110>::new() {
+            Ok(api) => {
+                let (major, minor, patch) = api.get_api_version();
+                info!("RenderDoc is attached (API v{}.{}.{})",
+                      major, minor, patch);
+                Some(EasyRenderDoc {
+                    api: Mutex::new(Api(api)),
+                })
+            },
+            Err(e) => {
+                debug!("RenderDoc is not attached: {}", e);
+                None
+            },
+        }
+    }
+
+    /// Capture the next frame that is presented
+    pub fn trigger_capture(&self) {
+        info!("Triggering a RenderDoc capture of the next frame");
+        self.api.lock().unwrap().0.trigger_capture();
+    }
+
+    /// Start capturing all Vulkan work, until end_capture() is called
+    pub fn start_capture(&self) {
+        info!("Starting a RenderDoc capture");
+        self.api.lock().unwrap().0.start_frame_capture(ptr::null(),
+                                                       ptr::null());
+    }
+
+    /// End a capture started by start_capture()
+    pub fn end_capture(&self) {
+        let mut api = self.api.lock().unwrap();
+        api.0.end_frame_capture(ptr::null(), ptr::null());
+        info!("RenderDoc capture ended ({} capture(s) taken so far)",
+              api.0.get_num_captures());
+    }
+
+    /// Truth that a capture started by start_capture() is ongoing
+    pub fn is_capturing(&self) -> bool {
+        self.api.lock().unwrap().0.is_frame_capturing()
+    }
+
+    /// Number of captures taken so far
+    pub fn num_captures(&self) -> u32 {
+        self.api.lock().unwrap().0.get_num_captures()
+    }
+
+    /// Capture all Vulkan work that is submitted while running some code
+    pub fn capture<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.start_capture();
+        let result = f();
+        self.end_capture();
+        result
+    }
+}
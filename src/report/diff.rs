@@ -0,0 +1,175 @@
+//! Comparison of device capability reports
+//!
+//! When a prototype works on one machine but not on another, the answer is
+//! usually hidden somewhere in the capabilities of the two devices. ReportDiff
+//! compares two DeviceReports, e.g. one produced locally and one saved on the
+//! other machine with the "serde-export" feature, and only keeps what differs.
+
+use super::DeviceReport;
+
+use std::fmt;
+
+
+/// Differences between two lists of names (features, extensions...)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize))]
+pub struct ListDiff {
+    /// Names which only appear in the first list
+    pub only_in_first: Vec<String>,
+
+    /// Names which only appear in the second list
+    pub only_in_second: Vec<String>,
+}
+
+impl ListDiff {
+    /// Compare two lists of names
+    pub fn new(first: &[String], second: &[String]) -> Self {
+        let missing_from = |list: &[String], other: &[String]| -> Vec<String> {
+            list.iter()
+                .filter(|name| !other.contains(name))
+                .cloned()
+                .collect()
+        };
+        ListDiff {
+            only_in_first: missing_from(first, second),
+            only_in_second: missing_from(second, first),
+        }
+    }
+
+    /// Truth that the lists contain the same names
+    pub fn is_empty(&self) -> bool {
+        self.only_in_first.is_empty() && self.only_in_second.is_empty()
+    }
+}
+
+
+/// Device limit which differs between two reports
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize))]
+pub struct LimitDiff {
+    /// Name of the limit, in snake_case
+    pub name: String,
+
+    /// Value of the limit in the first report, if it has it
+    pub first: Option<String>,
+
+    /// Value of the limit in the second report, if it has it
+    pub second: Option<String>,
+}
+
+
+/// Differences between the capabilities of two devices
+///
+/// The Display implementation produces a multi-line human-readable summary,
+/// where "-" marks what only the first device has and "+" marks what only the
+/// second device has.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize))]
+pub struct ReportDiff {
+    /// Names of the two devices
+    pub names: (String, String),
+
+    /// Vulkan API versions, if they differ
+    pub api_versions: Option<(String, String)>,
+
+    /// Driver versions, if they differ
+    pub driver_versions: Option<(u32, u32)>,
+
+    /// Differences in supported features
+    pub features: ListDiff,
+
+    /// Differences in supported extensions
+    pub extensions: ListDiff,
+
+    /// Device limits which differ, in the order of the first report
+    pub limits: Vec<LimitDiff>,
+}
+
+impl ReportDiff {
+    /// Compare the capabilities of two devices
+    pub fn new(first: &DeviceReport, second: &DeviceReport) -> Self {
+        // Limits are compared by name, so that reports from different
+        // vulkanoob versions can still be compared
+        let mut limits = first.limits.iter().filter_map(|(name, value)| {
+            let other = second.limit(name);
+            if other == Some(&value[..]) { return None; }
+            Some(LimitDiff {
+                name: name.clone(),
+                first: Some(value.clone()),
+                second: other.map(str::to_owned),
+            })
+        }).collect::<Vec<_>>();
+        limits.extend(second.limits.iter().filter_map(|(name, value)| {
+            if first.limit(name).is_some() { return None; }
+            Some(LimitDiff {
+                name: name.clone(),
+                first: None,
+                second: Some(value.clone()),
+            })
+        }));
+
+        ReportDiff {
+            names: (first.name.clone(), second.name.clone()),
+            api_versions: differs(first.api_version.clone(),
+                                  second.api_version.clone()),
+            driver_versions: differs(first.driver_version,
+                                     second.driver_version),
+            features: ListDiff::new(&first.features, &second.features),
+            extensions: ListDiff::new(&first.extensions, &second.extensions),
+            limits,
+        }
+    }
+
+    /// Truth that no difference was found
+    pub fn is_empty(&self) -> bool {
+        self.api_versions.is_none()
+            && self.driver_versions.is_none()
+            && self.features.is_empty()
+            && self.extensions.is_empty()
+            && self.limits.is_empty()
+    }
+}
+
+impl fmt::Display for ReportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Differences between \"{}\" (-) and \"{}\" (+):",
+                 self.names.0, self.names.1)?;
+        if self.is_empty() {
+            return writeln!(f, "    None");
+        }
+        if let Some((ref first, ref second)) = self.api_versions {
+            writeln!(f, "Vulkan API version: {} -> {}", first, second)?;
+        }
+        if let Some((first, second)) = self.driver_versions {
+            writeln!(f, "Driver version: {} -> {}", first, second)?;
+        }
+        for &(title, ref list) in &[("Features", &self.features),
+                                     ("Extensions", &self.extensions)] {
+            if list.is_empty() { continue; }
+            writeln!(f, "{}:", title)?;
+            for name in &list.only_in_first {
+                writeln!(f, "    - {}", name)?;
+            }
+            for name in &list.only_in_second {
+                writeln!(f, "    + {}", name)?;
+            }
+        }
+        if !self.limits.is_empty() {
+            writeln!(f, "Device limits:")?;
+            for limit in &self.limits {
+                writeln!(f, "    {}: {} -> {}",
+                         limit.name,
+                         limit.first.as_ref().map_or("(none)", |v| &v[..]),
+                         limit.second.as_ref().map_or("(none)", |v| &v[..]))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Keep a pair of values if they differ
+fn differs<T: PartialEq>(first: T, second: T) -> Option<(T, T)> {
+    if first != second { Some((first, second)) } else { None }
+}
@@ -0,0 +1,77 @@
+//! Machine-readable export of capability reports
+//!
+//! JSON export is always available when this module is enabled. TOML export
+//! additionally requires the "toml-export" feature.
+//!
+//! Reports can also be loaded back, e.g. in order to compare the capabilities
+//! of devices from different machines using report::diff.
+
+use ::{
+    Error,
+    Result,
+};
+
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+
+use serde_json;
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+#[cfg(feature = "toml-export")]
+use toml;
+
+
+/// Serialize a report (e.g. an InstanceReport) to pretty-printed JSON
+pub fn to_json(report: &impl Serialize) -> Result<String> {
+    Ok(serde_json::to_string_pretty(report)?)
+}
+
+/// Serialize a report (e.g. an InstanceReport) to TOML
+#[cfg(feature = "toml-export")]
+pub fn to_toml(report: &impl Serialize) -> Result<String> {
+    Ok(toml::to_string_pretty(report)?)
+}
+
+/// Save a report to a file, picking the format from the file extension
+///
+/// The ".json" extension is always supported, and the ".toml" extension is
+/// supported if the "toml-export" feature is enabled.
+///
+pub fn save(report: &impl Serialize, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let contents = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => to_json(report)?,
+        #[cfg(feature = "toml-export")]
+        Some("toml") => to_toml(report)?,
+        _ => return Err(Error::InvalidArgument(
+            format!("Unsupported report format for file {}", path.display())
+        )),
+    };
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Load a report (e.g. a DeviceReport) which was saved by save()
+///
+/// The format is picked from the file extension, as in save().
+///
+pub fn load<R: DeserializeOwned>(path: impl AsRef<Path>) -> Result<R> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => Ok(serde_json::from_str(&contents)?),
+        #[cfg(feature = "toml-export")]
+        Some("toml") => Ok(toml::from_str(&contents)?),
+        _ => Err(Error::InvalidArgument(
+            format!("Unsupported report format for file {}", path.display())
+        )),
+    }
+}
@@ -0,0 +1,778 @@
+//! Human-readable and machine-readable reports of Vulkan capabilities
+//!
+//! These reports are what vulkanoob logs when you select a physical device,
+//! but you can also build them yourself in order to inspect a device's
+//! capabilities programmatically or to save them somewhere.
+
+use ::{
+    display,
+    mesh_shader::MeshShaderSupport,
+    subgroup::SubgroupProperties,
+    video::VideoSupport,
+    Result,
+};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use std::{
+    fmt,
+    sync::Arc,
+};
+
+use vulkano::instance::{
+    self,
+    DeviceExtensions,
+    Features,
+    Instance,
+    PhysicalDevice,
+    RawDeviceExtensions,
+    RawInstanceExtensions,
+};
+
+pub mod diff;
+#[cfg(feature = "serde-export")]
+pub mod export;
+
+
+/// Capabilities of a physical device
+///
+/// This is a snapshot of the device's properties which only contains owned
+/// and plain data, so that it can be kept around after the instance is gone.
+///
+/// The Display implementation produces a multi-line human-readable report.
+///
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct DeviceReport {
+    /// Index of the device in the instance's device list
+    pub index: usize,
+
+    /// Name of the device
+    pub name: String,
+
+    /// Type of device (discrete GPU, integrated GPU...)
+    pub device_type: String,
+
+    /// Driver version, in a vendor-specific format
+    pub driver_version: u32,
+
+    /// PCI vendor identifier
+    pub pci_vendor_id: u32,
+
+    /// PCI device identifier
+    pub pci_device_id: u32,
+
+    /// Device UUID, as a hexadecimal string
+    pub uuid: String,
+
+    /// Supported Vulkan API version
+    pub api_version: String,
+
+    /// Supported device extensions, in alphabetical order
+    pub extensions: Vec<String>,
+
+    /// Supported device features, in vulkano's naming convention
+    pub features: Vec<String>,
+
+    /// Device limits, as (name, value) pairs using vulkano's snake_case names
+    /// (e.g. "max_image_dimension_2d")
+    pub limits: Vec<(String, String)>,
+
+    /// Vulkan Video support, if it could be queried
+    #[cfg_attr(feature = "serde-export", serde(default))]
+    pub video: Option<VideoSupport>,
+
+    /// Subgroup properties, if they could be queried
+    pub subgroup: Option<SubgroupReport>,
+
+    /// Displays which are attached to the device, if they can be driven
+    /// directly (see the display module)
+    #[cfg_attr(feature = "serde-export", serde(default))]
+    pub displays: Vec<DisplayReport>,
+
+    /// Queue families
+    pub queue_families: Vec<QueueFamilyReport>,
+
+    /// Memory types
+    pub memory_types: Vec<MemoryTypeReport>,
+
+    /// Memory heaps
+    pub memory_heaps: Vec<MemoryHeapReport>,
+}
+
+/// Capabilities of a queue family
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct QueueFamilyReport {
+    /// Queue family identifier
+    pub id: u32,
+
+    /// Number of queues in the family
+    pub queues_count: usize,
+
+    /// Truth that the queues support graphics operations
+    pub graphics: bool,
+
+    /// Truth that the queues support compute operations
+    pub compute: bool,
+
+    /// Truth that the queues support transfer operations
+    pub transfers: bool,
+
+    /// Truth that the queues support sparse resource bindings
+    pub sparse_binding: bool,
+}
+
+/// Subgroup properties of a device
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct SubgroupReport {
+    /// Default number of invocations per subgroup
+    pub size: u32,
+
+    /// Shader stages where subgroup operations are supported
+    pub stages: Vec<String>,
+
+    /// Supported subgroup operations
+    pub operations: Vec<String>,
+
+    /// Truth that quad operations are supported in all supported stages
+    pub quad_operations_in_all_stages: bool,
+}
+
+impl SubgroupReport {
+    /// Describe the subgroup properties of a device
+    pub fn new(properties: &SubgroupProperties) -> Self {
+        let names = |names: Vec<&str>| -> Vec<String> {
+            names.into_iter().map(str::to_owned).collect()
+        };
+        SubgroupReport {
+            size: properties.size,
+            stages: names(properties.stage_names()),
+            operations: names(properties.supported_operations.names()),
+            quad_operations_in_all_stages:
+                properties.quad_operations_in_all_stages,
+        }
+    }
+}
+
+/// Properties of a display
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct DisplayReport {
+    /// Name of the display
+    pub name: String,
+
+    /// Physical size of the display, in millimeters
+    pub physical_dimensions: [u32; 2],
+
+    /// Native resolution of the display, in pixels
+    pub physical_resolution: [u32; 2],
+
+    /// Display modes, as (visible region, refresh rate in mHz) pairs
+    pub modes: Vec<([u32; 2], u32)>,
+}
+
+/// Properties of a memory type
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct MemoryTypeReport {
+    /// Memory type identifier
+    pub id: u32,
+
+    /// Identifier of the heap that this memory type belongs to
+    pub heap_id: u32,
+
+    /// Truth that the memory is located on the device
+    pub device_local: bool,
+
+    /// Truth that the memory can be mapped by the host
+    pub host_visible: bool,
+
+    /// Truth that host accesses do not require explicit flushes
+    pub host_coherent: bool,
+
+    /// Truth that the memory is cached on the host
+    pub host_cached: bool,
+
+    /// Truth that the memory is lazily allocated
+    pub lazily_allocated: bool,
+}
+
+/// Properties of a memory heap
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct MemoryHeapReport {
+    /// Memory heap identifier
+    pub id: u32,
+
+    /// Size of the heap in bytes
+    pub size: usize,
+
+    /// Truth that the memory is located on the device
+    pub device_local: bool,
+}
+
+
+/// Vulkan capabilities of the instance and its physical devices
+///
+/// The Display implementation produces a multi-line human-readable report,
+/// and the "export" submodule (behind the "serde-export" feature) can turn
+/// this into machine-readable formats.
+///
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct InstanceReport {
+    /// Supported instance extensions, in alphabetical order
+    pub extensions: Vec<String>,
+
+    /// Available instance layers
+    pub layers: Vec<LayerReport>,
+
+    /// Capabilities of each physical device
+    pub devices: Vec<DeviceReport>,
+}
+
+/// Properties of an instance layer
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct LayerReport {
+    /// Name of the layer
+    pub name: String,
+
+    /// Description of the layer
+    pub description: String,
+
+    /// Version of the layer implementation
+    pub implementation_version: u32,
+
+    /// Vulkan API version that the layer was written against
+    pub vulkan_version: String,
+}
+
+impl InstanceReport {
+    /// Probe the capabilities of the Vulkan implementation
+    ///
+    /// Instance extensions and layers do not depend on the instance, but it
+    /// is needed in order to enumerate physical devices. With the "parallel"
+    /// feature, physical devices are probed concurrently.
+    ///
+    pub fn new(instance: &Arc<Instance>) -> Result<Self> {
+        let mut extensions =
+            RawInstanceExtensions::supported_by_core_raw()?
+                .iter()
+                .map(|ext| ext.to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+        extensions.sort();
+        let layers = instance::layers_list()?.map(|layer| {
+            LayerReport {
+                name: layer.name().to_owned(),
+                description: layer.description().to_owned(),
+                implementation_version: layer.implementation_version(),
+                vulkan_version: layer.vulkan_version().to_string(),
+            }
+        }).collect();
+        let devices = PhysicalDevice::enumerate(instance)
+                                     .collect::<Vec<_>>();
+        #[cfg(feature = "parallel")]
+        let devices = devices.into_par_iter()
+                             .map(DeviceReport::new)
+                             .collect::<Result<_>>()?;
+        #[cfg(not(feature = "parallel"))]
+        let devices = devices.into_iter()
+                             .map(DeviceReport::new)
+                             .collect::<Result<_>>()?;
+        Ok(InstanceReport {
+            extensions,
+            layers,
+            devices,
+        })
+    }
+}
+
+impl fmt::Display for InstanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Supported instance extensions: {}",
+                 self.extensions.join(", "))?;
+        writeln!(f, "Available instance layers:")?;
+        for layer in &self.layers {
+            writeln!(f, "    - {} ({}) [Version {}, targeting Vulkan v{}]",
+                     layer.name,
+                     layer.description,
+                     layer.implementation_version,
+                     layer.vulkan_version)?;
+        }
+        for device in &self.devices {
+            writeln!(f)?;
+            write!(f, "{}", device)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Names of the features which are enabled in a Features struct
+///
+/// Vulkano has no way to iterate over features, so we extract the names from
+/// the Debug output of the Features struct.
+///
+pub(crate) fn feature_names(features: &Features) -> Vec<String> {
+    format!("{:?}", features)
+        .trim_start_matches(|c| c != '{')
+        .trim_matches(|c| c == '{' || c == '}' || c == ' ')
+        .split(", ")
+        .filter_map(|entry| {
+            let mut parts = entry.split(": ");
+            match (parts.next(), parts.next()) {
+                (Some(name), Some("true")) => Some(name.to_owned()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Names of the extensions which are enabled in a DeviceExtensions struct
+pub(crate) fn extension_names(extensions: &DeviceExtensions) -> Vec<String> {
+    RawDeviceExtensions::from(extensions)
+        .iter()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .collect()
+}
+
+
+/// Collect a set of device limits into (name, value) pairs
+macro_rules! limits {
+    ($limits:expr, $($name:ident),* $(,)*) => {
+        vec![
+            $( (stringify!($name).to_owned(),
+                format!("{:?}", $limits.$name())) ),*
+        ]
+    };
+}
+
+impl DeviceReport {
+    /// Probe the capabilities of a physical device
+    pub fn new(device: PhysicalDevice) -> Result<Self> {
+        let supported_features = device.supported_features();
+        if !supported_features.robust_buffer_access {
+            return Err(format_err!("Device \"{}\" does not support robust \
+                                    buffer access, which is mandated by the \
+                                    Vulkan spec", device.name()).into());
+        }
+        let features = feature_names(supported_features);
+
+        // Extensions are sorted for ease of reading and comparison
+        let mut extensions =
+            RawDeviceExtensions::supported_by_device_raw(device)?
+                .iter()
+                .map(|ext| ext.to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+        extensions.sort();
+
+        let limits = device.limits();
+        let mut report = DeviceReport {
+            index: device.index(),
+            name: device.name(),
+            device_type: format!("{:?}", device.ty()),
+            driver_version: device.driver_version(),
+            pci_vendor_id: device.pci_vendor_id(),
+            pci_device_id: device.pci_device_id(),
+            uuid: device.uuid().iter()
+                               .map(|byte| format!("{:02x}", byte))
+                               .collect(),
+            api_version: device.api_version().to_string(),
+            extensions,
+            features,
+            video: VideoSupport::of_device(device).map_err(|e| {
+                warn!("Failed to query Vulkan Video support of device \
+                       \"{}\": {}", device.name(), e);
+            }).ok(),
+            subgroup: SubgroupProperties::of_device(device)
+                                        .as_ref()
+                                        .map(SubgroupReport::new),
+            displays: display::displays(device)?.iter().map(|display| {
+                Ok(DisplayReport {
+                    name: display.name().to_owned(),
+                    physical_dimensions: display.physical_dimensions(),
+                    physical_resolution: display.physical_resolution(),
+                    modes: display::display_modes(display)?
+                               .iter()
+                               .map(|mode| (mode.visible_region(),
+                                            mode.refresh_rate()))
+                               .collect(),
+                })
+            }).collect::<Result<_>>()?,
+            queue_families: device.queue_families().map(|family| {
+                QueueFamilyReport {
+                    id: family.id(),
+                    queues_count: family.queues_count(),
+                    graphics: family.supports_graphics(),
+                    compute: family.supports_compute(),
+                    transfers: family.supports_transfers(),
+                    sparse_binding: family.supports_sparse_binding(),
+                }
+            }).collect(),
+            memory_types: device.memory_types().map(|memory_type| {
+                MemoryTypeReport {
+                    id: memory_type.id(),
+                    heap_id: memory_type.heap().id(),
+                    device_local: memory_type.is_device_local(),
+                    host_visible: memory_type.is_host_visible(),
+                    host_coherent: memory_type.is_host_coherent(),
+                    host_cached: memory_type.is_host_cached(),
+                    lazily_allocated: memory_type.is_lazily_allocated(),
+                }
+            }).collect(),
+            memory_heaps: device.memory_heaps().map(|heap| {
+                MemoryHeapReport {
+                    id: heap.id(),
+                    size: heap.size(),
+                    device_local: heap.is_device_local(),
+                }
+            }).collect(),
+            limits: limits!(limits,
+                max_image_dimension_1d,
+                max_image_dimension_2d,
+                max_image_dimension_3d,
+                max_image_dimension_cube,
+                max_image_array_layers,
+                max_texel_buffer_elements,
+                max_uniform_buffer_range,
+                max_storage_buffer_range,
+                max_push_constants_size,
+                max_memory_allocation_count,
+                max_sampler_allocation_count,
+                buffer_image_granularity,
+                sparse_address_space_size,
+                max_bound_descriptor_sets,
+                max_per_stage_descriptor_samplers,
+                max_per_stage_descriptor_uniform_buffers,
+                max_per_stage_descriptor_storage_buffers,
+                max_per_stage_descriptor_sampled_images,
+                max_per_stage_descriptor_storage_images,
+                max_per_stage_descriptor_input_attachments,
+                max_per_stage_resources,
+                max_descriptor_set_samplers,
+                max_descriptor_set_uniform_buffers,
+                max_descriptor_set_uniform_buffers_dynamic,
+                max_descriptor_set_storage_buffers,
+                max_descriptor_set_storage_buffers_dynamic,
+                max_descriptor_set_sampled_images,
+                max_descriptor_set_storage_images,
+                max_descriptor_set_input_attachments,
+                max_vertex_input_attributes,
+                max_vertex_input_bindings,
+                max_vertex_input_attribute_offset,
+                max_vertex_input_binding_stride,
+                max_vertex_output_components,
+                max_tessellation_generation_level,
+                max_tessellation_patch_size,
+                max_tessellation_control_per_vertex_input_components,
+                max_tessellation_control_per_vertex_output_components,
+                max_tessellation_control_per_patch_output_components,
+                max_tessellation_control_total_output_components,
+                max_tessellation_evaluation_input_components,
+                max_tessellation_evaluation_output_components,
+                max_geometry_shader_invocations,
+                max_geometry_input_components,
+                max_geometry_output_components,
+                max_geometry_output_vertices,
+                max_geometry_total_output_components,
+                max_fragment_input_components,
+                max_fragment_output_attachments,
+                max_fragment_dual_src_attachments,
+                max_fragment_combined_output_resources,
+                max_compute_shared_memory_size,
+                max_compute_work_group_count,
+                max_compute_work_group_invocations,
+                max_compute_work_group_size,
+                sub_pixel_precision_bits,
+                sub_texel_precision_bits,
+                mipmap_precision_bits,
+                max_draw_indexed_index_value,
+                max_draw_indirect_count,
+                max_sampler_lod_bias,
+                max_sampler_anisotropy,
+                max_viewports,
+                max_viewport_dimensions,
+                viewport_bounds_range,
+                viewport_sub_pixel_bits,
+                min_memory_map_alignment,
+                min_texel_buffer_offset_alignment,
+                min_uniform_buffer_offset_alignment,
+                min_storage_buffer_offset_alignment,
+                min_texel_offset,
+                max_texel_offset,
+                min_texel_gather_offset,
+                max_texel_gather_offset,
+                min_interpolation_offset,
+                max_interpolation_offset,
+                sub_pixel_interpolation_offset_bits,
+                max_framebuffer_width,
+                max_framebuffer_height,
+                max_framebuffer_layers,
+                framebuffer_color_sample_counts,
+                framebuffer_depth_sample_counts,
+                framebuffer_stencil_sample_counts,
+                framebuffer_no_attachments_sample_counts,
+                max_color_attachments,
+                sampled_image_color_sample_counts,
+                sampled_image_integer_sample_counts,
+                sampled_image_depth_sample_counts,
+                sampled_image_stencil_sample_counts,
+                storage_image_sample_counts,
+                max_sample_mask_words,
+                timestamp_compute_and_graphics,
+                timestamp_period,
+                max_clip_distances,
+                max_cull_distances,
+                max_combined_clip_and_cull_distances,
+                discrete_queue_priorities,
+                point_size_range,
+                line_width_range,
+                point_size_granularity,
+                line_width_granularity,
+                strict_lines,
+                standard_sample_locations,
+                optimal_buffer_copy_offset_alignment,
+                optimal_buffer_copy_row_pitch_alignment,
+                non_coherent_atom_size,
+            ),
+        };
+
+        // Mesh shading features and limits are not known to vulkano, but are
+        // useful when comparing devices, so they are reported alongside the
+        // core ones
+        if let Some(mesh) = MeshShaderSupport::of_device(device)? {
+            report.features.extend(mesh.features.names());
+            report.limits.extend(mesh.limits.to_pairs());
+        }
+        Ok(report)
+    }
+
+    /// Look up a device limit by its snake_case name
+    pub fn limit(&self, name: &str) -> Option<&str> {
+        self.limits.iter()
+                   .find(|&&(ref limit, _)| limit == name)
+                   .map(|&(_, ref value)| &value[..])
+    }
+}
+
+
+/// Capabilities of a physical device, probed on first use
+///
+/// Building a DeviceReport queries and formats hundreds of device properties,
+/// which is wasted work if nobody looks at the result, e.g. when device logs
+/// are disabled. A LazyDeviceReport only builds the report when it is first
+/// requested, and keeps it around afterwards.
+///
+pub struct LazyDeviceReport<'a> {
+    /// Device which the report is about
+    device: PhysicalDevice<'a>,
+
+    /// Report of the device, if it was already built
+    report: Option<DeviceReport>,
+}
+
+impl<'a> LazyDeviceReport<'a> {
+    /// Prepare to report the capabilities of a physical device
+    pub fn new(device: PhysicalDevice<'a>) -> Self {
+        LazyDeviceReport {
+            device,
+            report: None,
+        }
+    }
+
+    /// Device which the report is about
+    pub fn device(&self) -> PhysicalDevice<'a> {
+        self.device
+    }
+
+    /// Truth that the report was already built
+    pub fn is_built(&self) -> bool {
+        self.report.is_some()
+    }
+
+    /// Access the report, building it if needed
+    pub fn get(&mut self) -> Result<&DeviceReport> {
+        if self.report.is_none() {
+            self.report = Some(DeviceReport::new(self.device)?);
+        }
+        Ok(self.report.as_ref().expect("The report was just built"))
+    }
+
+    /// Extract the report, building it if needed
+    pub fn into_report(mut self) -> Result<DeviceReport> {
+        self.get()?;
+        Ok(self.report.take().expect("The report was just built"))
+    }
+}
+
+impl fmt::Display for DeviceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Low-level device and driver information
+        writeln!(f, "Device #{}: {}", self.index, self.name)?;
+        writeln!(f, "Type: {}", self.device_type)?;
+        writeln!(f, "Driver version: {}", self.driver_version)?;
+        writeln!(f, "PCI vendor/device id: 0x{:x}/0x{:x}",
+                 self.pci_vendor_id,
+                 self.pci_device_id)?;
+        writeln!(f, "UUID: 0x{}", self.uuid)?;
+
+        // Supported Vulkan API version, extensions and features
+        writeln!(f, "Vulkan API version: {}", self.api_version)?;
+        writeln!(f, "Supported device extensions: {}",
+                 self.extensions.join(", "))?;
+        writeln!(f, "Supported features: {}", self.features.join(", "))?;
+        match self.video {
+            Some(ref video) => {
+                writeln!(f, "Vulkan Video support: queue={}, decode={}, \
+                             codecs={:?}",
+                         video.video_queue,
+                         video.decode_queue,
+                         video.decode_codecs)?;
+                for family in &video.queue_families {
+                    writeln!(f, "    Queue family {}: decode={}, encode={}, \
+                                 decode codecs={:?}",
+                             family.family,
+                             family.decode,
+                             family.encode,
+                             family.decode_codecs)?;
+                }
+            },
+            None => writeln!(f, "Vulkan Video support: unknown")?,
+        }
+        match self.subgroup {
+            Some(ref subgroup) => {
+                writeln!(f, "Subgroups: size {}, operations [{}] in stages \
+                             [{}]{}",
+                         subgroup.size,
+                         subgroup.operations.join(", "),
+                         subgroup.stages.join(", "),
+                         if subgroup.quad_operations_in_all_stages {
+                             ", quad operations in all stages"
+                         } else {
+                             ""
+                         })?;
+            },
+            None => writeln!(f, "Subgroups: unknown")?,
+        }
+
+        // Queue families
+        writeln!(f, "Queue familie(s):")?;
+        for family in &self.queue_families {
+            write!(f, "    {}: {} queue(s) for ", family.id,
+                                                 family.queues_count)?;
+            if family.graphics { write!(f, "graphics, ")?; }
+            if family.compute { write!(f, "compute, ")?; }
+            if family.transfers { write!(f, "transfers, ")?; }
+            if family.sparse_binding {
+                write!(f, "sparse resource bindings, ")?;
+            }
+            writeln!(f)?;
+        }
+
+        // Memory types
+        writeln!(f, "Memory type(s):")?;
+        for memory_type in &self.memory_types {
+            write!(f, "    {}: from heap #{}, ", memory_type.id,
+                                                memory_type.heap_id)?;
+            if memory_type.device_local {
+                write!(f, "on device, ")?;
+            } else {
+                write!(f, "on host, ")?;
+            }
+            if memory_type.host_visible {
+                write!(f, "host-visible, ")?;
+            } else {
+                write!(f, "only accessible by device, ")?;
+            }
+            if memory_type.host_coherent { write!(f, "host-coherent, ")?; }
+            if memory_type.host_cached { write!(f, "host-cached, ")?; }
+            if memory_type.lazily_allocated {
+                write!(f, "lazily allocated, ")?;
+            }
+            writeln!(f)?;
+        }
+
+        // Displays
+        if !self.displays.is_empty() {
+            writeln!(f, "Display(s):")?;
+            for display in &self.displays {
+                writeln!(f, "    - {} ({}x{} mm, native resolution {}x{})",
+                         display.name,
+                         display.physical_dimensions[0],
+                         display.physical_dimensions[1],
+                         display.physical_resolution[0],
+                         display.physical_resolution[1])?;
+                for &([width, height], refresh_rate) in &display.modes {
+                    writeln!(f, "        {}x{} @ {}.{:03} Hz",
+                             width, height,
+                             refresh_rate / 1000, refresh_rate % 1000)?;
+                }
+            }
+        }
+
+        // Memory heaps
+        writeln!(f, "Memory heap(s):")?;
+        for heap in &self.memory_heaps {
+            writeln!(f, "    {}: {} bytes, {}",
+                     heap.id,
+                     heap.size,
+                     if heap.device_local { "on device" } else { "on host" })?;
+        }
+
+        // Device limits
+        if !self.limits.is_empty() {
+            writeln!(f, "Device limits:")?;
+            for &(ref name, ref value) in &self.limits {
+                writeln!(f, "    - {}: {}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Short human-readable description of a physical device
+///
+/// Only mentions the device's name and type, its memory heaps and its queue
+/// families. This is what gets logged at the Summary level of detail.
+///
+pub struct DeviceSummary<'a>(&'a DeviceReport);
+
+impl DeviceReport {
+    /// Short description of the device, see DeviceSummary
+    pub fn summary(&self) -> DeviceSummary {
+        DeviceSummary(self)
+    }
+}
+
+impl<'a> fmt::Display for DeviceSummary<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let report = self.0;
+        writeln!(f, "Device #{}: {} ({}, Vulkan v{})",
+                 report.index,
+                 report.name,
+                 report.device_type,
+                 report.api_version)?;
+        write!(f, "Memory heap(s):")?;
+        for heap in &report.memory_heaps {
+            write!(f, " {} MiB {},",
+                   heap.size / (1024 * 1024),
+                   if heap.device_local { "on device" } else { "on host" })?;
+        }
+        writeln!(f)?;
+        write!(f, "Queue family(ies):")?;
+        for family in &report.queue_families {
+            write!(f, " {}x", family.queues_count)?;
+            if family.graphics { write!(f, "G")?; }
+            if family.compute { write!(f, "C")?; }
+            if family.transfers { write!(f, "T")?; }
+            if family.sparse_binding { write!(f, "S")?; }
+            write!(f, ",")?;
+        }
+        writeln!(f)
+    }
+}
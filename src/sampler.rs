@@ -0,0 +1,146 @@
+//! Sampler presets
+//!
+//! Most texture prototypes only need a handful of sampler configurations.
+//! EasySampler provides them, and checks the relevant device limits (which
+//! are logged during device enumeration) before creating the sampler, so that
+//! you get a clear error instead of a validation layer message.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::sync::{
+    Arc,
+    Mutex,
+    Weak,
+};
+
+use vulkano::{
+    device::Device,
+    sampler::{
+        Filter,
+        MipmapMode,
+        Sampler,
+        SamplerAddressMode,
+    },
+};
+
+
+/// Factory of samplers with common configurations
+///
+/// Devices can only have a limited amount of samplers alive at any given
+/// time (given by the max_sampler_allocation_count limit). EasySampler keeps
+/// track of the samplers that it created, and refuses to go above the limit.
+///
+pub struct EasySampler {
+    /// Device which the samplers are created for
+    device: Arc<Device>,
+
+    /// Samplers created so far, some of which may have been dropped
+    samplers: Mutex<Vec<Weak<Sampler>>>,
+}
+
+impl EasySampler {
+    /// Prepare to create samplers for a device
+    pub fn new(device: &Arc<Device>) -> Self {
+        EasySampler {
+            device: device.clone(),
+            samplers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Sampler with nearest-neighbor filtering and clamp-to-edge addressing
+    ///
+    /// This is what you want for pixel-exact lookups, e.g. when sampling a
+    /// render target or a lookup table.
+    ///
+    pub fn nearest_clamp(&self) -> Result<Arc<Sampler>> {
+        let _trace = trace::span("EasySampler::nearest_clamp", String::new);
+        self.create(Filter::Nearest,
+                    MipmapMode::Nearest,
+                    SamplerAddressMode::ClampToEdge,
+                    1.0)
+    }
+
+    /// Sampler with linear filtering and repeating addressing
+    ///
+    /// This is the usual setup for tiling textures.
+    ///
+    pub fn linear_repeat(&self) -> Result<Arc<Sampler>> {
+        let _trace = trace::span("EasySampler::linear_repeat", String::new);
+        self.create(Filter::Linear,
+                    MipmapMode::Linear,
+                    SamplerAddressMode::Repeat,
+                    1.0)
+    }
+
+    /// Like linear_repeat(), but with anisotropic filtering
+    ///
+    /// The sampler_anisotropy device feature must be enabled, and the maximal
+    /// anisotropy must be between 1.0 and the max_sampler_anisotropy limit.
+    ///
+    pub fn anisotropic(&self, max_anisotropy: f32) -> Result<Arc<Sampler>> {
+        let _trace = trace::span("EasySampler::anisotropic",
+                                 || format!("max_anisotropy: {}",
+                                            max_anisotropy));
+        if !self.device.enabled_features().sampler_anisotropy {
+            return Err(Error::InvalidArgument(
+                "Anisotropic filtering requires the sampler_anisotropy \
+                 device feature".to_owned()
+            ));
+        }
+        let limit = self.device.physical_device()
+                               .limits()
+                               .max_sampler_anisotropy();
+        if !(1.0..=limit).contains(&max_anisotropy) {
+            return Err(Error::InvalidArgument(
+                format!("Maximal anisotropy {} is not between 1.0 and the \
+                         device's max_sampler_anisotropy limit ({})",
+                        max_anisotropy, limit)
+            ));
+        }
+        self.create(Filter::Linear,
+                    MipmapMode::Linear,
+                    SamplerAddressMode::Repeat,
+                    max_anisotropy)
+    }
+
+    /// Number of samplers created by this factory which are still alive
+    pub fn live_samplers(&self) -> usize {
+        let mut samplers = self.samplers.lock().unwrap();
+        samplers.retain(|sampler| sampler.upgrade().is_some());
+        samplers.len()
+    }
+
+    /// Create a sampler, checking the sampler allocation limit
+    fn create(&self,
+              filter: Filter,
+              mipmap_mode: MipmapMode,
+              address_mode: SamplerAddressMode,
+              max_anisotropy: f32) -> Result<Arc<Sampler>> {
+        let max_samplers = self.device.physical_device()
+                                      .limits()
+                                      .max_sampler_allocation_count();
+        if self.live_samplers() >= max_samplers as usize {
+            return Err(Error::InvalidArgument(
+                format!("Cannot create more than {} samplers (device's \
+                         max_sampler_allocation_count limit)", max_samplers)
+            ));
+        }
+        let sampler = Sampler::new(self.device.clone(),
+                                   filter,
+                                   filter,
+                                   mipmap_mode,
+                                   address_mode,
+                                   address_mode,
+                                   address_mode,
+                                   0.0,
+                                   max_anisotropy,
+                                   0.0,
+                                   1000.0)?;
+        self.samplers.lock().unwrap().push(Arc::downgrade(&sampler));
+        Ok(sampler)
+    }
+}
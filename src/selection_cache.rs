@@ -0,0 +1,130 @@
+//! Persistent cache of device selection results
+//!
+//! On some systems, enumerating and describing every physical device takes a
+//! noticeable amount of time. A SelectionCache remembers which device was
+//! selected last time, along with a hash of the requirements which it was
+//! selected with. When the requirements are the same and the device is still
+//! around, EasyInstance::select_physical_device_cached() only checks that the
+//! device still passes the filter, and skips the full selection pass.
+//!
+//! The hash is not guaranteed to be stable across Rust versions, so an
+//! upgrade may cause one full selection pass, but never a wrong selection.
+
+use ::Result;
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Write as FmtWrite,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
+
+
+/// Device selection which was recorded in a cache
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CachedSelection {
+    /// UUID of the selected physical device
+    pub uuid: [u8; 16],
+
+    /// Hash of the requirements which the device was selected with
+    pub requirements_hash: u64,
+}
+
+
+/// Cache of the last device selection, stored in a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectionCache {
+    /// File where the selection is stored
+    path: PathBuf,
+}
+
+impl SelectionCache {
+    /// Use some file as a selection cache
+    ///
+    /// The file does not need to exist yet, it is created when a selection is
+    /// stored. Its parent directory must exist, however.
+    ///
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        SelectionCache {
+            path: path.into(),
+        }
+    }
+
+    /// File where the selection is stored
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the cached selection, if there is a valid one
+    ///
+    /// A missing or corrupt cache file is treated like an empty cache.
+    ///
+    pub fn load(&self) -> Option<CachedSelection> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Failed to read device selection cache {}: {}",
+                      self.path.display(), e);
+                return None;
+            },
+        };
+        let selection = parse_selection(contents.trim());
+        if selection.is_none() {
+            warn!("Ignoring corrupt device selection cache {}",
+                  self.path.display());
+        }
+        selection
+    }
+
+    /// Record a selection in the cache, replacing the previous one
+    pub fn store(&self, selection: &CachedSelection) -> Result<()> {
+        let mut contents = String::with_capacity(50);
+        for byte in &selection.uuid {
+            write!(contents, "{:02x}", byte).expect("Can't fail");
+        }
+        writeln!(contents, " {:016x}", selection.requirements_hash)
+            .expect("Can't fail");
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+
+    /// Forget the cached selection
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+
+/// Hash some description of device selection requirements
+pub fn requirements_hash(requirements: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    requirements.hash(&mut hasher);
+    hasher.finish()
+}
+
+
+/// Parse the contents of a cache file
+fn parse_selection(contents: &str) -> Option<CachedSelection> {
+    let mut parts = contents.split(' ');
+    let (uuid_hex, hash_hex) = match (parts.next(), parts.next()) {
+        (Some(uuid), Some(hash)) if parts.next().is_none() => (uuid, hash),
+        _ => return None,
+    };
+    if uuid_hex.len() != 32 { return None; }
+    let mut uuid = [0; 16];
+    for (idx, byte) in uuid.iter_mut().enumerate() {
+        let digits = uuid_hex.get(2 * idx..2 * idx + 2)?;
+        *byte = u8::from_str_radix(digits, 16).ok()?;
+    }
+    Some(CachedSelection {
+        uuid,
+        requirements_hash: u64::from_str_radix(hash_hex, 16).ok()?,
+    })
+}
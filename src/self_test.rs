@@ -0,0 +1,333 @@
+//! Self-test of the Vulkan implementation
+//!
+//! When a Vulkan application misbehaves, it can be hard to tell whether the
+//! problem lies in the application or in the Vulkan implementation. The self
+//! test exercises the basic functionality which every application relies on
+//! (device creation, memory allocation, compute dispatches and transfers) in
+//! a way that is known to be correct, and reports how far it got.
+//!
+//! Call run_self_test() at startup, or when something goes wrong, and log the
+//! resulting report. If it fails, your driver is probably the culprit.
+
+use ::{
+    buffer::EasyBuffer,
+    compute::{
+        easy_compute_filter,
+        EasyCompute,
+        EasyComputeLayout,
+    },
+    instance::EasyInstance,
+    preference,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use vulkano::{
+    device::DeviceExtensions,
+    instance::Features,
+};
+
+
+/// Number of values which the self-test shader processes
+const SELF_TEST_VALUES: u32 = 1024;
+
+/// Work group size of the self-test shader
+const SELF_TEST_WORKGROUP_SIZE: u32 = 64;
+
+/// SPIR-V code of the self-test shader, which replaces each value x of a
+/// storage buffer at set 0, binding 0 by 2 * x + 1
+///
+/// This is the hand-assembled equivalent of the following GLSL, so that the
+/// self-test does not need a shader compiler:
+///
+/// ```glsl
+/// #version 450
+/// layout(local_size_x = 64) in;
+/// layout(set = 0, binding = 0) buffer Data { uint data[]; };
+/// void main() {
+///     uint i = gl_GlobalInvocationID.x;
+///     data[i] = data[i] * 2 + 1;
+/// }
+/// ```
+///
+const SELF_TEST_SPIRV: &[u32] = &[
+    // Header: magic, version 1.0, generator, bound, schema
+    0x0723_0203, 0x0001_0000, 0, 26, 0,
+    // OpCapability Shader
+    0x0002_0011, 1,
+    // OpMemoryModel Logical GLSL450
+    0x0003_000e, 0, 1,
+    // OpEntryPoint GLCompute %main "main" %gid
+    0x0006_000f, 5, 1, 0x6e69_616d, 0, 2,
+    // OpExecutionMode %main LocalSize 64 1 1
+    0x0006_0010, 1, 17, 64, 1, 1,
+    // OpDecorate %gid BuiltIn GlobalInvocationId
+    0x0004_0047, 2, 11, 28,
+    // OpDecorate %rtarr ArrayStride 4
+    0x0004_0047, 3, 6, 4,
+    // OpMemberDecorate %struct 0 Offset 0
+    0x0005_0048, 4, 0, 35, 0,
+    // OpDecorate %struct BufferBlock
+    0x0003_0047, 4, 3,
+    // OpDecorate %buf DescriptorSet 0
+    0x0004_0047, 5, 34, 0,
+    // OpDecorate %buf Binding 0
+    0x0004_0047, 5, 33, 0,
+    // %void = OpTypeVoid
+    0x0002_0013, 6,
+    // %fn = OpTypeFunction %void
+    0x0003_0021, 7, 6,
+    // %uint = OpTypeInt 32 0
+    0x0004_0015, 8, 32, 0,
+    // %v3uint = OpTypeVector %uint 3
+    0x0004_0017, 9, 8, 3,
+    // %ptr_in_v3 = OpTypePointer Input %v3uint
+    0x0004_0020, 10, 1, 9,
+    // %gid = OpVariable %ptr_in_v3 Input
+    0x0004_003b, 10, 2, 1,
+    // %ptr_in_uint = OpTypePointer Input %uint
+    0x0004_0020, 11, 1, 8,
+    // %rtarr = OpTypeRuntimeArray %uint
+    0x0003_001d, 3, 8,
+    // %struct = OpTypeStruct %rtarr
+    0x0003_001e, 4, 3,
+    // %ptr_u_struct = OpTypePointer Uniform %struct
+    0x0004_0020, 12, 2, 4,
+    // %buf = OpVariable %ptr_u_struct Uniform
+    0x0004_003b, 12, 5, 2,
+    // %ptr_u_uint = OpTypePointer Uniform %uint
+    0x0004_0020, 13, 2, 8,
+    // %int = OpTypeInt 32 1
+    0x0004_0015, 14, 32, 1,
+    // %int0 = OpConstant %int 0
+    0x0004_002b, 14, 15, 0,
+    // %uint0 = OpConstant %uint 0
+    0x0004_002b, 8, 16, 0,
+    // %uint1 = OpConstant %uint 1
+    0x0004_002b, 8, 17, 1,
+    // %uint2 = OpConstant %uint 2
+    0x0004_002b, 8, 18, 2,
+    // %main = OpFunction %void None %fn
+    0x0005_0036, 6, 1, 0, 7,
+    // %label = OpLabel
+    0x0002_00f8, 19,
+    // %gidx = OpAccessChain %ptr_in_uint %gid %uint0
+    0x0005_0041, 11, 20, 2, 16,
+    // %idx = OpLoad %uint %gidx
+    0x0004_003d, 8, 21, 20,
+    // %elem = OpAccessChain %ptr_u_uint %buf %int0 %idx
+    0x0006_0041, 13, 22, 5, 15, 21,
+    // %value = OpLoad %uint %elem
+    0x0004_003d, 8, 23, 22,
+    // %doubled = OpIMul %uint %value %uint2
+    0x0005_0084, 8, 24, 23, 18,
+    // %result = OpIAdd %uint %doubled %uint1
+    0x0005_0080, 8, 25, 24, 17,
+    // OpStore %elem %result
+    0x0003_003e, 22, 25,
+    // OpReturn
+    0x0001_00fd,
+    // OpFunctionEnd
+    0x0001_0038,
+];
+
+
+/// Stage of the self-test
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelfTestStage {
+    /// Selecting a physical device with compute support
+    SelectDevice,
+
+    /// Creating a logical device and its queue
+    CreateDevice,
+
+    /// Allocating and initializing a storage buffer
+    AllocateBuffer,
+
+    /// Building the compute pipeline
+    BuildPipeline,
+
+    /// Running the compute shader
+    Dispatch,
+
+    /// Reading back the results
+    ReadBack,
+
+    /// Checking the results
+    Verify,
+}
+
+impl fmt::Display for SelfTestStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            SelfTestStage::SelectDevice => "device selection",
+            SelfTestStage::CreateDevice => "device creation",
+            SelfTestStage::AllocateBuffer => "buffer allocation",
+            SelfTestStage::BuildPipeline => "compute pipeline creation",
+            SelfTestStage::Dispatch => "compute dispatch",
+            SelfTestStage::ReadBack => "buffer readback",
+            SelfTestStage::Verify => "result verification",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+
+/// Outcome of the self-test
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfTestReport {
+    /// Name of the physical device which was tested, if one was selected
+    pub device: Option<String>,
+
+    /// Stages which completed successfully, with their duration
+    pub completed: Vec<(SelfTestStage, Duration)>,
+
+    /// Stage which failed, if any, along with a description of the problem
+    pub failure: Option<(SelfTestStage, String)>,
+}
+
+impl SelfTestReport {
+    /// Truth that the self-test passed
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.device {
+            Some(ref device) => writeln!(f, "Self-test of device {}", device)?,
+            None => writeln!(f, "Self-test")?,
+        }
+        for &(stage, duration) in &self.completed {
+            writeln!(f, "- {}: ok ({:?})", stage, duration)?;
+        }
+        match self.failure {
+            Some((stage, ref problem)) =>
+                write!(f, "- {}: FAILED ({})", stage, problem),
+            None => write!(f, "Self-test passed"),
+        }
+    }
+}
+
+
+/// Run the self-test on a device picked by the instance
+///
+/// The device is selected as usual, so the instance's API level, software
+/// rendering mode and environment overrides apply, with a preference for
+/// discrete GPUs. Failures are recorded in the report instead of being
+/// returned as errors.
+///
+pub fn run_self_test(instance: &Arc<EasyInstance>) -> SelfTestReport {
+    let _trace = trace::span("self_test::run_self_test", String::new);
+    let mut report = SelfTestReport {
+        device: None,
+        completed: Vec::new(),
+        failure: None,
+    };
+    if let Err((stage, error)) = self_test(instance, &mut report) {
+        report.failure = Some((stage, error.to_string()));
+    }
+    if report.passed() {
+        info!("{}", report);
+    } else {
+        error!("{}", report);
+    }
+    report
+}
+
+
+/// Implementation of run_self_test(), which stops on the first failure
+fn self_test(
+    instance: &Arc<EasyInstance>,
+    report: &mut SelfTestReport
+) -> ::std::result::Result<(), (SelfTestStage, Error)> {
+    let features = Features::none();
+    let extensions = DeviceExtensions::none();
+
+    let physical_device = run_stage(report, SelfTestStage::SelectDevice, || {
+        instance.select_physical_device(
+            easy_compute_filter(&features, &extensions, |_| true),
+            preference::prefer_discrete_gpu
+        )?.ok_or_else(|| format_err!("No physical device supports compute")
+                            .into())
+    })?;
+    report.device = Some(physical_device.physical_device().name());
+
+    let device = run_stage(report, SelfTestStage::CreateDevice, || {
+        physical_device.setup_single_queue_device(
+            &features,
+            &extensions,
+            |family| family.supports_compute(),
+            preference::prefer_dedicated_compute
+        )?.ok_or_else(|| Error::NoSuitableQueueFamily {
+            device: physical_device.physical_device().name(),
+        })
+    })?;
+    let queue = device.queues()[0].clone();
+
+    let buffer = run_stage(report, SelfTestStage::AllocateBuffer, || {
+        let values = (0..SELF_TEST_VALUES).collect::<Vec<_>>();
+        EasyBuffer::storage(&queue, &values)
+    })?;
+
+    let compute = run_stage(report, SelfTestStage::BuildPipeline, || {
+        let spirv = SELF_TEST_SPIRV.iter()
+                                   .flat_map(|word| {
+                                       (0..4).map(move |byte| {
+                                           (word >> (8 * byte)) as u8
+                                       })
+                                   })
+                                   .collect::<Vec<_>>();
+        // Safe because the shader and layout are known to match
+        unsafe {
+            EasyCompute::new(device.device(),
+                             &queue,
+                             &spirv,
+                             EasyComputeLayout::storage_buffers(1))
+        }
+    })?;
+
+    run_stage(report, SelfTestStage::Dispatch, || {
+        let work_groups = SELF_TEST_VALUES / SELF_TEST_WORKGROUP_SIZE;
+        easy_compute_run!(compute, [work_groups, 1, 1], buffer)
+    })?;
+
+    let results = run_stage(report, SelfTestStage::ReadBack, || buffer.read())?;
+
+    run_stage(report, SelfTestStage::Verify, || {
+        let mismatch = results.iter().enumerate().find(|&(input, &output)| {
+            output != 2 * input as u32 + 1
+        });
+        match mismatch {
+            Some((input, output)) => Err(format_err!(
+                "Expected {} at index {}, got {}",
+                2 * input + 1, input, output
+            ).into()),
+            None if results.len() != SELF_TEST_VALUES as usize => {
+                Err(format_err!("Expected {} results, got {}",
+                                SELF_TEST_VALUES, results.len()).into())
+            },
+            None => Ok(()),
+        }
+    })
+}
+
+/// Run a stage of the self-test, recording its duration if it succeeds
+fn run_stage<T>(
+    report: &mut SelfTestReport,
+    stage: SelfTestStage,
+    body: impl FnOnce() -> Result<T>
+) -> ::std::result::Result<T, (SelfTestStage, Error)> {
+    let start = Instant::now();
+    let result = body().map_err(|error| (stage, error))?;
+    report.completed.push((stage, start.elapsed()));
+    Ok(result)
+}
@@ -0,0 +1,263 @@
+//! Conveniences for loading SPIR-V shaders, and reloading them on change
+//!
+//! Shaders are loaded from SPIR-V files with some basic sanity checks, which
+//! catch common mistakes like passing GLSL sources or truncated files before
+//! the Vulkan implementation gets a chance to crash on them.
+//!
+//! With the "hot-reload" feature, a ShaderWatcher can additionally be used to
+//! tell when shader files were modified, so that render loops can rebuild the
+//! affected pipelines without restarting the application.
+
+use ::{
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+#[cfg(feature = "hot-reload")]
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+#[cfg(feature = "hot-reload")]
+use notify::{
+    self,
+    DebouncedEvent,
+    RecommendedWatcher,
+    RecursiveMode,
+    Watcher,
+};
+
+use vulkano::{
+    device::Device,
+    pipeline::shader::ShaderModule,
+};
+
+
+/// Magic number at the start of every SPIR-V module
+pub const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// Check that some bytes look like a SPIR-V module
+///
+/// This only validates the module header (size, magic number and version),
+/// not the actual code, which is the job of the validation layers.
+///
+pub fn validate_spirv(bytes: &[u8]) -> Result<()> {
+    // SPIR-V is made of 32-bit words, with a 5-word header
+    if bytes.len() % 4 != 0 {
+        return Err(Error::InvalidArgument(
+            format!("SPIR-V size ({} bytes) is not a multiple of 4",
+                    bytes.len())
+        ));
+    }
+    if bytes.len() < 20 {
+        return Err(Error::InvalidArgument(
+            format!("SPIR-V is too small ({} bytes) to contain a header",
+                    bytes.len())
+        ));
+    }
+
+    // Vulkan expects the module in host byte order
+    let word = |idx: usize| -> u32 {
+        let mut bytes_array = [0; 4];
+        bytes_array.copy_from_slice(&bytes[4*idx..4*(idx+1)]);
+        u32::from_ne_bytes(bytes_array)
+    };
+    let magic = word(0);
+    if magic != SPIRV_MAGIC {
+        return Err(Error::InvalidArgument(
+            if magic.swap_bytes() == SPIRV_MAGIC {
+                "SPIR-V is not in host byte order".to_owned()
+            } else {
+                format!("Bad SPIR-V magic number 0x{:08x}", magic)
+            }
+        ));
+    }
+
+    // Only SPIR-V 1.x is supported by Vulkan
+    let version = word(1);
+    let (major, minor) = ((version >> 16) & 0xff, (version >> 8) & 0xff);
+    if major != 1 {
+        return Err(Error::InvalidArgument(
+            format!("Unsupported SPIR-V version {}.{}", major, minor)
+        ));
+    }
+    Ok(())
+}
+
+/// Split a SPIR-V module into words, after checking its header
+pub(crate) fn spirv_words(spirv: &[u8]) -> Result<Vec<u32>> {
+    validate_spirv(spirv)?;
+    Ok(spirv.chunks(4).map(|chunk| {
+        let mut bytes_array = [0; 4];
+        bytes_array.copy_from_slice(chunk);
+        u32::from_ne_bytes(bytes_array)
+    }).collect())
+}
+
+/// Decode a nul-terminated SPIR-V literal string
+pub(crate) fn spirv_string(words: &[u32]) -> String {
+    let bytes = words.iter()
+                     .flat_map(|word| word.to_le_bytes().to_vec())
+                     .take_while(|&byte| byte != 0)
+                     .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Split a SPIR-V module into (opcode, operands) instructions, after checking
+/// its header
+pub(crate) fn spirv_instructions(spirv: &[u8]) -> Result<Vec<(u32, Vec<u32>)>> {
+    let words = spirv_words(spirv)?;
+
+    let mut instructions = Vec::new();
+    let mut index = 5;
+    while index < words.len() {
+        let word_count = (words[index] >> 16) as usize;
+        let opcode = words[index] & 0xffff;
+        if word_count == 0 || index + word_count > words.len() {
+            return Err(Error::InvalidArgument(
+                format!("Malformed SPIR-V instruction at word {}", index)
+            ));
+        }
+        instructions.push((opcode, words[index+1..index+word_count].to_vec()));
+        index += word_count;
+    }
+    Ok(instructions)
+}
+
+/// Load a SPIR-V module from a file, checking its header
+pub fn load_spirv(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    let path = path.as_ref();
+    let _trace = trace::span("shader::load_spirv",
+                             || format!("path: {:?}", path));
+    let bytes = fs::read(path)?;
+    validate_spirv(&bytes).map_err(|e| {
+        Error::InvalidArgument(format!("{:?}: {}", path, e))
+    })?;
+    info!("Loaded SPIR-V module {:?} ({} bytes)", path, bytes.len());
+    Ok(bytes)
+}
+
+/// Load a SPIR-V file into a shader module
+///
+/// # Safety
+///
+/// The SPIR-V code must be valid, which vulkano cannot check beyond the
+/// header checks performed by validate_spirv().
+///
+pub unsafe fn load_shader_module(
+    device: &Arc<Device>,
+    path: impl AsRef<Path>
+) -> Result<Arc<ShaderModule>> {
+    let bytes = load_spirv(path)?;
+    Ok(ShaderModule::new(device.clone(), &bytes)?)
+}
+
+
+/// Notification that a watched shader file has changed
+#[cfg(feature = "hot-reload")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderEvent {
+    /// The shader file was created or modified, and should be reloaded
+    Changed(PathBuf),
+
+    /// The shader file was removed
+    Removed(PathBuf),
+}
+
+/// Watcher which tells when shader files are modified
+///
+/// Text editors often save files by writing a new file and renaming it over
+/// the old one, so the directories containing the shaders are watched rather
+/// than the shader files themselves.
+///
+#[cfg(feature = "hot-reload")]
+pub struct ShaderWatcher {
+    /// Underlying file system watcher
+    watcher: RecommendedWatcher,
+
+    /// Channel through which file system events are received
+    events: Receiver<DebouncedEvent>,
+
+    /// Shader files which we are interested in
+    shaders: HashSet<PathBuf>,
+
+    /// Directories which are being watched
+    directories: HashSet<PathBuf>,
+}
+
+#[cfg(feature = "hot-reload")]
+impl ShaderWatcher {
+    /// Start a shader watcher
+    ///
+    /// Bursts of file system events which occur within a certain delay of
+    /// each other are merged into a single event.
+    ///
+    pub fn new(delay: Duration) -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::watcher(sender, delay)?;
+        Ok(ShaderWatcher {
+            watcher,
+            events,
+            shaders: HashSet::new(),
+            directories: HashSet::new(),
+        })
+    }
+
+    /// Start watching a shader file
+    pub fn watch(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = fs::canonicalize(path)?;
+        let _trace = trace::span("ShaderWatcher::watch",
+                                 || format!("path: {:?}", path));
+        let directory = path.parent().map(Path::to_owned).ok_or_else(|| {
+            Error::InvalidArgument(format!("{:?} has no parent directory",
+                                           path))
+        })?;
+        if self.directories.insert(directory.clone()) {
+            self.watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+        }
+        self.shaders.insert(path);
+        Ok(())
+    }
+
+    /// Check which shader files changed since the last call, without blocking
+    ///
+    /// Each file is reported at most once per call.
+    ///
+    pub fn poll(&self) -> Vec<ShaderEvent> {
+        let mut result = Vec::new();
+        for event in self.events.try_iter() {
+            let shader_event = match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => {
+                    ShaderEvent::Changed(path)
+                },
+                DebouncedEvent::Remove(path) => ShaderEvent::Removed(path),
+                DebouncedEvent::Error(e, path) => {
+                    warn!("Error while watching shaders ({:?}): {}", path, e);
+                    continue;
+                },
+                _ => continue,
+            };
+            let is_watched = match &shader_event {
+                ShaderEvent::Changed(path)
+                | ShaderEvent::Removed(path) => self.shaders.contains(path),
+            };
+            if is_watched && !result.contains(&shader_event) {
+                info!("Shader event: {:?}", shader_event);
+                result.push(shader_event);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,87 @@
+//! Software implementations of Vulkan
+//!
+//! CPU-based Vulkan implementations such as Mesa's lavapipe (which calls
+//! itself llvmpipe) and Google's SwiftShader are slow, but they run anywhere
+//! and behave the same on every machine. This makes them a good fit for
+//! automated tests, which should not depend on whichever GPU (if any) the
+//! test machine happens to have.
+//!
+//! A SoftwareRendering mode can be set on the EasyInstance, or overridden via
+//! the VULKANOOB_SOFTWARE environment variable (see the config module), in
+//! order to prefer or require such an implementation during device selection.
+
+use std::cmp::Ordering;
+
+use vulkano::instance::{
+    PhysicalDevice,
+    PhysicalDeviceType,
+};
+
+
+/// Lowercase substrings of the names of known software implementations
+pub const SOFTWARE_DEVICE_NAMES: &[&str] = &[
+    "llvmpipe",
+    "lavapipe",
+    "swiftshader",
+];
+
+
+/// Truth that a physical device is a software implementation of Vulkan
+///
+/// Devices are recognized either by their type, or by their name for
+/// implementations which do not report themselves as CPUs.
+///
+pub fn is_software_device(device: PhysicalDevice) -> bool {
+    if device.ty() == PhysicalDeviceType::Cpu { return true; }
+    let name = device.name().to_lowercase();
+    SOFTWARE_DEVICE_NAMES.iter().any(|pattern| name.contains(pattern))
+}
+
+/// Prefer software implementations of Vulkan over hardware ones
+pub fn prefer_software_device(dev1: PhysicalDevice,
+                              dev2: PhysicalDevice) -> Ordering {
+    is_software_device(dev1).cmp(&is_software_device(dev2))
+}
+
+
+/// Treatment of software implementations during device selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoftwareRendering {
+    /// Software implementations are treated like any other device ("allow")
+    Allow,
+
+    /// Software implementations are preferred over hardware ones, regardless
+    /// of the device preference, which is then only used to break ties
+    /// ("prefer")
+    Prefer,
+
+    /// Only software implementations may be selected ("require")
+    Require,
+}
+
+impl Default for SoftwareRendering {
+    fn default() -> Self {
+        SoftwareRendering::Allow
+    }
+}
+
+impl SoftwareRendering {
+    /// Truth that a device may be selected in this mode
+    pub fn allows_device(self, device: PhysicalDevice) -> bool {
+        match self {
+            SoftwareRendering::Allow | SoftwareRendering::Prefer => true,
+            SoftwareRendering::Require => is_software_device(device),
+        }
+    }
+
+    /// Compare two devices according to this mode
+    pub fn compare(self,
+                   dev1: PhysicalDevice,
+                   dev2: PhysicalDevice) -> Ordering {
+        match self {
+            SoftwareRendering::Allow => Ordering::Equal,
+            SoftwareRendering::Prefer | SoftwareRendering::Require =>
+                prefer_software_device(dev1, dev2),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Conveniences for experimenting with sparse resources
+//!
+//! Sparse buffers are not backed by a single memory allocation, but by pages
+//! of memory which can be bound and unbound at any time, using a queue that
+//! supports sparse binding operations.
+//!
+//! Vulkano does not expose sparse binding in a safe way yet, so
+//! EasySparseBuffer performs the binding operations using the raw Vulkan API.
+//! It only covers sparse buffers, which are enough to get a feel of how sparse
+//! binding works (sparse images add a lot of format-specific complexity).
+//!
+//! Binding operations are synchronous: we wait for the queue to be idle after
+//! each of them. This is terribly slow, but very convenient when prototyping.
+
+use ::{
+    easy_device_filter,
+    error::check_result,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    iter,
+    ptr,
+    sync::Arc,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    buffer::{
+        sys::{
+            SparseLevel,
+            UnsafeBuffer,
+        },
+        BufferUsage,
+    },
+    device::{
+        Device,
+        DeviceExtensions,
+        Queue,
+    },
+    instance::{
+        Features,
+        PhysicalDevice,
+        QueueFamily,
+    },
+    memory::DeviceMemory,
+    sync::Sharing,
+    SynchronizedVulkanObject,
+    VulkanObject,
+};
+
+
+/// Device features needed by EasySparseBuffer
+///
+/// Make sure that you enable these features (e.g. by computing the union of
+/// your own features with these ones) when creating your device.
+///
+pub fn sparse_features() -> Features {
+    Features {
+        sparse_binding: true,
+        sparse_residency_buffer: true,
+        ..Features::none()
+    }
+}
+
+/// Helper for building device filters for sparse resource experiments
+///
+/// This works like easy_device_filter(), but additionally requires support
+/// for the sparse_features(), and the queue family criterion is fixed to
+/// requiring support for sparse binding operations.
+///
+pub fn easy_sparse_filter<'a>(
+    features: &'a Features,
+    extensions: &'a DeviceExtensions,
+    mut other_criteria: impl FnMut(PhysicalDevice) -> bool + 'a
+) -> impl FnMut(PhysicalDevice) -> bool + 'a {
+    let sparse_features = sparse_features();
+    easy_device_filter(features,
+                       extensions,
+                       |family| family.supports_sparse_binding(),
+                       move |device| {
+                           device.supported_features()
+                                 .superset_of(&sparse_features)
+                               && other_criteria(device)
+                       })
+}
+
+/// Find a queue family which supports sparse binding operations
+pub fn find_sparse_queue_family(device: PhysicalDevice) -> Option<QueueFamily> {
+    device.queue_families().find(|family| family.supports_sparse_binding())
+}
+
+
+/// A sparse buffer, whose memory pages are bound on demand
+///
+/// Since vulkano's buffer access traits are not implemented for raw buffers,
+/// you will need to use the raw Vulkan API (through buffer()) in order to use
+/// this buffer in commands.
+///
+pub struct EasySparseBuffer {
+    /// Raw sparse buffer
+    buffer: UnsafeBuffer,
+
+    /// Queue used for binding operations
+    queue: Arc<Queue>,
+
+    /// Size of a memory page, in bytes
+    page_size: usize,
+
+    /// Memory bound to each page of the buffer, if any
+    pages: Vec<Option<DeviceMemory>>,
+
+    /// Memory type used for page allocations
+    memory_type_id: u32,
+}
+
+impl EasySparseBuffer {
+    /// Create a sparse buffer with no memory bound to it
+    ///
+    /// The buffer will be at least "size" bytes large, its size being rounded
+    /// up to a multiple of the page size. The queue must support sparse
+    /// binding operations, see find_sparse_queue_family().
+    ///
+    pub fn new(queue: &Arc<Queue>,
+               size: usize,
+               usage: BufferUsage) -> Result<Self> {
+        let _trace = trace::span("EasySparseBuffer::new",
+                                 || format!("size: {}, usage: {:?}",
+                                            size, usage));
+        let device = queue.device();
+        if !queue.family().supports_sparse_binding() {
+            return Err(Error::InvalidArgument(
+                "Queue does not support sparse binding operations".to_owned()
+            ));
+        }
+
+        // Create the sparse buffer
+        let sparse_level = SparseLevel {
+            sparse: true,
+            sparse_residency: true,
+            sparse_aliased: false,
+        };
+        let (buffer, requirements) = unsafe {
+            UnsafeBuffer::new(device.clone(),
+                              size,
+                              usage,
+                              Sharing::Exclusive::<iter::Empty<u32>>,
+                              sparse_level)?
+        };
+
+        // Pick a memory type for the pages, favoring device-local memory
+        let physical_device = device.physical_device();
+        let memory_type_id = physical_device
+            .memory_types()
+            .filter(|ty| requirements.memory_type_bits & (1 << ty.id()) != 0)
+            .max_by_key(|ty| ty.is_device_local())
+            .map(|ty| ty.id())
+            .ok_or_else(|| Error::Vulkan(format_err!(
+                "No memory type is suitable for sparse buffer pages"
+            )))?;
+
+        // Sparse buffers use their memory alignment as a page size
+        let page_size = requirements.alignment;
+        let num_pages = (requirements.size + page_size - 1) / page_size;
+        Ok(EasySparseBuffer {
+            buffer,
+            queue: queue.clone(),
+            page_size,
+            pages: (0..num_pages).map(|_| None).collect(),
+            memory_type_id,
+        })
+    }
+
+    /// Access the raw sparse buffer
+    pub fn buffer(&self) -> &UnsafeBuffer {
+        &self.buffer
+    }
+
+    /// Size of a memory page, in bytes
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Number of memory pages in the buffer
+    pub fn num_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Truth that a page has memory bound to it
+    pub fn is_bound(&self, page: usize) -> bool {
+        self.pages.get(page).map_or(false, |memory| memory.is_some())
+    }
+
+    /// Allocate memory for a page and bind it, if not done already
+    pub fn bind_page(&mut self, page: usize) -> Result<()> {
+        let _trace = trace::span("EasySparseBuffer::bind_page",
+                                 || format!("page: {}", page));
+        self.check_page(page)?;
+        if self.is_bound(page) { return Ok(()); }
+        let device = self.queue.device().clone();
+        let memory_type = device.physical_device()
+                                .memory_type_by_id(self.memory_type_id)
+                                .expect("Memory type should exist");
+        let memory = DeviceMemory::alloc(device,
+                                         memory_type,
+                                         self.page_size)?;
+        self.submit_bind(page, memory.internal_object())?;
+        self.pages[page] = Some(memory);
+        Ok(())
+    }
+
+    /// Unbind a page and liberate its memory, if it was bound
+    pub fn unbind_page(&mut self, page: usize) -> Result<()> {
+        let _trace = trace::span("EasySparseBuffer::unbind_page",
+                                 || format!("page: {}", page));
+        self.check_page(page)?;
+        if !self.is_bound(page) { return Ok(()); }
+        self.submit_bind(page, 0)?;
+        self.pages[page] = None;
+        Ok(())
+    }
+
+    /// Check that a page index is valid
+    fn check_page(&self, page: usize) -> Result<()> {
+        if page >= self.pages.len() {
+            return Err(Error::InvalidArgument(
+                format!("Page {} is out of range (buffer has {} pages)",
+                        page, self.pages.len())
+            ));
+        }
+        Ok(())
+    }
+
+    /// Bind some memory to a page (or unbind it if the memory handle is
+    /// null), and wait for the binding operation to complete
+    fn submit_bind(&self, page: usize, memory: vk::DeviceMemory) -> Result<()> {
+        let memory_bind = vk::SparseMemoryBind {
+            resourceOffset: (page * self.page_size) as vk::DeviceSize,
+            size: self.page_size as vk::DeviceSize,
+            memory,
+            memoryOffset: 0,
+            flags: 0,
+        };
+        let buffer_bind = vk::SparseBufferMemoryBindInfo {
+            buffer: self.buffer.internal_object(),
+            bindCount: 1,
+            pBinds: &memory_bind,
+        };
+        let bind_info = vk::BindSparseInfo {
+            sType: vk::STRUCTURE_TYPE_BIND_SPARSE_INFO,
+            pNext: ptr::null(),
+            waitSemaphoreCount: 0,
+            pWaitSemaphores: ptr::null(),
+            bufferBindCount: 1,
+            pBufferBinds: &buffer_bind,
+            imageOpaqueBindCount: 0,
+            pImageOpaqueBinds: ptr::null(),
+            imageBindCount: 0,
+            pImageBinds: ptr::null(),
+            signalSemaphoreCount: 0,
+            pSignalSemaphores: ptr::null(),
+        };
+        let fns = self.queue.device().pointers();
+        unsafe {
+            let queue = self.queue.internal_object_guard();
+            check_result(fns.QueueBindSparse(*queue, 1, &bind_info, 0),
+                         "vkQueueBindSparse")?;
+            check_result(fns.QueueWaitIdle(*queue), "vkQueueWaitIdle")
+        }
+    }
+}
+
+impl Drop for EasySparseBuffer {
+    /// Make sure that the device is done with the pages before freeing them
+    fn drop(&mut self) {
+        let device: &Arc<Device> = self.queue.device();
+        let fns = device.pointers();
+        unsafe {
+            let queue = self.queue.internal_object_guard();
+            if let Err(e) = check_result(fns.QueueWaitIdle(*queue),
+                                         "vkQueueWaitIdle") {
+                warn!("Failed to wait for sparse binding queue: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Specialization constants, declared by id and checked against the shader
+//!
+//! Specialization constants let a shader be tuned at pipeline creation time
+//! without recompiling it, which is how compute prototypes usually pick their
+//! work group size (see GLSL's local_size_x_id). Vulkano describes them with
+//! a struct implementing SpecializationConstants, which is tedious to write
+//! by hand. The easy_specialization_constants! macro generates that struct
+//! from a list of constant ids and typed values, and
+//! check_specialization_constants() checks it against the constants that a
+//! SPIR-V module actually declares, which vulkano does not do for SPIR-V
+//! that it did not compile itself.
+
+use ::{
+    shader,
+    Error,
+    Result,
+};
+
+use std::{
+    collections::BTreeMap,
+    mem,
+};
+
+pub use vulkano::pipeline::shader::{
+    SpecializationConstants,
+    SpecializationMapEntry,
+};
+
+
+/// SPIR-V opcodes and decorations which are relevant to specialization
+mod spirv {
+    pub const OP_SPEC_CONSTANT_TRUE: u32 = 48;
+    pub const OP_SPEC_CONSTANT_FALSE: u32 = 49;
+    pub const OP_SPEC_CONSTANT: u32 = 50;
+    pub const OP_DECORATE: u32 = 71;
+    pub const DECORATION_SPEC_ID: u32 = 1;
+}
+
+
+/// Declare a set of specialization constants, by constant id
+///
+/// This generates a #[repr(C)] struct with one public member per constant,
+/// which implements vulkano's SpecializationConstants trait and can thus be
+/// passed to pipeline constructors. Constants must be scalars (u32, i32, f32
+/// or f64). Boolean constants are 32-bit in Vulkan, so use u32 for them.
+///
+/// ```ignore
+/// easy_specialization_constants! {
+///     pub struct WorkGroupSize {
+///         0 => x: u32,
+///         1 => y: u32,
+///     }
+/// }
+/// let constants = WorkGroupSize { x: 16, y: 16 };
+/// ```
+///
+#[macro_export]
+macro_rules! easy_specialization_constants {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            $($id:expr => $member:ident: $ty:ty),+ $(,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug)]
+        #[repr(C)]
+        pub struct $name {
+            $(pub $member: $ty),+
+        }
+
+        unsafe impl $crate::specialization::SpecializationConstants for $name {
+            fn descriptors()
+                -> &'static [$crate::specialization::SpecializationMapEntry]
+            {
+                static INIT: ::std::sync::Once = ::std::sync::Once::new();
+                static mut DESCRIPTORS:
+                    &'static [$crate::specialization::SpecializationMapEntry]
+                = &[];
+                INIT.call_once(|| {
+                    // Measure member offsets on a dummy value, like vulkano's
+                    // impl_vertex! does. Specialization constants are scalars,
+                    // for which all-zeroes is a valid bit pattern.
+                    let dummy: $name = unsafe { ::std::mem::zeroed() };
+                    let base = &dummy as *const $name as usize;
+                    let descriptors = vec![$(
+                        $crate::specialization::SpecializationMapEntry {
+                            constant_id: $id,
+                            offset: (&dummy.$member as *const $ty as usize
+                                     - base) as u32,
+                            size: ::std::mem::size_of::<$ty>(),
+                        }
+                    ),+];
+                    // Safe because this is only done once, before any read
+                    unsafe {
+                        DESCRIPTORS =
+                            Box::leak(descriptors.into_boxed_slice());
+                    }
+                });
+                // Safe because call_once() has initialized DESCRIPTORS, and
+                // synchronizes with its initialization
+                unsafe { DESCRIPTORS }
+            }
+        }
+    };
+}
+
+
+/// List the specialization constants which a SPIR-V module declares
+///
+/// The result maps each constant id to the size of the constant in bytes, as
+/// it should be provided at pipeline creation time.
+///
+pub fn declared_specialization_constants(
+    spirv: &[u8]
+) -> Result<BTreeMap<u32, usize>> {
+    // Collect the constant ids and the size of the constants, by result id
+    let mut spec_ids = BTreeMap::new();
+    let mut sizes = BTreeMap::new();
+    for (opcode, operands) in shader::spirv_instructions(spirv)? {
+        match opcode {
+            spirv::OP_DECORATE if operands.len() >= 3 => {
+                if operands[1] == spirv::DECORATION_SPEC_ID {
+                    spec_ids.insert(operands[0], operands[2]);
+                }
+            },
+            spirv::OP_SPEC_CONSTANT_TRUE
+            | spirv::OP_SPEC_CONSTANT_FALSE if operands.len() >= 2 => {
+                sizes.insert(operands[1], 4);
+            },
+            spirv::OP_SPEC_CONSTANT if operands.len() >= 3 => {
+                sizes.insert(operands[1], 4 * (operands.len() - 2));
+            },
+            _ => {},
+        }
+    }
+
+    // Match them
+    Ok(spec_ids.into_iter().filter_map(|(result_id, constant_id)| {
+        sizes.get(&result_id).map(|&size| (constant_id, size))
+    }).collect())
+}
+
+/// Check some specialization constants against those declared by a shader
+///
+/// Every constant must be declared by the shader with the same size, as a
+/// constant which the shader does not know about is most likely a typo in the
+/// constant id. Constants which the shader declares but which are not
+/// specialized keep their default value, as usual.
+///
+pub fn check_specialization_constants<S: SpecializationConstants>(
+    spirv: &[u8]
+) -> Result<()> {
+    let declared = declared_specialization_constants(spirv)?;
+    let mut seen = Vec::new();
+    for entry in S::descriptors() {
+        if seen.contains(&entry.constant_id) {
+            return Err(Error::InvalidArgument(format!(
+                "Specialization constant {} is set more than once",
+                entry.constant_id
+            )));
+        }
+        seen.push(entry.constant_id);
+
+        if entry.offset as usize + entry.size > mem::size_of::<S>() {
+            return Err(Error::InvalidArgument(format!(
+                "Specialization constant {} lies outside of its struct",
+                entry.constant_id
+            )));
+        }
+        match declared.get(&entry.constant_id) {
+            None => return Err(Error::InvalidArgument(format!(
+                "The shader does not declare specialization constant {} (it \
+                 declares {:?})", entry.constant_id, declared.keys()
+            ))),
+            Some(&size) if size != entry.size => {
+                return Err(Error::InvalidArgument(format!(
+                    "Specialization constant {} is {} bytes large, but {} \
+                     bytes were provided", entry.constant_id, size, entry.size
+                )));
+            },
+            Some(_) => {},
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! Streaming of per-frame data to the GPU through a staging belt
+//!
+//! Uniforms and dynamic vertex data change every frame, and allocating new
+//! host-visible buffers for them every frame is wasteful. EasyStagingBelt
+//! instead maintains a set of host-visible buffers ("chunks"), hands out
+//! slices of them for uploads, and recycles the chunks used by a frame once
+//! the fence associated with that frame has signaled.
+
+use ::{
+    trace,
+    Result,
+};
+
+use std::{
+    mem,
+    slice,
+    sync::Arc,
+    time::Duration,
+};
+
+use vulkano::{
+    buffer::{
+        BufferSlice,
+        BufferUsage,
+        CpuAccessibleBuffer,
+        TypedBufferAccess,
+    },
+    device::Device,
+    sync::{
+        FenceSignalFuture,
+        GpuFuture,
+    },
+};
+
+
+/// Buffer type backing the staging belt
+pub type StagingBuffer = Arc<CpuAccessibleBuffer<[u8]>>;
+
+/// Slice of the staging belt, as handed out by EasyStagingBelt::upload()
+pub type StagingSlice<T> = BufferSlice<[T], StagingBuffer>;
+
+/// Alignment of the slices handed out by the staging belt
+///
+/// This is the largest alignment that Vulkan implementations may require for
+/// uniform and storage buffer offsets, so that slices can be bound directly.
+///
+const SLICE_ALIGNMENT: usize = 256;
+
+
+/// A staging buffer, along with how much of it is in use
+struct Chunk {
+    /// The staging buffer
+    buffer: StagingBuffer,
+
+    /// Number of bytes which were handed out
+    used: usize,
+}
+
+impl Chunk {
+    /// Number of bytes which can still be handed out, accounting for alignment
+    fn available(&self) -> usize {
+        self.buffer.len().saturating_sub(align(self.used))
+    }
+}
+
+/// Chunks used by a frame, waiting for the frame's fence to signal
+struct InFlightFrame {
+    /// Chunks which were used by the frame
+    chunks: Vec<Chunk>,
+
+    /// Truth that the frame's fence has signaled
+    is_done: Box<dyn Fn() -> bool + Send + Sync>,
+}
+
+
+/// Ring of host-visible buffers for streaming uploads
+pub struct EasyStagingBelt {
+    /// Device which the staging buffers are allocated on
+    device: Arc<Device>,
+
+    /// Default size of the staging buffers
+    chunk_size: usize,
+
+    /// Chunks which are used by the current frame
+    active: Vec<Chunk>,
+
+    /// Chunks which are used by frames that the GPU may still be processing
+    in_flight: Vec<InFlightFrame>,
+
+    /// Chunks which can be reused
+    free: Vec<Chunk>,
+}
+
+impl EasyStagingBelt {
+    /// Set up a staging belt, allocating chunks of a certain size
+    ///
+    /// Uploads larger than the chunk size get a dedicated chunk, so this is
+    /// not a hard limit, but it should exceed the size of typical uploads.
+    ///
+    pub fn new(device: &Arc<Device>, chunk_size: usize) -> Self {
+        EasyStagingBelt {
+            device: device.clone(),
+            chunk_size,
+            active: Vec::new(),
+            in_flight: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Copy some data into the staging belt, returning the matching slice
+    ///
+    /// The slice can be used as a uniform, vertex or storage buffer, or as the
+    /// source of a copy, by the GPU work of the current frame.
+    ///
+    pub fn upload<T>(&mut self, data: &[T]) -> Result<StagingSlice<T>>
+        where T: Copy + Send + Sync + 'static
+    {
+        let bytes = data.len() * mem::size_of::<T>();
+        let _trace = trace::span("EasyStagingBelt::upload",
+                                 || format!("bytes: {}", bytes));
+
+        // Find a chunk with enough room, or allocate one
+        let chunk_idx = match self.active.iter()
+                                         .position(|c| c.available() >= bytes)
+        {
+            Some(idx) => idx,
+            None => {
+                let chunk = self.take_free_chunk(bytes)?;
+                self.active.push(chunk);
+                self.active.len() - 1
+            },
+        };
+        let chunk = &mut self.active[chunk_idx];
+        let offset = align(chunk.used);
+
+        // Copy the data into the chunk
+        {
+            let mut contents = chunk.buffer.write().map_err(|e| {
+                format_err!("Staging chunk is unexpectedly locked: {}", e)
+            })?;
+            // Safe because T is Copy, and thus plain old data
+            let data_bytes = unsafe {
+                slice::from_raw_parts(data.as_ptr() as *const u8, bytes)
+            };
+            contents[offset..offset+bytes].copy_from_slice(data_bytes);
+        }
+        chunk.used = offset + bytes;
+
+        // Hand out the matching slice
+        let slice = BufferSlice::from_typed_buffer_access(chunk.buffer.clone())
+                                .slice(offset..offset+bytes)
+                                .expect("Range was checked above");
+        // Safe because the slice contains a valid [T] at a proper alignment
+        Ok(unsafe { slice.reinterpret::<[T]>() })
+    }
+
+    /// Mark the end of a frame, whose GPU work ends when a fence signals
+    ///
+    /// The chunks used by the frame will be recycled once that happens.
+    ///
+    pub fn finish_frame<F>(&mut self, fence: Arc<FenceSignalFuture<F>>)
+        where F: GpuFuture + Send + Sync + 'static
+    {
+        let chunks = mem::replace(&mut self.active, Vec::new());
+        debug!("Staging belt frame finished with {} chunk(s) in use, {} \
+                frame(s) in flight, {} free chunk(s)",
+               chunks.len(), self.in_flight.len(), self.free.len());
+        self.in_flight.push(InFlightFrame {
+            chunks,
+            is_done: Box::new(move || {
+                fence.wait(Some(Duration::from_secs(0))).is_ok()
+            }),
+        });
+    }
+
+    /// Recycle the chunks of finished frames, then pick a free chunk which
+    /// can accommodate an upload, or allocate a new one
+    fn take_free_chunk(&mut self, bytes: usize) -> Result<Chunk> {
+        // Recycle chunks from the frames which the GPU is done with
+        let (done, pending): (Vec<_>, Vec<_>) =
+            self.in_flight.drain(..).partition(|frame| (frame.is_done)());
+        self.in_flight = pending;
+        for frame in done {
+            for mut chunk in frame.chunks {
+                chunk.used = 0;
+                self.free.push(chunk);
+            }
+        }
+
+        // Reuse a free chunk if possible. Vulkano may still consider a chunk
+        // to be in use by the GPU until the frame's future is cleaned up, in
+        // which case we cannot write to it yet.
+        let reusable = self.free.iter().position(|chunk| {
+            chunk.buffer.len() >= bytes && chunk.buffer.write().is_ok()
+        });
+        if let Some(idx) = reusable {
+            return Ok(self.free.swap_remove(idx));
+        }
+
+        // Otherwise, allocate a new chunk
+        let size = self.chunk_size.max(bytes);
+        info!("Allocating a {}-byte staging belt chunk", size);
+        let buffer = unsafe {
+            CpuAccessibleBuffer::uninitialized_array(self.device.clone(),
+                                                     size,
+                                                     BufferUsage::all())?
+        };
+        Ok(Chunk { buffer, used: 0 })
+    }
+}
+
+
+/// Round an offset up to the slice alignment
+fn align(offset: usize) -> usize {
+    (offset + SLICE_ALIGNMENT - 1) / SLICE_ALIGNMENT * SLICE_ALIGNMENT
+}
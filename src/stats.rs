@@ -0,0 +1,206 @@
+//! Frame timing statistics
+//!
+//! FrameStats is fed with frame boundaries, either by EasyRenderLoop or by
+//! you, and computes rolling statistics over the last frames: frame rate,
+//! frame time percentiles, and how much of a frame is spent on the CPU and on
+//! the GPU (the latter requires GPU timings, e.g. from profiling::EasyTimer).
+
+use ::profiling::FrameTimings;
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+
+/// Default number of frames which statistics are computed over
+pub const DEFAULT_WINDOW: usize = 120;
+
+
+/// Statistics over the last frames
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameStatistics {
+    /// Number of frames which the statistics are computed over
+    pub frames: usize,
+
+    /// Average number of frames per second
+    pub fps: f64,
+
+    /// Median frame time
+    pub median: Duration,
+
+    /// 95th percentile of the frame time
+    pub p95: Duration,
+
+    /// 99th percentile of the frame time
+    pub p99: Duration,
+
+    /// Average CPU time per frame, from begin_frame() to end_frame()
+    pub cpu_time: Duration,
+
+    /// Average GPU time per frame, if GPU timings were provided
+    pub gpu_time: Option<Duration>,
+}
+
+impl fmt::Display for FrameStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1} FPS over {} frames, frame time median {:?} / p95 \
+                   {:?} / p99 {:?}, CPU {:?}",
+               self.fps, self.frames, self.median, self.p95, self.p99,
+               self.cpu_time)?;
+        if let Some(gpu_time) = self.gpu_time {
+            write!(f, ", GPU {:?}", gpu_time)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Collector of frame timing statistics
+///
+/// Call begin_frame() when you start working on a frame and end_frame() when
+/// you are done submitting it. Frame times are measured between consecutive
+/// calls to begin_frame(), and CPU times from begin_frame() to end_frame().
+/// GPU times are provided separately via record_gpu_timings().
+///
+pub struct FrameStats {
+    /// Number of frames which statistics are computed over
+    window: usize,
+
+    /// Start time of the current frame, if any
+    frame_start: Option<Instant>,
+
+    /// Start time of the previous frame, if any
+    last_frame_start: Option<Instant>,
+
+    /// Recent frame times
+    frame_times: VecDeque<Duration>,
+
+    /// Recent CPU times
+    cpu_times: VecDeque<Duration>,
+
+    /// Recent GPU times
+    gpu_times: VecDeque<Duration>,
+
+    /// Period at which statistics are logged, if enabled
+    log_period: Option<Duration>,
+
+    /// Last time statistics were logged
+    last_log: Instant,
+}
+
+impl FrameStats {
+    /// Compute statistics over the last DEFAULT_WINDOW frames
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    /// Compute statistics over the last "window" frames (at least one)
+    pub fn with_window(window: usize) -> Self {
+        let window = window.max(1);
+        FrameStats {
+            window,
+            frame_start: None,
+            last_frame_start: None,
+            frame_times: VecDeque::with_capacity(window),
+            cpu_times: VecDeque::with_capacity(window),
+            gpu_times: VecDeque::with_capacity(window),
+            log_period: None,
+            last_log: Instant::now(),
+        }
+    }
+
+    /// Log the statistics at the INFO level periodically, or stop doing so
+    pub fn set_log_period(&mut self, period: Option<Duration>) {
+        self.log_period = period;
+        self.last_log = Instant::now();
+    }
+
+    /// Mark the beginning of a frame
+    pub fn begin_frame(&mut self) {
+        let now = Instant::now();
+        if let Some(last_start) = self.last_frame_start {
+            push_sample(&mut self.frame_times, self.window, now - last_start);
+        }
+        self.last_frame_start = Some(now);
+        self.frame_start = Some(now);
+    }
+
+    /// Mark the end of a frame's CPU work, logging statistics if it's time
+    pub fn end_frame(&mut self) {
+        if let Some(start) = self.frame_start.take() {
+            push_sample(&mut self.cpu_times, self.window, start.elapsed());
+        }
+        if let Some(period) = self.log_period {
+            if self.last_log.elapsed() >= period {
+                if let Some(stats) = self.statistics() {
+                    info!("{}", stats);
+                }
+                self.last_log = Instant::now();
+            }
+        }
+    }
+
+    /// Record the GPU timings of a frame
+    ///
+    /// The GPU time of the frame is the sum of the durations of its top-level
+    /// profiling scopes.
+    ///
+    pub fn record_gpu_timings(&mut self, timings: &FrameTimings) {
+        let gpu_time = timings.scopes
+                              .iter()
+                              .filter(|scope| scope.depth == 0)
+                              .map(|scope| scope.duration)
+                              .fold(Duration::from_secs(0), |a, b| a + b);
+        push_sample(&mut self.gpu_times, self.window, gpu_time);
+    }
+
+    /// Statistics over the last frames, if at least one frame was timed
+    pub fn statistics(&self) -> Option<FrameStatistics> {
+        if self.frame_times.is_empty() { return None; }
+        let mut sorted = self.frame_times.iter().cloned().collect::<Vec<_>>();
+        sorted.sort();
+        let percentile = |p: usize| sorted[(sorted.len() - 1) * p / 100];
+        let total = sorted.iter().fold(Duration::from_secs(0), |a, &b| a + b);
+        Some(FrameStatistics {
+            frames: sorted.len(),
+            fps: sorted.len() as f64 / as_secs_f64(total).max(1e-9),
+            median: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            cpu_time: average(&self.cpu_times).unwrap_or_default(),
+            gpu_time: average(&self.gpu_times),
+        })
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Record a sample, forgetting about the oldest one if the window is full
+fn push_sample(samples: &mut VecDeque<Duration>,
+               window: usize,
+               sample: Duration) {
+    if samples.len() == window { samples.pop_front(); }
+    samples.push_back(sample);
+}
+
+/// Average of some durations, if there are any
+fn average(samples: &VecDeque<Duration>) -> Option<Duration> {
+    if samples.is_empty() { return None; }
+    let total = samples.iter().fold(Duration::from_secs(0), |a, &b| a + b);
+    Some(total / samples.len() as u32)
+}
+
+/// Convert a duration to floating-point seconds
+fn as_secs_f64(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
@@ -0,0 +1,346 @@
+//! Subgroup properties of physical devices
+//!
+//! Subgroups are the groups of shader invocations which a GPU executes in
+//! lockstep (warps, wavefronts...), and subgroup operations let these
+//! invocations communicate without going through shared memory. Which
+//! operations are available, in which shader stages, and how large subgroups
+//! are is described by Vulkan 1.1's VkPhysicalDeviceSubgroupProperties.
+//!
+//! Vulkano does not expose these properties, so we query them ourselves
+//! (see the properties2 module). They are only known for devices which
+//! support Vulkan 1.1. The range of subgroup sizes which compute shaders may
+//! use is also queried, on devices which support Vulkan 1.3 or
+//! VK_EXT_subgroup_size_control.
+
+use ::{
+    api_level::{
+        VULKAN_1_1,
+        VULKAN_1_3,
+    },
+    properties2,
+};
+
+use std::{
+    fmt,
+    os::raw::c_void,
+    ptr,
+};
+
+use vk_sys as vk;
+
+use vulkano::{
+    descriptor::descriptor::ShaderStages,
+    instance::{
+        PhysicalDevice,
+        RawDeviceExtensions,
+    },
+};
+
+
+/// Name of the VK_EXT_subgroup_size_control extension
+pub const SUBGROUP_SIZE_CONTROL_EXTENSION: &str =
+    "VK_EXT_subgroup_size_control";
+
+/// Structure type of VkPhysicalDeviceSubgroupProperties
+const STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_PROPERTIES: u32 = 1_000_094_000;
+
+/// Structure type of VkPhysicalDeviceSubgroupSizeControlProperties
+const STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_SIZE_CONTROL_PROPERTIES: u32 =
+    1_000_225_000;
+
+
+/// Subgroup operations (VkSubgroupFeatureFlagBits)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SubgroupOperations {
+    /// Subgroup election and barriers (GL_KHR_shader_subgroup_basic)
+    pub basic: bool,
+
+    /// Votes (GL_KHR_shader_subgroup_vote)
+    pub vote: bool,
+
+    /// Reductions and scans (GL_KHR_shader_subgroup_arithmetic)
+    pub arithmetic: bool,
+
+    /// Broadcasts and ballots (GL_KHR_shader_subgroup_ballot)
+    pub ballot: bool,
+
+    /// Shuffles (GL_KHR_shader_subgroup_shuffle)
+    pub shuffle: bool,
+
+    /// Relative shuffles (GL_KHR_shader_subgroup_shuffle_relative)
+    pub shuffle_relative: bool,
+
+    /// Clustered reductions (GL_KHR_shader_subgroup_clustered)
+    pub clustered: bool,
+
+    /// Quad operations (GL_KHR_shader_subgroup_quad)
+    pub quad: bool,
+}
+
+impl SubgroupOperations {
+    /// No subgroup operation
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Decode Vulkan's VkSubgroupFeatureFlags
+    fn from_bits(bits: u32) -> Self {
+        SubgroupOperations {
+            basic: bits & 0x01 != 0,
+            vote: bits & 0x02 != 0,
+            arithmetic: bits & 0x04 != 0,
+            ballot: bits & 0x08 != 0,
+            shuffle: bits & 0x10 != 0,
+            shuffle_relative: bits & 0x20 != 0,
+            clustered: bits & 0x40 != 0,
+            quad: bits & 0x80 != 0,
+        }
+    }
+
+    /// Truth that all operations of another set are also in this one
+    pub fn contains(&self, other: &SubgroupOperations) -> bool {
+        self.names().len() == self.union(other).names().len()
+    }
+
+    /// Operations which are in either this set or another one
+    pub fn union(&self, other: &SubgroupOperations) -> Self {
+        SubgroupOperations {
+            basic: self.basic || other.basic,
+            vote: self.vote || other.vote,
+            arithmetic: self.arithmetic || other.arithmetic,
+            ballot: self.ballot || other.ballot,
+            shuffle: self.shuffle || other.shuffle,
+            shuffle_relative: self.shuffle_relative || other.shuffle_relative,
+            clustered: self.clustered || other.clustered,
+            quad: self.quad || other.quad,
+        }
+    }
+
+    /// Names of the operations in this set
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.basic { names.push("basic"); }
+        if self.vote { names.push("vote"); }
+        if self.arithmetic { names.push("arithmetic"); }
+        if self.ballot { names.push("ballot"); }
+        if self.shuffle { names.push("shuffle"); }
+        if self.shuffle_relative { names.push("shuffle_relative"); }
+        if self.clustered { names.push("clustered"); }
+        if self.quad { names.push("quad"); }
+        names
+    }
+}
+
+
+/// Subgroup properties of a physical device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubgroupProperties {
+    /// Default number of invocations per subgroup
+    pub size: u32,
+
+    /// Shader stages where subgroup operations are supported
+    pub supported_stages: ShaderStages,
+
+    /// Subgroup operations which are supported
+    pub supported_operations: SubgroupOperations,
+
+    /// Truth that quad operations are supported in all supported stages, as
+    /// opposed to only fragment and compute shaders
+    pub quad_operations_in_all_stages: bool,
+
+    /// Range of subgroup sizes, if the device supports subgroup size control
+    pub size_control: Option<SubgroupSizeControl>,
+}
+
+impl SubgroupProperties {
+    /// Query the subgroup properties of a physical device, if possible
+    ///
+    /// This returns None if the device does not support Vulkan 1.1, or if the
+    /// instance was created without VK_KHR_get_physical_device_properties2.
+    ///
+    pub fn of_device(device: PhysicalDevice) -> Option<Self> {
+        if device.api_version() < VULKAN_1_1 { return None; }
+
+        let has_size_control = supports_size_control(device);
+        let mut size_control = RawSubgroupSizeControlProperties {
+            s_type:
+                STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_SIZE_CONTROL_PROPERTIES,
+            p_next: ptr::null_mut(),
+            min_subgroup_size: 0,
+            max_subgroup_size: 0,
+            max_compute_workgroup_subgroups: 0,
+            required_subgroup_size_stages: 0,
+        };
+        let mut subgroup = RawSubgroupProperties {
+            s_type: STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_PROPERTIES,
+            p_next: if has_size_control {
+                &mut size_control as *mut RawSubgroupSizeControlProperties
+                                  as *mut c_void
+            } else {
+                ptr::null_mut()
+            },
+            subgroup_size: 0,
+            supported_stages: 0,
+            supported_operations: 0,
+            quad_operations_in_all_stages: 0,
+        };
+        let queried = unsafe {
+            properties2::query_properties(
+                device,
+                &mut subgroup as *mut RawSubgroupProperties as *mut c_void
+            )
+        };
+        if !queried { return None; }
+
+        let stages = subgroup.supported_stages;
+        Some(SubgroupProperties {
+            size: subgroup.subgroup_size,
+            supported_stages: ShaderStages {
+                vertex: stages & vk::SHADER_STAGE_VERTEX_BIT != 0,
+                tessellation_control:
+                    stages & vk::SHADER_STAGE_TESSELLATION_CONTROL_BIT != 0,
+                tessellation_evaluation:
+                    stages & vk::SHADER_STAGE_TESSELLATION_EVALUATION_BIT != 0,
+                geometry: stages & vk::SHADER_STAGE_GEOMETRY_BIT != 0,
+                fragment: stages & vk::SHADER_STAGE_FRAGMENT_BIT != 0,
+                compute: stages & vk::SHADER_STAGE_COMPUTE_BIT != 0,
+            },
+            supported_operations:
+                SubgroupOperations::from_bits(subgroup.supported_operations),
+            quad_operations_in_all_stages:
+                subgroup.quad_operations_in_all_stages != 0,
+            size_control: if has_size_control {
+                Some(SubgroupSizeControl {
+                    min_size: size_control.min_subgroup_size,
+                    max_size: size_control.max_subgroup_size,
+                    max_compute_workgroup_subgroups:
+                        size_control.max_compute_workgroup_subgroups,
+                    required_size_in_compute:
+                        size_control.required_subgroup_size_stages
+                            & vk::SHADER_STAGE_COMPUTE_BIT != 0,
+                })
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Largest number of invocations that a subgroup of a compute shader may
+    /// have on this device
+    ///
+    /// With subgroup size control, the subgroup size of a compute shader may
+    /// be anywhere in a range, and this is the top of the range. Since
+    /// subgroup sizes are powers of two, work groups whose size is a multiple
+    /// of this one are made of full subgroups whatever size is picked.
+    ///
+    pub fn max_compute_size(&self) -> u32 {
+        self.size_control.map_or(self.size, |control| control.max_size)
+    }
+
+    /// Names of the shader stages where subgroup operations are supported
+    pub fn stage_names(&self) -> Vec<&'static str> {
+        let stages = &self.supported_stages;
+        let mut names = Vec::new();
+        if stages.vertex { names.push("vertex"); }
+        if stages.tessellation_control { names.push("tessellation_control"); }
+        if stages.tessellation_evaluation {
+            names.push("tessellation_evaluation");
+        }
+        if stages.geometry { names.push("geometry"); }
+        if stages.fragment { names.push("fragment"); }
+        if stages.compute { names.push("compute"); }
+        names
+    }
+
+    /// Truth that some operations are supported in some shader stages
+    pub fn supports(&self,
+                    operations: &SubgroupOperations,
+                    stages: &ShaderStages) -> bool {
+        self.supported_operations.contains(operations)
+            && self.supported_stages.is_superset_of(stages)
+    }
+}
+
+impl fmt::Display for SubgroupProperties {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "size {}, operations [{}] in stages [{}]{}",
+               self.size,
+               self.supported_operations.names().join(", "),
+               self.stage_names().join(", "),
+               if self.quad_operations_in_all_stages {
+                   ", quad operations in all stages"
+               } else {
+                   ""
+               })
+    }
+}
+
+
+/// Range of subgroup sizes of a device
+/// (VkPhysicalDeviceSubgroupSizeControlProperties)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubgroupSizeControl {
+    /// Minimal subgroup size
+    pub min_size: u32,
+
+    /// Maximal subgroup size
+    pub max_size: u32,
+
+    /// Maximal number of subgroups in a compute work group
+    pub max_compute_workgroup_subgroups: u32,
+
+    /// Truth that compute shaders can require a specific subgroup size
+    pub required_size_in_compute: bool,
+}
+
+
+/// Truth that a device can report its range of subgroup sizes
+fn supports_size_control(device: PhysicalDevice) -> bool {
+    if device.api_version() >= VULKAN_1_3 { return true; }
+    RawDeviceExtensions::supported_by_device_raw(device)
+        .map(|extensions| extensions.iter().any(|ext| {
+            ext.as_bytes() == SUBGROUP_SIZE_CONTROL_EXTENSION.as_bytes()
+        }))
+        .unwrap_or(false)
+}
+
+
+/// Device filter criterion requiring some subgroup operations in some shader
+/// stages, to be used as part of easy_device_filter()'s other criteria
+///
+/// Devices whose subgroup properties are unknown are rejected.
+///
+pub fn subgroup_filter(
+    operations: SubgroupOperations,
+    stages: ShaderStages
+) -> impl FnMut(PhysicalDevice) -> bool {
+    move |device| {
+        SubgroupProperties::of_device(device)
+            .map_or(false, |properties| properties.supports(&operations,
+                                                            &stages))
+    }
+}
+
+
+/// VkPhysicalDeviceSubgroupProperties, which vk-sys does not know about
+#[repr(C)]
+struct RawSubgroupProperties {
+    s_type: u32,
+    p_next: *mut c_void,
+    subgroup_size: u32,
+    supported_stages: u32,
+    supported_operations: u32,
+    quad_operations_in_all_stages: u32,
+}
+
+/// VkPhysicalDeviceSubgroupSizeControlProperties, which vk-sys does not know
+/// about
+#[repr(C)]
+struct RawSubgroupSizeControlProperties {
+    s_type: u32,
+    p_next: *mut c_void,
+    min_subgroup_size: u32,
+    max_subgroup_size: u32,
+    max_compute_workgroup_subgroups: u32,
+    required_subgroup_size_stages: u32,
+}
@@ -0,0 +1,168 @@
+//! Batching of command buffer submissions
+//!
+//! Every vkQueueSubmit has a fixed CPU and driver overhead, so submitting the
+//! many small command buffers of a frame one by one is wasteful. A
+//! SubmissionBatch accumulates the command buffers which target a given queue
+//! and submits them all at once when it is flushed.
+//!
+//! Vulkano merges consecutive command buffer executions on the same queue
+//! into a single submission, and turns dependencies on work from other queues
+//! (uploads, swapchain image acquisition...) into wait semaphores, so the
+//! batch only needs to chain GPU futures in the right order.
+//!
+//! To integrate with FramePacer, start the batch from the frame's start
+//! future, and end the frame with FramePacer::end_frame_batch(), so that the
+//! frame's fence is signaled by the batch's own submission:
+//!
+//! ```ignore
+//! let frame = pacer.begin_frame()?;
+//! batch.wait_for(frame.start);
+//! batch.submit(shadow_pass)?;
+//! batch.submit(main_pass)?;
+//! pacer.end_frame_batch(&mut batch)?;
+//! ```
+
+use ::{
+    trace,
+    Result,
+};
+
+use std::{
+    mem,
+    sync::Arc,
+};
+
+use vulkano::{
+    command_buffer::CommandBuffer,
+    device::{
+        DeviceOwned,
+        Queue,
+    },
+    sync::{
+        self,
+        GpuFuture,
+    },
+};
+
+
+/// Accumulator of the command buffers which target a given queue
+pub struct SubmissionBatch {
+    /// Queue which command buffers are submitted to
+    queue: Arc<Queue>,
+
+    /// GPU work which was accumulated so far
+    future: Box<dyn GpuFuture + Send + Sync>,
+
+    /// Number of command buffers which were not submitted yet
+    pending: usize,
+
+    /// Number of vkQueueSubmit calls which were made by flush()
+    submissions: u64,
+}
+
+impl SubmissionBatch {
+    /// Start an empty batch of command buffers for a certain queue
+    pub fn new(queue: &Arc<Queue>) -> Self {
+        SubmissionBatch {
+            queue: queue.clone(),
+            future: Self::empty_future(queue),
+            pending: 0,
+            submissions: 0,
+        }
+    }
+
+    /// Queue which command buffers are submitted to
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Make the command buffers which are submitted next wait for some GPU
+    /// work, such as a frame's start future or a swapchain image acquisition
+    pub fn wait_for(&mut self, future: impl GpuFuture + Send + Sync + 'static) {
+        let accumulated = self.take_accumulated();
+        self.future = Box::new(accumulated.join(future));
+    }
+
+    /// Add a command buffer to the batch
+    ///
+    /// The command buffer must have been allocated from the family of the
+    /// batch's queue. It will be executed after the command buffers which
+    /// were added before it.
+    ///
+    pub fn submit<Cb>(&mut self, command_buffer: Cb) -> Result<()>
+        where Cb: CommandBuffer + Send + Sync + 'static
+    {
+        let _trace = trace::span("SubmissionBatch::submit",
+                                 || format!("pending: {}", self.pending));
+        let accumulated = self.take_accumulated();
+        self.future = Box::new(accumulated.then_execute(self.queue.clone(),
+                                                        command_buffer)?);
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Number of command buffers which were not submitted yet
+    pub fn len(&self) -> usize {
+        self.pending
+    }
+
+    /// Truth that no command buffer is waiting to be submitted
+    pub fn is_empty(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// Number of submissions which were made by flush() so far
+    pub fn submissions(&self) -> u64 {
+        self.submissions
+    }
+
+    /// Submit the pending command buffers in a single vkQueueSubmit
+    ///
+    /// The submission signals a semaphore, which the command buffers that are
+    /// added to the batch afterwards will wait for. Flushing an empty batch
+    /// does nothing.
+    ///
+    pub fn flush(&mut self) -> Result<()> {
+        let _trace = trace::span("SubmissionBatch::flush",
+                                 || format!("pending: {}", self.pending));
+        if self.pending == 0 { return Ok(()); }
+        debug!("Submitting {} command buffer(s) to queue family {}",
+               self.pending, self.queue.family().id());
+        let accumulated = self.take_accumulated();
+        self.future = Box::new(accumulated.then_signal_semaphore_and_flush()?);
+        self.submissions += 1;
+        Ok(())
+    }
+
+    /// Take the accumulated GPU work out of the batch, without submitting it
+    ///
+    /// Use this to submit the batch together with some follow-up operation,
+    /// such as a presentation or a fence signal. The batch is left empty,
+    /// and the command buffers which are added to it afterwards do not wait
+    /// for the work that was taken out.
+    ///
+    pub fn take_future(&mut self) -> Box<dyn GpuFuture + Send + Sync> {
+        self.pending = 0;
+        self.take_accumulated()
+    }
+
+    /// Take the accumulated GPU work out, leaving an empty future behind
+    fn take_accumulated(&mut self) -> Box<dyn GpuFuture + Send + Sync> {
+        mem::replace(&mut self.future, Self::empty_future(&self.queue))
+    }
+
+    /// GPU future which an empty batch starts from
+    fn empty_future(queue: &Arc<Queue>) -> Box<dyn GpuFuture + Send + Sync> {
+        Box::new(sync::now(queue.device().clone()))
+    }
+}
+
+impl Drop for SubmissionBatch {
+    /// Warn about command buffers which were never submitted
+    fn drop(&mut self) {
+        if self.pending != 0 {
+            warn!("Dropping {} command buffer(s) which were never submitted \
+                   to queue family {}", self.pending, self.queue.family().id());
+        }
+    }
+}
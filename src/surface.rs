@@ -0,0 +1,313 @@
+//! Conveniences for selecting devices and queues that can present to a surface
+//! and for configuring presentation to it
+
+use ::{
+    easy_device_filter,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    cmp::Ordering,
+    sync::Arc,
+};
+
+use vulkano::{
+    device::DeviceExtensions,
+    format::Format,
+    image::ImageUsage,
+    instance::{
+        Features,
+        PhysicalDevice,
+        QueueFamily,
+    },
+    swapchain::{
+        Capabilities,
+        ColorSpace,
+        CompositeAlpha,
+        PresentMode,
+        Surface,
+        SurfaceTransform,
+    },
+};
+
+
+/// Helper for building device filters for window-based applications
+///
+/// This works like easy_device_filter(), but additionally requires that the
+/// device supports the VK_KHR_swapchain extension and has queue families which
+/// can respectively render graphics and present to the specified surface.
+///
+/// The graphics and presentation queue families may or may not be the same,
+/// see presentation_queue_families() for more details.
+///
+pub fn easy_presentation_filter<'a, W: 'a>(
+    features: &'a Features,
+    extensions: &'a DeviceExtensions,
+    surface: &'a Arc<Surface<W>>,
+    mut other_criteria: impl FnMut(PhysicalDevice) -> bool + 'a
+) -> impl FnMut(PhysicalDevice) -> bool + 'a {
+    let extensions = extensions.union(&presentation_extensions());
+    move |dev: PhysicalDevice| -> bool {
+        let mut basic_filter = easy_device_filter(
+            features,
+            &extensions,
+            |family| family.supports_graphics(),
+            |dev| presentation_queue_families(dev, surface).is_some()
+        );
+        basic_filter(dev) && other_criteria(dev)
+    }
+}
+
+/// Device extensions which are needed for presentation
+pub fn presentation_extensions() -> DeviceExtensions {
+    DeviceExtensions {
+        khr_swapchain: true,
+        .. DeviceExtensions::none()
+    }
+}
+
+/// Truth that a queue family can present to a surface
+///
+/// Errors during surface capability queries are treated as a lack of support.
+///
+pub fn supports_presentation<W>(family: QueueFamily,
+                                surface: &Surface<W>) -> bool {
+    surface.is_supported(family).unwrap_or(false)
+}
+
+/// Pick a pair of graphics and presentation queue families on a device
+///
+/// If a single queue family can do both, it is preferred, as it saves the
+/// application from transferring image ownership between queue families.
+/// Otherwise, the first graphics-capable queue family and the first
+/// presentation-capable queue family are returned.
+///
+/// The result is returned as a (graphics, presentation) pair.
+///
+pub fn presentation_queue_families<'a, W>(
+    device: PhysicalDevice<'a>,
+    surface: &Surface<W>
+) -> Option<(QueueFamily<'a>, QueueFamily<'a>)> {
+    let unified = device.queue_families().find(|&family| {
+        family.supports_graphics() && supports_presentation(family, surface)
+    });
+    if let Some(family) = unified {
+        return Some((family, family));
+    }
+    let graphics = device.queue_families().find(|f| f.supports_graphics())?;
+    let present = device.queue_families()
+                        .find(|&f| supports_presentation(f, surface))?;
+    Some((graphics, present))
+}
+
+/// Device preference favoring devices which can present from their graphics
+/// queue family, to be used after easy_presentation_filter().
+pub fn prefer_unified_presentation<'a, W>(
+    surface: &'a Arc<Surface<W>>
+) -> impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering + 'a {
+    move |dev1: PhysicalDevice, dev2: PhysicalDevice| -> Ordering {
+        let is_unified = |dev: PhysicalDevice| {
+            presentation_queue_families(dev, surface)
+                .map(|(graphics, present)| graphics.id() == present.id())
+                .unwrap_or(false)
+        };
+        is_unified(dev1).cmp(&is_unified(dev2))
+    }
+}
+
+/// Queue family preference favoring families which can do both graphics and
+/// presentation, for use with setup_single_queue_device().
+pub fn prefer_presentation_queue<'a, W>(
+    surface: &'a Arc<Surface<W>>
+) -> impl FnMut(&QueueFamily, &QueueFamily) -> Ordering + 'a {
+    move |family1: &QueueFamily, family2: &QueueFamily| -> Ordering {
+        let score = |family: &QueueFamily| {
+            (family.supports_graphics(),
+             supports_presentation(*family, surface))
+        };
+        score(family1).cmp(&score(family2))
+    }
+}
+
+
+/// What an application would like from presentation to a surface
+///
+/// These are only preferences: whenever the surface cannot honor them, we
+/// fall back to something that it supports.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfacePreferences {
+    /// Synchronize presentation with the display's refresh. If disabled, we
+    /// prefer immediate presentation (which may tear) for minimal latency.
+    pub vsync: bool,
+
+    /// Use a high dynamic range color space if the surface supports one.
+    /// Most of these require the VK_EXT_swapchain_colorspace instance
+    /// extension to be enabled.
+    pub hdr: bool,
+
+    /// Image dimensions, only used if the surface does not impose them
+    pub dimensions: [u32; 2],
+}
+
+impl Default for SurfacePreferences {
+    /// VSync without HDR, 800x600 images if the surface lets us choose
+    fn default() -> Self {
+        SurfacePreferences {
+            vsync: true,
+            hdr: false,
+            dimensions: [800, 600],
+        }
+    }
+}
+
+
+/// Swapchain configuration that a surface supports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfaceConfig {
+    /// Number of swapchain images
+    pub num_images: u32,
+
+    /// Format of the swapchain images
+    pub format: Format,
+
+    /// Color space in which the swapchain images are interpreted
+    pub color_space: ColorSpace,
+
+    /// Presentation mode
+    pub present_mode: PresentMode,
+
+    /// Dimensions of the swapchain images
+    pub dimensions: [u32; 2],
+
+    /// Usage of the swapchain images
+    pub usage: ImageUsage,
+
+    /// Transform applied to the images before presentation
+    pub transform: SurfaceTransform,
+
+    /// Alpha compositing mode
+    pub composite_alpha: CompositeAlpha,
+}
+
+/// Pick a swapchain configuration from a surface's capabilities
+///
+/// The surface format, present mode and number of images are picked as
+/// follows:
+///
+/// - If HDR is preferred, we look for an HDR10 color space, then for any
+///   other HDR color space. Otherwise (or if none is found), we prefer 8-bit
+///   BGRA and RGBA sRGB formats, and go for the first supported format if
+///   these are not available either.
+/// - With vsync, we prefer mailbox presentation (low latency without
+///   tearing), and otherwise fall back to FIFO, which is always available.
+///   Without vsync, we prefer immediate presentation, then mailbox, then FIFO.
+/// - We ask for one more image than the minimum, so that the application
+///   does not need to wait for the presentation engine to render.
+///
+/// Images are color attachments, which can also be captured (see
+/// image::capture_image_to_rgba) when the surface allows for it.
+///
+pub fn choose_surface_config<W>(
+    surface: &Surface<W>,
+    device: PhysicalDevice,
+    preferences: &SurfacePreferences
+) -> Result<SurfaceConfig> {
+    let _trace = trace::span("surface::choose_surface_config",
+                             || format!("{:?}", preferences));
+    let caps = surface.capabilities(device)?;
+    info!("Surface capabilities: {:?}", caps);
+
+    let (format, color_space) = select_format(&caps, preferences.hdr)?;
+    let composite_alpha = caps.supported_composite_alpha
+                              .iter()
+                              .next()
+                              .ok_or_else(|| Error::UnsupportedSurface(
+                                  "No composite alpha mode".to_owned()
+                              ))?;
+    let config = SurfaceConfig {
+        num_images: select_image_count(&caps),
+        format,
+        color_space,
+        present_mode: select_present_mode(&caps, preferences.vsync),
+        dimensions: select_dimensions(&caps, preferences.dimensions),
+        usage: ImageUsage {
+            transfer_source: caps.supported_usage_flags.transfer_source,
+            .. ImageUsage::color_attachment()
+        },
+        transform: caps.current_transform,
+        composite_alpha,
+    };
+    debug!("Selected surface configuration: {:?}", config);
+    Ok(config)
+}
+
+
+/// Pick a swapchain format among those supported by a surface
+fn select_format(caps: &Capabilities,
+                 hdr: bool) -> Result<(Format, ColorSpace)> {
+    let find = |color_space: ColorSpace| {
+        caps.supported_formats.iter()
+                              .find(|&&(_, space)| space == color_space)
+                              .cloned()
+    };
+    if hdr {
+        let hdr_spaces = [ColorSpace::Hdr10St2084,
+                          ColorSpace::Hdr10Hlg,
+                          ColorSpace::ExtendedSrgbLinear,
+                          ColorSpace::DolbyVision];
+        if let Some(format) = hdr_spaces.iter().filter_map(|&s| find(s))
+                                                .next()
+        {
+            return Ok(format);
+        }
+        info!("Surface does not support HDR, falling back to SDR");
+    }
+
+    let preferred_formats = [Format::B8G8R8A8Srgb, Format::R8G8B8A8Srgb];
+    for &preferred in preferred_formats.iter() {
+        let found = caps.supported_formats.iter().find(|&&(format, space)| {
+            format == preferred && space == ColorSpace::SrgbNonLinear
+        });
+        if let Some(&format) = found {
+            return Ok(format);
+        }
+    }
+    caps.supported_formats.first()
+                          .cloned()
+                          .ok_or_else(|| Error::UnsupportedSurface(
+                              "No supported format".to_owned()
+                          ))
+}
+
+/// Pick a present mode among those supported by a surface
+fn select_present_mode(caps: &Capabilities, vsync: bool) -> PresentMode {
+    let modes = &caps.present_modes;
+    if !vsync && modes.immediate {
+        PresentMode::Immediate
+    } else if modes.mailbox {
+        PresentMode::Mailbox
+    } else {
+        PresentMode::Fifo
+    }
+}
+
+/// Pick a number of swapchain images, given the surface's capabilities
+fn select_image_count(caps: &Capabilities) -> u32 {
+    let desired = caps.min_image_count + 1;
+    match caps.max_image_count {
+        Some(max) => desired.min(max),
+        None => desired,
+    }
+}
+
+/// Pick swapchain dimensions, given the surface's capabilities
+fn select_dimensions(caps: &Capabilities, desired: [u32; 2]) -> [u32; 2] {
+    caps.current_extent.unwrap_or_else(|| {
+        let clamp = |i: usize| desired[i].max(caps.min_image_extent[i])
+                                         .min(caps.max_image_extent[i]);
+        [clamp(0), clamp(1)]
+    })
+}
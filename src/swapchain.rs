@@ -0,0 +1,264 @@
+//! Conveniences for creating and using Vulkan swapchains
+
+use ::{
+    surface::{
+        self,
+        SurfaceConfig,
+        SurfacePreferences,
+    },
+    trace,
+    Result,
+};
+
+use std::sync::Arc;
+
+use vulkano::{
+    device::{
+        Device,
+        Queue,
+    },
+    format::Format,
+    image::SwapchainImage,
+    swapchain::{
+        self,
+        AcquireError,
+        PresentFuture,
+        Surface,
+        Swapchain,
+        SwapchainAcquireFuture,
+        SwapchainCreationError,
+    },
+    sync::{
+        FenceSignalFuture,
+        FlushError,
+        GpuFuture,
+    },
+};
+
+
+/// A convenience wrapper around vulkano's Swapchain
+///
+/// This picks a sensible configuration from the surface capabilities (unless
+/// you specify one yourself), and takes care of recreating the swapchain
+/// whenever it goes out of date.
+///
+/// The typical usage pattern is to call acquire() at the beginning of each
+/// frame, render to the image that it returns, then call present(). When the
+/// window is resized, tell the swapchain by calling resize().
+///
+pub struct EasySwapchain<W> {
+    /// Device which the swapchain belongs to
+    device: Arc<Device>,
+
+    /// Queue used for presentation
+    queue: Arc<Queue>,
+
+    /// Wrapped Swapchain
+    swapchain: Arc<Swapchain<W>>,
+
+    /// Images of the swapchain
+    images: Vec<Arc<SwapchainImage<W>>>,
+
+    /// Desired swapchain dimensions, when the surface does not impose them
+    dimensions: [u32; 2],
+
+    /// Truth that the swapchain must be recreated before the next acquire
+    needs_recreation: bool,
+}
+
+impl<W> EasySwapchain<W> {
+    /// Set up a swapchain for rendering to a surface
+    ///
+    /// The specified queue is the one which will be used for presentation. The
+    /// dimensions are only used if the surface does not impose any, which is
+    /// the case of some windowing systems.
+    ///
+    /// The rest of the configuration is picked by choose_surface_config(),
+    /// with vsync on and HDR off. Use with_config() if you want more control.
+    ///
+    pub fn new(
+        device: &Arc<Device>,
+        surface: &Arc<Surface<W>>,
+        queue: &Arc<Queue>,
+        dimensions: [u32; 2],
+    ) -> Result<Self> {
+        let preferences = SurfacePreferences {
+            dimensions,
+            .. SurfacePreferences::default()
+        };
+        let config = surface::choose_surface_config(surface,
+                                                    device.physical_device(),
+                                                    &preferences)?;
+        Self::with_config(device, surface, queue, &config)
+    }
+
+    /// Set up a swapchain with a specific configuration
+    ///
+    /// See surface::choose_surface_config() for a way to build a
+    /// configuration which the surface supports.
+    ///
+    pub fn with_config(
+        device: &Arc<Device>,
+        surface: &Arc<Surface<W>>,
+        queue: &Arc<Queue>,
+        config: &SurfaceConfig,
+    ) -> Result<Self> {
+        let _trace = trace::span("EasySwapchain::with_config",
+                                 || format!("{:?}", config));
+        info!("Creating a swapchain with {} {:?} images ({:?}, {:?}), \
+               presented in {:?} mode",
+              config.num_images, config.dimensions, config.format,
+              config.color_space, config.present_mode);
+
+        // Create the swapchain
+        let (swapchain, images) = Swapchain::new(
+            device.clone(),
+            surface.clone(),
+            config.num_images,
+            config.format,
+            config.dimensions,
+            1,
+            config.usage,
+            queue,
+            config.transform,
+            config.composite_alpha,
+            config.present_mode,
+            true,
+            None
+        )?;
+
+        // Return the freshly built wrapper
+        Ok(EasySwapchain {
+            device: device.clone(),
+            queue: queue.clone(),
+            swapchain,
+            images,
+            dimensions: config.dimensions,
+            needs_recreation: false,
+        })
+    }
+
+    /// Access the inner vulkano Swapchain
+    pub fn swapchain(&self) -> &Arc<Swapchain<W>> {
+        &self.swapchain
+    }
+
+    /// Access the swapchain images
+    ///
+    /// Beware that these change whenever the swapchain is recreated, so any
+    /// framebuffer built from them must be rebuilt as well. You can tell that
+    /// this happened by watching the return value of acquire().
+    ///
+    pub fn images(&self) -> &[Arc<SwapchainImage<W>>] {
+        &self.images[..]
+    }
+
+    /// Current dimensions of the swapchain images
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.swapchain.dimensions()
+    }
+
+    /// Format of the swapchain images
+    pub fn format(&self) -> Format {
+        self.swapchain.format()
+    }
+
+    /// Notify the swapchain that the window was resized
+    ///
+    /// The swapchain will be recreated on the next call to acquire().
+    ///
+    pub fn resize(&mut self, dimensions: [u32; 2]) {
+        self.dimensions = dimensions;
+        self.needs_recreation = true;
+    }
+
+    /// Recreate the swapchain, returns false if this is not possible yet
+    ///
+    /// Recreation can fail transiently, for example while a window is
+    /// minimized, in which case you should try again later.
+    ///
+    fn recreate(&mut self) -> Result<bool> {
+        let _trace = trace::span("EasySwapchain::recreate",
+                                 || format!("dimensions: {:?}",
+                                            self.dimensions));
+        let caps = self.swapchain.surface()
+                                 .capabilities(self.device.physical_device())?;
+        let dimensions = caps.current_extent.unwrap_or(self.dimensions);
+        match self.swapchain.recreate_with_dimension(dimensions) {
+            Ok((swapchain, images)) => {
+                info!("Swapchain recreated with dimensions {:?}", dimensions);
+                self.swapchain = swapchain;
+                self.images = images;
+                self.dimensions = dimensions;
+                self.needs_recreation = false;
+                Ok(true)
+            },
+            Err(SwapchainCreationError::UnsupportedDimensions) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Acquire the next swapchain image
+    ///
+    /// On success, this returns the index of the acquired image, a future
+    /// representing the moment where it becomes available, and whether the
+    /// swapchain was recreated (which means that the images changed).
+    ///
+    /// If the swapchain cannot be used at the moment (e.g. because the window
+    /// is minimized), None is returned and you should skip the frame.
+    ///
+    pub fn acquire(
+        &mut self
+    ) -> Result<Option<(usize, SwapchainAcquireFuture<W>, bool)>> {
+        let _trace = trace::span("EasySwapchain::acquire", String::new);
+        let mut recreated = false;
+        loop {
+            // Recreate the swapchain if needed
+            if self.needs_recreation {
+                if !self.recreate()? { return Ok(None); }
+                recreated = true;
+            }
+
+            // Try to acquire an image
+            match swapchain::acquire_next_image(self.swapchain.clone(), None) {
+                Ok((image_num, future)) => {
+                    return Ok(Some((image_num, future, recreated)));
+                },
+                Err(AcquireError::OutOfDate) => {
+                    info!("Swapchain is out of date, will recreate it");
+                    self.needs_recreation = true;
+                },
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Present a swapchain image once a future has completed
+    ///
+    /// This schedules the presentation of the image and flushes the resulting
+    /// future. If the swapchain turns out to be out of date, it is scheduled
+    /// for recreation and None is returned.
+    ///
+    pub fn present<F: GpuFuture>(
+        &mut self,
+        future: F,
+        image_num: usize
+    ) -> Result<Option<FenceSignalFuture<PresentFuture<F, W>>>> {
+        let _trace = trace::span("EasySwapchain::present",
+                                 || format!("image_num: {}", image_num));
+        let result = future.then_swapchain_present(self.queue.clone(),
+                                                   self.swapchain.clone(),
+                                                   image_num)
+                           .then_signal_fence_and_flush();
+        match result {
+            Ok(future) => Ok(Some(future)),
+            Err(FlushError::OutOfDate) => {
+                info!("Swapchain is out of date, will recreate it");
+                self.needs_recreation = true;
+                Ok(None)
+            },
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
@@ -0,0 +1,271 @@
+//! Pacing of the frames that are in flight on the GPU
+//!
+//! Letting the CPU prepare frames much faster than the GPU renders them
+//! increases latency and memory usage, while waiting for the GPU after every
+//! frame wastes performance. FramePacer implements the usual compromise of
+//! allowing a fixed number of frames in flight, using one fence per frame.
+//!
+//! Vulkano takes care of semaphores as part of its GPU futures, so the only
+//! synchronization objects which you need to handle are fences, and even
+//! those are managed by FramePacer.
+//!
+//! Resources which the frames in flight may still use must not be destroyed
+//! right away. FramePacer::defer_drop() keeps them alive in a DeletionQueue
+//! until the GPU is done with the corresponding frame.
+//!
+//! To submit all the command buffers of a frame at once, accumulate them in
+//! a SubmissionBatch and end the frame with FramePacer::end_frame_batch().
+//!
+//! Per-frame uniform data can be written to an EasyUniformRing, whose frame
+//! slots follow those of the FramePacer.
+
+use ::{
+    submission::SubmissionBatch,
+    trace,
+    Result,
+};
+
+use std::{
+    mem,
+    sync::Arc,
+    time::Instant,
+};
+
+use vulkano::{
+    device::Device,
+    sync::{
+        self,
+        FenceSignalFuture,
+        GpuFuture,
+    },
+};
+
+
+/// A frame whose GPU work may still be running
+trait PendingFrame: Send + Sync {
+    /// Wait for the frame's GPU work to be done
+    fn wait_done(&self) -> Result<()>;
+}
+
+impl<F> PendingFrame for FenceSignalFuture<F>
+    where F: GpuFuture + Send + Sync
+{
+    fn wait_done(&self) -> Result<()> {
+        Ok(self.wait(None)?)
+    }
+}
+
+
+/// Resources whose destruction is deferred until the GPU is done with them
+///
+/// Resources are associated with a frame slot when they are deferred, and
+/// dropped when that slot is flushed, which should happen once the fence of
+/// the last frame that used the slot has signaled. FramePacer does this for
+/// you, but you can also drive a DeletionQueue manually.
+///
+pub struct DeletionQueue {
+    /// Deferred resources, by frame slot
+    slots: Vec<Vec<Box<dyn Send>>>,
+}
+
+impl DeletionQueue {
+    /// Set up a deletion queue with a certain number of frame slots
+    pub fn new(num_slots: usize) -> Self {
+        assert!(num_slots > 0, "At least one frame slot is needed");
+        DeletionQueue {
+            slots: (0..num_slots).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    /// Keep a resource alive until a frame slot is flushed
+    pub fn defer_drop(&mut self, slot: usize, resource: impl Send + 'static) {
+        self.slots[slot].push(Box::new(resource));
+    }
+
+    /// Drop the resources of a frame slot, whose GPU work must be done
+    pub fn flush(&mut self, slot: usize) {
+        let resources = mem::replace(&mut self.slots[slot], Vec::new());
+        if !resources.is_empty() {
+            debug!("Dropping {} deferred resource(s) of frame slot {}",
+                   resources.len(), slot);
+        }
+    }
+
+    /// Drop all deferred resources, once all GPU work is done
+    pub fn flush_all(&mut self) {
+        for slot in 0..self.slots.len() {
+            self.flush(slot);
+        }
+    }
+
+    /// Number of resources whose destruction is pending
+    pub fn len(&self) -> usize {
+        self.slots.iter().map(|slot| slot.len()).sum()
+    }
+
+    /// Truth that no resource destruction is pending
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+
+/// Frame which is being prepared, as returned by FramePacer::begin_frame()
+pub struct FrameSlot {
+    /// Number of frames which were started before this one
+    pub frame: u64,
+
+    /// Index of this frame's slot, from 0 to the number of frames in flight
+    ///
+    /// Use it to index per-frame resources (uniform buffers, command
+    /// buffers...) which must not be modified while the GPU uses them.
+    ///
+    pub index: usize,
+
+    /// Future which the frame's GPU work should start from
+    pub start: Box<dyn GpuFuture + Send + Sync>,
+}
+
+
+/// Manager of a fixed number of frames in flight
+pub struct FramePacer {
+    /// Device which frames are rendered on
+    device: Arc<Device>,
+
+    /// Fence of each frame slot, if GPU work was submitted for it
+    pending: Vec<Option<Arc<dyn PendingFrame>>>,
+
+    /// Number of frames which were started so far
+    frame: u64,
+
+    /// Truth that begin_frame() was called, but not end_frame()
+    in_frame: bool,
+
+    /// Resources which the frames in flight may still use
+    deletion_queue: DeletionQueue,
+}
+
+impl FramePacer {
+    /// Set up a frame pacer allowing a certain number of frames in flight
+    pub fn new(device: &Arc<Device>, frames_in_flight: usize) -> Self {
+        assert!(frames_in_flight > 0, "At least one frame must be in flight");
+        FramePacer {
+            device: device.clone(),
+            pending: (0..frames_in_flight).map(|_| None).collect(),
+            frame: 0,
+            in_frame: false,
+            deletion_queue: DeletionQueue::new(frames_in_flight),
+        }
+    }
+
+    /// Number of frames which may be in flight at the same time
+    pub fn frames_in_flight(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Start preparing a new frame
+    ///
+    /// If the GPU is too far behind, this blocks until it is done with the
+    /// frame which previously used the same slot. Resources which were
+    /// deferred during that frame are then dropped.
+    ///
+    pub fn begin_frame(&mut self) -> Result<FrameSlot> {
+        let _trace = trace::span("FramePacer::begin_frame",
+                                 || format!("frame: {}", self.frame));
+        assert!(!self.in_frame, "end_frame() was not called");
+        let index = (self.frame % self.pending.len() as u64) as usize;
+        if let Some(pending) = self.pending[index].take() {
+            let start = Instant::now();
+            pending.wait_done()?;
+            debug!("Waited {:?} for the GPU to finish frame slot {}",
+                   start.elapsed(), index);
+        }
+        self.deletion_queue.flush(index);
+        self.in_frame = true;
+        Ok(FrameSlot {
+            frame: self.frame,
+            index,
+            start: Box::new(sync::now(self.device.clone())),
+        })
+    }
+
+    /// Submit the GPU work of the current frame
+    ///
+    /// The future should represent the whole GPU work of the frame, typically
+    /// ending with presentation. It is flushed and associated with a fence,
+    /// which is returned so that it can be shared with other frame-tracking
+    /// helpers, such as EasyStagingBelt.
+    ///
+    pub fn end_frame<F>(&mut self,
+                        future: F) -> Result<Arc<FenceSignalFuture<F>>>
+        where F: GpuFuture + Send + Sync + 'static
+    {
+        let _trace = trace::span("FramePacer::end_frame",
+                                 || format!("frame: {}", self.frame));
+        assert!(self.in_frame, "begin_frame() was not called");
+        let index = (self.frame % self.pending.len() as u64) as usize;
+        self.in_frame = false;
+        self.frame += 1;
+        let fence = Arc::new(future.then_signal_fence_and_flush()?);
+        self.pending[index] = Some(fence.clone());
+        Ok(fence)
+    }
+
+    /// Submit the GPU work of the current frame, as accumulated by a
+    /// SubmissionBatch
+    ///
+    /// The batch's pending command buffers and the frame's fence go into a
+    /// single submission. The batch is left empty, ready for the next frame.
+    ///
+    pub fn end_frame_batch(
+        &mut self,
+        batch: &mut SubmissionBatch
+    ) -> Result<Arc<FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>>> {
+        self.end_frame(batch.take_future())
+    }
+
+    /// Keep a resource alive until the GPU is done with the current frame
+    ///
+    /// The current frame is the one being prepared if begin_frame() was
+    /// called, and otherwise the one that was last submitted. Use this for
+    /// per-frame resources (buffers, descriptor sets, framebuffers...) which
+    /// you want to replace while frames in flight may still be using them.
+    ///
+    pub fn defer_drop(&mut self, resource: impl Send + 'static) {
+        let frame = if self.in_frame {
+            self.frame
+        } else {
+            self.frame.saturating_sub(1)
+        };
+        let index = (frame % self.pending.len() as u64) as usize;
+        self.deletion_queue.defer_drop(index, resource);
+    }
+
+    /// Number of resources whose destruction was deferred by defer_drop()
+    pub fn pending_drops(&self) -> usize {
+        self.deletion_queue.len()
+    }
+
+    /// Wait for the GPU to be done with all frames in flight
+    ///
+    /// All resources deferred by defer_drop() are dropped afterwards.
+    ///
+    pub fn wait_all(&mut self) -> Result<()> {
+        for pending in &mut self.pending {
+            if let Some(pending) = pending.take() {
+                pending.wait_done()?;
+            }
+        }
+        self.deletion_queue.flush_all();
+        Ok(())
+    }
+}
+
+impl Drop for FramePacer {
+    /// Wait for the frames in flight, so that their resources can be freed
+    fn drop(&mut self) {
+        if let Err(e) = self.wait_all() {
+            error!("Failed to wait for frames in flight: {}", e);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Harness for tests which need a Vulkan implementation
+//!
+//! Tests which exercise Vulkan code need an instance with validation enabled,
+//! should fail whenever the validation layers report an error, and should not
+//! fail on machines which have no Vulkan implementation at all (e.g. CI
+//! runners without a GPU). with_test_instance() takes care of all of this:
+//!
+//! ```ignore
+//! #[test]
+//! fn my_gpu_test() {
+//!     testing::with_test_instance(|instance| {
+//!         // ...select a device and test something with it...
+//!     });
+//! }
+//! ```
+//!
+//! Tests are also skipped when no validation layer is installed, since they
+//! could not check anything about validation then.
+//!
+//! Skipped tests still pass, since the standard test harness has no notion of
+//! skipped tests, but a warning is logged (set up a logger in your tests to
+//! see it). On machines which are known to have a Vulkan implementation and
+//! the validation layers, set VULKANOOB_REQUIRE_VULKAN=1 to turn skipped tests
+//! into failures. To get deterministic results on machines with and without
+//! GPUs, consider requiring a software implementation (see the software
+//! module).
+
+use ::{
+    debug::DebugMessage,
+    instance::{
+        self,
+        EasyInstance,
+        EasyInstanceBuilder,
+    },
+};
+
+use std::{
+    env,
+    sync::{Arc, Mutex},
+};
+
+use vulkano::instance::{
+    debug::MessageTypes,
+    PhysicalDevice,
+};
+
+
+/// Environment variable which turns skipped tests into failures
+pub const REQUIRE_VULKAN_VAR: &str = "VULKANOOB_REQUIRE_VULKAN";
+
+
+/// Run a test with a validation-enabled EasyInstance
+///
+/// The test is skipped, and None is returned, if no Vulkan implementation
+/// with at least one physical device is available, or if no validation layer
+/// is installed. Otherwise, the result of the test is returned, and any
+/// validation error which was reported while the test was running, or any
+/// debug message which was escalated by the instance's DebugFilter, makes
+/// this function panic.
+///
+pub fn with_test_instance<R>(
+    test: impl FnOnce(&Arc<EasyInstance>) -> R
+) -> Option<R> {
+    with_test_instance_builder(|builder| builder, test)
+}
+
+/// Run a test with a validation-enabled EasyInstance, configured further
+/// using an EasyInstanceBuilder (e.g. to enable extensions)
+///
+/// This works like with_test_instance(). The validation layer, the enabled
+/// debug messages and the message sink are set up by the harness, after the
+/// configuration step, so configuring them yourself has no effect.
+///
+pub fn with_test_instance_builder<R>(
+    configure: impl FnOnce(EasyInstanceBuilder) -> EasyInstanceBuilder,
+    test: impl FnOnce(&Arc<EasyInstance>) -> R
+) -> Option<R> {
+    // Record validation errors as they come
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let sink_errors = errors.clone();
+    let builder = configure(EasyInstance::builder())
+        .validation(true)
+        .debug_messages(MessageTypes::errors_and_warnings())
+        .message_sink(move |message: &DebugMessage| {
+            if message.ty.error {
+                sink_errors.lock().unwrap()
+                           .push(format!("[{}] {}",
+                                         message.layer_prefix,
+                                         message.description));
+            }
+        });
+
+    // Set up the instance, or skip the test if Vulkan is not available
+    let instance = match builder.build() {
+        Ok(instance) => instance,
+        Err(ref e) if e.is_vulkan_unavailable() => {
+            skip(&format!("Vulkan is not available ({})", e));
+            return None;
+        },
+        Err(e) => panic!("Failed to create a test instance: {}", e),
+    };
+    match instance::find_validation_layer() {
+        Ok(Some(_)) => {},
+        Ok(None) => {
+            skip("validation was requested, but no validation layer is \
+                  installed");
+            return None;
+        },
+        Err(e) => panic!("Failed to look up the validation layers: {}", e),
+    }
+    if PhysicalDevice::enumerate(instance.instance()).next().is_none() {
+        skip("no Vulkan physical device is available");
+        return None;
+    }
+
+    // Run the test, then check that validation did not complain
+    let result = test(&instance);
+    let errors = errors.lock().unwrap();
+    if !errors.is_empty() {
+        panic!("{} Vulkan validation error(s) were reported: {:#?}",
+               errors.len(), *errors);
+    }
+    if let Err(e) = instance.check_escalations() {
+        panic!("{}", e);
+    }
+    Some(result)
+}
+
+
+/// Skip a test, or fail it if a Vulkan implementation is required
+fn skip(reason: &str) {
+    let required = env::var(REQUIRE_VULKAN_VAR)
+        .map(|value| !value.is_empty() && value != "0")
+        .unwrap_or(false);
+    if required {
+        panic!("Cannot run a Vulkan test ({} is set): {}",
+               REQUIRE_VULKAN_VAR, reason);
+    }
+    warn!("Skipping Vulkan test: {}", reason);
+}
@@ -0,0 +1,123 @@
+//! Opt-in tracing of the Vulkan-touching vulkanoob helpers
+//!
+//! When trace mode is enabled, every vulkanoob helper which talks to Vulkan
+//! emits a one-line record with the "vulkanoob::trace" logging target once it
+//! is done. This record features a timestamp, the name of the helper, its key
+//! parameters and the time that it took to execute.
+//!
+//! This is a poor man's API trace, which will not replace a proper capture
+//! tool like RenderDoc, but can come handy when you want to know what is going
+//! on without leaving the comfort of your logs.
+//!
+//! If the "tracing" feature is enabled, the same helpers also open a span of
+//! the "tracing" ecosystem (named "vulkanoob", with "call" and "params"
+//! fields) while they execute, irrespective of whether trace mode is enabled.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "tracing")]
+use tracing::{
+    field,
+    span::EnteredSpan,
+};
+
+
+/// Logging target used by the trace records
+pub const TARGET: &str = "vulkanoob::trace";
+
+/// Global trace mode switch
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+
+/// Enable or disable trace mode
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Truth that trace mode is enabled
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+
+/// Trace record of a helper call, emitted when dropped
+struct TraceRecord {
+    /// Name of the helper being traced
+    call: &'static str,
+
+    /// Textual description of the helper's key parameters
+    params: String,
+
+    /// Time at which the helper was called
+    start: Instant,
+}
+
+impl Drop for TraceRecord {
+    /// Emit the trace record
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+                                         .unwrap_or_default();
+        info!(target: TARGET,
+              "{}.{:06} {}({}) took {}.{:03} ms",
+              timestamp.as_secs(),
+              timestamp.subsec_micros(),
+              self.call,
+              self.params,
+              elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64,
+              elapsed.subsec_micros() % 1000);
+    }
+}
+
+
+/// Tracing state of a helper call, which lasts until it is dropped
+pub(crate) struct TraceSpan {
+    /// Trace record, if trace mode is enabled
+    _record: Option<TraceRecord>,
+
+    /// Span of the "tracing" ecosystem, entered until the helper is done
+    #[cfg(feature = "tracing")]
+    _span: EnteredSpan,
+}
+
+
+/// Start tracing a helper call
+///
+/// The parameters are only formatted if trace mode is enabled, or if someone
+/// subscribes to the corresponding "tracing" span. Keep the returned span
+/// alive until the helper is done, the trace record will be emitted and the
+/// tracing span will be exited when it is dropped.
+///
+pub(crate) fn span(call: &'static str,
+                   params: impl FnOnce() -> String) -> TraceSpan {
+    #[cfg(feature = "tracing")]
+    let tracing_span = ::tracing::info_span!(target: TARGET,
+                                             "vulkanoob",
+                                             call,
+                                             params = field::Empty);
+    #[cfg(feature = "tracing")]
+    let needs_params = is_enabled() || !tracing_span.is_disabled();
+    #[cfg(not(feature = "tracing"))]
+    let needs_params = is_enabled();
+
+    let params = if needs_params { params() } else { String::new() };
+    #[cfg(feature = "tracing")]
+    tracing_span.record("params", &params.as_str());
+
+    TraceSpan {
+        _record: if is_enabled() {
+            Some(TraceRecord {
+                call,
+                params,
+                start: Instant::now(),
+            })
+        } else {
+            None
+        },
+        #[cfg(feature = "tracing")]
+        _span: tracing_span.entered(),
+    }
+}
@@ -0,0 +1,165 @@
+//! Background uploads on a dedicated transfer queue
+//!
+//! Many GPUs have a queue family which only supports transfers, and which is
+//! backed by DMA engines that can copy data while the graphics queue keeps
+//! rendering. Using it properly means submitting the copies to that queue,
+//! then making the graphics work that uses the data wait on a semaphore.
+//!
+//! EasyTransfer does exactly that when the device has a dedicated transfer
+//! queue, and falls back to submitting the copies to the graphics queue
+//! otherwise, so that the same code runs everywhere. Either way, uploads do
+//! not block the host: you get the destination buffer right away, along with
+//! a GPU future that the graphics work should be chained to.
+
+use ::{
+    device::EasyDevice,
+    trace,
+    Result,
+};
+
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{
+        BufferUsage,
+        CpuAccessibleBuffer,
+        DeviceLocalBuffer,
+    },
+    command_buffer::{
+        AutoCommandBufferBuilder,
+        CommandBuffer,
+    },
+    device::{
+        Device,
+        Queue,
+    },
+    sync::GpuFuture,
+};
+
+
+/// An upload which was submitted by EasyTransfer
+///
+/// Chain the graphics work which uses the buffer to the future, e.g. with
+/// `upload.future.then_execute(graphics_queue, command_buffer)`. When the
+/// upload ran on a dedicated transfer queue, this makes the graphics queue
+/// wait on the semaphore that the transfer queue signals.
+///
+pub struct EasyUpload<T: 'static> {
+    /// Destination buffer
+    pub buffer: Arc<DeviceLocalBuffer<[T]>>,
+
+    /// Completion of the upload, signaling a semaphore
+    pub future: Box<dyn GpuFuture + Send + Sync>,
+}
+
+impl<T: 'static> EasyUpload<T> {
+    /// Wait for the upload to complete, and get the buffer
+    ///
+    /// This gives up on the asynchronous nature of the upload, but is handy
+    /// for loading-time uploads.
+    ///
+    pub fn wait(self) -> Result<Arc<DeviceLocalBuffer<[T]>>> {
+        self.future.then_signal_fence_and_flush()?.wait(None)?;
+        Ok(self.buffer)
+    }
+}
+
+
+/// Uploader which uses a dedicated transfer queue when there is one
+pub struct EasyTransfer {
+    /// Device which the uploads are carried out on
+    device: Arc<Device>,
+
+    /// Queue which the copies are submitted to
+    transfer_queue: Arc<Queue>,
+
+    /// Queue which uses the uploaded data
+    graphics_queue: Arc<Queue>,
+}
+
+impl EasyTransfer {
+    /// Set up an uploader for data which will be used on some queue
+    ///
+    /// The uploads go through the device's dedicated transfer queue if it was
+    /// set up with one (see EasyDevice::transfer_queue()), and through the
+    /// specified queue otherwise.
+    ///
+    pub fn new(device: &EasyDevice, graphics_queue: &Arc<Queue>) -> Self {
+        let transfer_queue = match device.transfer_queue() {
+            Some(queue) => {
+                info!("Uploads will run on dedicated transfer queue family {}",
+                      queue.family().id());
+                queue.clone()
+            },
+            None => {
+                info!("No dedicated transfer queue, uploads will run on queue \
+                       family {}", graphics_queue.family().id());
+                graphics_queue.clone()
+            },
+        };
+        EasyTransfer {
+            device: device.device().clone(),
+            transfer_queue,
+            graphics_queue: graphics_queue.clone(),
+        }
+    }
+
+    /// Truth that uploads run on a dedicated transfer queue
+    pub fn is_dedicated(&self) -> bool {
+        self.transfer_queue.family().id() != self.graphics_queue.family().id()
+    }
+
+    /// Queue which the copies are submitted to
+    pub fn transfer_queue(&self) -> &Arc<Queue> {
+        &self.transfer_queue
+    }
+
+    /// Start uploading data to a new device-local buffer
+    ///
+    /// The buffer is shared between the transfer and graphics queue families,
+    /// so that no ownership transfer is needed between them. It gets the
+    /// usage flags that you specify, plus transfer_destination.
+    ///
+    pub fn upload<T>(&self,
+                     data: &[T],
+                     usage: BufferUsage) -> Result<EasyUpload<T>>
+        where T: Copy + Send + Sync + 'static
+    {
+        let _trace = trace::span("EasyTransfer::upload",
+                                 || format!("elements: {}, usage: {:?}",
+                                            data.len(), usage));
+
+        // Put the data in a staging buffer
+        let staging_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::transfer_source(),
+            data.iter().cloned()
+        )?;
+
+        // Allocate the destination buffer
+        let mut families = vec![self.transfer_queue.family()];
+        if self.is_dedicated() {
+            families.push(self.graphics_queue.family());
+        }
+        let buffer = DeviceLocalBuffer::array(self.device.clone(),
+                                              data.len(),
+                                              BufferUsage {
+                                                  transfer_destination: true,
+                                                  ..usage
+                                              },
+                                              families)?;
+
+        // Submit the copy, signaling a semaphore at the end
+        let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(
+            self.device.clone(),
+            self.transfer_queue.family()
+        )?.copy_buffer(staging_buffer, buffer.clone())?
+          .build()?;
+        let future = command_buffer.execute(self.transfer_queue.clone())?
+                                   .then_signal_semaphore_and_flush()?;
+        Ok(EasyUpload {
+            buffer,
+            future: Box::new(future),
+        })
+    }
+}
@@ -0,0 +1,233 @@
+//! Per-frame uniform data, suballocated from a ring of host-visible buffers
+//!
+//! Uniform data often changes for every draw of every frame. Giving each draw
+//! its own uniform buffer and descriptor set is wasteful, so EasyUniformRing
+//! gives each frame slot of a FramePacer a host-visible uniform buffer,
+//! suballocates uniform blocks from it with the device's
+//! min_uniform_buffer_offset_alignment, and returns their dynamic offsets.
+//!
+//! A descriptor set with a dynamic uniform buffer binding is then built once
+//! per frame slot, using EasyDescriptorSetBuilder::dynamic_buffer() and
+//! EasyUniformRing::descriptor_range(), and bound with the dynamic offset of
+//! each draw using descriptor::bind_dynamic_descriptor_set().
+//!
+//! Unlike EasyStagingBelt, the ring does not grow, as the descriptor sets
+//! would have to be rebuilt. Its frame capacity must thus be chosen large
+//! enough for the uniform data of a whole frame.
+
+use ::{
+    sync::FrameSlot,
+    trace,
+    Error,
+    Result,
+};
+
+use std::{
+    mem,
+    slice,
+    sync::Arc,
+};
+
+use vulkano::{
+    buffer::{
+        BufferSlice,
+        BufferUsage,
+        CpuAccessibleBuffer,
+        TypedBufferAccess,
+    },
+    device::{
+        Device,
+        DeviceOwned,
+    },
+};
+
+
+/// Buffer type backing each frame slot of the uniform ring
+pub type UniformRingBuffer = Arc<CpuAccessibleBuffer<[u8]>>;
+
+/// Range of a uniform ring buffer, as bound to a dynamic descriptor
+pub type UniformRingRange = BufferSlice<[u8], UniformRingBuffer>;
+
+
+/// Plain old data which can be copied into a uniform ring as raw bytes
+///
+/// # Safety
+///
+/// Implementors must be Copy types whose every byte is initialized, i.e.
+/// which contain no padding (including trailing padding), no pointers or
+/// references, and no uninitialized data. A #[repr(C)] struct whose fields
+/// are UniformBlocks laid out without gaps qualifies, with explicit padding
+/// fields where the std140 layout rules require them.
+///
+pub unsafe trait UniformBlock: Copy {}
+
+unsafe impl UniformBlock for f32 {}
+unsafe impl UniformBlock for f64 {}
+unsafe impl UniformBlock for i32 {}
+unsafe impl UniformBlock for u32 {}
+unsafe impl UniformBlock for [f32; 2] {}
+unsafe impl UniformBlock for [f32; 3] {}
+unsafe impl UniformBlock for [f32; 4] {}
+unsafe impl UniformBlock for [[f32; 4]; 4] {}
+
+
+/// Ring of per-frame uniform buffers, handing out dynamic offsets
+pub struct EasyUniformRing {
+    /// Uniform buffer of each frame slot
+    buffers: Vec<UniformRingBuffer>,
+
+    /// Alignment of the uniform blocks
+    alignment: usize,
+
+    /// Frame slot which is being filled, if begin_frame() was called
+    frame: Option<usize>,
+
+    /// Number of bytes which were handed out in the current frame slot
+    used: usize,
+}
+
+impl EasyUniformRing {
+    /// Set up a uniform ring for a certain number of frames in flight, with
+    /// a certain capacity per frame (in bytes)
+    pub fn new(device: &Arc<Device>,
+               frames_in_flight: usize,
+               frame_capacity: usize) -> Result<Self> {
+        let _trace = trace::span("EasyUniformRing::new", || {
+            format!("frames_in_flight: {}, frame_capacity: {}",
+                    frames_in_flight, frame_capacity)
+        });
+        if frames_in_flight == 0 {
+            return Err(Error::InvalidArgument(
+                "At least one frame must be in flight".to_owned()
+            ));
+        }
+        let limits = device.physical_device().limits();
+        let alignment =
+            limits.min_uniform_buffer_offset_alignment().max(1) as usize;
+        let frame_capacity = align_up(frame_capacity.max(1), alignment);
+        info!("Allocating a uniform ring of {} frame(s) x {} bytes, aligned \
+               to {} bytes", frames_in_flight, frame_capacity, alignment);
+        let buffers = (0..frames_in_flight).map(|_| unsafe {
+            CpuAccessibleBuffer::uninitialized_array(
+                device.clone(),
+                frame_capacity,
+                BufferUsage::uniform_buffer()
+            )
+        }).collect::<::std::result::Result<Vec<_>, _>>()?;
+        Ok(EasyUniformRing {
+            buffers,
+            alignment,
+            frame: None,
+            used: 0,
+        })
+    }
+
+    /// Number of frame slots of the ring
+    pub fn frames_in_flight(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Capacity of each frame slot, in bytes
+    pub fn frame_capacity(&self) -> usize {
+        self.buffers[0].len()
+    }
+
+    /// Alignment of the uniform blocks, i.e. of the dynamic offsets
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// Number of bytes which were handed out in the current frame
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Uniform buffer of a frame slot
+    pub fn buffer(&self, index: usize) -> &UniformRingBuffer {
+        &self.buffers[index % self.buffers.len()]
+    }
+
+    /// Range of a frame slot's buffer which should be bound to a dynamic
+    /// uniform buffer descriptor, for uniform blocks of type T
+    ///
+    /// Dynamic offsets are added to the start of this range when the
+    /// descriptor set is bound.
+    ///
+    pub fn descriptor_range<T>(&self,
+                               index: usize) -> Result<Arc<UniformRingRange>> {
+        let buffer = self.buffer(index);
+        let size = mem::size_of::<T>();
+        let max_range = buffer.device()
+                              .physical_device()
+                              .limits()
+                              .max_uniform_buffer_range() as usize;
+        if size == 0 || size > max_range || size > buffer.len() {
+            return Err(Error::InvalidArgument(format!(
+                "Uniform blocks of {} bytes do not fit in the uniform ring \
+                 ({} bytes per frame, at most {} bytes per block)",
+                size, buffer.len(), max_range
+            )));
+        }
+        let range = BufferSlice::from_typed_buffer_access(buffer.clone())
+                                .slice(0..size)
+                                .expect("Range was checked above");
+        Ok(Arc::new(range))
+    }
+
+    /// Start filling the frame slot of a frame which was started by a
+    /// FramePacer
+    ///
+    /// The frame pacer has waited for the GPU to be done with the slot, so
+    /// the uniform blocks of the frame that last used it are recycled.
+    ///
+    pub fn begin_frame(&mut self, frame: &FrameSlot) {
+        if let Some(index) = self.frame {
+            debug!("Uniform ring frame slot {} used {}/{} bytes",
+                   index, self.used, self.frame_capacity());
+        }
+        self.frame = Some(frame.index % self.buffers.len());
+        self.used = 0;
+    }
+
+    /// Copy a uniform block into the current frame slot, returning the
+    /// dynamic offset at which it should be bound
+    ///
+    /// begin_frame() must have been called beforehand.
+    ///
+    pub fn push<T: UniformBlock>(&mut self, data: &T) -> Result<u32> {
+        let index = self.frame.ok_or_else(|| Error::InvalidArgument(
+            "begin_frame() must be called before pushing data".to_owned()
+        ))?;
+        let buffer = &self.buffers[index];
+        let size = mem::size_of::<T>();
+        let offset = align_up(self.used, self.alignment);
+        if offset + size > buffer.len() {
+            return Err(Error::InvalidArgument(format!(
+                "Uniform ring frame capacity ({} bytes) exceeded by a {}-byte \
+                 block at offset {}", buffer.len(), size, offset
+            )));
+        }
+
+        // Copy the data into the frame slot's buffer
+        {
+            let mut contents = buffer.write().map_err(|e| {
+                format_err!("Uniform ring frame slot {} is still in use by \
+                             the GPU: {}", index, e)
+            })?;
+            // Safe because UniformBlocks have no padding or other
+            // uninitialized bytes
+            let data_bytes = unsafe {
+                slice::from_raw_parts(data as *const T as *const u8, size)
+            };
+            contents[offset..offset+size].copy_from_slice(data_bytes);
+        }
+        self.used = offset + size;
+        Ok(offset as u32)
+    }
+}
+
+
+/// Round an offset up to some alignment
+fn align_up(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) / alignment * alignment
+}
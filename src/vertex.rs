@@ -0,0 +1,136 @@
+//! Vertex input layouts described from plain Rust structs
+//!
+//! Vulkano's impl_vertex! macro tells it where the members of a vertex struct
+//! are, but nothing checks that the resulting layout fits the device until
+//! pipeline creation fails in mysterious ways. The easy_vertex! macro does the
+//! same job, and additionally implements EasyVertex, which checks the layout
+//! against the device's vertex input limits and produces the vulkano vertex
+//! definition that graphics pipelines need.
+
+use ::{
+    Error,
+    Result,
+};
+
+use std::mem;
+
+use vulkano::{
+    instance::PhysicalDevice,
+    pipeline::vertex::{
+        SingleBufferDefinition,
+        Vertex,
+        VertexMemberInfo,
+        VertexMemberTy,
+    },
+};
+
+#[doc(hidden)]
+pub use vulkano::impl_vertex;
+
+
+/// A vertex type whose layout can be checked against the device limits
+///
+/// Implement it using the easy_vertex! macro rather than manually.
+///
+pub trait EasyVertex: Vertex + Sized {
+    /// Names of the vertex attributes, i.e. of the struct members
+    fn attribute_names() -> &'static [&'static str];
+
+    /// Location and size of each vertex attribute, by name
+    fn attributes() -> Vec<(&'static str, VertexMemberInfo)> {
+        Self::attribute_names().iter().map(|&name| {
+            let info = Self::member(name)
+                            .expect("easy_vertex! should only list members");
+            (name, info)
+        }).collect()
+    }
+
+    /// Check that the vertex layout fits the vertex input limits of a device
+    ///
+    /// Each attribute uses one input location per 16 bytes (or fraction
+    /// thereof), which is how matrices and double-precision vectors are
+    /// handled by Vulkan.
+    ///
+    fn check_limits(device: PhysicalDevice) -> Result<()> {
+        let limits = device.limits();
+        let stride = mem::size_of::<Self>();
+        let max_stride = limits.max_vertex_input_binding_stride() as usize;
+        if stride > max_stride {
+            return Err(Error::InvalidArgument(format!(
+                "Vertex stride is {} bytes, but device \"{}\" only supports \
+                 strides of up to {} bytes", stride, device.name(), max_stride
+            )));
+        }
+
+        let max_offset = limits.max_vertex_input_attribute_offset() as usize;
+        let mut locations = 0;
+        for (name, info) in Self::attributes() {
+            if info.offset > max_offset {
+                return Err(Error::InvalidArgument(format!(
+                    "Vertex attribute {} is at offset {}, but device \"{}\" \
+                     only supports offsets of up to {}",
+                    name, info.offset, device.name(), max_offset
+                )));
+            }
+            let size = member_size(&info.ty) * info.array_size;
+            locations += ((size + 15) / 16).max(1);
+        }
+        let max_locations = limits.max_vertex_input_attributes() as usize;
+        if locations > max_locations {
+            return Err(Error::InvalidArgument(format!(
+                "Vertex layout uses {} input locations, but device \"{}\" \
+                 only supports {}", locations, device.name(), max_locations
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check the vertex layout, then build a vertex definition for it
+    ///
+    /// The vertex definition is what vulkano's graphics pipeline builder
+    /// expects as a vertex input description.
+    ///
+    fn definition(
+        device: PhysicalDevice
+    ) -> Result<SingleBufferDefinition<Self>> {
+        Self::check_limits(device)?;
+        Ok(SingleBufferDefinition::new())
+    }
+}
+
+
+/// Describe the layout of a vertex struct, see the EasyVertex trait
+///
+/// This works like vulkano's impl_vertex! macro, which it calls, and also
+/// implements EasyVertex for the struct. The struct should be #[repr(C)].
+///
+/// ```ignore
+/// #[derive(Clone, Copy, Default)]
+/// #[repr(C)]
+/// struct MyVertex { position: [f32; 3], color: [f32; 4] }
+/// easy_vertex!(MyVertex, position, color);
+/// ```
+///
+#[macro_export]
+macro_rules! easy_vertex {
+    ($vertex:ident, $($member:ident),+ $(,)*) => {
+        $crate::vertex::impl_vertex!($vertex, $($member),+);
+
+        impl $crate::vertex::EasyVertex for $vertex {
+            fn attribute_names() -> &'static [&'static str] {
+                &[$(stringify!($member)),+]
+            }
+        }
+    };
+}
+
+
+/// Size of a vertex struct member's components, in bytes
+fn member_size(ty: &VertexMemberTy) -> usize {
+    match *ty {
+        VertexMemberTy::I8 | VertexMemberTy::U8 => 1,
+        VertexMemberTy::I16 | VertexMemberTy::U16 => 2,
+        VertexMemberTy::I32 | VertexMemberTy::U32 | VertexMemberTy::F32 => 4,
+        VertexMemberTy::F64 => 8,
+    }
+}
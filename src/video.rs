@@ -0,0 +1,278 @@
+//! Detection of Vulkan Video support
+//!
+//! Vulkano does not know about the Vulkan Video extensions yet, so this module
+//! probes for them by name using the raw device extension list.
+//!
+//! The video capabilities of individual queue families are not exposed by
+//! vulkano either, so we query them ourselves (see the properties2 module).
+//! When that is not possible, filtering can only happen at the device level,
+//! and you will need to check the queue family flags yourself (e.g. using the
+//! vulkaninfo tool) once you have picked a device.
+
+use ::{
+    properties2,
+    Result,
+};
+
+use std::{
+    mem,
+    os::raw::c_void,
+    ptr,
+};
+
+use vk_sys as vk;
+
+use vulkano::instance::{
+    PhysicalDevice,
+    QueueFamily,
+    RawDeviceExtensions,
+};
+
+
+/// Name of the base Vulkan Video extension
+pub const VIDEO_QUEUE_EXTENSION: &str = "VK_KHR_video_queue";
+
+/// Name of the Vulkan Video decoding extension
+pub const VIDEO_DECODE_QUEUE_EXTENSION: &str = "VK_KHR_video_decode_queue";
+
+/// Names of the codec-specific Vulkan Video decoding extensions
+pub const VIDEO_DECODE_CODEC_EXTENSIONS: &[&str] = &[
+    "VK_KHR_video_decode_h264",
+    "VK_KHR_video_decode_h265",
+    "VK_KHR_video_decode_av1",
+];
+
+/// Structure type of VkQueueFamilyVideoPropertiesKHR
+const STRUCTURE_TYPE_QUEUE_FAMILY_VIDEO_PROPERTIES: u32 = 1_000_023_012;
+
+/// Queue flags of Vulkan Video (VkQueueFlagBits)
+const QUEUE_VIDEO_DECODE_BIT: u32 = 0x20;
+const QUEUE_VIDEO_ENCODE_BIT: u32 = 0x40;
+
+
+/// Video codec which Vulkan Video can decode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoCodec {
+    /// H.264 / AVC
+    H264,
+
+    /// H.265 / HEVC
+    H265,
+
+    /// AV1
+    Av1,
+}
+
+impl VideoCodec {
+    /// All codecs, in the order of VIDEO_DECODE_CODEC_EXTENSIONS
+    pub const ALL: [VideoCodec; 3] = [VideoCodec::H264,
+                                      VideoCodec::H265,
+                                      VideoCodec::Av1];
+
+    /// Codec-specific decoding extension
+    pub fn decode_extension(self) -> &'static str {
+        VIDEO_DECODE_CODEC_EXTENSIONS[self as usize]
+    }
+
+    /// Vulkan's VkVideoCodecOperationFlagBitsKHR for decoding this codec
+    fn decode_operation_bit(self) -> u32 {
+        match self {
+            VideoCodec::H264 => 0x1,
+            VideoCodec::H265 => 0x2,
+            VideoCodec::Av1 => 0x4,
+        }
+    }
+}
+
+
+/// Vulkan Video capabilities of a queue family
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct QueueFamilyVideoSupport {
+    /// Queue family identifier
+    pub family: u32,
+
+    /// Whether the queues support video decoding
+    pub decode: bool,
+
+    /// Whether the queues support video encoding
+    pub encode: bool,
+
+    /// Codec-specific decoding extensions whose codec the queues can decode
+    pub decode_codecs: Vec<String>,
+}
+
+impl QueueFamilyVideoSupport {
+    /// Truth that the queues can decode some codec
+    pub fn can_decode(&self, codec: VideoCodec) -> bool {
+        self.decode
+            && self.decode_codecs.iter().any(|c| c == codec.decode_extension())
+    }
+}
+
+
+/// Summary of the Vulkan Video support of a physical device
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-export", derive(Serialize, Deserialize))]
+pub struct VideoSupport {
+    /// Whether VK_KHR_video_queue is supported
+    pub video_queue: bool,
+
+    /// Whether VK_KHR_video_decode_queue is supported
+    pub decode_queue: bool,
+
+    /// Codec-specific decoding extensions which are supported
+    pub decode_codecs: Vec<String>,
+
+    /// Video capabilities of the queue families which support video, empty
+    /// if they could not be queried
+    #[cfg_attr(feature = "serde-export", serde(default))]
+    pub queue_families: Vec<QueueFamilyVideoSupport>,
+}
+
+impl VideoSupport {
+    /// Probe the Vulkan Video support of a physical device
+    pub fn of_device(device: PhysicalDevice) -> Result<Self> {
+        let extensions = RawDeviceExtensions::supported_by_device_raw(device)?;
+        let supports = |name: &str| {
+            extensions.iter().any(|ext| ext.as_bytes() == name.as_bytes())
+        };
+        let video_queue = supports(VIDEO_QUEUE_EXTENSION);
+        Ok(VideoSupport {
+            video_queue,
+            decode_queue: supports(VIDEO_DECODE_QUEUE_EXTENSION),
+            decode_codecs: VIDEO_DECODE_CODEC_EXTENSIONS.iter()
+                                                        .filter(|c| supports(c))
+                                                        .map(|c| c.to_string())
+                                                        .collect(),
+            queue_families: if video_queue {
+                queue_family_support(device)
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+    /// Truth that the device can decode video with at least one codec
+    pub fn can_decode(&self) -> bool {
+        self.video_queue && self.decode_queue && !self.decode_codecs.is_empty()
+    }
+
+    /// Truth that the device can decode video with some codec
+    ///
+    /// If the video capabilities of queue families are known, one of them
+    /// must be able to decode this codec.
+    ///
+    pub fn can_decode_codec(&self, codec: VideoCodec) -> bool {
+        let extension = codec.decode_extension();
+        self.video_queue
+            && self.decode_queue
+            && self.decode_codecs.iter().any(|c| c == extension)
+            && (self.queue_families.is_empty()
+                || self.queue_families.iter()
+                                      .any(|family| family.can_decode(codec)))
+    }
+
+    /// Video capabilities of a queue family, if it supports video
+    pub fn queue_family(&self, id: u32) -> Option<&QueueFamilyVideoSupport> {
+        self.queue_families.iter().find(|family| family.family == id)
+    }
+}
+
+
+/// Device filter which only accepts devices that can decode video
+///
+/// This is meant to be used as (part of) the "other_criteria" parameter of
+/// easy_device_filter(). Devices whose extensions cannot be queried are
+/// rejected.
+///
+pub fn supports_video_decode(device: PhysicalDevice) -> bool {
+    VideoSupport::of_device(device).map(|support| support.can_decode())
+                                   .unwrap_or(false)
+}
+
+/// Device filter which only accepts devices that can decode some codec
+///
+/// This works like supports_video_decode(), but also checks the codec.
+///
+pub fn requires_video_decode(
+    codec: VideoCodec
+) -> impl FnMut(PhysicalDevice) -> bool {
+    move |device| {
+        VideoSupport::of_device(device)
+            .map(|support| support.can_decode_codec(codec))
+            .unwrap_or(false)
+    }
+}
+
+/// Queue family filter which only accepts queue families that can decode
+/// some codec, to be used when setting up a device
+///
+/// This requires the video capabilities of queue families to be known.
+///
+pub fn video_decode_queue_filter(
+    codec: VideoCodec
+) -> impl FnMut(&QueueFamily) -> bool {
+    move |family| {
+        VideoSupport::of_device(family.physical_device())
+            .ok()
+            .and_then(|support| support.queue_family(family.id())
+                                       .map(|f| f.can_decode(codec)))
+            .unwrap_or(false)
+    }
+}
+
+
+/// Query the video capabilities of the queue families of a device, keeping
+/// only the queue families which support video
+fn queue_family_support(
+    device: PhysicalDevice
+) -> Vec<QueueFamilyVideoSupport> {
+    let num_families = device.queue_families().count();
+    let mut video_properties = (0..num_families).map(|_| {
+        RawQueueFamilyVideoProperties {
+            s_type: STRUCTURE_TYPE_QUEUE_FAMILY_VIDEO_PROPERTIES,
+            p_next: ptr::null_mut(),
+            video_codec_operations: 0,
+        }
+    }).collect::<Vec<_>>();
+    let mut properties = video_properties.iter_mut().map(|video| {
+        vk::QueueFamilyProperties2KHR {
+            sType: vk::STRUCTURE_TYPE_QUEUE_FAMILY_PROPERTIES_2_KHR,
+            pNext: video as *mut RawQueueFamilyVideoProperties
+                         as *mut c_void,
+            queueFamilyProperties: unsafe { mem::zeroed() },
+        }
+    }).collect::<Vec<_>>();
+    let queried = unsafe {
+        properties2::query_queue_family_properties(device, &mut properties)
+    };
+    if !queried { return Vec::new(); }
+
+    let families = properties.iter().zip(video_properties.iter());
+    families.enumerate().filter_map(|(id, (properties, video))| {
+        let flags = properties.queueFamilyProperties.queueFlags;
+        let decode = flags & QUEUE_VIDEO_DECODE_BIT != 0;
+        let encode = flags & QUEUE_VIDEO_ENCODE_BIT != 0;
+        if !decode && !encode { return None; }
+        let operations = video.video_codec_operations;
+        Some(QueueFamilyVideoSupport {
+            family: id as u32,
+            decode,
+            encode,
+            decode_codecs: VideoCodec::ALL.iter()
+                .filter(|codec| operations & codec.decode_operation_bit() != 0)
+                .map(|codec| codec.decode_extension().to_owned())
+                .collect(),
+        })
+    }).collect()
+}
+
+
+/// VkQueueFamilyVideoPropertiesKHR, which vk-sys does not know about
+#[repr(C)]
+struct RawQueueFamilyVideoProperties {
+    s_type: u32,
+    p_next: *mut c_void,
+    video_codec_operations: u32,
+}
@@ -0,0 +1,287 @@
+//! Conveniences for windowed prototypes, based on winit
+//!
+//! This module is only available when the "window" feature is enabled.
+//!
+//! Besides window and surface creation, it provides helpers for enumerating
+//! monitors and switching windows to fullscreen, which demos often need.
+
+use ::{
+    device::EasyPhysicalDevice,
+    instance::EasyInstance,
+    surface,
+    trace,
+    Result,
+};
+
+use std::{
+    cmp::Ordering,
+    sync::Arc,
+};
+
+use vulkano::{
+    device::DeviceExtensions,
+    instance::{
+        Features,
+        InstanceExtensions,
+        PhysicalDevice,
+    },
+    swapchain::{
+        display::Display,
+        Surface,
+    },
+};
+
+use vulkano_win::{
+    self,
+    VkSurfaceBuild,
+};
+
+use winit::{
+    dpi::{
+        LogicalPosition,
+        LogicalSize,
+    },
+    EventsLoop,
+    MonitorId,
+    Window,
+    WindowBuilder,
+};
+
+
+/// How a window is displayed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// Regular decorated window
+    Windowed,
+
+    /// Undecorated window covering a whole monitor, which makes switching to
+    /// other windows cheap but may prevent some presentation optimizations
+    Borderless,
+
+    /// Fullscreen mode of the windowing system, which gives the application
+    /// exclusive use of the monitor on platforms that support it
+    Exclusive,
+}
+
+
+/// A window with a Vulkan surface, along with its event loop
+///
+/// The instance that the window is built from must have the extensions from
+/// EasyWindow::required_extensions() enabled. If you use EasyInstanceBuilder,
+/// its window_extensions() method will take care of this.
+///
+pub struct EasyWindow {
+    /// Event loop of the window
+    events_loop: EventsLoop,
+
+    /// Vulkan surface, which also owns the window
+    surface: Arc<Surface<Window>>,
+
+    /// Current fullscreen mode
+    fullscreen_mode: FullscreenMode,
+
+    /// Window position and size to be restored when leaving borderless mode
+    windowed_geometry: Option<(LogicalPosition, LogicalSize)>,
+}
+
+impl EasyWindow {
+    /// Instance extensions which are required in order to create a window
+    pub fn required_extensions() -> InstanceExtensions {
+        vulkano_win::required_extensions()
+    }
+
+    /// Create a window and its Vulkan surface
+    pub fn new(instance: &EasyInstance,
+               builder: WindowBuilder) -> Result<Self> {
+        let events_loop = EventsLoop::new();
+        let surface = builder.build_vk_surface(&events_loop,
+                                               instance.instance().clone())?;
+        Ok(EasyWindow {
+            events_loop,
+            surface,
+            fullscreen_mode: FullscreenMode::Windowed,
+            windowed_geometry: None,
+        })
+    }
+
+    /// Create a window with default settings and the specified title
+    pub fn with_title(instance: &EasyInstance, title: &str) -> Result<Self> {
+        Self::new(instance, WindowBuilder::new().with_title(title))
+    }
+
+    /// Access the Vulkan surface
+    pub fn surface(&self) -> &Arc<Surface<Window>> {
+        &self.surface
+    }
+
+    /// Access the window
+    pub fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
+    /// Access the event loop of the window
+    pub fn events_loop(&mut self) -> &mut EventsLoop {
+        &mut self.events_loop
+    }
+
+    /// Current dimensions of the window's drawable area, in physical pixels
+    ///
+    /// This is what you want to use as swapchain dimensions. If the window
+    /// has been closed, None is returned.
+    ///
+    pub fn dimensions(&self) -> Option<[u32; 2]> {
+        let window = self.window();
+        window.get_inner_size().map(|logical_size| {
+            let physical_size =
+                logical_size.to_physical(window.get_hidpi_factor());
+            let (width, height): (u32, u32) = physical_size.into();
+            [width, height]
+        })
+    }
+
+    /// Select a physical device which can render to this window
+    ///
+    /// This works like EasyInstance::select_physical_device(), with a device
+    /// filter built using surface::easy_presentation_filter(). Devices which
+    /// can render and present from the same queue family are preferred over
+    /// other devices, and then your preference kicks in.
+    ///
+    pub fn select_physical_device(
+        &self,
+        instance: &Arc<EasyInstance>,
+        features: &Features,
+        extensions: &DeviceExtensions,
+        other_criteria: impl FnMut(PhysicalDevice) -> bool,
+        mut preference: impl FnMut(PhysicalDevice, PhysicalDevice) -> Ordering
+    ) -> Result<Option<EasyPhysicalDevice>> {
+        let filter = surface::easy_presentation_filter(features,
+                                                       extensions,
+                                                       &self.surface,
+                                                       other_criteria);
+        let mut prefer_unified =
+            surface::prefer_unified_presentation(&self.surface);
+        instance.select_physical_device(
+            filter,
+            |dev1, dev2| prefer_unified(dev1, dev2)
+                             .then_with(|| preference(dev1, dev2))
+        )
+    }
+
+    /// List the monitors which are connected to the system
+    pub fn monitors(&self) -> Vec<MonitorId> {
+        let monitors = self.events_loop.get_available_monitors()
+                                       .collect::<Vec<_>>();
+        for (idx, monitor) in monitors.iter().enumerate() {
+            debug!("Monitor {}: {:?} ({:?} at {:?})",
+                   idx,
+                   monitor.get_name(),
+                   monitor.get_dimensions(),
+                   monitor.get_position());
+        }
+        monitors
+    }
+
+    /// Pick the monitor that is most likely driven by a physical device
+    ///
+    /// If the VK_KHR_display instance extension is enabled, we look for a
+    /// monitor whose resolution matches that of a display which the device
+    /// exposes. Otherwise, or if nothing matches, we use the heuristic that
+    /// the monitor on which the window currently is, which is usually the
+    /// primary monitor, is the right one.
+    ///
+    pub fn monitor_for_device(&self, device: PhysicalDevice) -> MonitorId {
+        let _trace = trace::span("EasyWindow::monitor_for_device",
+                                 || format!("device: {}", device.name()));
+        if device.instance().loaded_extensions().khr_display {
+            let displays = Display::enumerate_raw(device)
+                                   .map(|displays| displays.collect())
+                                   .unwrap_or_else(|_| Vec::<Display>::new());
+            let monitors = self.monitors();
+            for display in displays {
+                let resolution = display.physical_resolution();
+                let matching = monitors.iter().find(|monitor| {
+                    let (width, height): (u32, u32) =
+                        monitor.get_dimensions().into();
+                    [width, height] == resolution
+                });
+                if let Some(monitor) = matching {
+                    info!("Display {} of device {} matches monitor {:?}",
+                          display.name(), device.name(), monitor.get_name());
+                    return monitor.clone();
+                }
+            }
+            debug!("No monitor matches the displays of device {}",
+                   device.name());
+        }
+        self.window().get_current_monitor()
+    }
+
+    /// Current fullscreen mode of the window
+    pub fn fullscreen_mode(&self) -> FullscreenMode {
+        self.fullscreen_mode
+    }
+
+    /// Switch the window to some fullscreen mode on some monitor
+    ///
+    /// The monitor is ignored in windowed mode. When switching back to
+    /// windowed mode, the window gets its previous position and size back.
+    /// Don't forget to resize your swapchain afterwards.
+    ///
+    pub fn set_fullscreen(&mut self,
+                          mode: FullscreenMode,
+                          monitor: MonitorId) {
+        let _trace = trace::span("EasyWindow::set_fullscreen", || {
+            format!("mode: {:?}, monitor: {:?}", mode, monitor.get_name())
+        });
+        if mode == self.fullscreen_mode { return; }
+
+        // Leave the current mode
+        let window = self.surface.window();
+        match self.fullscreen_mode {
+            FullscreenMode::Windowed => {
+                self.windowed_geometry =
+                    window.get_outer_position()
+                          .and_then(|pos| {
+                              window.get_inner_size().map(|size| (pos, size))
+                          });
+            },
+            FullscreenMode::Borderless => window.set_decorations(true),
+            FullscreenMode::Exclusive => window.set_fullscreen(None),
+        }
+
+        // Enter the new mode
+        match mode {
+            FullscreenMode::Windowed => {
+                if let Some((position, size)) = self.windowed_geometry.take() {
+                    window.set_position(position);
+                    window.set_inner_size(size);
+                }
+            },
+            FullscreenMode::Borderless => {
+                let hidpi_factor = monitor.get_hidpi_factor();
+                window.set_decorations(false);
+                window.set_position(
+                    monitor.get_position().to_logical(hidpi_factor)
+                );
+                window.set_inner_size(
+                    monitor.get_dimensions().to_logical(hidpi_factor)
+                );
+            },
+            FullscreenMode::Exclusive => window.set_fullscreen(Some(monitor)),
+        }
+        self.fullscreen_mode = mode;
+    }
+
+    /// Toggle between windowed mode and some fullscreen mode
+    ///
+    /// Fullscreen happens on the monitor where the window currently is.
+    ///
+    pub fn toggle_fullscreen(&mut self, mode: FullscreenMode) {
+        let monitor = self.window().get_current_monitor();
+        if self.fullscreen_mode == FullscreenMode::Windowed {
+            self.set_fullscreen(mode, monitor);
+        } else {
+            self.set_fullscreen(FullscreenMode::Windowed, monitor);
+        }
+    }
+}